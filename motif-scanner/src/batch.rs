@@ -0,0 +1,191 @@
+use crate::config::{MotifParams, RunConfig};
+use crate::{read_sequences, sample_input, scan_and_save, ScannerError, Sequences};
+use motif_scanner::io::{data_extension, data_stem, is_gzip};
+use polars::prelude::*;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tf_binding_rs::types::EWMCollection;
+use tracing::{error, info};
+
+const INPUT_EXTENSIONS: [&str; 6] = ["csv", "tsv", "fa", "fasta", "fna", "parquet"];
+
+/// Expands a directory (its CSV/TSV/FASTA/Parquet files, CSV and TSV also gzipped) or glob
+/// pattern into sorted input paths
+pub fn batch_inputs(pattern: &str) -> Result<Vec<PathBuf>, ScannerError> {
+    let mut inputs: Vec<PathBuf> = if Path::new(pattern).is_dir() {
+        fs::read_dir(pattern)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                data_extension(path).is_some_and(|ext| {
+                    INPUT_EXTENSIONS.contains(&ext)
+                        && (!is_gzip(path) || matches!(ext, "csv" | "tsv"))
+                })
+            })
+            .collect()
+    } else {
+        glob::glob(pattern)
+            .map_err(|e| ScannerError::Batch(e.to_string()))?
+            .filter_map(Result::ok)
+            .filter(|path| path.is_file())
+            .collect()
+    };
+    inputs.sort();
+
+    if inputs.is_empty() {
+        return Err(ScannerError::Batch(format!(
+            "no input files match '{}'",
+            pattern
+        )));
+    }
+    Ok(inputs)
+}
+
+/// Scans every input of a batch in parallel, writing `{stem}.{batch_format}` per input and
+/// `summary.csv` into the output directory.
+///
+/// A failing input does not stop the others; its error is recorded in the summary and the
+/// batch fails once all inputs have been processed. Returns the number of hits of all inputs.
+pub fn run_batch(
+    run: &RunConfig,
+    ewm: &EWMCollection,
+    params: &HashMap<String, MotifParams>,
+) -> Result<usize, ScannerError> {
+    let out_dir = PathBuf::from(run.output_file());
+    fs::create_dir_all(&out_dir)?;
+
+    let inputs = batch_inputs(&run.data_file)?;
+    let mut stems = HashSet::new();
+    let outputs: Vec<PathBuf> = inputs
+        .iter()
+        .map(|input| {
+            let stem = data_stem(input).unwrap_or_default();
+            if !stems.insert(stem.to_string()) {
+                return Err(ScannerError::Batch(format!(
+                    "several inputs would write {}.{}",
+                    stem, run.batch_format
+                )));
+            }
+            Ok(out_dir.join(format!("{}.{}", stem, run.batch_format)))
+        })
+        .collect::<Result<_, _>>()?;
+    info!(files = inputs.len(), "input files to scan");
+    // inputs are scanned concurrently, so they share the memory budget
+    let concurrent = rayon::current_num_threads().min(inputs.len()).max(1);
+
+    let results: Vec<(usize, usize, usize, f64, String)> = inputs
+        .par_iter()
+        .zip(&outputs)
+        .map(|(input, output)| {
+            let start_time = Instant::now();
+            let mut file_run = run.clone();
+            file_run.data_file = input.to_string_lossy().to_string();
+            file_run.output_files = vec![output.to_string_lossy().to_string()];
+            file_run.max_memory = run.max_memory.map(|budget| budget / concurrent);
+
+            let result = read_sequences(
+                &file_run.data_file,
+                file_run.duplicate_labels,
+                file_run.ambiguous_policy,
+                &file_run.normalization,
+                &file_run.input_columns,
+                file_run.separator,
+            )
+            .and_then(|(df, skipped)| {
+                Ok((sample_input(&file_run, df, &file_run.data_file)?, skipped))
+            })
+            .and_then(|(df, skipped)| {
+                let read_time = start_time.elapsed();
+                let mut summary = scan_and_save(
+                    &file_run,
+                    Sequences::Table {
+                        df: &df,
+                        skipped: &skipped,
+                    },
+                    ewm,
+                    params,
+                    start_time,
+                )?;
+                summary.first_stage("read sequences", read_time);
+                if file_run.summary_json {
+                    summary.write_json(file_run.output_file())?;
+                }
+                Ok((df.height(), summary.hits, summary.warnings))
+            });
+            let seconds = start_time.elapsed().as_secs_f64();
+            match result {
+                Ok((n_sequences, n_hits, n_warnings)) => {
+                    (n_sequences, n_hits, n_warnings, seconds, String::new())
+                }
+                Err(e) => {
+                    error!(file = %file_run.data_file, error = %e, "input failed");
+                    (0, 0, 0, seconds, e.to_string())
+                }
+            }
+        })
+        .collect();
+
+    let mut summary = DataFrame::new(vec![
+        Column::new(
+            "input_file".into(),
+            inputs
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "output_file".into(),
+            outputs
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "n_sequences".into(),
+            results.iter().map(|r| r.0 as u64).collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "n_hits".into(),
+            results.iter().map(|r| r.1 as u64).collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "n_warnings".into(),
+            results.iter().map(|r| r.2 as u64).collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "seconds".into(),
+            results.iter().map(|r| r.3).collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "status".into(),
+            results
+                .iter()
+                .map(|r| match (r.4.is_empty(), r.1) {
+                    (false, _) => "failed",
+                    (true, 0) => "no_hits",
+                    (true, _) => "ok",
+                })
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "error".into(),
+            results.iter().map(|r| r.4.as_str()).collect::<Vec<_>>(),
+        ),
+    ])?;
+
+    let mut file = fs::File::create(out_dir.join("summary.csv"))?;
+    CsvWriter::new(&mut file).finish(&mut summary)?;
+
+    let failed = results.iter().filter(|r| !r.4.is_empty()).count();
+    if failed > 0 {
+        return Err(ScannerError::Batch(format!(
+            "{} of {} inputs failed, see {}",
+            failed,
+            inputs.len(),
+            out_dir.join("summary.csv").display()
+        )));
+    }
+    Ok(results.iter().map(|r| r.1).sum())
+}
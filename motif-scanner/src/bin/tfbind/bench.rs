@@ -0,0 +1,248 @@
+use crate::{read_ewms, CliError};
+use clap::{Args, ValueEnum};
+use polars::prelude::*;
+use rayon::prelude::*;
+use std::fs::File;
+use std::time::{Duration, Instant};
+use tf_binding_rs::occupancy::{MotifBank, StrandedEwm};
+use tf_binding_rs::quantized::QuantizedEwm;
+use tf_binding_rs::types::EWMCollection;
+use tf_binding_rs::workload::Workload;
+use tracing::info;
+
+/// Scanning code path to measure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Method {
+    /// Hits from exact f64 scoring of every window
+    Exact,
+    /// Hits with the integer prefilter (`--quantized` of motif-scanner)
+    Quantized,
+    /// Full occupancy landscapes of all motifs in one pass
+    Landscape,
+}
+
+#[derive(Args, Debug)]
+pub struct BenchArgs {
+    /// Number of random sequences
+    #[arg(long, default_value_t = 1000)]
+    sequences: usize,
+
+    /// Length of every sequence in bp
+    #[arg(long, default_value_t = 1000)]
+    length: usize,
+
+    /// Number of random motifs
+    #[arg(long, default_value_t = 10)]
+    motifs: usize,
+
+    /// Width of every random motif in bp
+    #[arg(long, default_value_t = 12)]
+    motif_length: usize,
+
+    /// Scan with the motifs of this file instead of random ones
+    #[arg(long, value_name = "PWM_FILE")]
+    pwm_file: Option<String>,
+
+    /// Seed of the random sequences and motifs
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+
+    /// Code paths to measure (comma-separated) [default: all]
+    #[arg(long, value_enum, value_delimiter = ',')]
+    methods: Vec<Method>,
+
+    /// Thread counts to measure (comma-separated) [default: all cores]
+    #[arg(long, value_delimiter = ',')]
+    threads: Vec<usize>,
+
+    /// Timed repetitions of every measurement, after one warm-up run
+    #[arg(long, default_value_t = 5)]
+    samples: usize,
+
+    /// Chemical potential of the transcription factors
+    #[arg(long, default_value_t = 9.0)]
+    mu: f64,
+
+    /// Minimum occupancy of a hit
+    #[arg(long, default_value_t = 0.2)]
+    cutoff: f64,
+
+    /// Write the results as CSV to this file instead of printing a table
+    #[arg(long, value_name = "CSV_FILE")]
+    output: Option<String>,
+}
+
+/// Motifs of a workload prepared once for every code path
+struct Prepared {
+    exact: Vec<StrandedEwm>,
+    quantized: Vec<QuantizedEwm>,
+    bank: MotifBank,
+}
+
+impl Prepared {
+    fn new(ewms: &EWMCollection) -> Result<Self, CliError> {
+        Ok(Prepared {
+            exact: ewms
+                .values()
+                .map(StrandedEwm::new)
+                .collect::<Result<_, _>>()?,
+            quantized: ewms
+                .values()
+                .map(QuantizedEwm::new)
+                .collect::<Result<_, _>>()?,
+            bank: MotifBank::new(ewms)?,
+        })
+    }
+
+    /// Scans one sequence, returning the number of windows above the cutoff
+    fn scan(&self, method: Method, seq: &str, mu: f64, cutoff: f64) -> Result<usize, CliError> {
+        let mut n = 0;
+        match method {
+            Method::Exact => {
+                for matrix in &self.exact {
+                    let (focc, rocc) = matrix.occupancy_landscape(seq, mu)?;
+                    n += focc.iter().chain(&rocc).filter(|&&o| o > cutoff).count();
+                }
+            }
+            Method::Quantized => {
+                for matrix in &self.quantized {
+                    let (forward, reverse) = matrix.hits(seq, mu, cutoff)?;
+                    n += forward.len() + reverse.len();
+                }
+            }
+            Method::Landscape => {
+                for (focc, rocc) in self.bank.occupancy_landscapes(seq, mu)? {
+                    n += focc.iter().chain(&rocc).filter(|&&o| o > cutoff).count();
+                }
+            }
+        }
+        Ok(n)
+    }
+}
+
+fn median(samples: &mut [Duration]) -> Duration {
+    samples.sort();
+    samples[samples.len() / 2]
+}
+
+pub fn run(args: BenchArgs) -> Result<(), CliError> {
+    if args.samples == 0 || args.threads.contains(&0) {
+        return Err(CliError::InvalidArgument(
+            "--samples and --threads must be positive".to_string(),
+        ));
+    }
+
+    let workload = Workload {
+        sequences: args.sequences,
+        length: args.length,
+        motifs: args.motifs,
+        motif_length: args.motif_length,
+        seed: args.seed,
+    };
+    let ewms = match &args.pwm_file {
+        Some(path) => read_ewms(path)?,
+        None => workload.ewms()?,
+    };
+    let sequences = workload.sequences();
+    let prepared = Prepared::new(&ewms)?;
+    let work = (workload.bases() * ewms.len()) as f64;
+
+    let methods = if args.methods.is_empty() {
+        vec![Method::Exact, Method::Quantized, Method::Landscape]
+    } else {
+        args.methods.clone()
+    };
+    let threads = if args.threads.is_empty() {
+        vec![rayon::current_num_threads()]
+    } else {
+        args.threads.clone()
+    };
+    info!(
+        sequences = workload.sequences,
+        length = workload.length,
+        motifs = ewms.len(),
+        cores = rayon::current_num_threads(),
+        "running benchmark"
+    );
+
+    let mut rows: Vec<(String, u32, f64, f64, f64, u64)> = Vec::new();
+    for &method in &methods {
+        for &n_threads in &threads {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(n_threads)
+                .build()
+                .map_err(|e| CliError::InvalidArgument(e.to_string()))?;
+            let scan_all = || {
+                pool.install(|| {
+                    sequences
+                        .par_iter()
+                        .map(|seq| prepared.scan(method, seq, args.mu, args.cutoff))
+                        .sum::<Result<usize, CliError>>()
+                })
+            };
+
+            let hits = scan_all()?;
+            let mut samples = Vec::with_capacity(args.samples);
+            for _ in 0..args.samples {
+                let start = Instant::now();
+                scan_all()?;
+                samples.push(start.elapsed());
+            }
+            let fastest = *samples.iter().min().unwrap();
+            let seconds = median(&mut samples).as_secs_f64();
+
+            let name = method.to_possible_value().unwrap().get_name().to_string();
+            info!(method = %name, threads = n_threads, seconds, "measured");
+            rows.push((
+                name,
+                n_threads as u32,
+                seconds,
+                fastest.as_secs_f64(),
+                work / seconds,
+                hits as u64,
+            ));
+        }
+    }
+
+    let mut table = DataFrame::new(vec![
+        Column::new(
+            "method".into(),
+            rows.iter().map(|r| r.0.clone()).collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "threads".into(),
+            rows.iter().map(|r| r.1).collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "median_seconds".into(),
+            rows.iter().map(|r| r.2).collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "min_seconds".into(),
+            rows.iter().map(|r| r.3).collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "bp_motifs_per_sec".into(),
+            rows.iter().map(|r| r.4).collect::<Vec<_>>(),
+        ),
+        Column::new("hits".into(), rows.iter().map(|r| r.5).collect::<Vec<_>>()),
+    ])?;
+
+    match args.output {
+        Some(path) => {
+            let mut file = File::create(&path)?;
+            CsvWriter::new(&mut file).finish(&mut table)?;
+        }
+        None => {
+            println!(
+                "{} sequences x {} bp, {} motifs; median of {} runs",
+                workload.sequences,
+                workload.length,
+                ewms.len(),
+                args.samples
+            );
+            println!("{}", table);
+        }
+    }
+    Ok(())
+}
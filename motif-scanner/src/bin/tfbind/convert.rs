@@ -0,0 +1,95 @@
+use crate::CliError;
+use clap::Args;
+use tf_binding_rs::formats::{read_counts, read_motifs, write_counts, write_motifs, MotifFormat};
+use tf_binding_rs::matrix::{add_pseudocount, reverse_complement_matrix, trim_pwm};
+use tf_binding_rs::types::{PCMCollection, PWMCollection};
+use tracing::info;
+
+#[derive(Args, Debug)]
+pub struct ConvertArgs {
+    /// Path to the input motif file
+    #[arg(value_name = "INPUT")]
+    input: String,
+
+    /// Path for the output motif file
+    #[arg(value_name = "OUTPUT")]
+    output: String,
+
+    /// Format of the input file (meme, jaspar, transfac, homer, hocomoco)
+    /// Guessed from the file extension when omitted
+    #[arg(long)]
+    from: Option<String>,
+
+    /// Format of the output file (meme, jaspar, transfac, homer, hocomoco)
+    /// Guessed from the file extension when omitted
+    #[arg(long)]
+    to: Option<String>,
+
+    /// Pseudocount added to every probability before renormalizing
+    #[arg(long)]
+    pseudocount: Option<f64>,
+
+    /// Trim flanking positions with information content (bits) below this value
+    #[arg(long, value_name = "MIN_IC")]
+    trim: Option<f64>,
+
+    /// Reverse-complement every motif
+    #[arg(long)]
+    reverse_complement: bool,
+}
+
+fn resolve_format(explicit: Option<&str>, path: &str) -> Result<MotifFormat, CliError> {
+    match explicit {
+        Some(name) => Ok(name.parse()?),
+        None => MotifFormat::from_path(path).ok_or_else(|| {
+            CliError::InvalidArgument(format!(
+                "cannot guess the motif format of '{}', pass --from/--to",
+                path
+            ))
+        }),
+    }
+}
+
+pub fn run(args: ConvertArgs) -> Result<(), CliError> {
+    let from = resolve_format(args.from.as_deref(), &args.input)?;
+    let to = resolve_format(args.to.as_deref(), &args.output)?;
+
+    // without smoothing or trimming, carry the site counts over so count-based formats
+    // keep them
+    if args.pseudocount.is_none() && args.trim.is_none() {
+        let converted = read_counts(&args.input, from)?
+            .into_iter()
+            .map(|(id, pcm)| {
+                if args.reverse_complement {
+                    Ok((id, reverse_complement_matrix(&pcm)?))
+                } else {
+                    Ok((id, pcm))
+                }
+            })
+            .collect::<Result<PCMCollection, CliError>>()?;
+        write_counts(&converted, &args.output, to)?;
+        info!(motifs = converted.len(), %from, %to, output = %args.output, "converted motifs");
+        return Ok(());
+    }
+
+    let pwms = read_motifs(&args.input, from)?;
+    let converted = pwms
+        .into_iter()
+        .map(|(id, mut pwm)| {
+            if let Some(pseudocount) = args.pseudocount {
+                pwm = add_pseudocount(&pwm, pseudocount)?;
+            }
+            if let Some(min_ic) = args.trim {
+                pwm = trim_pwm(&pwm, min_ic)?;
+            }
+            if args.reverse_complement {
+                pwm = reverse_complement_matrix(&pwm)?;
+            }
+            Ok((id, pwm))
+        })
+        .collect::<Result<PWMCollection, CliError>>()?;
+
+    write_motifs(&converted, &args.output, to)?;
+    info!(motifs = converted.len(), %from, %to, output = %args.output, "converted motifs");
+    Ok(())
+}
@@ -0,0 +1,168 @@
+use crate::{read_ewms_with, read_sequence_records, CliError};
+use clap::Args;
+use motif_scanner::io::write_table;
+use polars::prelude::*;
+use tf_binding_rs::compare::{config_diff, ScanConfig};
+use tf_binding_rs::occupancy::EwmOptions;
+use tf_binding_rs::scan::check_sequence;
+use tf_binding_rs::types::EWMCollection;
+use tracing::{info, warn};
+
+#[derive(Args, Debug)]
+pub struct DiffArgs {
+    /// FASTA file (.fa/.fasta/.fna) or table (.csv/.parquet) with 'label' and 'sequence'
+    /// columns
+    #[arg(value_name = "SEQUENCES")]
+    sequences: String,
+
+    /// Motif file of the first (reference) configuration
+    #[arg(value_name = "PWM_A")]
+    pwm_a: String,
+
+    /// Motif file of the second configuration [default: PWM_A]
+    #[arg(value_name = "PWM_B")]
+    pwm_b: Option<String>,
+
+    /// Chemical potential of the first configuration
+    #[arg(long, default_value_t = 9.0)]
+    mu: f64,
+
+    /// Minimum occupancy of a site in the first configuration
+    #[arg(long, default_value_t = 0.2)]
+    cutoff: f64,
+
+    /// Temperature (°C) of the energy conversion of the first configuration
+    /// [default: RT = 2.5 kJ/mol]
+    #[arg(long, value_name = "CELSIUS", allow_negative_numbers = true)]
+    temperature: Option<f64>,
+
+    /// Chemical potential of the second configuration [default: --mu]
+    #[arg(long)]
+    mu_b: Option<f64>,
+
+    /// Minimum occupancy of a site in the second configuration [default: --cutoff]
+    #[arg(long)]
+    cutoff_b: Option<f64>,
+
+    /// Temperature (°C) of the second configuration [default: --temperature]
+    #[arg(long, value_name = "CELSIUS", allow_negative_numbers = true)]
+    temperature_b: Option<f64>,
+
+    /// Only compare these motifs (comma-separated full IDs or names before the first '_')
+    #[arg(long, value_delimiter = ',')]
+    motifs: Option<Vec<String>>,
+
+    /// Largest total occupancy difference of a sequence and motif not counted as a change
+    #[arg(long, default_value_t = 1e-6)]
+    tolerance: f64,
+
+    /// Write the per-sequence differences to this file (.csv, .parquet or .jsonl)
+    #[arg(long, value_name = "OUTPUT")]
+    output: Option<String>,
+
+    /// Fail when any motif is unpaired or any sequence and motif changed, e.g. in CI
+    #[arg(long)]
+    check: bool,
+}
+
+fn ewm_options(temperature: Option<f64>) -> Result<EwmOptions, CliError> {
+    match temperature {
+        Some(celsius) if celsius <= -273.15 => Err(CliError::InvalidArgument(format!(
+            "temperature {} °C is below absolute zero",
+            celsius
+        ))),
+        Some(celsius) => Ok(EwmOptions::default().with_temperature(celsius)),
+        None => Ok(EwmOptions::default()),
+    }
+}
+
+fn select_motifs(ewms: EWMCollection, names: &Option<Vec<String>>) -> EWMCollection {
+    match names {
+        Some(names) => ewms
+            .into_iter()
+            .filter(|(id, _)| {
+                names
+                    .iter()
+                    .any(|name| id == name || id.split('_').next() == Some(name))
+            })
+            .collect(),
+        None => ewms,
+    }
+}
+
+/// Reads the sequences, dropping those that cannot be scanned by every motif
+fn read_sequences(path: &str, longest: usize) -> Result<DataFrame, CliError> {
+    let df = read_sequence_records(path)?;
+    let scannable: BooleanChunked = df
+        .column("sequence")?
+        .str()?
+        .into_iter()
+        .map(|seq| seq.is_some_and(|seq| check_sequence(seq, longest).is_none()))
+        .collect();
+    let kept = df.filter(&scannable)?;
+    if kept.height() < df.height() {
+        warn!(
+            skipped = df.height() - kept.height(),
+            "skipped sequences that are missing, ambiguous or shorter than a motif"
+        );
+    }
+    Ok(kept)
+}
+
+pub fn run(args: DiffArgs) -> Result<(), CliError> {
+    let options_a = ewm_options(args.temperature)?;
+    let options_b = ewm_options(args.temperature_b.or(args.temperature))?;
+    let ewms_a = select_motifs(read_ewms_with(&args.pwm_a, &options_a)?, &args.motifs);
+    let pwm_b = args.pwm_b.as_deref().unwrap_or(&args.pwm_a);
+    let ewms_b = select_motifs(read_ewms_with(pwm_b, &options_b)?, &args.motifs);
+    if ewms_a.is_empty() && ewms_b.is_empty() {
+        return Err(CliError::InvalidArgument(
+            "no motifs to compare".to_string(),
+        ));
+    }
+
+    let longest = ewms_a
+        .values()
+        .chain(ewms_b.values())
+        .map(|ewm| ewm.height());
+    let sequences = read_sequences(&args.sequences, longest.max().unwrap_or(0))?;
+    let a = ScanConfig {
+        ewms: &ewms_a,
+        mu: args.mu,
+        cutoff: args.cutoff,
+    };
+    let b = ScanConfig {
+        ewms: &ewms_b,
+        mu: args.mu_b.unwrap_or(args.mu),
+        cutoff: args.cutoff_b.unwrap_or(args.cutoff),
+    };
+    let mut diff = config_diff(&sequences, &a, &b, args.tolerance)?;
+
+    for motif in &diff.only_a {
+        warn!(motif = %motif, "motif only in the first configuration");
+    }
+    for motif in &diff.only_b {
+        warn!(motif = %motif, "motif only in the second configuration");
+    }
+    println!("{}", diff.summary);
+    info!(
+        sequences = sequences.height(),
+        motifs = diff.summary.height(),
+        changed = diff.n_changed(),
+        "compared configurations"
+    );
+    if let Some(path) = &args.output {
+        write_table(&mut diff.per_sequence, path)?;
+    }
+
+    if args.check && !diff.is_identical() {
+        return Err(CliError::Differences(format!(
+            "{} sequence/motif pairs changed, {} motifs only in the first and {} only in the \
+             second configuration",
+            diff.n_changed(),
+            diff.only_a.len(),
+            diff.only_b.len()
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use crate::{read_ewms, read_sequence_records, CliError};
+use clap::Args;
+use motif_scanner::io::write_table;
+use tf_binding_rs::dimer::{find_dimer_hits, DimerOrientation, DimerSpec};
+use tracing::info;
+
+#[derive(Args, Debug)]
+pub struct DimerArgs {
+    /// FASTA file (.fa/.fasta/.fna) or table (.csv/.parquet) with 'label' and 'sequence'
+    /// columns
+    #[arg(value_name = "SEQUENCES")]
+    sequences: String,
+
+    /// Motif file holding both half-sites
+    #[arg(value_name = "PWM_FILE")]
+    pwm_file: String,
+
+    /// The two motifs of the element, FIRST,SECOND (full IDs or names before the first
+    /// '_'), e.g. CRX,NRL; give one motif twice for a homodimer
+    #[arg(long, value_delimiter = ',', num_args = 2, required = true)]
+    pair: Vec<String>,
+
+    /// Bases between the half-sites, N or MIN-MAX
+    #[arg(long, default_value = "0", value_parser = parse_spacing)]
+    spacing: (usize, usize),
+
+    /// Relative orientation of the half-sites: any, tandem (→ →), inverted (→ ←) or
+    /// everted (← →)
+    #[arg(long, default_value = "any")]
+    orientation: DimerOrientation,
+
+    /// Energy (kJ/mol) added to the summed half-site energies; negative for cooperative
+    /// binding
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    interaction: f64,
+
+    /// Chemical potential of each factor
+    #[arg(long, default_value_t = 9.0)]
+    mu: f64,
+
+    /// Minimum occupancy of an element
+    #[arg(long, default_value_t = 0.2)]
+    cutoff: f64,
+
+    /// Write the elements to this file (.csv, .parquet or .jsonl) instead of printing them
+    #[arg(long, value_name = "OUTPUT")]
+    output: Option<String>,
+}
+
+/// Parses a spacing of `N` or `MIN-MAX` bases
+fn parse_spacing(s: &str) -> Result<(usize, usize), String> {
+    let bound = |b: &str| {
+        b.trim()
+            .parse::<usize>()
+            .map_err(|_| format!("invalid spacing '{}', expected N or MIN-MAX", s))
+    };
+    match s.split_once('-') {
+        Some((min, max)) => Ok((bound(min)?, bound(max)?)),
+        None => bound(s).map(|n| (n, n)),
+    }
+}
+
+pub fn run(args: DimerArgs) -> Result<(), CliError> {
+    let ewms = read_ewms(&args.pwm_file)?;
+    let df = read_sequence_records(&args.sequences)?;
+    let spec = DimerSpec {
+        first: args.pair[0].clone(),
+        second: args.pair[1].clone(),
+        min_spacing: args.spacing.0,
+        max_spacing: args.spacing.1,
+        orientation: args.orientation,
+        interaction: args.interaction,
+    };
+
+    let mut hits = find_dimer_hits(
+        &df,
+        &ewms,
+        std::slice::from_ref(&spec),
+        args.mu,
+        args.cutoff,
+    )?;
+    info!(
+        dimer = %spec.name(),
+        orientation = %spec.orientation,
+        sequences = df.height(),
+        elements = hits.height(),
+        "scanned composite elements"
+    );
+    match &args.output {
+        Some(path) => write_table(&mut hits, path)?,
+        None => println!("{}", hits),
+    }
+    Ok(())
+}
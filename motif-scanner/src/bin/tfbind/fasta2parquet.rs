@@ -0,0 +1,41 @@
+use crate::CliError;
+use clap::Args;
+use motif_scanner::io::{parquet_compression, write_parquet};
+use tf_binding_rs::fasta::{read_fasta_with, DuplicateLabels};
+use tracing::info;
+
+#[derive(Args, Debug)]
+pub struct Fasta2parquetArgs {
+    /// Path to the input FASTA file
+    #[arg(value_name = "FASTA_FILE")]
+    input: String,
+
+    /// Path of the Parquet file with 'label' and 'sequence' columns
+    #[arg(value_name = "PARQUET_FILE")]
+    output: String,
+
+    /// Compression codec: zstd, snappy, gzip, brotli, lz4 or uncompressed
+    #[arg(long, default_value = "zstd")]
+    compression: String,
+
+    /// Compression level for zstd (1-22), gzip (0-10) or brotli (0-11)
+    #[arg(long)]
+    level: Option<i32>,
+
+    /// What to do with records repeating an earlier label: keep, error,
+    /// keep-first or suffix
+    #[arg(long, value_name = "POLICY", default_value = "keep")]
+    duplicate_labels: DuplicateLabels,
+}
+
+pub fn run(args: Fasta2parquetArgs) -> Result<(), CliError> {
+    let compression = parquet_compression(&args.compression, args.level)?;
+    let mut sequences = read_fasta_with(&args.input, args.duplicate_labels)?;
+    write_parquet(&mut sequences, &args.output, compression, None)?;
+    info!(
+        file = %args.output,
+        sequences = sequences.height(),
+        "sequences written"
+    );
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use crate::CliError;
+use clap::Args;
+use motif_scanner::io::write_table;
+use polars::prelude::*;
+use std::fs::File;
+use tf_binding_rs::fasta::{n_report, read_fasta, sequence_stats};
+use tracing::info;
+
+#[derive(Args, Debug)]
+pub struct FastaStatsArgs {
+    /// Path to the input FASTA file
+    #[arg(value_name = "FASTA_FILE")]
+    input: String,
+
+    /// Write the statistics as CSV to this file instead of printing a table
+    #[arg(long, value_name = "CSV_FILE")]
+    output: Option<String>,
+
+    /// Write the N count, runs, longest run and run positions of every sequence containing
+    /// N to this file (.csv, .parquet or .jsonl)
+    #[arg(long, value_name = "FILE")]
+    n_report: Option<String>,
+}
+
+pub fn run(args: FastaStatsArgs) -> Result<(), CliError> {
+    let sequences = read_fasta(&args.input)?;
+    let stats = sequence_stats(&sequences)?;
+    let mut table = stats.to_dataframe()?;
+
+    if let Some(path) = &args.n_report {
+        let report = n_report(&sequences)?;
+        let mut with_n = report.filter(&report.column("n_count")?.u32()?.gt(0))?;
+        write_table(&mut with_n, path)?;
+        info!(
+            file = %path,
+            sequences = with_n.height(),
+            longest_run = with_n.column("longest_n_run")?.u32()?.max().unwrap_or(0),
+            "sequences with N written"
+        );
+    }
+
+    match args.output {
+        Some(path) => {
+            let mut file = File::create(&path)?;
+            CsvWriter::new(&mut file).finish(&mut table)?;
+        }
+        None => {
+            println!("{}", table);
+            if !stats.duplicate_labels.is_empty() {
+                println!("Duplicate labels: {}", stats.duplicate_labels.join(", "));
+            }
+            if !stats.invalid_labels.is_empty() {
+                println!(
+                    "Sequences with characters outside ACGTN: {}",
+                    stats.invalid_labels.join(", ")
+                );
+            }
+        }
+    }
+    Ok(())
+}
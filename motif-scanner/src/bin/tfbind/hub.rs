@@ -0,0 +1,281 @@
+use crate::CliError;
+use clap::{Args, ValueEnum};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tracing::info;
+
+/// Distinct colors (Tableau 10), assigned to tracks in name order
+const PALETTE: [(u8, u8, u8); 10] = [
+    (31, 119, 180),
+    (255, 127, 14),
+    (44, 160, 44),
+    (214, 39, 40),
+    (148, 103, 189),
+    (140, 86, 75),
+    (227, 119, 194),
+    (127, 127, 127),
+    (188, 189, 34),
+    (23, 190, 207),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HubFormat {
+    /// UCSC track hub (single-file hub.txt)
+    Ucsc,
+    /// IGV session XML
+    Igv,
+}
+
+#[derive(Args, Debug)]
+pub struct HubArgs {
+    /// Track files: bigBed (.bb/.bigBed) and bigWig (.bw/.bigWig); IGV sessions also take
+    /// .bed and .bedGraph
+    #[arg(value_name = "TRACK_FILE", required = true)]
+    tracks: Vec<String>,
+
+    /// Genome assembly, e.g. hg38 or mm10
+    #[arg(long)]
+    genome: String,
+
+    /// Kind of file to generate
+    #[arg(long, value_enum, default_value_t = HubFormat::Ucsc)]
+    format: HubFormat,
+
+    /// Directory of the UCSC hub (hub.txt is written there), or path of the IGV session
+    #[arg(long, value_name = "PATH")]
+    output: String,
+
+    /// Hub name, used as its short label
+    #[arg(long, default_value = "tf-binding")]
+    name: String,
+
+    /// Contact email of the UCSC hub
+    #[arg(long, default_value = "")]
+    email: String,
+
+    /// URL prefix of the track files (e.g. where the hub directory is served); track paths
+    /// are relative to the hub or session otherwise
+    #[arg(long, value_name = "URL")]
+    base_url: Option<String>,
+
+    /// Color of a track, overriding the palette (repeatable), e.g. NRL=200,0,0
+    #[arg(long = "color", value_name = "TRACK=R,G,B")]
+    colors: Vec<String>,
+}
+
+/// Kind of data in a track file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TrackKind {
+    BigBed,
+    BigWig,
+    Bed,
+    BedGraph,
+}
+
+struct Track {
+    name: String,
+    url: String,
+    kind: TrackKind,
+    color: (u8, u8, u8),
+}
+
+pub fn run(args: HubArgs) -> Result<(), CliError> {
+    let overrides = args
+        .colors
+        .iter()
+        .map(|spec| parse_color(spec))
+        .collect::<Result<HashMap<_, _>, _>>()?;
+
+    // the directory that relative track paths start from
+    let base_dir = match args.format {
+        HubFormat::Ucsc => PathBuf::from(&args.output),
+        HubFormat::Igv => Path::new(&args.output)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default(),
+    };
+    fs::create_dir_all(if base_dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        &base_dir
+    })?;
+
+    let mut names: Vec<(String, &str)> = args
+        .tracks
+        .iter()
+        .map(|path| (track_name(path), path.as_str()))
+        .collect();
+    names.sort();
+    let mut tracks = Vec::new();
+    for (i, (name, path)) in names.into_iter().enumerate() {
+        let kind = track_kind(path)?;
+        if args.format == HubFormat::Ucsc && matches!(kind, TrackKind::Bed | TrackKind::BedGraph) {
+            return Err(CliError::InvalidArgument(format!(
+                "{}: UCSC hubs need bigBed or bigWig files",
+                path
+            )));
+        }
+        let url = match &args.base_url {
+            Some(base) => format!(
+                "{}/{}",
+                base.trim_end_matches('/'),
+                Path::new(path)
+                    .file_name()
+                    .and_then(|f| f.to_str())
+                    .unwrap_or(path)
+            ),
+            None => relative_path(Path::new(path), &base_dir)?,
+        };
+        let color = overrides
+            .get(&name)
+            .copied()
+            .unwrap_or(PALETTE[i % PALETTE.len()]);
+        tracks.push(Track {
+            name,
+            url,
+            kind,
+            color,
+        });
+    }
+
+    let (path, text) = match args.format {
+        HubFormat::Ucsc => (
+            base_dir.join("hub.txt"),
+            ucsc_hub(&args.name, &args.email, &args.genome, &tracks),
+        ),
+        HubFormat::Igv => (
+            PathBuf::from(&args.output),
+            igv_session(&args.genome, &tracks),
+        ),
+    };
+    fs::write(&path, text)?;
+    info!(file = %path.display(), tracks = tracks.len(), "track hub written");
+    Ok(())
+}
+
+/// Parses a `TRACK=R,G,B` color override
+fn parse_color(spec: &str) -> Result<(String, (u8, u8, u8)), CliError> {
+    let invalid =
+        || CliError::InvalidArgument(format!("invalid color '{}', expected TRACK=R,G,B", spec));
+    let (name, rgb) = spec.split_once('=').ok_or_else(invalid)?;
+    let channels = rgb
+        .split(',')
+        .map(|c| c.trim().parse::<u8>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid())?;
+    match channels[..] {
+        [r, g, b] => Ok((name.to_string(), (r, g, b))),
+        _ => Err(invalid()),
+    }
+}
+
+/// Track name: the file stem with characters other than letters, digits and '_' replaced
+fn track_name(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(path)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+fn track_kind(path: &str) -> Result<TrackKind, CliError> {
+    let ext = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    match ext.as_str() {
+        "bb" | "bigbed" => Ok(TrackKind::BigBed),
+        "bw" | "bigwig" => Ok(TrackKind::BigWig),
+        "bed" => Ok(TrackKind::Bed),
+        "bedgraph" => Ok(TrackKind::BedGraph),
+        _ => Err(CliError::InvalidArgument(format!(
+            "{}: unknown track type, expected .bb, .bw, .bed or .bedGraph",
+            path
+        ))),
+    }
+}
+
+/// Path of `file` relative to `dir`, with '/' separators
+fn relative_path(file: &Path, dir: &Path) -> Result<String, CliError> {
+    let file = fs::canonicalize(file)?;
+    let dir = fs::canonicalize(if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    })?;
+    let (file, dir): (Vec<Component>, Vec<Component>) =
+        (file.components().collect(), dir.components().collect());
+    let common = file.iter().zip(&dir).take_while(|(a, b)| a == b).count();
+    let parts: Vec<String> = std::iter::repeat_n("..".to_string(), dir.len() - common)
+        .chain(
+            file[common..]
+                .iter()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned()),
+        )
+        .collect();
+    Ok(parts.join("/"))
+}
+
+/// A single-file UCSC hub (`useOneFile on`)
+fn ucsc_hub(name: &str, email: &str, genome: &str, tracks: &[Track]) -> String {
+    let mut hub = format!(
+        "hub {}\nshortLabel {}\nlongLabel {} predicted binding sites\nuseOneFile on\nemail {}\n\ngenome {}\n",
+        track_name(name), name, name, email, genome
+    );
+    for track in tracks {
+        let (kind, visibility) = match track.kind {
+            TrackKind::BigWig => ("bigWig", "full"),
+            _ => ("bigBed 6", "pack"),
+        };
+        let (r, g, b) = track.color;
+        let _ = write!(
+            hub,
+            "\ntrack {name}\nbigDataUrl {url}\nshortLabel {name}\nlongLabel {name}\ntype {kind}\ncolor {r},{g},{b}\nvisibility {visibility}\n",
+            name = track.name,
+            url = track.url,
+        );
+    }
+    hub
+}
+
+/// An IGV session loading every track in its color
+fn igv_session(genome: &str, tracks: &[Track]) -> String {
+    let mut session = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n<Session genome=\"{}\" version=\"8\">\n    <Resources>\n",
+        xml_escape(genome)
+    );
+    for track in tracks {
+        let _ = writeln!(
+            session,
+            "        <Resource path=\"{}\"/>",
+            xml_escape(&track.url)
+        );
+    }
+    session.push_str("    </Resources>\n    <Panel name=\"DataPanel\">\n");
+    for track in tracks {
+        let (r, g, b) = track.color;
+        let _ = writeln!(
+            session,
+            "        <Track id=\"{}\" name=\"{}\" color=\"{},{},{}\" visible=\"true\"/>",
+            xml_escape(&track.url),
+            xml_escape(&track.name),
+            r,
+            g,
+            b
+        );
+    }
+    session.push_str("    </Panel>\n</Session>\n");
+    session
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,359 @@
+use crate::subset::LabelArgs;
+use crate::view::select_motifs;
+use crate::{read_ewms, read_sequence_records, CliError};
+use clap::{Args, ValueEnum};
+use motif_scanner::io::write_table;
+use polars::prelude::*;
+use std::path::Path;
+use tf_binding_rs::error::MotifError;
+use tf_binding_rs::fasta::{subset, subset_file};
+use tf_binding_rs::occupancy::{MotifBank, Precision};
+use tracing::{info, warn};
+
+#[derive(Args, Debug)]
+pub struct LandscapeArgs {
+    /// FASTA file (.fa/.fasta/.fna; its .fai index is used when present) or table
+    /// (.csv/.parquet) with 'label' and 'sequence' columns
+    #[arg(value_name = "SEQUENCES")]
+    sequences: String,
+
+    /// Path to the motif file (MEME, JASPAR, TRANSFAC, HOMER or HOCOMOCO, by extension)
+    #[arg(value_name = "PWM_FILE")]
+    pwm_file: String,
+
+    #[command(flatten)]
+    selection: LabelArgs,
+
+    /// Only these motifs (comma-separated full IDs or names before the first '_')
+    #[arg(long, value_delimiter = ',')]
+    motifs: Option<Vec<String>>,
+
+    /// Table layout: one row per sequence, motif, strand and window (long) or one row per
+    /// sequence and position with a MOTIF_F and a MOTIF_R column per motif (wide)
+    #[arg(long, value_enum, default_value_t = Layout::Long)]
+    layout: Layout,
+
+    /// Write binding energies (kJ/mol) instead of occupancies
+    #[arg(long)]
+    energies: bool,
+
+    /// Chemical potential of the transcription factors
+    #[arg(long, default_value_t = 9.0)]
+    mu: f64,
+
+    /// Precision of the landscapes: f64, or f32 for half the memory with differences far
+    /// below any signal (Float32 columns)
+    #[arg(long, default_value = "f64")]
+    precision: Precision,
+
+    /// Write the landscapes to this file (.csv, .parquet or .jsonl, or .bw for bigWig with
+    /// the `bigbed` feature) instead of printing them
+    #[arg(long, value_name = "OUTPUT")]
+    output: Option<String>,
+
+    /// Chromosome sizes of the assembly, for .bw outputs
+    #[arg(long, value_name = "FILE")]
+    chrom_sizes: Option<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Layout {
+    Long,
+    Wide,
+}
+
+/// Landscapes of one selected sequence, in `MotifBank::ids()` order, with f64 or f32 values
+struct SequenceLandscape<T> {
+    label: String,
+    length: usize,
+    scores: Vec<(Vec<T>, Vec<T>)>,
+}
+
+/// Values the landscapes can be computed in
+trait Score: Copy + Into<f64> {
+    fn column(name: &str, values: Vec<Self>) -> Column;
+    fn nullable_column(name: &str, values: Vec<Option<Self>>) -> Column;
+}
+
+impl Score for f64 {
+    fn column(name: &str, values: Vec<Self>) -> Column {
+        Column::new(name.into(), values)
+    }
+
+    fn nullable_column(name: &str, values: Vec<Option<Self>>) -> Column {
+        Column::new(name.into(), values)
+    }
+}
+
+impl Score for f32 {
+    fn column(name: &str, values: Vec<Self>) -> Column {
+        Column::new(name.into(), values)
+    }
+
+    fn nullable_column(name: &str, values: Vec<Option<Self>>) -> Column {
+        Column::new(name.into(), values)
+    }
+}
+
+pub fn run(args: LandscapeArgs) -> Result<(), CliError> {
+    let ewms = select_motifs(read_ewms(&args.pwm_file)?, &args.motifs);
+    if ewms.is_empty() {
+        return Err(CliError::InvalidArgument("no motifs selected".to_string()));
+    }
+    let bank = MotifBank::new(&ewms)?;
+    let records = args.selection.select(|selector| {
+        match Path::new(&args.sequences)
+            .extension()
+            .and_then(|ext| ext.to_str())
+        {
+            Some("fa" | "fasta" | "fna") => Ok(subset_file(&args.sequences, selector)?),
+            _ => Ok(subset(&read_sequence_records(&args.sequences)?, selector)?),
+        }
+    })?;
+
+    match (args.precision, args.energies) {
+        (Precision::F64, true) => {
+            write_landscapes(&args, &bank, &records, |seq| bank.energy_landscapes(seq))
+        }
+        (Precision::F64, false) => write_landscapes(&args, &bank, &records, |seq| {
+            bank.occupancy_landscapes(seq, args.mu)
+        }),
+        (Precision::F32, true) => write_landscapes(&args, &bank, &records, |seq| {
+            bank.energy_landscapes_f32(seq)
+        }),
+        (Precision::F32, false) => write_landscapes(&args, &bank, &records, |seq| {
+            bank.occupancy_landscapes_f32(seq, args.mu)
+        }),
+    }
+}
+
+/// Computes the landscapes of the selected records with `score` and writes them
+fn write_landscapes<T, F>(
+    args: &LandscapeArgs,
+    bank: &MotifBank,
+    records: &DataFrame,
+    score: F,
+) -> Result<(), CliError>
+where
+    T: Score,
+    F: Fn(&str) -> Result<Vec<(Vec<T>, Vec<T>)>, MotifError>,
+{
+    let mut landscapes = Vec::with_capacity(records.height());
+    for (label, sequence) in records
+        .column("label")?
+        .str()?
+        .into_no_null_iter()
+        .zip(records.column("sequence")?.str()?.into_no_null_iter())
+    {
+        match score(sequence) {
+            Ok(scores) => landscapes.push(SequenceLandscape {
+                label: label.to_string(),
+                length: sequence.len(),
+                scores,
+            }),
+            Err(e) => warn!(label, error = %e, "sequence skipped"),
+        }
+    }
+    info!(
+        sequences = landscapes.len(),
+        motifs = bank.ids().len(),
+        precision = %args.precision,
+        "computed landscapes"
+    );
+
+    let column = if args.energies { "energy" } else { "occupancy" };
+    match args.output.as_deref() {
+        Some(path) if is_bigwig(path) => write_bigwigs(args, bank.ids(), &landscapes, path),
+        output => {
+            let mut table = match args.layout {
+                Layout::Long => long_table(bank.ids(), &landscapes, column)?,
+                Layout::Wide => wide_table(bank.ids(), &landscapes)?,
+            };
+            match output {
+                Some(path) => {
+                    write_table(&mut table, path)?;
+                    info!(file = %path, rows = table.height(), "landscapes written");
+                }
+                None => println!("{}", table),
+            }
+            Ok(())
+        }
+    }
+}
+
+/// label, motif, position, strand and `column` of every window
+fn long_table<T: Score>(
+    ids: &[String],
+    landscapes: &[SequenceLandscape<T>],
+    column: &str,
+) -> Result<DataFrame, CliError> {
+    let mut labels = Vec::new();
+    let mut motifs = Vec::new();
+    let mut positions = Vec::new();
+    let mut strands = Vec::new();
+    let mut values = Vec::new();
+    for landscape in landscapes {
+        for (id, (fscores, rscores)) in ids.iter().zip(&landscape.scores) {
+            for (strand, scores) in [("F", fscores), ("R", rscores)] {
+                labels.extend(std::iter::repeat_n(landscape.label.as_str(), scores.len()));
+                motifs.extend(std::iter::repeat_n(id.as_str(), scores.len()));
+                positions.extend(0..scores.len() as i32);
+                strands.extend(std::iter::repeat_n(strand, scores.len()));
+                values.extend_from_slice(scores);
+            }
+        }
+    }
+    Ok(DataFrame::new(vec![
+        Column::new("label".into(), labels),
+        Column::new("motif".into(), motifs),
+        Column::new("position".into(), positions),
+        Column::new("strand".into(), strands),
+        T::column(column, values),
+    ])?)
+}
+
+/// label, position and a MOTIF_F and MOTIF_R column per motif, one row per base; windows
+/// running past the end of a sequence are null
+fn wide_table<T: Score>(
+    ids: &[String],
+    landscapes: &[SequenceLandscape<T>],
+) -> Result<DataFrame, CliError> {
+    let rows: usize = landscapes.iter().map(|landscape| landscape.length).sum();
+    let mut labels = Vec::with_capacity(rows);
+    let mut positions = Vec::with_capacity(rows);
+    let mut tracks: Vec<Vec<Option<T>>> = vec![Vec::with_capacity(rows); 2 * ids.len()];
+    for landscape in landscapes {
+        labels.extend(std::iter::repeat_n(
+            landscape.label.as_str(),
+            landscape.length,
+        ));
+        positions.extend(0..landscape.length as i32);
+        for (i, (fscores, rscores)) in landscape.scores.iter().enumerate() {
+            for (track, scores) in [(2 * i, fscores), (2 * i + 1, rscores)] {
+                let track = &mut tracks[track];
+                track.extend(scores.iter().copied().map(Some));
+                track.extend(std::iter::repeat_n(None, landscape.length - scores.len()));
+            }
+        }
+    }
+    let mut columns = vec![
+        Column::new("label".into(), labels),
+        Column::new("position".into(), positions),
+    ];
+    for (i, track) in tracks.into_iter().enumerate() {
+        let strand = if i % 2 == 0 { "F" } else { "R" };
+        columns.push(T::nullable_column(
+            &format!("{}_{}", ids[i / 2], strand),
+            track,
+        ));
+    }
+    Ok(DataFrame::new(columns)?)
+}
+
+fn is_bigwig(path: &str) -> bool {
+    matches!(
+        Path::new(path).extension().and_then(|ext| ext.to_str()),
+        Some("bw" | "bigWig" | "bigwig")
+    )
+}
+
+/// Path of the bigWig of `motif` when several motifs are written, e.g. `out.NRL_1.bw` for
+/// `out.bw`
+#[cfg(feature = "bigbed")]
+fn bigwig_path(output: &str, motif: &str) -> String {
+    let path = Path::new(output);
+    let stem = path.with_extension("");
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("bw");
+    format!("{}.{}.{}", stem.display(), motif, extension)
+}
+
+/// Writes one bigWig per motif of the landscapes projected onto the genome through the
+/// coordinates in the labels; each base holds the better strand of the window starting
+/// (leftmost) there, and bases covered by several sequences the best of them
+#[cfg(feature = "bigbed")]
+fn write_bigwigs<T: Score>(
+    args: &LandscapeArgs,
+    ids: &[String],
+    landscapes: &[SequenceLandscape<T>],
+    output: &str,
+) -> Result<(), CliError> {
+    use std::collections::HashMap;
+    use tf_binding_rs::coords::{read_chrom_sizes, LabelParser};
+    use tf_binding_rs::output::write_bigwig;
+
+    let sizes_file = args.chrom_sizes.as_deref().ok_or_else(|| {
+        CliError::InvalidArgument("--chrom-sizes is required for .bw outputs".to_string())
+    })?;
+    let sizes = read_chrom_sizes(sizes_file)?;
+    let parser = LabelParser::default();
+    let regions: Vec<_> = landscapes
+        .iter()
+        .map(|landscape| parser.parse(&landscape.label))
+        .collect();
+    let unparsed = regions.iter().filter(|region| region.is_none()).count();
+    if unparsed > 0 {
+        warn!(
+            sequences = unparsed,
+            "labels without genomic coordinates are left out of the bigWig"
+        );
+    }
+    // occupancies are better high, energies low
+    let better = |a: f64, b: f64| {
+        if args.energies {
+            a.min(b)
+        } else {
+            a.max(b)
+        }
+    };
+
+    for (i, id) in ids.iter().enumerate() {
+        let mut bases: HashMap<(String, u32), f64> = HashMap::new();
+        for (landscape, region) in landscapes.iter().zip(&regions) {
+            let Some(region) = region else {
+                continue;
+            };
+            let (fscores, rscores) = &landscape.scores[i];
+            let width = (landscape.length + 1 - fscores.len()) as u64;
+            for (p, (&f, &r)) in fscores.iter().zip(rscores).enumerate() {
+                let (f, r) = (f.into(), r.into());
+                let start = if region.minus_strand {
+                    region.end - p as u64 - width
+                } else {
+                    region.start + p as u64
+                };
+                bases
+                    .entry((region.chrom.clone(), start as u32))
+                    .and_modify(|v| *v = better(*v, better(f, r)))
+                    .or_insert(better(f, r));
+            }
+        }
+        let values: Vec<(String, u32, f32)> = bases
+            .into_iter()
+            .map(|((chrom, position), value)| (chrom, position, value as f32))
+            .collect();
+        let path = if ids.len() == 1 {
+            output.to_string()
+        } else {
+            bigwig_path(output, id)
+        };
+        let written = write_bigwig(&values, &sizes, &path)?;
+        info!(file = %path, motif = %id, bases = written, "bigWig written");
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "bigbed"))]
+fn write_bigwigs<T: Score>(
+    args: &LandscapeArgs,
+    _ids: &[String],
+    _landscapes: &[SequenceLandscape<T>],
+    _output: &str,
+) -> Result<(), CliError> {
+    let _ = &args.chrom_sizes;
+    Err(CliError::InvalidArgument(
+        ".bw output requires building with the `bigbed` feature".to_string(),
+    ))
+}
@@ -0,0 +1,202 @@
+use clap::{Parser, Subcommand};
+use motif_scanner::io::read_table;
+use motif_scanner::logging::{init_logging, LogFormat};
+use polars::prelude::DataFrame;
+use std::path::Path;
+use tf_binding_rs::error::MotifError;
+use tf_binding_rs::fasta::read_fasta;
+use tf_binding_rs::formats::{read_motifs, MotifFormat};
+use tf_binding_rs::occupancy::{pwm_to_ewm_with, EwmOptions};
+use tf_binding_rs::types::EWMCollection;
+
+mod bench;
+mod convert;
+mod diff;
+mod dimer;
+mod fasta2parquet;
+mod fasta_stats;
+mod hub;
+mod landscape;
+mod matrix;
+mod merge;
+mod migrate;
+mod parquet2fasta;
+mod scan_genome;
+#[cfg(feature = "serve")]
+mod serve;
+mod strand_bias;
+mod subset;
+mod tiles;
+mod trim;
+mod variants;
+mod view;
+
+#[derive(thiserror::Error, Debug)]
+pub enum CliError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("{0}")]
+    Motif(#[from] MotifError),
+
+    #[error("Polars error: {0}")]
+    Polars(#[from] polars::prelude::PolarsError),
+
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+
+    #[error("Results differ: {0}")]
+    Differences(String),
+}
+
+/// Reads a motif file as energy matrices, guessing the format from the extension (MEME if
+/// unknown)
+pub fn read_ewms(path: &str) -> Result<EWMCollection, CliError> {
+    read_ewms_with(path, &EwmOptions::default())
+}
+
+/// Reads a motif file as energy matrices converted with `options`; see `read_ewms()`
+pub fn read_ewms_with(path: &str, options: &EwmOptions) -> Result<EWMCollection, CliError> {
+    let format = MotifFormat::from_path(path).unwrap_or(MotifFormat::Meme);
+    read_motifs(path, format)?
+        .iter()
+        .map(|(id, pwm)| Ok((id.clone(), pwm_to_ewm_with(pwm, options)?)))
+        .collect()
+}
+
+/// Reads sequences from a FASTA file (.fa/.fasta/.fna) or a table (.csv/.parquet) with
+/// 'label' and 'sequence' columns, in file order
+pub fn read_sequence_records(path: &str) -> Result<DataFrame, CliError> {
+    match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+        Some("fa" | "fasta" | "fna") => Ok(read_fasta(path)?),
+        _ => Ok(read_table(path)?.collect()?),
+    }
+}
+
+#[derive(Parser)]
+#[command(
+    name = "tfbind",
+    about = "Toolkit for transcription factor binding analysis",
+    long_about = "A collection of subcommands for working with transcription factor motifs and \
+                  DNA sequences, built on the tf-binding-rs library.",
+    author = "Jiayu Huang | WUSTL Cohen Lab",
+    version,
+    after_help = "Example usage:\n    \
+                  tfbind bench --threads 1,8 --methods exact,quantized\n    \
+                  tfbind convert motifs.meme motifs.jaspar\n    \
+                  tfbind convert JASPAR2024.jaspar motifs.meme --trim 0.3 --pseudocount 0.01\n    \
+                  tfbind diff enhancers.fa JASPAR2022.meme JASPAR2024.meme --check\n    \
+                  tfbind dimer retina.fa motifs.meme --pair CRX,NRL --spacing 0-6 --orientation tandem\n    \
+                  tfbind fasta2parquet library.fasta library.parquet --compression zstd\n    \
+                  tfbind fasta-stats library.fasta --output stats.csv\n    \
+                  tfbind hub NRL.bb CRX.bb --genome mm10 --output hub\n    \
+                  tfbind landscape enhancers.fa motifs.meme --labels enh1,enh2 --output enh.parquet\n    \
+                  tfbind matrix hits.parquet occupancy.csv --aggregate max --sequences library.fa\n    \
+                  tfbind merge shard_*.parquet --output hits.parquet --sort --add-source\n    \
+                  tfbind migrate old_hits.csv --output hits.parquet --pwm-file motifs.meme\n    \
+                  tfbind scan-genome hg38.fa JASPAR2024.meme hits/ --regions peaks.bed --threads 16\n    \
+                  tfbind strand-bias hits.parquet\n    \
+                  tfbind subset genome.fa --labels enh1,enh2 --output enhancers.fa\n    \
+                  tfbind tiles hits.parquet tiles.fa --max-shift 2 --output sites.csv\n    \
+                  tfbind trim library.fa trimmed.fa --adapter-5p ACTGGCCGCTTGACG --adapter-3p CACTGCGGCTCCTGC\n    \
+                  tfbind variants designs.fa edited.fa motifs.meme --output effects.csv\n    \
+                  tfbind view enhancers.fasta motifs.meme --label enh1 --cutoff 0.5\n    \
+                  tfbind serve --motif-set jaspar=JASPAR2024.jaspar --addr 0.0.0.0:8080",
+    color = clap::ColorChoice::Always
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+
+    /// Log level (error, warn, info, debug, trace) or filter directive such as
+    /// "info,tf_binding_rs=debug"
+    #[arg(long, global = true, default_value = "info")]
+    log_level: String,
+
+    /// Format of the log lines written to stderr
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Measure scanning throughput (bp·motifs/sec) on standardized synthetic workloads
+    Bench(bench::BenchArgs),
+    /// Convert motif files between MEME, JASPAR, TRANSFAC, HOMER and HOCOMOCO formats
+    Convert(convert::ConvertArgs),
+    /// Scan sequences under two motif files or parameter sets and report the differences
+    Diff(diff::DiffArgs),
+    /// Scan for composite elements of two motifs with a set spacing and orientation
+    Dimer(dimer::DimerArgs),
+    /// Convert a FASTA file to a Parquet table with 'label' and 'sequence' columns
+    Fasta2parquet(fasta2parquet::Fasta2parquetArgs),
+    /// Report sequence count, length distribution, GC/N content and alphabet problems of a FASTA file
+    FastaStats(fasta_stats::FastaStatsArgs),
+    /// Generate a UCSC track hub or IGV session referencing bigBed/bigWig/BED outputs
+    Hub(hub::HubArgs),
+    /// Write the full per-position occupancy or energy landscapes of selected sequences
+    Landscape(landscape::LandscapeArgs),
+    /// Pivot a hit table into a sequences × motifs matrix of summed or maximum occupancy
+    Matrix(matrix::MatrixArgs),
+    /// Concatenate and deduplicate sharded scan outputs
+    Merge(merge::MergeArgs),
+    /// Upgrade hit tables of older versions to the current schema, filling added columns
+    Migrate(migrate::MigrateArgs),
+    /// Convert a table with 'label' and 'sequence' columns back to FASTA
+    Parquet2fasta(parquet2fasta::Parquet2fastaArgs),
+    /// Scan an indexed genome chromosome by chromosome, in parallel chunks, into
+    /// coordinate-sorted per-chromosome outputs
+    ScanGenome(scan_genome::ScanGenomeArgs),
+    /// Serve on-demand scanning over HTTP, with motif collections loaded once at startup
+    #[cfg(feature = "serve")]
+    Serve(serve::ServeArgs),
+    /// Count forward and reverse hits per motif and test for strand bias
+    StrandBias(strand_bias::StrandBiasArgs),
+    /// Extract records by label or label pattern from a FASTA file, using its .fai index if present
+    Subset(subset::SubsetArgs),
+    /// Merge hits of overlapping tiles into non-redundant genomic sites with tile support
+    Tiles(tiles::TilesArgs),
+    /// Remove adapter or primer sequences and fixed numbers of bases from the ends of sequences
+    Trim(trim::TrimArgs),
+    /// Pair reference and edited sequences by label and compare occupancy at every differing base
+    Variants(variants::VariantsArgs),
+    /// Draw a sequence with its predicted binding sites as tracks in the terminal
+    View(view::ViewArgs),
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = init_logging(&cli.log_level, cli.log_format) {
+        eprintln!("Error: {}", e);
+        std::process::exit(2);
+    }
+
+    let result = match cli.command {
+        Command::Bench(args) => bench::run(args),
+        Command::Convert(args) => convert::run(args),
+        Command::Diff(args) => diff::run(args),
+        Command::Dimer(args) => dimer::run(args),
+        Command::Fasta2parquet(args) => fasta2parquet::run(args),
+        Command::FastaStats(args) => fasta_stats::run(args),
+        Command::Hub(args) => hub::run(args),
+        Command::Landscape(args) => landscape::run(args),
+        Command::Matrix(args) => matrix::run(args),
+        Command::Merge(args) => merge::run(args),
+        Command::Migrate(args) => migrate::run(args),
+        Command::Parquet2fasta(args) => parquet2fasta::run(args),
+        Command::ScanGenome(args) => scan_genome::run(args),
+        #[cfg(feature = "serve")]
+        Command::Serve(args) => serve::run(args),
+        Command::StrandBias(args) => strand_bias::run(args),
+        Command::Subset(args) => subset::run(args),
+        Command::Tiles(args) => tiles::run(args),
+        Command::Trim(args) => trim::run(args),
+        Command::Variants(args) => variants::run(args),
+        Command::View(args) => view::run(args),
+    };
+
+    if let Err(e) = result {
+        tracing::error!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,58 @@
+use crate::{read_sequence_records, CliError};
+use clap::{Args, ValueEnum};
+use motif_scanner::io::{read_table, write_table};
+use tf_binding_rs::features::{hit_matrix, Aggregation};
+use tracing::info;
+
+/// How the hits of a motif in a sequence are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Aggregate {
+    /// Summed occupancy of the hits
+    Sum,
+    /// Occupancy of the strongest hit
+    Max,
+}
+
+#[derive(Args, Debug)]
+pub struct MatrixArgs {
+    /// Scan output with 'label', 'motif' and 'occupancy' columns (.csv or .parquet)
+    #[arg(value_name = "HITS_FILE")]
+    input: String,
+
+    /// Output file with a 'label' column and one column per motif (.csv, .parquet or
+    /// .jsonl)
+    #[arg(value_name = "OUTPUT")]
+    output: String,
+
+    /// How the hits of a motif in a sequence are combined
+    #[arg(long, value_enum, default_value_t = Aggregate::Sum)]
+    aggregate: Aggregate,
+
+    /// Scanned sequences (FASTA, or a table with a 'label' column) giving the rows and their
+    /// order, so sequences without hits get a row of zeros
+    #[arg(long, value_name = "FILE")]
+    sequences: Option<String>,
+}
+
+pub fn run(args: MatrixArgs) -> Result<(), CliError> {
+    let hits = read_table(&args.input)?.collect()?;
+    let sequences = args
+        .sequences
+        .as_deref()
+        .map(read_sequence_records)
+        .transpose()?;
+    let aggregation = match args.aggregate {
+        Aggregate::Sum => Aggregation::Sum,
+        Aggregate::Max => Aggregation::Max,
+    };
+
+    let mut matrix = hit_matrix(&hits, sequences.as_ref(), aggregation)?;
+    write_table(&mut matrix, &args.output)?;
+    info!(
+        sequences = matrix.height(),
+        motifs = matrix.width() - 1,
+        output = %args.output,
+        "wrote occupancy matrix"
+    );
+    Ok(())
+}
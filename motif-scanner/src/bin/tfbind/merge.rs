@@ -0,0 +1,98 @@
+use crate::CliError;
+use clap::Args;
+use motif_scanner::io::{read_table, write_table};
+use polars::prelude::*;
+use tracing::info;
+
+#[derive(Args, Debug)]
+pub struct MergeArgs {
+    /// Scan output files to merge (.csv or .parquet)
+    #[arg(value_name = "INPUT", required = true, num_args = 2..)]
+    inputs: Vec<String>,
+
+    /// Path of the merged output (.csv, .parquet or .jsonl)
+    #[arg(short, long, value_name = "OUTPUT")]
+    output: String,
+
+    /// Sort the merged hits by label and position (then motif and strand)
+    #[arg(long)]
+    sort: bool,
+
+    /// Keep duplicate rows instead of removing them
+    #[arg(long)]
+    keep_duplicates: bool,
+
+    /// Add a 'source_file' column naming the shard each row came from; required when the
+    /// hits are labelled by row index, since shards number their sequences independently
+    #[arg(long)]
+    add_source: bool,
+}
+
+pub fn run(args: MergeArgs) -> Result<(), CliError> {
+    // row index labels only identify sequences within their shard
+    let mut indexed = false;
+    let frames = args
+        .inputs
+        .iter()
+        .map(|path| {
+            let mut lf = read_table(path)?;
+            if lf
+                .collect_schema()?
+                .get("label")
+                .is_some_and(|dtype| dtype.is_integer())
+            {
+                if !args.add_source {
+                    return Err(CliError::InvalidArgument(format!(
+                        "{path} labels its hits by row index, which collide between shards; \
+                         pass --add-source to tell them apart"
+                    )));
+                }
+                indexed = true;
+            }
+            Ok(if args.add_source {
+                lf.with_column(lit(path.as_str()).alias("source_file"))
+            } else {
+                lf
+            })
+        })
+        .collect::<Result<Vec<_>, CliError>>()?;
+
+    // diagonal concatenation fills columns missing from a shard with nulls, and
+    // supertypes reconcile columns read with different types (e.g. i32/i64)
+    let mut merged = concat_lf_diagonal(
+        frames,
+        UnionArgs {
+            to_supertypes: true,
+            ..Default::default()
+        },
+    )?;
+
+    if !args.keep_duplicates {
+        // a hit found in several shards is kept once, unless its label is a row index
+        let subset = (!indexed).then(|| -> PolarsResult<Vec<PlSmallStr>> {
+            Ok(merged
+                .collect_schema()?
+                .iter_names()
+                .filter(|name| name.as_str() != "source_file")
+                .cloned()
+                .collect())
+        });
+        merged = merged.unique_stable(subset.transpose()?, UniqueKeepStrategy::First);
+    }
+
+    if args.sort {
+        let schema = merged.collect_schema()?;
+        let keys: Vec<&str> = ["source_file", "label", "position", "motif", "strand"]
+            .into_iter()
+            .filter(|name| schema.get(name).is_some())
+            .collect();
+        if !keys.is_empty() {
+            merged = merged.sort(keys, SortMultipleOptions::default());
+        }
+    }
+
+    let mut df = merged.collect()?;
+    write_table(&mut df, &args.output)?;
+    info!(files = args.inputs.len(), rows = df.height(), output = %args.output, "merged outputs");
+    Ok(())
+}
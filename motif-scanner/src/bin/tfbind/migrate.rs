@@ -0,0 +1,135 @@
+use crate::{read_ewms, read_sequence_records, CliError};
+use clap::Args;
+use motif_scanner::io::{read_schema_version, read_table, write_schema, write_table};
+use std::path::Path;
+use tf_binding_rs::coords::{CoordinateSystem, LabelParser, DEFAULT_LABEL_PATTERN};
+use tf_binding_rs::provenance::{manifest_path, RunManifest};
+use tf_binding_rs::schema::{detect_version, migrate, missing_columns, MigrationSources};
+use tf_binding_rs::schema::{SCHEMA_HISTORY, SCHEMA_VERSION};
+use tracing::{info, warn};
+
+#[derive(Args, Debug)]
+pub struct MigrateArgs {
+    /// Hit table written by any version of motif-scanner (.csv or .parquet)
+    #[arg(value_name = "HITS_FILE")]
+    input: String,
+
+    /// Path of the upgraded table (.csv, .parquet or .jsonl)
+    #[arg(short, long, value_name = "OUTPUT")]
+    output: String,
+
+    /// Chemical potential of the scan, to compute energies and p-values [default: the mu
+    /// of the run manifest next to HITS_FILE]
+    #[arg(long)]
+    mu: Option<f64>,
+
+    /// Motif file of the scan, to compute relative scores and p-values
+    #[arg(long, value_name = "PWM_FILE")]
+    pwm_file: Option<String>,
+
+    /// Scanned sequences (FASTA or table with 'label' and 'sequence' columns), to add the
+    /// matched sequences
+    #[arg(long, value_name = "SEQUENCES")]
+    sequences: Option<String>,
+
+    /// Also add genomic coordinates parsed from the labels of --sequences
+    #[arg(long, requires = "sequences")]
+    genomic: bool,
+
+    /// Regular expression replacing the default label pattern of --genomic
+    #[arg(long, value_name = "REGEX", requires = "genomic")]
+    label_pattern: Option<String>,
+
+    /// Label starts are 1-based instead of 0-based
+    #[arg(long, requires = "genomic")]
+    one_based_labels: bool,
+}
+
+pub fn run(args: MigrateArgs) -> Result<(), CliError> {
+    let hits = read_table(&args.input)?.collect()?;
+    let manifest_file = manifest_path(&args.input);
+    let manifest = if Path::new(&manifest_file).exists() {
+        Some(RunManifest::read(&manifest_file)?)
+    } else {
+        None
+    };
+    let recorded = match read_schema_version(&args.input)? {
+        Some(version) => Some(version),
+        None => manifest
+            .as_ref()
+            .and_then(|m| m.parameters.get("schema_version"))
+            .and_then(|v| v.parse().ok()),
+    };
+    let version = recorded.unwrap_or_else(|| detect_version(&hits));
+    if version > SCHEMA_VERSION {
+        return Err(CliError::InvalidArgument(format!(
+            "{} has schema version {}, newer than this tfbind ({})",
+            args.input, version, SCHEMA_VERSION
+        )));
+    }
+
+    let mu = args.mu.or_else(|| {
+        manifest
+            .as_ref()
+            .and_then(|m| m.parameters.get("mu"))
+            .and_then(|mu| mu.parse().ok())
+    });
+    let ewms = args.pwm_file.as_deref().map(read_ewms).transpose()?;
+    let sequences = args
+        .sequences
+        .as_deref()
+        .map(read_sequence_records)
+        .transpose()?;
+    let parser = if args.genomic {
+        let system = if args.one_based_labels {
+            CoordinateSystem::OneBased
+        } else {
+            CoordinateSystem::ZeroBased
+        };
+        let pattern = args
+            .label_pattern
+            .as_deref()
+            .unwrap_or(DEFAULT_LABEL_PATTERN);
+        Some(LabelParser::new(pattern, system)?)
+    } else {
+        None
+    };
+
+    let missing = missing_columns(&hits);
+    let sources = MigrationSources {
+        mu,
+        ewms: ewms.as_ref(),
+        sequences: sequences.as_ref(),
+        label_parser: parser.as_ref(),
+    };
+    let mut migrated = migrate(&hits, &sources)?;
+    let unfilled: Vec<&str> = missing
+        .iter()
+        .copied()
+        .filter(|name| {
+            migrated
+                .column(name)
+                .is_ok_and(|c| c.null_count() == c.len())
+        })
+        .collect();
+    if !unfilled.is_empty() && hits.height() > 0 {
+        warn!(
+            "no values for {}; pass --mu, --pwm-file or --sequences to compute them",
+            unfilled.join(", ")
+        );
+    }
+    for change in SCHEMA_HISTORY.iter().filter(|v| v.version > version) {
+        info!(version = change.version, "added {}", change.description);
+    }
+
+    write_table(&mut migrated, &args.output)?;
+    write_schema(&migrated, migrated.height(), &args.output)?;
+    info!(
+        file = %args.output,
+        from = version,
+        to = SCHEMA_VERSION,
+        added = missing.len(),
+        "migrated"
+    );
+    Ok(())
+}
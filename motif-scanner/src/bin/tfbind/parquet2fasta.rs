@@ -0,0 +1,44 @@
+use crate::CliError;
+use clap::Args;
+use motif_scanner::io::read_table;
+use polars::prelude::*;
+use tf_binding_rs::fasta::write_fasta;
+use tracing::info;
+
+#[derive(Args, Debug)]
+pub struct Parquet2fastaArgs {
+    /// Table with 'label' and 'sequence' columns (.parquet or .csv)
+    #[arg(value_name = "INPUT")]
+    input: String,
+
+    /// Path of the FASTA file to write
+    #[arg(value_name = "FASTA_FILE")]
+    output: String,
+}
+
+pub fn run(args: Parquet2fastaArgs) -> Result<(), CliError> {
+    let sequences = read_table(&args.input)?
+        .select([
+            col("label").cast(DataType::String),
+            col("sequence").cast(DataType::String),
+        ])
+        .collect()?;
+
+    for name in ["label", "sequence"] {
+        let nulls = sequences.column(name)?.null_count();
+        if nulls > 0 {
+            return Err(CliError::InvalidArgument(format!(
+                "{} rows of {} have no {}",
+                nulls, args.input, name
+            )));
+        }
+    }
+
+    write_fasta(&sequences, &args.output)?;
+    info!(
+        file = %args.output,
+        sequences = sequences.height(),
+        "sequences written"
+    );
+    Ok(())
+}
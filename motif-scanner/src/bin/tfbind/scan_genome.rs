@@ -0,0 +1,416 @@
+use crate::view::select_motifs;
+use crate::{read_ewms, CliError};
+use clap::{Args, ValueEnum};
+use motif_scanner::io::write_table;
+use polars::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+use tf_binding_rs::fasta::{read_fasta_index, subset_file, LabelSelector};
+use tf_binding_rs::output::write_bed;
+use tf_binding_rs::scan::unambiguous_segments;
+use tf_binding_rs::scanner::Scanner;
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GenomeFormat {
+    Parquet,
+    Csv,
+    Jsonl,
+    /// BED6 with the motif as name and the occupancy scaled to 0-1000 as score
+    Bed,
+}
+
+impl GenomeFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            GenomeFormat::Parquet => "parquet",
+            GenomeFormat::Csv => "csv",
+            GenomeFormat::Jsonl => "jsonl",
+            GenomeFormat::Bed => "bed",
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct ScanGenomeArgs {
+    /// Genome FASTA file, or its samtools index (GENOME.fa.fai); the index must exist, as
+    /// chromosomes are read one at a time at their offsets
+    #[arg(value_name = "GENOME")]
+    genome: String,
+
+    /// Path to the motif file (MEME, JASPAR, TRANSFAC, HOMER or HOCOMOCO, by extension)
+    #[arg(value_name = "PWM_FILE")]
+    pwm_file: String,
+
+    /// Directory of the per-chromosome outputs (CHROM.EXT), created if missing
+    #[arg(value_name = "OUTPUT_DIR")]
+    output_dir: String,
+
+    /// Scan only the regions of this BED file (chrom, start, end; further columns are
+    /// ignored and overlapping regions are merged)
+    #[arg(long, value_name = "BED_FILE")]
+    regions: Option<String>,
+
+    /// Skip the regions of this BED file, e.g. the ENCODE blacklist, so no hit overlaps
+    /// them (merged like --regions)
+    #[arg(long, value_name = "BED_FILE")]
+    blacklist: Option<String>,
+
+    /// Only these chromosomes (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    chromosomes: Option<Vec<String>>,
+
+    /// Only these motifs (comma-separated full IDs or names before the first '_')
+    #[arg(long, value_delimiter = ',')]
+    motifs: Option<Vec<String>>,
+
+    /// Chemical potential of the transcription factors
+    #[arg(long, default_value_t = 9.0)]
+    mu: f64,
+
+    /// Minimum occupancy of a reported hit
+    #[arg(long, default_value_t = 0.2)]
+    cutoff: f64,
+
+    /// Bases per scanned chunk; chunks overlap by the width of the widest motif, so every
+    /// window is scanned exactly once
+    #[arg(long, value_name = "BASES", default_value_t = 1_000_000)]
+    chunk_size: usize,
+
+    /// Threads scanning the chunks of a chromosome (default: all cores)
+    #[arg(long)]
+    threads: Option<usize>,
+
+    /// Format of the per-chromosome outputs
+    #[arg(long, value_enum, default_value_t = GenomeFormat::Parquet)]
+    format: GenomeFormat,
+}
+
+/// A scanned piece of a chromosome: windows starting in `start..keep_end` are its own,
+/// the bases up to `end` let the last windows extend into the next chunk
+struct Chunk {
+    start: usize,
+    keep_end: usize,
+    end: usize,
+}
+
+pub fn run(args: ScanGenomeArgs) -> Result<(), CliError> {
+    if args.chunk_size == 0 {
+        return Err(CliError::InvalidArgument(
+            "--chunk-size must be positive".to_string(),
+        ));
+    }
+    let fasta = args
+        .genome
+        .strip_suffix(".fai")
+        .unwrap_or(&args.genome)
+        .to_string();
+    let index_path = format!("{}.fai", fasta);
+    if !Path::new(&index_path).exists() {
+        return Err(CliError::InvalidArgument(format!(
+            "{} has no index; create {} with `samtools faidx {}`",
+            fasta, index_path, fasta
+        )));
+    }
+
+    let ewms = select_motifs(read_ewms(&args.pwm_file)?, &args.motifs);
+    if ewms.is_empty() {
+        return Err(CliError::InvalidArgument("no motifs selected".to_string()));
+    }
+    let widths: Vec<usize> = ewms.values().map(DataFrame::height).collect();
+    let (min_width, max_width) = (
+        widths.iter().copied().min().unwrap_or(1),
+        widths.iter().copied().max().unwrap_or(1),
+    );
+    let mut builder = Scanner::builder()
+        .motifs(ewms)
+        .mu(args.mu)
+        .cutoff(args.cutoff);
+    if let Some(threads) = args.threads {
+        builder = builder.threads(threads);
+    }
+    let scanner = builder.build()?;
+
+    let mut chromosomes: Vec<(String, usize)> = read_fasta_index(&index_path)?
+        .into_iter()
+        .map(|entry| (entry.name, entry.length as usize))
+        .collect();
+    if let Some(selected) = &args.chromosomes {
+        chromosomes.retain(|(name, _)| selected.contains(name));
+    }
+    let mut regions = match &args.regions {
+        Some(path) => read_regions(path)?,
+        None => HashMap::new(),
+    };
+    if args.regions.is_some() {
+        chromosomes.retain(|(name, _)| regions.contains_key(name));
+        let mut unknown: Vec<&String> = regions
+            .keys()
+            .filter(|name| !chromosomes.iter().any(|(chrom, _)| chrom == *name))
+            .collect();
+        unknown.sort();
+        if !unknown.is_empty() && args.chromosomes.is_none() {
+            warn!(
+                chromosomes = unknown.len(),
+                "regions on chromosomes missing from the genome: {}",
+                unknown
+                    .iter()
+                    .map(|name| name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+    if chromosomes.is_empty() {
+        return Err(CliError::InvalidArgument(
+            "no chromosomes to scan".to_string(),
+        ));
+    }
+    let blacklist = match &args.blacklist {
+        Some(path) => read_regions(path)?,
+        None => HashMap::new(),
+    };
+
+    fs::create_dir_all(&args.output_dir)?;
+    let start = Instant::now();
+    let (mut total_bases, mut total_hits) = (0, 0);
+    for (chrom, length) in &chromosomes {
+        let chrom_start = Instant::now();
+        let intervals = match regions.remove(chrom) {
+            Some(intervals) => clip_regions(intervals, chrom, *length),
+            None => vec![(0, *length)],
+        };
+        let requested: usize = intervals.iter().map(|(start, end)| end - start).sum();
+        let intervals = match blacklist.get(chrom) {
+            Some(excluded) => subtract_regions(&intervals, excluded),
+            None => intervals,
+        };
+        let record = subset_file(&fasta, &LabelSelector::labels([chrom.as_str()]))?;
+        let sequence = record
+            .column("sequence")?
+            .str()?
+            .get(0)
+            .ok_or_else(|| CliError::InvalidArgument(format!("{} has no sequence", chrom)))?
+            .to_string();
+
+        let chunks = chunks(&sequence, &intervals, min_width, max_width, args.chunk_size);
+        let mut hits = scan_chunks(&scanner, chrom, &sequence, &chunks)?;
+        let path = Path::new(&args.output_dir)
+            .join(format!("{}.{}", chrom, args.format.extension()))
+            .to_string_lossy()
+            .to_string();
+        match args.format {
+            GenomeFormat::Bed => {
+                write_bed(&hits, &path)?;
+            }
+            _ => write_table(&mut hits, &path)?,
+        }
+
+        let bases: usize = intervals.iter().map(|(start, end)| end - start).sum();
+        total_bases += bases;
+        total_hits += hits.height();
+        info!(
+            chrom = %chrom,
+            bases,
+            blacklisted = requested - bases,
+            chunks = chunks.len(),
+            hits = hits.height(),
+            file = %path,
+            elapsed_ms = chrom_start.elapsed().as_millis() as u64,
+            "chromosome scanned"
+        );
+    }
+    info!(
+        chromosomes = chromosomes.len(),
+        bases = total_bases,
+        hits = total_hits,
+        elapsed_ms = start.elapsed().as_millis() as u64,
+        "genome scanned"
+    );
+    Ok(())
+}
+
+/// Regions of a BED file by chromosome, sorted and with overlapping ones merged
+fn read_regions(path: &str) -> Result<HashMap<String, Vec<(usize, usize)>>, CliError> {
+    let mut regions: HashMap<String, Vec<(usize, usize)>> = HashMap::new();
+    for (i, line) in fs::read_to_string(path)?.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with("track")
+            || line.starts_with("browser")
+        {
+            continue;
+        }
+        let invalid =
+            || CliError::InvalidArgument(format!("{} line {}: invalid region", path, i + 1));
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 3 {
+            return Err(invalid());
+        }
+        let start: usize = fields[1].parse().map_err(|_| invalid())?;
+        let end: usize = fields[2].parse().map_err(|_| invalid())?;
+        if end <= start {
+            return Err(invalid());
+        }
+        regions
+            .entry(fields[0].to_string())
+            .or_default()
+            .push((start, end));
+    }
+    for intervals in regions.values_mut() {
+        intervals.sort_unstable();
+        let mut merged: Vec<(usize, usize)> = Vec::with_capacity(intervals.len());
+        for &(start, end) in intervals.iter() {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        *intervals = merged;
+    }
+    Ok(regions)
+}
+
+/// Regions cut to the chromosome length, warning about those past its end
+fn clip_regions(intervals: Vec<(usize, usize)>, chrom: &str, length: usize) -> Vec<(usize, usize)> {
+    let clipped: Vec<(usize, usize)> = intervals
+        .iter()
+        .filter(|(start, _)| *start < length)
+        .map(|&(start, end)| (start, end.min(length)))
+        .collect();
+    if clipped.len() < intervals.len() || intervals.last().is_some_and(|(_, end)| *end > length) {
+        warn!(
+            chrom,
+            length, "regions past the end of the chromosome clipped"
+        );
+    }
+    clipped
+}
+
+/// Parts of sorted, non-overlapping `intervals` outside sorted, non-overlapping `excluded`
+/// regions
+fn subtract_regions(
+    intervals: &[(usize, usize)],
+    excluded: &[(usize, usize)],
+) -> Vec<(usize, usize)> {
+    let mut kept = Vec::with_capacity(intervals.len());
+    let mut excluded = excluded.iter().peekable();
+    for &(start, end) in intervals {
+        let mut start = start;
+        // skip exclusions ending before the interval; later intervals start further right
+        while excluded
+            .next_if(|(_, excluded_end)| *excluded_end <= start)
+            .is_some()
+        {}
+        for &(excluded_start, excluded_end) in excluded.clone() {
+            if excluded_start >= end {
+                break;
+            }
+            if excluded_start > start {
+                kept.push((start, excluded_start));
+            }
+            start = start.max(excluded_end);
+            if start >= end {
+                break;
+            }
+        }
+        if start < end {
+            kept.push((start, end));
+        }
+    }
+    kept
+}
+
+/// Chunks of the unambiguous stretches of `intervals` that the narrowest motif fits in
+fn chunks(
+    sequence: &str,
+    intervals: &[(usize, usize)],
+    min_width: usize,
+    max_width: usize,
+    chunk_size: usize,
+) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    for &(start, end) in intervals {
+        for (segment_start, segment_end) in unambiguous_segments(&sequence[start..end], min_width) {
+            let (segment_start, segment_end) = (start + segment_start, start + segment_end);
+            let mut chunk_start = segment_start;
+            while chunk_start < segment_end {
+                let keep_end = (chunk_start + chunk_size).min(segment_end);
+                chunks.push(Chunk {
+                    start: chunk_start,
+                    keep_end,
+                    end: (keep_end + max_width - 1).min(segment_end),
+                });
+                chunk_start = keep_end;
+            }
+        }
+    }
+    chunks
+}
+
+/// Scans the chunks of a chromosome in parallel and returns its hits in genomic
+/// coordinates, sorted by start, end, motif and strand
+fn scan_chunks(
+    scanner: &Scanner,
+    chrom: &str,
+    sequence: &str,
+    chunks: &[Chunk],
+) -> Result<DataFrame, CliError> {
+    let labels: Vec<String> = (0..chunks.len()).map(|i| i.to_string()).collect();
+    let pieces: Vec<&str> = chunks
+        .iter()
+        .map(|chunk| &sequence[chunk.start..chunk.end])
+        .collect();
+    let result = scanner.scan_batch(&df!("label" => labels, "sequence" => pieces)?)?;
+    let hits = &result.hits;
+
+    let mut starts: Vec<i64> = Vec::with_capacity(hits.height());
+    let mut ends: Vec<i64> = Vec::with_capacity(hits.height());
+    let mut strands: Vec<&str> = Vec::with_capacity(hits.height());
+    let mut motifs: Vec<&str> = Vec::with_capacity(hits.height());
+    let mut occupancies: Vec<f64> = Vec::with_capacity(hits.height());
+    for ((((label, motif), position), (strand, length)), occupancy) in hits
+        .column("label")?
+        .str()?
+        .into_no_null_iter()
+        .zip(hits.column("motif")?.str()?.into_no_null_iter())
+        .zip(hits.column("position")?.i32()?.into_no_null_iter())
+        .zip(
+            hits.column("strand")?
+                .str()?
+                .into_no_null_iter()
+                .zip(hits.column("length")?.i32()?.into_no_null_iter()),
+        )
+        .zip(hits.column("occupancy")?.f64()?.into_no_null_iter())
+    {
+        let chunk = &chunks[label.parse::<usize>().expect("chunk labels are indices")];
+        let start = chunk.start + position as usize;
+        // windows starting in the overlap belong to the next chunk
+        if start >= chunk.keep_end {
+            continue;
+        }
+        starts.push(start as i64);
+        ends.push((start + length as usize) as i64);
+        strands.push(if strand == "R" { "-" } else { "+" });
+        motifs.push(motif);
+        occupancies.push(occupancy);
+    }
+
+    let n = starts.len();
+    df!(
+        "chrom" => vec![chrom; n],
+        "genomic_start" => starts,
+        "genomic_end" => ends,
+        "genomic_strand" => strands,
+        "motif" => motifs,
+        "occupancy" => occupancies,
+    )?
+    .sort(
+        ["genomic_start", "genomic_end", "motif", "genomic_strand"],
+        SortMultipleOptions::default(),
+    )
+    .map_err(CliError::from)
+}
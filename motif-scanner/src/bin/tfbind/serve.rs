@@ -0,0 +1,660 @@
+use crate::{read_ewms, CliError};
+use clap::Args;
+use polars::export::arrow::datatypes::ArrowSchema;
+use polars::export::arrow::io::ipc::read::{read_stream_metadata, StreamReader, StreamState};
+use polars::export::arrow::io::ipc::write::{StreamWriter, WriteOptions};
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use tf_binding_rs::error::MotifError;
+use tf_binding_rs::occupancy::occupancy_landscape;
+use tf_binding_rs::scan::{check_sequence, scan_sequences, ScanWarning};
+use tf_binding_rs::types::EWMCollection;
+use tiny_http::{Header, Method, Request, Response, Server};
+use tracing::{debug, info, warn};
+
+#[cfg(feature = "flight")]
+mod flight;
+
+#[derive(Args, Debug)]
+pub struct ServeArgs {
+    /// Motif collection to serve, as NAME=PATH or PATH (named after the file stem); repeat for
+    /// several collections. The format is guessed from the extension (MEME if unknown)
+    #[arg(long = "motif-set", value_name = "[NAME=]PATH", required = true)]
+    motif_sets: Vec<String>,
+
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    addr: String,
+
+    /// Number of worker threads handling requests
+    #[arg(long, default_value_t = 4)]
+    workers: usize,
+
+    /// Default chemical potential when a request does not set `mu`
+    #[arg(long, default_value_t = 9.0)]
+    mu: f64,
+
+    /// Default occupancy cutoff when a request does not set `cutoff`
+    #[arg(long, default_value_t = 0.2)]
+    cutoff: f64,
+
+    /// Maximum total sequence length (bp) of one request
+    #[arg(long, default_value_t = 10_000_000)]
+    max_bases: usize,
+
+    /// Also serve scans as an Arrow Flight `DoExchange` service on this address
+    #[cfg(feature = "flight")]
+    #[arg(long, value_name = "ADDR")]
+    flight_addr: Option<String>,
+}
+
+/// A sequence in a scan request: a bare string or an object with a label
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SequenceInput {
+    Bare(String),
+    Labeled { label: String, sequence: String },
+}
+
+/// Body of `POST /scan`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScanRequest {
+    motif_set: String,
+    sequences: Vec<SequenceInput>,
+    motifs: Option<Vec<String>>,
+    mu: Option<f64>,
+    cutoff: Option<f64>,
+    #[serde(default)]
+    landscape: bool,
+}
+
+#[derive(Serialize)]
+struct Hit {
+    label: String,
+    motif: String,
+    position: i32,
+    strand: String,
+    length: i32,
+    occupancy: f64,
+}
+
+#[derive(Serialize)]
+struct Warning {
+    index: usize,
+    label: Option<String>,
+    motif: Option<String>,
+    reason: &'static str,
+    message: String,
+}
+
+impl From<&ScanWarning> for Warning {
+    fn from(w: &ScanWarning) -> Self {
+        Warning {
+            index: w.index,
+            label: w.label.clone(),
+            motif: w.motif.clone(),
+            reason: w.reason.kind(),
+            message: w.reason.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Landscape {
+    label: String,
+    motif: String,
+    forward: Vec<f64>,
+    reverse: Vec<f64>,
+}
+
+#[derive(Serialize)]
+struct ScanResponse {
+    motif_set: String,
+    mu: f64,
+    cutoff: f64,
+    hits: Vec<Hit>,
+    warnings: Vec<Warning>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    landscapes: Option<Vec<Landscape>>,
+}
+
+#[derive(Serialize)]
+struct MotifSetInfo<'a> {
+    name: &'a str,
+    path: &'a str,
+    motifs: Vec<&'a str>,
+}
+
+/// An error answered with a JSON body `{"error": message}`
+struct HttpError {
+    status: u16,
+    message: String,
+}
+
+impl HttpError {
+    fn new(status: u16, message: impl ToString) -> Self {
+        HttpError {
+            status,
+            message: message.to_string(),
+        }
+    }
+}
+
+struct MotifSet {
+    path: String,
+    ewms: EWMCollection,
+}
+
+/// Motif collections loaded once at startup and shared by all workers
+struct State {
+    sets: BTreeMap<String, MotifSet>,
+    mu: f64,
+    cutoff: f64,
+    max_bases: usize,
+}
+
+fn load_motif_set(spec: &str) -> Result<(String, MotifSet), CliError> {
+    let (name, path) = match spec.split_once('=') {
+        Some((name, path)) => (name.to_string(), path.to_string()),
+        None => (
+            Path::new(spec)
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            spec.to_string(),
+        ),
+    };
+    let ewms = read_ewms(&path)?;
+    info!(name = %name, path = %path, motifs = ewms.len(), "loaded motif set");
+    Ok((name, MotifSet { path, ewms }))
+}
+
+fn matches_motif(id: &str, name: &str) -> bool {
+    id == name || id.split('_').next() == Some(name)
+}
+
+/// Motifs of a served collection, optionally restricted to full IDs or names before the
+/// first '_'
+fn select_motifs(
+    state: &State,
+    motif_set: &str,
+    names: Option<&[String]>,
+) -> Result<EWMCollection, HttpError> {
+    let set = state
+        .sets
+        .get(motif_set)
+        .ok_or_else(|| HttpError::new(404, format!("unknown motif set '{}'", motif_set)))?;
+
+    let Some(names) = names else {
+        return Ok(set.ewms.clone());
+    };
+    let selected: EWMCollection = set
+        .ewms
+        .iter()
+        .filter(|(id, _)| names.iter().any(|name| matches_motif(id, name)))
+        .map(|(id, ewm)| (id.clone(), ewm.clone()))
+        .collect();
+    if let Some(name) = names
+        .iter()
+        .find(|name| !selected.keys().any(|id| matches_motif(id, name)))
+    {
+        return Err(HttpError::new(404, format!("unknown motif '{}'", name)));
+    }
+    Ok(selected)
+}
+
+fn scan(state: &State, request: ScanRequest) -> Result<ScanResponse, HttpError> {
+    let ewms = select_motifs(state, &request.motif_set, request.motifs.as_deref())?;
+
+    let (labels, sequences): (Vec<String>, Vec<String>) = request
+        .sequences
+        .into_iter()
+        .enumerate()
+        .map(|(i, input)| match input {
+            SequenceInput::Bare(sequence) => (i.to_string(), sequence),
+            SequenceInput::Labeled { label, sequence } => (label, sequence),
+        })
+        .unzip();
+    let bases: usize = sequences.iter().map(String::len).sum();
+    if bases > state.max_bases {
+        return Err(HttpError::new(
+            413,
+            format!(
+                "request has {} bp, more than the limit of {}",
+                bases, state.max_bases
+            ),
+        ));
+    }
+
+    let mu = request.mu.unwrap_or(state.mu);
+    let cutoff = request.cutoff.unwrap_or(state.cutoff);
+    let df =
+        df!("label" => &labels, "sequence" => &sequences).map_err(|e| HttpError::new(500, e))?;
+    let result =
+        scan_sequences(&df, &ewms, mu, cutoff, |_| {}).map_err(|e| HttpError::new(500, e))?;
+
+    let hits = &result.hits;
+    let column = |name: &str| hits.column(name).map_err(|e| HttpError::new(500, e));
+    let hits = column("label")?
+        .str()
+        .map_err(|e| HttpError::new(500, e))?
+        .into_iter()
+        .zip(column("motif")?.str().map_err(|e| HttpError::new(500, e))?)
+        .zip(
+            column("position")?
+                .i32()
+                .map_err(|e| HttpError::new(500, e))?,
+        )
+        .zip(
+            column("strand")?
+                .str()
+                .map_err(|e| HttpError::new(500, e))?,
+        )
+        .zip(
+            column("length")?
+                .i32()
+                .map_err(|e| HttpError::new(500, e))?,
+        )
+        .zip(
+            column("occupancy")?
+                .f64()
+                .map_err(|e| HttpError::new(500, e))?,
+        )
+        .map(
+            |(((((label, motif), position), strand), length), occupancy)| Hit {
+                label: label.unwrap_or_default().to_string(),
+                motif: motif.unwrap_or_default().to_string(),
+                position: position.unwrap_or_default(),
+                strand: strand.unwrap_or_default().to_string(),
+                length: length.unwrap_or_default(),
+                occupancy: occupancy.unwrap_or_default(),
+            },
+        )
+        .collect();
+
+    let landscapes = if request.landscape {
+        let mut motif_ids: Vec<&String> = ewms.keys().collect();
+        motif_ids.sort();
+        let mut landscapes = Vec::new();
+        for (label, sequence) in labels.iter().zip(&sequences) {
+            for motif_id in &motif_ids {
+                let ewm = &ewms[*motif_id];
+                if check_sequence(sequence, ewm.height()).is_some() {
+                    continue;
+                }
+                let (forward, reverse) =
+                    occupancy_landscape(sequence, ewm, mu).map_err(|e| HttpError::new(500, e))?;
+                landscapes.push(Landscape {
+                    label: label.clone(),
+                    motif: motif_id.to_string(),
+                    forward,
+                    reverse,
+                });
+            }
+        }
+        Some(landscapes)
+    } else {
+        None
+    };
+
+    Ok(ScanResponse {
+        motif_set: request.motif_set,
+        mu,
+        cutoff,
+        hits,
+        warnings: result.warnings.iter().map(Warning::from).collect(),
+        landscapes,
+    })
+}
+
+/// Parameters of `POST /scan/arrow`, given in the query string
+struct ArrowQuery {
+    motif_set: String,
+    motifs: Option<Vec<String>>,
+    mu: Option<f64>,
+    cutoff: Option<f64>,
+}
+
+impl ArrowQuery {
+    fn parse(query: &str) -> Result<Self, HttpError> {
+        let mut parsed = ArrowQuery {
+            motif_set: String::new(),
+            motifs: None,
+            mu: None,
+            cutoff: None,
+        };
+        let number = |key: &str, value: &str| {
+            value
+                .parse::<f64>()
+                .map_err(|_| HttpError::new(400, format!("invalid {} '{}'", key, value)))
+        };
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            match key {
+                "motif_set" => parsed.motif_set = value.to_string(),
+                "motifs" => parsed.motifs = Some(value.split(',').map(str::to_string).collect()),
+                "mu" => parsed.mu = Some(number(key, value)?),
+                "cutoff" => parsed.cutoff = Some(number(key, value)?),
+                _ => {
+                    return Err(HttpError::new(
+                        400,
+                        format!("unknown query parameter '{}'", key),
+                    ))
+                }
+            }
+        }
+        if parsed.motif_set.is_empty() {
+            return Err(HttpError::new(400, "missing query parameter 'motif_set'"));
+        }
+        Ok(parsed)
+    }
+}
+
+/// Checks the columns of a record batch of a `POST /scan/arrow` request and returns its
+/// total sequence length
+fn batch_bases(batch: &DataFrame) -> Result<usize, HttpError> {
+    let column = batch
+        .column("sequence")
+        .map_err(|_| HttpError::new(400, "missing 'sequence' column"))?;
+    let bases = column
+        .str()
+        .map_err(|_| HttpError::new(400, "'sequence' must be a string column"))?
+        .into_iter()
+        .map(|seq| seq.map_or(0, str::len))
+        .sum();
+    if let Ok(labels) = batch.column("label") {
+        if labels.str().is_err() {
+            return Err(HttpError::new(400, "'label' must be a string column"));
+        }
+    }
+    Ok(bases)
+}
+
+/// A `POST /scan/arrow` request, scanned record batch by record batch as its body is decoded
+///
+/// A request is refused as soon as its batches exceed `--max-bases`, without reading the
+/// rest. Only the hits of the batches are kept until the response starts: tiny_http only
+/// responds once it owns the request, so the body has been received in full by then.
+struct ArrowScan {
+    schema: ArrowSchema,
+    hits: Vec<DataFrame>,
+}
+
+impl ArrowScan {
+    fn new(state: &State, query: &str, mut body: impl Read) -> Result<Self, HttpError> {
+        let query = ArrowQuery::parse(query)?;
+        let ewms = select_motifs(state, &query.motif_set, query.motifs.as_deref())?;
+        let mu = query.mu.unwrap_or(state.mu);
+        let cutoff = query.cutoff.unwrap_or(state.cutoff);
+
+        let empty = df!("label" => Vec::<&str>::new(), "sequence" => Vec::<&str>::new())
+            .map_err(|e| HttpError::new(500, e))?;
+        let hit_schema = scan_sequences(&empty, &ewms, mu, cutoff, |_| {})
+            .map_err(|e| HttpError::new(500, e))?
+            .hits
+            .schema()
+            .to_arrow(CompatLevel::newest());
+
+        let invalid =
+            |e: PolarsError| HttpError::new(400, format!("invalid Arrow IPC stream: {}", e));
+        let metadata = read_stream_metadata(&mut body).map_err(invalid)?;
+        let schema = metadata.schema.clone();
+        let mut hits = Vec::new();
+        let (mut bases, mut offset) = (0, 0);
+        for message in StreamReader::new(body, metadata, None) {
+            let StreamState::Some(batch) = message.map_err(invalid)? else {
+                continue;
+            };
+            let mut batch = DataFrame::try_from((batch, &schema)).map_err(invalid)?;
+            bases += batch_bases(&batch)?;
+            if bases > state.max_bases {
+                return Err(HttpError::new(
+                    413,
+                    format!("request has more than the limit of {} bp", state.max_bases),
+                ));
+            }
+
+            if batch.column("label").is_err() {
+                let labels: Vec<String> = (offset..offset + batch.height())
+                    .map(|i| i.to_string())
+                    .collect();
+                batch
+                    .with_column(Column::new("label".into(), labels))
+                    .map_err(|e| HttpError::new(500, e))?;
+            }
+            offset += batch.height();
+            let result = scan_sequences(
+                &batch,
+                &ewms,
+                mu,
+                cutoff,
+                |w| debug!(warning = %w, "skipped record"),
+            )
+            .map_err(|e| HttpError::new(500, e))?;
+            hits.push(result.hits);
+        }
+
+        Ok(ArrowScan {
+            schema: hit_schema,
+            hits,
+        })
+    }
+
+    /// Writes the hits of every record batch of the request as one record batch of an Arrow
+    /// IPC stream
+    fn write(self, writer: impl Write) -> Result<(), MotifError> {
+        let data_error = |e: PolarsError| MotifError::DataError(e.to_string());
+        let mut stream = StreamWriter::new(writer, WriteOptions { compression: None });
+        stream.start(&self.schema, None).map_err(data_error)?;
+        for hits in &self.hits {
+            for hits in hits.iter_chunks(CompatLevel::newest(), true) {
+                stream.write(&hits, None).map_err(data_error)?;
+            }
+        }
+        stream.finish().map_err(data_error)
+    }
+}
+
+/// Response of a route
+enum Reply {
+    Json(String),
+    Arrow(ArrowScan),
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, HttpError> {
+    serde_json::to_string(value).map_err(|e| HttpError::new(500, e))
+}
+
+fn route(state: &State, request: &mut Request) -> Result<Reply, HttpError> {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let json = match (request.method(), path) {
+        (Method::Get, "/health") => to_json(&serde_json::json!({ "status": "ok" })),
+        (Method::Get, "/motif-sets") => {
+            let sets: Vec<MotifSetInfo> = state
+                .sets
+                .iter()
+                .map(|(name, set)| {
+                    let mut motifs: Vec<&str> = set.ewms.keys().map(String::as_str).collect();
+                    motifs.sort();
+                    MotifSetInfo {
+                        name,
+                        path: &set.path,
+                        motifs,
+                    }
+                })
+                .collect();
+            to_json(&sets)
+        }
+        (Method::Post, "/scan") => {
+            let body: ScanRequest = serde_json::from_reader(request.as_reader())
+                .map_err(|e| HttpError::new(400, format!("invalid request: {}", e)))?;
+            to_json(&scan(state, body)?)
+        }
+        (Method::Post, "/scan/arrow") => {
+            return Ok(Reply::Arrow(ArrowScan::new(
+                state,
+                query,
+                request.as_reader(),
+            )?))
+        }
+        (_, "/health" | "/motif-sets" | "/scan" | "/scan/arrow") => {
+            Err(HttpError::new(405, "method not allowed"))
+        }
+        (_, path) => Err(HttpError::new(404, format!("no route for {}", path))),
+    };
+    json.map(Reply::Json)
+}
+
+fn json_header() -> Header {
+    Header::from_bytes("Content-Type", "application/json").unwrap()
+}
+
+/// Write end of a streamed response body: every write is handed to the responding thread
+struct ChannelWriter(mpsc::SyncSender<Vec<u8>>);
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .send(buf.to_vec())
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "response closed"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Read end of a streamed response body, at its end once the `ChannelWriter` is dropped
+struct ChannelReader {
+    receiver: mpsc::Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    offset: usize,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.offset == self.chunk.len() {
+            match self.receiver.recv() {
+                Ok(chunk) => (self.chunk, self.offset) = (chunk, 0),
+                Err(_) => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.offset);
+        buf[..n].copy_from_slice(&self.chunk[self.offset..self.offset + n]);
+        self.offset += n;
+        Ok(n)
+    }
+}
+
+/// Streams the hits of an Arrow scan: a scoped thread scans and writes into a channel while
+/// the response sends what has been written so far, with chunked transfer encoding
+fn respond_arrow(request: Request, scan: ArrowScan) -> io::Result<()> {
+    // bounded, so scanning waits for a slow client instead of buffering its hits
+    let (sender, receiver) = mpsc::sync_channel(64);
+    let writer = ChannelWriter(sender);
+    let reader = ChannelReader {
+        receiver,
+        chunk: Vec::new(),
+        offset: 0,
+    };
+    let header = Header::from_bytes("Content-Type", "application/vnd.apache.arrow.stream").unwrap();
+    let response = Response::new(200.into(), vec![header], reader, None, None);
+
+    thread::scope(|s| {
+        s.spawn(move || {
+            // on error the stream is cut short, which clients detect as a truncated stream
+            if let Err(e) = scan.write(writer) {
+                warn!(error = %e, "Arrow scan failed");
+            }
+        });
+        request.respond(response)
+    })
+}
+
+fn handle(state: &State, mut request: Request) {
+    let method = request.method().clone();
+    let url = request.url().to_string();
+    let (status, result) = match route(state, &mut request) {
+        Ok(Reply::Json(body)) => (
+            200,
+            request.respond(Response::from_string(body).with_header(json_header())),
+        ),
+        Ok(Reply::Arrow(scan)) => (200, respond_arrow(request, scan)),
+        Err(e) => {
+            if e.status >= 500 {
+                warn!(url = %url, error = %e.message, "request failed");
+            }
+            let body = serde_json::json!({ "error": e.message }).to_string();
+            let response = Response::from_string(body)
+                .with_status_code(e.status)
+                .with_header(json_header());
+            (e.status, request.respond(response))
+        }
+    };
+    info!(method = %method, url = %url, status, "request");
+    if let Err(e) = result {
+        warn!(error = %e, "failed to send response");
+    }
+}
+
+pub fn run(args: ServeArgs) -> Result<(), CliError> {
+    let mut sets = BTreeMap::new();
+    for spec in &args.motif_sets {
+        let (name, set) = load_motif_set(spec)?;
+        if sets.insert(name.clone(), set).is_some() {
+            return Err(CliError::InvalidArgument(format!(
+                "motif set '{}' given twice",
+                name
+            )));
+        }
+    }
+    if args.workers == 0 {
+        return Err(CliError::InvalidArgument(
+            "--workers must be positive".to_string(),
+        ));
+    }
+
+    let state = Arc::new(State {
+        sets,
+        mu: args.mu,
+        cutoff: args.cutoff,
+        max_bases: args.max_bases,
+    });
+    #[cfg(feature = "flight")]
+    let flight = args
+        .flight_addr
+        .as_deref()
+        .map(|addr| flight::spawn(addr, Arc::clone(&state)))
+        .transpose()?;
+    let server =
+        Arc::new(Server::http(&args.addr).map_err(|e| CliError::InvalidArgument(e.to_string()))?);
+    info!(addr = %args.addr, workers = args.workers, "listening");
+
+    let workers: Vec<_> = (0..args.workers)
+        .map(|_| {
+            let (server, state) = (Arc::clone(&server), Arc::clone(&state));
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    handle(&state, request);
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        let _ = worker.join();
+    }
+    #[cfg(feature = "flight")]
+    if let Some(flight) = flight {
+        let _ = flight.join();
+    }
+    Ok(())
+}
@@ -0,0 +1,259 @@
+use super::{select_motifs, ArrowQuery, HttpError, State};
+use crate::CliError;
+use arrow_array::cast::AsArray;
+use arrow_array::{ArrayRef, RecordBatch, StringArray};
+use arrow_flight::decode::FlightRecordBatchStream;
+use arrow_flight::encode::FlightDataEncoderBuilder;
+use arrow_flight::error::FlightError;
+use arrow_flight::flight_descriptor::DescriptorType;
+use arrow_flight::flight_service_server::{FlightService, FlightServiceServer};
+use arrow_flight::{
+    Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightInfo,
+    HandshakeRequest, HandshakeResponse, PollInfo, PutResult, SchemaResult, Ticket,
+};
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+use futures::channel::mpsc;
+use futures::stream::{self, BoxStream};
+use futures::{SinkExt, StreamExt, TryStreamExt};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::thread;
+use tf_binding_rs::table::ScanTable;
+use tonic::transport::server::TcpIncoming;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status, Streaming};
+use tracing::{info, warn};
+
+impl From<HttpError> for Status {
+    fn from(e: HttpError) -> Self {
+        match e.status {
+            400 => Status::invalid_argument(e.message),
+            404 => Status::not_found(e.message),
+            413 => Status::resource_exhausted(e.message),
+            _ => Status::internal(e.message),
+        }
+    }
+}
+
+/// Total sequence length of a record batch of an exchange, checking its columns
+fn sequence_bases(batch: &RecordBatch) -> Result<usize, HttpError> {
+    let column = batch
+        .column_by_name("sequence")
+        .ok_or_else(|| HttpError::new(400, "missing 'sequence' column"))?;
+    let sequences: Box<dyn Iterator<Item = Option<&str>>> =
+        if let Some(sequences) = column.as_string_opt::<i32>() {
+            Box::new(sequences.iter())
+        } else if let Some(sequences) = column.as_string_opt::<i64>() {
+            Box::new(sequences.iter())
+        } else if let Some(sequences) = column.as_string_view_opt() {
+            Box::new(sequences.iter())
+        } else {
+            return Err(HttpError::new(400, "'sequence' must be a string column"));
+        };
+    Ok(sequences.map(|seq| seq.map_or(0, str::len)).sum())
+}
+
+/// Labels the rows of a batch without a 'label' column by their index in the exchange
+fn with_labels(batch: RecordBatch, offset: usize) -> Result<RecordBatch, ArrowError> {
+    if batch.column_by_name("label").is_some() {
+        return Ok(batch);
+    }
+    let labels: StringArray = (offset..offset + batch.num_rows())
+        .map(|i| Some(i.to_string()))
+        .collect();
+    let mut fields = batch.schema().fields().to_vec();
+    fields.push(Arc::new(Field::new("label", DataType::Utf8, false)));
+    let mut columns = batch.columns().to_vec();
+    columns.push(Arc::new(labels) as ArrayRef);
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+}
+
+/// Scans every record batch of an exchange as soon as it is decoded and sends its hits
+async fn scan_exchange(
+    mut batches: FlightRecordBatchStream,
+    table: Arc<ScanTable>,
+    max_bases: usize,
+    hits: &mut mpsc::Sender<Result<RecordBatch, FlightError>>,
+) -> Result<(), FlightError> {
+    let (mut bases, mut offset) = (0, 0);
+    while let Some(batch) = batches.try_next().await? {
+        bases += sequence_bases(&batch).map_err(Status::from)?;
+        if bases > max_bases {
+            return Err(Status::resource_exhausted(format!(
+                "exchange has more than the limit of {} bp",
+                max_bases
+            ))
+            .into());
+        }
+        let batch = with_labels(batch, offset)?;
+        offset += batch.num_rows();
+
+        let table = Arc::clone(&table);
+        let batch_hits = tokio::task::spawn_blocking(move || table.scan_batch(&batch))
+            .await
+            .map_err(|e| FlightError::ExternalError(Box::new(e)))?
+            .map_err(|e| FlightError::ExternalError(Box::new(e)))?;
+        if hits.send(Ok(batch_hits)).await.is_err() {
+            // the client is gone
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+/// Arrow Flight service scanning the record batches of a `DoExchange` with the served motif
+/// sets; the other Flight methods are not implemented
+struct ScanService {
+    state: Arc<State>,
+}
+
+type FlightStream<T> = BoxStream<'static, Result<T, Status>>;
+
+#[tonic::async_trait]
+impl FlightService for ScanService {
+    type HandshakeStream = FlightStream<HandshakeResponse>;
+    type ListFlightsStream = FlightStream<FlightInfo>;
+    type DoGetStream = FlightStream<FlightData>;
+    type DoPutStream = FlightStream<PutResult>;
+    type DoExchangeStream = FlightStream<FlightData>;
+    type DoActionStream = FlightStream<arrow_flight::Result>;
+    type ListActionsStream = FlightStream<ActionType>;
+
+    async fn do_exchange(
+        &self,
+        request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoExchangeStream>, Status> {
+        let mut input = request.into_inner();
+        let first = input
+            .message()
+            .await?
+            .ok_or_else(|| Status::invalid_argument("empty exchange"))?;
+        let command = first
+            .flight_descriptor
+            .as_ref()
+            .filter(|descriptor| descriptor.r#type() == DescriptorType::Cmd)
+            .ok_or_else(|| {
+                Status::invalid_argument("the first message must carry a command descriptor")
+            })?;
+        let query = std::str::from_utf8(&command.cmd)
+            .map_err(|_| Status::invalid_argument("the command must be UTF-8"))?;
+        let query = ArrowQuery::parse(query)?;
+        let ewms = select_motifs(&self.state, &query.motif_set, query.motifs.as_deref())?;
+        let table = ScanTable::new(
+            ewms,
+            query.mu.unwrap_or(self.state.mu),
+            query.cutoff.unwrap_or(self.state.cutoff),
+        )
+        .map_err(|e| Status::internal(e.to_string()))?;
+        let schema = table.schema();
+
+        let batches = FlightRecordBatchStream::new_from_flight_data(
+            stream::once(async { Ok(first) }).chain(input.map_err(FlightError::from)),
+        );
+        // bounded, so scanning waits for a slow client instead of buffering its hits
+        let (mut sender, receiver) = mpsc::channel(2);
+        let max_bases = self.state.max_bases;
+        tokio::spawn(async move {
+            if let Err(e) = scan_exchange(batches, Arc::new(table), max_bases, &mut sender).await {
+                warn!(error = %e, "Flight exchange failed");
+                let _ = sender.send(Err(e)).await;
+            }
+        });
+
+        let hits = FlightDataEncoderBuilder::new()
+            .with_schema(schema)
+            .build(receiver)
+            .map_err(Status::from);
+        Ok(Response::new(hits.boxed()))
+    }
+
+    async fn handshake(
+        &self,
+        _request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<Response<Self::HandshakeStream>, Status> {
+        Err(Status::unimplemented("only DoExchange is served"))
+    }
+
+    async fn list_flights(
+        &self,
+        _request: Request<Criteria>,
+    ) -> Result<Response<Self::ListFlightsStream>, Status> {
+        Err(Status::unimplemented("only DoExchange is served"))
+    }
+
+    async fn get_flight_info(
+        &self,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        Err(Status::unimplemented("only DoExchange is served"))
+    }
+
+    async fn poll_flight_info(
+        &self,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<PollInfo>, Status> {
+        Err(Status::unimplemented("only DoExchange is served"))
+    }
+
+    async fn get_schema(
+        &self,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<SchemaResult>, Status> {
+        Err(Status::unimplemented("only DoExchange is served"))
+    }
+
+    async fn do_get(
+        &self,
+        _request: Request<Ticket>,
+    ) -> Result<Response<Self::DoGetStream>, Status> {
+        Err(Status::unimplemented("only DoExchange is served"))
+    }
+
+    async fn do_put(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoPutStream>, Status> {
+        Err(Status::unimplemented("only DoExchange is served"))
+    }
+
+    async fn do_action(
+        &self,
+        _request: Request<Action>,
+    ) -> Result<Response<Self::DoActionStream>, Status> {
+        Err(Status::unimplemented("only DoExchange is served"))
+    }
+
+    async fn list_actions(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<Self::ListActionsStream>, Status> {
+        Err(Status::unimplemented("only DoExchange is served"))
+    }
+}
+
+/// Starts the Flight service on `addr` in a thread of its own; the address is bound before
+/// returning, so a taken address fails the startup
+pub(super) fn spawn(addr: &str, state: Arc<State>) -> Result<thread::JoinHandle<()>, CliError> {
+    let socket: SocketAddr = addr
+        .parse()
+        .map_err(|_| CliError::InvalidArgument(format!("invalid Flight address '{}'", addr)))?;
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    let incoming = {
+        let _guard = runtime.enter();
+        TcpIncoming::new(socket, true, None)
+            .map_err(|e| CliError::InvalidArgument(format!("cannot listen on {}: {}", addr, e)))?
+    };
+    info!(addr = %addr, "serving Arrow Flight");
+
+    let service = FlightServiceServer::new(ScanService { state });
+    Ok(thread::spawn(move || {
+        let server = Server::builder()
+            .add_service(service)
+            .serve_with_incoming(incoming);
+        if let Err(e) = runtime.block_on(server) {
+            warn!(error = %e, "Flight service failed");
+        }
+    }))
+}
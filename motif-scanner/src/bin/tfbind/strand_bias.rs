@@ -0,0 +1,26 @@
+use crate::CliError;
+use clap::Args;
+use motif_scanner::io::{read_table, write_table};
+use tf_binding_rs::stats::strand_bias;
+
+#[derive(Args, Debug)]
+pub struct StrandBiasArgs {
+    /// Scan output with 'motif' and 'strand' columns (.csv or .parquet)
+    #[arg(value_name = "HITS_FILE")]
+    input: String,
+
+    /// Write the summary to this file (.csv, .parquet or .jsonl) instead of printing it
+    #[arg(long, value_name = "OUTPUT")]
+    output: Option<String>,
+}
+
+pub fn run(args: StrandBiasArgs) -> Result<(), CliError> {
+    let hits = read_table(&args.input)?.collect()?;
+    let mut summary = strand_bias(&hits)?;
+
+    match args.output {
+        Some(path) => write_table(&mut summary, &path)?,
+        None => println!("{}", summary),
+    }
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use crate::CliError;
+use clap::Args;
+use polars::prelude::DataFrame;
+use std::collections::HashSet;
+use std::io::{BufWriter, Write};
+use tf_binding_rs::fasta::{subset_file, write_fasta, LabelSelector};
+use tracing::{info, warn};
+
+/// Records to select by label, shared by the subcommands working on a few sequences
+#[derive(Args, Debug)]
+#[command(group(
+    clap::ArgGroup::new("selector")
+        .required(true)
+        .args(["labels", "labels_file", "pattern"])
+))]
+pub struct LabelArgs {
+    /// Labels of the records to extract (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    labels: Option<Vec<String>>,
+
+    /// File with one label per line
+    #[arg(long, value_name = "FILE")]
+    labels_file: Option<String>,
+
+    /// Regular expression matched anywhere in the label, e.g. "_WT$"
+    #[arg(long, value_name = "REGEX")]
+    pattern: Option<String>,
+}
+
+impl LabelArgs {
+    /// Reads the records picked by the selector with `read`, warning about requested labels
+    /// that have no record
+    pub fn select<F>(&self, read: F) -> Result<DataFrame, CliError>
+    where
+        F: FnOnce(&LabelSelector) -> Result<DataFrame, CliError>,
+    {
+        let labels: Option<HashSet<String>> = match (&self.labels, &self.labels_file) {
+            (Some(labels), _) => Some(labels.iter().cloned().collect()),
+            (None, Some(path)) => Some(
+                std::fs::read_to_string(path)?
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(String::from)
+                    .collect(),
+            ),
+            (None, None) => None,
+        };
+        let selector = match (&labels, &self.pattern) {
+            (Some(labels), _) => LabelSelector::Labels(labels.clone()),
+            (None, Some(pattern)) => LabelSelector::pattern(pattern)?,
+            (None, None) => unreachable!("clap requires a selector"),
+        };
+
+        let records = read(&selector)?;
+        if let Some(labels) = &labels {
+            let found: HashSet<&str> = records
+                .column("label")?
+                .str()?
+                .into_no_null_iter()
+                .collect();
+            let mut missing: Vec<&str> = labels
+                .iter()
+                .map(String::as_str)
+                .filter(|label| !found.contains(label))
+                .collect();
+            missing.sort_unstable();
+            if !missing.is_empty() {
+                warn!(
+                    missing = missing.len(),
+                    "labels not found: {}",
+                    missing.join(", ")
+                );
+            }
+        }
+        Ok(records)
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct SubsetArgs {
+    /// Path to the input FASTA file; a samtools index next to it (FASTA_FILE.fai) is used
+    /// when present
+    #[arg(value_name = "FASTA_FILE")]
+    input: String,
+
+    #[command(flatten)]
+    selection: LabelArgs,
+
+    /// Write the records to this FASTA file instead of stdout
+    #[arg(long, value_name = "FASTA_FILE")]
+    output: Option<String>,
+}
+
+pub fn run(args: SubsetArgs) -> Result<(), CliError> {
+    let records = args
+        .selection
+        .select(|selector| Ok(subset_file(&args.input, selector)?))?;
+
+    match &args.output {
+        Some(path) => {
+            write_fasta(&records, path)?;
+            info!(file = %path, records = records.height(), "records written");
+        }
+        None => {
+            let stdout = std::io::stdout();
+            let mut out = BufWriter::new(stdout.lock());
+            for (label, sequence) in records
+                .column("label")?
+                .str()?
+                .into_no_null_iter()
+                .zip(records.column("sequence")?.str()?.into_no_null_iter())
+            {
+                writeln!(out, ">{}\n{}", label, sequence)?;
+            }
+            out.flush()?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use crate::{read_sequence_records, CliError};
+use clap::Args;
+use motif_scanner::io::{read_table, write_table};
+use polars::prelude::*;
+use tf_binding_rs::coords::{
+    cluster_tile_hits, parse_labels, CoordinateSystem, LabelParser, DEFAULT_LABEL_PATTERN,
+};
+use tracing::{info, warn};
+
+#[derive(Args, Debug)]
+pub struct TilesArgs {
+    /// Scan output of a tiled library (.csv or .parquet)
+    #[arg(value_name = "HITS_FILE")]
+    input: String,
+
+    /// The scanned tiles (FASTA or table with 'label' and 'sequence' columns), whose labels
+    /// hold their genomic coordinates
+    #[arg(value_name = "SEQUENCES")]
+    sequences: String,
+
+    /// Largest distance in bases between the starts of hits merged into one site
+    #[arg(long, default_value_t = 0)]
+    max_shift: i64,
+
+    /// Regular expression with named groups chrom, start, end and optionally strand and
+    /// suffix, replacing the default label pattern
+    #[arg(long, value_name = "REGEX")]
+    label_pattern: Option<String>,
+
+    /// Label starts are 1-based instead of 0-based
+    #[arg(long)]
+    one_based_labels: bool,
+
+    /// Write the sites to this file (.csv, .parquet or .jsonl) instead of printing them
+    #[arg(long, value_name = "OUTPUT")]
+    output: Option<String>,
+}
+
+pub fn run(args: TilesArgs) -> Result<(), CliError> {
+    if args.max_shift < 0 {
+        return Err(CliError::InvalidArgument(
+            "--max-shift must not be negative".to_string(),
+        ));
+    }
+    let mut hits = read_table(&args.input)?.collect()?;
+    let tiles = read_sequence_records(&args.sequences)?;
+
+    // motif-scanner labels hits with the row of their sequence in the input
+    let names = tiles.column("label")?.str()?;
+    if hits.column("label")?.dtype().is_integer() {
+        let rows = hits.column("label")?.cast(&DataType::Int64)?;
+        let labels: StringChunked = rows
+            .i64()?
+            .into_iter()
+            .map(|row| row.and_then(|row| names.get(usize::try_from(row).ok()?)))
+            .collect();
+        hits.with_column(labels.into_series().with_name("label".into()))?;
+    }
+
+    let system = if args.one_based_labels {
+        CoordinateSystem::OneBased
+    } else {
+        CoordinateSystem::ZeroBased
+    };
+    let parser = LabelParser::new(
+        args.label_pattern
+            .as_deref()
+            .unwrap_or(DEFAULT_LABEL_PATTERN),
+        system,
+    )?;
+    let regions = parse_labels(&tiles, "label", &parser)?;
+    let unparsed = regions.column("chrom")?.null_count();
+    if unparsed > 0 {
+        warn!(
+            tiles = unparsed,
+            "labels without genomic coordinates; their hits are left out"
+        );
+    }
+
+    let mut sites = cluster_tile_hits(&hits, &regions, args.max_shift)?;
+    info!(
+        hits = hits.height(),
+        sites = sites.height(),
+        "clustered tile hits"
+    );
+    match &args.output {
+        Some(path) => write_table(&mut sites, path)?,
+        None => println!("{}", sites),
+    }
+    Ok(())
+}
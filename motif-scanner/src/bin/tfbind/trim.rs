@@ -0,0 +1,98 @@
+use crate::{read_sequence_records, CliError};
+use clap::Args;
+use motif_scanner::io::write_table;
+use std::path::Path;
+use tf_binding_rs::fasta::{trim, trim_adapters, write_fasta, AdapterOptions};
+use tracing::info;
+
+#[derive(Args, Debug)]
+pub struct TrimArgs {
+    /// FASTA file (.fa/.fasta/.fna) or table (.csv/.parquet) with 'label' and 'sequence'
+    /// columns
+    #[arg(value_name = "SEQUENCES")]
+    input: String,
+
+    /// Path of the trimmed sequences: FASTA (.fa/.fasta/.fna) or a table (.csv, .parquet or
+    /// .jsonl), which keeps the other columns and records the adapter bases removed
+    #[arg(value_name = "OUTPUT")]
+    output: String,
+
+    /// Adapter or primer removed from the start of the sequences, also when only its 3'
+    /// end is present
+    #[arg(long, value_name = "SEQ")]
+    adapter_5p: Option<String>,
+
+    /// Adapter or primer removed from the end of the sequences, also when only its 5' end
+    /// is present
+    #[arg(long, value_name = "SEQ")]
+    adapter_3p: Option<String>,
+
+    /// Shortest partial adapter removed
+    #[arg(long, default_value_t = 3)]
+    min_overlap: usize,
+
+    /// Mismatches allowed per base of an adapter match
+    #[arg(long, default_value_t = 0.1)]
+    error_rate: f64,
+
+    /// Bases removed from the start of every sequence, after the adapters
+    #[arg(long, default_value_t = 0)]
+    left: usize,
+
+    /// Bases removed from the end of every sequence, after the adapters
+    #[arg(long, default_value_t = 0)]
+    right: usize,
+}
+
+pub fn run(args: TrimArgs) -> Result<(), CliError> {
+    if args.adapter_5p.is_none() && args.adapter_3p.is_none() && args.left == 0 && args.right == 0 {
+        return Err(CliError::InvalidArgument(
+            "nothing to trim: give --adapter-5p, --adapter-3p, --left or --right".to_string(),
+        ));
+    }
+    let mut sequences = read_sequence_records(&args.input)?;
+
+    if args.adapter_5p.is_some() || args.adapter_3p.is_some() {
+        let options = AdapterOptions {
+            min_overlap: args.min_overlap,
+            error_rate: args.error_rate,
+        };
+        sequences = trim_adapters(
+            &sequences,
+            args.adapter_5p.as_deref(),
+            args.adapter_3p.as_deref(),
+            &options,
+        )?;
+        let trimmed = |column: &str| -> Result<usize, CliError> {
+            Ok(sequences
+                .column(column)?
+                .u32()?
+                .into_no_null_iter()
+                .filter(|&bases| bases > 0)
+                .count())
+        };
+        info!(
+            sequences = sequences.height(),
+            trimmed_5p = trimmed("trimmed_5p")?,
+            trimmed_3p = trimmed("trimmed_3p")?,
+            "adapters removed"
+        );
+    }
+    if args.left > 0 || args.right > 0 {
+        sequences = trim(&sequences, args.left, args.right)?;
+    }
+
+    match Path::new(&args.output)
+        .extension()
+        .and_then(|ext| ext.to_str())
+    {
+        Some("fa" | "fasta" | "fna") => write_fasta(&sequences, &args.output)?,
+        _ => write_table(&mut sequences, &args.output)?,
+    }
+    info!(
+        file = %args.output,
+        sequences = sequences.height(),
+        "sequences written"
+    );
+    Ok(())
+}
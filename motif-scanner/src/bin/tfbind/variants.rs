@@ -0,0 +1,128 @@
+use crate::view::select_motifs;
+use crate::{read_ewms, read_sequence_records, CliError};
+use clap::Args;
+use motif_scanner::io::write_table;
+use polars::prelude::*;
+use tf_binding_rs::compare::{pair_sequences, variant_delta};
+use tf_binding_rs::scan::check_sequence;
+use tracing::{info, warn};
+
+#[derive(Args, Debug)]
+pub struct VariantsArgs {
+    /// Reference sequences: FASTA file (.fa/.fasta/.fna) or table (.csv/.parquet) with
+    /// 'label' and 'sequence' columns
+    #[arg(value_name = "REFERENCE")]
+    reference: String,
+
+    /// Edited sequences, paired with the reference ones by label
+    #[arg(value_name = "EDITED")]
+    edited: String,
+
+    /// Path to the motif file (MEME, JASPAR, TRANSFAC, HOMER or HOCOMOCO, by extension)
+    #[arg(value_name = "PWM_FILE")]
+    pwm_file: String,
+
+    /// Only compare these motifs (comma-separated full IDs or names before the first '_')
+    #[arg(long, value_delimiter = ',')]
+    motifs: Option<Vec<String>>,
+
+    /// Chemical potential of the transcription factors
+    #[arg(long, default_value_t = 9.0)]
+    mu: f64,
+
+    /// Minimum occupancy of a site
+    #[arg(long, default_value_t = 0.2)]
+    cutoff: f64,
+
+    /// Write the occupancy change at every differing base to this file (.csv, .parquet or
+    /// .jsonl) instead of printing it
+    #[arg(long, value_name = "OUTPUT")]
+    output: Option<String>,
+
+    /// Also write the differing bases (label, position, ref, alt) to this file
+    #[arg(long, value_name = "FILE")]
+    variants: Option<String>,
+
+    /// Fail when a label is unpaired or a pair differs in length
+    #[arg(long)]
+    check: bool,
+}
+
+/// Keeps the sequences every motif can be scanned on, so one ambiguous design does not
+/// stop the comparison
+fn scannable(df: &DataFrame, longest: usize, path: &str) -> Result<DataFrame, CliError> {
+    let mask: BooleanChunked = df
+        .column("sequence")?
+        .str()?
+        .into_iter()
+        .map(|seq| seq.is_some_and(|seq| check_sequence(seq, longest).is_none()))
+        .collect();
+    let kept = df.filter(&mask)?;
+    if kept.height() < df.height() {
+        warn!(
+            file = %path,
+            skipped = df.height() - kept.height(),
+            "skipped sequences that are missing, ambiguous or shorter than a motif"
+        );
+    }
+    Ok(kept)
+}
+
+pub fn run(args: VariantsArgs) -> Result<(), CliError> {
+    let ewms = select_motifs(read_ewms(&args.pwm_file)?, &args.motifs);
+    if ewms.is_empty() {
+        return Err(CliError::InvalidArgument(
+            "no motifs to compare".to_string(),
+        ));
+    }
+    let reference = read_sequence_records(&args.reference)?;
+    let edited = read_sequence_records(&args.edited)?;
+
+    let mut pairing = pair_sequences(&reference, &edited)?;
+    for (label, reference_length, edited_length) in &pairing.length_mismatches {
+        warn!(
+            label = %label,
+            reference = reference_length,
+            edited = edited_length,
+            "pair differs in length and is skipped"
+        );
+    }
+    if !pairing.reference_only.is_empty() || !pairing.edited_only.is_empty() {
+        warn!(
+            reference_only = pairing.reference_only.len(),
+            edited_only = pairing.edited_only.len(),
+            "unpaired labels"
+        );
+    }
+    info!(
+        variants = pairing.variants.height(),
+        identical = pairing.identical,
+        "paired sequences"
+    );
+    if let Some(path) = &args.variants {
+        write_table(&mut pairing.variants, path)?;
+    }
+
+    let longest = ewms.values().map(|ewm| ewm.height()).max().unwrap_or(0);
+    let mut effects = variant_delta(
+        &scannable(&reference, longest, &args.reference)?,
+        &scannable(&edited, longest, &args.edited)?,
+        &ewms,
+        args.mu,
+        args.cutoff,
+    )?;
+    match &args.output {
+        Some(path) => write_table(&mut effects, path)?,
+        None => println!("{}", effects),
+    }
+
+    let unpaired = pairing.reference_only.len() + pairing.edited_only.len();
+    if args.check && (unpaired > 0 || !pairing.length_mismatches.is_empty()) {
+        return Err(CliError::Differences(format!(
+            "{} unpaired labels and {} pairs of different lengths",
+            unpaired,
+            pairing.length_mismatches.len()
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,274 @@
+use crate::{read_ewms, CliError};
+use clap::Args;
+use std::path::Path;
+use tf_binding_rs::fasta::read_fasta;
+use tf_binding_rs::occupancy::find_hits;
+use tf_binding_rs::types::EWMCollection;
+
+#[derive(Args, Debug)]
+pub struct ViewArgs {
+    /// DNA sequence, or path to a FASTA file (see --label)
+    #[arg(value_name = "SEQUENCE")]
+    sequence: String,
+
+    /// Path to the motif file (MEME, JASPAR, TRANSFAC, HOMER or HOCOMOCO, by extension)
+    #[arg(value_name = "PWM_FILE")]
+    pwm_file: String,
+
+    /// Label of the FASTA record to show [default: the first record]
+    #[arg(long)]
+    label: Option<String>,
+
+    /// Only show these motifs (comma-separated full IDs or names before the first '_')
+    #[arg(long, value_delimiter = ',')]
+    motifs: Option<Vec<String>>,
+
+    /// Minimum predicted occupancy of the hits shown
+    #[arg(long, default_value_t = 0.2)]
+    cutoff: f64,
+
+    /// Chemical potential of the transcription factors
+    #[arg(long, default_value_t = 9.0)]
+    mu: f64,
+
+    /// Bases per line
+    #[arg(long, default_value_t = 80)]
+    width: usize,
+
+    /// Draw with plain ASCII characters instead of Unicode blocks and arrows
+    #[arg(long)]
+    ascii: bool,
+}
+
+/// Characters of a track: occupancy shades from low to high and the strand arrowheads
+struct Glyphs {
+    shades: [char; 4],
+    forward: char,
+    reverse: char,
+}
+
+const UNICODE: Glyphs = Glyphs {
+    shades: ['░', '▒', '▓', '█'],
+    forward: '▶',
+    reverse: '◀',
+};
+
+const ASCII: Glyphs = Glyphs {
+    shades: ['.', ':', '=', '#'],
+    forward: '>',
+    reverse: '<',
+};
+
+impl Glyphs {
+    fn shade(&self, occupancy: f64) -> char {
+        let level = ((occupancy * 4.0) as usize).min(3);
+        self.shades[level]
+    }
+}
+
+struct TrackHit {
+    position: usize,
+    length: usize,
+    forward: bool,
+    occupancy: f64,
+}
+
+/// Hits of one motif, packed into as few non-overlapping lanes as possible
+struct Track {
+    name: String,
+    lanes: Vec<Vec<TrackHit>>,
+}
+
+impl Track {
+    fn new(name: String, mut hits: Vec<TrackHit>) -> Self {
+        hits.sort_by_key(|hit| hit.position);
+        let mut lanes: Vec<Vec<TrackHit>> = Vec::new();
+        for hit in hits {
+            let free = lanes.iter_mut().find(|lane| {
+                lane.last()
+                    .is_none_or(|last| last.position + last.length <= hit.position)
+            });
+            match free {
+                Some(lane) => lane.push(hit),
+                None => lanes.push(vec![hit]),
+            }
+        }
+        Track { name, lanes }
+    }
+}
+
+/// Draws the bases `start..end` of one lane
+fn render_lane(lane: &[TrackHit], start: usize, end: usize, glyphs: &Glyphs) -> String {
+    let mut line = vec![' '; end - start];
+    for hit in lane {
+        let (first, last) = (hit.position, hit.position + hit.length - 1);
+        for (pos, cell) in (start..end).zip(line.iter_mut()) {
+            if pos < first || pos > last {
+                continue;
+            }
+            *cell = if hit.forward && pos == last {
+                glyphs.forward
+            } else if !hit.forward && pos == first {
+                glyphs.reverse
+            } else {
+                glyphs.shade(hit.occupancy)
+            };
+        }
+    }
+    line.into_iter().collect::<String>().trim_end().to_string()
+}
+
+/// Position ruler with a tick every 10 bases, numbered from 1
+fn ruler(start: usize, end: usize) -> String {
+    let mut line = String::new();
+    let mut pos = start;
+    while pos < end {
+        if pos.is_multiple_of(10) {
+            let number = (pos + 1).to_string();
+            if pos + number.len() <= end {
+                line.push_str(&number);
+                pos += number.len();
+                continue;
+            }
+        }
+        line.push(' ');
+        pos += 1;
+    }
+    line.trim_end().to_string()
+}
+
+fn render(sequence: &str, tracks: &[Track], width: usize, glyphs: &Glyphs) -> String {
+    let margin = tracks
+        .iter()
+        .map(|t| t.name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(3);
+    let mut out = String::new();
+    for start in (0..sequence.len()).step_by(width) {
+        let end = (start + width).min(sequence.len());
+        out.push_str(&format!("{:margin$}  {}\n", "", ruler(start, end)));
+        out.push_str(&format!("{:margin$}  {}\n", "", &sequence[start..end]));
+        for track in tracks {
+            let lines = track
+                .lanes
+                .iter()
+                .map(|lane| render_lane(lane, start, end, glyphs))
+                .filter(|line| !line.is_empty());
+            for (i, line) in lines.enumerate() {
+                let name = if i == 0 { track.name.as_str() } else { "" };
+                out.push_str(&format!("{:margin$}  {}\n", name, line));
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// The sequence to show and its label
+fn load_sequence(args: &ViewArgs) -> Result<(String, String), CliError> {
+    if !Path::new(&args.sequence).exists() {
+        return Ok(("sequence".to_string(), args.sequence.to_uppercase()));
+    }
+
+    let records = read_fasta(&args.sequence)?;
+    let labels = records.column("label")?.str()?;
+    let sequences = records.column("sequence")?.str()?;
+    let record = labels
+        .into_iter()
+        .zip(sequences)
+        .find(|(label, _)| args.label.is_none() || *label == args.label.as_deref())
+        .and_then(|(label, seq)| Some((label?.to_string(), seq?.to_string())));
+    record.ok_or_else(|| {
+        CliError::InvalidArgument(match &args.label {
+            Some(label) => format!("no record '{}' in {}", label, args.sequence),
+            None => format!("no sequences in {}", args.sequence),
+        })
+    })
+}
+
+/// Keeps the motifs named in `names` (full IDs or names before the first '_'), or all of
+/// them
+pub fn select_motifs(ewms: EWMCollection, names: &Option<Vec<String>>) -> EWMCollection {
+    match names {
+        Some(names) => ewms
+            .into_iter()
+            .filter(|(id, _)| {
+                names
+                    .iter()
+                    .any(|name| id == name || id.split('_').next() == Some(name))
+            })
+            .collect(),
+        None => ewms,
+    }
+}
+
+pub fn run(args: ViewArgs) -> Result<(), CliError> {
+    if args.width == 0 {
+        return Err(CliError::InvalidArgument(
+            "--width must be positive".to_string(),
+        ));
+    }
+    let (label, sequence) = load_sequence(&args)?;
+    let ewms = select_motifs(read_ewms(&args.pwm_file)?, &args.motifs);
+    if ewms.is_empty() {
+        return Err(CliError::InvalidArgument("no motifs to show".to_string()));
+    }
+
+    let hits = find_hits(&sequence, &ewms, args.mu, args.cutoff)?;
+    let motifs = hits.column("motif")?.str()?;
+    let positions = hits.column("position")?.i32()?;
+    let strands = hits.column("strand")?.str()?;
+    let lengths = hits.column("length")?.i32()?;
+    let occupancies = hits.column("occupancy")?.f64()?;
+
+    let mut by_motif: Vec<(String, Vec<TrackHit>)> = Vec::new();
+    for ((((motif, position), strand), length), occupancy) in motifs
+        .into_iter()
+        .zip(positions)
+        .zip(strands)
+        .zip(lengths)
+        .zip(occupancies)
+    {
+        let name = motif
+            .unwrap_or_default()
+            .split('_')
+            .next()
+            .unwrap_or_default();
+        let hit = TrackHit {
+            position: position.unwrap_or_default() as usize,
+            length: length.unwrap_or_default() as usize,
+            forward: strand != Some("R"),
+            occupancy: occupancy.unwrap_or_default(),
+        };
+        match by_motif.iter_mut().find(|(n, _)| n == name) {
+            Some((_, hits)) => hits.push(hit),
+            None => by_motif.push((name.to_string(), vec![hit])),
+        }
+    }
+    let tracks: Vec<Track> = by_motif
+        .into_iter()
+        .map(|(name, hits)| Track::new(name, hits))
+        .collect();
+
+    let glyphs = if args.ascii { &ASCII } else { &UNICODE };
+    println!(
+        "{} ({} bp), {} hits with occupancy > {} at mu {}",
+        label,
+        sequence.len(),
+        hits.height(),
+        args.cutoff,
+        args.mu
+    );
+    print!("{}", render(&sequence, &tracks, args.width, glyphs));
+    println!(
+        "occupancy {} <0.25  {} <0.5  {} <0.75  {} >=0.75   strand {} forward  {} reverse",
+        glyphs.shades[0],
+        glyphs.shades[1],
+        glyphs.shades[2],
+        glyphs.shades[3],
+        glyphs.forward,
+        glyphs.reverse
+    );
+    Ok(())
+}
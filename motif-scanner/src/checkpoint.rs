@@ -0,0 +1,157 @@
+use crate::config::MotifParams;
+use crate::{process_sequences, ScannerError, Scoring};
+use polars::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tf_binding_rs::provenance::RunManifest;
+use tf_binding_rs::scan::warnings_frame;
+use tf_binding_rs::types::EWMCollection;
+use tracing::info;
+
+/// Chunked checkpointing of a scan
+pub struct Checkpoint {
+    /// Directory holding the checkpoint: `{OUTPUT_FILE}.checkpoint`
+    pub dir: PathBuf,
+    /// Number of sequences scanned between checkpoints
+    pub chunk_size: usize,
+    /// Whether to continue from an existing checkpoint
+    pub resume: bool,
+}
+
+fn part_path(dir: &Path, chunk: usize) -> PathBuf {
+    dir.join(format!("part-{:06}.parquet", chunk))
+}
+
+fn warnings_part_path(dir: &Path, chunk: usize) -> PathBuf {
+    dir.join(format!("warnings-{:06}.parquet", chunk))
+}
+
+/// Concatenates the parquet files of all chunks
+fn read_parts(
+    dir: &Path,
+    n_chunks: usize,
+    path: fn(&Path, usize) -> PathBuf,
+) -> Result<Option<DataFrame>, ScannerError> {
+    let mut results: Option<DataFrame> = None;
+    for chunk in 0..n_chunks {
+        let part = ParquetReader::new(fs::File::open(path(dir, chunk))?).finish()?;
+        match results.as_mut() {
+            Some(all) => {
+                all.vstack_mut(&part)?;
+            }
+            None => results = Some(part),
+        }
+    }
+    Ok(results)
+}
+
+/// Loads the number of completed chunks of a previous run, if it is compatible.
+fn completed_chunks(state_path: &Path, state: &RunManifest) -> Result<usize, ScannerError> {
+    let saved = RunManifest::read(&state_path.to_string_lossy())
+        .map_err(|e| ScannerError::Checkpoint(e.to_string()))?;
+    if saved.parameters != state.parameters || saved.inputs != state.inputs {
+        return Err(ScannerError::Checkpoint(
+            "checkpoint was written by a run with different inputs or parameters".to_string(),
+        ));
+    }
+    Ok(saved.counts.get("chunks_completed").copied().unwrap_or(0) as usize)
+}
+
+impl Checkpoint {
+    /// Checkpoint for the run writing `output_file`
+    pub fn for_output(output_file: &str, chunk_size: usize, resume: bool) -> Self {
+        Checkpoint {
+            dir: PathBuf::from(format!("{}.checkpoint", output_file)),
+            chunk_size,
+            resume,
+        }
+    }
+
+    /// Scans sequences in chunks of `chunk_size`, saving each chunk's hits and warnings to the
+    /// checkpoint directory as soon as it completes.
+    ///
+    /// With `resume`, chunks completed by an earlier run with the same inputs and parameters
+    /// (as recorded in `manifest`) are loaded instead of rescanned. Returns the hits and the
+    /// warnings of all chunks; labels are indices into the full input, as without
+    /// checkpointing.
+    pub fn scan(
+        &self,
+        df: &DataFrame,
+        ewm: &EWMCollection,
+        params: &HashMap<String, MotifParams>,
+        manifest: &RunManifest,
+        scoring: Scoring,
+    ) -> Result<(DataFrame, DataFrame), ScannerError> {
+        let (dir, chunk_size, resume) = (self.dir.as_path(), self.chunk_size, self.resume);
+        if chunk_size == 0 {
+            return Err(ScannerError::Checkpoint(
+                "checkpoint size must be positive".to_string(),
+            ));
+        }
+
+        let state_path = dir.join("state.json");
+        let mut state = manifest.clone();
+        state.parameter("checkpoint", chunk_size);
+
+        let mut done = 0;
+        if resume && state_path.exists() {
+            done = completed_chunks(&state_path, &state)?;
+            info!(chunks = done, "resuming after completed chunks");
+        } else {
+            if resume {
+                info!(dir = %dir.display(), "no checkpoint found, starting from scratch");
+            }
+            if dir.exists() {
+                fs::remove_dir_all(dir)?;
+            }
+            fs::create_dir_all(dir)?;
+        }
+
+        let n_chunks = df.height().div_ceil(chunk_size);
+        for chunk in done..n_chunks {
+            let offset = chunk * chunk_size;
+            let mut result = process_sequences(
+                &df.slice(offset as i64, chunk_size),
+                ewm,
+                params,
+                offset,
+                scoring,
+            )?;
+
+            let mut file = fs::File::create(part_path(dir, chunk))?;
+            ParquetWriter::new(&mut file).finish(&mut result.hits)?;
+            let mut file = fs::File::create(warnings_part_path(dir, chunk))?;
+            ParquetWriter::new(&mut file).finish(
+                &mut warnings_frame(&result.warnings)
+                    .map_err(|e| ScannerError::Output(e.to_string()))?,
+            )?;
+
+            // write the state atomically so a preempted run never sees a partial file
+            state.count("chunks_completed", chunk + 1);
+            let tmp_path = dir.join("state.json.tmp");
+            state
+                .write(&tmp_path.to_string_lossy())
+                .map_err(|e| ScannerError::Checkpoint(e.to_string()))?;
+            fs::rename(&tmp_path, &state_path)?;
+
+            info!(completed = chunk + 1, total = n_chunks, "checkpoint saved");
+        }
+
+        let hits = match read_parts(dir, n_chunks, part_path)? {
+            Some(hits) => hits,
+            None => process_sequences(&df.clear(), ewm, params, 0, scoring)?.hits,
+        };
+        let warnings = match read_parts(dir, n_chunks, warnings_part_path)? {
+            Some(warnings) => warnings,
+            None => warnings_frame(&[]).map_err(|e| ScannerError::Output(e.to_string()))?,
+        };
+        Ok((hits, warnings))
+    }
+
+    /// Deletes the checkpoint once the final output has been written
+    pub fn remove(&self) -> Result<(), ScannerError> {
+        fs::remove_dir_all(&self.dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,669 @@
+use crate::{Args, ScannerError};
+use polars::prelude::DataFrame;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use tf_binding_rs::annotations::TfAnnotations;
+use tf_binding_rs::collection::{MotifFilter, DEFAULT_DEDUPE_TOLERANCE};
+use tf_binding_rs::coords::{CoordinateSystem, LabelParser, DEFAULT_LABEL_PATTERN};
+use tf_binding_rs::error::MotifError;
+use tf_binding_rs::fasta::{DuplicateLabels, InputColumns, Normalization};
+use tf_binding_rs::occupancy::EwmOptions;
+use tf_binding_rs::scan::AmbiguousPolicy;
+use tf_binding_rs::types::EWMCollection;
+
+/// Columns of the hit table that `--columns` can select, besides the genomic and carried
+/// columns
+pub const HIT_COLUMNS: [&str; 12] = [
+    "label",
+    "position",
+    "motif",
+    "strand",
+    "length",
+    "occupancy",
+    "energy",
+    "relative_score",
+    "matched_seq",
+    "pvalue",
+    "neg_log10_pvalue",
+    "background_per_kb",
+];
+
+/// Columns added by `--genomic`
+const GENOMIC_COLUMNS: [&str; 4] = ["chrom", "genomic_start", "genomic_end", "genomic_strand"];
+
+/// Per-motif overrides of the global chemical potential and cutoff
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MotifOverride {
+    pub mu: Option<f64>,
+    pub cutoff: Option<f64>,
+}
+
+/// Contents of a `--config` file; every field is optional and command line flags win
+///
+/// ```toml
+/// data_file = "sequences.csv"
+/// pwm_file = "motifs.meme"
+/// output_file = "hits.parquet"
+/// extra_outputs = ["hits.jsonl"]
+/// motifs = ["NRL", "MAZ"]
+/// carry = ["name", "group"]  # input columns copied into the hits
+/// # tf_annotations = "tf_annotations.csv"  # adds tf_name, family, role, concentration
+/// # columns = ["label", "position", "motif", "strand", "occupancy", "energy", "matched_seq"]
+/// # sort_by = ["motif", "position"]  # lets Parquet readers prune row groups by motif
+/// # row_group_size = 100000          # rows per Parquet row group
+/// duplicate_labels = "keep-first"  # or keep, error, suffix
+/// # ambiguous_policy = "mask"      # or drop, scan-lenient
+/// # keep_case = true         # soft-masked (lowercase) bases count as ambiguous
+/// # validate_sequences = true  # fail on characters that are not nucleotide codes
+/// # sequence_col = "oligo_seq"  # sequence column of CSV/Parquet inputs
+/// # id_col = "oligo_id"         # record IDs used as labels
+/// # separator = "tab"           # of CSV/TSV inputs; .tsv and .tsv.gz default to tabs
+/// # genomic = true           # genomic hit coordinates from labels like chr1-100-200_WT
+/// # label_pattern = '^(?P<chrom>\w+):(?P<start>\d+)-(?P<end>\d+)$'
+/// # one_based_labels = true
+/// # chrom_sizes = "mm10.chrom.sizes"  # for .bb (bigBed) outputs
+/// mu = 9
+/// cutoff = 0.2
+/// # background = "shuffled.fa"  # calibrate a cutoff per motif instead of `cutoff`
+/// # fp_rate = 0.1                # expected background hits per kb at that cutoff
+/// # temperature = 37          # °C, sets RT of the energy conversion (default 2.5 kJ/mol)
+/// # motif_cache = "motifs.tfbm"  # reuse the parsed and converted motifs across runs
+/// threads = 8
+/// # quantized = true         # integer prefilter, same hits
+/// # pvalues = true           # add pvalue, neg_log10_pvalue and background_per_kb columns
+/// # min_ic = 8               # drop motifs below 8 bits of information
+/// # max_width = 30           # drop motifs longer than 30 positions
+/// # keep_filtered = true     # only warn about them
+/// # dedupe = true            # scan duplicate motifs once
+/// # dedupe_tolerance = 0.01
+/// # sample_motifs = 20       # quick exploratory run on random subsets
+/// # sample_sequences = 1000
+/// # seed = 42
+/// # output_schema = true     # write {output_file}.schema.json
+/// # fail_empty = true        # exit with code 7 when there are no hits
+/// # profile = true           # write {output_file}.profile.csv with per-motif time and hits
+/// # summary_json = true      # write the end-of-run summary to {output_file}.summary.json
+/// # max_memory = "8G"        # stream hits in chunks or refuse runs that do not fit
+/// # chunk_rows = 100000      # read and scan a large CSV/Parquet input in chunks
+/// # batch = true             # data_file is a directory or glob, output_file a directory
+/// # batch_format = "csv"
+///
+/// [motif.NRL]
+/// mu = 11
+/// cutoff = 0.4
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    pub data_file: Option<String>,
+    pub pwm_file: Option<String>,
+    pub output_file: Option<String>,
+    #[serde(default)]
+    pub extra_outputs: Vec<String>,
+    pub motifs: Option<Vec<String>>,
+    pub carry: Option<Vec<String>>,
+    pub tf_annotations: Option<String>,
+    pub columns: Option<Vec<String>>,
+    pub sort_by: Option<Vec<String>>,
+    pub row_group_size: Option<usize>,
+    pub duplicate_labels: Option<String>,
+    pub ambiguous_policy: Option<String>,
+    pub keep_case: Option<bool>,
+    pub validate_sequences: Option<bool>,
+    pub sequence_col: Option<String>,
+    pub id_col: Option<String>,
+    pub separator: Option<String>,
+    pub genomic: Option<bool>,
+    pub label_pattern: Option<String>,
+    pub one_based_labels: Option<bool>,
+    pub chrom_sizes: Option<String>,
+    pub mu: Option<f64>,
+    pub cutoff: Option<f64>,
+    pub background: Option<String>,
+    pub fp_rate: Option<f64>,
+    pub temperature: Option<f64>,
+    pub motif_cache: Option<String>,
+    #[serde(default)]
+    pub motif: BTreeMap<String, MotifOverride>,
+    pub threads: Option<usize>,
+    pub quantized: Option<bool>,
+    pub pvalues: Option<bool>,
+    pub min_ic: Option<f64>,
+    pub max_width: Option<usize>,
+    pub keep_filtered: Option<bool>,
+    pub dedupe: Option<bool>,
+    pub dedupe_tolerance: Option<f64>,
+    pub sample_motifs: Option<usize>,
+    pub sample_sequences: Option<usize>,
+    pub seed: Option<u64>,
+    pub checkpoint: Option<usize>,
+    pub metadata: Option<bool>,
+    pub output_schema: Option<bool>,
+    pub fail_empty: Option<bool>,
+    pub profile: Option<bool>,
+    pub summary_json: Option<bool>,
+    pub max_memory: Option<String>,
+    pub chunk_rows: Option<usize>,
+    pub batch: Option<bool>,
+    pub batch_format: Option<String>,
+}
+
+impl ConfigFile {
+    /// Reads a TOML (`.toml`) or YAML (`.yaml`/`.yml`) config file
+    pub fn read(path: &str) -> Result<Self, ScannerError> {
+        let text = std::fs::read_to_string(path)?;
+        let ext = Path::new(path).extension().and_then(|e| e.to_str());
+        match ext {
+            Some("yaml" | "yml") => {
+                serde_yaml::from_str(&text).map_err(|e| ScannerError::Config(e.to_string()))
+            }
+            _ => toml::from_str(&text).map_err(|e| ScannerError::Config(e.to_string())),
+        }
+    }
+}
+
+/// Chemical potential and occupancy cutoff used for one motif
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotifParams {
+    pub mu: f64,
+    pub cutoff: f64,
+}
+
+/// Background on which per-motif cutoffs are calibrated, replacing the global cutoff
+#[derive(Debug, Clone)]
+pub struct Calibration {
+    /// Background sequence file
+    pub background: String,
+    /// Expected false-positive hits per kb of background
+    pub rate_per_kb: f64,
+}
+
+/// Fully resolved settings of a run, after merging the config file and the command line
+#[derive(Debug, Clone)]
+pub struct RunConfig {
+    pub data_file: String,
+    pub pwm_file: String,
+    pub output_files: Vec<String>,
+    pub motifs: Option<Vec<String>>,
+    pub carry: Option<Vec<String>>,
+    /// Annotations joined onto the hits by motif
+    pub tf_annotations: Option<TfAnnotations>,
+    /// Columns of the tabular outputs, in order
+    pub columns: Option<Vec<String>>,
+    /// Sort order of the hits
+    pub sort_by: Option<Vec<String>>,
+    /// Rows per row group of Parquet outputs
+    pub row_group_size: Option<usize>,
+    pub duplicate_labels: DuplicateLabels,
+    /// What to do with sequences containing bases other than A, C, G, T
+    pub ambiguous_policy: AmbiguousPolicy,
+    /// Whitespace, case and validation of the input sequences
+    pub normalization: Normalization,
+    /// Sequence and ID columns of table inputs
+    pub input_columns: InputColumns,
+    /// Field separator of delimited inputs, when not chosen by their extension
+    pub separator: Option<u8>,
+    /// Parser of the input labels when hits get genomic coordinates
+    pub genomic: Option<LabelParser>,
+    /// Chromosome sizes file for bigBed outputs
+    pub chrom_sizes: Option<String>,
+    pub defaults: MotifParams,
+    /// Per-motif cutoffs calibrated on a background, when enabled
+    pub calibration: Option<Calibration>,
+    /// RT of the conversion of the PWMs to energies
+    pub ewm_options: EwmOptions,
+    /// Binary cache of the parsed and converted motifs
+    pub motif_cache: Option<String>,
+    pub overrides: BTreeMap<String, MotifOverride>,
+    pub threads: Option<usize>,
+    pub quantized: bool,
+    pub pvalues: bool,
+    pub motif_filter: MotifFilter,
+    pub keep_filtered: bool,
+    /// Tolerance for collapsing duplicate motifs, when enabled
+    pub dedupe: Option<f64>,
+    /// Number of motifs drawn at random to scan
+    pub sample_motifs: Option<usize>,
+    /// Number of sequences of each input drawn at random to scan
+    pub sample_sequences: Option<usize>,
+    /// Seed of the motif and sequence sampling
+    pub seed: u64,
+    pub checkpoint: Option<usize>,
+    pub resume: bool,
+    pub metadata: bool,
+    pub no_manifest: bool,
+    pub output_schema: bool,
+    pub fail_empty: bool,
+    /// Record per-motif time and hit counts of the scan
+    pub profile: bool,
+    /// Write the end-of-run summary as JSON
+    pub summary_json: bool,
+    pub dry_run: bool,
+    /// Memory budget in bytes
+    pub max_memory: Option<usize>,
+    /// Records of the input read and scanned at a time
+    pub chunk_rows: Option<usize>,
+    pub batch: bool,
+    pub batch_format: String,
+}
+
+impl RunConfig {
+    /// Merges command line arguments over the optional config file
+    pub fn resolve(args: &Args) -> Result<Self, ScannerError> {
+        let config = match &args.config {
+            Some(path) => ConfigFile::read(path)?,
+            None => ConfigFile::default(),
+        };
+
+        let required = |flag: &Option<String>, file: Option<String>, name: &str| {
+            flag.clone().or(file).ok_or_else(|| {
+                ScannerError::Config(format!(
+                    "{} must be given on the command line or in --config",
+                    name
+                ))
+            })
+        };
+
+        let mut output_files = vec![required(
+            &args.output_file,
+            config.output_file,
+            "OUTPUT_FILE",
+        )?];
+        output_files.extend(config.extra_outputs);
+
+        let batch = args.batch || config.batch.unwrap_or(false);
+        if batch && (args.checkpoint.or(config.checkpoint).is_some() || output_files.len() > 1) {
+            return Err(ScannerError::Config(
+                "batch mode does not support checkpoints or extra outputs".to_string(),
+            ));
+        }
+
+        if args.resume && args.checkpoint.or(config.checkpoint).is_none() {
+            return Err(ScannerError::Config(
+                "--resume requires --checkpoint or a checkpoint in --config".to_string(),
+            ));
+        }
+
+        let duplicate_labels = match (args.duplicate_labels, &config.duplicate_labels) {
+            (Some(policy), _) => policy,
+            (None, Some(name)) => name
+                .parse()
+                .map_err(|e: MotifError| ScannerError::Config(e.to_string()))?,
+            (None, None) => DuplicateLabels::default(),
+        };
+        let ambiguous_policy = match (args.ambiguous_policy, &config.ambiguous_policy) {
+            (Some(policy), _) => policy,
+            (None, Some(name)) => name
+                .parse()
+                .map_err(|e: MotifError| ScannerError::Config(e.to_string()))?,
+            (None, None) => AmbiguousPolicy::default(),
+        };
+        let normalization = Normalization {
+            uppercase: !(args.keep_case || config.keep_case.unwrap_or(false)),
+            validate: args.validate_sequences || config.validate_sequences.unwrap_or(false),
+            ..Normalization::default()
+        };
+        let input_columns = InputColumns {
+            sequence: args
+                .sequence_col
+                .clone()
+                .or(config.sequence_col)
+                .unwrap_or_else(|| InputColumns::default().sequence),
+            id: args.id_col.clone().or(config.id_col),
+        };
+        let separator = match (args.separator, &config.separator) {
+            (Some(separator), _) => Some(separator),
+            (None, Some(name)) => Some(
+                motif_scanner::io::parse_separator(name)
+                    .map_err(|e| ScannerError::Config(format!("separator: {}", e)))?,
+            ),
+            (None, None) => None,
+        };
+
+        let genomic = if args.genomic || config.genomic.unwrap_or(false) {
+            let system = if args.one_based_labels || config.one_based_labels.unwrap_or(false) {
+                CoordinateSystem::OneBased
+            } else {
+                CoordinateSystem::ZeroBased
+            };
+            let pattern = args
+                .label_pattern
+                .as_ref()
+                .or(config.label_pattern.as_ref());
+            Some(
+                LabelParser::new(
+                    pattern.map_or(DEFAULT_LABEL_PATTERN, String::as_str),
+                    system,
+                )
+                .map_err(|e| ScannerError::Config(e.to_string()))?,
+            )
+        } else {
+            None
+        };
+        let batch_format = args
+            .batch_format
+            .as_deref()
+            .or(config.batch_format.as_deref());
+        let writes = |extensions: &[&str]| {
+            batch_format.is_some_and(|format| extensions.contains(&format))
+                || output_files.iter().any(|file| {
+                    Path::new(file)
+                        .extension()
+                        .and_then(|ext| ext.to_str())
+                        .is_some_and(|ext| extensions.contains(&ext))
+                })
+        };
+        let bigbed = writes(&["bb", "bigBed", "bigbed"]);
+        if genomic.is_none() && (bigbed || writes(&["bed"])) {
+            return Err(ScannerError::Config(
+                ".bed and .bb outputs require --genomic".to_string(),
+            ));
+        }
+        let chrom_sizes = args.chrom_sizes.clone().or(config.chrom_sizes);
+        let mut ewm_options = EwmOptions::default();
+        if let Some(celsius) = args.temperature.or(config.temperature) {
+            if celsius <= -273.15 {
+                return Err(ScannerError::Config(format!(
+                    "temperature {} °C is below absolute zero",
+                    celsius
+                )));
+            }
+            ewm_options = ewm_options.with_temperature(celsius);
+        }
+        let carry = args.carry.clone().or(config.carry);
+        let tf_annotations = args
+            .tf_annotations
+            .clone()
+            .or(config.tf_annotations)
+            .map(|path| {
+                TfAnnotations::read(&path)
+                    .map_err(|e| ScannerError::Config(format!("tf_annotations: {}", e)))
+            })
+            .transpose()?;
+        let columns = args.columns.clone().or(config.columns);
+        let sort_by = args.sort_by.clone().or(config.sort_by);
+        for (flag, columns) in [("--columns", &columns), ("--sort-by", &sort_by)] {
+            if let Some(columns) = columns {
+                check_columns(
+                    flag,
+                    columns,
+                    genomic.is_some(),
+                    carry.as_deref(),
+                    tf_annotations.as_ref(),
+                )?;
+            }
+        }
+        let row_group_size = args.row_group_size.or(config.row_group_size);
+        if row_group_size == Some(0) {
+            return Err(ScannerError::Config(
+                "row_group_size must be at least 1".to_string(),
+            ));
+        }
+        let pvalue_columns = columns
+            .iter()
+            .chain(&sort_by)
+            .flatten()
+            .any(|c| c == "pvalue" || c == "neg_log10_pvalue" || c == "background_per_kb");
+        let max_memory = match (args.max_memory, &config.max_memory) {
+            (Some(bytes), _) => Some(bytes),
+            (None, Some(size)) => Some(
+                crate::estimate::parse_size(size)
+                    .map_err(|e| ScannerError::Config(format!("max_memory: {}", e)))?,
+            ),
+            (None, None) => None,
+        };
+        let calibration = match (
+            args.background.clone().or(config.background),
+            args.fp_rate.or(config.fp_rate),
+        ) {
+            (Some(background), Some(rate_per_kb)) => {
+                if !rate_per_kb.is_finite() || rate_per_kb < 0.0 {
+                    return Err(ScannerError::Config(format!(
+                        "fp_rate {} must be a non-negative number of hits per kb",
+                        rate_per_kb
+                    )));
+                }
+                Some(Calibration {
+                    background,
+                    rate_per_kb,
+                })
+            }
+            (None, None) => None,
+            _ => {
+                return Err(ScannerError::Config(
+                    "background and fp_rate must be given together".to_string(),
+                ))
+            }
+        };
+        let chunk_rows = args.chunk_rows.or(config.chunk_rows);
+        if let Some(rows) = chunk_rows {
+            if rows == 0 {
+                return Err(ScannerError::Config(
+                    "chunk_rows must be at least 1".to_string(),
+                ));
+            }
+            if batch
+                || args.checkpoint.or(config.checkpoint).is_some()
+                || args.sample_sequences.or(config.sample_sequences).is_some()
+                || max_memory.is_some()
+            {
+                return Err(ScannerError::Config(
+                    "chunk_rows does not support batch mode, checkpoints, sample_sequences or \
+                     max_memory"
+                        .to_string(),
+                ));
+            }
+        }
+        if bigbed && chrom_sizes.is_none() {
+            return Err(ScannerError::Config(
+                ".bb output requires --chrom-sizes".to_string(),
+            ));
+        }
+
+        Ok(RunConfig {
+            data_file: required(&args.data_file, config.data_file, "DATA_FILE")?,
+            pwm_file: required(&args.pwm_file, config.pwm_file, "PWM_FILE")?,
+            output_files,
+            motifs: args.motifs.clone().or(config.motifs),
+            carry,
+            tf_annotations,
+            columns,
+            sort_by,
+            row_group_size,
+            duplicate_labels,
+            ambiguous_policy,
+            normalization,
+            input_columns,
+            separator,
+            genomic,
+            chrom_sizes,
+            defaults: MotifParams {
+                mu: args.mu.or(config.mu).unwrap_or(9.0),
+                cutoff: args.cutoff.or(config.cutoff).unwrap_or(0.2),
+            },
+            calibration,
+            ewm_options,
+            motif_cache: args.motif_cache.clone().or(config.motif_cache),
+            overrides: config.motif,
+            threads: args.threads.or(config.threads),
+            quantized: args.quantized || config.quantized.unwrap_or(false),
+            pvalues: args.pvalues || config.pvalues.unwrap_or(false) || pvalue_columns,
+            motif_filter: MotifFilter {
+                min_information: args.min_ic.or(config.min_ic),
+                max_width: args.max_width.or(config.max_width),
+            },
+            keep_filtered: args.keep_filtered || config.keep_filtered.unwrap_or(false),
+            dedupe: (args.dedupe || config.dedupe.unwrap_or(false)).then(|| {
+                args.dedupe_tolerance
+                    .or(config.dedupe_tolerance)
+                    .unwrap_or(DEFAULT_DEDUPE_TOLERANCE)
+            }),
+            sample_motifs: args.sample_motifs.or(config.sample_motifs),
+            sample_sequences: args.sample_sequences.or(config.sample_sequences),
+            seed: args.seed.or(config.seed).unwrap_or(0),
+            checkpoint: args.checkpoint.or(config.checkpoint),
+            resume: args.resume,
+            metadata: args.metadata || config.metadata.unwrap_or(false),
+            no_manifest: args.no_manifest,
+            output_schema: args.output_schema || config.output_schema.unwrap_or(false),
+            fail_empty: args.fail_empty || config.fail_empty.unwrap_or(false),
+            profile: args.profile || config.profile.unwrap_or(false),
+            summary_json: args.summary_json || config.summary_json.unwrap_or(false),
+            dry_run: args.dry_run,
+            max_memory,
+            chunk_rows,
+            batch,
+            batch_format: args
+                .batch_format
+                .clone()
+                .or(config.batch_format)
+                .unwrap_or_else(|| "parquet".to_string()),
+        })
+    }
+
+    /// Primary output file
+    pub fn output_file(&self) -> &str {
+        &self.output_files[0]
+    }
+
+    /// Whether `--columns` selects the column `name` or `--sort-by` sorts by it, so it has
+    /// to be computed
+    pub fn selects(&self, name: &str) -> bool {
+        self.columns
+            .iter()
+            .chain(&self.sort_by)
+            .flatten()
+            .any(|c| c == name)
+    }
+
+    /// The columns of `hits` written to `output_file`: the `--columns` selection, except
+    /// for BED and bigBed outputs, whose fields are fixed
+    pub fn output_columns(
+        &self,
+        hits: &DataFrame,
+        output_file: &str,
+    ) -> Result<DataFrame, ScannerError> {
+        let fixed = matches!(
+            Path::new(output_file)
+                .extension()
+                .and_then(|ext| ext.to_str()),
+            Some("bed" | "bb" | "bigBed" | "bigbed")
+        );
+        match &self.columns {
+            Some(columns) if !fixed => Ok(hits.select(columns.iter().map(String::as_str))?),
+            _ => Ok(hits.clone()),
+        }
+    }
+
+    /// Restricts the motifs (PWMs or EWMs) to the configured subset, matching full motif IDs
+    /// or the name before the first '_'
+    pub fn select_motifs<T>(
+        &self,
+        motifs: HashMap<String, T>,
+    ) -> Result<HashMap<String, T>, ScannerError> {
+        let Some(subset) = &self.motifs else {
+            return Ok(motifs);
+        };
+
+        let selected: HashMap<String, T> = motifs
+            .into_iter()
+            .filter(|(id, _)| subset.iter().any(|name| matches_motif(id, name)))
+            .collect();
+
+        for name in subset {
+            if !selected.keys().any(|id| matches_motif(id, name)) {
+                return Err(ScannerError::Config(format!(
+                    "motif '{}' not found in {}",
+                    name, self.pwm_file
+                )));
+            }
+        }
+        Ok(selected)
+    }
+
+    /// Whether the config file sets the cutoff of `motif_id` explicitly
+    pub fn overrides_cutoff(&self, motif_id: &str) -> bool {
+        self.overrides
+            .iter()
+            .any(|(name, o)| o.cutoff.is_some() && matches_motif(motif_id, name))
+    }
+
+    /// Chemical potential and cutoff of every motif, applying per-motif overrides
+    pub fn motif_params(
+        &self,
+        ewms: &EWMCollection,
+    ) -> Result<HashMap<String, MotifParams>, ScannerError> {
+        for name in self.overrides.keys() {
+            if !ewms.keys().any(|id| matches_motif(id, name)) {
+                return Err(ScannerError::Config(format!(
+                    "override for motif '{}', which is not scanned",
+                    name
+                )));
+            }
+        }
+
+        Ok(ewms
+            .keys()
+            .map(|id| {
+                let mut params = self.defaults;
+                for (name, o) in &self.overrides {
+                    if matches_motif(id, name) {
+                        params.mu = o.mu.unwrap_or(params.mu);
+                        params.cutoff = o.cutoff.unwrap_or(params.cutoff);
+                    }
+                }
+                (id.clone(), params)
+            })
+            .collect())
+    }
+}
+
+fn matches_motif(id: &str, name: &str) -> bool {
+    id == name || id.split('_').next() == Some(name)
+}
+
+/// Checks that every entry of `flag` (`--columns` or `--sort-by`) names a column of the hit
+/// table, once
+fn check_columns(
+    flag: &str,
+    columns: &[String],
+    genomic: bool,
+    carry: Option<&[String]>,
+    tf_annotations: Option<&TfAnnotations>,
+) -> Result<(), ScannerError> {
+    let mut available: Vec<String> = HIT_COLUMNS.iter().map(|c| c.to_string()).collect();
+    if genomic {
+        available.extend(GENOMIC_COLUMNS.iter().map(|c| c.to_string()));
+    }
+    for name in carry.unwrap_or_default() {
+        // carried columns clashing with hit columns get an `input_` prefix
+        if available.contains(name) {
+            available.push(format!("input_{}", name));
+        } else {
+            available.push(name.clone());
+        }
+    }
+    if let Some(annotations) = tf_annotations {
+        available.extend(annotations.columns().iter().map(|c| c.to_string()));
+    }
+
+    for (i, name) in columns.iter().enumerate() {
+        if !available.contains(name) {
+            return Err(ScannerError::Config(format!(
+                "unknown hit column '{}' in {}, expected one of: {}",
+                name,
+                flag,
+                available.join(", ")
+            )));
+        }
+        if columns[..i].contains(name) {
+            return Err(ScannerError::Config(format!(
+                "hit column '{}' is given twice in {}",
+                name, flag
+            )));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,241 @@
+use crate::config::MotifParams;
+use crate::{process_sequences, ScannerError, Scoring};
+use polars::prelude::*;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Instant;
+use tf_binding_rs::types::EWMCollection;
+
+/// Bases of the sample scanned to calibrate the estimates
+const SAMPLE_BASES: usize = 200_000;
+
+/// Bytes of one hit while the results are collected: the row tuple, then the DataFrame
+/// columns, with the motif name and strand as heap strings
+const HIT_BYTES: usize = 96;
+
+/// Size and cost of a scan, extrapolated from a timed scan of its first sequences
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Estimate {
+    /// Number of input sequences
+    pub sequences: usize,
+    /// Total length of the input sequences
+    pub bases: usize,
+    /// Length of the longest sequence
+    pub max_length: usize,
+    /// Number of motifs scanned
+    pub motifs: usize,
+    /// Amount of work in bp·motifs
+    pub work: usize,
+    /// Expected number of hits
+    pub hits: usize,
+    /// Memory of the input table
+    pub input_bytes: usize,
+    /// Memory of the collected hits
+    pub hit_bytes: usize,
+    /// Expected scan time
+    pub seconds: f64,
+}
+
+impl Estimate {
+    /// Estimates a scan of `df` by scanning a sample of about `SAMPLE_BASES` bases
+    pub fn new(
+        df: &DataFrame,
+        ewm: &EWMCollection,
+        params: &HashMap<String, MotifParams>,
+        quantized: bool,
+    ) -> Result<Estimate, ScannerError> {
+        let lengths: Vec<usize> = df
+            .column("sequence")
+            .map_err(|_| ScannerError::MissingSequenceColumn)?
+            .str()?
+            .into_iter()
+            .map(|seq| seq.map_or(0, str::len))
+            .collect();
+        let bases: usize = lengths.iter().sum();
+        let max_length = lengths.iter().copied().max().unwrap_or(0);
+
+        let mut sample_rows = 0;
+        let mut sample_bases = 0;
+        while sample_rows < lengths.len() && sample_bases < SAMPLE_BASES {
+            sample_bases += lengths[sample_rows];
+            sample_rows += 1;
+        }
+        let start = Instant::now();
+        let sample = process_sequences(
+            &df.slice(0, sample_rows),
+            ewm,
+            params,
+            0,
+            Scoring {
+                quantized,
+                ..Scoring::default()
+            },
+        )?;
+        let sample_seconds = start.elapsed().as_secs_f64();
+
+        let scale = if sample_bases == 0 {
+            0.0
+        } else {
+            bases as f64 / sample_bases as f64
+        };
+        let hits = (sample.hits.height() as f64 * scale).round() as usize;
+        Ok(Estimate {
+            sequences: df.height(),
+            bases,
+            max_length,
+            motifs: ewm.len(),
+            work: bases * ewm.len(),
+            hits,
+            input_bytes: df.estimated_size(),
+            hit_bytes: hits * HIT_BYTES,
+            seconds: sample_seconds * scale,
+        })
+    }
+
+    /// Combines the estimates of the inputs of a batch
+    pub fn add(&mut self, other: &Estimate) {
+        self.sequences += other.sequences;
+        self.bases += other.bases;
+        self.max_length = self.max_length.max(other.max_length);
+        self.motifs = self.motifs.max(other.motifs);
+        self.work += other.work;
+        self.hits += other.hits;
+        self.input_bytes += other.input_bytes;
+        self.hit_bytes += other.hit_bytes;
+        self.seconds += other.seconds;
+    }
+
+    /// Memory of the occupancy landscapes being scanned: every thread holds both strands
+    /// of one motif on its current sequence
+    pub fn landscape_bytes(&self) -> usize {
+        rayon::current_num_threads() * 2 * self.max_length * std::mem::size_of::<f64>()
+    }
+
+    /// Peak memory of the scan: the input, the landscapes and the hits, which are held
+    /// twice while the rows are turned into columns
+    pub fn peak_bytes(&self) -> usize {
+        self.input_bytes + self.landscape_bytes() + 2 * self.hit_bytes
+    }
+
+    /// Decides how the scan fits into `budget` bytes: at once when everything fits, or
+    /// streaming the hits to the outputs in chunks of sequences when only the input does.
+    ///
+    /// # Arguments
+    /// * `budget` - Memory budget in bytes
+    /// * `streamable` - Whether every output can be written chunk by chunk
+    ///
+    /// # Errors
+    /// * A message explaining why the run cannot fit
+    pub fn plan(&self, budget: usize, streamable: bool) -> Result<MemoryPlan, String> {
+        if self.peak_bytes() <= budget {
+            return Ok(MemoryPlan::InMemory);
+        }
+        let fixed = self.input_bytes + self.landscape_bytes();
+        if fixed >= budget {
+            return Err(format!(
+                "--max-memory {} cannot hold the input: the sequences take {} and the \
+                 landscapes of {} threads {}; split the input and scan it with --batch, or \
+                 use fewer --threads",
+                format_bytes(budget),
+                format_bytes(self.input_bytes),
+                rayon::current_num_threads(),
+                format_bytes(self.landscape_bytes())
+            ));
+        }
+        if !streamable {
+            return Err(format!(
+                "the ~{} expected hits need {}, more than the {} left by --max-memory {}; \
+                 .bed, .bb and SQLite outputs and --checkpoint keep all hits in memory, \
+                 write .csv, .jsonl or .parquet outputs to stream them in chunks",
+                self.hits,
+                format_bytes(2 * self.hit_bytes),
+                format_bytes(budget - fixed),
+                format_bytes(budget)
+            ));
+        }
+        let rows = self.sequences as f64 * (budget - fixed) as f64 / (2 * self.hit_bytes) as f64;
+        Ok(MemoryPlan::Stream {
+            chunk_size: (rows as usize).max(1),
+        })
+    }
+}
+
+/// How a run fits its memory budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryPlan {
+    /// Collect all hits, then write the outputs
+    InMemory,
+    /// Scan chunks of `chunk_size` sequences and append their hits to the outputs
+    Stream { chunk_size: usize },
+}
+
+/// Parses a byte count with an optional unit: `500000`, `512M`, `8G`, `1.5GiB`
+pub fn parse_size(text: &str) -> Result<usize, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{}', expected e.g. 512M or 8G", text))?;
+    let factor: u64 = match unit.trim().to_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        "T" | "TB" | "TIB" => 1 << 40,
+        _ => {
+            return Err(format!(
+                "invalid size unit '{}', expected K, M, G or T",
+                unit
+            ))
+        }
+    };
+    Ok((number * factor as f64) as usize)
+}
+
+/// Formats a byte count with a binary unit, e.g. `1.5 GiB`
+pub fn format_bytes(bytes: usize) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < units.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, units[unit])
+    }
+}
+
+/// Formats a duration in seconds as e.g. `42 s`, `12.5 min` or `3.2 h`
+fn format_seconds(seconds: f64) -> String {
+    if seconds < 60.0 {
+        format!("{:.1} s", seconds)
+    } else if seconds < 3600.0 {
+        format!("{:.1} min", seconds / 60.0)
+    } else {
+        format!("{:.1} h", seconds / 3600.0)
+    }
+}
+
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "sequences:      {}", self.sequences)?;
+        writeln!(f, "bases:          {}", self.bases)?;
+        writeln!(f, "motifs:         {}", self.motifs)?;
+        writeln!(f, "work:           {} bp·motifs", self.work)?;
+        writeln!(f, "expected hits:  {}", self.hits)?;
+        writeln!(
+            f,
+            "peak memory:    {} (input {}, hits {})",
+            format_bytes(self.peak_bytes()),
+            format_bytes(self.input_bytes),
+            format_bytes(self.hit_bytes)
+        )?;
+        write!(f, "scan time:      {}", format_seconds(self.seconds))
+    }
+}
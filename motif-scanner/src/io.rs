@@ -0,0 +1,236 @@
+use polars::prelude::*;
+use std::path::Path;
+use tf_binding_rs::output::write_jsonl_file;
+use tf_binding_rs::schema::SCHEMA_VERSION;
+
+fn extension(path: &str) -> Option<&str> {
+    Path::new(path).extension().and_then(|ext| ext.to_str())
+}
+
+/// Lazily reads a CSV or Parquet table, chosen by file extension. CSV columns get the
+/// types recorded in a `{path}.schema.json` sidecar when there is one.
+pub fn read_table(path: &str) -> PolarsResult<LazyFrame> {
+    match extension(path) {
+        Some("parquet") => LazyFrame::scan_parquet(path, ScanArgsParquet::default()),
+        Some("csv") => LazyCsvReader::new(path)
+            .with_has_header(true)
+            .with_dtype_overwrite(read_schema(path)?.map(Arc::new))
+            .finish(),
+        _ => Err(polars_err!(
+            ComputeError: "unsupported table format of '{}', expected .csv or .parquet", path
+        )),
+    }
+}
+
+/// Whether a data file is gzip-compressed, by its `.gz` extension
+pub fn is_gzip(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "gz")
+}
+
+/// Extension of a data file ignoring a `.gz` suffix, e.g. `csv` for `oligos.csv.gz`
+pub fn data_extension(path: &Path) -> Option<&str> {
+    let path = if is_gzip(path) {
+        Path::new(path.file_stem()?)
+    } else {
+        path
+    };
+    path.extension().and_then(|ext| ext.to_str())
+}
+
+/// File stem of a data file ignoring a `.gz` suffix, e.g. `oligos` for `oligos.csv.gz`
+pub fn data_stem(path: &Path) -> Option<&str> {
+    let path = if is_gzip(path) {
+        Path::new(path.file_stem()?)
+    } else {
+        path
+    };
+    path.file_stem().and_then(|stem| stem.to_str())
+}
+
+/// Field separator of a delimited table: a tab for `.tsv` files and a comma otherwise
+pub fn default_separator(path: &str) -> u8 {
+    match data_extension(Path::new(path)) {
+        Some("tsv") => b'\t',
+        _ => b',',
+    }
+}
+
+/// Parses a field separator: one ASCII character, or `tab` (also `\t`)
+pub fn parse_separator(s: &str) -> Result<u8, String> {
+    match s {
+        "tab" | "\\t" | "\t" => Ok(b'\t'),
+        _ if s.len() == 1 && s.is_ascii() => Ok(s.as_bytes()[0]),
+        _ => Err(format!(
+            "invalid separator '{}', expected one ASCII character or 'tab'",
+            s
+        )),
+    }
+}
+
+/// Reads a delimited table with a header, split on `separator` or the
+/// `default_separator()` of its extension. Gzip-compressed tables (`.csv.gz`, `.tsv.gz`)
+/// are decompressed in memory, so they do not have to be unpacked to disk first.
+pub fn read_delimited(path: &str, separator: Option<u8>) -> PolarsResult<LazyFrame> {
+    let separator = separator.unwrap_or_else(|| default_separator(path));
+    if is_gzip(Path::new(path)) {
+        return Ok(CsvReadOptions::default()
+            .with_has_header(true)
+            .map_parse_options(|options| options.with_separator(separator))
+            .try_into_reader_with_file_path(Some(path.into()))?
+            .finish()?
+            .lazy());
+    }
+    LazyCsvReader::new(path)
+        .with_has_header(true)
+        .with_separator(separator)
+        .finish()
+}
+
+/// Path of the schema sidecar of an output table
+pub fn schema_path(path: &str) -> String {
+    format!("{}.schema.json", path)
+}
+
+/// Stable names of the column types in schema sidecars
+const SCHEMA_TYPES: [(&str, DataType); 7] = [
+    ("string", DataType::String),
+    ("bool", DataType::Boolean),
+    ("int32", DataType::Int32),
+    ("int64", DataType::Int64),
+    ("uint32", DataType::UInt32),
+    ("uint64", DataType::UInt64),
+    ("float64", DataType::Float64),
+];
+
+/// Writes the `{path}.schema.json` sidecar describing the columns of `df`, a table of
+/// `rows` rows: `{"output": path, "schema_version": V, "rows": N, "columns": [{"name": ...,
+/// "type": ...}, ...]}`, where V is the hit table schema version of this crate
+pub fn write_schema(df: &DataFrame, rows: usize, path: &str) -> PolarsResult<()> {
+    let columns: Vec<serde_json::Value> = df
+        .get_columns()
+        .iter()
+        .map(|column| {
+            let name = SCHEMA_TYPES
+                .iter()
+                .find(|(_, dtype)| dtype == column.dtype())
+                .map_or_else(|| column.dtype().to_string(), |(name, _)| name.to_string());
+            serde_json::json!({ "name": column.name().as_str(), "type": name })
+        })
+        .collect();
+    let schema = serde_json::json!({
+        "output": path,
+        "schema_version": SCHEMA_VERSION,
+        "rows": rows,
+        "columns": columns,
+    });
+    let text =
+        serde_json::to_string_pretty(&schema).map_err(|e| polars_err!(ComputeError: "{}", e))?;
+    std::fs::write(schema_path(path), text + "\n")?;
+    Ok(())
+}
+
+/// Reads the column types of the `{path}.schema.json` sidecar, if it exists. Columns of
+/// other types than those written by `write_schema` are left to inference.
+pub fn read_schema(path: &str) -> PolarsResult<Option<Schema>> {
+    let sidecar = schema_path(path);
+    if !Path::new(&sidecar).exists() {
+        return Ok(None);
+    }
+    let text = std::fs::read_to_string(&sidecar)?;
+    let value: serde_json::Value = serde_json::from_str(&text)
+        .map_err(|e| polars_err!(ComputeError: "invalid schema file '{}': {}", sidecar, e))?;
+    let columns = value["columns"].as_array().ok_or_else(
+        || polars_err!(ComputeError: "schema file '{}' has no 'columns' list", sidecar),
+    )?;
+
+    let mut schema = Schema::default();
+    for column in columns {
+        let (Some(name), Some(type_name)) = (column["name"].as_str(), column["type"].as_str())
+        else {
+            continue;
+        };
+        if let Some((_, dtype)) = SCHEMA_TYPES.iter().find(|(n, _)| *n == type_name) {
+            schema.with_column(name.into(), dtype.clone());
+        }
+    }
+    Ok(Some(schema))
+}
+
+/// Reads the hit table schema version recorded in the `{path}.schema.json` sidecar, if it
+/// exists; sidecars of versions before schema versioning have none
+pub fn read_schema_version(path: &str) -> PolarsResult<Option<u32>> {
+    let sidecar = schema_path(path);
+    if !Path::new(&sidecar).exists() {
+        return Ok(None);
+    }
+    let text = std::fs::read_to_string(&sidecar)?;
+    let value: serde_json::Value = serde_json::from_str(&text)
+        .map_err(|e| polars_err!(ComputeError: "invalid schema file '{}': {}", sidecar, e))?;
+    Ok(value["schema_version"].as_u64().map(|v| v as u32))
+}
+
+/// Writes a table as Parquet (snappy), JSON Lines (.jsonl/.ndjson) or CSV (anything else),
+/// chosen by file extension
+pub fn write_table(df: &mut DataFrame, path: &str) -> PolarsResult<()> {
+    match extension(path) {
+        Some("jsonl" | "ndjson") => {
+            write_jsonl_file(df, path).map_err(|e| polars_err!(ComputeError: "{}", e))?;
+        }
+        Some("parquet") => write_parquet(df, path, ParquetCompression::Snappy, None)?,
+        _ => {
+            let mut file = std::fs::File::create(path)?;
+            CsvWriter::new(&mut file).include_header(true).finish(df)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes a table as Parquet with the given compression and, unless `None`, row groups of
+/// `row_group_size` rows
+pub fn write_parquet(
+    df: &mut DataFrame,
+    path: &str,
+    compression: ParquetCompression,
+    row_group_size: Option<usize>,
+) -> PolarsResult<()> {
+    let mut file = std::fs::File::create(path)?;
+    ParquetWriter::new(&mut file)
+        .with_compression(compression)
+        .with_row_group_size(row_group_size)
+        .finish(df)?;
+    Ok(())
+}
+
+/// Parquet codec by name (`zstd`, `snappy`, `gzip`, `brotli`, `lz4` or `uncompressed`), with
+/// an optional level for zstd, gzip and brotli
+pub fn parquet_compression(name: &str, level: Option<i32>) -> PolarsResult<ParquetCompression> {
+    let no_level = |codec: ParquetCompression| match level {
+        Some(_) => Err(polars_err!(
+            ComputeError: "compression '{}' does not take a level", name
+        )),
+        None => Ok(codec),
+    };
+    match name.to_lowercase().as_str() {
+        "zstd" => Ok(ParquetCompression::Zstd(
+            level.map(ZstdLevel::try_new).transpose()?,
+        )),
+        "gzip" => Ok(ParquetCompression::Gzip(
+            level
+                .map(|l| GzipLevel::try_new(u8::try_from(l).unwrap_or(u8::MAX)))
+                .transpose()?,
+        )),
+        "brotli" => Ok(ParquetCompression::Brotli(
+            level
+                .map(|l| BrotliLevel::try_new(u32::try_from(l).unwrap_or(u32::MAX)))
+                .transpose()?,
+        )),
+        "snappy" => no_level(ParquetCompression::Snappy),
+        "lz4" => no_level(ParquetCompression::Lz4Raw),
+        "uncompressed" | "none" => no_level(ParquetCompression::Uncompressed),
+        _ => Err(polars_err!(
+            ComputeError:
+            "unknown compression '{}', expected zstd, snappy, gzip, brotli, lz4 or uncompressed",
+            name
+        )),
+    }
+}
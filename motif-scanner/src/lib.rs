@@ -0,0 +1,4 @@
+//! Shared code of the motif-scanner and tfbind command line tools
+
+pub mod io;
+pub mod logging;
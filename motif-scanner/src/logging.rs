@@ -0,0 +1,29 @@
+use clap::ValueEnum;
+use tracing_subscriber::EnvFilter;
+
+/// Format of log lines written to stderr
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, for pipeline monitoring
+    Json,
+}
+
+/// Installs the global tracing subscriber, logging to stderr.
+///
+/// `level` is a level (`error`, `warn`, `info`, `debug`, `trace`) or a full filter
+/// directive such as `info,tf_binding_rs=debug`.
+pub fn init_logging(level: &str, format: LogFormat) -> Result<(), String> {
+    let filter = EnvFilter::try_new(level).map_err(|e| format!("invalid log level: {}", e))?;
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+
+    match format {
+        LogFormat::Text => builder.with_target(false).try_init(),
+        LogFormat::Json => builder.json().try_init(),
+    }
+    .map_err(|e| e.to_string())
+}
@@ -1,10 +1,58 @@
+use checkpoint::Checkpoint;
 use clap::Parser;
+use config::{MotifParams, RunConfig};
+use estimate::{format_bytes, Estimate, MemoryPlan};
+use motif_scanner::io::{self, schema_path, write_parquet, write_schema, write_table};
+use motif_scanner::logging::{init_logging, LogFormat};
 use polars::prelude::*;
+use profile::ScanProfile;
 use rayon::prelude::*;
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
-use tf_binding_rs::occupancy::{read_pwm_to_ewm, total_landscape};
-use tf_binding_rs::types::EWMCollection;
+use std::process::ExitCode;
+use std::time::Instant;
+use summary::{HitSummary, RunSummary};
+use tf_binding_rs::annotations::TfAnnotations;
+use tf_binding_rs::cache::load_cached;
+use tf_binding_rs::calibration::calibrate_cutoff;
+use tf_binding_rs::collection::{deduplicate, sample_motifs};
+#[cfg(feature = "bigbed")]
+use tf_binding_rs::coords::read_chrom_sizes;
+use tf_binding_rs::coords::{parse_labels, project_hits, LabelParser};
+use tf_binding_rs::error::MotifError;
+use tf_binding_rs::fasta::{
+    normalize_sequences, read_fasta_normalized, rename_input_columns, resolve_duplicate_labels,
+    reverse_complement, sample_sequences, DuplicateLabels, InputColumns, Normalization,
+};
+use tf_binding_rs::occupancy::{
+    read_pwm_files, read_pwm_to_ewm_with, EwmOptions, LazyEwmCollection, StrandedEwm,
+};
+use tf_binding_rs::output::write_bed;
+#[cfg(feature = "bigbed")]
+use tf_binding_rs::output::write_bigbed;
+use tf_binding_rs::provenance::{manifest_path, RunManifest};
+use tf_binding_rs::pvalue::{
+    score_distributions, ScoreDistribution, DEFAULT_STEP, UNIFORM_BACKGROUND, WINDOWS_PER_KB,
+};
+use tf_binding_rs::quantized::{QuantizedEwm, StrandHits};
+use tf_binding_rs::scan::{
+    check_sequence, unambiguous_segments, warnings_frame, AmbiguousPolicy, ScanResult, ScanWarning,
+    SkipReason,
+};
+use tf_binding_rs::schema::SCHEMA_VERSION;
+use tf_binding_rs::types::{EWMCollection, EWM};
+use tracing::{info, warn};
+
+mod batch;
+mod checkpoint;
+mod config;
+mod estimate;
+mod profile;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod stream;
+mod summary;
 
 #[derive(thiserror::Error, Debug)]
 pub enum ScannerError {
@@ -22,6 +70,55 @@ pub enum ScannerError {
 
     #[error("PWM processing error: {0}")]
     PwmError(String),
+
+    #[cfg(feature = "sqlite")]
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error("Input error: {0}")]
+    Input(String),
+
+    #[error("Batch error: {0}")]
+    Batch(String),
+
+    #[error("Config error: {0}")]
+    Config(String),
+
+    #[error("Checkpoint error: {0}")]
+    Checkpoint(String),
+
+    #[error("Output error: {0}")]
+    Output(String),
+
+    #[error("Unsupported output format: {0}")]
+    UnsupportedOutput(String),
+
+    #[error("No hits found (--fail-empty)")]
+    EmptyResults,
+
+    #[error("Memory budget exceeded: {0}")]
+    Memory(String),
+}
+
+impl ScannerError {
+    /// Process exit code of the error class, stable across releases so workflow
+    /// engines can branch on it; 2 is left to clap's usage errors.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ScannerError::Config(_) => 3,
+            ScannerError::InvalidMotifFormat | ScannerError::PwmError(_) => 4,
+            ScannerError::MissingSequenceColumn | ScannerError::Input(_) => 5,
+            ScannerError::Output(_) | ScannerError::UnsupportedOutput(_) => 6,
+            #[cfg(feature = "sqlite")]
+            ScannerError::Sqlite(_) => 6,
+            ScannerError::EmptyResults => 7,
+            ScannerError::Memory(_) => 8,
+            ScannerError::Io(_)
+            | ScannerError::Polars(_)
+            | ScannerError::Batch(_)
+            | ScannerError::Checkpoint(_) => 1,
+        }
+    }
 }
 
 #[derive(Parser)]
@@ -35,201 +132,1674 @@ pub enum ScannerError {
     version,
     after_help = "Example usage:\n    \
                   motif-scanner data.csv motifs.meme results.parquet --cutoff 0.3 --mu 12\n    \
-                  motif-scanner sequences.csv pwm.meme output.csv",
+                  motif-scanner sequences.csv pwm.meme output.csv\n    \
+                  motif-scanner --config run.toml --threads 16\n    \
+                  motif-scanner --batch 'libraries/*.fasta' results/ --batch-format csv\n\n\
+                  Exit codes:\n    \
+                  0  success\n    \
+                  1  other failure (I/O, batch, checkpoint)\n    \
+                  2  invalid command line\n    \
+                  3  invalid configuration\n    \
+                  4  invalid motif file\n    \
+                  5  invalid input (unreadable, missing or mistyped columns)\n    \
+                  6  output could not be written\n    \
+                  7  no hits, with --fail-empty\n    \
+                  8  the run does not fit --max-memory",
     color = clap::ColorChoice::Always
 )]
 #[derive(Debug)]
 struct Args {
-    /// Path to input data file (CSV format)
-    /// Must contain a 'sequence' column with DNA sequences
-    #[arg(value_name = "DATA_FILE")]
-    data_file: String,
+    /// Path to input data file (CSV, TSV, Parquet or FASTA format; CSV and TSV may be gzipped)
+    /// CSV files must contain a 'sequence' column with DNA sequences; with
+    /// --batch, a directory or glob pattern of input files
+    #[arg(value_name = "DATA_FILE", required_unless_present = "config")]
+    data_file: Option<String>,
 
     /// Path to .meme format file containing Position Weight Matrices (PWMs)
     /// for the motifs to be scanned
-    #[arg(value_name = "PWM_FILE")]
-    pwm_file: String,
+    #[arg(value_name = "PWM_FILE", required_unless_present = "config")]
+    pwm_file: Option<String>,
 
-    /// Path for output file (supports .csv or .parquet format)
+    /// Path for output file (supports .csv, .parquet or .jsonl format, and
+    /// .sqlite/.db when built with the `sqlite` feature); with --batch, the
+    /// output directory
     /// Will create output directory if it doesn't exist
-    #[arg(value_name = "OUTPUT_FILE")]
-    output_file: String,
+    #[arg(value_name = "OUTPUT_FILE", required_unless_present = "config")]
+    output_file: Option<String>,
+
+    /// TOML or YAML file describing the run (inputs, motif subset, per-motif
+    /// mu/cutoff, extra outputs, threads); command line flags take precedence
+    #[arg(long, value_name = "CONFIG_FILE")]
+    config: Option<String>,
 
-    /// Minimum predicted occupancy threshold
+    /// Minimum predicted occupancy threshold [default: 0.2]
     /// Only motifs with occupancy scores above this value will be included
     /// in the output
-    #[arg(long, default_value = "0.2")]
-    cutoff: f64,
+    #[arg(long)]
+    cutoff: Option<f64>,
 
-    /// Predicted affinity parameter (mu) of transcription factor to motif
+    /// Background sequences (CSV, Parquet or FASTA) on which a cutoff is calibrated per
+    /// motif for --fp-rate, replacing the global --cutoff
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "fp_rate",
+        conflicts_with = "cutoff"
+    )]
+    background: Option<String>,
+
+    /// Expected false-positive hits per kb of --background (both strands) at the
+    /// calibrated cutoff of every motif, e.g. 0.1
+    #[arg(long, value_name = "PER_KB", requires = "background")]
+    fp_rate: Option<f64>,
+
+    /// Predicted affinity parameter (mu) of transcription factor to motif [default: 9]
     /// Higher values indicate stronger binding affinity
-    #[arg(long, default_value = "9")]
-    mu: i32,
+    #[arg(long)]
+    mu: Option<f64>,
+
+    /// Temperature (°C) of the energy conversion: energies are -RT ln(p / p_max)
+    /// [default: RT = 2.5 kJ/mol, about 28 °C]
+    #[arg(long, value_name = "CELSIUS", allow_negative_numbers = true)]
+    temperature: Option<f64>,
+
+    /// Binary cache (.tfbm) of the parsed and converted motifs: read when it matches the
+    /// motif file and conversion options, and (re)written otherwise
+    #[arg(long, value_name = "FILE")]
+    motif_cache: Option<String>,
+
+    /// Only scan these motifs (comma-separated full IDs or names before the first '_')
+    #[arg(long, value_delimiter = ',')]
+    motifs: Option<Vec<String>>,
+
+    /// Reject most windows with integer-quantized energies before scoring the
+    /// rest exactly; the hits are identical, and selective cutoffs scan faster
+    #[arg(long)]
+    quantized: bool,
+
+    /// Add `pvalue` and `neg_log10_pvalue` columns: the probability that a
+    /// random window (uniform background) reaches the hit's occupancy, and
+    /// `background_per_kb`, the expected number of such windows per kb
+    #[arg(long)]
+    pvalues: bool,
+
+    /// Copy these input columns (comma-separated, e.g. name,group) into the
+    /// hit table, matched by sequence; names clashing with hit columns get an
+    /// `input_` prefix
+    #[arg(long, value_delimiter = ',', value_name = "COLUMNS")]
+    carry: Option<Vec<String>>,
+
+    /// CSV or TSV table of motif_id and any of tf_name, family, role and
+    /// concentration, joined onto the hits by motif ID or name
+    #[arg(long, value_name = "FILE")]
+    tf_annotations: Option<String>,
+
+    /// Columns of the hit table, in order (comma-separated): label, position,
+    /// motif, strand, length, occupancy, energy, relative_score, matched_seq,
+    /// pvalue, neg_log10_pvalue, background_per_kb and the --genomic, --carry and --tf-annotations
+    /// columns; .bed and
+    /// .bb outputs keep their fixed fields
+    #[arg(long, value_delimiter = ',', value_name = "COLUMNS")]
+    columns: Option<Vec<String>>,
+
+    /// Sort the hits of every output by these columns (comma-separated), e.g.
+    /// motif,position, so Parquet readers can skip row groups by their min/max
+    /// statistics; streamed runs sort each chunk
+    #[arg(long, value_delimiter = ',', value_name = "COLUMNS")]
+    sort_by: Option<Vec<String>>,
+
+    /// Rows per row group of Parquet outputs [default: polars' 512^2]
+    #[arg(long, value_name = "ROWS")]
+    row_group_size: Option<usize>,
+
+    /// Add genomic coordinates of the hits (chrom, genomic_start, genomic_end,
+    /// genomic_strand), parsed from input labels such as chr1-4357766-4357930_WT
+    /// or chr1:100-200(-); required for .bed output
+    #[arg(long)]
+    genomic: bool,
+
+    /// Regular expression with named groups chrom, start, end and optionally
+    /// strand and suffix, replacing the default label pattern of --genomic
+    #[arg(long, value_name = "REGEX", requires = "genomic")]
+    label_pattern: Option<String>,
+
+    /// Chromosome sizes file (chrom<TAB>size per line), required for .bb (bigBed)
+    /// output
+    #[arg(long, value_name = "FILE")]
+    chrom_sizes: Option<String>,
+
+    /// Label starts are 1-based (browser positions) instead of 0-based (BED)
+    #[arg(long, requires = "genomic")]
+    one_based_labels: bool,
+
+    /// What to do with records repeating an earlier label: keep, error,
+    /// keep-first (the others are reported as skipped) or suffix (renamed to
+    /// label_2, label_3, ...) [default: keep]
+    #[arg(long, value_name = "POLICY")]
+    duplicate_labels: Option<DuplicateLabels>,
+
+    /// What to do with sequences containing bases other than A, C, G, T: drop (skip the
+    /// sequence), mask (scan only windows without them) or scan-lenient (score IUPAC
+    /// codes such as N and Y with the mean energy of their bases) [default: drop]
+    #[arg(long, value_name = "POLICY")]
+    ambiguous_policy: Option<AmbiguousPolicy>,
+
+    /// Keep the case of the input bases instead of uppercasing them, so soft-masked
+    /// (lowercase) repeats count as ambiguous bases and follow --ambiguous-policy
+    #[arg(long)]
+    keep_case: bool,
+
+    /// Fail on the first sequence character that is not an IUPAC nucleotide code, naming
+    /// the record and the offset of the character, instead of skipping the sequence
+    #[arg(long)]
+    validate_sequences: bool,
+
+    /// Column of a CSV or Parquet input holding the sequences [default: sequence]
+    #[arg(long, value_name = "COLUMN")]
+    sequence_col: Option<String>,
+
+    /// Column of a CSV or Parquet input identifying the records; its values label the hits
+    /// instead of the row index, and the skipped records instead of the 'label' column
+    #[arg(long, value_name = "COLUMN")]
+    id_col: Option<String>,
+
+    /// Field separator of a CSV or TSV input, one character or 'tab' [default: tab for
+    /// .tsv and .tsv.gz files, comma otherwise]
+    #[arg(long, value_name = "CHAR", value_parser = io::parse_separator)]
+    separator: Option<u8>,
+
+    /// Drop motifs whose total information content is below BITS
+    #[arg(long, value_name = "BITS")]
+    min_ic: Option<f64>,
+
+    /// Drop motifs longer than N positions
+    #[arg(long, value_name = "N")]
+    max_width: Option<usize>,
+
+    /// Only warn about motifs failing --min-ic/--max-width and scan them anyway
+    #[arg(long)]
+    keep_filtered: bool,
+
+    /// Scan identical and reverse-complementary motifs only once, under the
+    /// first ID in sorted order; merges are logged
+    #[arg(long)]
+    dedupe: bool,
+
+    /// Largest per-cell PWM difference still considered a duplicate [default: 0.01]
+    #[arg(long, requires = "dedupe")]
+    dedupe_tolerance: Option<f64>,
+
+    /// Scan only N motifs drawn at random (after --motifs and the filters), for a quick
+    /// exploratory run; the same motif file, N and --seed give the same motifs
+    #[arg(long, value_name = "N")]
+    sample_motifs: Option<usize>,
+
+    /// Scan only N sequences drawn at random from each input, kept in input order; the
+    /// same input, N and --seed give the same sequences
+    #[arg(long, value_name = "N")]
+    sample_sequences: Option<usize>,
+
+    /// Seed of --sample-motifs and --sample-sequences [default: 0]
+    #[arg(long, value_name = "S")]
+    seed: Option<u64>,
+
+    /// Number of threads used for scanning [default: all cores]
+    #[arg(long)]
+    threads: Option<usize>,
+
+    /// Also store run metadata (inputs, parameters, version) in the output
+    /// database; only used for SQLite output
+    #[arg(long)]
+    metadata: bool,
+
+    /// Skip writing the `{OUTPUT_FILE}.manifest.json` provenance sidecar
+    /// (tool version, parameters, input hashes, wall time)
+    #[arg(long)]
+    no_manifest: bool,
+
+    /// Also write `{OUTPUT_FILE}.schema.json`, the column names and types of the hit
+    /// table, so workflow engines can validate the output
+    #[arg(long)]
+    output_schema: bool,
+
+    /// Exit with code 7 when the scan finds no hits (outputs are still written)
+    #[arg(long)]
+    fail_empty: bool,
+
+    /// Write `{OUTPUT_FILE}.profile.csv` with the time spent, windows scanned and hits
+    /// produced per motif, and log the slowest motifs
+    #[arg(long)]
+    profile: bool,
+
+    /// Also write the end-of-run summary (per-motif hits, mean occupancy, sequences with
+    /// hits and time per stage) to `{OUTPUT_FILE}.summary.json`
+    #[arg(long)]
+    summary_json: bool,
+
+    /// Parse the motif file, validate the inputs and print the estimated memory and
+    /// run time of the scan, then exit without scanning
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Memory budget, e.g. 8G or 512M: hits are streamed to the outputs in chunks when
+    /// they would not fit, and runs that cannot fit are refused before scanning
+    #[arg(long, value_name = "SIZE", value_parser = estimate::parse_size)]
+    max_memory: Option<usize>,
+
+    /// Read a CSV or Parquet DATA_FILE N records at a time, filtering and scanning every
+    /// chunk and appending its hits to the outputs, so memory stays flat however large the
+    /// input; duplicate labels are only resolved within a chunk
+    #[arg(long, value_name = "N")]
+    chunk_rows: Option<usize>,
+
+    /// Save progress every N sequences to `{OUTPUT_FILE}.checkpoint`, so an
+    /// interrupted run can be continued with --resume
+    #[arg(long, value_name = "N")]
+    checkpoint: Option<usize>,
+
+    /// Continue an interrupted run from its checkpoint (requires a checkpoint
+    /// size and the same inputs and parameters as the interrupted run)
+    #[arg(long)]
+    resume: bool,
+
+    /// Process every CSV/FASTA file of the DATA_FILE directory or glob in
+    /// parallel, writing one output per input and a summary.csv into the
+    /// OUTPUT_FILE directory
+    #[arg(long)]
+    batch: bool,
+
+    /// Output format (file extension) of each input in batch mode [default: parquet]
+    #[arg(long, value_name = "EXT")]
+    batch_format: Option<String>,
+
+    /// Log level (error, warn, info, debug, trace) or filter directive such as
+    /// "info,tf_binding_rs=debug"
+    #[arg(long, default_value = "info")]
+    log_level: String,
+
+    /// Format of the log lines written to stderr
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
 }
 
-trait UnzipN<A, B, C, D, E, F> {
-    fn unzip_n_vec(self) -> (Vec<A>, Vec<B>, Vec<C>, Vec<D>, Vec<E>, Vec<F>);
+/// Column holding the row of each sequence in the input file
+const INPUT_ROW: &str = "input_row";
+
+#[allow(clippy::type_complexity)]
+trait UnzipN<A, B, C, D, E, F, G> {
+    fn unzip_n_vec(self) -> (Vec<A>, Vec<B>, Vec<C>, Vec<D>, Vec<E>, Vec<F>, Vec<G>);
 }
 
-impl<I, A, B, C, D, E, F> UnzipN<A, B, C, D, E, F> for I
+impl<I, A, B, C, D, E, F, G> UnzipN<A, B, C, D, E, F, G> for I
 where
-    I: Iterator<Item = (A, B, C, D, E, F)>,
+    I: Iterator<Item = (A, B, C, D, E, F, G)>,
 {
-    fn unzip_n_vec(self) -> (Vec<A>, Vec<B>, Vec<C>, Vec<D>, Vec<E>, Vec<F>) {
+    fn unzip_n_vec(self) -> (Vec<A>, Vec<B>, Vec<C>, Vec<D>, Vec<E>, Vec<F>, Vec<G>) {
         let mut a_vec = Vec::new();
         let mut b_vec = Vec::new();
         let mut c_vec = Vec::new();
         let mut d_vec = Vec::new();
         let mut e_vec = Vec::new();
         let mut f_vec = Vec::new();
+        let mut g_vec = Vec::new();
 
-        for (a, b, c, d, e, f) in self {
+        for (a, b, c, d, e, f, g) in self {
             a_vec.push(a);
             b_vec.push(b);
             c_vec.push(c);
             d_vec.push(d);
             e_vec.push(e);
             f_vec.push(f);
+            g_vec.push(g);
+        }
+
+        (a_vec, b_vec, c_vec, d_vec, e_vec, f_vec, g_vec)
+    }
+}
+
+/// How windows are scored, shared by every chunk of a run
+#[derive(Debug, Clone, Copy, Default)]
+struct Scoring<'a> {
+    /// Prefilter windows with integer-quantized energies
+    quantized: bool,
+    /// Energy distributions of the motifs, to report p-values
+    distributions: Option<&'a HashMap<String, ScoreDistribution>>,
+    /// Report the binding energy and relative score of every hit
+    energies: bool,
+    /// Report the sequence of every hit window
+    matched_seq: bool,
+    /// Per-motif time and hit counts, with --profile
+    profile: Option<&'a ScanProfile>,
+    /// How sequences with bases other than A, C, G, T are scanned
+    ambiguous: AmbiguousPolicy,
+}
+
+/// Values of a hit that are only computed when their columns are reported
+#[derive(Debug, Default)]
+struct HitDetails {
+    pvalue: Option<f64>,
+    energy: Option<f64>,
+    relative_score: Option<f64>,
+    matched_seq: Option<String>,
+}
+
+/// A motif prepared for scanning, either exactly or with a quantized prefilter
+enum ScanMatrix {
+    Exact(StrandedEwm),
+    Quantized(QuantizedEwm),
+}
+
+impl ScanMatrix {
+    fn new(ewm: &EWM, quantized: bool) -> Result<Self, MotifError> {
+        Ok(if quantized {
+            ScanMatrix::Quantized(QuantizedEwm::new(ewm)?)
+        } else {
+            ScanMatrix::Exact(StrandedEwm::new(ewm)?)
+        })
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            ScanMatrix::Exact(matrix) => matrix.len(),
+            ScanMatrix::Quantized(matrix) => matrix.len(),
+        }
+    }
+
+    /// The exact matrix, which gives the energies of the hits
+    fn exact(&self) -> &StrandedEwm {
+        match self {
+            ScanMatrix::Exact(matrix) => matrix,
+            ScanMatrix::Quantized(matrix) => matrix.exact(),
         }
+    }
 
-        (a_vec, b_vec, c_vec, d_vec, e_vec, f_vec)
+    /// (position, occupancy) of the windows above `cutoff` on each strand of a sequence with
+    /// ambiguous bases, scanned according to `policy`
+    fn ambiguous_hits(
+        &self,
+        seq: &str,
+        mu: f64,
+        cutoff: f64,
+        policy: AmbiguousPolicy,
+    ) -> Result<StrandHits, MotifError> {
+        match policy {
+            AmbiguousPolicy::Drop => self.hits(seq, mu, cutoff),
+            AmbiguousPolicy::Mask => {
+                let mut hits: StrandHits = (Vec::new(), Vec::new());
+                for (start, end) in unambiguous_segments(seq, self.len()) {
+                    let (forward, reverse) = self.hits(&seq[start..end], mu, cutoff)?;
+                    hits.0
+                        .extend(forward.into_iter().map(|(pos, occ)| (start + pos, occ)));
+                    hits.1
+                        .extend(reverse.into_iter().map(|(pos, occ)| (start + pos, occ)));
+                }
+                Ok(hits)
+            }
+            AmbiguousPolicy::ScanLenient => {
+                let (fscores, rscores) = self.exact().lenient_energy_landscape(seq)?;
+                let above = |energies: Vec<f64>| {
+                    energies
+                        .into_iter()
+                        .map(|e| 1.0 / (1.0 + (e - mu).exp()))
+                        .enumerate()
+                        .filter(|&(_, occ)| occ > cutoff)
+                        .collect()
+                };
+                Ok((above(fscores), above(rscores)))
+            }
+        }
+    }
+
+    /// (position, occupancy) of the windows above `cutoff` on each strand
+    fn hits(&self, seq: &str, mu: f64, cutoff: f64) -> Result<StrandHits, MotifError> {
+        match self {
+            ScanMatrix::Exact(matrix) => {
+                let (focc, rocc) = matrix.occupancy_landscape(seq, mu)?;
+                let above = |occs: Vec<f64>| {
+                    occs.into_iter()
+                        .enumerate()
+                        .filter(|&(_, occ)| occ > cutoff)
+                        .collect()
+                };
+                Ok((above(focc), above(rocc)))
+            }
+            ScanMatrix::Quantized(matrix) => matrix.hits(seq, mu, cutoff),
+        }
     }
 }
 
+/// Input row and label of the scanned sequences, for reporting skipped records
+fn row_info(df: &DataFrame, offset: usize) -> Result<Vec<(usize, Option<String>)>, ScannerError> {
+    let rows: Vec<usize> = match df.column(INPUT_ROW) {
+        Ok(rows) => rows
+            .idx()?
+            .into_iter()
+            .map(|row| row.unwrap_or_default() as usize)
+            .collect(),
+        Err(_) => (offset..offset + df.height()).collect(),
+    };
+    let labels: Vec<Option<String>> = match df.column("label").and_then(|c| c.str().cloned()) {
+        Ok(labels) => labels.into_iter().map(|l| l.map(str::to_string)).collect(),
+        Err(_) => vec![None; df.height()],
+    };
+    Ok(rows.into_iter().zip(labels).collect())
+}
+
+/// Scans sequences for hits with per-motif parameters; labels are row indices shifted by
+/// `offset`. Sequences with invalid bases and motifs longer than a sequence are skipped and
+/// returned as warnings.
+#[allow(clippy::type_complexity)]
 fn process_sequences(
     df: &DataFrame,
     ewm: &EWMCollection,
-    mu: f64,
-    cutoff: f64,
-) -> Result<DataFrame, ScannerError> {
+    params: &HashMap<String, MotifParams>,
+    offset: usize,
+    scoring: Scoring,
+) -> Result<ScanResult, ScannerError> {
     let sequences = df
         .column("sequence")
         .map_err(|_| ScannerError::MissingSequenceColumn)?;
 
-    let total_seqs = sequences.len();
-    println!("{} sequences to scan", total_seqs);
-
     // convert ChunkedArray<String> to Vec<String> for parallel processing
-    let sequences_vec: Vec<_> = sequences.str()?.into_iter().collect();
+    let sequences_vec: Vec<_> = sequences
+        .str()?
+        .into_iter()
+        .zip(row_info(df, offset)?)
+        .collect();
+
+    // prepare every matrix and its reverse complement once, not per sequence
+    let matrices = ewm
+        .iter()
+        .map(|(motif_id, motif_df)| {
+            ScanMatrix::new(motif_df, scoring.quantized)
+                .map(|matrix| (motif_id, matrix))
+                .map_err(|e| ScannerError::PwmError(e.to_string()))
+        })
+        .collect::<Result<Vec<_>, ScannerError>>()?;
 
     // Parallel processing of sequences
     let results: Vec<_> = sequences_vec
         .into_par_iter()
         .enumerate()
-        .filter_map(|(idx, seq)| {
-            seq.map(|sequence| {
-                let landscape = match total_landscape(sequence, ewm, mu) {
-                    Ok(l) => l,
-                    Err(_) => return Vec::new(),
+        .map(|(idx, (seq, (row, label)))| {
+            let mut local_results = Vec::new();
+            let mut local_warnings = Vec::new();
+            let warning = |motif: Option<&str>, reason| ScanWarning {
+                index: row,
+                label: label.clone(),
+                motif: motif.map(str::to_string),
+                reason,
+            };
+
+            let Some(sequence) = seq else {
+                local_warnings.push(warning(None, SkipReason::MissingSequence));
+                return (local_results, local_warnings);
+            };
+            let ambiguous = check_sequence(sequence, 0);
+            if let Some(reason) = ambiguous.clone() {
+                if scoring.ambiguous == AmbiguousPolicy::Drop {
+                    local_warnings.push(warning(None, reason));
+                    return (local_results, local_warnings);
+                }
+            }
+            let ambiguous = ambiguous.is_some();
+
+            // Iterate through each motif in the EWM collection
+            for (motif_id, matrix) in &matrices {
+                if sequence.len() < matrix.len() {
+                    local_warnings.push(warning(
+                        Some(motif_id),
+                        SkipReason::TooShort {
+                            length: sequence.len(),
+                            motif_length: matrix.len(),
+                        },
+                    ));
+                    continue;
+                }
+                let MotifParams { mu, cutoff } = params[*motif_id];
+                let started = scoring.profile.map(|_| Instant::now());
+                let hits = if ambiguous {
+                    matrix.ambiguous_hits(sequence, mu, cutoff, scoring.ambiguous)
+                } else {
+                    matrix.hits(sequence, mu, cutoff)
+                };
+                let (forward, reverse) = match hits {
+                    Ok(hits) => hits,
+                    Err(e) => {
+                        local_warnings.push(warning(
+                            Some(motif_id),
+                            SkipReason::ScanError(e.to_string()),
+                        ));
+                        continue;
+                    }
                 };
 
-                let n_positions = landscape.height();
-                let mut local_results = Vec::new();
-
-                // Iterate through each motif in the EWM collection
-                for (motif_id, motif_df) in ewm.iter() {
-                    // Check both forward and reverse strands
-                    for strand in ["F", "R"] {
-                        let col_name = format!("{}_{}", motif_id, strand);
-
-                        // Get the column for this motif+strand from the landscape
-                        if let Ok(motif_col) = landscape.column(&col_name) {
-                            // Iterate through positions
-                            for pos in 0..n_positions {
-                                if let Ok(occ) = motif_col.get(pos).unwrap().try_extract::<f64>() {
-                                    if occ > cutoff {
-                                        local_results.push((
-                                            idx as i32,
-                                            pos as i32,
-                                            motif_id.split('_').next().unwrap().to_string(),
-                                            strand.to_string(),
-                                            motif_df.height() as i32,
-                                            occ,
-                                        ));
-                                    }
+                if let (Some(profile), Some(started)) = (scoring.profile, started) {
+                    profile.record(
+                        motif_id,
+                        started.elapsed(),
+                        2 * (sequence.len() - matrix.len() + 1),
+                        forward.len() + reverse.len(),
+                    );
+                }
+
+                // Collect the hits of both forward and reverse strands
+                let distribution = scoring.distributions.map(|d| &d[*motif_id]);
+                let (lowest, highest) = matrix.exact().energy_range();
+                for (strand, hits) in [("F", forward), ("R", reverse)] {
+                    for (pos, occ) in hits {
+                        let mut details = HitDetails {
+                            pvalue: distribution.map(|d| d.occupancy_pvalue(occ, mu)),
+                            ..HitDetails::default()
+                        };
+                        if scoring.energies {
+                            let energy = matrix.exact().window_energy(sequence, pos, strand == "R");
+                            details.energy = energy.ok();
+                            // 1 for the best possible site of the motif, 0 for the worst
+                            details.relative_score = details.energy.map(|e| {
+                                if highest > lowest {
+                                    (highest - e) / (highest - lowest)
+                                } else {
+                                    1.0
                                 }
-                            }
+                            });
+                        }
+                        if scoring.matched_seq {
+                            let window = &sequence[pos..pos + matrix.len()];
+                            details.matched_seq = if strand == "R" {
+                                reverse_complement(window).ok()
+                            } else {
+                                Some(window.to_string())
+                            };
                         }
+                        local_results.push((
+                            (offset + idx) as i32,
+                            pos as i32,
+                            motif_id.split('_').next().unwrap().to_string(),
+                            strand.to_string(),
+                            matrix.len() as i32,
+                            occ,
+                            details,
+                        ));
                     }
                 }
-                local_results
-            })
+            }
+            (local_results, local_warnings)
         })
-        .flatten()
         .collect();
 
+    let mut warnings = Vec::new();
+    let mut hits = Vec::new();
+    for (local_results, local_warnings) in results {
+        hits.extend(local_results);
+        warnings.extend(local_warnings);
+    }
+
     // Unzip results into separate vectors
-    let (labels, positions, motifs, strands, lengths, occupancies): (
+    let (labels, positions, motifs, strands, lengths, occupancies, details): (
         Vec<i32>,
         Vec<i32>,
         Vec<String>,
         Vec<String>,
         Vec<i32>,
         Vec<f64>,
-    ) = results.into_iter().unzip_n_vec();
+        Vec<HitDetails>,
+    ) = hits.into_iter().unzip_n_vec();
 
-    let df = DataFrame::new(vec![
+    let mut columns = vec![
         Column::new("label".into(), labels),
         Column::new("position".into(), positions),
         Column::new("motif".into(), motifs),
         Column::new("strand".into(), strands),
         Column::new("length".into(), lengths),
         Column::new("occupancy".into(), occupancies),
-    ])?;
+    ];
+    if scoring.distributions.is_some() {
+        let pvalues: Vec<Option<f64>> = details.iter().map(|d| d.pvalue).collect();
+        let neg_log10: Vec<Option<f64>> = pvalues.iter().map(|p| p.map(|p| -p.log10())).collect();
+        let per_kb: Vec<Option<f64>> = pvalues
+            .iter()
+            .map(|p| p.map(|p| p * WINDOWS_PER_KB))
+            .collect();
+        columns.push(Column::new("pvalue".into(), pvalues));
+        columns.push(Column::new("neg_log10_pvalue".into(), neg_log10));
+        columns.push(Column::new("background_per_kb".into(), per_kb));
+    }
+    if scoring.energies {
+        let energies: Vec<Option<f64>> = details.iter().map(|d| d.energy).collect();
+        let relative: Vec<Option<f64>> = details.iter().map(|d| d.relative_score).collect();
+        columns.push(Column::new("energy".into(), energies));
+        columns.push(Column::new("relative_score".into(), relative));
+    }
+    if scoring.matched_seq {
+        let windows: Vec<Option<String>> = details.into_iter().map(|d| d.matched_seq).collect();
+        columns.push(Column::new("matched_seq".into(), windows));
+    }
+    let df = DataFrame::new(columns)?;
+
+    Ok(ScanResult { hits: df, warnings })
+}
 
-    Ok(df)
+fn run_manifest(
+    run: &RunConfig,
+    params: &HashMap<String, MotifParams>,
+) -> Result<RunManifest, ScannerError> {
+    let mut manifest = RunManifest::new("motif-scanner", env!("CARGO_PKG_VERSION"));
+    manifest
+        .parameter("cutoff", run.defaults.cutoff)
+        .parameter("mu", run.defaults.mu)
+        .parameter("schema_version", SCHEMA_VERSION);
+    if run.ewm_options != EwmOptions::default() {
+        manifest.parameter("rt", run.ewm_options.rt);
+    }
+    if run.quantized {
+        manifest.parameter("quantized", true);
+    }
+    if run.pvalues {
+        manifest.parameter("pvalues", true);
+    }
+    if let Some(columns) = &run.columns {
+        manifest.parameter("columns", columns.join(","));
+    }
+    if let Some(bits) = run.motif_filter.min_information {
+        manifest.parameter("min_ic", bits);
+    }
+    if let Some(width) = run.motif_filter.max_width {
+        manifest.parameter("max_width", width);
+    }
+    if run.genomic.is_some() {
+        manifest.parameter("genomic", true);
+    }
+    if run.duplicate_labels != DuplicateLabels::Keep {
+        manifest.parameter("duplicate_labels", run.duplicate_labels.to_string());
+    }
+    if run.ambiguous_policy != AmbiguousPolicy::Drop {
+        manifest.parameter("ambiguous_policy", run.ambiguous_policy.to_string());
+    }
+    if !run.normalization.uppercase {
+        manifest.parameter("keep_case", true);
+    }
+    if run.normalization.validate {
+        manifest.parameter("validate_sequences", true);
+    }
+    if run.input_columns.sequence != "sequence" {
+        manifest.parameter("sequence_col", &run.input_columns.sequence);
+    }
+    if let Some(id) = &run.input_columns.id {
+        manifest.parameter("id_col", id);
+    }
+    if let Some(separator) = run.separator {
+        manifest.parameter("separator", (separator as char).to_string());
+    }
+    if let Some(cache) = &run.motif_cache {
+        manifest.parameter("motif_cache", cache);
+    }
+    if let Some(tolerance) = run.dedupe {
+        manifest.parameter("dedupe_tolerance", tolerance);
+    }
+    if let Some(n) = run.sample_motifs {
+        manifest.parameter("sample_motifs", n);
+    }
+    if let Some(n) = run.sample_sequences {
+        manifest.parameter("sample_sequences", n);
+    }
+    if let Some(n) = run.chunk_rows {
+        manifest.parameter("chunk_rows", n);
+    }
+    if run.sample_motifs.is_some() || run.sample_sequences.is_some() {
+        manifest.parameter("seed", run.seed);
+    }
+    for (motif_id, motif_params) in params {
+        if *motif_params != run.defaults {
+            manifest
+                .parameter(&format!("motif.{}.mu", motif_id), motif_params.mu)
+                .parameter(&format!("motif.{}.cutoff", motif_id), motif_params.cutoff);
+        }
+    }
+    if let Some(motifs) = &run.motifs {
+        manifest.parameter("motifs", motifs.join(","));
+    }
+    if let Some(columns) = &run.carry {
+        manifest.parameter("carry", columns.join(","));
+    }
+    if let Some(columns) = &run.sort_by {
+        manifest.parameter("sort_by", columns.join(","));
+    }
+    if let Some(rows) = run.row_group_size {
+        manifest.parameter("row_group_size", rows);
+    }
+    if let Some(annotations) = &run.tf_annotations {
+        manifest.parameter("tf_annotations", annotations.len());
+    }
+    manifest
+        .add_input(&run.data_file)
+        .and_then(|m| m.add_input(&run.pwm_file))
+        .map_err(|e| ScannerError::Output(e.to_string()))?;
+    if let Some(calibration) = &run.calibration {
+        manifest
+            .parameter("fp_rate_per_kb", calibration.rate_per_kb)
+            .add_input(&calibration.background)
+            .map_err(|e| ScannerError::Output(e.to_string()))?;
+    }
+    for output_file in &run.output_files {
+        manifest.add_output(output_file);
+    }
+    Ok(manifest)
 }
 
-fn save_results(df: &mut DataFrame, output_file: &str) -> Result<(), ScannerError> {
+fn save_results(
+    df: &mut DataFrame,
+    output_file: &str,
+    metadata: Option<&[(String, String)]>,
+    chrom_sizes: Option<&str>,
+    row_group_size: Option<usize>,
+) -> Result<(), ScannerError> {
     match Path::new(output_file)
         .extension()
         .and_then(|ext| ext.to_str())
     {
+        #[cfg(feature = "sqlite")]
+        Some("sqlite" | "sqlite3" | "db") => {
+            sqlite::save_sqlite(df, output_file, metadata)?;
+        }
+        #[cfg(not(feature = "sqlite"))]
+        Some(ext @ ("sqlite" | "sqlite3" | "db")) => {
+            let _ = metadata;
+            return Err(ScannerError::UnsupportedOutput(format!(
+                ".{} output requires building with the `sqlite` feature",
+                ext
+            )));
+        }
+        Some("bed") => {
+            write_bed(df, output_file).map_err(|e| ScannerError::Output(e.to_string()))?;
+        }
+        #[cfg(feature = "bigbed")]
+        Some("bb" | "bigBed" | "bigbed") => {
+            let sizes = read_chrom_sizes(chrom_sizes.unwrap_or_default())
+                .map_err(|e| ScannerError::Input(e.to_string()))?;
+            write_bigbed(df, &sizes, output_file)
+                .map_err(|e| ScannerError::Output(e.to_string()))?;
+        }
+        #[cfg(not(feature = "bigbed"))]
+        Some(ext @ ("bb" | "bigBed" | "bigbed")) => {
+            let _ = chrom_sizes;
+            return Err(ScannerError::UnsupportedOutput(format!(
+                ".{} output requires building with the `bigbed` feature",
+                ext
+            )));
+        }
         Some("parquet") => {
-            let mut file = std::fs::File::create(output_file)?;
-            ParquetWriter::new(&mut file)
-                .with_compression(ParquetCompression::Snappy)
-                .finish(df)?;
+            write_parquet(df, output_file, ParquetCompression::Snappy, row_group_size)?
         }
-        _ => {
-            let mut file = std::fs::File::create(output_file)?;
-            CsvWriter::new(&mut file).include_header(true).finish(df)?;
+        _ => write_table(df, output_file)?,
+    }
+
+    Ok(())
+}
+
+/// The hits in the `--sort-by` order, if any; ties keep their scan order
+fn sort_hits(hits: DataFrame, run: &RunConfig) -> Result<DataFrame, ScannerError> {
+    match &run.sort_by {
+        Some(columns) => Ok(hits.sort(
+            columns.clone(),
+            SortMultipleOptions::default().with_maintain_order(true),
+        )?),
+        None => Ok(hits),
+    }
+}
+
+/// Reads the motif file as EWMs, dropping (or only reporting, with --keep-filtered) motifs
+/// that fail --min-ic/--max-width, collapsing duplicates with --dedupe and keeping the
+/// --motifs subset; only the motifs that are scanned are converted to energies, unless
+/// they come from the --motif-cache
+fn load_motifs(run: &RunConfig) -> Result<EWMCollection, ScannerError> {
+    let pwm_error = |e: MotifError| ScannerError::PwmError(e.to_string());
+    let subset = !run.motif_filter.is_empty()
+        || run.dedupe.is_some()
+        || run.motifs.is_some()
+        || run.sample_motifs.is_some();
+    let (pwms, mut cached) = match &run.motif_cache {
+        Some(cache) => {
+            let motifs = load_cached(&run.pwm_file, &run.ewm_options, cache).map_err(pwm_error)?;
+            if !subset {
+                return Ok(motifs.ewms);
+            }
+            (motifs.pwms, Some(motifs.ewms))
+        }
+        None if !subset => {
+            return read_pwm_to_ewm_with(&run.pwm_file, &run.ewm_options).map_err(pwm_error);
+        }
+        None => (read_pwm_files(&run.pwm_file).map_err(pwm_error)?, None),
+    };
+
+    let (pwms, rejected) = if run.keep_filtered {
+        let rejected = run.motif_filter.flag(&pwms).map_err(pwm_error)?;
+        (pwms, rejected)
+    } else {
+        run.motif_filter.apply(pwms).map_err(pwm_error)?
+    };
+    for motif in &rejected {
+        warn!(
+            motif = %motif.id,
+            kept = run.keep_filtered,
+            "motif fails filter: {}",
+            motif.reason
+        );
+    }
+    if !run.keep_filtered && !rejected.is_empty() {
+        info!(
+            dropped = rejected.len(),
+            remaining = pwms.len(),
+            "filtered motifs"
+        );
+    }
+
+    let pwms = match run.dedupe {
+        Some(tolerance) => {
+            let deduplicated = deduplicate(pwms, tolerance).map_err(pwm_error)?;
+            for merge in &deduplicated.merged {
+                info!(
+                    motif = %merge.duplicate,
+                    into = %merge.kept,
+                    reverse_complement = merge.reverse_complement,
+                    "merged duplicate motif"
+                );
+            }
+            deduplicated.pwms
+        }
+        None => pwms,
+    };
+
+    let mut pwms = run.select_motifs(pwms)?;
+    if let Some(n) = run.sample_motifs {
+        let available = pwms.len();
+        pwms = sample_motifs(pwms, n, run.seed);
+        info!(
+            motifs = available,
+            sampled = pwms.len(),
+            seed = run.seed,
+            "sampled motifs"
+        );
+    }
+    match &mut cached {
+        Some(ewms) => Ok(pwms
+            .into_keys()
+            .filter_map(|id| ewms.remove(&id).map(|ewm| (id, ewm)))
+            .collect()),
+        None => LazyEwmCollection::new(pwms, run.ewm_options)
+            .into_collection()
+            .map_err(pwm_error),
+    }
+}
+
+/// Replaces the global cutoff of every motif by the one calibrated for --fp-rate on the
+/// --background sequences; cutoffs set per motif in the config file are kept
+fn calibrate_params(
+    run: &RunConfig,
+    ewm: &EWMCollection,
+    params: HashMap<String, MotifParams>,
+) -> Result<HashMap<String, MotifParams>, ScannerError> {
+    let Some(calibration) = &run.calibration else {
+        return Ok(params);
+    };
+    let (df, skipped) = read_sequences(
+        &calibration.background,
+        DuplicateLabels::Keep,
+        AmbiguousPolicy::Drop,
+        &run.normalization,
+        &run.input_columns,
+        run.separator,
+    )?;
+    let background: Vec<&str> = df
+        .column("sequence")?
+        .str()?
+        .into_iter()
+        .flatten()
+        .collect();
+    info!(
+        file = %calibration.background,
+        sequences = background.len(),
+        skipped = skipped.len(),
+        rate_per_kb = calibration.rate_per_kb,
+        "calibrating cutoffs"
+    );
+
+    params
+        .into_par_iter()
+        .map(|(motif_id, mut motif_params)| {
+            if !run.overrides_cutoff(&motif_id) {
+                let calibrated = calibrate_cutoff(
+                    &background,
+                    &ewm[&motif_id],
+                    motif_params.mu,
+                    calibration.rate_per_kb,
+                )
+                .map_err(|e| ScannerError::Input(format!("--background: {}", e)))?;
+                info!(
+                    motif = %motif_id,
+                    cutoff = calibrated.cutoff,
+                    background_hits_per_kb = calibrated.hits_per_kb(),
+                    "calibrated cutoff"
+                );
+                motif_params.cutoff = calibrated.cutoff;
+            }
+            Ok((motif_id, motif_params))
+        })
+        .collect()
+}
+
+/// Reads a CSV or Parquet file with a 'sequence' column or a FASTA file (.fa/.fasta/.fna),
+/// normalizing the sequences, dropping missing sequences, applying the duplicate label
+/// policy and, with `AmbiguousPolicy::Drop`, dropping sequences with bases other than A, C,
+/// G, T.
+///
+/// The dropped sequences are returned as warnings, and the number of sequences with
+/// ambiguous bases is logged with what the policy does with them. The kept rows carry their
+/// row in the input file in an `input_row` column, so later warnings refer to the same rows.
+fn read_sequences(
+    path: &str,
+    duplicate_labels: DuplicateLabels,
+    ambiguous_policy: AmbiguousPolicy,
+    normalization: &Normalization,
+    columns: &InputColumns,
+    separator: Option<u8>,
+) -> Result<(DataFrame, Vec<ScanWarning>), ScannerError> {
+    let fasta = is_fasta(path);
+    let mut df = if fasta {
+        read_fasta_normalized(path, normalization)
+            .map_err(|e| ScannerError::Input(format!("{}: {}", path, e)))?
+            .lazy()
+    } else if Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext == "parquet")
+    {
+        LazyFrame::scan_parquet(path, ScanArgsParquet::default())?
+    } else {
+        io::read_delimited(path, separator)?
+    };
+
+    let sequence_column = if fasta {
+        "sequence"
+    } else {
+        columns.sequence.as_str()
+    };
+    if df.collect_schema()?.get(sequence_column).is_none() {
+        return Err(ScannerError::MissingSequenceColumn);
+    }
+
+    let mut df = df.collect()?;
+    if !fasta {
+        df = prepare_table(df, path, normalization, columns)?;
+    }
+    filter_sequences(df, path, 0, duplicate_labels, ambiguous_policy)
+}
+
+/// Renames the sequence and ID columns of a CSV or Parquet input and normalizes the
+/// sequences
+fn prepare_table(
+    df: DataFrame,
+    path: &str,
+    normalization: &Normalization,
+    columns: &InputColumns,
+) -> Result<DataFrame, ScannerError> {
+    let df = rename_input_columns(df, columns)
+        .map_err(|e| ScannerError::Input(format!("{}: {}", path, e)))?;
+    normalize_sequences(&df, normalization)
+        .map_err(|e| ScannerError::Input(format!("{}: {}", path, e)))
+}
+
+/// Numbers the records read from `path` from `first_row` on, resolves duplicate labels and
+/// drops the records that cannot be scanned, returning them as warnings
+fn filter_sequences(
+    df: DataFrame,
+    path: &str,
+    first_row: usize,
+    duplicate_labels: DuplicateLabels,
+    ambiguous_policy: AmbiguousPolicy,
+) -> Result<(DataFrame, Vec<ScanWarning>), ScannerError> {
+    let mut df = df.with_row_index(INPUT_ROW.into(), Some(first_row as IdxSize))?;
+    let mut warnings = Vec::new();
+    if df
+        .column("label")
+        .is_ok_and(|c| c.dtype() == &DataType::String)
+    {
+        let (resolved, duplicates) = resolve_duplicate_labels(&df, duplicate_labels)
+            .map_err(|e| ScannerError::Input(format!("{}: {}", path, e)))?;
+        if !duplicates.is_empty() {
+            warn!(
+                file = path,
+                duplicates = duplicates.len(),
+                policy = %duplicate_labels,
+                "records repeat an earlier label"
+            );
+        }
+        if duplicate_labels == DuplicateLabels::KeepFirst {
+            warnings.extend(duplicates.into_iter().map(|d| ScanWarning {
+                index: first_row + d.index,
+                label: Some(d.label),
+                motif: None,
+                reason: SkipReason::DuplicateLabel {
+                    first_index: first_row + d.first_index,
+                },
+            }));
+        }
+        df = resolved;
+    }
+    let mut keep = Vec::with_capacity(df.height());
+    let mut ambiguous = 0;
+    for (seq, (row, label)) in df
+        .column("sequence")?
+        .str()?
+        .into_iter()
+        .zip(row_info(&df, 0)?)
+    {
+        let reason = match seq {
+            None => Some(SkipReason::MissingSequence),
+            Some(seq) => check_sequence(seq, 0),
+        };
+        let reason = match reason {
+            Some(SkipReason::AmbiguousBases { .. }) => {
+                ambiguous += 1;
+                reason.filter(|_| ambiguous_policy == AmbiguousPolicy::Drop)
+            }
+            _ => reason,
+        };
+        keep.push(reason.is_none());
+        if let Some(reason) = reason {
+            warnings.push(ScanWarning {
+                index: row,
+                label,
+                motif: None,
+                reason,
+            });
         }
     }
+    let filtered = df.filter(&BooleanChunked::from_slice("keep".into(), &keep))?;
+
+    let missing = warnings
+        .iter()
+        .filter(|w| w.reason == SkipReason::MissingSequence)
+        .count();
+    if missing > 0 {
+        warn!(file = path, skipped = missing, "skipping missing sequences");
+    }
+    if ambiguous > 0 {
+        let action = match ambiguous_policy {
+            AmbiguousPolicy::Drop => "skipping sequences with bases other than A, C, G, T",
+            AmbiguousPolicy::Mask => "masking bases other than A, C, G, T",
+            AmbiguousPolicy::ScanLenient => "scoring ambiguous bases leniently",
+        };
+        warn!(
+            file = path,
+            sequences = ambiguous,
+            policy = %ambiguous_policy,
+            "{}",
+            action
+        );
+    }
+    Ok((filtered, warnings))
+}
 
+/// Keeps the --sample-sequences random subset of the sequences read from `input`
+fn sample_input(run: &RunConfig, df: DataFrame, input: &str) -> Result<DataFrame, ScannerError> {
+    let Some(n) = run.sample_sequences else {
+        return Ok(df);
+    };
+    let sampled =
+        sample_sequences(&df, n, run.seed).map_err(|e| ScannerError::Input(e.to_string()))?;
+    info!(
+        file = input,
+        sequences = df.height(),
+        sampled = sampled.height(),
+        seed = run.seed,
+        "sampled sequences"
+    );
+    Ok(sampled)
+}
+
+fn is_fasta(path: &str) -> bool {
+    matches!(
+        Path::new(path).extension().and_then(|ext| ext.to_str()),
+        Some("fa" | "fasta" | "fna")
+    )
+}
+
+/// Adds the TF annotation columns of every hit's motif
+fn annotate_hits(hits: &DataFrame, annotations: &TfAnnotations) -> Result<DataFrame, ScannerError> {
+    annotations
+        .annotate(hits, "motif")
+        .map_err(|e| ScannerError::Output(e.to_string()))
+}
+
+/// Checks that the columns to carry exist in the input, before scanning
+fn check_carry_columns(df: &DataFrame, columns: &[String]) -> Result<(), ScannerError> {
+    let schema = df.schema();
+    match columns
+        .iter()
+        .find(|name| *name == INPUT_ROW || !schema.contains(name))
+    {
+        Some(name) => Err(ScannerError::Config(format!(
+            "column '{}' to carry is not in the input",
+            name
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Joins `columns` of the scanned sequences onto the hits, whose labels index the rows of
+/// `df` shifted by `offset`. Carried columns named like a hit column are prefixed with
+/// `input_`.
+fn carry_columns(
+    hits: &DataFrame,
+    df: &DataFrame,
+    columns: &[String],
+    offset: usize,
+) -> Result<DataFrame, ScannerError> {
+    let rows: IdxCa = hits
+        .column("label")?
+        .i32()?
+        .into_iter()
+        .map(|label| label.map(|l| (l as usize - offset) as IdxSize))
+        .collect();
+    let mut carried = df.select(columns.iter().map(String::as_str))?.take(&rows)?;
+    for name in columns {
+        if hits.schema().contains(name) {
+            carried.rename(name, format!("input_{}", name).into())?;
+        }
+    }
+    Ok(hits.hstack(carried.get_columns())?)
+}
+
+/// Replaces the row index labels of the hits with the IDs of the scanned sequences `df`
+/// (the `--id-col` column, renamed to "label"), whose rows the hit labels index shifted by
+/// `offset`
+fn id_labels(hits: &DataFrame, df: &DataFrame, offset: usize) -> Result<DataFrame, ScannerError> {
+    let rows: IdxCa = hits
+        .column("label")?
+        .i32()?
+        .into_iter()
+        .map(|label| label.map(|l| (l as usize - offset) as IdxSize))
+        .collect();
+    let ids = df.column("label")?.take(&rows)?;
+    let mut hits = hits.clone();
+    hits.with_column(ids)?;
+    Ok(hits)
+}
+
+/// Adds the genomic coordinates of the hits, parsed from the labels of the scanned sequences
+/// `df` (which the hit labels index, shifted by `offset`)
+fn genomic_columns(
+    hits: &DataFrame,
+    df: &DataFrame,
+    parser: &LabelParser,
+    offset: usize,
+) -> Result<DataFrame, ScannerError> {
+    let mut regions = parse_labels(df, "label", parser)
+        .map_err(|e| ScannerError::Input(format!("--genomic: {}", e)))?;
+    let unparsed = regions.column("chrom")?.null_count();
+    if unparsed > 0 {
+        warn!(
+            sequences = unparsed,
+            "labels without genomic coordinates; their hits get null coordinates"
+        );
+    }
+    regions.with_column(Column::new(
+        "label".into(),
+        (offset as i32..(offset + df.height()) as i32).collect::<Vec<_>>(),
+    ))?;
+    project_hits(hits, &regions).map_err(|e| ScannerError::Output(e.to_string()))
+}
+
+/// Path of the table listing the records skipped while scanning for `output_file`
+fn warnings_path(output_file: &str) -> String {
+    format!("{}.warnings.csv", output_file)
+}
+
+/// Writes the skipped records, in input order, next to the primary output and logs a count
+/// per reason. A stale warnings file of an earlier run is removed when nothing was skipped.
+fn save_warnings(warnings: &mut DataFrame, output_file: &str) -> Result<(), ScannerError> {
+    let path = warnings_path(output_file);
+    if warnings.height() == 0 {
+        if Path::new(&path).exists() {
+            fs::remove_file(&path)?;
+        }
+        return Ok(());
+    }
+
+    let counts = warnings
+        .column("reason")?
+        .as_materialized_series()
+        .value_counts(true, false, "count".into(), false)?;
+    for (reason, count) in counts
+        .column("reason")?
+        .str()?
+        .into_iter()
+        .zip(counts.column("count")?.idx()?)
+    {
+        warn!(
+            reason = reason.unwrap_or_default(),
+            count = count.unwrap_or_default(),
+            "skipped records"
+        );
+    }
+
+    warnings.sort_in_place(
+        ["index", "motif"],
+        SortMultipleOptions::default().with_maintain_order(true),
+    )?;
+    let mut file = fs::File::create(&path)?;
+    CsvWriter::new(&mut file).finish(warnings)?;
+    info!(file = %path, warnings = warnings.height(), "warnings written");
     Ok(())
 }
 
-fn main() -> Result<(), ScannerError> {
-    let start_time = std::time::Instant::now();
+/// Checks that an input has the columns the run needs
+fn validate_input(run: &RunConfig, df: &DataFrame, path: &str) -> Result<(), ScannerError> {
+    if df
+        .column("sequence")
+        .and_then(|c| c.str().cloned())
+        .is_err()
+    {
+        return Err(ScannerError::MissingSequenceColumn);
+    }
+    if let Some(columns) = &run.carry {
+        check_carry_columns(df, columns)?;
+    }
+    if run.genomic.is_some() && df.column("label").and_then(|c| c.str().cloned()).is_err() {
+        return Err(ScannerError::Input(format!(
+            "--genomic needs a string 'label' column in {}",
+            path
+        )));
+    }
+    Ok(())
+}
 
-    let args = Args::parse();
+/// Validates the inputs of the run and prints the estimated size, memory and time of the
+/// scan, without scanning or writing anything
+fn dry_run(
+    run: &RunConfig,
+    ewm: &EWMCollection,
+    params: &HashMap<String, MotifParams>,
+) -> Result<(), ScannerError> {
+    let inputs: Vec<String> = if run.batch {
+        batch::batch_inputs(&run.data_file)?
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect()
+    } else {
+        vec![run.data_file.clone()]
+    };
 
-    // Create output directory if it doesn't exist
-    if let Some(parent) = Path::new(&args.output_file).parent() {
-        fs::create_dir_all(parent)?;
+    // batch inputs are scanned concurrently and share the budget, as in `run_batch`
+    let budget = run
+        .max_memory
+        .map(|budget| budget / rayon::current_num_threads().min(inputs.len()).max(1));
+    let mut total = Estimate::default();
+    let mut streamed = 0;
+    for input in &inputs {
+        let (df, skipped) = read_sequences(
+            input,
+            run.duplicate_labels,
+            run.ambiguous_policy,
+            &run.normalization,
+            &run.input_columns,
+            run.separator,
+        )?;
+        let df = sample_input(run, df, input)?;
+        validate_input(run, &df, input)?;
+        info!(file = %input, sequences = df.height(), skipped = skipped.len(), "input is valid");
+        let estimate = Estimate::new(&df, ewm, params, run.quantized)?;
+        if let Some(budget) = budget {
+            let plan = estimate
+                .plan(budget, stream::can_stream(run))
+                .map_err(|e| ScannerError::Memory(format!("{}: {}", input, e)))?;
+            if plan != MemoryPlan::InMemory {
+                streamed += 1;
+            }
+        }
+        total.add(&estimate);
     }
 
-    let df = LazyCsvReader::new(&args.data_file)
-        .with_has_header(true)
-        .finish()?
-        .filter(col("sequence").str().contains(lit("N"), false).not())
-        .filter(col("sequence").str().contains(lit("Y"), false).not())
-        .collect()?;
+    println!("motif file:     {}", run.pwm_file);
+    println!("inputs:         {}", inputs.len());
+    println!("{}", total);
+    if let Some(budget) = run.max_memory {
+        println!(
+            "memory budget:  {} ({} of {} inputs streamed in chunks)",
+            format_bytes(budget),
+            streamed,
+            inputs.len()
+        );
+    }
+    println!("outputs:        {}", run.output_files.join(", "));
+    Ok(())
+}
+
+/// Sequences of a scan
+#[derive(Clone, Copy)]
+enum Sequences<'a> {
+    /// Read whole, with the records skipped while reading
+    Table {
+        df: &'a DataFrame,
+        skipped: &'a [ScanWarning],
+    },
+    /// Read from the input file this many records at a time (`--chunk-rows`)
+    Chunked(usize),
+}
+
+/// Scans the sequences of one input and writes every output of the run, with manifests.
+/// The records skipped while reading the input are written with the scan's own warnings to
+/// `{OUTPUT_FILE}.warnings.csv`. Returns the summary of the scan.
+fn scan_and_save(
+    run: &RunConfig,
+    sequences: Sequences,
+    ewm: &EWMCollection,
+    params: &HashMap<String, MotifParams>,
+    start_time: Instant,
+) -> Result<RunSummary, ScannerError> {
+    // Create output directories if they don't exist
+    for output_file in &run.output_files {
+        if let Some(parent) = Path::new(output_file).parent() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+
+    if let Sequences::Table { df, .. } = sequences {
+        validate_input(run, df, &run.data_file)?;
+    }
+    let manifest = run_manifest(run, params)?;
 
-    // read pwm file and convert to ewm
-    let ewm = read_pwm_to_ewm(&args.pwm_file).map_err(|e| ScannerError::PwmError(e.to_string()))?;
+    // computed once per run: the distributions of long motifs take a while
+    let distributions = if run.pvalues {
+        Some(
+            score_distributions(ewm, UNIFORM_BACKGROUND, DEFAULT_STEP)
+                .map_err(|e| ScannerError::PwmError(e.to_string()))?,
+        )
+    } else {
+        None
+    };
+    let profile = run.profile.then(|| ScanProfile::new(ewm));
+    let scoring = Scoring {
+        quantized: run.quantized,
+        distributions: distributions.as_ref(),
+        energies: run.selects("energy") || run.selects("relative_score"),
+        matched_seq: run.selects("matched_seq"),
+        profile: profile.as_ref(),
+        ambiguous: run.ambiguous_policy,
+    };
 
-    let mut results_df = process_sequences(&df, &ewm, args.mu as f64, args.cutoff)?;
+    let plan = match (run.max_memory, sequences) {
+        (Some(budget), Sequences::Table { df, .. }) => {
+            let estimate = Estimate::new(df, ewm, params, run.quantized)?;
+            let plan = estimate
+                .plan(budget, stream::can_stream(run))
+                .map_err(ScannerError::Memory)?;
+            info!(
+                budget = %format_bytes(budget),
+                peak = %format_bytes(estimate.peak_bytes()),
+                ?plan,
+                "memory plan"
+            );
+            plan
+        }
+        _ => MemoryPlan::InMemory,
+    };
+
+    let scan_start = Instant::now();
+    let (df, skipped) = match sequences {
+        Sequences::Table { df, skipped } => (df, skipped),
+        Sequences::Chunked(rows) => {
+            let scan = stream::scan_input_chunked(run, ewm, params, scoring, rows)?;
+            let scan_time = scan_start.elapsed();
+            let mut summary = finish_scan(
+                run,
+                manifest,
+                scan.schema,
+                scan.warnings,
+                scan.sequences,
+                Some(scan.summary),
+                profile.as_ref(),
+                ewm,
+                start_time,
+            )?;
+            summary.first_stage("scan", scan_time);
+            return Ok(summary);
+        }
+    };
 
-    let elapsed = start_time.elapsed();
-    println!(
-        "Total execution time: {:.4} minutes",
-        elapsed.as_secs_f64() / 60.0
+    // streamed hits are already in the outputs; only their schema and count are kept
+    let mut streamed = None;
+    let (mut results_df, scan_warnings) = match (plan, run.checkpoint) {
+        (MemoryPlan::Stream { chunk_size }, _) => {
+            let (schema, summary, warnings) =
+                stream::scan_streaming(run, df, ewm, params, scoring, chunk_size)?;
+            streamed = Some(summary);
+            (schema, warnings)
+        }
+        (MemoryPlan::InMemory, Some(chunk_size)) => {
+            Checkpoint::for_output(run.output_file(), chunk_size, run.resume)
+                .scan(df, ewm, params, &manifest, scoring)?
+        }
+        (MemoryPlan::InMemory, None) => {
+            let result = process_sequences(df, ewm, params, 0, scoring)?;
+            (
+                result.hits,
+                warnings_frame(&result.warnings)
+                    .map_err(|e| ScannerError::Output(e.to_string()))?,
+            )
+        }
+    };
+    let mut warnings = warnings_frame(skipped).map_err(|e| ScannerError::Output(e.to_string()))?;
+    warnings.vstack_mut(&scan_warnings)?;
+    if streamed.is_none() {
+        if let Some(parser) = &run.genomic {
+            results_df = genomic_columns(&results_df, df, parser, 0)?;
+        }
+        if let Some(columns) = &run.carry {
+            results_df = carry_columns(&results_df, df, columns, 0)?;
+        }
+        if run.input_columns.id.is_some() {
+            results_df = id_labels(&results_df, df, 0)?;
+        }
+        if let Some(annotations) = &run.tf_annotations {
+            results_df = annotate_hits(&results_df, annotations)?;
+        }
+        results_df = sort_hits(results_df, run)?;
+    }
+    let scan_time = scan_start.elapsed();
+    let mut summary = finish_scan(
+        run,
+        manifest,
+        results_df,
+        warnings,
+        df.height(),
+        streamed,
+        profile.as_ref(),
+        ewm,
+        start_time,
+    )?;
+    summary.first_stage("scan", scan_time);
+    Ok(summary)
+}
+
+/// Writes the hits (unless `streamed`, the summary of the hits already in the outputs), the
+/// manifest, schema, warnings and profile of a finished scan of `n_sequences` sequences
+#[allow(clippy::too_many_arguments)]
+fn finish_scan(
+    run: &RunConfig,
+    mut manifest: RunManifest,
+    results_df: DataFrame,
+    mut warnings: DataFrame,
+    n_sequences: usize,
+    streamed: Option<HitSummary>,
+    profile: Option<&ScanProfile>,
+    ewm: &EWMCollection,
+    start_time: Instant,
+) -> Result<RunSummary, ScannerError> {
+    let write_start = Instant::now();
+    let in_memory = streamed.is_none();
+    let hits = match streamed {
+        Some(summary) => summary,
+        None => {
+            let mut summary = HitSummary::default();
+            summary.add(&results_df)?;
+            summary
+        }
+    };
+    let n_hits = hits.hits();
+
+    manifest
+        .count("sequences", n_sequences)
+        .count("hits", n_hits)
+        .count("warnings", warnings.height())
+        .finish(start_time.elapsed());
+    info!(
+        file = %run.data_file,
+        sequences = n_sequences,
+        hits = n_hits,
+        warnings = warnings.height(),
+        seconds = manifest.wall_time_seconds,
+        "scan finished"
     );
+    if n_hits == 0 {
+        warn!(file = %run.data_file, "no hits passed the cutoff, writing empty outputs");
+    }
 
     // save results
-    save_results(&mut results_df, &args.output_file)?;
+    let metadata = run.metadata.then(|| manifest.to_key_values());
+    for output_file in &run.output_files {
+        let mut table = run.output_columns(&results_df, output_file)?;
+        if in_memory {
+            save_results(
+                &mut table,
+                output_file,
+                metadata.as_deref(),
+                run.chrom_sizes.as_deref(),
+                run.row_group_size,
+            )?;
+        }
+
+        if !run.no_manifest {
+            manifest
+                .write(&manifest_path(output_file))
+                .map_err(|e| ScannerError::Output(e.to_string()))?;
+        }
+        // a header-only CSV loses its column types, so empty outputs always get the sidecar
+        if run.output_schema || n_hits == 0 {
+            write_schema(&table, n_hits, output_file)
+                .map_err(|e| ScannerError::Output(e.to_string()))?;
+        } else if Path::new(&schema_path(output_file)).exists() {
+            // a sidecar left by an earlier run would describe another table
+            fs::remove_file(schema_path(output_file))?;
+        }
+    }
+    save_warnings(&mut warnings, run.output_file())?;
+    if let Some(profile) = profile {
+        profile.save(ewm, run.ewm_options.rt, run.output_file())?;
+    }
+
+    // the output is complete, so the checkpoint is no longer needed
+    if let Some(chunk_size) = run.checkpoint {
+        Checkpoint::for_output(run.output_file(), chunk_size, run.resume).remove()?;
+    }
+
+    let mut summary = RunSummary::new(n_sequences, warnings.height(), hits);
+    summary.stage("write", write_start.elapsed());
+    Ok(summary)
+}
 
+fn main() -> ExitCode {
+    match run_scanner() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+fn run_scanner() -> Result<(), ScannerError> {
+    let start_time = Instant::now();
+
+    let args = Args::parse();
+    init_logging(&args.log_level, args.log_format).map_err(ScannerError::Config)?;
+    let run = RunConfig::resolve(&args)?;
+
+    if let Some(threads) = run.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .map_err(|e| ScannerError::Config(e.to_string()))?;
+    }
+
+    let ewm = load_motifs(&run)?;
+    if let Some(annotations) = &run.tf_annotations {
+        let mut unannotated: Vec<&str> = ewm
+            .keys()
+            .filter(|id| annotations.get(id).is_none())
+            .map(String::as_str)
+            .collect();
+        if !unannotated.is_empty() {
+            unannotated.sort();
+            warn!(
+                motifs = unannotated.len(),
+                "motifs without TF annotation: {}",
+                unannotated.join(", ")
+            );
+        }
+    }
+    let params = calibrate_params(&run, &ewm, run.motif_params(&ewm)?)?;
+    if run.dry_run {
+        return dry_run(&run, &ewm, &params);
+    }
+    let motif_time = start_time.elapsed();
+
+    let n_hits = if run.batch {
+        batch::run_batch(&run, &ewm, &params)?
+    } else {
+        let mut summary = if let Some(rows) = run.chunk_rows {
+            scan_and_save(&run, Sequences::Chunked(rows), &ewm, &params, start_time)?
+        } else {
+            let read_start = Instant::now();
+            let (df, skipped) = read_sequences(
+                &run.data_file,
+                run.duplicate_labels,
+                run.ambiguous_policy,
+                &run.normalization,
+                &run.input_columns,
+                run.separator,
+            )?;
+            let df = sample_input(&run, df, &run.data_file)?;
+            info!(sequences = df.height(), "sequences to scan");
+            let read_time = read_start.elapsed();
+            let mut summary = scan_and_save(
+                &run,
+                Sequences::Table {
+                    df: &df,
+                    skipped: &skipped,
+                },
+                &ewm,
+                &params,
+                start_time,
+            )?;
+            summary.first_stage("read sequences", read_time);
+            summary
+        };
+        summary.first_stage("read motifs", motif_time);
+        println!("{}", summary);
+        if run.summary_json {
+            summary.write_json(run.output_file())?;
+        }
+        summary.hits
+    };
+
+    info!(
+        minutes = start_time.elapsed().as_secs_f64() / 60.0,
+        "total execution time"
+    );
+
+    if run.fail_empty && n_hits == 0 {
+        return Err(ScannerError::EmptyResults);
+    }
     Ok(())
 }
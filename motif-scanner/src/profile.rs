@@ -0,0 +1,170 @@
+use crate::ScannerError;
+use polars::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tf_binding_rs::matrix::{information_content, matrix_rows, pwm_from_rows};
+use tf_binding_rs::types::EWMCollection;
+use tracing::info;
+
+/// Motifs named in the log summary of a profile
+const TOP_MOTIFS: usize = 5;
+
+/// Time and output of one motif, summed over the scanning threads
+#[derive(Debug, Default)]
+struct MotifCounters {
+    nanos: AtomicU64,
+    windows: AtomicU64,
+    hits: AtomicU64,
+}
+
+/// Per-motif time spent, windows scanned and hits produced by a scan (`--profile`), so
+/// wide or low-information matrices that dominate the run time can be found
+#[derive(Debug, Default)]
+pub struct ScanProfile {
+    motifs: HashMap<String, MotifCounters>,
+}
+
+impl ScanProfile {
+    /// An empty profile of the motifs of `ewm`
+    pub fn new(ewm: &EWMCollection) -> Self {
+        ScanProfile {
+            motifs: ewm
+                .keys()
+                .map(|id| (id.clone(), MotifCounters::default()))
+                .collect(),
+        }
+    }
+
+    /// Adds one scan of a sequence by `motif_id`; called concurrently by the scanning threads
+    pub fn record(&self, motif_id: &str, elapsed: Duration, windows: usize, hits: usize) {
+        if let Some(counters) = self.motifs.get(motif_id) {
+            counters
+                .nanos
+                .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+            counters
+                .windows
+                .fetch_add(windows as u64, Ordering::Relaxed);
+            counters.hits.fetch_add(hits as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// One row per motif, slowest first; the information content is computed from the
+    /// energies at `rt`
+    fn rows(&self, ewm: &EWMCollection, rt: f64) -> Result<Vec<ProfileRow>, ScannerError> {
+        let mut rows = Vec::with_capacity(self.motifs.len());
+        for (id, counters) in &self.motifs {
+            let matrix = &ewm[id];
+            // energies are -RT ln(p / p_max), so exp(-E / RT) gives the probabilities back
+            let probabilities: Vec<[f64; 4]> = matrix_rows(matrix)
+                .map_err(|e| ScannerError::PwmError(e.to_string()))?
+                .iter()
+                .map(|row| row.map(|energy| (-energy / rt).exp()))
+                .collect();
+            let information_bits = pwm_from_rows(&probabilities)
+                .and_then(|pwm| information_content(&pwm))
+                .map_err(|e| ScannerError::PwmError(e.to_string()))?
+                .iter()
+                .sum();
+            rows.push(ProfileRow {
+                motif_id: id.clone(),
+                width: matrix.height() as i32,
+                information_bits,
+                seconds: counters.nanos.load(Ordering::Relaxed) as f64 / 1e9,
+                windows: counters.windows.load(Ordering::Relaxed),
+                hits: counters.hits.load(Ordering::Relaxed),
+            });
+        }
+        rows.sort_by(|a, b| {
+            b.seconds
+                .total_cmp(&a.seconds)
+                .then_with(|| a.motif_id.cmp(&b.motif_id))
+        });
+        Ok(rows)
+    }
+
+    /// The profile table: motif_id, width, information_bits, seconds, time_share (of the
+    /// summed scan time), windows, hits and hits_per_million_windows, slowest motif first
+    pub fn frame(&self, ewm: &EWMCollection, rt: f64) -> Result<DataFrame, ScannerError> {
+        let rows = self.rows(ewm, rt)?;
+        let total: f64 = rows.iter().map(|row| row.seconds).sum();
+        Ok(df!(
+            "motif_id" => rows.iter().map(|row| row.motif_id.as_str()).collect::<Vec<_>>(),
+            "width" => rows.iter().map(|row| row.width).collect::<Vec<_>>(),
+            "information_bits" => rows.iter().map(|row| row.information_bits).collect::<Vec<_>>(),
+            "seconds" => rows.iter().map(|row| row.seconds).collect::<Vec<_>>(),
+            "time_share" => rows.iter().map(|row| row.share(total)).collect::<Vec<_>>(),
+            "windows" => rows.iter().map(|row| row.windows).collect::<Vec<_>>(),
+            "hits" => rows.iter().map(|row| row.hits).collect::<Vec<_>>(),
+            "hits_per_million_windows" => rows
+                .iter()
+                .map(|row| row.hits_per_million_windows())
+                .collect::<Vec<_>>(),
+        )?)
+    }
+
+    /// Writes the profile table to `{OUTPUT_FILE}.profile.csv` and logs the slowest motifs
+    pub fn save(
+        &self,
+        ewm: &EWMCollection,
+        rt: f64,
+        output_file: &str,
+    ) -> Result<(), ScannerError> {
+        let mut table = self.frame(ewm, rt)?;
+        let path = profile_path(output_file);
+        let mut file = fs::File::create(&path)?;
+        CsvWriter::new(&mut file).finish(&mut table)?;
+
+        let rows = self.rows(ewm, rt)?;
+        let total: f64 = rows.iter().map(|row| row.seconds).sum();
+        for row in rows.iter().take(TOP_MOTIFS) {
+            info!(
+                motif = %row.motif_id,
+                width = row.width,
+                information_bits = row.information_bits,
+                seconds = row.seconds,
+                percent = 100.0 * row.share(total),
+                hits = row.hits,
+                "motif profile"
+            );
+        }
+        info!(file = %path, motifs = table.height(), "profile written");
+        Ok(())
+    }
+}
+
+/// Profile of one motif
+#[derive(Debug, Clone)]
+struct ProfileRow {
+    motif_id: String,
+    width: i32,
+    information_bits: f64,
+    seconds: f64,
+    windows: u64,
+    hits: u64,
+}
+
+impl ProfileRow {
+    /// Fraction of `total` seconds spent on this motif
+    fn share(&self, total: f64) -> f64 {
+        if total > 0.0 {
+            self.seconds / total
+        } else {
+            0.0
+        }
+    }
+
+    fn hits_per_million_windows(&self) -> f64 {
+        if self.windows > 0 {
+            self.hits as f64 * 1e6 / self.windows as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Path of the per-motif profile of the scan writing `output_file`
+pub fn profile_path(output_file: &str) -> String {
+    format!("{}.profile.csv", output_file)
+}
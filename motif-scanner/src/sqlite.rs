@@ -0,0 +1,96 @@
+use crate::ScannerError;
+use polars::prelude::*;
+use rusqlite::{params_from_iter, types::Value, Connection};
+
+fn sql_type(dtype: &DataType) -> &'static str {
+    if dtype.is_integer() || dtype.is_bool() {
+        "INTEGER"
+    } else if dtype.is_float() {
+        "REAL"
+    } else {
+        "TEXT"
+    }
+}
+
+fn sql_value(value: AnyValue) -> Value {
+    match value {
+        AnyValue::Null => Value::Null,
+        AnyValue::Boolean(b) => Value::Integer(b as i64),
+        AnyValue::String(s) => Value::Text(s.to_string()),
+        AnyValue::StringOwned(s) => Value::Text(s.to_string()),
+        v if v.is_integer() => Value::Integer(v.extract::<i64>().unwrap_or_default()),
+        v if v.is_float() => Value::Real(v.extract::<f64>().unwrap_or_default()),
+        v => Value::Text(v.to_string()),
+    }
+}
+
+/// Writes the hits table into an SQLite database, replacing any previous `hits` table.
+///
+/// Indexes on `motif` and `label` keep interactive queries fast on scans with millions of
+/// hits. When `metadata` is given, its key/value pairs are stored in a `run_metadata` table.
+pub fn save_sqlite(
+    df: &DataFrame,
+    output_file: &str,
+    metadata: Option<&[(String, String)]>,
+) -> Result<(), ScannerError> {
+    let mut conn = Connection::open(output_file)?;
+    let tx = conn.transaction()?;
+
+    let columns: Vec<(String, &'static str)> = df
+        .get_columns()
+        .iter()
+        .map(|c| (c.name().to_string(), sql_type(c.dtype())))
+        .collect();
+    let definitions: Vec<String> = columns
+        .iter()
+        .map(|(name, ty)| format!("\"{}\" {}", name, ty))
+        .collect();
+
+    tx.execute("DROP TABLE IF EXISTS hits", [])?;
+    tx.execute(
+        &format!("CREATE TABLE hits ({})", definitions.join(", ")),
+        [],
+    )?;
+
+    {
+        let placeholders = vec!["?"; columns.len()].join(", ");
+        let mut insert = tx.prepare(&format!("INSERT INTO hits VALUES ({})", placeholders))?;
+        for row in 0..df.height() {
+            let values = df
+                .get_columns()
+                .iter()
+                .map(|c| c.get(row).map(sql_value))
+                .collect::<Result<Vec<_>, _>>()?;
+            insert.execute(params_from_iter(values))?;
+        }
+    }
+
+    for index_column in ["motif", "label"] {
+        if columns.iter().any(|(name, _)| name == index_column) {
+            tx.execute(
+                &format!(
+                    "CREATE INDEX IF NOT EXISTS idx_hits_{0} ON hits (\"{0}\")",
+                    index_column
+                ),
+                [],
+            )?;
+        }
+    }
+
+    if let Some(metadata) = metadata {
+        tx.execute("DROP TABLE IF EXISTS run_metadata", [])?;
+        tx.execute(
+            "CREATE TABLE run_metadata (key TEXT PRIMARY KEY, value TEXT)",
+            [],
+        )?;
+        for (key, value) in metadata {
+            tx.execute(
+                "INSERT INTO run_metadata VALUES (?1, ?2)",
+                (key.as_str(), value.as_str()),
+            )?;
+        }
+    }
+
+    tx.commit()?;
+    Ok(())
+}
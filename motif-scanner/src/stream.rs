@@ -0,0 +1,368 @@
+use crate::config::{MotifParams, RunConfig};
+use crate::summary::HitSummary;
+use crate::{
+    annotate_hits, carry_columns, filter_sequences, genomic_columns, id_labels, is_fasta,
+    prepare_table, process_sequences, sort_hits, validate_input, ScannerError, Scoring,
+};
+use motif_scanner::io::{default_separator, is_gzip, read_delimited};
+use polars::prelude::*;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use tf_binding_rs::output::write_jsonl;
+use tf_binding_rs::scan::warnings_frame;
+use tf_binding_rs::types::EWMCollection;
+use tracing::debug;
+
+/// An output written chunk by chunk, so the hits of a run never have to be in memory at once
+enum ChunkWriter {
+    Csv {
+        file: File,
+        header: bool,
+    },
+    Jsonl(BufWriter<File>),
+    /// The batched writer needs the schema, so it is created with the first chunk
+    Parquet {
+        file: Option<File>,
+        row_group_size: Option<usize>,
+        writer: Option<Box<polars::io::parquet::write::BatchedWriter<File>>>,
+    },
+}
+
+impl ChunkWriter {
+    /// Whether an output path can be written chunk by chunk (CSV, JSON Lines and Parquet)
+    fn can_stream(path: &str) -> bool {
+        !matches!(
+            Path::new(path).extension().and_then(|ext| ext.to_str()),
+            Some("sqlite" | "sqlite3" | "db" | "bed" | "bb" | "bigBed" | "bigbed")
+        )
+    }
+
+    fn create(path: &str, row_group_size: Option<usize>) -> Result<ChunkWriter, ScannerError> {
+        let file = File::create(path)?;
+        Ok(
+            match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+                Some("jsonl" | "ndjson") => ChunkWriter::Jsonl(BufWriter::new(file)),
+                Some("parquet") => ChunkWriter::Parquet {
+                    file: Some(file),
+                    row_group_size,
+                    writer: None,
+                },
+                _ => ChunkWriter::Csv { file, header: true },
+            },
+        )
+    }
+
+    fn write(&mut self, df: &mut DataFrame) -> Result<(), ScannerError> {
+        match self {
+            ChunkWriter::Csv { file, header } => {
+                CsvWriter::new(file).include_header(*header).finish(df)?;
+                *header = false;
+            }
+            ChunkWriter::Jsonl(writer) => {
+                write_jsonl(df, writer).map_err(|e| ScannerError::Output(e.to_string()))?;
+            }
+            ChunkWriter::Parquet {
+                file,
+                row_group_size,
+                writer,
+            } => {
+                if writer.is_none() {
+                    let file = file.take().expect("parquet file is opened with the writer");
+                    *writer = Some(Box::new(
+                        ParquetWriter::new(file)
+                            .with_compression(ParquetCompression::Snappy)
+                            .with_row_group_size(*row_group_size)
+                            .batched(&df.schema())?,
+                    ));
+                }
+                if let Some(writer) = writer {
+                    writer.write_batch(df)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), ScannerError> {
+        match self {
+            ChunkWriter::Csv { .. } => {}
+            ChunkWriter::Jsonl(mut writer) => std::io::Write::flush(&mut writer)?,
+            ChunkWriter::Parquet { writer, .. } => {
+                if let Some(writer) = writer {
+                    writer.finish()?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether every output of the run can be streamed
+pub fn can_stream(run: &RunConfig) -> bool {
+    run.checkpoint.is_none()
+        && run
+            .output_files
+            .iter()
+            .all(|path| ChunkWriter::can_stream(path))
+}
+
+/// Scans `df` in chunks of `chunk_size` sequences and appends the hits of every chunk to all
+/// outputs of the run. Returns an empty frame with the schema of the written hits, their
+/// summary and the scan warnings.
+pub fn scan_streaming(
+    run: &RunConfig,
+    df: &DataFrame,
+    ewm: &EWMCollection,
+    params: &HashMap<String, MotifParams>,
+    scoring: Scoring,
+    chunk_size: usize,
+) -> Result<(DataFrame, HitSummary, DataFrame), ScannerError> {
+    let mut writers = create_writers(run)?;
+    let mut schema = None;
+    let mut summary = HitSummary::default();
+    let mut warnings = warnings_frame(&[]).map_err(|e| ScannerError::Output(e.to_string()))?;
+    // an empty input still scans one (empty) chunk, so the outputs get their columns
+    for offset in (0..df.height().max(1)).step_by(chunk_size) {
+        let chunk = df.slice(offset as i64, chunk_size);
+        let (hits, chunk_warnings) =
+            scan_chunk(run, &chunk, ewm, params, offset, scoring, &mut writers)?;
+        warnings.vstack_mut(&chunk_warnings)?;
+        summary.add(&hits)?;
+        schema.get_or_insert_with(|| hits.clear());
+        debug!(
+            sequences = offset + chunk.height(),
+            hits = summary.hits(),
+            "chunk written"
+        );
+    }
+    for writer in writers {
+        writer.finish()?;
+    }
+
+    Ok((schema.unwrap_or_default(), summary, warnings))
+}
+
+/// Outcome of `scan_input_chunked()`
+pub struct ChunkedScan {
+    /// Empty frame with the schema of the written hits
+    pub schema: DataFrame,
+    pub summary: HitSummary,
+    /// Records skipped while reading and scanning, in input order
+    pub warnings: DataFrame,
+    /// Sequences scanned, after the skipped records are dropped
+    pub sequences: usize,
+}
+
+/// Reads the CSV or Parquet input of the run `rows` records at a time (`--chunk-rows`)
+/// and filters, scans and appends the hits of every chunk to all outputs, so neither the
+/// input table nor the hits are ever in memory at once. The hits are those of an
+/// unchunked run, except that duplicate labels are only resolved within a chunk.
+pub fn scan_input_chunked(
+    run: &RunConfig,
+    ewm: &EWMCollection,
+    params: &HashMap<String, MotifParams>,
+    scoring: Scoring,
+    rows: usize,
+) -> Result<ChunkedScan, ScannerError> {
+    let path = run.data_file.as_str();
+    if is_fasta(path) {
+        return Err(ScannerError::Config(
+            "--chunk-rows needs a CSV or Parquet input".to_string(),
+        ));
+    }
+    if is_gzip(Path::new(path)) {
+        return Err(ScannerError::Config(
+            "--chunk-rows needs an uncompressed input".to_string(),
+        ));
+    }
+    if !can_stream(run) {
+        return Err(ScannerError::Config(
+            "--chunk-rows needs CSV, JSON Lines or Parquet outputs".to_string(),
+        ));
+    }
+    let parquet = Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext == "parquet");
+    let mut lazy = if parquet {
+        LazyFrame::scan_parquet(path, ScanArgsParquet::default())?
+    } else {
+        read_delimited(path, run.separator)?
+    };
+    if lazy
+        .collect_schema()?
+        .get(&run.input_columns.sequence)
+        .is_none()
+    {
+        return Err(ScannerError::MissingSequenceColumn);
+    }
+
+    let mut input = InputChunks {
+        run,
+        ewm,
+        params,
+        scoring,
+        writers: create_writers(run)?,
+        rows_read: 0,
+        result: ChunkedScan {
+            schema: DataFrame::default(),
+            summary: HitSummary::default(),
+            warnings: warnings_frame(&[]).map_err(|e| ScannerError::Output(e.to_string()))?,
+            sequences: 0,
+        },
+    };
+    if parquet {
+        // the slice is pushed down to the reader, which only decodes the row groups it needs
+        loop {
+            let chunk = lazy
+                .clone()
+                .slice(input.rows_read as i64, rows as IdxSize)
+                .collect()?;
+            if chunk.height() == 0 {
+                break;
+            }
+            input.scan(chunk)?;
+        }
+    } else {
+        let mut reader = CsvReadOptions::default()
+            .with_has_header(true)
+            .with_chunk_size(rows)
+            .map_parse_options(|options| {
+                options.with_separator(run.separator.unwrap_or_else(|| default_separator(path)))
+            })
+            .try_into_reader_with_file_path(Some(path.into()))?;
+        // the CSV chunk size is only a hint to the reader, so its batches are re-sliced into
+        // chunks of exactly `rows` records, as for Parquet
+        let mut batches = reader.batched_borrowed()?;
+        let mut pending: Option<DataFrame> = None;
+        while let Some(chunks) = batches.next_batches(1)? {
+            for chunk in chunks {
+                let mut buffered = match pending.take() {
+                    Some(mut buffered) => {
+                        buffered.vstack_mut(&chunk)?;
+                        buffered
+                    }
+                    None => chunk,
+                };
+                while buffered.height() >= rows {
+                    input.scan(buffered.slice(0, rows))?;
+                    buffered = buffered.slice(rows as i64, buffered.height() - rows);
+                }
+                pending = Some(buffered);
+            }
+        }
+        if let Some(rest) = pending.filter(|rest| rest.height() > 0) {
+            input.scan(rest)?;
+        }
+    }
+    // an empty input still scans one (empty) chunk, so the outputs get their columns
+    if input.rows_read == 0 {
+        input.scan(lazy.limit(0).collect()?)?;
+    }
+    for writer in input.writers {
+        writer.finish()?;
+    }
+    Ok(input.result)
+}
+
+/// State of `scan_input_chunked()` between chunks
+struct InputChunks<'a> {
+    run: &'a RunConfig,
+    ewm: &'a EWMCollection,
+    params: &'a HashMap<String, MotifParams>,
+    scoring: Scoring<'a>,
+    writers: Vec<ChunkWriter>,
+    /// Records read so far, numbering the records of the next chunk
+    rows_read: usize,
+    result: ChunkedScan,
+}
+
+impl InputChunks<'_> {
+    fn scan(&mut self, chunk: DataFrame) -> Result<(), ScannerError> {
+        let run = self.run;
+        let path = run.data_file.as_str();
+        let read = chunk.height();
+        let chunk = prepare_table(chunk, path, &run.normalization, &run.input_columns)?;
+        let (chunk, skipped) = filter_sequences(
+            chunk,
+            path,
+            self.rows_read,
+            run.duplicate_labels,
+            run.ambiguous_policy,
+        )?;
+        validate_input(run, &chunk, path)?;
+
+        // hits are labelled by the sequences scanned before, as in an unchunked run
+        let offset = self.result.sequences;
+        let (hits, warnings) = scan_chunk(
+            run,
+            &chunk,
+            self.ewm,
+            self.params,
+            offset,
+            self.scoring,
+            &mut self.writers,
+        )?;
+        self.result.warnings.vstack_mut(
+            &warnings_frame(&skipped).map_err(|e| ScannerError::Output(e.to_string()))?,
+        )?;
+        self.result.warnings.vstack_mut(&warnings)?;
+        if self.result.schema.width() == 0 {
+            self.result.schema = hits.clear();
+        }
+        self.rows_read += read;
+        self.result.sequences += chunk.height();
+        self.result.summary.add(&hits)?;
+        debug!(
+            chunk_records = read,
+            records = self.rows_read,
+            sequences = self.result.sequences,
+            hits = self.result.summary.hits(),
+            "chunk written"
+        );
+        Ok(())
+    }
+}
+
+fn create_writers(run: &RunConfig) -> Result<Vec<ChunkWriter>, ScannerError> {
+    run.output_files
+        .iter()
+        .map(|path| ChunkWriter::create(path, run.row_group_size))
+        .collect()
+}
+
+/// Scans one chunk of sequences, whose hits are labelled from `offset`, and appends its
+/// hits to `writers`. Returns the written hits and the scan warnings.
+fn scan_chunk(
+    run: &RunConfig,
+    chunk: &DataFrame,
+    ewm: &EWMCollection,
+    params: &HashMap<String, MotifParams>,
+    offset: usize,
+    scoring: Scoring,
+    writers: &mut [ChunkWriter],
+) -> Result<(DataFrame, DataFrame), ScannerError> {
+    let result = process_sequences(chunk, ewm, params, offset, scoring)?;
+    let mut hits = result.hits;
+    if let Some(parser) = &run.genomic {
+        hits = genomic_columns(&hits, chunk, parser, offset)?;
+    }
+    if let Some(columns) = &run.carry {
+        hits = carry_columns(&hits, chunk, columns, offset)?;
+    }
+    if run.input_columns.id.is_some() {
+        hits = id_labels(&hits, chunk, offset)?;
+    }
+    if let Some(annotations) = &run.tf_annotations {
+        hits = annotate_hits(&hits, annotations)?;
+    }
+    let hits = sort_hits(hits, run)?;
+    let warnings =
+        warnings_frame(&result.warnings).map_err(|e| ScannerError::Output(e.to_string()))?;
+
+    for (writer, path) in writers.iter_mut().zip(&run.output_files) {
+        writer.write(&mut run.output_columns(&hits, path)?)?;
+    }
+    Ok((hits, warnings))
+}
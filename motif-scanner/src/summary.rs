@@ -0,0 +1,200 @@
+use crate::ScannerError;
+use polars::prelude::*;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::time::Duration;
+
+/// Motifs listed in the printed summary; the JSON summary lists all of them
+const PRINTED_MOTIFS: usize = 20;
+
+/// Hits of one motif
+#[derive(Debug, Clone, Default)]
+struct MotifHits {
+    hits: usize,
+    occupancy_sum: f64,
+    sequences: usize,
+}
+
+/// Per-motif counts of the hits of a run, added chunk by chunk so streamed runs get them
+/// without holding the hits
+#[derive(Debug, Clone, Default)]
+pub struct HitSummary {
+    motifs: HashMap<String, MotifHits>,
+    hits: usize,
+    sequences_with_hits: usize,
+}
+
+impl HitSummary {
+    /// Counts one frame of hits; the frames of a run must hold the hits of distinct
+    /// sequences, as the chunks of a scan do
+    pub fn add(&mut self, hits: &DataFrame) -> Result<(), ScannerError> {
+        // labels are row indices, or the IDs of `--id-col`
+        let labels = hits.column("label")?.cast(&DataType::String)?;
+        let labels = labels.str()?;
+        let motifs = hits.column("motif")?.str()?;
+        let occupancies = hits.column("occupancy")?.f64()?;
+
+        let mut motif_sequences: HashSet<(&str, &str)> = HashSet::new();
+        let mut sequences: HashSet<&str> = HashSet::new();
+        for ((label, motif), occupancy) in labels.into_iter().zip(motifs).zip(occupancies) {
+            let (Some(label), Some(motif)) = (label, motif) else {
+                continue;
+            };
+            if !self.motifs.contains_key(motif) {
+                self.motifs.insert(motif.to_string(), MotifHits::default());
+            }
+            let counts = self.motifs.get_mut(motif).expect("inserted above");
+            counts.hits += 1;
+            counts.occupancy_sum += occupancy.unwrap_or_default();
+            if motif_sequences.insert((motif, label)) {
+                counts.sequences += 1;
+            }
+            sequences.insert(label);
+        }
+        self.hits += hits.height();
+        self.sequences_with_hits += sequences.len();
+        Ok(())
+    }
+
+    /// Number of hits counted
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+}
+
+/// Hits of one motif in a `RunSummary`
+#[derive(Debug, Clone, Serialize)]
+pub struct MotifSummary {
+    pub motif: String,
+    pub hits: usize,
+    pub mean_occupancy: f64,
+    /// Sequences with at least one hit of the motif
+    pub sequences_with_hits: usize,
+}
+
+/// Wall time of one stage of a run
+#[derive(Debug, Clone, Serialize)]
+pub struct Stage {
+    pub name: String,
+    pub seconds: f64,
+}
+
+/// What a run found and where its time went, printed at the end of the run and written
+/// to `{OUTPUT_FILE}.summary.json` with `--summary-json`
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    pub sequences: usize,
+    pub sequences_with_hits: usize,
+    pub hits: usize,
+    pub warnings: usize,
+    /// Most hits first
+    pub motifs: Vec<MotifSummary>,
+    pub stages: Vec<Stage>,
+}
+
+impl RunSummary {
+    pub fn new(sequences: usize, warnings: usize, hits: HitSummary) -> Self {
+        let mut motifs: Vec<MotifSummary> = hits
+            .motifs
+            .into_iter()
+            .map(|(motif, counts)| MotifSummary {
+                motif,
+                hits: counts.hits,
+                mean_occupancy: counts.occupancy_sum / counts.hits.max(1) as f64,
+                sequences_with_hits: counts.sequences,
+            })
+            .collect();
+        motifs.sort_by(|a, b| b.hits.cmp(&a.hits).then_with(|| a.motif.cmp(&b.motif)));
+        RunSummary {
+            sequences,
+            sequences_with_hits: hits.sequences_with_hits,
+            hits: hits.hits,
+            warnings,
+            motifs,
+            stages: Vec::new(),
+        }
+    }
+
+    /// Records the time of a stage, after the stages recorded before
+    pub fn stage(&mut self, name: &str, elapsed: Duration) -> &mut Self {
+        self.stages.push(Stage {
+            name: name.to_string(),
+            seconds: elapsed.as_secs_f64(),
+        });
+        self
+    }
+
+    /// Records the time of a stage that ran before the stages recorded so far
+    pub fn first_stage(&mut self, name: &str, elapsed: Duration) -> &mut Self {
+        self.stages.insert(
+            0,
+            Stage {
+                name: name.to_string(),
+                seconds: elapsed.as_secs_f64(),
+            },
+        );
+        self
+    }
+
+    /// Writes the summary as JSON next to the primary output
+    pub fn write_json(&self, output_file: &str) -> Result<(), ScannerError> {
+        let json =
+            serde_json::to_string_pretty(self).map_err(|e| ScannerError::Output(e.to_string()))?;
+        fs::write(summary_path(output_file), json)?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "sequences:      {} ({} with hits)",
+            self.sequences, self.sequences_with_hits
+        )?;
+        writeln!(f, "hits:           {}", self.hits)?;
+        writeln!(f, "warnings:       {}", self.warnings)?;
+        if !self.motifs.is_empty() {
+            let width = self
+                .motifs
+                .iter()
+                .take(PRINTED_MOTIFS)
+                .map(|m| m.motif.len())
+                .max()
+                .unwrap_or_default()
+                .max("motif".len());
+            writeln!(
+                f,
+                "{:<width$}  {:>10}  {:>10}  {:>14}",
+                "motif", "hits", "sequences", "mean_occupancy"
+            )?;
+            for motif in self.motifs.iter().take(PRINTED_MOTIFS) {
+                writeln!(
+                    f,
+                    "{:<width$}  {:>10}  {:>10}  {:>14.3}",
+                    motif.motif, motif.hits, motif.sequences_with_hits, motif.mean_occupancy
+                )?;
+            }
+            if self.motifs.len() > PRINTED_MOTIFS {
+                writeln!(
+                    f,
+                    "... {} more motifs with hits",
+                    self.motifs.len() - PRINTED_MOTIFS
+                )?;
+            }
+        }
+        let stages: Vec<String> = self
+            .stages
+            .iter()
+            .map(|stage| format!("{} {:.2} s", stage.name, stage.seconds))
+            .collect();
+        write!(f, "stages:         {}", stages.join(", "))
+    }
+}
+
+/// Path of the JSON summary of the scan writing `output_file`
+pub fn summary_path(output_file: &str) -> String {
+    format!("{}.summary.json", output_file)
+}
@@ -0,0 +1,238 @@
+use polars::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const MOTIFS: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../tf-binding-rs/tests/data/tdmMotifs.meme"
+);
+
+/// A fresh scratch directory for the test `name`
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("motif_scanner_{name}"));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn scanner(input: &Path, output: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_motif-scanner"))
+        .arg(input)
+        .arg(MOTIFS)
+        .arg(output)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Runs motif-scanner on `input`, writing the hits to `output`, checks that it succeeded and
+/// returns its log
+fn scan(input: &Path, output: &Path, args: &[&str]) -> String {
+    let out = scanner(input, output, args);
+    let log = String::from_utf8_lossy(&out.stderr).to_string();
+    assert!(out.status.success(), "{}", log);
+    log
+}
+
+/// `n` pseudo-random 40 bp sequences labelled by their row, every 7th with an ambiguous base
+/// so that it is skipped with a warning
+fn sequences(n: usize) -> DataFrame {
+    let mut state: u64 = 42;
+    let sequences: Vec<String> = (0..n)
+        .map(|row| {
+            (0..40)
+                .map(|i| {
+                    state = state
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+                    if row % 7 == 3 && i == 20 {
+                        'N'
+                    } else {
+                        b"ACGT"[(state >> 62) as usize] as char
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let labels: Vec<String> = (0..n).map(|row| format!("seq{row}")).collect();
+    df!("label" => labels, "sequence" => sequences).unwrap()
+}
+
+fn write_csv(df: &mut DataFrame, path: &Path) {
+    CsvWriter::new(fs::File::create(path).unwrap())
+        .finish(df)
+        .unwrap();
+}
+
+fn read_csv(path: &Path) -> DataFrame {
+    CsvReadOptions::default()
+        .with_has_header(true)
+        .try_into_reader_with_file_path(Some(path.to_path_buf()))
+        .unwrap()
+        .finish()
+        .unwrap()
+}
+
+/// Rows of `df` sorted by `columns`, for outputs whose row order differs
+fn sorted(df: &DataFrame, columns: &[&str]) -> DataFrame {
+    df.sort(columns.to_vec(), SortMultipleOptions::default())
+        .unwrap()
+}
+
+fn labels(df: &DataFrame) -> Vec<String> {
+    let labels = df.column("label").unwrap().cast(&DataType::String).unwrap();
+    labels
+        .str()
+        .unwrap()
+        .into_iter()
+        .map(|l| l.unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn test_id_col_labels_hits() {
+    let dir = scratch("id_col");
+    let input = dir.join("oligos.csv");
+    fs::write(
+        &input,
+        "oligo_id,oligo_seq\n\
+         enhA,GGGCTTAGTAACGTTACGGAAGGGCGTGGCCTTTAAATTCCGGGG\n\
+         enhB,AGCTTTTTAATAGAGTCAGCAAAACTGAAGCCTCTTCTCATCCTCTGATAATCACTGACC\n",
+    )
+    .unwrap();
+
+    let by_row = dir.join("by_row.csv");
+    scan(&input, &by_row, &["--sequence-col", "oligo_seq"]);
+    let by_row = read_csv(&by_row);
+    assert!(by_row.height() > 0);
+    let expected: Vec<String> = labels(&by_row)
+        .iter()
+        .map(|l| ["enhA", "enhB"][l.parse::<usize>().unwrap()].to_string())
+        .collect();
+
+    // the in-memory, chunked and checkpointed paths label the hits with the IDs
+    for args in [vec![], vec!["--chunk-rows", "1"], vec!["--checkpoint", "1"]] {
+        let output = dir.join("by_id.csv");
+        let mut args = args;
+        args.extend(["--sequence-col", "oligo_seq", "--id-col", "oligo_id"]);
+        scan(&input, &output, &args);
+        let by_id = read_csv(&output);
+        assert_eq!(labels(&by_id), expected);
+        assert!(by_id
+            .drop("label")
+            .unwrap()
+            .equals(&by_row.drop("label").unwrap()));
+    }
+}
+
+#[test]
+fn test_stale_schema_sidecar_removed() {
+    let dir = scratch("schema");
+    let input = dir.join("seqs.csv");
+    fs::write(
+        &input,
+        "label,sequence\n0,AGCTTTTTAATAGAGTCAGCAAAACTGAAGCCTCTTCTCATCCTCTGATAATCACTGACC\n",
+    )
+    .unwrap();
+    let output = dir.join("hits.csv");
+    let sidecar = dir.join("hits.csv.schema.json");
+
+    scan(&input, &output, &["--output-schema"]);
+    assert!(sidecar.exists());
+    scan(&input, &output, &[]);
+    assert!(!sidecar.exists());
+
+    // outputs without hits always get one
+    scan(&input, &output, &["--cutoff", "1"]);
+    assert!(sidecar.exists());
+}
+
+#[test]
+fn test_checkpoint_resume() {
+    let dir = scratch("checkpoint");
+    let input = dir.join("seqs.csv");
+    write_csv(&mut sequences(30), &input);
+    let args = ["--cutoff", "0.01", "--checkpoint", "8"];
+    let expected = dir.join("expected.csv");
+    scan(&input, &expected, &args);
+
+    // a run interrupted after 2 of its 4 chunks: the output cannot be written, so the
+    // checkpoint is kept, and the last chunks are then dropped from it
+    let output = dir.join("hits.csv");
+    let checkpoint = dir.join("hits.csv.checkpoint");
+    let interrupt = || {
+        let _ = fs::remove_file(&output);
+        fs::create_dir(&output).unwrap();
+        assert!(!scanner(&input, &output, &args).status.success());
+        fs::remove_dir(&output).unwrap();
+
+        let state_path = checkpoint.join("state.json");
+        let mut state: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&state_path).unwrap()).unwrap();
+        assert_eq!(state["counts"]["chunks_completed"], 4);
+        state["counts"]["chunks_completed"] = 2.into();
+        fs::write(&state_path, state.to_string()).unwrap();
+        for chunk in 2..4 {
+            fs::remove_file(checkpoint.join(format!("part-{chunk:06}.parquet"))).unwrap();
+            fs::remove_file(checkpoint.join(format!("warnings-{chunk:06}.parquet"))).unwrap();
+        }
+    };
+
+    interrupt();
+    let mut resume = args.to_vec();
+    resume.extend(["--resume", "--log-format", "json"]);
+    let log = scan(&input, &output, &resume);
+    assert!(log.contains("resuming after completed chunks"), "{}", log);
+    assert!(log.contains("\"chunks\":2"), "{}", log);
+    assert_eq!(
+        fs::read_to_string(&output).unwrap(),
+        fs::read_to_string(&expected).unwrap()
+    );
+    assert_eq!(
+        fs::read_to_string(dir.join("hits.csv.warnings.csv")).unwrap(),
+        fs::read_to_string(dir.join("expected.csv.warnings.csv")).unwrap()
+    );
+    assert!(!checkpoint.exists());
+
+    // a checkpoint of other parameters is not resumed
+    interrupt();
+    let out = scanner(
+        &input,
+        &output,
+        &["--cutoff", "0.05", "--checkpoint", "8", "--resume"],
+    );
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("different inputs or parameters"));
+    assert!(checkpoint.join("part-000001.parquet").exists());
+}
+
+#[test]
+fn test_chunk_rows_match_unchunked() {
+    let dir = scratch("chunk_rows");
+    let mut df = sequences(30);
+    let csv = dir.join("seqs.csv");
+    write_csv(&mut df, &csv);
+    let parquet = dir.join("seqs.parquet");
+    ParquetWriter::new(fs::File::create(&parquet).unwrap())
+        .with_row_group_size(Some(4))
+        .finish(&mut df)
+        .unwrap();
+
+    for input in [csv, parquet] {
+        let expected = dir.join("expected.csv");
+        scan(&input, &expected, &["--cutoff", "0.01"]);
+        // 7 does not divide the 30 rows, nor the row groups of 4
+        let output = dir.join("chunked.csv");
+        scan(&input, &output, &["--cutoff", "0.01", "--chunk-rows", "7"]);
+
+        let keys = ["label", "position", "motif", "strand"];
+        let hits = sorted(&read_csv(&output), &keys);
+        assert!(hits.height() > 0);
+        assert!(hits.equals(&sorted(&read_csv(&expected), &keys)));
+        let warnings = |path: &str| sorted(&read_csv(&dir.join(path)), &["index", "motif"]);
+        let skipped = warnings("chunked.csv.warnings.csv");
+        assert!(skipped.height() > 0);
+        assert!(skipped.equals_missing(&warnings("expected.csv.warnings.csv")));
+    }
+}
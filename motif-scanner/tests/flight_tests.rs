@@ -0,0 +1,133 @@
+#![cfg(feature = "flight")]
+
+use arrow_array::{ArrayRef, RecordBatch, StringArray};
+use arrow_flight::encode::FlightDataEncoderBuilder;
+use arrow_flight::error::FlightError;
+use arrow_flight::{FlightClient, FlightDescriptor};
+use futures::TryStreamExt;
+use std::net::TcpListener;
+use std::process::{Child, Command};
+use std::sync::Arc;
+use std::time::Duration;
+use tf_binding_rs::occupancy::read_pwm_to_ewm;
+use tf_binding_rs::table::ScanTable;
+use tonic::transport::Channel;
+
+const MOTIFS: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../tf-binding-rs/tests/data/tdmMotifs.meme"
+);
+
+/// A `tfbind serve` process, killed when dropped
+struct Server(Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn free_addr() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().to_string()
+}
+
+fn sequence_batch(sequences: &[&str]) -> RecordBatch {
+    let sequences = StringArray::from(sequences.to_vec());
+    RecordBatch::try_from_iter([("sequence", Arc::new(sequences) as ArrayRef)]).unwrap()
+}
+
+async fn exchange(
+    client: &mut FlightClient,
+    command: &str,
+    batches: Vec<RecordBatch>,
+) -> Result<Vec<RecordBatch>, FlightError> {
+    let input = FlightDataEncoderBuilder::new()
+        .with_flight_descriptor(Some(FlightDescriptor::new_cmd(command.to_string())))
+        .build(futures::stream::iter(batches.into_iter().map(Ok)));
+    client.do_exchange(input).await?.try_collect().await
+}
+
+#[test]
+fn test_flight_exchange() {
+    let flight_addr = free_addr();
+    let _server = Server(
+        Command::new(env!("CARGO_BIN_EXE_tfbind"))
+            .args(["serve", "--motif-set", &format!("tdm={}", MOTIFS)])
+            .args(["--addr", &free_addr(), "--flight-addr", &flight_addr])
+            .args(["--max-bases", "200"])
+            .spawn()
+            .unwrap(),
+    );
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async {
+        let mut channel = None;
+        for _ in 0..100 {
+            match Channel::from_shared(format!("http://{}", flight_addr))
+                .unwrap()
+                .connect()
+                .await
+            {
+                Ok(connected) => {
+                    channel = Some(connected);
+                    break;
+                }
+                Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+            }
+        }
+        let mut client = FlightClient::new(channel.expect("Flight service did not start"));
+
+        let batches = vec![
+            sequence_batch(&["GGGCTTAGTAACGTTACGGAAGGGCGTGGCCTTTAAATTCCGGGG"]),
+            sequence_batch(&[
+                "AGCTTTTTAATAGAGTCAGCAAAACTGAAGCCTCTTCTCATCCTCTGATAATCACTGACC",
+                "ACGT",
+            ]),
+        ];
+        let hits = exchange(&mut client, "motif_set=tdm&mu=9", batches.clone())
+            .await
+            .unwrap();
+
+        // the hits of every sequence batch, as the library's batch scanner finds them, with
+        // unlabeled rows labeled by their index in the exchange
+        let table = ScanTable::new(read_pwm_to_ewm(MOTIFS).unwrap(), 9.0, 0.2).unwrap();
+        let mut offset = 0;
+        let mut expected = Vec::new();
+        for batch in &batches {
+            let labels: Vec<String> = (offset..offset + batch.num_rows())
+                .map(|i| i.to_string())
+                .collect();
+            offset += batch.num_rows();
+            let labeled = RecordBatch::try_from_iter([
+                ("label", Arc::new(StringArray::from(labels)) as ArrayRef),
+                ("sequence", batch.column(0).clone()),
+            ])
+            .unwrap();
+            expected.push(table.scan_batch(&labeled).unwrap());
+        }
+        let non_empty = |batches: &[RecordBatch]| -> Vec<RecordBatch> {
+            batches
+                .iter()
+                .filter(|batch| batch.num_rows() > 0)
+                .cloned()
+                .collect()
+        };
+        assert!(!non_empty(&hits).is_empty());
+        assert_eq!(non_empty(&hits), non_empty(&expected));
+
+        let err = exchange(&mut client, "motif_set=jaspar", batches.clone())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("unknown motif set"), "{}", err);
+
+        // the batches exceed --max-bases once the fourth is decoded
+        let mut too_long = batches.clone();
+        too_long.extend(batches);
+        let err = exchange(&mut client, "motif_set=tdm", too_long)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("limit of 200 bp"), "{}", err);
+    });
+}
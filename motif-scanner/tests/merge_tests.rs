@@ -0,0 +1,78 @@
+use polars::prelude::*;
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn merge(inputs: &[&Path], output: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_tfbind"))
+        .arg("merge")
+        .args(inputs)
+        .arg("--output")
+        .arg(output)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn read_csv(path: &Path) -> DataFrame {
+    CsvReadOptions::default()
+        .with_has_header(true)
+        .try_into_reader_with_file_path(Some(path.to_path_buf()))
+        .unwrap()
+        .finish()
+        .unwrap()
+}
+
+#[test]
+fn test_merge_shards() {
+    let dir = std::env::temp_dir().join("motif_scanner_merge");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let header = "label,position,motif,strand,length,occupancy\n";
+    let shard_a = dir.join("a.csv");
+    let shard_b = dir.join("b.csv");
+    let output = dir.join("merged.csv");
+
+    // row index labels: the same row of both shards is two different hits
+    std::fs::write(
+        &shard_a,
+        format!("{header}0,3,NRL,F,11,0.9\n0,3,NRL,F,11,0.9\n"),
+    )
+    .unwrap();
+    std::fs::write(&shard_b, format!("{header}0,3,NRL,F,11,0.9\n")).unwrap();
+    let out = merge(&[&shard_a, &shard_b], &output, &[]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("--add-source"));
+    assert!(merge(&[&shard_a, &shard_b], &output, &["--add-source"])
+        .status
+        .success());
+    let merged = read_csv(&output);
+    assert_eq!(merged.height(), 2);
+    assert_eq!(
+        merged
+            .column("source_file")
+            .unwrap()
+            .str()
+            .unwrap()
+            .n_unique()
+            .unwrap(),
+        2
+    );
+
+    // sequence IDs: a hit found in both shards is kept once, from the first
+    std::fs::write(&shard_a, format!("{header}enhA,3,NRL,F,11,0.9\n")).unwrap();
+    std::fs::write(
+        &shard_b,
+        format!("{header}enhA,3,NRL,F,11,0.9\nenhB,5,NRL,R,11,0.8\n"),
+    )
+    .unwrap();
+    assert!(merge(&[&shard_a, &shard_b], &output, &["--add-source"])
+        .status
+        .success());
+    let merged = read_csv(&output);
+    assert_eq!(merged.height(), 2);
+    let sources = merged.column("source_file").unwrap().str().unwrap();
+    assert!(sources.get(0).unwrap().ends_with("a.csv"));
+    assert!(sources.get(1).unwrap().ends_with("b.csv"));
+    assert!(merge(&[&shard_a, &shard_b], &output, &[]).status.success());
+    assert_eq!(read_csv(&output).height(), 2);
+}
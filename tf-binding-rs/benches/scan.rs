@@ -0,0 +1,88 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use tf_binding_rs::occupancy::{find_hits, LandscapeBuffer, MotifBank};
+use tf_binding_rs::quantized::find_hits_quantized;
+use tf_binding_rs::workload::Workload;
+
+/// Throughput is reported in bp·motifs per second, as by `tfbind bench`
+fn scan_benchmarks(c: &mut Criterion) {
+    let workloads = [
+        Workload {
+            sequences: 100,
+            length: 200,
+            ..Workload::default()
+        },
+        Workload {
+            sequences: 10,
+            length: 10_000,
+            ..Workload::default()
+        },
+        Workload {
+            sequences: 10,
+            length: 1000,
+            motifs: 100,
+            ..Workload::default()
+        },
+    ];
+
+    let mut group = c.benchmark_group("scan");
+    for workload in workloads {
+        let sequences = workload.sequences();
+        let ewms = workload.ewms().unwrap();
+        let bank = MotifBank::new(&ewms).unwrap();
+        let name = format!(
+            "{}x{}bp/{}x{}bp",
+            workload.sequences, workload.length, workload.motifs, workload.motif_length
+        );
+        group.throughput(Throughput::Elements(workload.work() as u64));
+
+        group.bench_with_input(
+            BenchmarkId::new("find_hits", &name),
+            &sequences,
+            |b, seqs| {
+                b.iter(|| {
+                    for seq in seqs {
+                        find_hits(seq, &ewms, 9.0, 0.2).unwrap();
+                    }
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("find_hits_quantized", &name),
+            &sequences,
+            |b, seqs| {
+                b.iter(|| {
+                    for seq in seqs {
+                        find_hits_quantized(seq, &ewms, 9.0, 0.2).unwrap();
+                    }
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("occupancy_landscapes", &name),
+            &sequences,
+            |b, seqs| {
+                b.iter(|| {
+                    for seq in seqs {
+                        bank.occupancy_landscapes(seq, 9.0).unwrap();
+                    }
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("total_landscape", &name),
+            &sequences,
+            |b, seqs| {
+                let mut buffer = LandscapeBuffer::default();
+                b.iter(|| {
+                    for seq in seqs {
+                        bank.total_landscape_into(seq, 9.0, &mut buffer).unwrap();
+                    }
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, scan_benchmarks);
+criterion_main!(benches);
@@ -0,0 +1,388 @@
+use crate::error::MotifError;
+use crate::features::{Aggregation, MotifRole};
+use polars::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+
+/// What is known about the factor behind one motif
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TfAnnotation {
+    /// Motif ID, or the name before the first '_' of the IDs it applies to
+    pub motif_id: String,
+    /// Name of the transcription factor
+    pub tf_name: Option<String>,
+    /// Structural family, e.g. "homeodomain"
+    pub family: Option<String>,
+    /// Whether binding raises or lowers activity
+    pub role: Option<MotifRole>,
+    /// Relative abundance of the factor, e.g. its expression level
+    pub concentration: Option<f64>,
+}
+
+/// Columns of an annotation table besides "motif_id"
+const ANNOTATION_COLUMNS: [&str; 4] = ["tf_name", "family", "role", "concentration"];
+
+/// A table of TF annotations, keyed by motif
+///
+/// Annotations are matched to motifs by full ID, or by the name before the first '_' on
+/// either side, so a table listing `NRL` applies to `NRL_HUMAN.MA0842.1` and to hit tables
+/// that report motifs by name.
+#[derive(Debug, Clone, Default)]
+pub struct TfAnnotations {
+    records: Vec<TfAnnotation>,
+    /// Columns given in the source table, in `ANNOTATION_COLUMNS` order
+    columns: Vec<&'static str>,
+    index: HashMap<String, usize>,
+}
+
+impl TfAnnotations {
+    /// Builds the table from annotations; later records override earlier ones of the same
+    /// motif, and every column is reported by `annotate()`
+    pub fn new(records: Vec<TfAnnotation>) -> Self {
+        Self::with_columns(records, ANNOTATION_COLUMNS.to_vec())
+    }
+
+    fn with_columns(records: Vec<TfAnnotation>, columns: Vec<&'static str>) -> Self {
+        let mut index = HashMap::new();
+        for (i, record) in records.iter().enumerate() {
+            index.insert(record.motif_id.clone(), i);
+        }
+        TfAnnotations {
+            records,
+            columns,
+            index,
+        }
+    }
+
+    /// Reads an annotation table
+    ///
+    /// The file is comma- or tab-separated (tab when the header contains one), with a
+    /// header naming a "motif_id" column and any of "tf_name", "family", "role"
+    /// ("activator" or "repressor") and "concentration"; other columns are ignored, as are
+    /// empty lines and lines starting with '#'. Empty fields are missing values. Fields are
+    /// not quoted.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the annotation table
+    ///
+    /// # Returns
+    /// * `Result<TfAnnotations, MotifError>` - The annotations, one per line
+    ///
+    /// # Errors
+    /// * `MotifError::Io` - If the file cannot be read
+    /// * `MotifError::InvalidFileFormat` - If the header has no "motif_id" column or a line
+    ///   has no motif ID
+    /// * `MotifError::InvalidParameter` - If a role or concentration cannot be parsed
+    ///
+    /// # Example
+    /// ```ignore
+    /// use tf_binding_rs::annotations::TfAnnotations;
+    ///
+    /// // motif_id,tf_name,family,role,concentration
+    /// // NRL_HUMAN.MA0842.1,NRL,bZIP,activator,12.5
+    /// let annotations = TfAnnotations::read("tf_annotations.csv").unwrap();
+    /// ```
+    pub fn read(path: &str) -> Result<Self, MotifError> {
+        let text = std::fs::read_to_string(path)?;
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'));
+        let Some((_, header)) = lines.next() else {
+            return Err(MotifError::InvalidFileFormat(format!(
+                "{}: empty annotation table",
+                path
+            )));
+        };
+        let separator = if header.contains('\t') { '\t' } else { ',' };
+        let names: Vec<&str> = header.split(separator).map(str::trim).collect();
+        let position = |name: &str| names.iter().position(|n| *n == name);
+        let Some(id_column) = position("motif_id") else {
+            return Err(MotifError::InvalidFileFormat(format!(
+                "{}: the header has no motif_id column",
+                path
+            )));
+        };
+        let columns: Vec<&'static str> = ANNOTATION_COLUMNS
+            .into_iter()
+            .filter(|name| position(name).is_some())
+            .collect();
+
+        let mut records = Vec::new();
+        for (number, line) in lines {
+            let fields: Vec<&str> = line.split(separator).map(str::trim).collect();
+            let field = |column: Option<usize>| {
+                column
+                    .and_then(|i| fields.get(i))
+                    .filter(|value| !value.is_empty())
+                    .copied()
+            };
+            let Some(motif_id) = field(Some(id_column)) else {
+                return Err(MotifError::InvalidFileFormat(format!(
+                    "{} line {}: no motif_id",
+                    path,
+                    number + 1
+                )));
+            };
+            let concentration = field(position("concentration"))
+                .map(|value| {
+                    value.parse::<f64>().map_err(|_| {
+                        MotifError::invalid_parameter(
+                            "concentration",
+                            value,
+                            format!("line {} of {} is not a number", number + 1, path),
+                        )
+                    })
+                })
+                .transpose()?;
+            records.push(TfAnnotation {
+                motif_id: motif_id.to_string(),
+                tf_name: field(position("tf_name")).map(str::to_string),
+                family: field(position("family")).map(str::to_string),
+                role: field(position("role")).map(str::parse).transpose()?,
+                concentration,
+            });
+        }
+        Ok(Self::with_columns(records, columns))
+    }
+
+    /// Number of annotated motifs
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Whether no motif is annotated
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Annotation of a motif, by full ID or by the name before the first '_'
+    pub fn get(&self, motif: &str) -> Option<&TfAnnotation> {
+        let name = motif.split('_').next().unwrap_or(motif);
+        self.index
+            .get(motif)
+            .or_else(|| self.index.get(name))
+            .copied()
+            .or_else(|| {
+                // names in hit tables against full IDs in the annotations
+                self.records
+                    .iter()
+                    .rposition(|r| r.motif_id.split('_').next() == Some(motif))
+            })
+            .map(|i| &self.records[i])
+    }
+
+    /// Roles of the annotated motifs, e.g. for `features::predicted_activity()`
+    pub fn roles(&self) -> HashMap<String, MotifRole> {
+        self.records
+            .iter()
+            .filter_map(|r| r.role.map(|role| (r.motif_id.clone(), role)))
+            .collect()
+    }
+
+    /// Concentration proxies of the annotated motifs, e.g. as the weights of
+    /// `features::predicted_activity()`
+    pub fn concentrations(&self) -> HashMap<String, f64> {
+        self.records
+            .iter()
+            .filter_map(|r| r.concentration.map(|c| (r.motif_id.clone(), c)))
+            .collect()
+    }
+
+    /// Family of a motif, or `None` if it is unannotated or has no family
+    pub fn family(&self, motif: &str) -> Option<&str> {
+        self.get(motif).and_then(|a| a.family.as_deref())
+    }
+
+    /// Combines the motif columns of a feature matrix into one column per family
+    ///
+    /// Database entries of one family (e.g. the many ETS motifs) bind near-identical sites,
+    /// so their columns are strongly collinear; one column per family is a more stable
+    /// input for a model. Motifs without a family keep their own column. Nulls are ignored,
+    /// and a row with nulls in all columns of a family stays null.
+    ///
+    /// # Arguments
+    /// * `features` - Feature matrix, e.g. from `features::occupancy_features()`; the
+    ///   "label" column and other non-numeric columns are kept as they are
+    /// * `aggregation` - How the columns of a family are combined
+    ///
+    /// # Returns
+    /// * `Result<DataFrame, MotifError>` - The non-numeric columns followed by one f64
+    ///   column per family or unannotated motif, in sorted order
+    ///
+    /// # Errors
+    /// * `MotifError::DataError` - If a column cannot be converted to f64
+    ///
+    /// # Example
+    /// ```ignore
+    /// use tf_binding_rs::features::{occupancy_features, Aggregation};
+    ///
+    /// let features = occupancy_features(&sequences, &ewms, 9.0)?;
+    /// let families = annotations.family_features(&features, Aggregation::Max)?;
+    /// ```
+    pub fn family_features(
+        &self,
+        features: &DataFrame,
+        aggregation: Aggregation,
+    ) -> Result<DataFrame, MotifError> {
+        let data_error = |e: PolarsError| MotifError::DataError(e.to_string());
+        let mut columns = Vec::new();
+        let mut families: BTreeMap<&str, Vec<Float64Chunked>> = BTreeMap::new();
+        for column in features.get_columns() {
+            let name = column.name().as_str();
+            if name == "label" || !column.dtype().is_numeric() {
+                columns.push(column.clone());
+                continue;
+            }
+            let values = column.cast(&DataType::Float64).map_err(data_error)?;
+            families
+                .entry(self.family(name).unwrap_or(name))
+                .or_default()
+                .push(values.f64().map_err(data_error)?.clone());
+        }
+
+        for (family, members) in families {
+            let combined: Vec<Option<f64>> = (0..features.height())
+                .map(|row| {
+                    members
+                        .iter()
+                        .filter_map(|values| values.get(row))
+                        .reduce(|a, b| match aggregation {
+                            Aggregation::Sum => a + b,
+                            Aggregation::Max => a.max(b),
+                        })
+                })
+                .collect();
+            columns.push(Column::new(family.into(), combined));
+        }
+        DataFrame::new(columns).map_err(data_error)
+    }
+
+    /// Reports the hits of a table by family instead of by motif
+    ///
+    /// The motif column is replaced by the family of each hit's motif (the motif itself
+    /// without a family) and the original motif is kept in a "source_motif" column, so
+    /// `features::hit_matrix()` and other per-motif summaries of the result work per family.
+    ///
+    /// # Arguments
+    /// * `hits` - Hit table with a string motif column
+    /// * `motif_column` - Name of the motif column, "motif" in hit tables
+    ///
+    /// # Returns
+    /// * `Result<DataFrame, MotifError>` - `hits` with the motif column replaced and
+    ///   "source_motif" added
+    ///
+    /// # Errors
+    /// * `MotifError::DataError` - If the motif column is missing or not a string column
+    ///
+    /// # Example
+    /// ```ignore
+    /// use tf_binding_rs::features::{hit_matrix, Aggregation};
+    ///
+    /// let families = annotations.family_hits(&result.hits, "motif")?;
+    /// let matrix = hit_matrix(&families, Some(&sequences), Aggregation::Max)?;
+    /// ```
+    pub fn family_hits(
+        &self,
+        hits: &DataFrame,
+        motif_column: &str,
+    ) -> Result<DataFrame, MotifError> {
+        let data_error = |e: PolarsError| MotifError::DataError(e.to_string());
+        let motifs = hits
+            .column(motif_column)
+            .and_then(|c| c.str())
+            .map_err(data_error)?;
+
+        let mut cache: HashMap<&str, &str> = HashMap::new();
+        let families: Vec<Option<&str>> = motifs
+            .into_iter()
+            .map(|motif| {
+                motif.map(|m| {
+                    *cache
+                        .entry(m)
+                        .or_insert_with(|| self.family(m).unwrap_or(m))
+                })
+            })
+            .collect();
+
+        let mut out = hits.clone();
+        let source = motifs.clone().with_name("source_motif".into());
+        out.with_column(source.into_column()).map_err(data_error)?;
+        out.with_column(Column::new(motif_column.into(), families))
+            .map_err(data_error)?;
+        Ok(out)
+    }
+
+    /// Names of the columns `annotate()` adds
+    pub fn columns(&self) -> &[&'static str] {
+        &self.columns
+    }
+
+    /// Adds the annotation of every row's motif to a table
+    ///
+    /// # Arguments
+    /// * `df` - Table with a motif column, e.g. hits
+    /// * `motif_column` - Name of the motif column, "motif" in hit tables
+    ///
+    /// # Returns
+    /// * `Result<DataFrame, MotifError>` - `df` with the columns of `columns()`, in that
+    ///   order; rows of unannotated motifs get nulls
+    ///
+    /// # Errors
+    /// * `MotifError::DataError` - If the motif column is missing or not a string column, or
+    ///   an annotation column already exists
+    pub fn annotate(&self, df: &DataFrame, motif_column: &str) -> Result<DataFrame, MotifError> {
+        let data_error = |e: PolarsError| MotifError::DataError(e.to_string());
+        let motifs = df
+            .column(motif_column)
+            .and_then(|c| c.str())
+            .map_err(data_error)?;
+
+        // one lookup per distinct motif, not per row
+        let mut cache: HashMap<&str, Option<&TfAnnotation>> = HashMap::new();
+        let annotations: Vec<Option<&TfAnnotation>> = motifs
+            .into_iter()
+            .map(|motif| motif.and_then(|m| *cache.entry(m).or_insert_with(|| self.get(m))))
+            .collect();
+
+        let mut annotated = df.clone();
+        for &name in &self.columns {
+            let column = match name {
+                "tf_name" => Column::new(
+                    name.into(),
+                    annotations
+                        .iter()
+                        .map(|a| a.and_then(|a| a.tf_name.as_deref()))
+                        .collect::<Vec<_>>(),
+                ),
+                "family" => Column::new(
+                    name.into(),
+                    annotations
+                        .iter()
+                        .map(|a| a.and_then(|a| a.family.as_deref()))
+                        .collect::<Vec<_>>(),
+                ),
+                "role" => Column::new(
+                    name.into(),
+                    annotations
+                        .iter()
+                        .map(|a| {
+                            a.and_then(|a| a.role).map(|role| match role {
+                                MotifRole::Activator => "activator",
+                                MotifRole::Repressor => "repressor",
+                            })
+                        })
+                        .collect::<Vec<_>>(),
+                ),
+                _ => Column::new(
+                    name.into(),
+                    annotations
+                        .iter()
+                        .map(|a| a.and_then(|a| a.concentration))
+                        .collect::<Vec<_>>(),
+                ),
+            };
+            annotated.with_column(column).map_err(data_error)?;
+        }
+        Ok(annotated)
+    }
+}
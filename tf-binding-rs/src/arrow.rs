@@ -0,0 +1,111 @@
+//! Conversion of DataFrames to and from arrow-rs `RecordBatch`es
+//!
+//! Hit tables, landscapes and feature matrices are all polars DataFrames, whose columns
+//! are Arrow arrays already. These helpers hand the buffers across the Arrow C Data
+//! Interface instead of serializing them, so embedders built on arrow-rs (DataFusion,
+//! DuckDB, ...) can use the results directly. Numeric and boolean columns are shared
+//! without copying; string columns are exported as `LargeUtf8`, which every arrow-rs
+//! consumer reads, and therefore copied once.
+
+use crate::error::MotifError;
+use arrow_array::ffi::{from_ffi, to_ffi, FFI_ArrowArray, FFI_ArrowSchema};
+use arrow_array::{make_array, RecordBatch};
+use arrow_schema::{Field, Schema};
+use polars::export::arrow::ffi::{
+    export_array_to_c, export_field_to_c, import_array_from_c, import_field_from_c, ArrowArray,
+    ArrowSchema,
+};
+use polars::prelude::*;
+use std::sync::Arc;
+
+/// Converts a DataFrame into an arrow-rs `RecordBatch`.
+///
+/// # Arguments
+/// * `df` - Any DataFrame, e.g. a hit table, a landscape or a feature matrix
+///
+/// # Returns
+/// * `Result<RecordBatch, MotifError>` - A batch with one array per column, in column order
+///
+/// # Errors
+/// * `MotifError::DataError` - If a column has a type arrow-rs cannot import
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::arrow::to_arrow;
+///
+/// let batch = to_arrow(&result.hits).unwrap();
+/// println!("{} rows", batch.num_rows());
+/// ```
+pub fn to_arrow(df: &DataFrame) -> Result<RecordBatch, MotifError> {
+    let mut fields = Vec::with_capacity(df.width());
+    let mut arrays = Vec::with_capacity(df.width());
+    for column in df.get_columns() {
+        let series = column.as_materialized_series().rechunk();
+        let array = series.to_arrow(0, CompatLevel::oldest());
+        let field = polars::export::arrow::datatypes::Field::new(
+            series.name().clone(),
+            array.dtype().clone(),
+            true,
+        );
+
+        // SAFETY: polars-arrow and arrow-rs both implement the Arrow C Data Interface,
+        // whose structs have the same #[repr(C)] layout in either crate.
+        let schema: FFI_ArrowSchema =
+            unsafe { std::mem::transmute::<ArrowSchema, _>(export_field_to_c(&field)) };
+        let ffi_array: FFI_ArrowArray =
+            unsafe { std::mem::transmute::<ArrowArray, _>(export_array_to_c(array)) };
+
+        let data = unsafe { from_ffi(ffi_array, &schema) }
+            .map_err(|e| MotifError::DataError(e.to_string()))?;
+        fields.push(Field::try_from(&schema).map_err(|e| MotifError::DataError(e.to_string()))?);
+        arrays.push(make_array(data));
+    }
+
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)
+        .map_err(|e| MotifError::DataError(e.to_string()))
+}
+
+/// Converts an arrow-rs `RecordBatch` into a DataFrame.
+///
+/// # Arguments
+/// * `batch` - Record batch, e.g. a table read by DataFusion or DuckDB
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - A DataFrame with one column per array, in batch order
+///
+/// # Errors
+/// * `MotifError::DataError` - If an array has a type polars does not support
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::arrow::from_arrow;
+///
+/// let sequences = from_arrow(&batch).unwrap();
+/// let result = scan::scan_sequences(&sequences, &ewms, 9.0, 0.2, |_| {}).unwrap();
+/// ```
+pub fn from_arrow(batch: &RecordBatch) -> Result<DataFrame, MotifError> {
+    let columns = batch
+        .schema()
+        .fields()
+        .iter()
+        .zip(batch.columns())
+        .map(|(field, array)| {
+            let (ffi_array, schema) =
+                to_ffi(&array.to_data()).map_err(|e| MotifError::DataError(e.to_string()))?;
+
+            // SAFETY: see `to_arrow`; the structs were just exported by arrow-rs.
+            let schema: ArrowSchema = unsafe { std::mem::transmute::<FFI_ArrowSchema, _>(schema) };
+            let ffi_array: ArrowArray =
+                unsafe { std::mem::transmute::<FFI_ArrowArray, _>(ffi_array) };
+            let imported = unsafe { import_field_from_c(&schema) }
+                .and_then(|f| unsafe { import_array_from_c(ffi_array, f.dtype) })
+                .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+            Series::from_arrow(field.name().into(), imported)
+                .map(Column::from)
+                .map_err(|e| MotifError::DataError(e.to_string()))
+        })
+        .collect::<Result<Vec<_>, MotifError>>()?;
+
+    DataFrame::new(columns).map_err(|e| MotifError::DataError(e.to_string()))
+}
@@ -0,0 +1,240 @@
+use crate::error::MotifError;
+use crate::matrix::{matrix_rows, pwm_from_rows};
+use crate::occupancy::{read_pwm_files, EwmOptions, LazyEwmCollection};
+use crate::provenance::file_sha256;
+use crate::types::*;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::Path;
+use std::time::Instant;
+use tracing::{debug, info};
+
+/// First bytes of a motif cache file
+const MAGIC: &[u8; 4] = b"TFBM";
+
+/// Version of the `.tfbm` layout; caches written by another version are rebuilt
+pub const CACHE_VERSION: u32 = 1;
+
+/// Parsed PWMs of a motif file together with their EWMs, as stored in a `.tfbm` cache
+#[derive(Debug, Clone)]
+pub struct CachedMotifs {
+    /// Probability matrices, for filters and deduplication that work on PWMs
+    pub pwms: PWMCollection,
+    /// Energy matrices converted from the PWMs
+    pub ewms: EWMCollection,
+}
+
+/// Key of the cache of `source` converted with `options`: a hex SHA-256 of the contents of
+/// the motif file, the RT and the pseudocount, so a cache is only reused for the same
+/// matrices and energy conversion.
+///
+/// # Errors
+/// * `MotifError::Io` - If the motif file cannot be read
+pub fn cache_key(source: &str, options: &EwmOptions) -> Result<String, MotifError> {
+    let mut hasher = Sha256::new();
+    hasher.update(file_sha256(source)?.as_bytes());
+    hasher.update(options.rt.to_le_bytes());
+    hasher.update(options.pseudocount.to_le_bytes());
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// Writes motifs to a binary `.tfbm` cache tagged with `key` (see `cache_key()`).
+///
+/// The layout is little-endian: the magic `TFBM`, the format version (u32), the key
+/// (u32 length and bytes), the number of motifs (u32) and, per motif sorted by ID, the ID
+/// (u32 length and bytes), the width (u32) and the PWM then the EWM rows as A, C, G, T f64
+/// values.
+///
+/// # Errors
+/// * `MotifError::Io` - If the file cannot be written
+/// * `MotifError::DataError` - If a motif has no EWM or a matrix lacks a nucleotide column
+pub fn write_motif_cache(
+    motifs: &CachedMotifs,
+    key: &str,
+    filename: &str,
+) -> Result<(), MotifError> {
+    let mut ids: Vec<&String> = motifs.pwms.keys().collect();
+    ids.sort();
+
+    let mut out = BufWriter::new(File::create(filename)?);
+    out.write_all(MAGIC)?;
+    out.write_all(&CACHE_VERSION.to_le_bytes())?;
+    write_bytes(&mut out, key.as_bytes())?;
+    out.write_all(&(ids.len() as u32).to_le_bytes())?;
+    for id in ids {
+        let ewm = motifs
+            .ewms
+            .get(id)
+            .ok_or_else(|| MotifError::DataError(format!("no EWM for motif {}", id)))?;
+        let pwm_rows = matrix_rows(&motifs.pwms[id])?;
+        let ewm_rows = matrix_rows(ewm)?;
+        if pwm_rows.len() != ewm_rows.len() {
+            return Err(MotifError::DataError(format!(
+                "PWM and EWM of motif {} differ in width",
+                id
+            )));
+        }
+        write_bytes(&mut out, id.as_bytes())?;
+        out.write_all(&(pwm_rows.len() as u32).to_le_bytes())?;
+        for value in pwm_rows.iter().chain(&ewm_rows).flatten() {
+            out.write_all(&value.to_le_bytes())?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Reads a `.tfbm` cache written by `write_motif_cache()`.
+///
+/// # Returns
+/// * `Result<Option<CachedMotifs>, MotifError>` - The motifs, or `None` if the file does
+///   not exist or is stale: written by another format version or with another key
+///
+/// # Errors
+/// * `MotifError::InvalidFileFormat` - If the file is not a motif cache or is truncated
+/// * `MotifError::Io` - If the file cannot be read
+pub fn read_motif_cache(filename: &str, key: &str) -> Result<Option<CachedMotifs>, MotifError> {
+    let bytes = match fs::read(filename) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut reader = CacheReader {
+        bytes: &bytes,
+        filename,
+    };
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(MotifError::InvalidFileFormat(format!(
+            "{} is not a motif cache",
+            filename
+        )));
+    }
+    let version = reader.u32()?;
+    if version != CACHE_VERSION {
+        debug!(file = filename, version, "motif cache of another version");
+        return Ok(None);
+    }
+    if reader.bytes_field()? != key.as_bytes() {
+        debug!(file = filename, "motif cache of other motifs or options");
+        return Ok(None);
+    }
+
+    let n_motifs = reader.u32()? as usize;
+    let mut motifs = CachedMotifs {
+        pwms: PWMCollection::with_capacity(n_motifs),
+        ewms: EWMCollection::with_capacity(n_motifs),
+    };
+    for _ in 0..n_motifs {
+        let id = String::from_utf8(reader.bytes_field()?.to_vec()).map_err(|_| {
+            MotifError::InvalidFileFormat(format!("{}: motif ID is not UTF-8", filename))
+        })?;
+        let width = reader.u32()? as usize;
+        let pwm = reader.rows(width)?;
+        let ewm = reader.rows(width)?;
+        motifs.pwms.insert(id.clone(), pwm_from_rows(&pwm)?);
+        motifs.ewms.insert(id, pwm_from_rows(&ewm)?);
+    }
+    Ok(Some(motifs))
+}
+
+/// Reads the PWMs of a MEME file and their EWMs from the `cache` file when it holds the
+/// same matrices converted with the same options, and otherwise parses and converts the
+/// motif file and (re)writes the cache, so repeated runs on the same database skip parsing.
+///
+/// # Errors
+/// * The errors of `read_pwm_files()`, `read_motif_cache()` and `write_motif_cache()`
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::cache::load_cached;
+/// use tf_binding_rs::occupancy::EwmOptions;
+///
+/// let motifs = load_cached("JASPAR2024.meme", &EwmOptions::default(), "JASPAR2024.tfbm")?;
+/// println!("{} motifs", motifs.ewms.len());
+/// ```
+pub fn load_cached(
+    source: &str,
+    options: &EwmOptions,
+    cache: &str,
+) -> Result<CachedMotifs, MotifError> {
+    let start = Instant::now();
+    let key = cache_key(source, options)?;
+    if let Some(motifs) = read_motif_cache(cache, &key)? {
+        info!(
+            file = cache,
+            motifs = motifs.ewms.len(),
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "read motif cache"
+        );
+        return Ok(motifs);
+    }
+
+    let pwms = read_pwm_files(source)?;
+    let ewms = LazyEwmCollection::new(pwms.clone(), *options).into_collection()?;
+    let motifs = CachedMotifs { pwms, ewms };
+    if Path::new(cache).exists() {
+        info!(file = cache, "motif cache is stale, rebuilding");
+    }
+    write_motif_cache(&motifs, &key, cache)?;
+    info!(
+        file = cache,
+        motifs = motifs.ewms.len(),
+        elapsed_ms = start.elapsed().as_millis() as u64,
+        "wrote motif cache"
+    );
+    Ok(motifs)
+}
+
+fn write_bytes<W: Write>(out: &mut W, bytes: &[u8]) -> std::io::Result<()> {
+    out.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    out.write_all(bytes)
+}
+
+/// Cursor over the bytes of a cache file
+struct CacheReader<'a> {
+    bytes: &'a [u8],
+    filename: &'a str,
+}
+
+impl<'a> CacheReader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], MotifError> {
+        if self.bytes.len() < n {
+            return Err(MotifError::InvalidFileFormat(format!(
+                "{}: truncated motif cache",
+                self.filename
+            )));
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, MotifError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn bytes_field(&mut self) -> Result<&'a [u8], MotifError> {
+        let n = self.u32()? as usize;
+        self.take(n)
+    }
+
+    fn rows(&mut self, width: usize) -> Result<Vec<[f64; 4]>, MotifError> {
+        let bytes = self.take(width.saturating_mul(32))?;
+        Ok(bytes
+            .chunks_exact(32)
+            .map(|row| {
+                let mut values = [0.0; 4];
+                for (value, b) in values.iter_mut().zip(row.chunks_exact(8)) {
+                    *value = f64::from_le_bytes(b.try_into().expect("8 bytes"));
+                }
+                values
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,167 @@
+use crate::error::MotifError;
+use crate::occupancy::StrandedEwm;
+use crate::types::*;
+use polars::prelude::*;
+use std::collections::HashMap;
+use tracing::warn;
+
+/// Occupancy cutoff of one motif calibrated on background sequences
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalibratedCutoff {
+    /// Occupancy cutoff; background windows above it occur at most at the requested rate
+    pub cutoff: f64,
+    /// Number of background windows above the cutoff, on both strands
+    pub background_hits: usize,
+    /// Total length of the background sequences that were scanned
+    pub background_bases: usize,
+    /// Number of background sequences skipped as shorter than the motif or ambiguous
+    pub skipped_sequences: usize,
+}
+
+impl CalibratedCutoff {
+    /// Background windows above the cutoff per kb of background sequence
+    pub fn hits_per_kb(&self) -> f64 {
+        if self.background_bases == 0 {
+            0.0
+        } else {
+            self.background_hits as f64 * 1000.0 / self.background_bases as f64
+        }
+    }
+}
+
+/// Selects the occupancy cutoff of a motif giving an expected false-positive rate of
+/// `rate_per_kb` hits per kb on background sequences.
+///
+/// Every window of the background is scored on both strands, and the cutoff is the lowest
+/// occupancy at which no more than `rate_per_kb` windows per kb of background lie strictly
+/// above it, so the same rate translates into very different occupancy cutoffs for
+/// strong, specific motifs and for weak, degenerate ones. Background sequences shorter than
+/// the motif or with bases other than A, C, G, T (e.g. N) are skipped; they are counted in
+/// `skipped_sequences` and reported with a warning.
+///
+/// # Arguments
+/// * `background` - Background DNA sequences, e.g. shuffled or genomic controls
+/// * `ewm` - Energy Weight Matrix of the motif
+/// * `mu` - Chemical potential of the transcription factor
+/// * `rate_per_kb` - Expected number of background hits per kb, on both strands
+///
+/// # Returns
+/// * `Result<CalibratedCutoff, MotifError>` - The cutoff and the background hits it allows
+///
+/// # Errors
+/// * `MotifError::InvalidParameter` - If `rate_per_kb` is negative or not finite
+/// * `MotifError::InvalidInput` - If no background sequence could be scanned
+/// * `MotifError::DataError` - If the EWM is malformed
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::calibration::calibrate_cutoff;
+///
+/// // one expected false positive every 10 kb
+/// let calibrated = calibrate_cutoff(&shuffled, &ewm, 9.0, 0.1).unwrap();
+/// println!("cutoff {}", calibrated.cutoff);
+/// ```
+pub fn calibrate_cutoff(
+    background: &[&str],
+    ewm: &EWM,
+    mu: f64,
+    rate_per_kb: f64,
+) -> Result<CalibratedCutoff, MotifError> {
+    if !rate_per_kb.is_finite() || rate_per_kb < 0.0 {
+        return Err(MotifError::invalid_parameter(
+            "rate_per_kb",
+            rate_per_kb,
+            "must be a non-negative number",
+        ));
+    }
+
+    let matrix = StrandedEwm::new(ewm)?;
+    let mut energies = Vec::new();
+    let mut bases = 0;
+    let mut skipped = 0;
+    for seq in background {
+        match matrix.energy_landscape(seq) {
+            Ok((forward, reverse)) => {
+                energies.extend(forward);
+                energies.extend(reverse);
+                bases += seq.len();
+            }
+            Err(_) => skipped += 1,
+        }
+    }
+    if skipped > 0 {
+        warn!(
+            skipped,
+            sequences = background.len(),
+            "skipping background sequences shorter than the motif or with bases other than A, C, G, T"
+        );
+    }
+    if energies.is_empty() {
+        return Err(MotifError::InvalidInput(format!(
+            "no background sequence of at least {} bp with only A, C, G, T",
+            matrix.len()
+        )));
+    }
+
+    let allowed = (rate_per_kb * bases as f64 / 1000.0).floor() as usize;
+    if allowed >= energies.len() {
+        // every window may pass, and occupancies are always above 0
+        return Ok(CalibratedCutoff {
+            cutoff: 0.0,
+            background_hits: energies.len(),
+            background_bases: bases,
+            skipped_sequences: skipped,
+        });
+    }
+
+    // occupancy falls with energy, so only windows better than the (allowed + 1)-th best
+    // window lie above its occupancy; windows tied with it stay at the cutoff
+    let occupancy = |energy: f64| 1.0 / (1.0 + (energy - mu).exp());
+    let (_, &mut threshold, _) = energies.select_nth_unstable_by(allowed, f64::total_cmp);
+    let cutoff = occupancy(threshold);
+    Ok(CalibratedCutoff {
+        cutoff,
+        background_hits: energies.iter().filter(|&&e| occupancy(e) > cutoff).count(),
+        background_bases: bases,
+        skipped_sequences: skipped,
+    })
+}
+
+/// Calibrates the cutoff of every motif of a collection on the "sequence" column of
+/// `background`; see `calibrate_cutoff()`.
+///
+/// # Arguments
+/// * `background` - DataFrame with a "sequence" column of background sequences
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are motif IDs
+/// * `mu` - Chemical potential of the transcription factors
+/// * `rate_per_kb` - Expected number of background hits per kb, on both strands
+///
+/// # Returns
+/// * `Result<HashMap<String, CalibratedCutoff>, MotifError>` - The cutoff of every motif
+///
+/// # Errors
+/// * `MotifError::DataError` - If the "sequence" column is missing or not a string column
+/// * The errors of `calibrate_cutoff()`
+pub fn calibrate_cutoffs(
+    background: &DataFrame,
+    ewms: &EWMCollection,
+    mu: f64,
+    rate_per_kb: f64,
+) -> Result<HashMap<String, CalibratedCutoff>, MotifError> {
+    let sequences: Vec<&str> = background
+        .column("sequence")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .str()
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .into_iter()
+        .flatten()
+        .collect();
+    ewms.iter()
+        .map(|(id, ewm)| {
+            Ok((
+                id.clone(),
+                calibrate_cutoff(&sequences, ewm, mu, rate_per_kb)?,
+            ))
+        })
+        .collect()
+}
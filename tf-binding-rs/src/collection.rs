@@ -0,0 +1,306 @@
+use crate::error::MotifError;
+use crate::matrix::{information_content, matrix_rows};
+use crate::types::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Default largest per-cell difference between two PWMs considered the same motif
+pub const DEFAULT_DEDUPE_TOLERANCE: f64 = 0.01;
+
+/// Why a motif did not pass a `MotifFilter`
+#[derive(Debug, Clone, PartialEq)]
+pub enum RejectReason {
+    /// Total information content (bits) below the minimum
+    LowInformation { bits: f64, min_bits: f64 },
+    /// More positions than the maximum width
+    TooWide { width: usize, max_width: usize },
+}
+
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RejectReason::LowInformation { bits, min_bits } => write!(
+                f,
+                "information content {:.2} bits is below {:.2}",
+                bits, min_bits
+            ),
+            RejectReason::TooWide { width, max_width } => {
+                write!(f, "width {} exceeds {}", width, max_width)
+            }
+        }
+    }
+}
+
+/// A motif rejected by a `MotifFilter`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RejectedMotif {
+    pub id: String,
+    pub reason: RejectReason,
+}
+
+impl fmt::Display for RejectedMotif {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.id, self.reason)
+    }
+}
+
+/// Quality thresholds for the motifs of a collection
+///
+/// Large databases contain near-uniform or very long matrices that dominate scanning time
+/// and mostly produce noise hits. A filter finds them by total information content (the sum
+/// over positions, see `information_content()`) and width.
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::collection::MotifFilter;
+///
+/// let filter = MotifFilter { min_information: Some(8.0), max_width: Some(30) };
+/// let (pwms, rejected) = filter.apply(pwms).unwrap();
+/// for motif in &rejected {
+///     eprintln!("dropped {}", motif);
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MotifFilter {
+    /// Minimum total information content in bits
+    pub min_information: Option<f64>,
+    /// Maximum number of positions
+    pub max_width: Option<usize>,
+}
+
+impl MotifFilter {
+    /// Whether the filter has no thresholds
+    pub fn is_empty(&self) -> bool {
+        self.min_information.is_none() && self.max_width.is_none()
+    }
+
+    /// Checks one PWM, returning why it fails the filter, if it does
+    ///
+    /// # Errors
+    /// * `MotifError::DataError` - If the PWM is malformed
+    pub fn check(&self, pwm: &PWM) -> Result<Option<RejectReason>, MotifError> {
+        if let Some(max_width) = self.max_width {
+            if pwm.height() > max_width {
+                return Ok(Some(RejectReason::TooWide {
+                    width: pwm.height(),
+                    max_width,
+                }));
+            }
+        }
+        if let Some(min_bits) = self.min_information {
+            let bits: f64 = information_content(pwm)?.iter().sum();
+            if bits < min_bits {
+                return Ok(Some(RejectReason::LowInformation { bits, min_bits }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Lists the motifs of a collection that fail the filter, sorted by ID, without removing
+    /// them
+    ///
+    /// # Errors
+    /// * `MotifError::DataError` - If a PWM is malformed
+    pub fn flag(&self, pwms: &PWMCollection) -> Result<Vec<RejectedMotif>, MotifError> {
+        let mut rejected = Vec::new();
+        for (id, pwm) in pwms {
+            if let Some(reason) = self.check(pwm)? {
+                rejected.push(RejectedMotif {
+                    id: id.clone(),
+                    reason,
+                });
+            }
+        }
+        rejected.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(rejected)
+    }
+
+    /// Removes the motifs that fail the filter
+    ///
+    /// # Returns
+    /// * `Result<(PWMCollection, Vec<RejectedMotif>), MotifError>` - The remaining motifs
+    ///   and the removed ones, sorted by ID
+    ///
+    /// # Errors
+    /// * `MotifError::DataError` - If a PWM is malformed
+    pub fn apply(
+        &self,
+        mut pwms: PWMCollection,
+    ) -> Result<(PWMCollection, Vec<RejectedMotif>), MotifError> {
+        let rejected = self.flag(&pwms)?;
+        for motif in &rejected {
+            pwms.remove(&motif.id);
+        }
+        Ok((pwms, rejected))
+    }
+}
+
+/// A motif collapsed into an equivalent one by `deduplicate()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergedMotif {
+    /// ID of the removed motif
+    pub duplicate: String,
+    /// ID of the motif it was merged into
+    pub kept: String,
+    /// Whether the removed motif is the reverse complement of the kept one
+    pub reverse_complement: bool,
+    /// Largest per-cell difference between the two matrices
+    pub max_difference: f64,
+}
+
+impl fmt::Display for MergedMotif {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} -> {}{} (max difference {:.4})",
+            self.duplicate,
+            self.kept,
+            if self.reverse_complement {
+                " (reverse complement)"
+            } else {
+                ""
+            },
+            self.max_difference
+        )
+    }
+}
+
+/// A collection with duplicate motifs collapsed
+#[derive(Debug, Clone)]
+pub struct Deduplicated {
+    /// The remaining motifs
+    pub pwms: PWMCollection,
+    /// Maps the ID of every removed motif to the ID it was merged into
+    pub aliases: HashMap<String, String>,
+    /// The merges, sorted by the removed ID
+    pub merged: Vec<MergedMotif>,
+}
+
+/// Largest per-cell difference between two matrices of the same width
+fn max_difference(a: &[[f64; 4]], b: &[[f64; 4]]) -> f64 {
+    a.iter()
+        .zip(b)
+        .flat_map(|(x, y)| x.iter().zip(y).map(|(x, y)| (x - y).abs()))
+        .fold(0.0, f64::max)
+}
+
+/// Collapses motifs that are identical, or reverse complements of each other, within a
+/// tolerance
+///
+/// Combined databases often contain the same matrix several times under different IDs, which
+/// doubles the scanning time and the hits. Two PWMs are duplicates when they have the same
+/// width and no cell differs by more than `tolerance`, directly or after reverse complementing
+/// one of them. Motifs are visited in ID order, and each duplicate is merged into the first
+/// motif it matches, so the result does not depend on the collection's iteration order.
+///
+/// # Arguments
+/// * `pwms` - Collection of PWMs
+/// * `tolerance` - Largest per-cell difference, e.g. `DEFAULT_DEDUPE_TOLERANCE`
+///
+/// # Returns
+/// * `Result<Deduplicated, MotifError>` - The remaining motifs, the alias map and the merges
+///
+/// # Errors
+/// * `MotifError::InvalidParameter` - If `tolerance` is negative
+/// * `MotifError::DataError` - If a PWM is malformed
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::collection::{deduplicate, DEFAULT_DEDUPE_TOLERANCE};
+///
+/// let deduplicated = deduplicate(pwms, DEFAULT_DEDUPE_TOLERANCE).unwrap();
+/// for merge in &deduplicated.merged {
+///     println!("{}", merge);
+/// }
+/// ```
+pub fn deduplicate(mut pwms: PWMCollection, tolerance: f64) -> Result<Deduplicated, MotifError> {
+    if tolerance.is_nan() || tolerance < 0.0 {
+        return Err(MotifError::invalid_parameter(
+            "tolerance",
+            tolerance,
+            "must be non-negative",
+        ));
+    }
+
+    let mut ids: Vec<&String> = pwms.keys().collect();
+    ids.sort();
+    let mut kept: Vec<(&String, Vec<[f64; 4]>)> = Vec::new();
+    let mut merged = Vec::new();
+    for id in ids {
+        let rows = matrix_rows(&pwms[id])?;
+        let reverse: Vec<[f64; 4]> = rows
+            .iter()
+            .rev()
+            .map(|&[a, c, g, t]| [t, g, c, a])
+            .collect();
+        let duplicate_of = kept
+            .iter()
+            .filter(|(_, other)| other.len() == rows.len())
+            .find_map(|(other_id, other)| {
+                let direct = max_difference(&rows, other);
+                let complement = max_difference(&reverse, other);
+                if direct <= tolerance && direct <= complement {
+                    Some((*other_id, false, direct))
+                } else if complement <= tolerance {
+                    Some((*other_id, true, complement))
+                } else {
+                    None
+                }
+            });
+        match duplicate_of {
+            Some((other_id, reverse_complement, max_difference)) => merged.push(MergedMotif {
+                duplicate: id.clone(),
+                kept: other_id.clone(),
+                reverse_complement,
+                max_difference,
+            }),
+            None => kept.push((id, rows)),
+        }
+    }
+
+    let aliases = merged
+        .iter()
+        .map(|m| (m.duplicate.clone(), m.kept.clone()))
+        .collect();
+    for merge in &merged {
+        pwms.remove(&merge.duplicate);
+    }
+    Ok(Deduplicated {
+        pwms,
+        aliases,
+        merged,
+    })
+}
+
+/// Draws a random subset of `n` motifs (PWMs or EWMs) for quick exploratory scans; all of
+/// them are kept if there are no more than `n`.
+///
+/// The draw is deterministic: the motifs are ordered by ID before sampling, so the same
+/// IDs and seed always give the same subset, whatever the order of the collection (with
+/// the same version of the `rand` crate).
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::collection::sample_motifs;
+///
+/// let subset = sample_motifs(ewms, 20, 42);
+/// ```
+pub fn sample_motifs<T>(motifs: HashMap<String, T>, n: usize, seed: u64) -> HashMap<String, T> {
+    if motifs.len() <= n {
+        return motifs;
+    }
+    let mut motifs: Vec<(String, T)> = motifs.into_iter().collect();
+    motifs.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut chosen = rand::seq::index::sample(&mut rng, motifs.len(), n).into_vec();
+    chosen.sort_unstable();
+
+    let mut chosen = chosen.into_iter().peekable();
+    motifs
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, motif)| chosen.next_if_eq(&i).map(|_| motif))
+        .collect()
+}
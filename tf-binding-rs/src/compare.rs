@@ -0,0 +1,980 @@
+use crate::error::{MotifError, ResultExt};
+use crate::occupancy::{occupancies, occupancy_landscape, EncodedSeq, StrandedEwm};
+use crate::stats::pearson_correlation;
+use crate::types::*;
+use polars::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+/// Result of `delta_landscape()`
+#[derive(Debug, Clone)]
+pub struct DeltaComparison {
+    /// One row per pair and motif, with columns:
+    /// - "label": Label shared by the pair
+    /// - "motif": Motif ID
+    /// - "wt_occupancy": Total occupancy of the motif in the first sequence
+    /// - "mut_occupancy": Total occupancy of the motif in the second sequence
+    /// - "delta": `mut_occupancy - wt_occupancy`
+    /// - "sites_created": Sites above cutoff only in the second sequence
+    /// - "sites_destroyed": Sites above cutoff only in the first sequence
+    pub per_pair: DataFrame,
+    /// One row per motif, with columns:
+    /// - "motif": Motif ID
+    /// - "n_pairs": Number of compared pairs
+    /// - "mean_delta": Mean occupancy difference over pairs
+    /// - "sites_created": Total sites created
+    /// - "sites_destroyed": Total sites destroyed
+    /// - "pairs_changed": Number of pairs with any site created or destroyed
+    pub summary: DataFrame,
+}
+
+/// Compares occupancy landscapes between paired sequences
+///
+/// Sequences are paired by label, so `wt` and `mutant` can hold e.g. the WT and MUT
+/// columns of an MPRA library after splitting them into two label/sequence tables; labels
+/// present in only one table are ignored. For each pair and motif, total occupancies over
+/// both strands are compared, and sites (windows above `cutoff`) are matched by strand and
+/// position to count created and destroyed sites. Matching by position assumes the pair
+/// differs by substitutions, not indels.
+///
+/// # Arguments
+/// * `wt` - DataFrame with "label" and "sequence" columns for the reference sequences
+/// * `mutant` - DataFrame with "label" and "sequence" columns for the variant sequences
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are motif IDs
+/// * `mu` - Chemical potential of the transcription factors
+/// * `cutoff` - Minimum occupancy for a window to count as a site
+///
+/// # Returns
+/// * `Result<DeltaComparison, MotifError>` - Per-pair and per-motif differences
+///
+/// # Errors
+/// * `MotifError::InvalidInput` - If no labels are shared between the two tables
+/// * `MotifError::DataError` - If required columns are missing or DataFrame creation fails
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::compare::delta_landscape;
+///
+/// let comparison = delta_landscape(&wt, &mutant, &ewms, 9.0, 0.2).unwrap();
+/// println!("{}", comparison.summary);
+/// ```
+pub fn delta_landscape(
+    wt: &DataFrame,
+    mutant: &DataFrame,
+    ewms: &EWMCollection,
+    mu: f64,
+    cutoff: f64,
+) -> Result<DeltaComparison, MotifError> {
+    let pairs = paired_sequences(wt, mutant)?;
+
+    let mut motif_ids: Vec<&String> = ewms.keys().collect();
+    motif_ids.sort();
+
+    let mut labels: Vec<&str> = Vec::new();
+    let mut motifs: Vec<&str> = Vec::new();
+    let mut wt_totals: Vec<f64> = Vec::new();
+    let mut mut_totals: Vec<f64> = Vec::new();
+    let mut created: Vec<u32> = Vec::new();
+    let mut destroyed: Vec<u32> = Vec::new();
+
+    for (label, wt_seq, mut_seq) in &pairs {
+        for motif_id in &motif_ids {
+            let ewm = &ewms[*motif_id];
+            let (wt_total, wt_sites) = motif_sites(wt_seq, ewm, mu, cutoff)?;
+            let (mut_total, mut_sites) = motif_sites(mut_seq, ewm, mu, cutoff)?;
+
+            labels.push(label);
+            motifs.push(motif_id);
+            wt_totals.push(wt_total);
+            mut_totals.push(mut_total);
+            created.push(mut_sites.difference(&wt_sites).count() as u32);
+            destroyed.push(wt_sites.difference(&mut_sites).count() as u32);
+        }
+    }
+
+    let deltas: Vec<f64> = wt_totals
+        .iter()
+        .zip(&mut_totals)
+        .map(|(w, m)| m - w)
+        .collect();
+
+    let per_pair = DataFrame::new(vec![
+        Column::new("label".into(), labels),
+        Column::new("motif".into(), motifs),
+        Column::new("wt_occupancy".into(), wt_totals),
+        Column::new("mut_occupancy".into(), mut_totals),
+        Column::new("delta".into(), deltas),
+        Column::new("sites_created".into(), created),
+        Column::new("sites_destroyed".into(), destroyed),
+    ])
+    .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    let summary = per_pair
+        .clone()
+        .lazy()
+        .group_by_stable([col("motif")])
+        .agg([
+            len().cast(DataType::UInt32).alias("n_pairs"),
+            col("delta").mean().alias("mean_delta"),
+            col("sites_created").sum().alias("sites_created"),
+            col("sites_destroyed").sum().alias("sites_destroyed"),
+            (col("sites_created") + col("sites_destroyed"))
+                .gt(lit(0))
+                .sum()
+                .alias("pairs_changed"),
+        ])
+        .collect()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    Ok(DeltaComparison { per_pair, summary })
+}
+
+/// Collects (label, sequence) pairs from a label/sequence DataFrame
+pub(crate) fn labelled_sequences(df: &DataFrame) -> Result<Vec<(&str, &str)>, MotifError> {
+    let labels = df
+        .column("label")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .str()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    let sequences = df
+        .column("sequence")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .str()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    Ok(labels
+        .into_iter()
+        .zip(sequences)
+        .filter_map(|(label, seq)| Some((label?, seq?)))
+        .collect())
+}
+
+/// Total occupancy of one motif and the set of (strand, position) sites above cutoff
+fn motif_sites(
+    seq: &str,
+    ewm: &EWM,
+    mu: f64,
+    cutoff: f64,
+) -> Result<(f64, HashSet<(char, usize)>), MotifError> {
+    let (focc, rocc) = occupancy_landscape(seq, ewm, mu)?;
+    let total = focc.iter().chain(&rocc).sum();
+
+    let sites = [('F', &focc), ('R', &rocc)]
+        .into_iter()
+        .flat_map(|(strand, occs)| {
+            occs.iter()
+                .enumerate()
+                .filter(|(_, &occ)| occ > cutoff)
+                .map(move |(pos, _)| (strand, pos))
+        })
+        .collect();
+
+    Ok((total, sites))
+}
+
+/// Result of `pair_sequences()`
+#[derive(Debug, Clone)]
+pub struct SequencePairing {
+    /// One row per differing base of the pairs of equal length, with columns:
+    /// - "label": Label shared by the pair
+    /// - "position": 0-based position of the base
+    /// - "ref": Base in the reference sequence
+    /// - "alt": Base in the edited sequence
+    pub variants: DataFrame,
+    /// Number of pairs with identical sequences
+    pub identical: usize,
+    /// Pairs whose sequences differ in length, as (label, reference length, edited length);
+    /// their differences cannot be placed without an alignment
+    pub length_mismatches: Vec<(String, usize, usize)>,
+    /// Labels only in the reference table
+    pub reference_only: Vec<String>,
+    /// Labels only in the edited table
+    pub edited_only: Vec<String>,
+}
+
+/// Pairs reference and edited sequences by label and lists the bases that differ
+///
+/// Alignment-free: pairs are compared base by base, so only pairs of equal length (designs
+/// with substitutions) get variants; pairs of different lengths are reported in
+/// `length_mismatches`. Bases are compared case-insensitively.
+///
+/// # Arguments
+/// * `reference` - DataFrame with "label" and "sequence" columns, e.g. the original designs
+/// * `edited` - DataFrame with "label" and "sequence" columns, e.g. the edited designs
+///
+/// # Returns
+/// * `Result<SequencePairing, MotifError>` - The differing bases and the unpaired labels
+///
+/// # Errors
+/// * `MotifError::InvalidInput` - If no labels are shared between the two tables
+/// * `MotifError::DataError` - If required columns are missing or DataFrame creation fails
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::compare::pair_sequences;
+///
+/// let pairing = pair_sequences(&reference, &edited).unwrap();
+/// for (label, ref_len, alt_len) in &pairing.length_mismatches {
+///     eprintln!("{}: {} vs {} bp", label, ref_len, alt_len);
+/// }
+/// ```
+pub fn pair_sequences(
+    reference: &DataFrame,
+    edited: &DataFrame,
+) -> Result<SequencePairing, MotifError> {
+    let pairs = paired_sequences(reference, edited)?;
+    let reference_labels: HashSet<&str> = labelled_sequences(reference)?
+        .into_iter()
+        .map(|(label, _)| label)
+        .collect();
+    let edited_labels: HashSet<&str> = labelled_sequences(edited)?
+        .into_iter()
+        .map(|(label, _)| label)
+        .collect();
+    let unpaired = |labels: &HashSet<&str>, other: &HashSet<&str>| {
+        let mut only: Vec<String> = labels
+            .difference(other)
+            .map(|label| label.to_string())
+            .collect();
+        only.sort_unstable();
+        only
+    };
+
+    let mut labels: Vec<&str> = Vec::new();
+    let mut positions: Vec<u32> = Vec::new();
+    let mut refs: Vec<String> = Vec::new();
+    let mut alts: Vec<String> = Vec::new();
+    let mut identical = 0;
+    let mut length_mismatches = Vec::new();
+    for (label, ref_seq, alt_seq) in &pairs {
+        if ref_seq.len() != alt_seq.len() {
+            length_mismatches.push((label.to_string(), ref_seq.len(), alt_seq.len()));
+            continue;
+        }
+        let differences = substitutions(ref_seq, alt_seq);
+        if differences.is_empty() {
+            identical += 1;
+        }
+        for (position, ref_base, alt_base) in differences {
+            labels.push(label);
+            positions.push(position as u32);
+            refs.push(ref_base.to_string());
+            alts.push(alt_base.to_string());
+        }
+    }
+
+    let variants = DataFrame::new(vec![
+        Column::new("label".into(), labels),
+        Column::new("position".into(), positions),
+        Column::new("ref".into(), refs),
+        Column::new("alt".into(), alts),
+    ])
+    .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    Ok(SequencePairing {
+        variants,
+        identical,
+        length_mismatches,
+        reference_only: unpaired(&reference_labels, &edited_labels),
+        edited_only: unpaired(&edited_labels, &reference_labels),
+    })
+}
+
+/// Compares occupancy around every differing base of paired sequences
+///
+/// The differing bases are found as in `pair_sequences()`, and for each base and motif
+/// the occupancies of the windows covering it, on both strands, are compared between the
+/// reference and the reference carrying only that substitution, so the effect of every edit
+/// is read off without listing the positions by hand. Each row is the effect of its own
+/// edit: a window covering several edits is rescored for each of them alone, as in
+/// `saturation_mutagenesis()`. Pairs of different lengths are skipped.
+///
+/// # Arguments
+/// * `reference` - DataFrame with "label" and "sequence" columns for the reference sequences
+/// * `edited` - DataFrame with "label" and "sequence" columns for the edited sequences
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are motif IDs
+/// * `mu` - Chemical potential of the transcription factors
+/// * `cutoff` - Minimum occupancy for a window to count as a site
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - One row per differing base and motif, with the
+///   columns of `SequencePairing::variants` and:
+///   - "motif": Motif ID
+///   - "wt_occupancy": Summed occupancy of the windows covering the base in the reference
+///   - "mut_occupancy": The same with only this base substituted
+///   - "delta": `mut_occupancy - wt_occupancy`
+///   - "sites_created": Covering sites above cutoff only with the substitution
+///   - "sites_destroyed": Covering sites above cutoff only in the reference
+///
+/// # Errors
+/// * `MotifError::InvalidInput` - If no labels are shared, or a sequence has bases other
+///   than A, C, G, T
+/// * `MotifError::InvalidSequence` - If a sequence is shorter than a motif
+/// * `MotifError::DataError` - If required columns are missing or DataFrame creation fails
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::compare::variant_delta;
+///
+/// let effects = variant_delta(&reference, &edited, &ewms, 9.0, 0.2).unwrap();
+/// let strongest = effects.sort(["delta"], Default::default()).unwrap();
+/// ```
+pub fn variant_delta(
+    reference: &DataFrame,
+    edited: &DataFrame,
+    ewms: &EWMCollection,
+    mu: f64,
+    cutoff: f64,
+) -> Result<DataFrame, MotifError> {
+    let pairs = paired_sequences(reference, edited)?;
+    let mut motif_ids: Vec<&String> = ewms.keys().collect();
+    motif_ids.sort();
+
+    let mut labels: Vec<&str> = Vec::new();
+    let mut positions: Vec<u32> = Vec::new();
+    let mut refs: Vec<String> = Vec::new();
+    let mut alts: Vec<String> = Vec::new();
+    let mut motifs: Vec<&str> = Vec::new();
+    let mut wt_totals: Vec<f64> = Vec::new();
+    let mut mut_totals: Vec<f64> = Vec::new();
+    let mut created: Vec<u32> = Vec::new();
+    let mut destroyed: Vec<u32> = Vec::new();
+
+    for (label, ref_seq, alt_seq) in &pairs {
+        if ref_seq.len() != alt_seq.len() {
+            continue;
+        }
+        let differences = substitutions(ref_seq, alt_seq);
+        if differences.is_empty() {
+            continue;
+        }
+        let encoded = EncodedSeq::new(ref_seq).with_label(label)?;
+        for motif_id in &motif_ids {
+            let matrix = StrandedEwm::new(&ewms[*motif_id]).with_motif(motif_id)?;
+            let energies = matrix
+                .energy_landscape_encoded(&encoded)
+                .with_motif(motif_id)?;
+            let wt = occupancies(&energies, mu);
+            let occupancy = |energy: f64| 1.0 / (1.0 + (energy - mu).exp());
+            for &(position, ref_base, alt_base) in &differences {
+                // only the windows covering the edit change
+                let update = matrix
+                    .rescore_mutation(&encoded, &energies, position, alt_base as u8)
+                    .with_motif(motif_id)?;
+                let (mut wt_total, mut mut_total) = (0.0, 0.0);
+                let (mut n_created, mut n_destroyed) = (0, 0);
+                for (wt_occ, mut_energies) in [(&wt.0, &update.forward), (&wt.1, &update.reverse)] {
+                    for (i, &energy) in mut_energies.iter().enumerate() {
+                        let (w, m) = (wt_occ[update.start + i], occupancy(energy));
+                        wt_total += w;
+                        mut_total += m;
+                        match (w > cutoff, m > cutoff) {
+                            (false, true) => n_created += 1,
+                            (true, false) => n_destroyed += 1,
+                            _ => {}
+                        }
+                    }
+                }
+                labels.push(label);
+                positions.push(position as u32);
+                refs.push(ref_base.to_string());
+                alts.push(alt_base.to_string());
+                motifs.push(motif_id);
+                wt_totals.push(wt_total);
+                mut_totals.push(mut_total);
+                created.push(n_created);
+                destroyed.push(n_destroyed);
+            }
+        }
+    }
+
+    let deltas: Vec<f64> = wt_totals
+        .iter()
+        .zip(&mut_totals)
+        .map(|(w, m)| m - w)
+        .collect();
+    DataFrame::new(vec![
+        Column::new("label".into(), labels),
+        Column::new("position".into(), positions),
+        Column::new("ref".into(), refs),
+        Column::new("alt".into(), alts),
+        Column::new("motif".into(), motifs),
+        Column::new("wt_occupancy".into(), wt_totals),
+        Column::new("mut_occupancy".into(), mut_totals),
+        Column::new("delta".into(), deltas),
+        Column::new("sites_created".into(), created),
+        Column::new("sites_destroyed".into(), destroyed),
+    ])
+    .map_err(|e| MotifError::DataError(e.to_string()))
+}
+
+/// (label, reference, edited) of every label in both tables, in reference order
+fn paired_sequences<'a>(
+    reference: &'a DataFrame,
+    edited: &'a DataFrame,
+) -> Result<Vec<(&'a str, &'a str, &'a str)>, MotifError> {
+    let edited_seqs: HashMap<&str, &str> = labelled_sequences(edited)?.into_iter().collect();
+    let pairs: Vec<(&str, &str, &str)> = labelled_sequences(reference)?
+        .into_iter()
+        .filter_map(|(label, seq)| edited_seqs.get(label).map(|e| (label, seq, *e)))
+        .collect();
+    if pairs.is_empty() {
+        return Err(MotifError::InvalidInput(
+            "No labels shared between the sequence tables".to_string(),
+        ));
+    }
+    Ok(pairs)
+}
+
+/// (position, reference base, edited base) of the bases differing between two sequences of
+/// equal length, ignoring case
+fn substitutions(reference: &str, edited: &str) -> Vec<(usize, char, char)> {
+    reference
+        .bytes()
+        .zip(edited.bytes())
+        .enumerate()
+        .filter(|(_, (r, e))| !r.eq_ignore_ascii_case(e))
+        .map(|(position, (r, e))| {
+            (
+                position,
+                r.to_ascii_uppercase() as char,
+                e.to_ascii_uppercase() as char,
+            )
+        })
+        .collect()
+}
+
+/// Effect of every single-base substitution of a sequence on every motif (in silico
+/// saturation mutagenesis).
+///
+/// The reference landscape of each motif is scored once, and each mutant only rescores the
+/// windows overlapping its substitution (see `StrandedEwm::rescore_mutation()`), so the
+/// whole scan costs O(L·W) per motif instead of O(L²·W).
+///
+/// # Arguments
+/// * `seq` - Reference sequence of A, C, G, T
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are motif IDs
+/// * `mu` - Chemical potential of the transcription factors
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - Three rows per position and motif, sorted by motif
+///   and position, with columns:
+///   - "motif": Motif ID
+///   - "position": 0-based position of the substitution
+///   - "ref", "alt": Reference and substituted base
+///   - "max_occupancy": Highest occupancy of any window of the mutant, on either strand
+///   - "delta_max_occupancy": `max_occupancy` minus that of the reference
+///   - "delta_total_occupancy": Change of the summed occupancy of all windows
+///
+/// # Errors
+/// * `MotifError::InvalidInput` - If the sequence has bases other than A, C, G, T
+/// * `MotifError::InvalidSequence` - If the sequence is shorter than a motif
+/// * `MotifError::DataError` - If an EWM is malformed or DataFrame creation fails
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::compare::saturation_mutagenesis;
+///
+/// let effects = saturation_mutagenesis("GGGCTTAGTAACGTTACGG", &ewms, 9.0).unwrap();
+/// ```
+pub fn saturation_mutagenesis(
+    seq: &str,
+    ewms: &EWMCollection,
+    mu: f64,
+) -> Result<DataFrame, MotifError> {
+    const BASES: [u8; 4] = *b"ACGT";
+    let encoded = EncodedSeq::new(seq)?;
+    let mut motif_ids: Vec<&String> = ewms.keys().collect();
+    motif_ids.sort();
+
+    let mut motifs: Vec<&str> = Vec::new();
+    let mut positions: Vec<u32> = Vec::new();
+    let mut refs: Vec<String> = Vec::new();
+    let mut alts: Vec<String> = Vec::new();
+    let mut max_occupancies: Vec<f64> = Vec::new();
+    let mut delta_max: Vec<f64> = Vec::new();
+    let mut delta_total: Vec<f64> = Vec::new();
+    for id in motif_ids {
+        let matrix = StrandedEwm::new(&ewms[id]).with_motif(id)?;
+        let energies = matrix.energy_landscape_encoded(&encoded).with_motif(id)?;
+        let (forward, reverse) = occupancies(&energies, mu);
+        let best: Vec<f64> = forward
+            .iter()
+            .zip(&reverse)
+            .map(|(f, r)| f.max(*r))
+            .collect();
+        // best window before and after each window, so the maximum of a mutant only needs
+        // its changed windows
+        let mut prefix = best.clone();
+        for w in 1..prefix.len() {
+            prefix[w] = prefix[w].max(prefix[w - 1]);
+        }
+        let mut suffix = best;
+        for w in (0..suffix.len().saturating_sub(1)).rev() {
+            suffix[w] = suffix[w].max(suffix[w + 1]);
+        }
+        let reference_max = prefix.last().copied().unwrap_or(0.0);
+        let occupancy = |energy: f64| 1.0 / (1.0 + (energy - mu).exp());
+
+        for (position, &reference) in encoded.bases().iter().enumerate() {
+            for (b, &alt) in BASES.iter().enumerate() {
+                if b == reference {
+                    continue;
+                }
+                let update = matrix.rescore_mutation(&encoded, &energies, position, alt)?;
+                let mut mutant_max = f64::NEG_INFINITY;
+                if update.start > 0 {
+                    mutant_max = prefix[update.start - 1];
+                }
+                if update.end() < suffix.len() {
+                    mutant_max = mutant_max.max(suffix[update.end()]);
+                }
+                let mut change = 0.0;
+                for (i, (&f, &r)) in update.forward.iter().zip(&update.reverse).enumerate() {
+                    let window = update.start + i;
+                    let (f, r) = (occupancy(f), occupancy(r));
+                    mutant_max = mutant_max.max(f).max(r);
+                    change += f - forward[window] + r - reverse[window];
+                }
+                motifs.push(id);
+                positions.push(position as u32);
+                refs.push((BASES[reference] as char).to_string());
+                alts.push((alt as char).to_string());
+                max_occupancies.push(mutant_max);
+                delta_max.push(mutant_max - reference_max);
+                delta_total.push(change);
+            }
+        }
+    }
+
+    df!(
+        "motif" => motifs,
+        "position" => positions,
+        "ref" => refs,
+        "alt" => alts,
+        "max_occupancy" => max_occupancies,
+        "delta_max_occupancy" => delta_max,
+        "delta_total_occupancy" => delta_total,
+    )
+    .map_err(|e| MotifError::DataError(e.to_string()))
+}
+
+/// One side of `config_diff()`: the motifs and parameters of a scan
+#[derive(Debug, Clone, Copy)]
+pub struct ScanConfig<'a> {
+    /// Collection of Energy Weight Matrices, where keys are motif IDs
+    pub ewms: &'a EWMCollection,
+    /// Chemical potential of the transcription factors
+    pub mu: f64,
+    /// Minimum occupancy for a window to count as a site
+    pub cutoff: f64,
+}
+
+/// Result of `config_diff()`
+#[derive(Debug, Clone)]
+pub struct ConfigDiff {
+    /// One row per sequence and motif pair, with columns:
+    /// - "label": Sequence label
+    /// - "motif_a": Motif ID in the first configuration
+    /// - "motif_b": Motif ID in the second configuration
+    /// - "occupancy_a": Total occupancy of the motif under the first configuration
+    /// - "occupancy_b": Total occupancy of the motif under the second configuration
+    /// - "delta": `occupancy_b - occupancy_a`
+    /// - "sites_a": Sites above the first cutoff
+    /// - "sites_b": Sites above the second cutoff
+    /// - "sites_gained": Sites only under the second configuration
+    /// - "sites_lost": Sites only under the first configuration
+    /// - "changed": Whether a site was gained or lost, or `|delta|` exceeds the tolerance
+    pub per_sequence: DataFrame,
+    /// One row per motif pair, with columns:
+    /// - "motif_a", "motif_b": Motif IDs in the two configurations
+    /// - "n_sequences": Number of compared sequences
+    /// - "mean_delta": Mean occupancy difference over sequences
+    /// - "max_abs_delta": Largest absolute occupancy difference
+    /// - "sites_a", "sites_b", "sites_gained", "sites_lost": Totals over sequences
+    /// - "sequences_changed": Number of sequences marked as changed
+    pub summary: DataFrame,
+    /// Motifs of the first configuration without a counterpart in the second
+    pub only_a: Vec<String>,
+    /// Motifs of the second configuration without a counterpart in the first
+    pub only_b: Vec<String>,
+}
+
+impl ConfigDiff {
+    /// Number of sequence and motif pairs that changed
+    pub fn n_changed(&self) -> usize {
+        self.per_sequence
+            .column("changed")
+            .ok()
+            .and_then(|c| c.bool().ok().map(|changed| changed.sum().unwrap_or(0)))
+            .unwrap_or(0) as usize
+    }
+
+    /// Whether both configurations give the same results, within the tolerance
+    pub fn is_identical(&self) -> bool {
+        self.only_a.is_empty() && self.only_b.is_empty() && self.n_changed() == 0
+    }
+}
+
+/// Pairs the motifs of two collections: by full ID, then by the name before the first '_'
+/// when it is unique on both sides, as for a database release that bumps matrix versions
+fn pair_motifs(
+    a: &EWMCollection,
+    b: &EWMCollection,
+) -> (Vec<(String, String)>, Vec<String>, Vec<String>) {
+    let name = |id: &str| id.split('_').next().unwrap_or(id).to_string();
+    let mut only_a: Vec<&String> = a.keys().filter(|id| !b.contains_key(*id)).collect();
+    let mut only_b: Vec<&String> = b.keys().filter(|id| !a.contains_key(*id)).collect();
+    only_a.sort();
+    only_b.sort();
+
+    let mut pairs: Vec<(String, String)> = a
+        .keys()
+        .filter(|id| b.contains_key(*id))
+        .map(|id| (id.clone(), id.clone()))
+        .collect();
+    let count = |ids: &[&String], n: &str| ids.iter().filter(|id| name(id) == n).count();
+    let mut unpaired_a = Vec::new();
+    for id in &only_a {
+        let n = name(id);
+        let partner = only_b.iter().find(|other| name(other) == n);
+        match partner {
+            Some(other) if count(&only_a, &n) == 1 && count(&only_b, &n) == 1 => {
+                pairs.push(((*id).clone(), (*other).clone()))
+            }
+            _ => unpaired_a.push((*id).clone()),
+        }
+    }
+    let unpaired_b = only_b
+        .into_iter()
+        .filter(|id| !pairs.iter().any(|(_, b)| b == *id))
+        .cloned()
+        .collect();
+    pairs.sort();
+    (pairs, unpaired_a, unpaired_b)
+}
+
+/// Scans the same sequences under two configurations and reports their differences
+///
+/// Meant for checking that a new motif database release, chemical potential or cutoff does
+/// not silently change results. Motifs are paired by full ID, or by the name before the
+/// first '_' when the ID changed (e.g. `NRL_HUMAN.MA0842.1` and `NRL_HUMAN.MA0842.2`) and
+/// the name maps to one motif on each side; unpaired motifs are listed in `only_a` and
+/// `only_b`. For each sequence and motif pair, total occupancies over both strands are
+/// compared, and sites are matched by strand and position, so matrices of different widths
+/// or offsets gain and lose sites even where their binding agrees.
+///
+/// # Arguments
+/// * `sequences` - DataFrame with "label" and "sequence" columns
+/// * `a` - First (reference) configuration
+/// * `b` - Second configuration
+/// * `tolerance` - Largest absolute total occupancy difference not counted as a change
+///
+/// # Returns
+/// * `Result<ConfigDiff, MotifError>` - Per-sequence and per-motif differences
+///
+/// # Errors
+/// * `MotifError::InvalidSequence` - If a sequence is shorter than a motif
+/// * `MotifError::InvalidInput` - If a sequence contains characters other than A, C, G, T
+/// * `MotifError::DataError` - If required columns are missing or DataFrame creation fails
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::compare::{config_diff, ScanConfig};
+///
+/// let old = ScanConfig { ewms: &jaspar_2022, mu: 9.0, cutoff: 0.2 };
+/// let new = ScanConfig { ewms: &jaspar_2024, ..old };
+/// let diff = config_diff(&sequences, &old, &new, 1e-6).unwrap();
+/// println!("{}", diff.summary);
+/// ```
+pub fn config_diff(
+    sequences: &DataFrame,
+    a: &ScanConfig,
+    b: &ScanConfig,
+    tolerance: f64,
+) -> Result<ConfigDiff, MotifError> {
+    let records = labelled_sequences(sequences)?;
+    let (pairs, only_a, only_b) = pair_motifs(a.ewms, b.ewms);
+
+    let mut labels: Vec<&str> = Vec::new();
+    let mut motifs_a: Vec<&str> = Vec::new();
+    let mut motifs_b: Vec<&str> = Vec::new();
+    let mut totals_a: Vec<f64> = Vec::new();
+    let mut totals_b: Vec<f64> = Vec::new();
+    let mut sites_a: Vec<u32> = Vec::new();
+    let mut sites_b: Vec<u32> = Vec::new();
+    let mut gained: Vec<u32> = Vec::new();
+    let mut lost: Vec<u32> = Vec::new();
+
+    for (label, seq) in &records {
+        for (id_a, id_b) in &pairs {
+            let (total_a, found_a) = motif_sites(seq, &a.ewms[id_a], a.mu, a.cutoff)
+                .with_motif(id_a)
+                .with_label(label)?;
+            let (total_b, found_b) = motif_sites(seq, &b.ewms[id_b], b.mu, b.cutoff)
+                .with_motif(id_b)
+                .with_label(label)?;
+
+            labels.push(label);
+            motifs_a.push(id_a);
+            motifs_b.push(id_b);
+            totals_a.push(total_a);
+            totals_b.push(total_b);
+            sites_a.push(found_a.len() as u32);
+            sites_b.push(found_b.len() as u32);
+            gained.push(found_b.difference(&found_a).count() as u32);
+            lost.push(found_a.difference(&found_b).count() as u32);
+        }
+    }
+
+    let deltas: Vec<f64> = totals_a.iter().zip(&totals_b).map(|(a, b)| b - a).collect();
+    let changed: Vec<bool> = deltas
+        .iter()
+        .zip(gained.iter().zip(&lost))
+        .map(|(delta, (gained, lost))| delta.abs() > tolerance || gained + lost > 0)
+        .collect();
+
+    let per_sequence = DataFrame::new(vec![
+        Column::new("label".into(), labels),
+        Column::new("motif_a".into(), motifs_a),
+        Column::new("motif_b".into(), motifs_b),
+        Column::new("occupancy_a".into(), totals_a),
+        Column::new("occupancy_b".into(), totals_b),
+        Column::new("delta".into(), deltas),
+        Column::new("sites_a".into(), sites_a),
+        Column::new("sites_b".into(), sites_b),
+        Column::new("sites_gained".into(), gained),
+        Column::new("sites_lost".into(), lost),
+        Column::new("changed".into(), changed),
+    ])
+    .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    let summary = per_sequence
+        .clone()
+        .lazy()
+        .with_column(
+            when(col("delta").lt(lit(0.0)))
+                .then(lit(0.0) - col("delta"))
+                .otherwise(col("delta"))
+                .alias("abs_delta"),
+        )
+        .group_by_stable([col("motif_a"), col("motif_b")])
+        .agg([
+            len().cast(DataType::UInt32).alias("n_sequences"),
+            col("delta").mean().alias("mean_delta"),
+            col("abs_delta").max().alias("max_abs_delta"),
+            col("sites_a").sum().alias("sites_a"),
+            col("sites_b").sum().alias("sites_b"),
+            col("sites_gained").sum().alias("sites_gained"),
+            col("sites_lost").sum().alias("sites_lost"),
+            col("changed")
+                .cast(DataType::UInt32)
+                .sum()
+                .alias("sequences_changed"),
+        ])
+        .collect()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    Ok(ConfigDiff {
+        per_sequence,
+        summary,
+        only_a,
+        only_b,
+    })
+}
+
+/// Result of `pairwise_similarity()`
+#[derive(Debug, Clone)]
+pub struct PairwiseSimilarity {
+    /// One row per pair of sequences, with columns:
+    /// - "label_a", "label_b": Labels of the pair
+    /// - "sites_a", "sites_b": Sites above cutoff of all motifs in each sequence
+    /// - "shared_sites": Sites of the same motif at the same strand and position in both
+    /// - "jaccard": `shared / (sites_a + sites_b - shared)`, null when neither has a site
+    /// - "occupancy_correlation": Pearson correlation of the total occupancies of the motifs,
+    ///   null with fewer than two motifs or constant occupancies
+    pub pairs: DataFrame,
+    /// One row per pair and motif, with columns:
+    /// - "label_a", "label_b", "motif"
+    /// - "occupancy_a", "occupancy_b": Total occupancy of the motif in each sequence
+    /// - "jaccard": Jaccard overlap of the motif's sites, null when neither has a site
+    /// - "landscape_correlation": Pearson correlation of the two occupancy landscapes, both
+    ///   strands, null when the sequences differ in length or a landscape is constant
+    pub per_motif: DataFrame,
+}
+
+/// Occupancy landscapes and sites of one sequence, for `pairwise_similarity()`
+struct SequenceProfile<'a> {
+    label: &'a str,
+    /// Per motif, both strands concatenated
+    landscapes: Vec<Vec<f64>>,
+    totals: Vec<f64>,
+    sites: Vec<HashSet<(char, usize)>>,
+}
+
+fn jaccard(a: &HashSet<(char, usize)>, b: &HashSet<(char, usize)>) -> (usize, Option<f64>) {
+    let shared = a.intersection(b).count();
+    let union = a.len() + b.len() - shared;
+    (shared, (union > 0).then(|| shared as f64 / union as f64))
+}
+
+/// Compares the predicted regulatory content of sequences pair by pair
+///
+/// For every pair of sequences, or every sequence against a reference, measures how much
+/// their sites overlap (Jaccard index of the sites above `cutoff`, matched by motif, strand
+/// and position) and how similar their occupancies are, over motifs (correlation of total
+/// occupancies) and along the sequence (correlation of the landscapes of each motif). Meant
+/// for grouping MPRA variants of an element, which share length and coordinates; positions
+/// of sequences that differ by indels do not correspond.
+///
+/// # Arguments
+/// * `sequences` - DataFrame with "label" and "sequence" columns
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are motif IDs
+/// * `mu` - Chemical potential of the transcription factors
+/// * `cutoff` - Minimum occupancy for a window to count as a site
+/// * `reference` - Label of the sequence to compare all others against; all pairs if `None`
+///
+/// # Returns
+/// * `Result<PairwiseSimilarity, MotifError>` - Per-pair and per-motif similarities, pairs
+///   in input order
+///
+/// # Errors
+/// * `MotifError::InvalidInput` - If the reference label is not in the table, or a sequence
+///   contains characters other than A, C, G, T
+/// * `MotifError::InvalidSequence` - If a sequence is shorter than a motif
+/// * `MotifError::DataError` - If required columns are missing or DataFrame creation fails
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::compare::pairwise_similarity;
+///
+/// let similarity = pairwise_similarity(&variants, &ewms, 9.0, 0.2, Some("CPPP_WT")).unwrap();
+/// println!("{}", similarity.pairs);
+/// ```
+pub fn pairwise_similarity(
+    sequences: &DataFrame,
+    ewms: &EWMCollection,
+    mu: f64,
+    cutoff: f64,
+    reference: Option<&str>,
+) -> Result<PairwiseSimilarity, MotifError> {
+    let mut motif_ids: Vec<&String> = ewms.keys().collect();
+    motif_ids.sort();
+
+    let profiles = labelled_sequences(sequences)?
+        .into_iter()
+        .map(|(label, seq)| {
+            let mut profile = SequenceProfile {
+                label,
+                landscapes: Vec::with_capacity(motif_ids.len()),
+                totals: Vec::with_capacity(motif_ids.len()),
+                sites: Vec::with_capacity(motif_ids.len()),
+            };
+            for id in &motif_ids {
+                let (forward, reverse) = occupancy_landscape(seq, &ewms[*id], mu)?;
+                let sites = [('F', &forward), ('R', &reverse)]
+                    .into_iter()
+                    .flat_map(|(strand, occs)| {
+                        occs.iter()
+                            .enumerate()
+                            .filter(|(_, &occ)| occ > cutoff)
+                            .map(move |(pos, _)| (strand, pos))
+                    })
+                    .collect();
+                let mut landscape = forward;
+                landscape.extend(reverse);
+                profile.totals.push(landscape.iter().sum());
+                profile.landscapes.push(landscape);
+                profile.sites.push(sites);
+            }
+            Ok(profile)
+        })
+        .collect::<Result<Vec<_>, MotifError>>()?;
+
+    let pairs: Vec<(usize, usize)> = match reference {
+        Some(reference) => {
+            let r = profiles
+                .iter()
+                .position(|p| p.label == reference)
+                .ok_or_else(|| {
+                    MotifError::InvalidInput(format!(
+                        "reference sequence '{}' is not in the table",
+                        reference
+                    ))
+                })?;
+            (0..profiles.len())
+                .filter(|&i| i != r)
+                .map(|i| (r, i))
+                .collect()
+        }
+        None => (0..profiles.len())
+            .flat_map(|i| (i + 1..profiles.len()).map(move |j| (i, j)))
+            .collect(),
+    };
+
+    let mut labels_a: Vec<&str> = Vec::with_capacity(pairs.len());
+    let mut labels_b: Vec<&str> = Vec::with_capacity(pairs.len());
+    let mut sites_a: Vec<u32> = Vec::with_capacity(pairs.len());
+    let mut sites_b: Vec<u32> = Vec::with_capacity(pairs.len());
+    let mut shared_sites: Vec<u32> = Vec::with_capacity(pairs.len());
+    let mut jaccards: Vec<Option<f64>> = Vec::with_capacity(pairs.len());
+    let mut correlations: Vec<Option<f64>> = Vec::with_capacity(pairs.len());
+    let rows = pairs.len() * motif_ids.len();
+    let mut motif_labels_a: Vec<&str> = Vec::with_capacity(rows);
+    let mut motif_labels_b: Vec<&str> = Vec::with_capacity(rows);
+    let mut motifs: Vec<&str> = Vec::with_capacity(rows);
+    let mut totals_a: Vec<f64> = Vec::with_capacity(rows);
+    let mut totals_b: Vec<f64> = Vec::with_capacity(rows);
+    let mut motif_jaccards: Vec<Option<f64>> = Vec::with_capacity(rows);
+    let mut landscape_correlations: Vec<Option<f64>> = Vec::with_capacity(rows);
+
+    for &(i, j) in &pairs {
+        let (a, b) = (&profiles[i], &profiles[j]);
+        let (mut shared, mut n_a, mut n_b) = (0, 0, 0);
+        for (m, id) in motif_ids.iter().enumerate() {
+            let (motif_shared, motif_jaccard) = jaccard(&a.sites[m], &b.sites[m]);
+            shared += motif_shared;
+            n_a += a.sites[m].len();
+            n_b += b.sites[m].len();
+
+            motif_labels_a.push(a.label);
+            motif_labels_b.push(b.label);
+            motifs.push(id);
+            totals_a.push(a.totals[m]);
+            totals_b.push(b.totals[m]);
+            motif_jaccards.push(motif_jaccard);
+            landscape_correlations.push(pearson_correlation(&a.landscapes[m], &b.landscapes[m]));
+        }
+        let union = n_a + n_b - shared;
+
+        labels_a.push(a.label);
+        labels_b.push(b.label);
+        sites_a.push(n_a as u32);
+        sites_b.push(n_b as u32);
+        shared_sites.push(shared as u32);
+        jaccards.push((union > 0).then(|| shared as f64 / union as f64));
+        correlations.push(pearson_correlation(&a.totals, &b.totals));
+    }
+
+    let per_motif = DataFrame::new(vec![
+        Column::new("label_a".into(), motif_labels_a),
+        Column::new("label_b".into(), motif_labels_b),
+        Column::new("motif".into(), motifs),
+        Column::new("occupancy_a".into(), totals_a),
+        Column::new("occupancy_b".into(), totals_b),
+        Column::new("jaccard".into(), motif_jaccards),
+        Column::new("landscape_correlation".into(), landscape_correlations),
+    ])
+    .map_err(|e| MotifError::DataError(e.to_string()))?;
+    let pairs = DataFrame::new(vec![
+        Column::new("label_a".into(), labels_a),
+        Column::new("label_b".into(), labels_b),
+        Column::new("sites_a".into(), sites_a),
+        Column::new("sites_b".into(), sites_b),
+        Column::new("shared_sites".into(), shared_sites),
+        Column::new("jaccard".into(), jaccards),
+        Column::new("occupancy_correlation".into(), correlations),
+    ])
+    .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    Ok(PairwiseSimilarity { pairs, per_motif })
+}
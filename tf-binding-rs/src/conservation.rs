@@ -0,0 +1,243 @@
+use crate::error::MotifError;
+use crate::occupancy::find_hits;
+use crate::tracks::window_mean;
+use crate::types::*;
+use polars::prelude::*;
+
+/// (motif, strand, position) identifying a hit within one sequence
+type HitKey = (String, String, i32);
+
+/// Finds hits and weights their occupancy by per-base conservation
+///
+/// Conserved motif instances are far more likely to be functional, so each hit reported by
+/// `find_hits()` is annotated with the mean conservation score over its window, and a
+/// weighted score computed as `occupancy * conservation`.
+///
+/// The conservation track is used as-is. phastCons scores already lie in [0, 1]; phyloP
+/// scores are unbounded and can be negative, so they should be rescaled first if a
+/// weighted score in [0, 1] is desired.
+///
+/// # Arguments
+/// * `seq` - The DNA sequence to scan
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are motif IDs
+/// * `mu` - Chemical potential of the transcription factors
+/// * `cutoff` - Minimum occupancy for a window to be reported
+/// * `conservation` - Per-base conservation scores aligned to `seq`, e.g. from
+///   `tracks::track_for_region()`
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - The columns of `find_hits()` plus:
+///   - "conservation": Mean conservation over the hit window
+///   - "weighted_occupancy": Occupancy multiplied by the window conservation
+///
+/// # Errors
+/// * `MotifError::InvalidInput` - If the track length does not match the sequence length
+/// * `MotifError::DataError` - If there are issues calculating occupancies or creating the DataFrame
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::{conservation, tracks};
+///
+/// let bedgraph = tracks::read_bedgraph("phastcons.bedGraph").unwrap();
+/// let track = tracks::track_for_region(&bedgraph, "chr1", 4357766, 4357930).unwrap();
+/// let hits = conservation::conservation_weighted_hits(&seq, &ewms, 9.0, 0.2, &track).unwrap();
+/// ```
+pub fn conservation_weighted_hits(
+    seq: &str,
+    ewms: &EWMCollection,
+    mu: f64,
+    cutoff: f64,
+    conservation: &[f64],
+) -> Result<DataFrame, MotifError> {
+    if conservation.len() != seq.len() {
+        return Err(MotifError::InvalidInput(format!(
+            "Conservation track has {} values but sequence has {} bases",
+            conservation.len(),
+            seq.len()
+        )));
+    }
+
+    let mut hits = find_hits(seq, ewms, mu, cutoff)?;
+
+    let positions = hits
+        .column("position")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .i32()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    let lengths = hits
+        .column("length")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .i32()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    let occupancies = hits
+        .column("occupancy")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .f64()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    let (scores, weighted): (Vec<f64>, Vec<f64>) = positions
+        .into_iter()
+        .zip(lengths)
+        .zip(occupancies)
+        .map(|((pos, len), occ)| {
+            let score = window_mean(
+                conservation,
+                pos.unwrap_or(0) as usize,
+                len.unwrap_or(0) as usize,
+            );
+            (score, occ.unwrap_or(0.0) * score)
+        })
+        .unzip();
+
+    hits.with_column(Column::new("conservation".into(), scores))
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    hits.with_column(Column::new("weighted_occupancy".into(), weighted))
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    Ok(hits)
+}
+
+/// Scans orthologous sequences and reports which reference hits are conserved
+///
+/// This supports phylogenetic footprinting: every sequence is scanned with `find_hits()`,
+/// and each hit in the reference sequence (the first row) is checked against the hits of
+/// all species. A species supports a reference hit if it has a hit for the same motif on
+/// the same strand whose position differs by at most `tolerance` bases. A hit is conserved
+/// when at least `min_species` species, including the reference, support it.
+///
+/// # Arguments
+/// * `orthologs` - DataFrame with one orthologous sequence per row, with "label" (species
+///   name) and "sequence" columns; the first row is used as the reference
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are motif IDs
+/// * `mu` - Chemical potential of the transcription factors
+/// * `cutoff` - Minimum occupancy for a window to count as a hit in any species
+/// * `min_species` - Number of supporting species required for a hit to be conserved
+/// * `tolerance` - Maximum positional offset between matching hits
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - The reference hits from `find_hits()` plus:
+///   - "n_species": Number of species supporting the hit
+///   - "species": Comma-separated labels of the supporting species
+///   - "conserved": Whether `n_species >= min_species`
+///
+/// # Errors
+/// * `MotifError::InvalidInput` - If `orthologs` is empty
+/// * `MotifError::DataError` - If required columns are missing or DataFrame creation fails
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::conservation::conserved_hits;
+///
+/// let orthologs = fasta::read_fasta("enhancer_orthologs.fasta").unwrap();
+/// let hits = conserved_hits(&orthologs, &ewms, 9.0, 0.2, 3, 5).unwrap();
+/// ```
+pub fn conserved_hits(
+    orthologs: &DataFrame,
+    ewms: &EWMCollection,
+    mu: f64,
+    cutoff: f64,
+    min_species: usize,
+    tolerance: usize,
+) -> Result<DataFrame, MotifError> {
+    if orthologs.height() == 0 {
+        return Err(MotifError::InvalidInput(
+            "No orthologous sequences given".to_string(),
+        ));
+    }
+
+    let labels = orthologs
+        .column("label")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .str()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    let sequences = orthologs
+        .column("sequence")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .str()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    // (motif, strand, position) of every hit, per species
+    let mut species_hits: Vec<(String, Vec<HitKey>)> = Vec::new();
+    let mut reference_hits = DataFrame::empty();
+
+    for idx in 0..orthologs.height() {
+        let label = labels.get(idx).unwrap_or_default().to_string();
+        let seq = sequences
+            .get(idx)
+            .ok_or_else(|| MotifError::DataError(format!("Missing sequence for {}", label)))?;
+
+        let hits = find_hits(seq, ewms, mu, cutoff)?;
+        species_hits.push((label, hit_keys(&hits)?));
+        if idx == 0 {
+            reference_hits = hits;
+        }
+    }
+
+    let reference_keys = &species_hits[0].1;
+    let mut n_species: Vec<u32> = Vec::with_capacity(reference_keys.len());
+    let mut supporting: Vec<String> = Vec::with_capacity(reference_keys.len());
+
+    for (motif, strand, pos) in reference_keys {
+        let species: Vec<&str> = species_hits
+            .iter()
+            .filter(|(_, keys)| {
+                keys.iter().any(|(m, s, p)| {
+                    m == motif && s == strand && p.abs_diff(*pos) as usize <= tolerance
+                })
+            })
+            .map(|(label, _)| label.as_str())
+            .collect();
+
+        n_species.push(species.len() as u32);
+        supporting.push(species.join(","));
+    }
+
+    let conserved: Vec<bool> = n_species
+        .iter()
+        .map(|&n| n as usize >= min_species)
+        .collect();
+
+    reference_hits
+        .with_column(Column::new("n_species".into(), n_species))
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    reference_hits
+        .with_column(Column::new("species".into(), supporting))
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    reference_hits
+        .with_column(Column::new("conserved".into(), conserved))
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    Ok(reference_hits)
+}
+
+/// Extracts (motif, strand, position) keys from a hit table
+fn hit_keys(hits: &DataFrame) -> Result<Vec<HitKey>, MotifError> {
+    let motifs = hits
+        .column("motif")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .str()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    let strands = hits
+        .column("strand")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .str()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    let positions = hits
+        .column("position")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .i32()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    Ok(motifs
+        .into_iter()
+        .zip(strands)
+        .zip(positions)
+        .map(|((m, s), p)| {
+            (
+                m.unwrap_or_default().to_string(),
+                s.unwrap_or_default().to_string(),
+                p.unwrap_or_default(),
+            )
+        })
+        .collect())
+}
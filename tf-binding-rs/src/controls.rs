@@ -0,0 +1,257 @@
+use crate::error::MotifError;
+use crate::occupancy::find_hits;
+use crate::types::*;
+use polars::prelude::*;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+
+/// Kind of control sequence to generate
+#[derive(Debug, Clone, Copy)]
+pub enum ControlKind<'a> {
+    /// Uniform shuffle of all bases, preserving mononucleotide composition
+    Shuffle,
+    /// Shuffle preserving dinucleotide counts (Altschul-Erickson)
+    DinucleotideShuffle,
+    /// Shuffle of only the bases inside predicted sites, leaving the rest untouched
+    MotifScramble {
+        /// Motifs whose predicted sites are scrambled
+        ewms: &'a EWMCollection,
+        /// Chemical potential of the transcription factors
+        mu: f64,
+        /// Minimum occupancy for a window to count as a site
+        cutoff: f64,
+    },
+}
+
+impl ControlKind<'_> {
+    fn suffix(&self) -> &'static str {
+        match self {
+            ControlKind::Shuffle => "shuffle",
+            ControlKind::DinucleotideShuffle => "dishuffle",
+            ControlKind::MotifScramble { .. } => "motifscramble",
+        }
+    }
+}
+
+/// Shuffles all bases of a sequence, preserving its base composition.
+///
+/// # Arguments
+/// * `seq` - Input DNA sequence
+/// * `rng` - Random number generator
+///
+/// # Returns
+/// * `String` - A uniformly random permutation of `seq`
+pub fn shuffle_sequence<R: Rng>(seq: &str, rng: &mut R) -> String {
+    let mut bases: Vec<char> = seq.chars().collect();
+    bases.shuffle(rng);
+    bases.into_iter().collect()
+}
+
+/// Shuffles a sequence while preserving its exact dinucleotide counts.
+///
+/// Implements the Altschul-Erickson algorithm via a random Eulerian walk: the sequence is
+/// treated as a path through a graph of bases, a random spanning arborescence of "last
+/// exit" edges rooted at the final base is drawn, and the remaining edges of each base are
+/// visited in random order. The result starts and ends with the same bases as the input.
+///
+/// # Arguments
+/// * `seq` - Input DNA sequence
+/// * `rng` - Random number generator
+///
+/// # Returns
+/// * `String` - A random sequence with the same dinucleotide counts as `seq`
+pub fn dinucleotide_shuffle<R: Rng>(seq: &str, rng: &mut R) -> String {
+    let chars: Vec<char> = seq.chars().collect();
+    if chars.len() < 3 {
+        return seq.to_string();
+    }
+
+    let mut edges: BTreeMap<char, Vec<char>> = BTreeMap::new();
+    for pair in chars.windows(2) {
+        edges.entry(pair[0]).or_default().push(pair[1]);
+    }
+
+    let last = chars[chars.len() - 1];
+    let mut vertices: Vec<char> = edges.keys().copied().filter(|&v| v != last).collect();
+    vertices.sort();
+
+    // draw last-exit edges until they form a tree rooted at the final base
+    let last_exit: BTreeMap<char, usize> = loop {
+        let choice: BTreeMap<char, usize> = vertices
+            .iter()
+            .map(|v| (*v, rng.gen_range(0..edges[v].len())))
+            .collect();
+
+        let reaches_last = vertices.iter().all(|&start| {
+            let mut current = start;
+            for _ in 0..=vertices.len() {
+                if current == last {
+                    return true;
+                }
+                current = edges[&current][choice[&current]];
+            }
+            current == last
+        });
+        if reaches_last {
+            break choice;
+        }
+    };
+
+    for (vertex, targets) in edges.iter_mut() {
+        match last_exit.get(vertex) {
+            Some(&idx) => {
+                let exit = targets.swap_remove(idx);
+                targets.shuffle(rng);
+                targets.push(exit);
+            }
+            None => targets.shuffle(rng),
+        }
+    }
+
+    let mut next_edge: BTreeMap<char, usize> = BTreeMap::new();
+    let mut current = chars[0];
+    let mut out = String::with_capacity(chars.len());
+    out.push(current);
+    for _ in 1..chars.len() {
+        let idx = next_edge.entry(current).or_insert(0);
+        let next = edges[&current][*idx];
+        *idx += 1;
+        out.push(next);
+        current = next;
+    }
+    out
+}
+
+/// Shuffles only the bases inside predicted motif sites.
+///
+/// Windows reported by `find_hits()` are merged into non-overlapping site regions, and the
+/// bases of each region are shuffled independently, so flanking sequence and local base
+/// composition are preserved while the sites themselves are destroyed.
+///
+/// # Arguments
+/// * `seq` - Input DNA sequence
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are motif IDs
+/// * `mu` - Chemical potential of the transcription factors
+/// * `cutoff` - Minimum occupancy for a window to count as a site
+/// * `rng` - Random number generator
+///
+/// # Returns
+/// * `Result<String, MotifError>` - The motif-scrambled sequence
+///
+/// # Errors
+/// * `MotifError::DataError` - If there are issues calculating occupancies
+pub fn motif_scramble<R: Rng>(
+    seq: &str,
+    ewms: &EWMCollection,
+    mu: f64,
+    cutoff: f64,
+    rng: &mut R,
+) -> Result<String, MotifError> {
+    let hits = find_hits(seq, ewms, mu, cutoff)?;
+    let positions = hits
+        .column("position")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .i32()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    let lengths = hits
+        .column("length")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .i32()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    let mut intervals: Vec<(usize, usize)> = positions
+        .into_iter()
+        .zip(lengths)
+        .filter_map(|(p, l)| Some((p? as usize, (p? + l?) as usize)))
+        .collect();
+    intervals.sort();
+
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in intervals {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+
+    let mut bases: Vec<char> = seq.chars().collect();
+    for (start, end) in merged {
+        bases[start..end].shuffle(rng);
+    }
+    Ok(bases.into_iter().collect())
+}
+
+/// Generates control sequences for every sequence in a DataFrame.
+///
+/// # Arguments
+/// * `df` - DataFrame containing sequences with "label" and "sequence" columns
+/// * `kind` - Kind of control to generate
+/// * `replicates` - Number of controls to generate per input sequence
+/// * `seed` - Seed of the random number generator; the same seed and input always give
+///   the same controls
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - A DataFrame with:
+///   - "label": `{source_label}_{kind}{replicate}`, e.g. `enh1_dishuffle0`
+///   - "sequence": The control sequence
+///   - "source_label": Label of the sequence the control was derived from
+///
+/// # Errors
+/// * `MotifError::DataError` - If required columns are missing or DataFrame creation fails
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::controls::{generate_controls, ControlKind};
+///
+/// let controls = generate_controls(&df, ControlKind::DinucleotideShuffle, 3, 42).unwrap();
+/// ```
+pub fn generate_controls(
+    df: &DataFrame,
+    kind: ControlKind,
+    replicates: usize,
+    seed: u64,
+) -> Result<DataFrame, MotifError> {
+    let labels = df
+        .column("label")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .str()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    let sequences = df
+        .column("sequence")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .str()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut out_labels: Vec<String> = Vec::new();
+    let mut out_sequences: Vec<String> = Vec::new();
+    let mut source_labels: Vec<String> = Vec::new();
+
+    for (label, seq) in labels.into_iter().zip(sequences) {
+        let (Some(label), Some(seq)) = (label, seq) else {
+            continue;
+        };
+
+        for replicate in 0..replicates {
+            let control = match kind {
+                ControlKind::Shuffle => shuffle_sequence(seq, &mut rng),
+                ControlKind::DinucleotideShuffle => dinucleotide_shuffle(seq, &mut rng),
+                ControlKind::MotifScramble { ewms, mu, cutoff } => {
+                    motif_scramble(seq, ewms, mu, cutoff, &mut rng)?
+                }
+            };
+            out_labels.push(format!("{}_{}{}", label, kind.suffix(), replicate));
+            out_sequences.push(control);
+            source_labels.push(label.to_string());
+        }
+    }
+
+    DataFrame::new(vec![
+        Column::new("label".into(), out_labels),
+        Column::new("sequence".into(), out_sequences),
+        Column::new("source_label".into(), source_labels),
+    ])
+    .map_err(|e| MotifError::DataError(e.to_string()))
+}
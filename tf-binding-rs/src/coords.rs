@@ -0,0 +1,552 @@
+use crate::error::MotifError;
+use polars::prelude::*;
+use regex::Regex;
+use std::collections::HashMap;
+
+/// Default label pattern: `chrom-start-end` or `chrom:start-end`, optionally followed by the
+/// strand in parentheses (as written by `bedtools getfasta -s`) and `_suffix`, e.g.
+/// `chr1-4357766-4357930_CPPP_WT` or `chr2:100-200(-)`
+pub const DEFAULT_LABEL_PATTERN: &str = r"^(?P<chrom>[^:\-\s]+)[:\-](?P<start>\d+)-(?P<end>\d+)(?:\((?P<strand>[+-])\))?(?:_(?P<suffix>.*))?$";
+
+/// Convention of the start coordinate written in labels
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CoordinateSystem {
+    /// 0-based start, exclusive end, as in BED files
+    #[default]
+    ZeroBased,
+    /// 1-based start, inclusive end, as in `chr1:100-200` browser positions and GFF
+    OneBased,
+}
+
+/// A genomic interval parsed from a label, always stored 0-based with an exclusive end
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub chrom: String,
+    pub start: u64,
+    pub end: u64,
+    /// Whether the sequence was extracted from the minus strand (reverse complemented)
+    pub minus_strand: bool,
+    /// Text after the coordinates, such as a construct name
+    pub suffix: Option<String>,
+}
+
+/// Parses genomic coordinates out of sequence labels
+///
+/// The pattern must have the named groups `chrom`, `start` and `end`, and may have `strand`
+/// (`+` or `-`) and `suffix`.
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::coords::LabelParser;
+///
+/// let region = LabelParser::default().parse("chr1-4357766-4357930_CPPP_WT").unwrap();
+/// assert_eq!((region.start, region.end), (4357766, 4357930));
+/// assert_eq!(region.suffix.as_deref(), Some("CPPP_WT"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct LabelParser {
+    pattern: Regex,
+    system: CoordinateSystem,
+}
+
+impl Default for LabelParser {
+    fn default() -> Self {
+        LabelParser {
+            pattern: Regex::new(DEFAULT_LABEL_PATTERN).expect("valid default pattern"),
+            system: CoordinateSystem::ZeroBased,
+        }
+    }
+}
+
+impl LabelParser {
+    /// Creates a parser from a regular expression and the coordinate system of its labels
+    ///
+    /// # Errors
+    /// * `MotifError::InvalidParameter` - If the pattern is invalid or lacks a `chrom`,
+    ///   `start` or `end` group
+    pub fn new(pattern: &str, system: CoordinateSystem) -> Result<Self, MotifError> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| MotifError::invalid_parameter("pattern", pattern, e.to_string()))?;
+        for group in ["chrom", "start", "end"] {
+            if !regex.capture_names().any(|name| name == Some(group)) {
+                return Err(MotifError::invalid_parameter(
+                    "pattern",
+                    pattern,
+                    format!("missing the named group '{}'", group),
+                ));
+            }
+        }
+        Ok(LabelParser {
+            pattern: regex,
+            system,
+        })
+    }
+
+    /// Parses one label, returning `None` if it does not match or its interval is empty
+    pub fn parse(&self, label: &str) -> Option<Region> {
+        let captures = self.pattern.captures(label)?;
+        let mut start: u64 = captures["start"].parse().ok()?;
+        let end: u64 = captures["end"].parse().ok()?;
+        if self.system == CoordinateSystem::OneBased {
+            start = start.checked_sub(1)?;
+        }
+        if end <= start {
+            return None;
+        }
+        Some(Region {
+            chrom: captures["chrom"].to_string(),
+            start,
+            end,
+            minus_strand: captures.name("strand").is_some_and(|m| m.as_str() == "-"),
+            suffix: captures
+                .name("suffix")
+                .map(|m| m.as_str().to_string())
+                .filter(|s| !s.is_empty()),
+        })
+    }
+}
+
+/// Adds the coordinates encoded in a label column as `chrom`, `start`, `end`, `strand` (`+`
+/// or `-`) and `suffix` columns.
+///
+/// `start` is 0-based and `end` exclusive whatever the labels use, so the columns can be
+/// written as BED directly. Rows whose label does not parse get nulls. Existing columns with
+/// these names are replaced.
+///
+/// # Arguments
+/// * `df` - DataFrame with a string column of labels
+/// * `column` - Name of the label column, usually "label"
+/// * `parser` - Label pattern and coordinate system
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - `df` with the coordinate columns added
+///
+/// # Errors
+/// * `MotifError::DataError` - If the label column is missing or not a string column
+pub fn parse_labels(
+    df: &DataFrame,
+    column: &str,
+    parser: &LabelParser,
+) -> Result<DataFrame, MotifError> {
+    let regions: Vec<Option<Region>> = df
+        .column(column)
+        .and_then(|c| c.str().cloned())
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .into_iter()
+        .map(|label| label.and_then(|label| parser.parse(label)))
+        .collect();
+
+    let mut out = df.clone();
+    let columns = [
+        Column::new(
+            "chrom".into(),
+            regions
+                .iter()
+                .map(|r| r.as_ref().map(|r| r.chrom.as_str()))
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "start".into(),
+            regions
+                .iter()
+                .map(|r| r.as_ref().map(|r| r.start))
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "end".into(),
+            regions
+                .iter()
+                .map(|r| r.as_ref().map(|r| r.end))
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "strand".into(),
+            regions
+                .iter()
+                .map(|r| r.as_ref().map(|r| if r.minus_strand { "-" } else { "+" }))
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "suffix".into(),
+            regions
+                .iter()
+                .map(|r| r.as_ref().and_then(|r| r.suffix.as_deref()))
+                .collect::<Vec<_>>(),
+        ),
+    ];
+    for column in columns {
+        out.with_column(column)
+            .map_err(|e| MotifError::DataError(e.to_string()))?;
+    }
+    Ok(out)
+}
+
+/// Converts local hit positions into genomic coordinates.
+///
+/// `regions` describes where every sequence comes from, with the columns `label`, `chrom`,
+/// `start` (0-based) and `end` (exclusive), and optionally `strand`; the output of
+/// `parse_labels()` qualifies. For a sequence extracted from the minus strand, positions count
+/// from the region's end and hit strands flip, so a forward hit on a reverse-complemented
+/// sequence lies on the genomic minus strand.
+///
+/// Adds the columns `chrom`, `genomic_start` (0-based), `genomic_end` (exclusive) and
+/// `genomic_strand` (`+` or `-`) to `hits`; they are null for hits whose label has no region.
+/// Labels are compared as strings, so integer and string labels can be mixed.
+///
+/// # Arguments
+/// * `hits` - Hit table with "label", "position", "strand" ("F"/"R") and "length" columns
+/// * `regions` - One row per sequence, as described above
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - `hits` with the genomic columns added
+///
+/// # Errors
+/// * `MotifError::DataError` - If a required column is missing or has the wrong type
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::coords::{parse_labels, project_hits, LabelParser};
+///
+/// let regions = parse_labels(&sequences, "label", &LabelParser::default()).unwrap();
+/// let hits = project_hits(&scan.hits, &regions).unwrap();
+/// ```
+pub fn project_hits(hits: &DataFrame, regions: &DataFrame) -> Result<DataFrame, MotifError> {
+    let data_err = |e: PolarsError| MotifError::DataError(e.to_string());
+    let strings = |df: &DataFrame, name: &str| -> Result<StringChunked, MotifError> {
+        Ok(df
+            .column(name)
+            .and_then(|c| c.cast(&DataType::String))
+            .map_err(data_err)?
+            .str()
+            .map_err(data_err)?
+            .clone())
+    };
+    let integers = |df: &DataFrame, name: &str| -> Result<Int64Chunked, MotifError> {
+        Ok(df
+            .column(name)
+            .and_then(|c| c.cast(&DataType::Int64))
+            .map_err(data_err)?
+            .i64()
+            .map_err(data_err)?
+            .clone())
+    };
+
+    let region_strands = match regions.column("strand") {
+        Ok(_) => strings(regions, "strand")?,
+        Err(_) => StringChunked::full_null("strand".into(), regions.height()),
+    };
+    let mut lookup: HashMap<&str, (&str, i64, i64, bool)> = HashMap::new();
+    let (region_labels, chroms) = (strings(regions, "label")?, strings(regions, "chrom")?);
+    let (starts, ends) = (integers(regions, "start")?, integers(regions, "end")?);
+    for i in 0..regions.height() {
+        if let (Some(label), Some(chrom), Some(start), Some(end)) = (
+            region_labels.get(i),
+            chroms.get(i),
+            starts.get(i),
+            ends.get(i),
+        ) {
+            lookup.insert(
+                label,
+                (chrom, start, end, region_strands.get(i) == Some("-")),
+            );
+        }
+    }
+
+    let labels = strings(hits, "label")?;
+    let positions = integers(hits, "position")?;
+    let lengths = integers(hits, "length")?;
+    let strands = strings(hits, "strand")?;
+    let n = hits.height();
+    let (mut out_chroms, mut out_starts, mut out_ends, mut out_strands) = (
+        Vec::with_capacity(n),
+        Vec::with_capacity(n),
+        Vec::with_capacity(n),
+        Vec::with_capacity(n),
+    );
+    for i in 0..n {
+        let projected = labels
+            .get(i)
+            .and_then(|label| lookup.get(label))
+            .zip(positions.get(i).zip(lengths.get(i)));
+        match projected {
+            Some((&(chrom, start, end, minus), (position, length))) => {
+                let genomic_start = if minus {
+                    end - position - length
+                } else {
+                    start + position
+                };
+                let forward = strands.get(i) != Some("R");
+                out_chroms.push(Some(chrom));
+                out_starts.push(Some(genomic_start));
+                out_ends.push(Some(genomic_start + length));
+                out_strands.push(Some(if forward != minus { "+" } else { "-" }));
+            }
+            None => {
+                out_chroms.push(None);
+                out_starts.push(None);
+                out_ends.push(None);
+                out_strands.push(None);
+            }
+        }
+    }
+
+    let mut out = hits.clone();
+    for column in [
+        Column::new("chrom".into(), out_chroms),
+        Column::new("genomic_start".into(), out_starts),
+        Column::new("genomic_end".into(), out_ends),
+        Column::new("genomic_strand".into(), out_strands),
+    ] {
+        out.with_column(column).map_err(data_err)?;
+    }
+    Ok(out)
+}
+
+/// One hit of `cluster_tile_hits()` in genomic coordinates
+struct TileHit<'a> {
+    motif: &'a str,
+    chrom: &'a str,
+    strand: &'a str,
+    start: i64,
+    end: i64,
+    tile: &'a str,
+    occupancy: f64,
+}
+
+/// Merges the hits of overlapping tiles into a non-redundant list of genomic sites.
+///
+/// Tiled libraries scan every genomic site once per tile that covers it. The hits are
+/// projected with `project_hits()`, and hits of the same motif on the same chromosome and
+/// genomic strand whose starts lie within `max_shift` bases of the first hit of a cluster are
+/// one site; with `max_shift = 0` only exact duplicates merge. Every site reports the
+/// coordinates of its strongest hit, the number of tiles it was found in and the number of
+/// tiles that fully cover it: a site found in fewer tiles than cover it changes with its
+/// flanks, e.g. near tile edges of motifs with dinucleotide-dependent energies, or differs
+/// between overlapping tiles of variant libraries. Hits whose label has no region are left
+/// out.
+///
+/// # Arguments
+/// * `hits` - Hit table with "label", "position", "motif", "strand", "length" and
+///   "occupancy" columns
+/// * `regions` - One row per tile with "label", "chrom", "start", "end" and optionally
+///   "strand" columns, e.g. from `parse_labels()`
+/// * `max_shift` - Largest distance in bases between the starts of hits of one site
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - One row per site, sorted by motif, chromosome, strand
+///   and position, with the columns "motif", "chrom", "genomic_start", "genomic_end",
+///   "genomic_strand", "occupancy" (highest), "mean_occupancy", "support" (tiles with the
+///   site), "covering" (tiles containing the site), "support_fraction" and "tiles" (labels of
+///   the supporting tiles, comma-separated)
+///
+/// # Errors
+/// * `MotifError::DataError` - If a required column is missing or has the wrong type
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::coords::{cluster_tile_hits, parse_labels, LabelParser};
+///
+/// let regions = parse_labels(&tiles, "label", &LabelParser::default()).unwrap();
+/// let sites = cluster_tile_hits(&hits, &regions, 0).unwrap();
+/// ```
+pub fn cluster_tile_hits(
+    hits: &DataFrame,
+    regions: &DataFrame,
+    max_shift: i64,
+) -> Result<DataFrame, MotifError> {
+    let data_err = |e: PolarsError| MotifError::DataError(e.to_string());
+    let projected = project_hits(hits, regions)?;
+    let strings = |df: &DataFrame, name: &str| -> Result<StringChunked, MotifError> {
+        Ok(df
+            .column(name)
+            .and_then(|c| c.cast(&DataType::String))
+            .map_err(data_err)?
+            .str()
+            .map_err(data_err)?
+            .clone())
+    };
+    let integers = |df: &DataFrame, name: &str| -> Result<Int64Chunked, MotifError> {
+        Ok(df
+            .column(name)
+            .and_then(|c| c.cast(&DataType::Int64))
+            .map_err(data_err)?
+            .i64()
+            .map_err(data_err)?
+            .clone())
+    };
+
+    let (motifs, chroms, strands, labels) = (
+        strings(&projected, "motif")?,
+        strings(&projected, "chrom")?,
+        strings(&projected, "genomic_strand")?,
+        strings(&projected, "label")?,
+    );
+    let (starts, ends) = (
+        integers(&projected, "genomic_start")?,
+        integers(&projected, "genomic_end")?,
+    );
+    let occupancies = projected
+        .column("occupancy")
+        .and_then(|c| c.f64())
+        .map_err(data_err)?;
+    let mut tile_hits: Vec<TileHit> = (0..projected.height())
+        .filter_map(|i| {
+            Some(TileHit {
+                motif: motifs.get(i)?,
+                chrom: chroms.get(i)?,
+                strand: strands.get(i)?,
+                start: starts.get(i)?,
+                end: ends.get(i)?,
+                tile: labels.get(i)?,
+                occupancy: occupancies.get(i)?,
+            })
+        })
+        .collect();
+    tile_hits.sort_by(|a, b| {
+        (a.motif, a.chrom, a.strand, a.start).cmp(&(b.motif, b.chrom, b.strand, b.start))
+    });
+
+    // tiles of every chromosome sorted by start, to count the tiles covering a site
+    let (region_chroms, region_starts, region_ends) = (
+        strings(regions, "chrom")?,
+        integers(regions, "start")?,
+        integers(regions, "end")?,
+    );
+    let mut tiles: HashMap<&str, Vec<(i64, i64)>> = HashMap::new();
+    for i in 0..regions.height() {
+        if let (Some(chrom), Some(start), Some(end)) = (
+            region_chroms.get(i),
+            region_starts.get(i),
+            region_ends.get(i),
+        ) {
+            tiles.entry(chrom).or_default().push((start, end));
+        }
+    }
+    let longest = tiles
+        .values()
+        .flatten()
+        .map(|(start, end)| end - start)
+        .max()
+        .unwrap_or(0);
+    for spans in tiles.values_mut() {
+        spans.sort_unstable();
+    }
+    let covering = |chrom: &str, start: i64, end: i64| -> u32 {
+        let Some(spans) = tiles.get(chrom) else {
+            return 0;
+        };
+        let last = spans.partition_point(|&(s, _)| s <= start);
+        spans[..last]
+            .iter()
+            .rev()
+            .take_while(|&&(s, _)| start - s <= longest)
+            .filter(|&&(_, e)| e >= end)
+            .count() as u32
+    };
+
+    let mut sites = SiteColumns::default();
+    let mut first = 0;
+    while first < tile_hits.len() {
+        let anchor = &tile_hits[first];
+        let mut last = first + 1;
+        while last < tile_hits.len() {
+            let hit = &tile_hits[last];
+            if (hit.motif, hit.chrom, hit.strand) != (anchor.motif, anchor.chrom, anchor.strand)
+                || hit.start - anchor.start > max_shift
+            {
+                break;
+            }
+            last += 1;
+        }
+        let cluster = &tile_hits[first..last];
+        let best = cluster
+            .iter()
+            .max_by(|a, b| a.occupancy.total_cmp(&b.occupancy))
+            .expect("clusters are not empty");
+        let mut supporting: Vec<&str> = cluster.iter().map(|hit| hit.tile).collect();
+        supporting.sort_unstable();
+        supporting.dedup();
+        let cover = covering(best.chrom, best.start, best.end).max(supporting.len() as u32);
+
+        sites.motifs.push(best.motif);
+        sites.chroms.push(best.chrom);
+        sites.starts.push(best.start);
+        sites.ends.push(best.end);
+        sites.strands.push(best.strand);
+        sites.occupancies.push(best.occupancy);
+        sites
+            .mean_occupancies
+            .push(cluster.iter().map(|hit| hit.occupancy).sum::<f64>() / cluster.len() as f64);
+        sites.support.push(supporting.len() as u32);
+        sites.covering.push(cover);
+        sites.fractions.push(supporting.len() as f64 / cover as f64);
+        sites.tiles.push(supporting.join(","));
+        first = last;
+    }
+
+    DataFrame::new(vec![
+        Column::new("motif".into(), sites.motifs),
+        Column::new("chrom".into(), sites.chroms),
+        Column::new("genomic_start".into(), sites.starts),
+        Column::new("genomic_end".into(), sites.ends),
+        Column::new("genomic_strand".into(), sites.strands),
+        Column::new("occupancy".into(), sites.occupancies),
+        Column::new("mean_occupancy".into(), sites.mean_occupancies),
+        Column::new("support".into(), sites.support),
+        Column::new("covering".into(), sites.covering),
+        Column::new("support_fraction".into(), sites.fractions),
+        Column::new("tiles".into(), sites.tiles),
+    ])
+    .map_err(data_err)
+}
+
+/// Columns of the site table of `cluster_tile_hits()`
+#[derive(Default)]
+struct SiteColumns<'a> {
+    motifs: Vec<&'a str>,
+    chroms: Vec<&'a str>,
+    starts: Vec<i64>,
+    ends: Vec<i64>,
+    strands: Vec<&'a str>,
+    occupancies: Vec<f64>,
+    mean_occupancies: Vec<f64>,
+    support: Vec<u32>,
+    covering: Vec<u32>,
+    fractions: Vec<f64>,
+    tiles: Vec<String>,
+}
+
+/// Reads a chromosome sizes file (`chrom<TAB>size` per line, as from `fetchChromSizes` or
+/// the first two columns of a `.fai` index).
+///
+/// # Arguments
+/// * `filename` - Path to the chrom sizes file
+///
+/// # Returns
+/// * `Result<HashMap<String, u32>, MotifError>` - Length of every chromosome
+///
+/// # Errors
+/// * `MotifError::InvalidFileFormat` - If a line has no valid size
+/// * `MotifError::Io` - For file reading issues
+pub fn read_chrom_sizes(filename: &str) -> Result<HashMap<String, u32>, MotifError> {
+    let text = std::fs::read_to_string(filename)?;
+    let mut sizes = HashMap::new();
+    for (i, line) in text.lines().enumerate() {
+        let mut fields = line.split_whitespace();
+        let Some(chrom) = fields.next() else {
+            continue;
+        };
+        let size = fields
+            .next()
+            .and_then(|size| size.parse::<u32>().ok())
+            .ok_or_else(|| {
+                MotifError::InvalidFileFormat(format!(
+                    "{} line {}: expected a chromosome and its size",
+                    filename,
+                    i + 1
+                ))
+            })?;
+        sizes.insert(chrom.to_string(), size);
+    }
+    Ok(sizes)
+}
@@ -0,0 +1,965 @@
+use crate::error::MotifError;
+use crate::fasta::reverse_complement;
+use crate::occupancy::{occupancy_landscape, total_landscape, total_occupancy};
+use crate::types::*;
+use polars::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+
+const BASES: [char; 4] = ['A', 'C', 'G', 'T'];
+
+/// Parameters for tiling candidate regions into MPRA constructs
+#[derive(Debug, Clone)]
+pub struct TileOptions {
+    /// Length of each tile, excluding adapters
+    pub tile_length: usize,
+    /// Offset between the starts of consecutive tiles
+    pub step: usize,
+    /// Adapter prepended to every tile
+    pub left_adapter: String,
+    /// Adapter appended to every tile
+    pub right_adapter: String,
+    /// Restriction sites that must not occur in the final construct, on either strand
+    pub restriction_sites: Vec<String>,
+    /// Maximum number of single-base edits used to repair a tile before rejecting it
+    pub max_edits: usize,
+}
+
+impl Default for TileOptions {
+    fn default() -> Self {
+        TileOptions {
+            tile_length: 164,
+            step: 164,
+            left_adapter: String::new(),
+            right_adapter: String::new(),
+            restriction_sites: Vec::new(),
+            max_edits: 3,
+        }
+    }
+}
+
+/// Tiles candidate regions into fixed-length MPRA constructs with adapters
+///
+/// Each region is cut into tiles of `tile_length` bases every `step` bases; a final tile
+/// aligned to the end of the region is added when the regular tiling would leave bases
+/// uncovered. A region shorter than `tile_length` produces a single tile of the whole
+/// region, which is shorter than the others, and an empty region produces no tiles.
+/// Tiles are wrapped in the adapters and checked for restriction sites (and their reverse
+/// complements, so non-palindromic Golden Gate sites are caught too), including sites
+/// spanning the adapter junctions.
+///
+/// Tiles containing a site are repaired with single-base edits inside the tile: for each
+/// remaining site, every substitution that removes it without creating another site is
+/// tried, and the one that changes the tile's occupancy landscape the least (sum of
+/// absolute differences over all motifs and strands) is kept. Tiles that cannot be
+/// repaired within `max_edits` edits are kept in the table but marked as rejected, as are
+/// tiles with a site that are shorter than the longest motif, whose edits cannot be scored.
+///
+/// # Arguments
+/// * `regions` - DataFrame with "label" and "sequence" columns
+/// * `options` - Tiling, adapter and restriction site parameters
+/// * `ewms` - Collection of Energy Weight Matrices used to score edits
+/// * `mu` - Chemical potential of the transcription factors
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - Design table with one row per tile and columns:
+///   - "label": Label of the source region
+///   - "tile": Index of the tile within its region
+///   - "start": 0-based start of the tile in the region
+///   - "end": Exclusive end of the tile in the region
+///   - "sequence": Full construct sequence including adapters
+///   - "n_edits": Number of bases changed during repair
+///   - "edits": Edits as `position:REF>ALT`, separated by `;` (positions within the tile)
+///   - "status": "ok", "repaired" or "rejected"
+///
+/// # Errors
+/// * `MotifError::InvalidParameter` - If `tile_length` or `step` is zero
+/// * `MotifError::InvalidInput` - If a region contains invalid nucleotides
+/// * `MotifError::DataError` - If required columns are missing or DataFrame creation fails
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::design::{design_tiles, TileOptions};
+///
+/// let options = TileOptions {
+///     tile_length: 150,
+///     step: 50,
+///     left_adapter: "ACTGGCCGCTTGACG".to_string(),
+///     right_adapter: "CACTGCGGCTCCTGC".to_string(),
+///     restriction_sites: vec!["GAATTC".to_string(), "GGTACC".to_string()],
+///     ..Default::default()
+/// };
+/// let design = design_tiles(&regions, &options, &ewms, 9.0).unwrap();
+/// ```
+pub fn design_tiles(
+    regions: &DataFrame,
+    options: &TileOptions,
+    ewms: &EWMCollection,
+    mu: f64,
+) -> Result<DataFrame, MotifError> {
+    if options.tile_length == 0 {
+        return Err(MotifError::invalid_parameter(
+            "tile_length",
+            options.tile_length,
+            "must be positive",
+        ));
+    }
+    if options.step == 0 {
+        return Err(MotifError::invalid_parameter(
+            "step",
+            options.step,
+            "must be positive",
+        ));
+    }
+
+    let sites = site_patterns(&options.restriction_sites)?;
+
+    let labels = regions
+        .column("label")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .str()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    let sequences = regions
+        .column("sequence")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .str()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    let mut out_labels: Vec<String> = Vec::new();
+    let mut out_tiles: Vec<u32> = Vec::new();
+    let mut out_starts: Vec<u32> = Vec::new();
+    let mut out_ends: Vec<u32> = Vec::new();
+    let mut out_sequences: Vec<String> = Vec::new();
+    let mut out_n_edits: Vec<u32> = Vec::new();
+    let mut out_edits: Vec<String> = Vec::new();
+    let mut out_status: Vec<&str> = Vec::new();
+
+    for idx in 0..regions.height() {
+        let label = labels.get(idx).unwrap_or_default();
+        let Some(region) = sequences.get(idx) else {
+            continue;
+        };
+
+        for (tile_idx, (start, end)) in tile_bounds(region.len(), options).into_iter().enumerate() {
+            let tile = &region[start..end];
+            let repair = repair_tile(tile, options, &sites, ewms, mu)?;
+
+            let status = match (&repair, repair.as_ref().map_or(0, |r| r.1.len())) {
+                (None, _) => "rejected",
+                (Some(_), 0) => "ok",
+                (Some(_), _) => "repaired",
+            };
+            let (tile_seq, edits) = repair.unwrap_or_else(|| (tile.to_string(), Vec::new()));
+
+            out_labels.push(label.to_string());
+            out_tiles.push(tile_idx as u32);
+            out_starts.push(start as u32);
+            out_ends.push(end as u32);
+            out_sequences.push(format!(
+                "{}{}{}",
+                options.left_adapter, tile_seq, options.right_adapter
+            ));
+            out_n_edits.push(edits.len() as u32);
+            out_edits.push(
+                edits
+                    .iter()
+                    .map(|(pos, from, to)| format!("{}:{}>{}", pos, from, to))
+                    .collect::<Vec<_>>()
+                    .join(";"),
+            );
+            out_status.push(status);
+        }
+    }
+
+    DataFrame::new(vec![
+        Column::new("label".into(), out_labels),
+        Column::new("tile".into(), out_tiles),
+        Column::new("start".into(), out_starts),
+        Column::new("end".into(), out_ends),
+        Column::new("sequence".into(), out_sequences),
+        Column::new("n_edits".into(), out_n_edits),
+        Column::new("edits".into(), out_edits),
+        Column::new("status".into(), out_status),
+    ])
+    .map_err(|e| MotifError::DataError(e.to_string()))
+}
+
+/// Computes the (start, end) of every tile of a region
+fn tile_bounds(region_len: usize, options: &TileOptions) -> Vec<(usize, usize)> {
+    if region_len == 0 {
+        return Vec::new();
+    }
+    if region_len <= options.tile_length {
+        return vec![(0, region_len)];
+    }
+
+    let last_start = region_len - options.tile_length;
+    let mut bounds: Vec<(usize, usize)> = (0..=last_start)
+        .step_by(options.step)
+        .map(|start| (start, start + options.tile_length))
+        .collect();
+    if bounds.last().is_some_and(|&(start, _)| start != last_start) {
+        bounds.push((last_start, region_len));
+    }
+    bounds
+}
+
+/// Restriction site patterns on both strands, deduplicated
+fn site_patterns(sites: &[String]) -> Result<Vec<String>, MotifError> {
+    let mut patterns: Vec<String> = Vec::new();
+    for site in sites {
+        let site = site.to_uppercase();
+        let rc = reverse_complement(&site)?;
+        for pattern in [site, rc] {
+            if !pattern.is_empty() && !patterns.contains(&pattern) {
+                patterns.push(pattern);
+            }
+        }
+    }
+    Ok(patterns)
+}
+
+/// Number of restriction site occurrences in a construct, counting overlaps
+fn count_sites(construct: &str, sites: &[String]) -> usize {
+    sites
+        .iter()
+        .map(|site| {
+            (0..=construct.len().saturating_sub(site.len()))
+                .filter(|&i| construct[i..].starts_with(site.as_str()))
+                .count()
+        })
+        .sum()
+}
+
+/// First restriction site occurrence in a construct as (start, end)
+fn first_site(construct: &str, sites: &[String]) -> Option<(usize, usize)> {
+    sites
+        .iter()
+        .filter_map(|site| construct.find(site.as_str()).map(|i| (i, i + site.len())))
+        .min()
+}
+
+/// Sum of absolute occupancy differences between two landscapes over shared columns
+pub(crate) fn landscape_distance(a: &DataFrame, b: &DataFrame) -> Result<f64, MotifError> {
+    let mut total = 0.0;
+    for column in a.get_columns() {
+        let other = b
+            .column(column.name())
+            .map_err(|e| MotifError::DataError(e.to_string()))?;
+        let lhs = column
+            .f64()
+            .map_err(|e| MotifError::DataError(e.to_string()))?;
+        let rhs = other
+            .f64()
+            .map_err(|e| MotifError::DataError(e.to_string()))?;
+        total += lhs
+            .into_iter()
+            .zip(rhs)
+            .map(|(x, y)| (x.unwrap_or(0.0) - y.unwrap_or(0.0)).abs())
+            .sum::<f64>();
+    }
+    Ok(total)
+}
+
+/// Removes restriction sites from a tile with minimal-impact single-base edits
+///
+/// Returns the repaired tile and the applied edits as (position, from, to), or `None` if
+/// the tile cannot be repaired within `max_edits` edits or is too short to be scored.
+#[allow(clippy::type_complexity)]
+fn repair_tile(
+    tile: &str,
+    options: &TileOptions,
+    sites: &[String],
+    ewms: &EWMCollection,
+    mu: f64,
+) -> Result<Option<(String, Vec<(usize, char, char)>)>, MotifError> {
+    let left = options.left_adapter.as_str();
+    let right = options.right_adapter.as_str();
+    let construct = |t: &str| format!("{}{}{}", left, t, right);
+
+    let mut current = tile.to_string();
+    let mut edits: Vec<(usize, char, char)> = Vec::new();
+    let mut baseline: Option<DataFrame> = None;
+    let longest = ewms.values().map(|ewm| ewm.height()).max().unwrap_or(0);
+
+    loop {
+        let full = construct(&current);
+        let Some((site_start, site_end)) = first_site(&full, sites) else {
+            return Ok(Some((current, edits)));
+        };
+        if edits.len() >= options.max_edits || tile.len() < longest {
+            return Ok(None);
+        }
+
+        let landscape = match &baseline {
+            Some(l) => l,
+            None => baseline.insert(total_landscape(tile, ewms, mu)?),
+        };
+        let n_sites = count_sites(&full, sites);
+
+        // edit positions of the site that fall inside the tile
+        let lo = site_start.max(left.len()) - left.len();
+        let hi = site_end
+            .min(left.len() + current.len())
+            .saturating_sub(left.len());
+
+        let mut best: Option<(f64, usize, char, char, String)> = None;
+        for pos in lo..hi {
+            let from = current.as_bytes()[pos] as char;
+            for to in BASES.iter().copied().filter(|&b| b != from) {
+                let mut candidate = current.clone();
+                candidate.replace_range(pos..pos + 1, &to.to_string());
+
+                if count_sites(&construct(&candidate), sites) >= n_sites {
+                    continue;
+                }
+
+                let distance =
+                    landscape_distance(landscape, &total_landscape(&candidate, ewms, mu)?)?;
+                if best.as_ref().is_none_or(|b| distance < b.0) {
+                    best = Some((distance, pos, from, to, candidate));
+                }
+            }
+        }
+
+        match best {
+            Some((_, pos, from, to, candidate)) => {
+                edits.push((pos, from, to));
+                current = candidate;
+            }
+            None => return Ok(None),
+        }
+    }
+}
+
+/// A single motif site on a sequence
+#[derive(Debug, Clone, PartialEq)]
+pub struct Site {
+    /// Motif ID of the site
+    pub motif: String,
+    /// 0-based start of the site window on the forward strand
+    pub position: usize,
+    /// "F" or "R"
+    pub strand: String,
+}
+
+/// Parameters for `ablate_site()`
+#[derive(Debug, Clone)]
+pub struct AblationOptions {
+    /// Occupancy the target site must drop below
+    pub threshold: f64,
+    /// Maximum allowed absolute occupancy change of any other window
+    pub tolerance: f64,
+    /// Maximum number of bases to change
+    pub max_edits: usize,
+}
+
+impl Default for AblationOptions {
+    fn default() -> Self {
+        AblationOptions {
+            threshold: 0.1,
+            tolerance: 0.05,
+            max_edits: 3,
+        }
+    }
+}
+
+/// Outcome of a successful design edit
+#[derive(Debug, Clone, PartialEq)]
+pub struct EditResult {
+    /// The edited sequence
+    pub sequence: String,
+    /// Applied edits as (position, reference base, new base)
+    pub edits: Vec<(usize, char, char)>,
+    /// Occupancy of the target site after editing
+    pub site_occupancy: f64,
+    /// Largest absolute occupancy change over all other windows
+    pub max_off_target_change: f64,
+}
+
+/// Finds the smallest set of base changes that knocks out a motif site
+///
+/// All combinations of substitutions inside the site window are searched with increasing
+/// size, up to `max_edits` changed bases. A combination is valid when the target site's
+/// occupancy drops below `threshold` and no other window (any motif, either strand) changes
+/// occupancy by more than `tolerance`; both strands of the target motif at the target
+/// position count as the site itself, so palindromic sites are handled. Among the valid
+/// combinations of the smallest size, the one with the least off-target change is returned.
+///
+/// # Arguments
+/// * `seq` - The DNA sequence containing the site
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are motif IDs
+/// * `mu` - Chemical potential of the transcription factors
+/// * `site` - The site to knock out
+/// * `options` - Threshold, tolerance and edit budget
+///
+/// # Returns
+/// * `Result<Option<EditResult>, MotifError>` - The knockout design, or `None` if no valid
+///   combination exists within the edit budget
+///
+/// # Errors
+/// * `MotifError::InvalidInput` - If the site motif is not in the collection or the site
+///   does not fit in the sequence
+/// * `MotifError::DataError` - If there are issues calculating occupancies
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::design::{ablate_site, AblationOptions, Site};
+///
+/// let site = Site { motif: "CRX".to_string(), position: 42, strand: "F".to_string() };
+/// let knockout = ablate_site(&seq, &ewms, 9.0, &site, &AblationOptions::default()).unwrap();
+/// ```
+pub fn ablate_site(
+    seq: &str,
+    ewms: &EWMCollection,
+    mu: f64,
+    site: &Site,
+    options: &AblationOptions,
+) -> Result<Option<EditResult>, MotifError> {
+    let ewm = site_ewm(seq, ewms, site)?;
+    let width = ewm.height();
+    let before = total_landscape(seq, ewms, mu)?;
+
+    for n_edits in 1..=options.max_edits.min(width) {
+        let mut best: Option<EditResult> = None;
+
+        for positions in combinations(site.position, site.position + width, n_edits) {
+            for candidate in substitutions(seq, &positions) {
+                let site_occupancy = window_occupancy(&candidate.0, ewm, mu, site)?;
+                if site_occupancy >= options.threshold {
+                    continue;
+                }
+
+                let after = total_landscape(&candidate.0, ewms, mu)?;
+                let change = max_off_target_change(&before, &after, site)?;
+                if change > options.tolerance {
+                    continue;
+                }
+
+                if best
+                    .as_ref()
+                    .is_none_or(|b| change < b.max_off_target_change)
+                {
+                    best = Some(EditResult {
+                        sequence: candidate.0,
+                        edits: candidate.1,
+                        site_occupancy,
+                        max_off_target_change: change,
+                    });
+                }
+            }
+        }
+
+        if best.is_some() {
+            return Ok(best);
+        }
+    }
+
+    Ok(None)
+}
+
+/// Looks up the matrix of a site and checks that the site fits in the sequence
+fn site_ewm<'a>(seq: &str, ewms: &'a EWMCollection, site: &Site) -> Result<&'a EWM, MotifError> {
+    let ewm = ewms
+        .get(&site.motif)
+        .ok_or_else(|| MotifError::InvalidInput(format!("Unknown motif: {}", site.motif)))?;
+    if site.position + ewm.height() > seq.len() {
+        return Err(MotifError::InvalidInput(format!(
+            "Site {} at {} does not fit in a sequence of length {}",
+            site.motif,
+            site.position,
+            seq.len()
+        )));
+    }
+    if site.strand != "F" && site.strand != "R" {
+        return Err(MotifError::invalid_parameter(
+            "strand",
+            &site.strand,
+            "must be F or R",
+        ));
+    }
+    Ok(ewm)
+}
+
+/// Occupancy of a single site window
+fn window_occupancy(seq: &str, ewm: &EWM, mu: f64, site: &Site) -> Result<f64, MotifError> {
+    let window = &seq[site.position..site.position + ewm.height()];
+    let (focc, rocc) = occupancy_landscape(window, ewm, mu)?;
+    Ok(if site.strand == "F" { focc[0] } else { rocc[0] })
+}
+
+/// Largest occupancy change between two landscapes outside the given site
+fn max_off_target_change(
+    before: &DataFrame,
+    after: &DataFrame,
+    site: &Site,
+) -> Result<f64, MotifError> {
+    let site_columns = [format!("{}_F", site.motif), format!("{}_R", site.motif)];
+    let mut max_change: f64 = 0.0;
+
+    for column in before.get_columns() {
+        let is_site_column = site_columns
+            .iter()
+            .any(|c| c.as_str() == column.name().as_str());
+        let lhs = column
+            .f64()
+            .map_err(|e| MotifError::DataError(e.to_string()))?;
+        let rhs = after
+            .column(column.name())
+            .map_err(|e| MotifError::DataError(e.to_string()))?
+            .f64()
+            .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+        for (pos, (x, y)) in lhs.into_iter().zip(rhs).enumerate() {
+            if is_site_column && pos == site.position {
+                continue;
+            }
+            max_change = max_change.max((x.unwrap_or(0.0) - y.unwrap_or(0.0)).abs());
+        }
+    }
+
+    Ok(max_change)
+}
+
+/// All sorted `k`-element subsets of `start..end`
+fn combinations(start: usize, end: usize, k: usize) -> Vec<Vec<usize>> {
+    fn extend(
+        from: usize,
+        end: usize,
+        k: usize,
+        current: &mut Vec<usize>,
+        out: &mut Vec<Vec<usize>>,
+    ) {
+        if current.len() == k {
+            out.push(current.clone());
+            return;
+        }
+        for pos in from..end {
+            current.push(pos);
+            extend(pos + 1, end, k, current, out);
+            current.pop();
+        }
+    }
+
+    let mut out = Vec::new();
+    extend(start, end, k, &mut Vec::with_capacity(k), &mut out);
+    out
+}
+
+/// Every sequence obtained by substituting all of `positions` with a different base
+#[allow(clippy::type_complexity)]
+fn substitutions(seq: &str, positions: &[usize]) -> Vec<(String, Vec<(usize, char, char)>)> {
+    let mut out: Vec<(String, Vec<(usize, char, char)>)> = vec![(seq.to_string(), Vec::new())];
+
+    for &pos in positions {
+        let from = seq.as_bytes()[pos] as char;
+        out = out
+            .into_iter()
+            .flat_map(|(candidate, edits)| {
+                BASES.iter().filter(move |&&b| b != from).map(move |&to| {
+                    let mut candidate = candidate.clone();
+                    candidate.replace_range(pos..pos + 1, &to.to_string());
+                    let mut edits = edits.clone();
+                    edits.push((pos, from, to));
+                    (candidate, edits)
+                })
+            })
+            .collect();
+    }
+
+    out
+}
+
+/// Outcome of `knock_in_site()`
+#[derive(Debug, Clone)]
+pub struct KnockInResult {
+    /// The edited sequence
+    pub sequence: String,
+    /// Applied edits as (position, reference base, new base)
+    pub edits: Vec<(usize, char, char)>,
+    /// Occupancy of the planted site
+    pub site_occupancy: f64,
+    /// Every window whose occupancy changed, with columns "motif", "strand", "position",
+    /// "before", "after" and "delta"
+    pub audit: DataFrame,
+    /// Windows other than the planted site that now exceed the threshold but did not before
+    pub unintended_sites: Vec<Site>,
+}
+
+impl KnockInResult {
+    /// Whether the knock-in created no unintended sites
+    pub fn is_clean(&self) -> bool {
+        self.unintended_sites.is_empty()
+    }
+}
+
+/// Plants a motif site at a chosen position with the fewest possible edits
+///
+/// Because the energy model is additive over positions, the fewest substitutions that
+/// raise the site's occupancy to `threshold` are found by applying the best base at each
+/// position of the window in order of decreasing energy gain. The edited sequence is then
+/// rescanned with every motif in the collection, and the result reports the
+/// delta-occupancy audit of all changed windows along with any unintended sites: windows
+/// other than the planted one that now exceed `threshold` but did not before.
+///
+/// # Arguments
+/// * `seq` - The DNA sequence to edit
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are motif IDs
+/// * `mu` - Chemical potential of the transcription factors
+/// * `site` - Motif, position and strand of the site to plant
+/// * `threshold` - Occupancy the planted site must reach, and above which other windows
+///   count as unintended sites
+///
+/// # Returns
+/// * `Result<Option<KnockInResult>, MotifError>` - The knock-in design, or `None` if even
+///   the consensus site does not reach `threshold` at this `mu`
+///
+/// # Errors
+/// * `MotifError::InvalidInput` - If the site motif is not in the collection or the site
+///   does not fit in the sequence
+/// * `MotifError::DataError` - If there are issues calculating occupancies
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::design::{knock_in_site, Site};
+///
+/// let site = Site { motif: "NRL".to_string(), position: 80, strand: "R".to_string() };
+/// let design = knock_in_site(&seq, &ewms, 9.0, &site, 0.5).unwrap().unwrap();
+/// assert!(design.is_clean());
+/// ```
+pub fn knock_in_site(
+    seq: &str,
+    ewms: &EWMCollection,
+    mu: f64,
+    site: &Site,
+    threshold: f64,
+) -> Result<Option<KnockInResult>, MotifError> {
+    let ewm = site_ewm(seq, ewms, site)?;
+    let width = ewm.height();
+
+    // best substitution and its occupancy gain at every position of the window
+    let current_occupancy = window_occupancy(seq, ewm, mu, site)?;
+    let mut gains: Vec<(f64, usize, char, char)> = Vec::new();
+    for pos in site.position..site.position + width {
+        let from = seq.as_bytes()[pos] as char;
+        let mut best: Option<(f64, char)> = None;
+        for to in BASES.iter().copied().filter(|&b| b != from) {
+            let mut candidate = seq.to_string();
+            candidate.replace_range(pos..pos + 1, &to.to_string());
+            let occupancy = window_occupancy(&candidate, ewm, mu, site)?;
+            if best.is_none_or(|b| occupancy > b.0) {
+                best = Some((occupancy, to));
+            }
+        }
+        if let Some((occupancy, to)) = best {
+            if occupancy > current_occupancy {
+                gains.push((occupancy - current_occupancy, pos, from, to));
+            }
+        }
+    }
+    gains.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut edited = seq.to_string();
+    let mut edits: Vec<(usize, char, char)> = Vec::new();
+    let mut site_occupancy = current_occupancy;
+    for (_, pos, from, to) in gains {
+        if site_occupancy >= threshold {
+            break;
+        }
+        edited.replace_range(pos..pos + 1, &to.to_string());
+        edits.push((pos, from, to));
+        site_occupancy = window_occupancy(&edited, ewm, mu, site)?;
+    }
+    if site_occupancy < threshold {
+        return Ok(None);
+    }
+    edits.sort();
+
+    let before = total_landscape(seq, ewms, mu)?;
+    let after = total_landscape(&edited, ewms, mu)?;
+    let (audit, unintended_sites) = occupancy_audit(&before, &after, site, threshold)?;
+
+    Ok(Some(KnockInResult {
+        sequence: edited,
+        edits,
+        site_occupancy,
+        audit,
+        unintended_sites,
+    }))
+}
+
+/// Lists changed windows between two landscapes and the sites newly above threshold
+fn occupancy_audit(
+    before: &DataFrame,
+    after: &DataFrame,
+    site: &Site,
+    threshold: f64,
+) -> Result<(DataFrame, Vec<Site>), MotifError> {
+    let mut motifs: Vec<String> = Vec::new();
+    let mut strands: Vec<String> = Vec::new();
+    let mut positions: Vec<i32> = Vec::new();
+    let mut befores: Vec<f64> = Vec::new();
+    let mut afters: Vec<f64> = Vec::new();
+    let mut unintended: Vec<Site> = Vec::new();
+
+    let mut names: Vec<&PlSmallStr> = before.get_column_names();
+    names.sort();
+
+    for name in names {
+        let Some((motif, strand)) = name.rsplit_once('_') else {
+            continue;
+        };
+        let lhs = before
+            .column(name)
+            .map_err(|e| MotifError::DataError(e.to_string()))?
+            .f64()
+            .map_err(|e| MotifError::DataError(e.to_string()))?;
+        let rhs = after
+            .column(name)
+            .map_err(|e| MotifError::DataError(e.to_string()))?
+            .f64()
+            .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+        for (pos, (x, y)) in lhs.into_iter().zip(rhs).enumerate() {
+            let (x, y) = (x.unwrap_or(0.0), y.unwrap_or(0.0));
+            if x == y {
+                continue;
+            }
+
+            let is_target = motif == site.motif && pos == site.position;
+            if !is_target && x <= threshold && y > threshold {
+                unintended.push(Site {
+                    motif: motif.to_string(),
+                    position: pos,
+                    strand: strand.to_string(),
+                });
+            }
+
+            motifs.push(motif.to_string());
+            strands.push(strand.to_string());
+            positions.push(pos as i32);
+            befores.push(x);
+            afters.push(y);
+        }
+    }
+
+    let deltas: Vec<f64> = befores.iter().zip(&afters).map(|(x, y)| y - x).collect();
+    let audit = DataFrame::new(vec![
+        Column::new("motif".into(), motifs),
+        Column::new("strand".into(), strands),
+        Column::new("position".into(), positions),
+        Column::new("before".into(), befores),
+        Column::new("after".into(), afters),
+        Column::new("delta".into(), deltas),
+    ])
+    .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    Ok((audit, unintended))
+}
+
+/// Parameters for `optimize_sequence()`
+#[derive(Debug, Clone)]
+pub struct OptimizeOptions {
+    /// Target total occupancy per motif ID (e.g. high for CRX, zero for NRL)
+    pub targets: HashMap<String, f64>,
+    /// Minimum GC fraction of the designed sequence
+    pub gc_min: f64,
+    /// Maximum GC fraction of the designed sequence
+    pub gc_max: f64,
+    /// Sites that must not occur in the designed sequence, on either strand
+    pub forbidden_sites: Vec<String>,
+    /// Number of proposed single-base mutations
+    pub iterations: usize,
+    /// Initial annealing temperature; `0.0` gives a purely greedy search
+    pub start_temperature: f64,
+    /// Seed of the random number generator, making runs reproducible
+    pub seed: u64,
+}
+
+impl Default for OptimizeOptions {
+    fn default() -> Self {
+        OptimizeOptions {
+            targets: HashMap::new(),
+            gc_min: 0.0,
+            gc_max: 1.0,
+            forbidden_sites: Vec::new(),
+            iterations: 5000,
+            start_temperature: 1.0,
+            seed: 0,
+        }
+    }
+}
+
+/// Outcome of `optimize_sequence()`
+#[derive(Debug, Clone)]
+pub struct OptimizeResult {
+    /// Best sequence found
+    pub sequence: String,
+    /// Squared error between achieved and target totals of the best sequence
+    pub loss: f64,
+    /// Achieved total occupancy of each targeted motif
+    pub totals: HashMap<String, f64>,
+    /// Whether the best sequence satisfies the GC and forbidden site constraints
+    pub feasible: bool,
+}
+
+/// Penalty per violated constraint, large enough to dominate any occupancy error
+const CONSTRAINT_PENALTY: f64 = 1e3;
+
+/// Evolves a sequence toward per-TF total occupancy targets
+///
+/// Runs simulated annealing over single-base substitutions. The objective is the sum of
+/// squared differences between each targeted motif's `total_occupancy()` and its target,
+/// plus a large penalty for every forbidden site occurrence and for GC content outside
+/// `[gc_min, gc_max]`, so the search is steered back into the feasible region. Worse
+/// proposals are accepted with probability `exp(-delta / T)`, with the temperature
+/// decreasing linearly from `start_temperature` to zero. Only the targeted motifs are
+/// scanned. Results are deterministic for a given `seed`.
+///
+/// # Arguments
+/// * `initial` - Starting sequence; its length is preserved
+/// * `ewms` - Collection of Energy Weight Matrices containing every targeted motif
+/// * `mu` - Chemical potential of the transcription factors
+/// * `options` - Targets, constraints and annealing schedule
+///
+/// # Returns
+/// * `Result<OptimizeResult, MotifError>` - The best sequence encountered
+///
+/// # Errors
+/// * `MotifError::InvalidInput` - If a targeted motif is not in the collection, or the
+///   starting sequence contains invalid nucleotides
+/// * `MotifError::InvalidParameter` - If the GC bounds are not within [0, 1] or inverted
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::design::{optimize_sequence, OptimizeOptions};
+///
+/// let options = OptimizeOptions {
+///     targets: [("CRX".to_string(), 3.0), ("NRL".to_string(), 0.0)].into(),
+///     gc_min: 0.4,
+///     gc_max: 0.6,
+///     forbidden_sites: vec!["GAATTC".to_string()],
+///     ..Default::default()
+/// };
+/// let result = optimize_sequence(&seed_seq, &ewms, 9.0, &options).unwrap();
+/// ```
+pub fn optimize_sequence(
+    initial: &str,
+    ewms: &EWMCollection,
+    mu: f64,
+    options: &OptimizeOptions,
+) -> Result<OptimizeResult, MotifError> {
+    if !(0.0..=1.0).contains(&options.gc_min)
+        || !(0.0..=1.0).contains(&options.gc_max)
+        || options.gc_min > options.gc_max
+    {
+        return Err(MotifError::invalid_parameter(
+            "gc_min",
+            options.gc_min,
+            format!(
+                "GC bounds must satisfy 0 <= gc_min <= gc_max ({}) <= 1",
+                options.gc_max
+            ),
+        ));
+    }
+
+    let targeted: EWMCollection = options
+        .targets
+        .keys()
+        .map(|id| {
+            ewms.get(id)
+                .map(|ewm| (id.clone(), ewm.clone()))
+                .ok_or_else(|| MotifError::InvalidInput(format!("Unknown motif: {}", id)))
+        })
+        .collect::<Result<_, _>>()?;
+    let forbidden = site_patterns(&options.forbidden_sites)?;
+    reverse_complement(initial)?;
+
+    // sum the squared errors in motif ID order, not HashMap order, so that the loss, and
+    // with it every accept/reject decision, is bit-identical across runs with one seed
+    let mut targets: Vec<(&String, f64)> = options
+        .targets
+        .iter()
+        .map(|(id, &target)| (id, target))
+        .collect();
+    targets.sort_by(|a, b| a.0.cmp(b.0));
+    let squared_error = |totals: &HashMap<String, f64>| -> f64 {
+        targets
+            .iter()
+            .map(|&(id, target)| (totals[id] - target).powi(2))
+            .sum()
+    };
+
+    let evaluate = |seq: &str| -> Result<(f64, HashMap<String, f64>, bool), MotifError> {
+        let totals = total_occupancy(seq, &targeted, mu)?;
+        let error = squared_error(&totals);
+
+        let gc = seq.bytes().filter(|&b| b == b'G' || b == b'C').count() as f64 / seq.len() as f64;
+        let gc_violation = (options.gc_min - gc).max(0.0) + (gc - options.gc_max).max(0.0);
+        let site_violations = count_sites(seq, &forbidden);
+        let feasible = gc_violation == 0.0 && site_violations == 0;
+        let penalty =
+            CONSTRAINT_PENALTY * (site_violations as f64 + gc_violation * seq.len() as f64);
+
+        Ok((error + penalty, totals, feasible))
+    };
+
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let mut current = initial.to_string();
+    let (mut current_loss, mut totals, mut feasible) = evaluate(&current)?;
+    let mut best = OptimizeResult {
+        sequence: current.clone(),
+        loss: current_loss,
+        totals: totals.clone(),
+        feasible,
+    };
+
+    for iteration in 0..options.iterations {
+        if current.is_empty() {
+            break;
+        }
+        let temperature =
+            options.start_temperature * (1.0 - iteration as f64 / options.iterations as f64);
+
+        let pos = rng.gen_range(0..current.len());
+        let from = current.as_bytes()[pos] as char;
+        let to = loop {
+            let base = BASES[rng.gen_range(0..BASES.len())];
+            if base != from {
+                break base;
+            }
+        };
+        let mut candidate = current.clone();
+        candidate.replace_range(pos..pos + 1, &to.to_string());
+
+        let (loss, candidate_totals, candidate_feasible) = evaluate(&candidate)?;
+        let delta = loss - current_loss;
+        let accept =
+            delta <= 0.0 || (temperature > 0.0 && rng.gen::<f64>() < (-delta / temperature).exp());
+        if !accept {
+            continue;
+        }
+
+        current = candidate;
+        current_loss = loss;
+        totals = candidate_totals;
+        feasible = candidate_feasible;
+
+        if current_loss < best.loss {
+            best = OptimizeResult {
+                sequence: current.clone(),
+                loss: current_loss,
+                totals: totals.clone(),
+                feasible,
+            };
+        }
+    }
+
+    if best.feasible {
+        // report the occupancy error alone once the constraints hold
+        best.loss = squared_error(&best.totals);
+    }
+
+    Ok(best)
+}
@@ -0,0 +1,389 @@
+use crate::error::MotifError;
+use crate::occupancy::StrandedEwm;
+use crate::types::*;
+use polars::prelude::*;
+use std::fmt;
+use std::str::FromStr;
+
+/// Relative orientation of the two half-sites of a composite element, read on the strand
+/// where the first motif comes first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DimerOrientation {
+    /// Any arrangement of the two half-sites
+    #[default]
+    Any,
+    /// Both half-sites on the same strand, first then second (→ →)
+    Tandem,
+    /// Half-sites facing each other (→ ←)
+    Inverted,
+    /// Half-sites facing away from each other (← →)
+    Everted,
+}
+
+impl DimerOrientation {
+    /// Whether half-sites on strands `first` and `second` (true for forward), in the frame
+    /// of the element, have this orientation
+    fn allows(&self, first: bool, second: bool) -> bool {
+        match self {
+            DimerOrientation::Any => true,
+            DimerOrientation::Tandem => first && second,
+            DimerOrientation::Inverted => first && !second,
+            DimerOrientation::Everted => !first && second,
+        }
+    }
+}
+
+impl FromStr for DimerOrientation {
+    type Err = MotifError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "any" => Ok(DimerOrientation::Any),
+            "tandem" => Ok(DimerOrientation::Tandem),
+            "inverted" => Ok(DimerOrientation::Inverted),
+            "everted" => Ok(DimerOrientation::Everted),
+            _ => Err(MotifError::invalid_parameter(
+                "orientation",
+                s,
+                "expected one of any, tandem, inverted, everted",
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DimerOrientation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DimerOrientation::Any => "any",
+            DimerOrientation::Tandem => "tandem",
+            DimerOrientation::Inverted => "inverted",
+            DimerOrientation::Everted => "everted",
+        })
+    }
+}
+
+/// A composite element of two motifs, such as CRX/NRL or SOX/OCT, scanned as a unit
+#[derive(Debug, Clone, PartialEq)]
+pub struct DimerSpec {
+    /// First motif, by full ID or name before the first '_'
+    pub first: String,
+    /// Second motif; the same as `first` for a homodimer
+    pub second: String,
+    /// Fewest bases between the end of one half-site and the start of the other
+    pub min_spacing: usize,
+    /// Most bases between the end of one half-site and the start of the other
+    pub max_spacing: usize,
+    pub orientation: DimerOrientation,
+    /// Energy (kJ/mol) added to the summed half-site energies; negative for cooperative
+    /// binding
+    pub interaction: f64,
+}
+
+impl DimerSpec {
+    /// Name of the element in hit tables, `first::second`
+    pub fn name(&self) -> String {
+        format!("{}::{}", self.first, self.second)
+    }
+}
+
+/// One composite element found by `DimerScanner::scan()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DimerSite {
+    /// Start of the element, i.e. of its leftmost half-site, on the forward strand
+    pub position: usize,
+    /// Span of both half-sites and the spacer
+    pub length: usize,
+    /// "F" when the element reads first half-site, then second on the forward strand, "R"
+    /// when it does on the reverse strand
+    pub strand: &'static str,
+    pub first_position: usize,
+    pub first_strand: &'static str,
+    pub second_position: usize,
+    pub second_strand: &'static str,
+    /// Bases between the two half-sites
+    pub spacing: usize,
+    /// Summed half-site energies plus the interaction energy
+    pub energy: f64,
+    /// Occupancy of the element as a unit, `1 / (1 + exp(energy - 2 mu))`
+    pub occupancy: f64,
+}
+
+/// Energy matrix of a motif, by full ID or by the name before the first '_'
+fn find_ewm<'a>(ewms: &'a EWMCollection, name: &str) -> Result<&'a EWM, MotifError> {
+    ewms.get(name)
+        .or_else(|| {
+            let mut ids: Vec<&String> = ewms
+                .keys()
+                .filter(|id| id.split('_').next() == Some(name))
+                .collect();
+            ids.sort();
+            ids.first().map(|id| &ewms[*id])
+        })
+        .ok_or_else(|| MotifError::InvalidInput(format!("motif '{}' not found", name)))
+}
+
+fn strand(forward: bool) -> &'static str {
+    if forward {
+        "F"
+    } else {
+        "R"
+    }
+}
+
+/// Scans sequences for the composite element of a `DimerSpec`, with both matrices prepared
+/// once
+///
+/// Every pair of half-sites within the spacing range is scored jointly: the energies of the
+/// two windows and the interaction energy are summed, and the element is bound when both
+/// factors are, at chemical potential `mu` each. Pairs are read on both strands, so an
+/// element is found whichever strand it lies on.
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::dimer::{DimerOrientation, DimerScanner, DimerSpec};
+///
+/// let spec = DimerSpec {
+///     first: "CRX".to_string(),
+///     second: "NRL".to_string(),
+///     min_spacing: 0,
+///     max_spacing: 6,
+///     orientation: DimerOrientation::Tandem,
+///     interaction: -2.0,
+/// };
+/// let scanner = DimerScanner::new(&spec, &ewms).unwrap();
+/// let sites = scanner.scan("ATCGATCGTAGCTACGTGGGCTTAGTAACG", 9.0, 0.2).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct DimerScanner {
+    spec: DimerSpec,
+    first: StrandedEwm,
+    second: StrandedEwm,
+}
+
+impl DimerScanner {
+    /// Prepares the two matrices of `spec` from `ewms`
+    ///
+    /// # Errors
+    /// * `MotifError::InvalidInput` - If a motif of `spec` is not in `ewms`
+    /// * `MotifError::InvalidParameter` - If the spacing range is empty
+    /// * `MotifError::DataError` - If a matrix is malformed
+    pub fn new(spec: &DimerSpec, ewms: &EWMCollection) -> Result<Self, MotifError> {
+        if spec.min_spacing > spec.max_spacing {
+            return Err(MotifError::invalid_parameter(
+                "spacing",
+                format!("{}-{}", spec.min_spacing, spec.max_spacing),
+                "the minimum spacing exceeds the maximum",
+            ));
+        }
+        Ok(DimerScanner {
+            spec: spec.clone(),
+            first: StrandedEwm::new(find_ewm(ewms, &spec.first)?)?,
+            second: StrandedEwm::new(find_ewm(ewms, &spec.second)?)?,
+        })
+    }
+
+    /// The composite element being scanned
+    pub fn spec(&self) -> &DimerSpec {
+        &self.spec
+    }
+
+    /// Composite elements of `seq` with an occupancy above `cutoff`, by position
+    ///
+    /// # Errors
+    /// * `MotifError::InvalidSequence` - If the sequence is shorter than a motif
+    /// * `MotifError::InvalidInput` - If the sequence contains characters other than A, C, G, T
+    pub fn scan(&self, seq: &str, mu: f64, cutoff: f64) -> Result<Vec<DimerSite>, MotifError> {
+        let (first_fwd, first_rev) = self.first.energy_landscape(seq)?;
+        let (second_fwd, second_rev) = self.second.energy_landscape(seq)?;
+        let first = [&first_rev, &first_fwd];
+        let second = [&second_rev, &second_fwd];
+        let homodimer = self.spec.first == self.spec.second;
+
+        let mut sites = Vec::new();
+        // with the first motif on the left of the forward strand the element is on the
+        // forward strand; otherwise it is read on the reverse strand, flipping both strands.
+        // The two cases are the same placements for a homodimer.
+        for first_left in [true, false] {
+            if homodimer && !first_left {
+                continue;
+            }
+            for left_forward in [true, false] {
+                for right_forward in [true, false] {
+                    let (mut first_forward, mut second_forward) = if first_left {
+                        (left_forward, right_forward)
+                    } else {
+                        (!right_forward, !left_forward)
+                    };
+                    // whether the first half-site, on the element's strand, is the left one
+                    let (mut element_forward, mut first_is_left) = (first_left, first_left);
+                    if homodimer && !first_forward && !second_forward {
+                        // ← ← is a tandem pair read on the reverse strand
+                        (first_forward, second_forward) = (true, true);
+                        (element_forward, first_is_left) = (false, false);
+                    }
+                    if !self.spec.orientation.allows(first_forward, second_forward) {
+                        continue;
+                    }
+
+                    let (left, left_width, right, right_width) = if first_left {
+                        (
+                            first[left_forward as usize],
+                            self.first.len(),
+                            second[right_forward as usize],
+                            self.second.len(),
+                        )
+                    } else {
+                        (
+                            second[left_forward as usize],
+                            self.second.len(),
+                            first[right_forward as usize],
+                            self.first.len(),
+                        )
+                    };
+                    for (left_pos, &left_energy) in left.iter().enumerate() {
+                        for spacing in self.spec.min_spacing..=self.spec.max_spacing {
+                            let right_pos = left_pos + left_width + spacing;
+                            let Some(&right_energy) = right.get(right_pos) else {
+                                break;
+                            };
+                            let energy = left_energy + right_energy + self.spec.interaction;
+                            let occupancy = 1.0 / (1.0 + (energy - 2.0 * mu).exp());
+                            if occupancy <= cutoff {
+                                continue;
+                            }
+                            let ((first_position, first_strand), (second_position, second_strand)) =
+                                if first_is_left {
+                                    ((left_pos, left_forward), (right_pos, right_forward))
+                                } else {
+                                    ((right_pos, right_forward), (left_pos, left_forward))
+                                };
+                            sites.push(DimerSite {
+                                position: left_pos,
+                                length: right_pos + right_width - left_pos,
+                                strand: strand(element_forward),
+                                first_position,
+                                first_strand: strand(first_strand),
+                                second_position,
+                                second_strand: strand(second_strand),
+                                spacing,
+                                energy,
+                                occupancy,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        sites.sort_by(|a, b| {
+            (
+                a.position,
+                a.length,
+                a.first_position,
+                a.first_strand,
+                a.second_strand,
+            )
+                .cmp(&(
+                    b.position,
+                    b.length,
+                    b.first_position,
+                    b.first_strand,
+                    b.second_strand,
+                ))
+        });
+        Ok(sites)
+    }
+}
+
+/// Scans every sequence of a DataFrame for the composite elements of `specs`
+///
+/// # Arguments
+/// * `df` - DataFrame with "label" and "sequence" columns
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are motif IDs
+/// * `specs` - Composite elements to scan for
+/// * `mu` - Chemical potential of each transcription factor
+/// * `cutoff` - Minimum occupancy of an element
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - One row per element with columns "label", "dimer"
+///   (see `DimerSpec::name()`), "position", "length", "strand", "first_position",
+///   "first_strand", "second_position", "second_strand", "spacing", "energy" and
+///   "occupancy". Sequences that cannot be scanned (missing, shorter than a motif, or with
+///   ambiguous bases) have no rows.
+///
+/// # Errors
+/// * `MotifError::DataError` - If required columns are missing or DataFrame creation fails
+/// * The errors of `DimerScanner::new()`
+pub fn find_dimer_hits(
+    df: &DataFrame,
+    ewms: &EWMCollection,
+    specs: &[DimerSpec],
+    mu: f64,
+    cutoff: f64,
+) -> Result<DataFrame, MotifError> {
+    let data_error = |e: PolarsError| MotifError::DataError(e.to_string());
+    let labels = df
+        .column("label")
+        .and_then(|c| c.str())
+        .map_err(data_error)?;
+    let sequences = df
+        .column("sequence")
+        .and_then(|c| c.str())
+        .map_err(data_error)?;
+    let scanners = specs
+        .iter()
+        .map(|spec| DimerScanner::new(spec, ewms))
+        .collect::<Result<Vec<_>, MotifError>>()?;
+
+    let mut rows: Vec<(Option<&str>, String, DimerSite)> = Vec::new();
+    for (label, seq) in labels.into_iter().zip(sequences) {
+        let Some(seq) = seq else {
+            continue;
+        };
+        for scanner in &scanners {
+            if let Ok(sites) = scanner.scan(seq, mu, cutoff) {
+                let name = scanner.spec().name();
+                rows.extend(sites.into_iter().map(|site| (label, name.clone(), site)));
+            }
+        }
+    }
+
+    let int = |f: fn(&DimerSite) -> usize| -> Vec<i32> {
+        rows.iter().map(|(_, _, site)| f(site) as i32).collect()
+    };
+    let text = |f: fn(&DimerSite) -> &'static str| -> Vec<&str> {
+        rows.iter().map(|(_, _, site)| f(site)).collect()
+    };
+    DataFrame::new(vec![
+        Column::new(
+            "label".into(),
+            rows.iter().map(|(label, _, _)| *label).collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "dimer".into(),
+            rows.iter()
+                .map(|(_, name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+        ),
+        Column::new("position".into(), int(|s| s.position)),
+        Column::new("length".into(), int(|s| s.length)),
+        Column::new("strand".into(), text(|s| s.strand)),
+        Column::new("first_position".into(), int(|s| s.first_position)),
+        Column::new("first_strand".into(), text(|s| s.first_strand)),
+        Column::new("second_position".into(), int(|s| s.second_position)),
+        Column::new("second_strand".into(), text(|s| s.second_strand)),
+        Column::new("spacing".into(), int(|s| s.spacing)),
+        Column::new(
+            "energy".into(),
+            rows.iter()
+                .map(|(_, _, site)| site.energy)
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "occupancy".into(),
+            rows.iter()
+                .map(|(_, _, site)| site.occupancy)
+                .collect::<Vec<_>>(),
+        ),
+    ])
+    .map_err(data_error)
+}
@@ -1,7 +1,11 @@
+use std::fmt;
 use std::io;
 use thiserror::Error;
 
+/// Errors of the crate; match on `kind()` rather than on the variants, since new
+/// variants may be added in minor releases
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum MotifError {
     #[error("IO error: {0}")]
     Io(#[from] io::Error),
@@ -27,12 +31,101 @@ pub enum MotifError {
 
     #[error("Invalid input: {0}")]
     InvalidInput(String),
+
+    /// Another error together with the motif, sequence and position it occurred at
+    #[error("{context}: {source}")]
+    Context {
+        context: ErrorContext,
+        source: Box<MotifError>,
+    },
+}
+
+/// Where in a scan an error occurred; every field is optional, since e.g. a malformed
+/// matrix fails before any sequence is scanned
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ErrorContext {
+    /// Motif ID
+    pub motif: Option<String>,
+    /// Sequence label
+    pub label: Option<String>,
+    /// 0-based position in the sequence
+    pub position: Option<usize>,
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(motif) = &self.motif {
+            parts.push(format!("motif {}", motif));
+        }
+        if let Some(label) = &self.label {
+            parts.push(format!("sequence {}", label));
+        }
+        if let Some(position) = self.position {
+            parts.push(format!("position {}", position));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// Type alias for Result with MotifError
+pub type Result<T> = std::result::Result<T, MotifError>;
+
+/// Class of a `MotifError`, stable across releases that add error variants
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Reading or writing a file failed
+    Io,
+    /// A sequence is too short or contains invalid characters
+    InvalidSequence,
+    /// A motif matrix is malformed
+    InvalidPwm,
+    /// A file is not in the expected format
+    InvalidFileFormat,
+    /// A DataFrame lacks a column or could not be built
+    Data,
+    /// A parameter is out of range
+    InvalidParameter,
+    /// Other invalid input, e.g. an unknown motif ID
+    InvalidInput,
 }
 
-// // Type alias for Result with MotifError
-// pub type Result<T> = std::result::Result<T, MotifError>;
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ErrorKind::Io => "io",
+            ErrorKind::InvalidSequence => "invalid_sequence",
+            ErrorKind::InvalidPwm => "invalid_pwm",
+            ErrorKind::InvalidFileFormat => "invalid_file_format",
+            ErrorKind::Data => "data",
+            ErrorKind::InvalidParameter => "invalid_parameter",
+            ErrorKind::InvalidInput => "invalid_input",
+        };
+        write!(f, "{}", name)
+    }
+}
 
 impl MotifError {
+    /// Class of the error; errors with context report the class of the underlying error
+    pub fn kind(&self) -> ErrorKind {
+        match self.root() {
+            MotifError::Io(_) => ErrorKind::Io,
+            MotifError::InvalidSequence { .. } => ErrorKind::InvalidSequence,
+            MotifError::InvalidPwm(_) => ErrorKind::InvalidPwm,
+            MotifError::InvalidFileFormat(_) => ErrorKind::InvalidFileFormat,
+            MotifError::DataError(_) => ErrorKind::Data,
+            MotifError::InvalidParameter { .. } => ErrorKind::InvalidParameter,
+            MotifError::InvalidInput(_) => ErrorKind::InvalidInput,
+            MotifError::Context { source, .. } => source.kind(),
+        }
+    }
+
+    /// Whether the error is of class `kind`
+    pub fn is(&self, kind: ErrorKind) -> bool {
+        self.kind() == kind
+    }
+
     /// Create a new InvalidSequence error
     pub fn invalid_sequence(position: usize, message: impl Into<String>) -> Self {
         MotifError::InvalidSequence {
@@ -46,6 +139,71 @@ impl MotifError {
         MotifError::InvalidPwm(message.into())
     }
 
+    /// Attaches the motif the error occurred on, unless one is already recorded
+    pub fn with_motif(self, motif: impl Into<String>) -> Self {
+        self.with_context(|context| {
+            context.motif.get_or_insert_with(|| motif.into());
+        })
+    }
+
+    /// Attaches the label of the sequence the error occurred on, unless one is already
+    /// recorded
+    pub fn with_label(self, label: impl Into<String>) -> Self {
+        self.with_context(|context| {
+            context.label.get_or_insert_with(|| label.into());
+        })
+    }
+
+    /// Attaches the sequence position the error occurred at, unless one is already recorded
+    pub fn with_position(self, position: usize) -> Self {
+        self.with_context(|context| {
+            context.position.get_or_insert(position);
+        })
+    }
+
+    fn with_context(self, update: impl FnOnce(&mut ErrorContext)) -> Self {
+        let (mut context, source) = match self {
+            MotifError::Context { context, source } => (context, source),
+            error => (ErrorContext::default(), Box::new(error)),
+        };
+        update(&mut context);
+        MotifError::Context { context, source }
+    }
+
+    /// The recorded context, if any
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            MotifError::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Motif the error occurred on, if recorded
+    pub fn motif(&self) -> Option<&str> {
+        self.context().and_then(|c| c.motif.as_deref())
+    }
+
+    /// Label of the sequence the error occurred on, if recorded
+    pub fn label(&self) -> Option<&str> {
+        self.context().and_then(|c| c.label.as_deref())
+    }
+
+    /// Sequence position the error occurred at, from the context or an `InvalidSequence`
+    pub fn position(&self) -> Option<usize> {
+        match self.root() {
+            MotifError::InvalidSequence { position, .. } => Some(*position),
+            _ => self.context().and_then(|c| c.position),
+        }
+    }
+
+    /// The error without its context
+    pub fn root(&self) -> &MotifError {
+        match self {
+            MotifError::Context { source, .. } => source,
+            error => error,
+        }
+    }
+
     /// Create a new InvalidParameter error
     pub fn invalid_parameter(
         name: impl Into<String>,
@@ -59,3 +217,29 @@ impl MotifError {
         }
     }
 }
+
+/// Adds scan context to the error of a `Result`; see `MotifError::with_motif()`
+pub trait ResultExt<T> {
+    /// Attaches the motif ID to an error
+    fn with_motif(self, motif: &str) -> Result<T>;
+
+    /// Attaches the sequence label to an error
+    fn with_label(self, label: &str) -> Result<T>;
+
+    /// Attaches the sequence position to an error
+    fn with_position(self, position: usize) -> Result<T>;
+}
+
+impl<T> ResultExt<T> for Result<T> {
+    fn with_motif(self, motif: &str) -> Result<T> {
+        self.map_err(|e| e.with_motif(motif))
+    }
+
+    fn with_label(self, label: &str) -> Result<T> {
+        self.map_err(|e| e.with_label(label))
+    }
+
+    fn with_position(self, position: usize) -> Result<T> {
+        self.map_err(|e| e.with_position(position))
+    }
+}
@@ -1,8 +1,16 @@
 use crate::error::MotifError;
 use polars::prelude::*;
-use std::collections::HashSet;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Write};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Instant;
+use tracing::{debug, warn};
 
 /// Reads sequences from a FASTA format file and converts them into a Polars DataFrame.
 ///
@@ -19,45 +27,690 @@ use std::io::{BufRead, BufReader, Write};
 /// * Returns `MotifError::DataError` if DataFrame creation fails
 /// * Returns `std::io::Error` for file reading issues
 pub fn read_fasta(filename: &str) -> Result<DataFrame, MotifError> {
-    let mut sequences: Vec<(String, String)> = Vec::new();
+    let start = Instant::now();
     let file = File::open(filename)?;
-    let reader = BufReader::new(file);
+    let sequences = parse_records(BufReader::new(file), true, |_| true)?;
+
+    if sequences.is_empty() {
+        return Err(MotifError::InvalidFileFormat(
+            "No sequences found".to_string(),
+        ));
+    }
+
+    let df = records_frame(sequences)?;
+    debug!(
+        file = filename,
+        sequences = df.height(),
+        elapsed_ms = start.elapsed().as_millis() as u64,
+        "read FASTA"
+    );
+    Ok(df)
+}
 
+/// Parses the (label, sequence) records whose label passes `keep`, uppercasing the sequences
+/// with `uppercase`; the lines of other records are skipped without being buffered
+fn parse_records<R: BufRead>(
+    reader: R,
+    uppercase: bool,
+    mut keep: impl FnMut(&str) -> bool,
+) -> Result<Vec<(String, String)>, MotifError> {
+    let finish = |sequence: &str| {
+        if uppercase {
+            sequence.to_uppercase()
+        } else {
+            sequence.to_string()
+        }
+    };
+    let mut sequences: Vec<(String, String)> = Vec::new();
     let mut current_header = String::new();
     let mut current_sequence = String::new();
+    let mut keeping = false;
 
     for line in reader.lines() {
         let line = line?;
         let line = line.trim();
 
-        if line.starts_with('>') {
-            if !current_header.is_empty() {
-                sequences.push((current_header, current_sequence.to_uppercase()));
-                current_sequence.clear();
+        if let Some(header) = line.strip_prefix('>') {
+            if keeping && !current_header.is_empty() {
+                sequences.push((current_header, finish(&current_sequence)));
             }
-            current_header = line[1..].to_string();
-        } else if !line.is_empty() {
+            current_sequence.clear();
+            current_header = header.to_string();
+            keeping = keep(header);
+        } else if keeping && !line.is_empty() {
             current_sequence.push_str(line);
         }
     }
 
-    if !current_header.is_empty() {
-        sequences.push((current_header, current_sequence.to_uppercase()));
+    if keeping && !current_header.is_empty() {
+        sequences.push((current_header, finish(&current_sequence)));
+    }
+    Ok(sequences)
+}
+
+/// Builds the "label"/"sequence" DataFrame of parsed records
+fn records_frame(records: Vec<(String, String)>) -> Result<DataFrame, MotifError> {
+    let (labels, sequences): (Vec<String>, Vec<String>) = records.into_iter().unzip();
+    DataFrame::new(vec![
+        Column::new("label".into(), labels),
+        Column::new("sequence".into(), sequences),
+    ])
+    .map_err(|e| MotifError::DataError(e.to_string()))
+}
+
+/// How records sharing a label are handled by `read_fasta_with()` and
+/// `resolve_duplicate_labels()`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateLabels {
+    /// Keep every record, as `read_fasta()` does
+    #[default]
+    Keep,
+    /// Fail with `MotifError::InvalidInput`
+    Error,
+    /// Keep the first record of each label and drop the others
+    KeepFirst,
+    /// Rename repeated labels to `label_2`, `label_3`, ...
+    Suffix,
+}
+
+impl fmt::Display for DuplicateLabels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DuplicateLabels::Keep => "keep",
+            DuplicateLabels::Error => "error",
+            DuplicateLabels::KeepFirst => "keep-first",
+            DuplicateLabels::Suffix => "suffix",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for DuplicateLabels {
+    type Err = MotifError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('_', "-").as_str() {
+            "keep" => Ok(DuplicateLabels::Keep),
+            "error" => Ok(DuplicateLabels::Error),
+            "keep-first" => Ok(DuplicateLabels::KeepFirst),
+            "suffix" => Ok(DuplicateLabels::Suffix),
+            _ => Err(MotifError::invalid_parameter(
+                "duplicate_labels",
+                s,
+                "expected one of keep, error, keep-first, suffix",
+            )),
+        }
+    }
+}
+
+/// A record whose label was already used by an earlier record
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateLabel {
+    /// Row index of the record
+    pub index: usize,
+    /// The repeated label
+    pub label: String,
+    /// Row index of the first record with this label
+    pub first_index: usize,
+    /// New label under `DuplicateLabels::Suffix`, `None` if the record was kept as is or
+    /// dropped
+    pub renamed: Option<String>,
+}
+
+/// Applies a duplicate label policy to a DataFrame of sequences.
+///
+/// Joins on "label" silently multiply rows when labels repeat, so this reports every
+/// repeated record and handles it according to `policy`.
+///
+/// # Arguments
+/// * `df` - DataFrame with a "label" column
+/// * `policy` - What to do with repeated labels
+///
+/// # Returns
+/// * `Result<(DataFrame, Vec<DuplicateLabel>)>` - The resulting DataFrame and the
+///   repeated records, in input order
+///
+/// # Errors
+/// * Returns `MotifError::InvalidInput` if labels repeat and `policy` is `Error`
+/// * Returns `MotifError::DataError` if the "label" column is missing
+pub fn resolve_duplicate_labels(
+    df: &DataFrame,
+    policy: DuplicateLabels,
+) -> Result<(DataFrame, Vec<DuplicateLabel>), MotifError> {
+    let labels = df
+        .column("label")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .str()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    let mut first: HashMap<&str, usize> = HashMap::new();
+    let mut duplicates = Vec::new();
+    for (index, label) in labels.into_iter().enumerate() {
+        let Some(label) = label else { continue };
+        match first.get(label) {
+            Some(&first_index) => duplicates.push(DuplicateLabel {
+                index,
+                label: label.to_string(),
+                first_index,
+                renamed: None,
+            }),
+            None => {
+                first.insert(label, index);
+            }
+        }
+    }
+
+    if duplicates.is_empty() {
+        return Ok((df.clone(), duplicates));
+    }
+
+    let df = match policy {
+        DuplicateLabels::Keep => df.clone(),
+        DuplicateLabels::Error => {
+            return Err(MotifError::InvalidInput(format!(
+                "{} records repeat an earlier label, e.g. '{}'",
+                duplicates.len(),
+                duplicates[0].label
+            )))
+        }
+        DuplicateLabels::KeepFirst => {
+            let dropped: HashSet<usize> = duplicates.iter().map(|d| d.index).collect();
+            let mask: BooleanChunked = (0..df.height()).map(|i| !dropped.contains(&i)).collect();
+            df.filter(&mask)
+                .map_err(|e| MotifError::DataError(e.to_string()))?
+        }
+        DuplicateLabels::Suffix => {
+            let mut used: HashSet<String> = first.keys().map(|l| l.to_string()).collect();
+            let mut next: HashMap<String, usize> = HashMap::new();
+            let mut new_labels: Vec<Option<String>> =
+                labels.into_iter().map(|l| l.map(String::from)).collect();
+            for duplicate in &mut duplicates {
+                let n = next.entry(duplicate.label.clone()).or_insert(2);
+                let mut renamed = format!("{}_{}", duplicate.label, n);
+                while used.contains(&renamed) {
+                    *n += 1;
+                    renamed = format!("{}_{}", duplicate.label, n);
+                }
+                *n += 1;
+                used.insert(renamed.clone());
+                new_labels[duplicate.index] = Some(renamed.clone());
+                duplicate.renamed = Some(renamed);
+            }
+            let mut df = df.clone();
+            df.with_column(Column::new("label".into(), new_labels))
+                .map_err(|e| MotifError::DataError(e.to_string()))?;
+            df
+        }
+    };
+    Ok((df, duplicates))
+}
+
+/// Reads a FASTA file like `read_fasta()`, handling repeated labels according to `policy`.
+///
+/// # Arguments
+/// * `filename` - Path to the FASTA file to read
+/// * `policy` - What to do with records whose label was already used
+///
+/// # Returns
+/// * `Result<DataFrame>` - A DataFrame with "label" and "sequence" columns
+///
+/// # Errors
+/// * Returns `MotifError::InvalidInput` if labels repeat and `policy` is `Error`
+/// * Otherwise the errors of `read_fasta()`
+pub fn read_fasta_with(filename: &str, policy: DuplicateLabels) -> Result<DataFrame, MotifError> {
+    let df = read_fasta(filename)?;
+    let (df, duplicates) = resolve_duplicate_labels(&df, policy)?;
+    if !duplicates.is_empty() {
+        warn!(
+            file = filename,
+            duplicates = duplicates.len(),
+            policy = %policy,
+            "duplicate FASTA labels"
+        );
+    }
+    Ok(df)
+}
+
+/// Draws `n` random rows of a DataFrame of sequences for quick exploratory scans, keeping
+/// them in input order; all rows are kept if there are no more than `n`.
+///
+/// The same input and seed always give the same rows (with the same version of the `rand`
+/// crate).
+///
+/// # Errors
+/// * `MotifError::DataError` - If the rows cannot be taken
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::fasta::sample_sequences;
+///
+/// let subset = sample_sequences(&sequences, 1000, 42).unwrap();
+/// ```
+pub fn sample_sequences(df: &DataFrame, n: usize, seed: u64) -> Result<DataFrame, MotifError> {
+    if df.height() <= n {
+        return Ok(df.clone());
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut keep = vec![false; df.height()];
+    for row in rand::seq::index::sample(&mut rng, df.height(), n).into_vec() {
+        keep[row] = true;
+    }
+    df.filter(&BooleanChunked::from_slice("keep".into(), &keep))
+        .map_err(|e| MotifError::DataError(e.to_string()))
+}
+
+/// How sequences are cleaned on ingestion by `normalize_sequences()` and
+/// `read_fasta_normalized()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Normalization {
+    /// Remove whitespace anywhere in a sequence, e.g. spaces, tabs and carriage returns
+    /// left by spreadsheets or Windows line endings
+    pub strip_whitespace: bool,
+    /// Uppercase the bases; when false, lowercase (soft-masked) bases are kept, so that
+    /// masked repeats can be left out of a scan as ambiguous bases
+    pub uppercase: bool,
+    /// Fail on the first character that is not an IUPAC nucleotide code (in either case)
+    /// instead of leaving it to the scan to skip the sequence
+    pub validate: bool,
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Normalization {
+            strip_whitespace: true,
+            uppercase: true,
+            validate: false,
+        }
+    }
+}
+
+/// Cleans the "sequence" column of a DataFrame according to `options`; missing sequences
+/// stay missing.
+///
+/// # Arguments
+/// * `df` - DataFrame with a "sequence" column and, for error messages, a "label" column
+/// * `options` - Whitespace, case and validation settings
+///
+/// # Returns
+/// * `Result<DataFrame>` - `df` with the normalized sequences
+///
+/// # Errors
+/// * Returns `MotifError::InvalidSequence` with `options.validate` if a sequence contains a
+///   character that is not an IUPAC nucleotide code; the error names the record (row and
+///   label) and the character, and its position is the offset of the character in the
+///   sequence as given
+/// * Returns `MotifError::DataError` if the "sequence" column is missing or not a string
+///   column
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::fasta::{normalize_sequences, Normalization};
+///
+/// let soft_masked = Normalization { uppercase: false, ..Normalization::default() };
+/// let sequences = normalize_sequences(&table, &soft_masked).unwrap();
+/// ```
+pub fn normalize_sequences(
+    df: &DataFrame,
+    options: &Normalization,
+) -> Result<DataFrame, MotifError> {
+    let sequences = df
+        .column("sequence")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .str()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    let labels = df.column("label").ok().and_then(|c| c.str().ok());
+
+    let mut normalized: Vec<Option<String>> = Vec::with_capacity(df.height());
+    for (row, seq) in sequences.into_iter().enumerate() {
+        let Some(seq) = seq else {
+            normalized.push(None);
+            continue;
+        };
+        if options.validate {
+            let invalid = seq.char_indices().find(|&(_, c)| {
+                let allowed = (c.is_ascii() && is_iupac(c.to_ascii_uppercase() as u8))
+                    || (options.strip_whitespace && c.is_whitespace());
+                !allowed
+            });
+            if let Some((offset, c)) = invalid {
+                let record = match labels.and_then(|labels| labels.get(row)) {
+                    Some(label) => format!("record {} ({})", row, label),
+                    None => format!("record {}", row),
+                };
+                return Err(MotifError::invalid_sequence(
+                    offset,
+                    format!("{}: {:?} is not a nucleotide code", record, c),
+                ));
+            }
+        }
+        let mut seq: String = if options.strip_whitespace {
+            seq.chars().filter(|c| !c.is_whitespace()).collect()
+        } else {
+            seq.to_string()
+        };
+        if options.uppercase {
+            seq.make_ascii_uppercase();
+        }
+        normalized.push(Some(seq));
     }
 
+    let mut df = df.clone();
+    df.with_column(Column::new("sequence".into(), normalized))
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    Ok(df)
+}
+
+/// Whether an uppercase byte is an IUPAC nucleotide code
+fn is_iupac(base: u8) -> bool {
+    matches!(
+        base,
+        b'A' | b'C'
+            | b'G'
+            | b'T'
+            | b'U'
+            | b'R'
+            | b'Y'
+            | b'S'
+            | b'W'
+            | b'K'
+            | b'M'
+            | b'B'
+            | b'D'
+            | b'H'
+            | b'V'
+            | b'N'
+    )
+}
+
+/// Reads a FASTA file like `read_fasta()`, cleaning the sequences according to `options`
+/// instead of only uppercasing them; see `normalize_sequences()`.
+///
+/// # Errors
+/// * Returns `MotifError::InvalidSequence` if validation is enabled and a sequence contains
+///   a character that is not a nucleotide code
+/// * Otherwise the errors of `read_fasta()`
+pub fn read_fasta_normalized(
+    filename: &str,
+    options: &Normalization,
+) -> Result<DataFrame, MotifError> {
+    let file = File::open(filename)?;
+    let sequences = parse_records(BufReader::new(file), false, |_| true)?;
     if sequences.is_empty() {
         return Err(MotifError::InvalidFileFormat(
             "No sequences found".to_string(),
         ));
     }
+    normalize_sequences(&records_frame(sequences)?, options)
+}
 
-    let (labels, sequences): (Vec<String>, Vec<String>) = sequences.into_iter().unzip();
-    let df = DataFrame::new(vec![
-        Column::new("label".into(), labels),
-        Column::new("sequence".into(), sequences),
-    ])
-    .map_err(|e| MotifError::DataError(e.to_string()))?;
+/// Names of the sequence and ID columns of an input table, for tables that do not use the
+/// "sequence" and "label" columns expected by the scans
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputColumns {
+    /// Column holding the sequences (default "sequence")
+    pub sequence: String,
+    /// Column identifying the records, used as their label; without one the existing
+    /// "label" column (or the row index) identifies them
+    pub id: Option<String>,
+}
+
+impl Default for InputColumns {
+    fn default() -> Self {
+        InputColumns {
+            sequence: "sequence".to_string(),
+            id: None,
+        }
+    }
+}
+
+impl InputColumns {
+    /// Whether these are the default "sequence" and "label" columns
+    pub fn is_default(&self) -> bool {
+        self == &InputColumns::default()
+    }
+}
+
+/// Renames the configured sequence and ID columns of a table to "sequence" and "label", so
+/// tables with columns like "oligo_seq" can be scanned without rewriting them. IDs are
+/// cast to strings; a "sequence" or "label" column the configured ones replace is dropped.
+///
+/// # Arguments
+/// * `df` - Input table
+/// * `columns` - Names of its sequence and ID columns
+///
+/// # Errors
+/// * Returns `MotifError::DataError` if a configured column is missing or its IDs cannot
+///   be cast to strings
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::fasta::{rename_input_columns, InputColumns};
+///
+/// let columns = InputColumns { sequence: "oligo_seq".into(), id: Some("oligo_id".into()) };
+/// let table = rename_input_columns(table, &columns).unwrap();
+/// ```
+pub fn rename_input_columns(
+    mut df: DataFrame,
+    columns: &InputColumns,
+) -> Result<DataFrame, MotifError> {
+    let data_error = |e: PolarsError| MotifError::DataError(e.to_string());
+    let mut rename = |from: &str, to: &str| -> Result<(), MotifError> {
+        if from == to {
+            return Ok(());
+        }
+        if df.column(from).is_err() {
+            return Err(MotifError::DataError(format!(
+                "input has no '{}' column",
+                from
+            )));
+        }
+        if df.column(to).is_ok() {
+            df = df.drop(to).map_err(data_error)?;
+        }
+        df.rename(from, to.into()).map_err(data_error)?;
+        Ok(())
+    };
+    rename(&columns.sequence, "sequence")?;
+    if let Some(id) = &columns.id {
+        rename(id, "label")?;
+        let labels = df
+            .column("label")
+            .and_then(|c| c.cast(&DataType::String))
+            .map_err(data_error)?;
+        df.with_column(labels).map_err(data_error)?;
+    }
+    Ok(df)
+}
+
+/// Records to pull out of a set of sequences with `subset()` or `subset_file()`
+#[derive(Debug, Clone)]
+pub enum LabelSelector {
+    /// Records whose label is one of these
+    Labels(HashSet<String>),
+    /// Records whose label matches this regular expression anywhere
+    Pattern(Regex),
+}
+
+impl LabelSelector {
+    /// Selects records by exact label
+    pub fn labels<S: Into<String>>(labels: impl IntoIterator<Item = S>) -> Self {
+        LabelSelector::Labels(labels.into_iter().map(Into::into).collect())
+    }
+
+    /// Selects records whose label matches a regular expression
+    ///
+    /// # Errors
+    /// * Returns `MotifError::InvalidParameter` if the pattern is not a valid regex
+    pub fn pattern(pattern: &str) -> Result<Self, MotifError> {
+        Regex::new(pattern)
+            .map(LabelSelector::Pattern)
+            .map_err(|e| MotifError::invalid_parameter("pattern", pattern, e.to_string()))
+    }
+
+    /// Whether a label is selected
+    pub fn matches(&self, label: &str) -> bool {
+        match self {
+            LabelSelector::Labels(labels) => labels.contains(label),
+            LabelSelector::Pattern(regex) => regex.is_match(label),
+        }
+    }
+}
+
+/// Keeps the sequences whose label is selected, in their original order.
+///
+/// # Arguments
+/// * `df` - DataFrame with "label" and "sequence" columns
+/// * `selector` - Labels or pattern of the records to keep
+///
+/// # Returns
+/// * `Result<DataFrame>` - The selected rows, possibly none
+///
+/// # Errors
+/// * Returns `MotifError::DataError` if the "label" column is missing
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::fasta::{read_fasta, subset, LabelSelector};
+///
+/// let df = read_fasta("library.fasta").unwrap();
+/// let wild_type = subset(&df, &LabelSelector::pattern("_WT$").unwrap()).unwrap();
+/// ```
+pub fn subset(df: &DataFrame, selector: &LabelSelector) -> Result<DataFrame, MotifError> {
+    let mask: BooleanChunked = df
+        .column("label")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .str()
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .into_iter()
+        .map(|label| label.is_some_and(|label| selector.matches(label)))
+        .collect();
+    df.filter(&mask)
+        .map_err(|e| MotifError::DataError(e.to_string()))
+}
+
+/// One line of a samtools `.fai` FASTA index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FastaIndexEntry {
+    /// Record name: the header up to the first whitespace
+    pub name: String,
+    /// Sequence length in bases
+    pub length: u64,
+    /// Byte offset of the first base
+    pub offset: u64,
+    /// Bases per full line
+    pub line_bases: u64,
+    /// Bytes per full line, including the line terminator
+    pub line_width: u64,
+}
+
+/// Reads a samtools `.fai` index, as written by `samtools faidx`.
+///
+/// # Arguments
+/// * `filename` - Path to the `.fai` file
+///
+/// # Returns
+/// * `Result<Vec<FastaIndexEntry>>` - The entries in file order
+///
+/// # Errors
+/// * Returns `MotifError::InvalidFileFormat` if a line does not have five valid fields
+/// * Returns `MotifError::Io` for file reading issues
+pub fn read_fasta_index(filename: &str) -> Result<Vec<FastaIndexEntry>, MotifError> {
+    let reader = BufReader::new(File::open(filename)?);
+    let mut entries = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        let invalid = || {
+            MotifError::InvalidFileFormat(format!(
+                "{} line {}: invalid index line",
+                filename,
+                i + 1
+            ))
+        };
+        if fields.len() < 5 {
+            return Err(invalid());
+        }
+        let number = |field: &str| field.trim().parse::<u64>().map_err(|_| invalid());
+        entries.push(FastaIndexEntry {
+            name: fields[0].to_string(),
+            length: number(fields[1])?,
+            offset: number(fields[2])?,
+            line_bases: number(fields[3])?,
+            line_width: number(fields[4])?,
+        });
+    }
+    Ok(entries)
+}
+
+/// Reads one indexed record by seeking to its offset
+fn read_indexed(file: &mut File, entry: &FastaIndexEntry) -> Result<String, MotifError> {
+    let span = entry
+        .length
+        .checked_div(entry.line_bases)
+        .map_or(0, |lines| {
+            lines * entry.line_width + entry.length % entry.line_bases
+        });
+    let mut bytes = vec![0u8; span as usize];
+    file.seek(SeekFrom::Start(entry.offset))?;
+    file.read_exact(&mut bytes)?;
+    let sequence: String = String::from_utf8_lossy(&bytes)
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+    if sequence.len() as u64 != entry.length {
+        return Err(MotifError::InvalidFileFormat(format!(
+            "record {} does not match the FASTA index",
+            entry.name
+        )));
+    }
+    Ok(sequence.to_uppercase())
+}
 
+/// Reads only the selected records of a FASTA file.
+///
+/// When a samtools index (`{filename}.fai`) exists, the selected records are read directly at
+/// their offsets, so pulling a few enhancers out of a genome-sized file is fast; labels are
+/// then matched against the index names (the header up to the first whitespace). Otherwise the
+/// file is streamed, and only selected records are kept in memory.
+///
+/// # Arguments
+/// * `filename` - Path to the FASTA file
+/// * `selector` - Labels or pattern of the records to keep
+///
+/// # Returns
+/// * `Result<DataFrame>` - "label" and "sequence" columns of the selected records, in file
+///   order
+///
+/// # Errors
+/// * Returns `MotifError::InvalidFileFormat` if the index is malformed or out of date
+/// * Returns `MotifError::Io` for file reading issues
+pub fn subset_file(filename: &str, selector: &LabelSelector) -> Result<DataFrame, MotifError> {
+    let start = Instant::now();
+    let index_path = format!("{}.fai", filename);
+    let records = if Path::new(&index_path).exists() {
+        let mut file = File::open(filename)?;
+        read_fasta_index(&index_path)?
+            .into_iter()
+            .filter(|entry| selector.matches(&entry.name))
+            .map(|entry| Ok((entry.name.clone(), read_indexed(&mut file, &entry)?)))
+            .collect::<Result<Vec<_>, MotifError>>()?
+    } else {
+        parse_records(BufReader::new(File::open(filename)?), true, |label| {
+            selector.matches(label)
+        })?
+    };
+
+    let df = records_frame(records)?;
+    debug!(
+        file = filename,
+        sequences = df.height(),
+        indexed = Path::new(&index_path).exists(),
+        elapsed_ms = start.elapsed().as_millis() as u64,
+        "subset FASTA"
+    );
     Ok(df)
 }
 
@@ -215,3 +868,406 @@ pub fn has_restriction_sites(
 
     Ok(new_df)
 }
+
+/// Removes a fixed number of bases from both ends of every sequence, e.g. constant primer
+/// sites of a library.
+///
+/// Other columns are kept as they are; sequences no longer than `left + right` become empty.
+/// Positions of hits in the trimmed sequences are `left` bases lower than in the original.
+///
+/// # Arguments
+/// * `df` - DataFrame with a "sequence" column
+/// * `left` - Bases removed from the start (5' end) of every sequence
+/// * `right` - Bases removed from the end (3' end) of every sequence
+///
+/// # Returns
+/// * `Result<DataFrame>` - `df` with the trimmed sequences
+///
+/// # Errors
+/// * Returns `MotifError::DataError` if the "sequence" column is missing or not a string
+///   column
+pub fn trim(df: &DataFrame, left: usize, right: usize) -> Result<DataFrame, MotifError> {
+    replace_sequences(df, |seq| {
+        let start = left.min(seq.len());
+        let end = seq.len().saturating_sub(right).max(start);
+        (seq[start..end].to_string(), ())
+    })
+    .map(|(df, _)| df)
+}
+
+/// How `trim_adapters()` matches adapters at the ends of sequences
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdapterOptions {
+    /// Shortest partial adapter removed at an end; shorter overlaps are too likely by chance
+    pub min_overlap: usize,
+    /// Mismatches allowed per base of the overlap, as in cutadapt
+    pub error_rate: f64,
+}
+
+impl Default for AdapterOptions {
+    fn default() -> Self {
+        AdapterOptions {
+            min_overlap: 3,
+            error_rate: 0.1,
+        }
+    }
+}
+
+/// Removes primer or adapter sequences from the ends of every sequence before scanning,
+/// since their bases create spurious motif hits at tile boundaries.
+///
+/// The 5' adapter is removed where the sequence starts with it, or with one of its suffixes
+/// of at least `min_overlap` bases (a partially synthesized primer), and the 3' adapter
+/// where the sequence ends with it or with one of its prefixes. The longest match within
+/// `error_rate` mismatches per base wins. Bases are compared case-insensitively. Two
+/// columns record what was removed from each sequence, so hit positions can be mapped back.
+///
+/// # Arguments
+/// * `df` - DataFrame with a "sequence" column
+/// * `five_prime` - Adapter at the start of the sequences, if any
+/// * `three_prime` - Adapter at the end of the sequences, if any
+/// * `options` - Minimum overlap and error rate of a match
+///
+/// # Returns
+/// * `Result<DataFrame>` - `df` with the trimmed sequences and "trimmed_5p" and "trimmed_3p"
+///   columns of the bases removed at each end
+///
+/// # Errors
+/// * Returns `MotifError::InvalidParameter` if `error_rate` is not between 0 and 1
+/// * Returns `MotifError::DataError` if the "sequence" column is missing or not a string
+///   column
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::fasta::{read_fasta, trim_adapters, AdapterOptions};
+///
+/// let library = read_fasta("library.fasta").unwrap();
+/// let trimmed = trim_adapters(
+///     &library,
+///     Some("ACTGGCCGCTTGACG"),
+///     Some("CACTGCGGCTCCTGC"),
+///     &AdapterOptions::default(),
+/// )
+/// .unwrap();
+/// ```
+pub fn trim_adapters(
+    df: &DataFrame,
+    five_prime: Option<&str>,
+    three_prime: Option<&str>,
+    options: &AdapterOptions,
+) -> Result<DataFrame, MotifError> {
+    if !(0.0..=1.0).contains(&options.error_rate) {
+        return Err(MotifError::invalid_parameter(
+            "error_rate",
+            options.error_rate,
+            "must be between 0 and 1",
+        ));
+    }
+    let five_prime = five_prime.unwrap_or_default().as_bytes();
+    let three_prime = three_prime.unwrap_or_default().as_bytes();
+    let matches = |seq: &[u8], adapter: &[u8]| {
+        let mismatches = seq
+            .iter()
+            .zip(adapter)
+            .filter(|(a, b)| !a.eq_ignore_ascii_case(b))
+            .count();
+        mismatches as f64 <= options.error_rate * seq.len() as f64
+    };
+
+    let (mut df, trimmed) = replace_sequences(df, |seq| {
+        let bases = seq.as_bytes();
+        let start = (options.min_overlap.max(1)..=five_prime.len().min(bases.len()))
+            .rev()
+            .find(|&k| matches(&bases[..k], &five_prime[five_prime.len() - k..]))
+            .unwrap_or(0);
+        let rest = &bases[start..];
+        let end = (options.min_overlap.max(1)..=three_prime.len().min(rest.len()))
+            .rev()
+            .find(|&k| matches(&rest[rest.len() - k..], &three_prime[..k]))
+            .unwrap_or(0);
+        (seq[start..seq.len() - end].to_string(), (start, end))
+    })?;
+    let (left, right): (Vec<Option<u32>>, Vec<Option<u32>>) = trimmed
+        .into_iter()
+        .map(|ends| match ends {
+            Some((left, right)) => (Some(left as u32), Some(right as u32)),
+            None => (None, None),
+        })
+        .unzip();
+    df.with_column(Column::new("trimmed_5p".into(), left))
+        .and_then(|df| df.with_column(Column::new("trimmed_3p".into(), right)))
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    Ok(df)
+}
+
+/// Replaces every non-null sequence of `df` by the first element returned by `f`, collecting
+/// the second (None for missing sequences)
+fn replace_sequences<F, T>(
+    df: &DataFrame,
+    mut f: F,
+) -> Result<(DataFrame, Vec<Option<T>>), MotifError>
+where
+    F: FnMut(&str) -> (String, T),
+{
+    let sequences = df
+        .column("sequence")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .str()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    let (replaced, extra): (Vec<Option<String>>, Vec<Option<T>>) = sequences
+        .into_iter()
+        .map(|seq| match seq {
+            Some(seq) => {
+                let (seq, extra) = f(seq);
+                (Some(seq), Some(extra))
+            }
+            None => (None, None),
+        })
+        .unzip();
+    let mut df = df.clone();
+    df.with_column(Column::new("sequence".into(), replaced))
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    Ok((df, extra))
+}
+
+/// Summary statistics of a set of sequences, as computed by `sequence_stats()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequenceStats {
+    /// Number of sequences
+    pub n_sequences: usize,
+    /// Length of the shortest sequence
+    pub min_length: usize,
+    /// Median sequence length
+    pub median_length: f64,
+    /// Length of the longest sequence
+    pub max_length: usize,
+    /// Length such that sequences at least this long contain half of all bases
+    pub n50: usize,
+    /// Fraction of G and C among all bases
+    pub gc_content: f64,
+    /// Fraction of N among all bases
+    pub n_content: f64,
+    /// Labels occurring more than once, in order of first occurrence
+    pub duplicate_labels: Vec<String>,
+    /// Labels of sequences containing characters other than A, C, G, T and N
+    pub invalid_labels: Vec<String>,
+}
+
+impl SequenceStats {
+    /// Converts the statistics to a two-column "metric"/"value" DataFrame for display or export.
+    ///
+    /// # Errors
+    /// * Returns `MotifError::DataError` if DataFrame creation fails
+    pub fn to_dataframe(&self) -> Result<DataFrame, MotifError> {
+        let rows = [
+            ("n_sequences", self.n_sequences.to_string()),
+            ("min_length", self.min_length.to_string()),
+            ("median_length", self.median_length.to_string()),
+            ("max_length", self.max_length.to_string()),
+            ("n50", self.n50.to_string()),
+            ("gc_content", format!("{:.4}", self.gc_content)),
+            ("n_content", format!("{:.4}", self.n_content)),
+            ("duplicate_labels", self.duplicate_labels.len().to_string()),
+            ("invalid_sequences", self.invalid_labels.len().to_string()),
+        ];
+
+        DataFrame::new(vec![
+            Column::new(
+                "metric".into(),
+                rows.iter().map(|(metric, _)| *metric).collect::<Vec<_>>(),
+            ),
+            Column::new(
+                "value".into(),
+                rows.iter()
+                    .map(|(_, value)| value.as_str())
+                    .collect::<Vec<_>>(),
+            ),
+        ])
+        .map_err(|e| MotifError::DataError(e.to_string()))
+    }
+}
+
+/// Computes quality-control statistics for the sequences in a DataFrame.
+///
+/// Useful as a quick check before launching long scans: it reports the length
+/// distribution, base composition, duplicate labels and sequences with characters
+/// outside the A, C, G, T, N alphabet.
+///
+/// # Arguments
+/// * `df` - DataFrame containing sequences with "label" and "sequence" columns
+///
+/// # Returns
+/// * `Result<SequenceStats>` - Summary statistics of the sequences
+///
+/// # Errors
+/// * Returns `MotifError::DataError` if required columns are missing
+/// * Returns `MotifError::InvalidInput` if the DataFrame contains no sequences
+pub fn sequence_stats(df: &DataFrame) -> Result<SequenceStats, MotifError> {
+    let labels = df
+        .column("label")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .str()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    let sequences = df
+        .column("sequence")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .str()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    let mut lengths: Vec<usize> = Vec::new();
+    let mut gc = 0usize;
+    let mut n = 0usize;
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut duplicate_labels: Vec<String> = Vec::new();
+    let mut invalid_labels: Vec<String> = Vec::new();
+
+    for (label, seq) in labels.into_iter().zip(sequences) {
+        let (label, seq) = (label.unwrap_or_default(), seq.unwrap_or_default());
+        lengths.push(seq.len());
+        gc += seq.chars().filter(|&c| c == 'G' || c == 'C').count();
+        n += seq.chars().filter(|&c| c == 'N').count();
+
+        if !seen.insert(label) && !duplicate_labels.iter().any(|l| l == label) {
+            duplicate_labels.push(label.to_string());
+        }
+        if seq
+            .chars()
+            .any(|c| !matches!(c, 'A' | 'C' | 'G' | 'T' | 'N'))
+        {
+            invalid_labels.push(label.to_string());
+        }
+    }
+
+    if lengths.is_empty() {
+        return Err(MotifError::InvalidInput("No sequences found".to_string()));
+    }
+
+    lengths.sort_unstable();
+    let count = lengths.len();
+    let median_length = if count.is_multiple_of(2) {
+        (lengths[count / 2 - 1] + lengths[count / 2]) as f64 / 2.0
+    } else {
+        lengths[count / 2] as f64
+    };
+
+    let total: usize = lengths.iter().sum();
+    let mut cumulative = 0;
+    let n50 = lengths
+        .iter()
+        .rev()
+        .find(|&&len| {
+            cumulative += len;
+            2 * cumulative >= total
+        })
+        .copied()
+        .unwrap_or(0);
+
+    let total_f = total.max(1) as f64;
+    Ok(SequenceStats {
+        n_sequences: count,
+        min_length: lengths[0],
+        median_length,
+        max_length: lengths[count - 1],
+        n50,
+        gc_content: gc as f64 / total_f,
+        n_content: n as f64 / total_f,
+        duplicate_labels,
+        invalid_labels,
+    })
+}
+
+/// Reports where the N bases of every sequence are, so that sequences with a few isolated
+/// Ns can be told apart from sequences with long assembly gaps before deciding whether to
+/// scan them leniently or exclude them.
+///
+/// Lowercase `n` counts as N. Runs are maximal stretches of consecutive Ns.
+///
+/// # Arguments
+/// * `df` - DataFrame containing sequences with "label" and "sequence" columns
+///
+/// # Returns
+/// * `Result<DataFrame>` - One row per sequence, in input order, with columns:
+///   - "label": Sequence label
+///   - "length": Sequence length
+///   - "n_count": Number of N bases
+///   - "n_fraction": Fraction of the sequence that is N
+///   - "n_runs": Number of runs of Ns
+///   - "longest_n_run": Length of the longest run (0 without Ns)
+///   - "n_positions": The runs as comma-separated 0-based `start-end` intervals with
+///     exclusive ends, e.g. "10-12,40-41"
+///
+/// # Errors
+/// * Returns `MotifError::DataError` if required columns are missing or DataFrame creation fails
+pub fn n_report(df: &DataFrame) -> Result<DataFrame, MotifError> {
+    let labels = df
+        .column("label")
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    let sequences = df
+        .column("sequence")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .str()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    let mut lengths: Vec<u32> = Vec::with_capacity(df.height());
+    let mut counts: Vec<u32> = Vec::with_capacity(df.height());
+    let mut fractions: Vec<f64> = Vec::with_capacity(df.height());
+    let mut n_runs: Vec<u32> = Vec::with_capacity(df.height());
+    let mut longest: Vec<u32> = Vec::with_capacity(df.height());
+    let mut positions: Vec<String> = Vec::with_capacity(df.height());
+    for seq in sequences {
+        let seq = seq.unwrap_or_default();
+        let runs = n_runs_of(seq);
+        let count: usize = runs.iter().map(|(start, end)| end - start).sum();
+        lengths.push(seq.len() as u32);
+        counts.push(count as u32);
+        fractions.push(if seq.is_empty() {
+            0.0
+        } else {
+            count as f64 / seq.len() as f64
+        });
+        n_runs.push(runs.len() as u32);
+        longest.push(
+            runs.iter()
+                .map(|(start, end)| end - start)
+                .max()
+                .unwrap_or(0) as u32,
+        );
+        positions.push(
+            runs.iter()
+                .map(|(start, end)| format!("{}-{}", start, end))
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+    }
+
+    DataFrame::new(vec![
+        labels.clone(),
+        Column::new("length".into(), lengths),
+        Column::new("n_count".into(), counts),
+        Column::new("n_fraction".into(), fractions),
+        Column::new("n_runs".into(), n_runs),
+        Column::new("longest_n_run".into(), longest),
+        Column::new("n_positions".into(), positions),
+    ])
+    .map_err(|e| MotifError::DataError(e.to_string()))
+}
+
+/// 0-based (start, exclusive end) of every run of N or n in `seq`
+fn n_runs_of(seq: &str) -> Vec<(usize, usize)> {
+    let mut runs = Vec::new();
+    let mut start = None;
+    for (i, base) in seq.bytes().enumerate() {
+        match (base.eq_ignore_ascii_case(&b'N'), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                runs.push((s, i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        runs.push((s, seq.len()));
+    }
+    runs
+}
@@ -0,0 +1,635 @@
+use crate::error::{MotifError, ResultExt};
+use crate::occupancy::StrandedEwm;
+use crate::types::*;
+use polars::prelude::*;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+
+/// Normalization applied to every motif column of a feature matrix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    /// Gives every column the same distribution: the mean of the sorted values across columns
+    Quantile,
+    /// Replaces values by their rank divided by the number of values, with ties averaged
+    Rank,
+    /// Centers every column to mean 0 and scales it to standard deviation 1
+    ZScore,
+}
+
+/// Builds the sequences × motifs matrix of total predicted occupancy
+///
+/// # Arguments
+/// * `df` - DataFrame with "label" and "sequence" columns
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are motif IDs
+/// * `mu` - Chemical potential of the transcription factors
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - One row per sequence with a "label" column and one
+///   column per motif, in sorted order, holding the summed occupancy of both strands (see
+///   `total_occupancy()`). Sequences that cannot be scanned with a motif (e.g. shorter than
+///   it, or with ambiguous bases) are null.
+///
+/// # Errors
+/// * `MotifError::DataError` - If required columns are missing or DataFrame creation fails
+pub fn occupancy_features(
+    df: &DataFrame,
+    ewms: &EWMCollection,
+    mu: f64,
+) -> Result<DataFrame, MotifError> {
+    let data_error = |e: PolarsError| MotifError::DataError(e.to_string());
+    let labels = df.column("label").map_err(data_error)?.clone();
+    let sequences = df
+        .column("sequence")
+        .and_then(|c| c.str())
+        .map_err(data_error)?;
+
+    let mut motif_ids: Vec<&String> = ewms.keys().collect();
+    motif_ids.sort();
+    let matrices = motif_ids
+        .iter()
+        .map(|id| StrandedEwm::new(&ewms[*id]).with_motif(id))
+        .collect::<Result<Vec<_>, MotifError>>()?;
+
+    let mut values: Vec<Vec<Option<f64>>> = vec![Vec::with_capacity(df.height()); matrices.len()];
+    for seq in sequences {
+        for (matrix, column) in matrices.iter().zip(values.iter_mut()) {
+            let total = seq
+                .and_then(|seq| matrix.occupancy_landscape(seq, mu).ok())
+                .map(|(focc, rocc)| focc.iter().sum::<f64>() + rocc.iter().sum::<f64>());
+            column.push(total);
+        }
+    }
+
+    let mut columns = vec![labels];
+    for (id, column) in motif_ids.into_iter().zip(values) {
+        columns.push(Column::new(id.into(), column));
+    }
+    DataFrame::new(columns).map_err(data_error)
+}
+
+/// Builds the sequences × (motifs × positional bins) matrix of predicted occupancy
+///
+/// Splits every sequence into `bins` equal parts and sums the occupancy of both strands
+/// separately per part, so a model can learn where in the element a motif matters (e.g.
+/// close to the promoter at one end of the design). Every window counts towards the bin
+/// holding its center, so the bins of a motif add up to its column in
+/// `occupancy_features()`.
+///
+/// # Arguments
+/// * `df` - DataFrame with "label" and "sequence" columns
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are motif IDs
+/// * `mu` - Chemical potential of the transcription factors
+/// * `bins` - Number of bins per sequence, counted from its 5' end
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - One row per sequence with a "label" column and the
+///   columns `MOTIF_bin0` to `MOTIF_bin{bins - 1}` for every motif, in sorted order.
+///   Sequences that cannot be scanned with a motif are null in all its bins.
+///
+/// # Errors
+/// * `MotifError::InvalidParameter` - If `bins` is 0
+/// * `MotifError::DataError` - If required columns are missing or DataFrame creation fails
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::features::positional_features;
+///
+/// let features = positional_features(&sequences, &ewms, 9.0, 10)?;
+/// ```
+pub fn positional_features(
+    df: &DataFrame,
+    ewms: &EWMCollection,
+    mu: f64,
+    bins: usize,
+) -> Result<DataFrame, MotifError> {
+    if bins == 0 {
+        return Err(MotifError::invalid_parameter(
+            "bins",
+            bins,
+            "must be at least 1",
+        ));
+    }
+    let data_error = |e: PolarsError| MotifError::DataError(e.to_string());
+    let labels = df.column("label").map_err(data_error)?.clone();
+    let sequences = df
+        .column("sequence")
+        .and_then(|c| c.str())
+        .map_err(data_error)?;
+
+    let mut motif_ids: Vec<&String> = ewms.keys().collect();
+    motif_ids.sort();
+    let matrices = motif_ids
+        .iter()
+        .map(|id| StrandedEwm::new(&ewms[*id]).with_motif(id))
+        .collect::<Result<Vec<_>, MotifError>>()?;
+
+    // bins of every motif, motif by motif
+    let mut values: Vec<Vec<Option<f64>>> =
+        vec![Vec::with_capacity(df.height()); matrices.len() * bins];
+    for seq in sequences {
+        for (matrix, columns) in matrices.iter().zip(values.chunks_mut(bins)) {
+            let landscape = seq.and_then(|seq| {
+                let (focc, rocc) = matrix.occupancy_landscape(seq, mu).ok()?;
+                Some((focc, rocc, seq.len()))
+            });
+            let Some((focc, rocc, len)) = landscape else {
+                columns.iter_mut().for_each(|column| column.push(None));
+                continue;
+            };
+            let mut sums = vec![0.0; bins];
+            for (start, (f, r)) in focc.iter().zip(&rocc).enumerate() {
+                // twice the center, to stay in integers
+                let center = 2 * start + matrix.len();
+                sums[(center * bins / (2 * len)).min(bins - 1)] += f + r;
+            }
+            for (column, sum) in columns.iter_mut().zip(sums) {
+                column.push(Some(sum));
+            }
+        }
+    }
+
+    let mut columns = vec![labels];
+    for (id, motif_columns) in motif_ids.into_iter().zip(values.chunks(bins)) {
+        for (bin, column) in motif_columns.iter().enumerate() {
+            columns.push(Column::new(format!("{}_bin{}", id, bin).into(), column));
+        }
+    }
+    DataFrame::new(columns).map_err(data_error)
+}
+
+/// How the hits of one motif in one sequence are combined into a cell of `hit_matrix()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Aggregation {
+    /// Summed occupancy of the hits, the expected number of bound sites above the cutoff
+    #[default]
+    Sum,
+    /// Occupancy of the strongest hit
+    Max,
+}
+
+/// Pivots a long hit table into the sequences × motifs matrix of occupancy
+///
+/// Unlike `occupancy_features()`, which rescans the sequences, this summarizes hits that
+/// were already called, so only windows above the scan's cutoff contribute.
+///
+/// # Arguments
+/// * `hits` - Hit table with "label", "motif" and "occupancy" columns, e.g. from
+///   `scan::scan_sequences()`
+/// * `sequences` - Optional DataFrame with a "label" column giving the rows and their order,
+///   so sequences without hits get a row of zeros; without it the rows are the labels of
+///   the hits, in order of first appearance
+/// * `aggregation` - How the hits of a motif in a sequence are combined
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - One row per sequence with a "label" column and one
+///   f64 column per motif, in sorted order; motifs without hits in a sequence are 0
+///
+/// # Errors
+/// * `MotifError::InvalidInput` - If a hit label is not among the labels of `sequences`
+/// * `MotifError::DataError` - If a required column is missing or has the wrong type
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::features::{hit_matrix, Aggregation};
+///
+/// let matrix = hit_matrix(&result.hits, Some(&sequences), Aggregation::Max)?;
+/// ```
+pub fn hit_matrix(
+    hits: &DataFrame,
+    sequences: Option<&DataFrame>,
+    aggregation: Aggregation,
+) -> Result<DataFrame, MotifError> {
+    let data_error = |e: PolarsError| MotifError::DataError(e.to_string());
+    let cast = |df: &DataFrame, name: &str, dtype: DataType| {
+        df.column(name)
+            .and_then(|c| c.cast(&dtype))
+            .map_err(data_error)
+    };
+    let hit_labels = cast(hits, "label", DataType::String)?;
+    let motifs = cast(hits, "motif", DataType::String)?;
+    let occupancies = cast(hits, "occupancy", DataType::Float64)?;
+    let hit_labels = hit_labels.str().map_err(data_error)?;
+    let motifs = motifs.str().map_err(data_error)?;
+    let occupancies = occupancies.f64().map_err(data_error)?;
+
+    let mut row_of: HashMap<String, usize> = HashMap::new();
+    let labels = match sequences {
+        Some(sequences) => {
+            let labels = cast(sequences, "label", DataType::String)?;
+            for (row, label) in labels.str().map_err(data_error)?.into_iter().enumerate() {
+                if let Some(label) = label {
+                    row_of.entry(label.to_string()).or_insert(row);
+                }
+            }
+            sequences.column("label").map_err(data_error)?.clone()
+        }
+        None => {
+            let mut order: Vec<&str> = Vec::new();
+            for label in hit_labels.into_iter().flatten() {
+                if !row_of.contains_key(label) {
+                    row_of.insert(label.to_string(), order.len());
+                    order.push(label);
+                }
+            }
+            Column::new("label".into(), order)
+        }
+    };
+
+    let motif_ids: BTreeSet<&str> = motifs.into_iter().flatten().collect();
+    let column_of: HashMap<&str, usize> = motif_ids
+        .iter()
+        .enumerate()
+        .map(|(column, &id)| (id, column))
+        .collect();
+    let mut values = vec![vec![0.0; labels.len()]; motif_ids.len()];
+    for ((label, motif), occupancy) in hit_labels.into_iter().zip(motifs).zip(occupancies) {
+        let (Some(label), Some(motif), Some(occupancy)) = (label, motif, occupancy) else {
+            continue;
+        };
+        let row = *row_of.get(label).ok_or_else(|| {
+            MotifError::InvalidInput(format!("hit label {} is not among the sequences", label))
+        })?;
+        let cell = &mut values[column_of[motif]][row];
+        *cell = match aggregation {
+            Aggregation::Sum => *cell + occupancy,
+            Aggregation::Max => cell.max(occupancy),
+        };
+    }
+
+    let mut columns = vec![labels];
+    for (id, column) in motif_ids.into_iter().zip(values) {
+        columns.push(Column::new(id.into(), column));
+    }
+    DataFrame::new(columns).map_err(data_error)
+}
+
+/// Ranks of values, starting at 1, with tied values sharing their average rank
+pub(crate) fn average_ranks(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].partial_cmp(&values[b]).unwrap_or(Ordering::Equal));
+
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        // ranks start..end (0-based) share the mean rank
+        let rank = (start + end + 1) as f64 / 2.0;
+        for &i in &order[start..end] {
+            ranks[i] = rank;
+        }
+        start = end;
+    }
+    ranks
+}
+
+/// Normalizes the motif columns of a feature matrix so they are comparable
+///
+/// Occupancies of motifs with different widths and affinities live on very different
+/// scales; normalizing puts them on a common one before modeling. Every numeric column is
+/// normalized independently except for quantile normalization, which couples them; other
+/// columns (such as "label") are kept as they are. Nulls stay null and are ignored by the
+/// rank and z-score transforms. Columns without variance become 0 under z-scoring.
+///
+/// # Arguments
+/// * `features` - Feature matrix, e.g. from `occupancy_features()`
+/// * `method` - The normalization to apply
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - The matrix with normalized f64 motif columns
+///
+/// # Errors
+/// * `MotifError::InvalidInput` - If quantile normalization meets a null value
+/// * `MotifError::DataError` - If a column cannot be converted to f64
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::features::{normalize_features, occupancy_features, Normalization};
+///
+/// let features = occupancy_features(&sequences, &ewms, 9.0).unwrap();
+/// let normalized = normalize_features(&features, Normalization::Quantile).unwrap();
+/// ```
+pub fn normalize_features(
+    features: &DataFrame,
+    method: Normalization,
+) -> Result<DataFrame, MotifError> {
+    let data_error = |e: PolarsError| MotifError::DataError(e.to_string());
+    let numeric: Vec<usize> = features
+        .get_columns()
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.dtype().is_numeric())
+        .map(|(i, _)| i)
+        .collect();
+
+    let mut columns: Vec<Vec<Option<f64>>> = numeric
+        .iter()
+        .map(|&i| {
+            let column = features.get_columns()[i]
+                .cast(&DataType::Float64)
+                .map_err(data_error)?;
+            Ok(column.f64().map_err(data_error)?.into_iter().collect())
+        })
+        .collect::<Result<_, MotifError>>()?;
+
+    match method {
+        Normalization::Rank | Normalization::ZScore => {
+            for column in columns.iter_mut() {
+                let present: Vec<f64> = column.iter().flatten().copied().collect();
+                let transformed = if method == Normalization::Rank {
+                    let n = present.len() as f64;
+                    average_ranks(&present).into_iter().map(|r| r / n).collect()
+                } else {
+                    let n = present.len() as f64;
+                    let mean = present.iter().sum::<f64>() / n;
+                    let var = present.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+                    let sd = var.sqrt();
+                    present
+                        .iter()
+                        .map(|x| if sd > 0.0 { (x - mean) / sd } else { 0.0 })
+                        .collect::<Vec<f64>>()
+                };
+                let mut transformed = transformed.into_iter();
+                for value in column.iter_mut().flatten() {
+                    *value = transformed.next().unwrap_or_default();
+                }
+            }
+        }
+        Normalization::Quantile => {
+            let values: Vec<Vec<f64>> = columns
+                .iter()
+                .zip(&numeric)
+                .map(|(column, &i)| {
+                    column
+                        .iter()
+                        .copied()
+                        .collect::<Option<Vec<f64>>>()
+                        .ok_or_else(|| {
+                            MotifError::InvalidInput(format!(
+                            "column {} has nulls, which quantile normalization does not support",
+                            features.get_columns()[i].name()
+                        ))
+                        })
+                })
+                .collect::<Result<_, _>>()?;
+
+            // reference distribution: mean of the k-th smallest values of all columns
+            let n_rows = features.height();
+            let mut reference = vec![0.0; n_rows];
+            for column in &values {
+                let mut sorted = column.clone();
+                sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+                for (r, x) in reference.iter_mut().zip(sorted) {
+                    *r += x / values.len() as f64;
+                }
+            }
+
+            for (column, original) in columns.iter_mut().zip(&values) {
+                // tied values get the mean of the reference values of their ranks
+                for (value, rank) in column.iter_mut().zip(average_ranks(original)) {
+                    let (low, high) = ((rank - 1.0).floor() as usize, (rank - 1.0).ceil() as usize);
+                    *value = Some((reference[low] + reference[high]) / 2.0);
+                }
+            }
+        }
+    }
+
+    let mut normalized = features.clone();
+    for (&i, values) in numeric.iter().zip(columns) {
+        let name = features.get_columns()[i].name().clone();
+        normalized
+            .replace_column(i, Column::new(name, values))
+            .map_err(data_error)?;
+    }
+    Ok(normalized)
+}
+
+/// Regulatory role of the factor binding a motif, giving the sign of its contribution to
+/// predicted activity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MotifRole {
+    /// Binding raises activity; the role of motifs without one
+    #[default]
+    Activator,
+    /// Binding lowers activity
+    Repressor,
+}
+
+impl MotifRole {
+    /// +1 for activators, -1 for repressors
+    pub fn sign(self) -> f64 {
+        match self {
+            MotifRole::Activator => 1.0,
+            MotifRole::Repressor => -1.0,
+        }
+    }
+}
+
+impl FromStr for MotifRole {
+    type Err = MotifError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "activator" | "activating" | "+" => Ok(MotifRole::Activator),
+            "repressor" | "repressing" | "-" => Ok(MotifRole::Repressor),
+            _ => Err(MotifError::invalid_parameter(
+                "role",
+                s,
+                "expected activator or repressor",
+            )),
+        }
+    }
+}
+
+/// Role of a motif column, matching role map keys against the full motif ID or the name
+/// before the first '_'
+fn role_of(id: &str, roles: &HashMap<String, MotifRole>) -> Option<MotifRole> {
+    roles
+        .get(id)
+        .or_else(|| id.split('_').next().and_then(|name| roles.get(name)))
+        .copied()
+}
+
+/// Reads a role map: one motif per line, followed by its role, separated by a tab, comma
+/// or spaces; empty lines and lines starting with '#' are skipped
+///
+/// # Arguments
+/// * `path` - Path of the role file, e.g. a line `NRL repressor`
+///
+/// # Returns
+/// * `Result<HashMap<String, MotifRole>, MotifError>` - Role of every listed motif, keyed by
+///   motif ID or name
+///
+/// # Errors
+/// * `MotifError::Io` - If the file cannot be read
+/// * `MotifError::InvalidFileFormat` - If a line has no role
+/// * `MotifError::InvalidParameter` - If a role is neither activator nor repressor
+pub fn read_roles(path: &str) -> Result<HashMap<String, MotifRole>, MotifError> {
+    let mut roles = HashMap::new();
+    for (number, line) in std::fs::read_to_string(path)?.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line
+            .split([',', '\t', ' '])
+            .filter(|field| !field.is_empty());
+        let (Some(motif), Some(role)) = (fields.next(), fields.next()) else {
+            return Err(MotifError::InvalidFileFormat(format!(
+                "{} line {}: expected a motif and its role",
+                path,
+                number + 1
+            )));
+        };
+        roles.insert(motif.to_string(), role.parse()?);
+    }
+    Ok(roles)
+}
+
+/// Gives the motif columns of a feature matrix the sign of their role
+///
+/// Repressor columns are negated, so that summaries, correlations and models built on the
+/// matrix see binding of a repressor as lowering activity; activator columns and motifs
+/// without a role are kept as they are, as are non-numeric columns such as "label".
+///
+/// # Arguments
+/// * `features` - Feature matrix, e.g. from `occupancy_features()`
+/// * `roles` - Role of motifs by full ID or name before the first '_', e.g. from
+///   `read_roles()`
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - The matrix with f64 repressor columns negated
+///
+/// # Errors
+/// * `MotifError::DataError` - If a repressor column cannot be converted to f64
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::features::{occupancy_features, read_roles, signed_features};
+///
+/// let features = occupancy_features(&sequences, &ewms, 9.0).unwrap();
+/// let signed = signed_features(&features, &read_roles("roles.tsv").unwrap()).unwrap();
+/// ```
+pub fn signed_features(
+    features: &DataFrame,
+    roles: &HashMap<String, MotifRole>,
+) -> Result<DataFrame, MotifError> {
+    let data_error = |e: PolarsError| MotifError::DataError(e.to_string());
+    let mut signed = features.clone();
+    for (i, column) in features.get_columns().iter().enumerate() {
+        if !column.dtype().is_numeric()
+            || role_of(column.name(), roles) != Some(MotifRole::Repressor)
+        {
+            continue;
+        }
+        let values = column.cast(&DataType::Float64).map_err(data_error)?;
+        let negated: Float64Chunked = values
+            .f64()
+            .map_err(data_error)?
+            .apply_values(|x| -x)
+            .with_name(column.name().clone());
+        signed
+            .replace_column(i, negated.into_column())
+            .map_err(data_error)?;
+    }
+    Ok(signed)
+}
+
+/// Predicts the regulatory activity of every sequence from its motif occupancies
+///
+/// A sign-aware additive model: the activity of a sequence is the summed occupancy of its
+/// activator motifs minus that of its repressor motifs, optionally with a weight per motif
+/// (e.g. regression coefficients fitted to reporter assays). Motifs without a role count as
+/// activators. Null features (sequences that could not be scanned with a motif) contribute
+/// nothing; a sequence with only null features has null activity.
+///
+/// # Arguments
+/// * `features` - Feature matrix, e.g. from `occupancy_features()`
+/// * `roles` - Role of motifs by full ID or name before the first '_'
+/// * `weights` - Optional magnitude of every motif's contribution, by full ID or name; motifs
+///   without a weight count with weight 1
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - One row per sequence with the "label" column of
+///   `features` (when present) and the columns "activation" (weighted activator
+///   occupancy), "repression" (weighted repressor occupancy, positive) and "activity"
+///   (activation minus repression)
+///
+/// # Errors
+/// * `MotifError::DataError` - If a motif column cannot be converted to f64
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::features::{occupancy_features, predicted_activity, MotifRole};
+///
+/// let roles = HashMap::from([("NRL".to_string(), MotifRole::Repressor)]);
+/// let features = occupancy_features(&sequences, &ewms, 9.0).unwrap();
+/// let activity = predicted_activity(&features, &roles, None).unwrap();
+/// ```
+pub fn predicted_activity(
+    features: &DataFrame,
+    roles: &HashMap<String, MotifRole>,
+    weights: Option<&HashMap<String, f64>>,
+) -> Result<DataFrame, MotifError> {
+    let data_error = |e: PolarsError| MotifError::DataError(e.to_string());
+    let n = features.height();
+    let mut activation = vec![0.0; n];
+    let mut repression = vec![0.0; n];
+    let mut scanned = vec![false; n];
+
+    for column in features.get_columns() {
+        if !column.dtype().is_numeric() || column.name() == "label" {
+            continue;
+        }
+        let id = column.name().as_str();
+        let weight = weights
+            .and_then(|weights| {
+                weights
+                    .get(id)
+                    .or_else(|| id.split('_').next().and_then(|name| weights.get(name)))
+            })
+            .copied()
+            .unwrap_or(1.0);
+        let target = match role_of(id, roles).unwrap_or_default() {
+            MotifRole::Activator => &mut activation,
+            MotifRole::Repressor => &mut repression,
+        };
+        let values = column.cast(&DataType::Float64).map_err(data_error)?;
+        let values = values.f64().map_err(data_error)?;
+        for ((total, seen), value) in target.iter_mut().zip(scanned.iter_mut()).zip(values) {
+            if let Some(value) = value {
+                *total += weight * value;
+                *seen = true;
+            }
+        }
+    }
+
+    let present = |totals: &[f64]| -> Vec<Option<f64>> {
+        totals
+            .iter()
+            .zip(&scanned)
+            .map(|(&total, &seen)| seen.then_some(total))
+            .collect()
+    };
+    let activity: Vec<f64> = activation
+        .iter()
+        .zip(&repression)
+        .map(|(a, r)| a - r)
+        .collect();
+    let (activation, repression, activity) = (
+        present(&activation),
+        present(&repression),
+        present(&activity),
+    );
+    let mut columns = Vec::with_capacity(4);
+    if let Ok(labels) = features.column("label") {
+        columns.push(labels.clone());
+    }
+    columns.push(Column::new("activation".into(), activation));
+    columns.push(Column::new("repression".into(), repression));
+    columns.push(Column::new("activity".into(), activity));
+    DataFrame::new(columns).map_err(data_error)
+}
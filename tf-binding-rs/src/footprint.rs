@@ -0,0 +1,183 @@
+use crate::error::MotifError;
+use crate::occupancy::{find_hits, occupancy_landscape};
+use crate::tracks::window_mean;
+use crate::types::*;
+use polars::prelude::*;
+
+/// Computes the cleavage protection of a window from a per-base cut-count track
+///
+/// A bound factor protects the DNA under it from Tn5/DNase cleavage, so a footprint shows up
+/// as a dip in cut counts over the site relative to its flanks. The protection score is
+/// `1 - center / flank`, where `center` is the mean cut count over the window and `flank`
+/// is the mean over up to `flank` bases on each side, clamped to [0, 1]. Windows with no
+/// cleavage in either flank are treated as inaccessible and score `0.0`.
+///
+/// # Arguments
+/// * `cuts` - Per-base cut counts aligned to the scanned sequence
+/// * `start` - 0-based start of the window
+/// * `width` - Width of the window
+/// * `flank` - Number of bases on each side used as the local background
+///
+/// # Returns
+/// * `f64` - Protection score in [0, 1]
+pub fn protection_score(cuts: &[f64], start: usize, width: usize, flank: usize) -> f64 {
+    let left_start = start.saturating_sub(flank);
+    let left_len = start - left_start;
+    let right_start = (start + width).min(cuts.len());
+    let right_len = flank.min(cuts.len() - right_start);
+
+    let flank_len = left_len + right_len;
+    if flank_len == 0 {
+        return 0.0;
+    }
+    let flank_mean = (window_mean(cuts, left_start, left_len) * left_len as f64
+        + window_mean(cuts, right_start, right_len) * right_len as f64)
+        / flank_len as f64;
+    if flank_mean <= 0.0 {
+        return 0.0;
+    }
+
+    let center_mean = window_mean(cuts, start, width);
+    (1.0 - center_mean / flank_mean).clamp(0.0, 1.0)
+}
+
+/// Computes a footprint-adjusted occupancy landscape for multiple transcription factors
+///
+/// This mirrors `total_landscape()`, but the occupancy of every window is multiplied by
+/// its `protection_score()`, so predicted sites without cleavage protection are
+/// down-weighted.
+///
+/// # Arguments
+/// * `seq` - The DNA sequence to scan
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are TF names
+/// * `mu` - Chemical potential of the transcription factors
+/// * `cuts` - Per-base cut counts aligned to `seq`
+/// * `flank` - Number of bases on each side of a window used as background
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - DataFrame with the same layout as `total_landscape()`
+///
+/// # Errors
+/// * `MotifError::InvalidInput` - If the cut track length does not match the sequence length
+/// * `MotifError::DataError` - If there are issues creating the DataFrame or calculating occupancies
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::{footprint, tracks};
+///
+/// let bedgraph = tracks::read_bedgraph("atac_cuts.bedGraph").unwrap();
+/// let cuts = tracks::track_for_region(&bedgraph, "chr1", 4357766, 4357930).unwrap();
+/// let landscape = footprint::footprint_landscape(&seq, &ewms, 9.0, &cuts, 10).unwrap();
+/// ```
+pub fn footprint_landscape(
+    seq: &str,
+    ewms: &EWMCollection,
+    mu: f64,
+    cuts: &[f64],
+    flank: usize,
+) -> Result<DataFrame, MotifError> {
+    check_track_length(seq, cuts)?;
+
+    let seq_len = seq.len();
+    let mut columns: Vec<Column> = Vec::new();
+
+    for (name, ewm) in ewms {
+        let width = ewm.height();
+        let (focc, rocc) = occupancy_landscape(seq, ewm, mu)?;
+
+        for (strand, occs) in [("F", focc), ("R", rocc)] {
+            let mut adjusted: Vec<f64> = occs
+                .into_iter()
+                .enumerate()
+                .map(|(pos, occ)| occ * protection_score(cuts, pos, width, flank))
+                .collect();
+            adjusted.resize(seq_len, 0.0);
+            columns.push(Column::new(format!("{}_{}", name, strand).into(), adjusted));
+        }
+    }
+
+    DataFrame::new(columns).map_err(|e| MotifError::DataError(e.to_string()))
+}
+
+/// Finds hits and annotates them with footprint-adjusted occupancy
+///
+/// Hits are selected on the sequence-based occupancy with `find_hits()`, then annotated
+/// with the cleavage protection of their window.
+///
+/// # Arguments
+/// * `seq` - The DNA sequence to scan
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are motif IDs
+/// * `mu` - Chemical potential of the transcription factors
+/// * `cutoff` - Minimum occupancy for a window to be reported
+/// * `cuts` - Per-base cut counts aligned to `seq`
+/// * `flank` - Number of bases on each side of a window used as background
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - The columns of `find_hits()` plus:
+///   - "protection": Cleavage protection score of the hit window
+///   - "footprint_occupancy": Occupancy multiplied by the protection score
+///
+/// # Errors
+/// * `MotifError::InvalidInput` - If the cut track length does not match the sequence length
+/// * `MotifError::DataError` - If there are issues calculating occupancies or creating the DataFrame
+pub fn footprint_hits(
+    seq: &str,
+    ewms: &EWMCollection,
+    mu: f64,
+    cutoff: f64,
+    cuts: &[f64],
+    flank: usize,
+) -> Result<DataFrame, MotifError> {
+    check_track_length(seq, cuts)?;
+
+    let mut hits = find_hits(seq, ewms, mu, cutoff)?;
+
+    let positions = hits
+        .column("position")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .i32()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    let lengths = hits
+        .column("length")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .i32()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    let occupancies = hits
+        .column("occupancy")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .f64()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    let (protection, adjusted): (Vec<f64>, Vec<f64>) = positions
+        .into_iter()
+        .zip(lengths)
+        .zip(occupancies)
+        .map(|((pos, len), occ)| {
+            let score = protection_score(
+                cuts,
+                pos.unwrap_or(0) as usize,
+                len.unwrap_or(0) as usize,
+                flank,
+            );
+            (score, occ.unwrap_or(0.0) * score)
+        })
+        .unzip();
+
+    hits.with_column(Column::new("protection".into(), protection))
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    hits.with_column(Column::new("footprint_occupancy".into(), adjusted))
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    Ok(hits)
+}
+
+fn check_track_length(seq: &str, cuts: &[f64]) -> Result<(), MotifError> {
+    if cuts.len() != seq.len() {
+        return Err(MotifError::InvalidInput(format!(
+            "Cut-count track has {} values but sequence has {} bases",
+            cuts.len(),
+            seq.len()
+        )));
+    }
+    Ok(())
+}
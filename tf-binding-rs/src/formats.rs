@@ -0,0 +1,626 @@
+use crate::error::MotifError;
+use crate::matrix::{matrix_rows, normalize_rows, pwm_from_rows, pwm_to_pcm};
+use crate::occupancy::read_pwm_files;
+use crate::types::*;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use tracing::debug;
+
+/// Number of sites assumed when a frequency matrix is written to a count-based format
+pub const DEFAULT_NSITES: f64 = 1000.0;
+
+/// A motif ID with its matrix rows as stored in the file (counts or frequencies)
+pub(crate) type RawMatrix = (String, Vec<[f64; 4]>);
+
+/// Supported motif file formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotifFormat {
+    /// MEME minimal motif format (letter-probability matrices)
+    Meme,
+    /// JASPAR count matrices (`>ID name` followed by one bracketed row per base)
+    Jaspar,
+    /// TRANSFAC matrix records (`ID`/`P0` lines, terminated by `//`)
+    Transfac,
+    /// HOMER `.motif` files (`>consensus\tname\tthreshold` followed by probability rows)
+    Homer,
+    /// HOCOMOCO `.pcm`/`.pfm` files (`>name` followed by count or frequency rows)
+    Hocomoco,
+}
+
+impl MotifFormat {
+    /// All supported formats
+    pub const ALL: [MotifFormat; 5] = [
+        MotifFormat::Meme,
+        MotifFormat::Jaspar,
+        MotifFormat::Transfac,
+        MotifFormat::Homer,
+        MotifFormat::Hocomoco,
+    ];
+
+    /// Guesses the format of a motif file from its extension.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the motif file
+    ///
+    /// # Returns
+    /// * `Option<MotifFormat>` - The format, or `None` for unknown extensions
+    pub fn from_path(path: &str) -> Option<MotifFormat> {
+        let ext = Path::new(path).extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "meme" => Some(MotifFormat::Meme),
+            "jaspar" => Some(MotifFormat::Jaspar),
+            "transfac" | "dat" => Some(MotifFormat::Transfac),
+            "motif" | "motifs" | "homer" => Some(MotifFormat::Homer),
+            "pcm" | "pfm" | "hocomoco" => Some(MotifFormat::Hocomoco),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for MotifFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MotifFormat::Meme => "meme",
+            MotifFormat::Jaspar => "jaspar",
+            MotifFormat::Transfac => "transfac",
+            MotifFormat::Homer => "homer",
+            MotifFormat::Hocomoco => "hocomoco",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for MotifFormat {
+    type Err = MotifError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        MotifFormat::ALL
+            .into_iter()
+            .find(|format| format.to_string() == s.to_lowercase())
+            .ok_or_else(|| {
+                MotifError::invalid_parameter(
+                    "format",
+                    s,
+                    "expected one of meme, jaspar, transfac, homer, hocomoco",
+                )
+            })
+    }
+}
+
+/// Reads Position Weight Matrices from a motif file in any supported format
+///
+/// Matrices stored as counts are normalized to frequencies, so the result can be used
+/// wherever `read_pwm_files()` output is accepted.
+///
+/// # Arguments
+/// * `filename` - Path to the motif file
+/// * `format` - Format of the file, e.g. from `MotifFormat::from_path()`
+///
+/// # Returns
+/// * `Result<PWMCollection, MotifError>` - A HashMap where keys are motif IDs and values are their PWMs
+///
+/// # Errors
+/// * `MotifError::Io` - If the file cannot be opened or read
+/// * `MotifError::InvalidFileFormat` - If the file cannot be parsed or contains no motifs
+/// * `MotifError::DataError` - If there are issues creating the PWM DataFrames
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::formats::{read_motifs, MotifFormat};
+///
+/// let pwms = read_motifs("JASPAR2024_CORE.jaspar", MotifFormat::Jaspar).unwrap();
+/// ```
+pub fn read_motifs(filename: &str, format: MotifFormat) -> Result<PWMCollection, MotifError> {
+    if format == MotifFormat::Meme {
+        return read_pwm_files(filename);
+    }
+
+    let pwms = read_raw_matrices(filename, format)?
+        .into_iter()
+        .map(|(id, mut rows)| {
+            normalize_rows(&mut rows);
+            Ok((id, pwm_from_rows(&rows)?))
+        })
+        .collect::<Result<PWMCollection, MotifError>>()?;
+
+    debug!(file = filename, %format, motifs = pwms.len(), "read motifs");
+    Ok(pwms)
+}
+
+/// Parses the matrices of a non-MEME motif file as stored (counts or frequencies)
+pub(crate) fn read_raw_matrices(
+    filename: &str,
+    format: MotifFormat,
+) -> Result<Vec<RawMatrix>, MotifError> {
+    let file = File::open(filename)?;
+    let lines: Vec<String> = BufReader::new(file)
+        .lines()
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let matrices = match format {
+        MotifFormat::Meme => {
+            return Err(MotifError::InvalidFileFormat(
+                "MEME matrices are read with read_pwm_files".to_string(),
+            ))
+        }
+        MotifFormat::Jaspar => parse_jaspar(&lines)?,
+        MotifFormat::Transfac => parse_transfac(&lines)?,
+        MotifFormat::Homer => parse_header_rows(&lines, true)?,
+        MotifFormat::Hocomoco => parse_header_rows(&lines, false)?,
+    };
+
+    if matrices.is_empty() {
+        return Err(MotifError::InvalidFileFormat("No motifs found".into()));
+    }
+    Ok(matrices)
+}
+
+fn parse_values(fields: &[&str], context: &str) -> Result<Vec<f64>, MotifError> {
+    fields
+        .iter()
+        .map(|s| s.parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| MotifError::InvalidFileFormat(format!("{}: invalid value: {}", context, e)))
+}
+
+fn to_row(values: &[f64], context: &str) -> Result<[f64; 4], MotifError> {
+    values.try_into().map_err(|_| {
+        MotifError::InvalidFileFormat(format!(
+            "{}: expected 4 values per position, found {}",
+            context,
+            values.len()
+        ))
+    })
+}
+
+/// Parses JASPAR matrices, with or without base letters and brackets
+fn parse_jaspar(lines: &[String]) -> Result<Vec<RawMatrix>, MotifError> {
+    let mut matrices = Vec::new();
+    let mut id: Option<String> = None;
+    let mut base_rows: Vec<Vec<f64>> = Vec::new();
+
+    let mut finish = |id: Option<String>, base_rows: &mut Vec<Vec<f64>>| {
+        if let Some(id) = id {
+            if base_rows.len() != 4 || base_rows.iter().any(|r| r.len() != base_rows[0].len()) {
+                return Err(MotifError::InvalidFileFormat(format!(
+                    "{}: expected 4 rows of equal length",
+                    id
+                )));
+            }
+            let rows = (0..base_rows[0].len())
+                .map(|i| {
+                    [
+                        base_rows[0][i],
+                        base_rows[1][i],
+                        base_rows[2][i],
+                        base_rows[3][i],
+                    ]
+                })
+                .collect();
+            matrices.push((id, rows));
+        }
+        base_rows.clear();
+        Ok(())
+    };
+
+    for line in lines {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('>') {
+            finish(id.take(), &mut base_rows)?;
+            id = Some(
+                header
+                    .split_whitespace()
+                    .next()
+                    .ok_or_else(|| MotifError::InvalidFileFormat("Missing motif ID".into()))?
+                    .to_string(),
+            );
+        } else if !line.is_empty() && id.is_some() {
+            let cleaned = line
+                .trim_start_matches(['A', 'C', 'G', 'T'])
+                .replace(['[', ']'], " ");
+            let fields: Vec<&str> = cleaned.split_whitespace().collect();
+            base_rows.push(parse_values(&fields, id.as_deref().unwrap_or_default())?);
+        }
+    }
+    finish(id, &mut base_rows)?;
+
+    Ok(matrices)
+}
+
+/// Parses TRANSFAC matrix records
+fn parse_transfac(lines: &[String]) -> Result<Vec<RawMatrix>, MotifError> {
+    let mut matrices = Vec::new();
+    let mut id: Option<String> = None;
+    let mut accession: Option<String> = None;
+    let mut rows: Vec<[f64; 4]> = Vec::new();
+    let mut in_matrix = false;
+
+    for line in lines {
+        let line = line.trim_end();
+        let code = line.get(..2).unwrap_or(line);
+        let value = line.get(2..).unwrap_or("").trim();
+
+        match code {
+            "ID" => id = Some(value.to_string()),
+            "AC" => accession = Some(value.to_string()),
+            "P0" | "PO" => in_matrix = true,
+            "XX" => in_matrix = false,
+            "//" => {
+                if !rows.is_empty() {
+                    let name = id.take().or(accession.take()).ok_or_else(|| {
+                        MotifError::InvalidFileFormat("TRANSFAC record without ID or AC".into())
+                    })?;
+                    matrices.push((name, std::mem::take(&mut rows)));
+                }
+                id = None;
+                accession = None;
+                in_matrix = false;
+            }
+            _ if in_matrix && !line.trim().is_empty() => {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                let context = id.as_deref().or(accession.as_deref()).unwrap_or("TRANSFAC");
+                if fields.len() < 5 {
+                    return Err(MotifError::InvalidFileFormat(format!(
+                        "{}: malformed matrix row '{}'",
+                        context, line
+                    )));
+                }
+                rows.push(to_row(&parse_values(&fields[1..5], context)?, context)?);
+            }
+            _ => {}
+        }
+    }
+
+    // tolerate a missing final terminator
+    if !rows.is_empty() {
+        if let Some(name) = id.or(accession) {
+            matrices.push((name, rows));
+        }
+    }
+
+    Ok(matrices)
+}
+
+/// Parses `>header` blocks followed by one row of four values per position.
+///
+/// HOMER headers are `>consensus\tname\t...` and use the name as ID; HOCOMOCO headers are
+/// `>name`.
+fn parse_header_rows(lines: &[String], homer: bool) -> Result<Vec<RawMatrix>, MotifError> {
+    let mut matrices: Vec<RawMatrix> = Vec::new();
+
+    for line in lines {
+        let trimmed = line.trim();
+        if let Some(header) = trimmed.strip_prefix('>') {
+            let fields: Vec<&str> = header.split('\t').map(str::trim).collect();
+            let id = if homer && fields.len() > 1 && !fields[1].is_empty() {
+                fields[1]
+            } else {
+                header.split_whitespace().next().unwrap_or_default()
+            };
+            if id.is_empty() {
+                return Err(MotifError::InvalidFileFormat("Missing motif ID".into()));
+            }
+            matrices.push((id.to_string(), Vec::new()));
+        } else if !trimmed.is_empty() {
+            let (id, rows) = matrices.last_mut().ok_or_else(|| {
+                MotifError::InvalidFileFormat("Matrix row before the first header".into())
+            })?;
+            let fields: Vec<&str> = trimmed.split_whitespace().collect();
+            rows.push(to_row(&parse_values(&fields, id)?, id)?);
+        }
+    }
+
+    Ok(matrices)
+}
+
+/// Writes Position Weight Matrices to a motif file in any supported format
+///
+/// Motifs are written in sorted ID order. Count-based formats (JASPAR, TRANSFAC) store
+/// frequencies scaled to `DEFAULT_NSITES` sites.
+///
+/// # Arguments
+/// * `pwms` - Collection of PWMs to write
+/// * `filename` - Path of the output file
+/// * `format` - Format to write
+///
+/// # Returns
+/// * `Result<(), MotifError>` - Unit type if successful
+///
+/// # Errors
+/// * `MotifError::Io` - For file writing issues
+/// * `MotifError::DataError` - If a PWM is malformed
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::formats::{read_motifs, write_motifs, MotifFormat};
+///
+/// let pwms = read_motifs("motifs.meme", MotifFormat::Meme).unwrap();
+/// write_motifs(&pwms, "motifs.jaspar", MotifFormat::Jaspar).unwrap();
+/// ```
+pub fn write_motifs(
+    pwms: &PWMCollection,
+    filename: &str,
+    format: MotifFormat,
+) -> Result<(), MotifError> {
+    let mut ids: Vec<&String> = pwms.keys().collect();
+    ids.sort();
+    let matrices = ids
+        .into_iter()
+        .map(|id| Ok((id.as_str(), matrix_rows(&pwms[id])?)))
+        .collect::<Result<Vec<_>, MotifError>>()?;
+
+    let mut out = BufWriter::new(File::create(filename)?);
+    match format {
+        MotifFormat::Meme => {
+            write_meme(&mut out, &matrices, &vec![DEFAULT_NSITES; matrices.len()])?
+        }
+        MotifFormat::Jaspar => write_jaspar(&mut out, &scaled(&matrices, DEFAULT_NSITES))?,
+        MotifFormat::Transfac => write_transfac(&mut out, &scaled(&matrices, DEFAULT_NSITES))?,
+        MotifFormat::Homer => write_homer(&mut out, &matrices)?,
+        MotifFormat::Hocomoco => write_hocomoco(&mut out, &matrices)?,
+    }
+    out.flush()?;
+
+    Ok(())
+}
+
+/// Reads Position Count Matrices from a motif file in any supported format
+///
+/// Counts are kept exactly as stored by count-based files (JASPAR, TRANSFAC, HOCOMOCO
+/// `.pcm`). MEME frequencies are scaled by the motif's `nsites`; other frequency matrices,
+/// and MEME motifs without `nsites`, are scaled to `DEFAULT_NSITES` sites. Use
+/// `pcm_to_pwm()` to derive frequencies under a prior of your choice.
+///
+/// # Arguments
+/// * `filename` - Path to the motif file
+/// * `format` - Format of the file, e.g. from `MotifFormat::from_path()`
+///
+/// # Returns
+/// * `Result<PCMCollection, MotifError>` - A HashMap where keys are motif IDs and values are
+///   their PCMs
+///
+/// # Errors
+/// * `MotifError::Io` - If the file cannot be opened or read
+/// * `MotifError::InvalidFileFormat` - If the file cannot be parsed or contains no motifs
+/// * `MotifError::DataError` - If there are issues creating the DataFrames
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::formats::{read_counts, MotifFormat};
+/// use tf_binding_rs::matrix::{pcm_to_pwm, Prior};
+///
+/// let pcms = read_counts("JASPAR2024_CORE.jaspar", MotifFormat::Jaspar).unwrap();
+/// let pwm = pcm_to_pwm(&pcms["MA0842.1"], Prior::Pseudocount(0.8)).unwrap();
+/// ```
+pub fn read_counts(filename: &str, format: MotifFormat) -> Result<PCMCollection, MotifError> {
+    if format == MotifFormat::Meme {
+        let nsites = meme_nsites(filename)?;
+        return read_pwm_files(filename)?
+            .into_iter()
+            .map(|(id, pwm)| {
+                let sites = nsites.get(&id).copied().unwrap_or(DEFAULT_NSITES);
+                Ok((id, pwm_to_pcm(&pwm, sites)?))
+            })
+            .collect();
+    }
+
+    read_raw_matrices(filename, format)?
+        .into_iter()
+        .map(|(id, mut rows)| {
+            let frequencies = rows
+                .iter()
+                .all(|row| (row.iter().sum::<f64>() - 1.0).abs() < 0.02);
+            if frequencies {
+                debug!(file = filename, motif = %id, "no counts stored, assuming {} sites", DEFAULT_NSITES);
+                normalize_rows(&mut rows);
+                rows.iter_mut()
+                    .for_each(|row| row.iter_mut().for_each(|v| *v *= DEFAULT_NSITES));
+            }
+            Ok((id, pwm_from_rows(&rows)?))
+        })
+        .collect()
+}
+
+/// `nsites` of every MEME motif that declares it
+fn meme_nsites(filename: &str) -> Result<HashMap<String, f64>, MotifError> {
+    let mut nsites = HashMap::new();
+    let mut id: Option<String> = None;
+    for line in BufReader::new(File::open(filename)?).lines() {
+        let line = line?;
+        if let Some(rest) = line.strip_prefix("MOTIF") {
+            id = rest.split_whitespace().next().map(str::to_string);
+        } else if line.starts_with("letter-probability matrix") {
+            // "nsites= 20" or "nsites=20"
+            let value = line
+                .split("nsites=")
+                .nth(1)
+                .and_then(|rest| rest.split_whitespace().next())
+                .and_then(|v| v.parse::<f64>().ok());
+            if let (Some(id), Some(value)) = (id.take(), value) {
+                nsites.insert(id, value);
+            }
+        }
+    }
+    Ok(nsites)
+}
+
+/// Writes Position Count Matrices to a motif file in any supported format
+///
+/// Count-based formats (JASPAR, TRANSFAC, HOCOMOCO) store the counts unchanged, so counts
+/// read with `read_counts()` survive a conversion between them. MEME and HOMER store
+/// frequencies; MEME records the mean number of sites per position as `nsites`. Motifs are
+/// written in sorted ID order.
+///
+/// # Arguments
+/// * `pcms` - Collection of PCMs to write
+/// * `filename` - Path of the output file
+/// * `format` - Format to write
+///
+/// # Errors
+/// * `MotifError::Io` - For file writing issues
+/// * `MotifError::DataError` - If a PCM is malformed
+pub fn write_counts(
+    pcms: &PCMCollection,
+    filename: &str,
+    format: MotifFormat,
+) -> Result<(), MotifError> {
+    let mut ids: Vec<&String> = pcms.keys().collect();
+    ids.sort();
+    let counts = ids
+        .into_iter()
+        .map(|id| Ok((id.as_str(), matrix_rows(&pcms[id])?)))
+        .collect::<Result<Vec<_>, MotifError>>()?;
+    let nsites: Vec<f64> = counts
+        .iter()
+        .map(|(_, rows)| {
+            rows.iter().map(|row| row.iter().sum::<f64>()).sum::<f64>() / rows.len().max(1) as f64
+        })
+        .collect();
+    let mut frequencies = counts.clone();
+    for (_, rows) in frequencies.iter_mut() {
+        normalize_rows(rows);
+    }
+
+    let mut out = BufWriter::new(File::create(filename)?);
+    match format {
+        MotifFormat::Meme => write_meme(&mut out, &frequencies, &nsites)?,
+        MotifFormat::Jaspar => write_jaspar(&mut out, &counts)?,
+        MotifFormat::Transfac => write_transfac(&mut out, &counts)?,
+        MotifFormat::Homer => write_homer(&mut out, &frequencies)?,
+        MotifFormat::Hocomoco => write_hocomoco(&mut out, &counts)?,
+    }
+    out.flush()?;
+
+    Ok(())
+}
+
+/// Frequency rows multiplied by a number of sites
+fn scaled<'a>(matrices: &[(&'a str, Vec<[f64; 4]>)], nsites: f64) -> Vec<(&'a str, Vec<[f64; 4]>)> {
+    matrices
+        .iter()
+        .map(|(id, rows)| {
+            (
+                *id,
+                rows.iter().map(|row| row.map(|v| v * nsites)).collect(),
+            )
+        })
+        .collect()
+}
+
+fn consensus(rows: &[[f64; 4]]) -> String {
+    rows.iter()
+        .map(|row| {
+            let best = (0..4)
+                .max_by(|&a, &b| row[a].total_cmp(&row[b]))
+                .unwrap_or(0);
+            ['A', 'C', 'G', 'T'][best]
+        })
+        .collect()
+}
+
+fn write_meme<W: Write>(
+    out: &mut W,
+    matrices: &[(&str, Vec<[f64; 4]>)],
+    nsites: &[f64],
+) -> std::io::Result<()> {
+    writeln!(out, "MEME version 4\n")?;
+    writeln!(out, "ALPHABET= ACGT\n")?;
+    writeln!(out, "strands: + -\n")?;
+    writeln!(out, "Background letter frequencies")?;
+    writeln!(out, "A 0.25 C 0.25 G 0.25 T 0.25\n")?;
+
+    for ((id, rows), nsites) in matrices.iter().zip(nsites) {
+        // the blank line keeps the layout read_pwm_files expects: two lines before the rows
+        writeln!(out, "MOTIF {}\n", id)?;
+        writeln!(
+            out,
+            "letter-probability matrix: alength= 4 w= {} nsites= {} E= 0",
+            rows.len(),
+            nsites.round()
+        )?;
+        for row in rows {
+            writeln!(
+                out,
+                " {:.6}  {:.6}  {:.6}  {:.6}",
+                row[0], row[1], row[2], row[3]
+            )?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+fn write_jaspar<W: Write>(out: &mut W, matrices: &[(&str, Vec<[f64; 4]>)]) -> std::io::Result<()> {
+    for (id, rows) in matrices {
+        writeln!(out, ">{}\t{}", id, id)?;
+        for (i, base) in ['A', 'C', 'G', 'T'].iter().enumerate() {
+            let counts: Vec<String> = rows.iter().map(|row| format!("{:.0}", row[i])).collect();
+            writeln!(out, "{}  [ {} ]", base, counts.join(" "))?;
+        }
+    }
+    Ok(())
+}
+
+fn write_transfac<W: Write>(
+    out: &mut W,
+    matrices: &[(&str, Vec<[f64; 4]>)],
+) -> std::io::Result<()> {
+    for (id, rows) in matrices {
+        writeln!(out, "AC  {}\nXX\nID  {}\nXX", id, id)?;
+        writeln!(out, "P0      A      C      G      T")?;
+        for (pos, (row, base)) in rows.iter().zip(consensus(rows).chars()).enumerate() {
+            writeln!(
+                out,
+                "{:02} {:>6.0} {:>6.0} {:>6.0} {:>6.0}      {}",
+                pos + 1,
+                row[0],
+                row[1],
+                row[2],
+                row[3],
+                base
+            )?;
+        }
+        writeln!(out, "XX\n//")?;
+    }
+    Ok(())
+}
+
+fn write_homer<W: Write>(out: &mut W, matrices: &[(&str, Vec<[f64; 4]>)]) -> std::io::Result<()> {
+    for (id, rows) in matrices {
+        // HOMER needs a log-odds detection threshold; use 60% of the best possible score
+        let max_score: f64 = rows
+            .iter()
+            .map(|row| (row.iter().copied().fold(0.0, f64::max).max(1e-3) / 0.25).ln())
+            .sum();
+        writeln!(out, ">{}\t{}\t{:.6}", consensus(rows), id, 0.6 * max_score)?;
+        for row in rows {
+            writeln!(
+                out,
+                "{:.3}\t{:.3}\t{:.3}\t{:.3}",
+                row[0], row[1], row[2], row[3]
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn write_hocomoco<W: Write>(
+    out: &mut W,
+    matrices: &[(&str, Vec<[f64; 4]>)],
+) -> std::io::Result<()> {
+    for (id, rows) in matrices {
+        writeln!(out, ">{}", id)?;
+        for row in rows {
+            writeln!(
+                out,
+                "{:.6}\t{:.6}\t{:.6}\t{:.6}",
+                row[0], row[1], row[2], row[3]
+            )?;
+        }
+    }
+    Ok(())
+}
@@ -1,6 +1,46 @@
 //! Fast transcription factor binding site prediction and FASTA manipulation in Rust
+//!
+//! `prelude` exports the core API; import it with `use tf_binding_rs::prelude::*;`
+//! rather than reaching into the other modules, whose items may change in any release;
+//! see the `prelude` docs for its stability policy.
 
+pub mod annotations;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod cache;
+pub mod calibration;
+pub mod collection;
+pub mod compare;
+pub mod conservation;
+pub mod controls;
+pub mod coords;
+pub mod design;
+pub mod dimer;
 pub mod error;
 pub mod fasta;
+pub mod features;
+pub mod footprint;
+pub mod formats;
+#[cfg(feature = "liftover")]
+pub mod liftover;
+pub mod matrix;
+pub mod ml;
+pub mod model;
 pub mod occupancy;
+pub mod output;
+pub mod overlap;
+pub mod prelude;
+pub mod provenance;
+pub mod pvalue;
+pub mod quantized;
+pub mod scan;
+pub mod scanner;
+pub mod schema;
+pub mod split;
+pub mod stats;
+#[cfg(feature = "arrow")]
+pub mod table;
+pub mod thermo;
+pub mod tracks;
 pub mod types;
+pub mod workload;
@@ -0,0 +1,370 @@
+use crate::error::MotifError;
+use flate2::read::MultiGzDecoder;
+use polars::prelude::*;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::time::Instant;
+use tracing::debug;
+
+/// Default fraction of the bases of an interval that must map, as in UCSC liftOver
+pub const DEFAULT_MIN_MATCH: f64 = 0.95;
+
+/// Ungapped aligned block of a chain: source bases `t_start..t_start + size` map to target
+/// bases `q_start..q_start + size`, counted on the target strand of the chain
+#[derive(Debug, Clone)]
+struct Block {
+    t_start: u64,
+    q_start: u64,
+    size: u64,
+}
+
+/// One chain of a chain file
+#[derive(Debug, Clone)]
+struct Chain {
+    score: f64,
+    t_start: u64,
+    t_end: u64,
+    q_name: String,
+    q_size: u64,
+    /// Whether the target side is on the minus strand
+    q_minus: bool,
+    blocks: Vec<Block>,
+}
+
+/// Chains of one source chromosome, sorted by start, with the running maximum of their
+/// ends so overlapping chains are found without scanning all of them
+#[derive(Debug, Clone, Default)]
+struct ChromChains {
+    chains: Vec<Chain>,
+    max_end: Vec<u64>,
+}
+
+/// An interval lifted to the target assembly, 0-based with an exclusive end
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiftedInterval {
+    pub chrom: String,
+    pub start: u64,
+    pub end: u64,
+    /// Whether the interval maps to the opposite strand
+    pub reversed: bool,
+}
+
+/// Alignment chains between two assemblies, read from a UCSC chain file such as
+/// `hg19ToHg38.over.chain.gz`
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::liftover::{ChainMap, DEFAULT_MIN_MATCH};
+///
+/// let chains = ChainMap::read("hg19ToHg38.over.chain.gz")?;
+/// let lifted = chains.lift("chr1", 4357766, 4357930, DEFAULT_MIN_MATCH);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ChainMap {
+    chroms: HashMap<String, ChromChains>,
+}
+
+impl ChainMap {
+    /// Reads a chain file, plain or gzip-compressed
+    ///
+    /// # Errors
+    /// * `MotifError::InvalidFileFormat` - If a header or block line is malformed, or a
+    ///   chain does not end where its header says
+    /// * `MotifError::Io` - For file reading issues
+    pub fn read(filename: &str) -> Result<Self, MotifError> {
+        let start = Instant::now();
+        let mut reader = BufReader::new(File::open(filename)?);
+        let gzip = reader.fill_buf()?.starts_with(&[0x1f, 0x8b]);
+        let map = if gzip {
+            Self::from_reader(BufReader::new(MultiGzDecoder::new(reader)), filename)?
+        } else {
+            Self::from_reader(reader, filename)?
+        };
+        debug!(
+            file = filename,
+            chromosomes = map.chroms.len(),
+            chains = map.chroms.values().map(|c| c.chains.len()).sum::<usize>(),
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "read chain file"
+        );
+        Ok(map)
+    }
+
+    fn from_reader<R: BufRead>(reader: R, filename: &str) -> Result<Self, MotifError> {
+        let mut chroms: HashMap<String, ChromChains> = HashMap::new();
+        // source chromosome and chain being read, with the next source and target positions
+        let mut current: Option<(String, Chain, u64, u64)> = None;
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let invalid = |what: &str| {
+                MotifError::InvalidFileFormat(format!("{} line {}: {}", filename, i + 1, what))
+            };
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.is_empty() || fields[0].starts_with('#') {
+                continue;
+            }
+            let number = |field: &str| field.parse::<u64>().map_err(|_| invalid("invalid number"));
+
+            if fields[0] == "chain" {
+                if current.is_some() {
+                    return Err(invalid("chain header before the last block of a chain"));
+                }
+                if fields.len() < 12 {
+                    return Err(invalid("chain header with fewer than 12 fields"));
+                }
+                let (t_start, q_start) = (number(fields[5])?, number(fields[10])?);
+                let chain = Chain {
+                    score: fields[1].parse().map_err(|_| invalid("invalid score"))?,
+                    t_start,
+                    t_end: number(fields[6])?,
+                    q_name: fields[7].to_string(),
+                    q_size: number(fields[8])?,
+                    q_minus: fields[9] == "-",
+                    blocks: Vec::new(),
+                };
+                current = Some((fields[2].to_string(), chain, t_start, q_start));
+                continue;
+            }
+
+            let Some((_, chain, t, q)) = current.as_mut() else {
+                return Err(invalid("alignment block outside a chain"));
+            };
+            let size = number(fields[0])?;
+            chain.blocks.push(Block {
+                t_start: *t,
+                q_start: *q,
+                size,
+            });
+            match fields[..] {
+                [_] => {
+                    let (chrom, chain, t, _) = current.take().expect("checked above");
+                    if t + size != chain.t_end {
+                        return Err(invalid("chain does not end at the end of its header"));
+                    }
+                    chroms.entry(chrom).or_default().chains.push(chain);
+                }
+                [_, dt, dq] => {
+                    *t += size + number(dt)?;
+                    *q += size + number(dq)?;
+                }
+                _ => return Err(invalid("alignment block without 1 or 3 fields")),
+            }
+        }
+        if current.is_some() {
+            return Err(MotifError::InvalidFileFormat(format!(
+                "{}: truncated chain file",
+                filename
+            )));
+        }
+
+        for chrom in chroms.values_mut() {
+            chrom.chains.sort_by_key(|chain| chain.t_start);
+            let mut max_end = 0;
+            chrom.max_end = chrom
+                .chains
+                .iter()
+                .map(|chain| {
+                    max_end = max_end.max(chain.t_end);
+                    max_end
+                })
+                .collect();
+        }
+        Ok(ChainMap { chroms })
+    }
+
+    /// Lifts the source interval `start..end` of `chrom` to the target assembly
+    ///
+    /// The interval is mapped through the chain aligning most of its bases (the higher
+    /// scoring one on ties) and spans from the first to the last mapped base, so small
+    /// gaps inside it are bridged.
+    ///
+    /// # Returns
+    /// * `Option<LiftedInterval>` - The lifted interval, or `None` if fewer than `min_match`
+    ///   of its bases map to one chain
+    pub fn lift(
+        &self,
+        chrom: &str,
+        start: u64,
+        end: u64,
+        min_match: f64,
+    ) -> Option<LiftedInterval> {
+        let chrom_chains = self.chroms.get(chrom)?;
+        if end <= start {
+            return None;
+        }
+        // (mapped bases, chain, first and last + 1 mapped target position)
+        let mut best: Option<(u64, &Chain, u64, u64)> = None;
+        let last = chrom_chains
+            .chains
+            .partition_point(|chain| chain.t_start < end);
+        let candidates = chrom_chains.chains[..last]
+            .iter()
+            .zip(&chrom_chains.max_end[..last]);
+        for (chain, &max_end) in candidates.rev() {
+            if max_end <= start {
+                break;
+            }
+            if chain.t_end <= start {
+                continue;
+            }
+            let (mut mapped, mut q_first, mut q_last) = (0, u64::MAX, 0);
+            let first = chain
+                .blocks
+                .partition_point(|block| block.t_start + block.size <= start);
+            for block in &chain.blocks[first..] {
+                if block.t_start >= end {
+                    break;
+                }
+                let overlap_start = start.max(block.t_start);
+                let overlap_end = end.min(block.t_start + block.size);
+                let q_start = block.q_start + (overlap_start - block.t_start);
+                mapped += overlap_end - overlap_start;
+                q_first = q_first.min(q_start);
+                q_last = q_last.max(q_start + (overlap_end - overlap_start));
+            }
+            let better = match best {
+                None => mapped > 0,
+                Some((best_mapped, best_chain, _, _)) => {
+                    mapped > best_mapped
+                        || (mapped == best_mapped && chain.score > best_chain.score)
+                }
+            };
+            if better {
+                best = Some((mapped, chain, q_first, q_last));
+            }
+        }
+
+        let (mapped, chain, q_first, q_last) = best?;
+        if (mapped as f64) < min_match * (end - start) as f64 {
+            return None;
+        }
+        let (start, end) = if chain.q_minus {
+            (chain.q_size - q_last, chain.q_size - q_first)
+        } else {
+            (q_first, q_last)
+        };
+        Some(LiftedInterval {
+            chrom: chain.q_name.clone(),
+            start,
+            end,
+            reversed: chain.q_minus,
+        })
+    }
+}
+
+/// Lifts the genomic coordinates of a hit table to another assembly
+///
+/// Hits whose interval does not map (fewer than `min_match` of its bases aligned to one
+/// chain, or a chromosome without chains) and hits without coordinates get null
+/// coordinates; `genomic_strand` is flipped for hits mapping to the opposite strand.
+///
+/// # Arguments
+/// * `hits` - Hit table with "chrom", "genomic_start", "genomic_end" and "genomic_strand"
+///   columns, e.g. from `coords::project_hits()`
+/// * `chains` - Chains from the assembly of the hits to the target assembly
+/// * `min_match` - Fraction of the bases of a hit that must map, in (0, 1]; see
+///   `DEFAULT_MIN_MATCH`
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - `hits` with the four genomic columns replaced in
+///   place by the lifted ones, each keeping its original dtype; the source coordinates
+///   are not kept, so rename or copy those columns first to compare both assemblies
+///
+/// # Errors
+/// * `MotifError::InvalidParameter` - If `min_match` is outside (0, 1]
+/// * `MotifError::DataError` - If a genomic column is missing or has the wrong type
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::liftover::{lift_hits, ChainMap, DEFAULT_MIN_MATCH};
+///
+/// let chains = ChainMap::read("hg19ToHg38.over.chain.gz")?;
+/// let hg38_hits = lift_hits(&hits, &chains, DEFAULT_MIN_MATCH)?;
+/// ```
+pub fn lift_hits(
+    hits: &DataFrame,
+    chains: &ChainMap,
+    min_match: f64,
+) -> Result<DataFrame, MotifError> {
+    if !(min_match > 0.0 && min_match <= 1.0) {
+        return Err(MotifError::invalid_parameter(
+            "min_match",
+            min_match,
+            "must be in (0, 1]",
+        ));
+    }
+    let data_err = |e: PolarsError| MotifError::DataError(e.to_string());
+    let column = |name: &str, dtype: DataType| {
+        hits.column(name)
+            .and_then(|c| c.cast(&dtype))
+            .map_err(data_err)
+    };
+    let (chroms, strands) = (
+        column("chrom", DataType::String)?,
+        column("genomic_strand", DataType::String)?,
+    );
+    let (starts, ends) = (
+        column("genomic_start", DataType::Int64)?,
+        column("genomic_end", DataType::Int64)?,
+    );
+    let (chroms, strands) = (
+        chroms.str().map_err(data_err)?,
+        strands.str().map_err(data_err)?,
+    );
+    let (starts, ends) = (
+        starts.i64().map_err(data_err)?,
+        ends.i64().map_err(data_err)?,
+    );
+
+    let n = hits.height();
+    let (mut out_chroms, mut out_starts, mut out_ends, mut out_strands) = (
+        Vec::with_capacity(n),
+        Vec::with_capacity(n),
+        Vec::with_capacity(n),
+        Vec::with_capacity(n),
+    );
+    for i in 0..n {
+        let lifted = match (chroms.get(i), starts.get(i), ends.get(i)) {
+            (Some(chrom), Some(start), Some(end)) if start >= 0 && end >= 0 => {
+                chains.lift(chrom, start as u64, end as u64, min_match)
+            }
+            _ => None,
+        };
+        match lifted {
+            Some(lifted) => {
+                let strand = strands
+                    .get(i)
+                    .map(|strand| match (strand, lifted.reversed) {
+                        ("+", true) => "-",
+                        ("-", true) => "+",
+                        (strand, _) => strand,
+                    });
+                out_chroms.push(Some(lifted.chrom));
+                out_starts.push(Some(lifted.start as i64));
+                out_ends.push(Some(lifted.end as i64));
+                out_strands.push(strand.map(str::to_string));
+            }
+            None => {
+                out_chroms.push(None);
+                out_starts.push(None);
+                out_ends.push(None);
+                out_strands.push(None);
+            }
+        }
+    }
+
+    let mut out = hits.clone();
+    for column in [
+        Column::new("chrom".into(), out_chroms),
+        Column::new("genomic_start".into(), out_starts),
+        Column::new("genomic_end".into(), out_ends),
+        Column::new("genomic_strand".into(), out_strands),
+    ] {
+        // the coordinates were lifted as Int64; give each column back the dtype it had
+        let dtype = hits.column(column.name()).map_err(data_err)?.dtype();
+        let column = column.cast(dtype).map_err(data_err)?;
+        out.with_column(column).map_err(data_err)?;
+    }
+    Ok(out)
+}
@@ -0,0 +1,295 @@
+use crate::error::MotifError;
+use crate::types::*;
+use polars::prelude::*;
+
+/// Column order of every matrix in this crate
+pub const NUCLEOTIDES: [&str; 4] = ["A", "C", "G", "T"];
+
+/// Builds a PWM DataFrame from rows of A, C, G, T values.
+///
+/// # Arguments
+/// * `rows` - One `[A, C, G, T]` row per motif position
+///
+/// # Returns
+/// * `Result<PWM, MotifError>` - DataFrame with columns A, C, G, T
+///
+/// # Errors
+/// * `MotifError::DataError` - If DataFrame creation fails
+pub fn pwm_from_rows(rows: &[[f64; 4]]) -> Result<PWM, MotifError> {
+    DataFrame::new(
+        NUCLEOTIDES
+            .iter()
+            .enumerate()
+            .map(|(i, base)| {
+                Column::new(
+                    (*base).into(),
+                    rows.iter().map(|row| row[i]).collect::<Vec<f64>>(),
+                )
+            })
+            .collect(),
+    )
+    .map_err(|e| MotifError::DataError(e.to_string()))
+}
+
+/// Extracts the rows of A, C, G, T values from a matrix DataFrame.
+///
+/// # Arguments
+/// * `matrix` - DataFrame with columns A, C, G, T (a PWM, EWM or count matrix)
+///
+/// # Returns
+/// * `Result<Vec<[f64; 4]>, MotifError>` - One `[A, C, G, T]` row per motif position
+///
+/// # Errors
+/// * `MotifError::DataError` - If a column is missing or not of type f64
+pub fn matrix_rows(matrix: &DataFrame) -> Result<Vec<[f64; 4]>, MotifError> {
+    let columns = NUCLEOTIDES
+        .iter()
+        .map(|base| {
+            matrix
+                .column(base)
+                .and_then(|c| c.f64().cloned())
+                .map_err(|e| MotifError::DataError(e.to_string()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok((0..matrix.height())
+        .map(|i| {
+            let mut row = [0.0; 4];
+            for (value, column) in row.iter_mut().zip(&columns) {
+                *value = column.get(i).unwrap_or(0.0);
+            }
+            row
+        })
+        .collect())
+}
+
+/// Scales every row of a matrix so it sums to one.
+///
+/// Rows summing to zero are replaced by a uniform distribution.
+pub fn normalize_rows(rows: &mut [[f64; 4]]) {
+    for row in rows.iter_mut() {
+        let total: f64 = row.iter().sum();
+        if total > 0.0 {
+            row.iter_mut().for_each(|v| *v /= total);
+        } else {
+            *row = [0.25; 4];
+        }
+    }
+}
+
+/// Adds a pseudocount to every entry of a PWM and renormalizes each position.
+///
+/// # Arguments
+/// * `pwm` - Position Weight Matrix
+/// * `pseudocount` - Value added to every probability before renormalizing
+///
+/// # Returns
+/// * `Result<PWM, MotifError>` - The smoothed PWM
+///
+/// # Errors
+/// * `MotifError::InvalidParameter` - If `pseudocount` is negative
+/// * `MotifError::DataError` - If the PWM is malformed
+pub fn add_pseudocount(pwm: &PWM, pseudocount: f64) -> Result<PWM, MotifError> {
+    if pseudocount < 0.0 {
+        return Err(MotifError::invalid_parameter(
+            "pseudocount",
+            pseudocount,
+            "must not be negative",
+        ));
+    }
+
+    let mut rows = matrix_rows(pwm)?;
+    for row in rows.iter_mut() {
+        row.iter_mut().for_each(|v| *v += pseudocount);
+    }
+    normalize_rows(&mut rows);
+    pwm_from_rows(&rows)
+}
+
+/// Computes the information content (in bits) of each position of a PWM.
+///
+/// Uses a uniform background, so each position scores between 0 (uniform) and 2 bits
+/// (a single base).
+///
+/// # Arguments
+/// * `pwm` - Position Weight Matrix
+///
+/// # Returns
+/// * `Result<Vec<f64>, MotifError>` - Information content per position
+///
+/// # Errors
+/// * `MotifError::DataError` - If the PWM is malformed
+pub fn information_content(pwm: &PWM) -> Result<Vec<f64>, MotifError> {
+    Ok(matrix_rows(pwm)?
+        .iter()
+        .map(|row| {
+            let total: f64 = row.iter().sum();
+            2.0 + row
+                .iter()
+                .map(|&v| v / total)
+                .filter(|&p| p > 0.0)
+                .map(|p| p * p.log2())
+                .sum::<f64>()
+        })
+        .collect())
+}
+
+/// Trims low-information positions from both ends of a PWM.
+///
+/// Positions are removed from each flank while their information content is below
+/// `min_ic`; interior positions are never removed. If every position is below the
+/// threshold, the single most informative position is kept.
+///
+/// # Arguments
+/// * `pwm` - Position Weight Matrix
+/// * `min_ic` - Minimum information content (in bits) of the retained flank positions
+///
+/// # Returns
+/// * `Result<PWM, MotifError>` - The trimmed PWM
+///
+/// # Errors
+/// * `MotifError::DataError` - If the PWM is malformed
+pub fn trim_pwm(pwm: &PWM, min_ic: f64) -> Result<PWM, MotifError> {
+    let ic = information_content(pwm)?;
+    let start = ic.iter().position(|&v| v >= min_ic);
+    let end = ic.iter().rposition(|&v| v >= min_ic);
+
+    let (start, end) = match (start, end) {
+        (Some(start), Some(end)) => (start, end),
+        _ => {
+            let best = ic
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.total_cmp(b.1))
+                .map_or(0, |(i, _)| i);
+            (best, best)
+        }
+    };
+
+    Ok(pwm.slice(start as i64, end + 1 - start))
+}
+
+/// Reverse-complements a matrix: positions are reversed and A/T, C/G are swapped.
+///
+/// Works on any matrix with columns A, C, G, T (PWM, EWM or count matrix).
+///
+/// # Arguments
+/// * `matrix` - Matrix DataFrame
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - The reverse-complemented matrix
+///
+/// # Errors
+/// * `MotifError::DataError` - If the matrix is malformed
+pub fn reverse_complement_matrix(matrix: &DataFrame) -> Result<DataFrame, MotifError> {
+    let rows: Vec<[f64; 4]> = matrix_rows(matrix)?
+        .into_iter()
+        .rev()
+        .map(|[a, c, g, t]| [t, g, c, a])
+        .collect();
+    pwm_from_rows(&rows)
+}
+
+/// Prior counts added to a count matrix before it is converted to frequencies
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Prior {
+    /// Add this count to every base of every position
+    Pseudocount(f64),
+    /// Spread `total` counts over the bases of every position in proportion to `background`
+    Background { total: f64, background: [f64; 4] },
+    /// Spread sqrt(N) counts evenly over the bases, N being the number of sites at the
+    /// position (the default of TFBSTools' `toPWM`)
+    SqrtSites,
+}
+
+/// Converts a count matrix to a frequency matrix, smoothing with a prior.
+///
+/// Every position becomes `(count + prior) / (sites + total prior)`, so motifs built from a
+/// few sites are pulled toward the prior more strongly than motifs built from thousands.
+/// Positions without counts or prior become uniform.
+///
+/// # Arguments
+/// * `pcm` - Position Count Matrix
+/// * `prior` - Prior counts to add
+///
+/// # Returns
+/// * `Result<PWM, MotifError>` - The Position Weight Matrix
+///
+/// # Errors
+/// * `MotifError::InvalidParameter` - If the prior is negative or the background does not
+///   sum to 1
+/// * `MotifError::DataError` - If the matrix is malformed
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::matrix::{pcm_to_pwm, Prior};
+///
+/// let pwm = pcm_to_pwm(&pcm, Prior::SqrtSites).unwrap();
+/// ```
+pub fn pcm_to_pwm(pcm: &PCM, prior: Prior) -> Result<PWM, MotifError> {
+    let (total, background) = match prior {
+        Prior::Pseudocount(count) => (4.0 * count, [0.25; 4]),
+        Prior::Background { total, background } => {
+            let sum: f64 = background.iter().sum();
+            if background.iter().any(|&p| p < 0.0) || (sum - 1.0).abs() > 1e-6 {
+                return Err(MotifError::invalid_parameter(
+                    "background",
+                    format!("{:?}", background),
+                    "must be probabilities summing to 1",
+                ));
+            }
+            (total, background)
+        }
+        Prior::SqrtSites => (0.0, [0.25; 4]),
+    };
+    if total < 0.0 {
+        return Err(MotifError::invalid_parameter(
+            "prior",
+            total,
+            "must not be negative",
+        ));
+    }
+
+    let mut rows = matrix_rows(pcm)?;
+    for row in rows.iter_mut() {
+        let total = match prior {
+            Prior::SqrtSites => row.iter().sum::<f64>().sqrt(),
+            _ => total,
+        };
+        for (value, p) in row.iter_mut().zip(background) {
+            *value += total * p;
+        }
+    }
+    normalize_rows(&mut rows);
+    pwm_from_rows(&rows)
+}
+
+/// Converts a frequency matrix to a count matrix of `nsites` sites per position.
+///
+/// Counts are not rounded, so converting back with a zero prior returns the original PWM.
+///
+/// # Arguments
+/// * `pwm` - Position Weight Matrix
+/// * `nsites` - Number of sites, e.g. the `nsites` of a MEME motif
+///
+/// # Returns
+/// * `Result<PCM, MotifError>` - The Position Count Matrix
+///
+/// # Errors
+/// * `MotifError::InvalidParameter` - If `nsites` is not positive
+/// * `MotifError::DataError` - If the matrix is malformed
+pub fn pwm_to_pcm(pwm: &PWM, nsites: f64) -> Result<PCM, MotifError> {
+    if nsites <= 0.0 {
+        return Err(MotifError::invalid_parameter(
+            "nsites",
+            nsites,
+            "must be positive",
+        ));
+    }
+    let mut rows = matrix_rows(pwm)?;
+    normalize_rows(&mut rows);
+    for row in rows.iter_mut() {
+        row.iter_mut().for_each(|v| *v *= nsites);
+    }
+    pwm_from_rows(&rows)
+}
@@ -0,0 +1,573 @@
+use crate::error::MotifError;
+use crate::features::average_ranks;
+use polars::prelude::*;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use tracing::warn;
+
+/// What a baseline model predicts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Objective {
+    /// Ridge regression of a continuous activity, e.g. MPRA log ratios (the default)
+    #[default]
+    Linear,
+    /// L2-regularized logistic regression of 0/1 classes, e.g. active and inactive sequences
+    Logistic,
+}
+
+/// Options of `fit_regression()` and `cross_validate()`
+#[derive(Debug, Clone, Copy)]
+pub struct RegressionOptions {
+    pub objective: Objective,
+    /// Strength of the L2 penalty on the standardized coefficients (default 1); the
+    /// intercept is not penalized
+    pub l2: f64,
+    /// Newton iterations of logistic fits (default 100)
+    pub max_iter: usize,
+    /// Logistic fits stop once no parameter moves by more than this (default 1e-8)
+    pub tolerance: f64,
+}
+
+impl Default for RegressionOptions {
+    fn default() -> Self {
+        RegressionOptions {
+            objective: Objective::Linear,
+            l2: 1.0,
+            max_iter: 100,
+            tolerance: 1e-8,
+        }
+    }
+}
+
+/// A linear or logistic model of a feature matrix, fitted by `fit_regression()`
+///
+/// Features are standardized before fitting, so coefficients are per standard deviation of
+/// a feature and comparable across motifs whatever the scale of their occupancies.
+#[derive(Debug, Clone)]
+pub struct FittedModel {
+    pub objective: Objective,
+    /// Names of the feature columns, in the order of `coefficients`
+    pub features: Vec<String>,
+    /// Prediction (linear) or log-odds (logistic) of a sequence with every feature at its
+    /// mean
+    pub intercept: f64,
+    /// Change of the prediction or log-odds per standard deviation of every feature
+    pub coefficients: Vec<f64>,
+    means: Vec<f64>,
+    scales: Vec<f64>,
+}
+
+impl FittedModel {
+    /// Predictions for the rows of a feature matrix holding the columns of the fitted one:
+    /// activities for linear models, probabilities of class 1 for logistic ones; null
+    /// features count as 0
+    ///
+    /// # Errors
+    /// * `MotifError::DataError` - If a feature column is missing or cannot be converted to f64
+    pub fn predict(&self, features: &DataFrame) -> Result<Vec<f64>, MotifError> {
+        let rows = feature_rows(features, &self.features)?;
+        Ok(rows.iter().map(|row| self.predict_row(row)).collect())
+    }
+
+    /// Coefficient table of the model, for export
+    ///
+    /// # Returns
+    /// * `Result<DataFrame, MotifError>` - One row per feature in fitting order, with columns:
+    ///   - "feature": Column name, usually a motif ID
+    ///   - "coefficient": Coefficient per standard deviation of the feature
+    ///   - "unit_coefficient": Coefficient per unit of the feature, e.g. as the weight of
+    ///     the motif in `predicted_activity()`
+    ///
+    /// # Errors
+    /// * `MotifError::DataError` - If DataFrame creation fails
+    pub fn coefficient_table(&self) -> Result<DataFrame, MotifError> {
+        let unit: Vec<f64> = self
+            .coefficients
+            .iter()
+            .zip(&self.scales)
+            .map(|(coefficient, scale)| coefficient / scale)
+            .collect();
+        df!(
+            "feature" => &self.features,
+            "coefficient" => &self.coefficients,
+            "unit_coefficient" => unit,
+        )
+        .map_err(|e| MotifError::DataError(e.to_string()))
+    }
+
+    fn predict_row(&self, row: &[f64]) -> f64 {
+        let eta = self.intercept
+            + row
+                .iter()
+                .zip(&self.coefficients)
+                .zip(self.means.iter().zip(&self.scales))
+                .map(|((x, coefficient), (mean, scale))| coefficient * (x - mean) / scale)
+                .sum::<f64>();
+        match self.objective {
+            Objective::Linear => eta,
+            Objective::Logistic => sigmoid(eta),
+        }
+    }
+}
+
+/// Score of one fold of `cross_validate()`
+#[derive(Debug, Clone)]
+pub struct FoldScore {
+    pub fold: usize,
+    /// Sequences the fold's model was trained on
+    pub train: usize,
+    /// Held-out sequences the fold was scored on
+    pub test: usize,
+    /// R² of linear models or ROC AUC of logistic ones on the held-out sequences; `None`
+    /// when they have constant targets
+    pub score: Option<f64>,
+}
+
+/// Result of `cross_validate()`
+#[derive(Debug, Clone)]
+pub struct CrossValidation {
+    pub folds: Vec<FoldScore>,
+    /// Out-of-fold prediction of every sequence, made by the model not trained on it
+    pub predictions: Vec<f64>,
+}
+
+impl CrossValidation {
+    /// Mean score of the folds that have one
+    pub fn mean_score(&self) -> Option<f64> {
+        let scores: Vec<f64> = self.folds.iter().filter_map(|fold| fold.score).collect();
+        (!scores.is_empty()).then(|| scores.iter().sum::<f64>() / scores.len() as f64)
+    }
+}
+
+/// Fits a regularized linear or logistic regression of a target on a feature matrix
+///
+/// A baseline model of MPRA and similar assays: every numeric column of `features` except
+/// "label" is a feature, standardized to mean 0 and standard deviation 1, and null values
+/// (sequences that could not be scanned with a motif) count as 0. Linear models minimize
+/// the mean squared error plus `l2 / 2` times the squared coefficients and are solved
+/// exactly; logistic models minimize the mean log loss with the same penalty by Newton's
+/// method.
+///
+/// # Arguments
+/// * `features` - Feature matrix, e.g. from `occupancy_features()` or `normalize_features()`
+/// * `target` - Activity (linear) or class, 0 or 1 (logistic), of every row of `features`
+/// * `options` - Objective, penalty and convergence settings
+///
+/// # Returns
+/// * `Result<FittedModel, MotifError>` - The fitted model
+///
+/// # Errors
+/// * `MotifError::InvalidInput` - If there are no rows, `target` does not have one finite
+///   value per row, or a logistic target has values other than 0 and 1 or only one class
+/// * `MotifError::InvalidParameter` - If `l2` is negative, `max_iter` is 0 or `tolerance`
+///   is not positive
+/// * `MotifError::DataError` - If a feature cannot be converted to f64 or the system is
+///   singular (e.g. duplicated features with `l2` = 0)
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::features::occupancy_features;
+/// use tf_binding_rs::ml::{fit_regression, RegressionOptions};
+///
+/// let features = occupancy_features(&sequences, &ewms, 9.0)?;
+/// let model = fit_regression(&features, &activities, &RegressionOptions::default())?;
+/// println!("{}", model.coefficient_table()?);
+/// ```
+pub fn fit_regression(
+    features: &DataFrame,
+    target: &[f64],
+    options: &RegressionOptions,
+) -> Result<FittedModel, MotifError> {
+    check_options(options)?;
+    let names = feature_names(features);
+    let rows = feature_rows(features, &names)?;
+    check_target(target, rows.len(), options.objective)?;
+    let rows: Vec<&[f64]> = rows.iter().map(Vec::as_slice).collect();
+    fit_rows(names, &rows, target, options)
+}
+
+/// Estimates how well `fit_regression()` generalizes by k-fold cross-validation
+///
+/// Rows are shuffled with `seed` and dealt into `folds` folds; every fold is predicted by
+/// a model fitted on the other folds and scored by R² (linear) or ROC AUC (logistic).
+/// Random folds can put near-identical sequences (variants of one element, overlapping
+/// tiles) on both sides; see `cross_validate_folds()` to keep them together.
+///
+/// # Arguments
+/// * `features` - Feature matrix, as for `fit_regression()`
+/// * `target` - Activity or class of every row of `features`
+/// * `options` - Objective, penalty and convergence settings
+/// * `folds` - Number of folds, between 2 and the number of rows
+/// * `seed` - Seed of the shuffle, so folds are reproducible
+///
+/// # Returns
+/// * `Result<CrossValidation, MotifError>` - The score of every fold and the out-of-fold
+///   predictions
+///
+/// # Errors
+/// * The errors of `fit_regression()`
+/// * `MotifError::InvalidParameter` - If `folds` is below 2 or above the number of rows
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::ml::{cross_validate, Objective, RegressionOptions};
+///
+/// let options = RegressionOptions {
+///     objective: Objective::Logistic,
+///     ..Default::default()
+/// };
+/// let cv = cross_validate(&features, &active, &options, 5, 42)?;
+/// println!("mean AUC {:?}", cv.mean_score());
+/// ```
+pub fn cross_validate(
+    features: &DataFrame,
+    target: &[f64],
+    options: &RegressionOptions,
+    folds: usize,
+    seed: u64,
+) -> Result<CrossValidation, MotifError> {
+    let n = features.height();
+    if folds < 2 || folds > n {
+        return Err(MotifError::invalid_parameter(
+            "folds",
+            folds,
+            format!("must be between 2 and the number of rows ({})", n),
+        ));
+    }
+    let mut order: Vec<usize> = (0..n).collect();
+    order.shuffle(&mut StdRng::seed_from_u64(seed));
+    let mut fold_of = vec![0; n];
+    for (position, &row) in order.iter().enumerate() {
+        fold_of[row] = position % folds;
+    }
+    cross_validate_folds(features, target, options, &fold_of)
+}
+
+/// Cross-validates `fit_regression()` on given folds, e.g. from `split::group_folds()`, so
+/// related sequences never train the model that scores them
+///
+/// # Arguments
+/// * `features` - Feature matrix, as for `fit_regression()`
+/// * `target` - Activity or class of every row of `features`
+/// * `options` - Objective, penalty and convergence settings
+/// * `fold_of` - Fold of every row, numbered from 0
+///
+/// # Returns
+/// * `Result<CrossValidation, MotifError>` - The score of every fold and the out-of-fold
+///   predictions
+///
+/// # Errors
+/// * The errors of `fit_regression()`
+/// * `MotifError::InvalidInput` - If `fold_of` does not have one fold per row
+/// * `MotifError::InvalidParameter` - If there are fewer than 2 folds or a fold has no rows
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::ml::{cross_validate_folds, RegressionOptions};
+/// use tf_binding_rs::split::{group_folds, split_keys, SplitKey};
+///
+/// let keys = split_keys(&sequences, &SplitKey::LabelPrefix('_'))?;
+/// let folds = group_folds(&keys, 5, 42)?;
+/// let cv = cross_validate_folds(&features, &activities, &RegressionOptions::default(), &folds)?;
+/// ```
+pub fn cross_validate_folds(
+    features: &DataFrame,
+    target: &[f64],
+    options: &RegressionOptions,
+    fold_of: &[usize],
+) -> Result<CrossValidation, MotifError> {
+    check_options(options)?;
+    let names = feature_names(features);
+    let rows = feature_rows(features, &names)?;
+    check_target(target, rows.len(), options.objective)?;
+    if fold_of.len() != rows.len() {
+        return Err(MotifError::InvalidInput(format!(
+            "{} folds for {} feature rows",
+            fold_of.len(),
+            rows.len()
+        )));
+    }
+    let folds = fold_of.iter().max().map_or(0, |fold| fold + 1);
+    let mut sizes = vec![0; folds];
+    for &fold in fold_of {
+        sizes[fold] += 1;
+    }
+    if folds < 2 || sizes.contains(&0) {
+        return Err(MotifError::invalid_parameter(
+            "folds",
+            folds,
+            "need at least 2 folds, every one with rows",
+        ));
+    }
+
+    let mut predictions = vec![0.0; rows.len()];
+    let mut scores = Vec::with_capacity(folds);
+    for fold in 0..folds {
+        let (mut train_rows, mut train_target) = (Vec::new(), Vec::new());
+        let mut test = Vec::new();
+        for (i, row) in rows.iter().enumerate() {
+            if fold_of[i] == fold {
+                test.push(i);
+            } else {
+                train_rows.push(row.as_slice());
+                train_target.push(target[i]);
+            }
+        }
+        let model = fit_rows(names.clone(), &train_rows, &train_target, options)?;
+        let (mut test_target, mut test_predictions) = (Vec::new(), Vec::new());
+        for &i in &test {
+            predictions[i] = model.predict_row(&rows[i]);
+            test_target.push(target[i]);
+            test_predictions.push(predictions[i]);
+        }
+        scores.push(FoldScore {
+            fold,
+            train: train_rows.len(),
+            test: test.len(),
+            score: score(options.objective, &test_target, &test_predictions),
+        });
+    }
+    Ok(CrossValidation {
+        folds: scores,
+        predictions,
+    })
+}
+
+/// Numeric columns of a feature matrix other than "label"
+fn feature_names(features: &DataFrame) -> Vec<String> {
+    features
+        .get_columns()
+        .iter()
+        .filter(|c| c.dtype().is_numeric() && c.name() != "label")
+        .map(|c| c.name().to_string())
+        .collect()
+}
+
+/// Rows of the `names` columns of a feature matrix, with nulls as 0
+fn feature_rows(features: &DataFrame, names: &[String]) -> Result<Vec<Vec<f64>>, MotifError> {
+    let data_error = |e: PolarsError| MotifError::DataError(e.to_string());
+    let mut rows = vec![Vec::with_capacity(names.len()); features.height()];
+    for name in names {
+        let column = features
+            .column(name)
+            .and_then(|c| c.cast(&DataType::Float64))
+            .map_err(data_error)?;
+        for (row, value) in rows.iter_mut().zip(column.f64().map_err(data_error)?) {
+            row.push(value.unwrap_or(0.0));
+        }
+    }
+    Ok(rows)
+}
+
+fn check_options(options: &RegressionOptions) -> Result<(), MotifError> {
+    if !options.l2.is_finite() || options.l2 < 0.0 {
+        return Err(MotifError::invalid_parameter(
+            "l2",
+            options.l2,
+            "must not be negative",
+        ));
+    }
+    if options.max_iter == 0 {
+        return Err(MotifError::invalid_parameter(
+            "max_iter",
+            0,
+            "must be at least 1",
+        ));
+    }
+    if options.tolerance.is_nan() || options.tolerance <= 0.0 {
+        return Err(MotifError::invalid_parameter(
+            "tolerance",
+            options.tolerance,
+            "must be positive",
+        ));
+    }
+    Ok(())
+}
+
+fn check_target(target: &[f64], rows: usize, objective: Objective) -> Result<(), MotifError> {
+    if rows == 0 {
+        return Err(MotifError::InvalidInput(
+            "no feature rows to fit".to_string(),
+        ));
+    }
+    if target.len() != rows {
+        return Err(MotifError::InvalidInput(format!(
+            "{} target values for {} feature rows",
+            target.len(),
+            rows
+        )));
+    }
+    if target.iter().any(|y| !y.is_finite()) {
+        return Err(MotifError::InvalidInput(
+            "target values must be finite".to_string(),
+        ));
+    }
+    if objective == Objective::Logistic {
+        if target.iter().any(|&y| y != 0.0 && y != 1.0) {
+            return Err(MotifError::InvalidInput(
+                "logistic targets must be 0 or 1".to_string(),
+            ));
+        }
+        if target.iter().all(|&y| y == target[0]) {
+            return Err(MotifError::InvalidInput(
+                "logistic targets need both classes".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Fits standardized `rows` by Newton's method; one step solves the linear objective
+fn fit_rows(
+    names: Vec<String>,
+    rows: &[&[f64]],
+    target: &[f64],
+    options: &RegressionOptions,
+) -> Result<FittedModel, MotifError> {
+    let n = rows.len() as f64;
+    let p = names.len();
+    let mut means = vec![0.0; p];
+    let mut scales = vec![0.0; p];
+    for (j, (mean, scale)) in means.iter_mut().zip(&mut scales).enumerate() {
+        *mean = rows.iter().map(|row| row[j]).sum::<f64>() / n;
+        let variance = rows.iter().map(|row| (row[j] - *mean).powi(2)).sum::<f64>() / n;
+        // constant columns stay 0 after centering and get no weight
+        *scale = if variance > 0.0 { variance.sqrt() } else { 1.0 };
+    }
+    // standardized rows with a leading 1 for the intercept
+    let design: Vec<Vec<f64>> = rows
+        .iter()
+        .map(|row| {
+            std::iter::once(1.0)
+                .chain(
+                    row.iter()
+                        .zip(means.iter().zip(&scales))
+                        .map(|(x, (mean, scale))| (x - mean) / scale),
+                )
+                .collect()
+        })
+        .collect();
+
+    let iterations = match options.objective {
+        Objective::Linear => 1,
+        Objective::Logistic => options.max_iter,
+    };
+    let mut theta = vec![0.0; p + 1];
+    let mut converged = false;
+    for _ in 0..iterations {
+        let mut hessian = vec![vec![0.0; p + 1]; p + 1];
+        let mut gradient = vec![0.0; p + 1];
+        for (a, &y) in design.iter().zip(target) {
+            let eta: f64 = a.iter().zip(&theta).map(|(x, t)| x * t).sum();
+            let (weight, residual) = match options.objective {
+                Objective::Linear => (1.0, eta - y),
+                Objective::Logistic => {
+                    let prob = sigmoid(eta);
+                    (prob * (1.0 - prob), prob - y)
+                }
+            };
+            for (&aj, (g, row)) in a.iter().zip(gradient.iter_mut().zip(&mut hessian)) {
+                *g += residual * aj / n;
+                for (h, &ak) in row.iter_mut().zip(a) {
+                    *h += weight * aj * ak / n;
+                }
+            }
+        }
+        for (j, (g, row)) in gradient.iter_mut().zip(&mut hessian).enumerate().skip(1) {
+            *g += options.l2 * theta[j];
+            row[j] += options.l2;
+        }
+
+        let step = solve_spd(hessian, gradient).ok_or_else(|| {
+            MotifError::DataError(
+                "singular regression system; remove duplicated features or increase l2".to_string(),
+            )
+        })?;
+        for (t, s) in theta.iter_mut().zip(&step) {
+            *t -= s;
+        }
+        if step.iter().all(|s| s.abs() < options.tolerance) {
+            converged = true;
+            break;
+        }
+    }
+    if options.objective == Objective::Logistic && !converged {
+        warn!(
+            iterations = options.max_iter,
+            "logistic regression did not converge"
+        );
+    }
+
+    Ok(FittedModel {
+        objective: options.objective,
+        features: names,
+        intercept: theta[0],
+        coefficients: theta[1..].to_vec(),
+        means,
+        scales,
+    })
+}
+
+/// Solves `a x = b` for a symmetric positive-definite `a` by Cholesky decomposition, or
+/// `None` if `a` is not positive-definite
+#[allow(clippy::needless_range_loop)]
+fn solve_spd(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    // the lower triangle of `a` becomes L with a = L Lᵀ
+    for j in 0..n {
+        let diagonal = a[j][j] - (0..j).map(|k| a[j][k] * a[j][k]).sum::<f64>();
+        if diagonal.is_nan() || diagonal <= 1e-12 {
+            return None;
+        }
+        a[j][j] = diagonal.sqrt();
+        for i in j + 1..n {
+            let dot: f64 = (0..j).map(|k| a[i][k] * a[j][k]).sum();
+            a[i][j] = (a[i][j] - dot) / a[j][j];
+        }
+    }
+    for i in 0..n {
+        let dot: f64 = (0..i).map(|k| a[i][k] * b[k]).sum();
+        b[i] = (b[i] - dot) / a[i][i];
+    }
+    for i in (0..n).rev() {
+        let dot: f64 = (i + 1..n).map(|k| a[k][i] * b[k]).sum();
+        b[i] = (b[i] - dot) / a[i][i];
+    }
+    Some(b)
+}
+
+fn sigmoid(x: f64) -> f64 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+/// R² of linear predictions or ROC AUC of logistic ones; `None` for constant targets
+fn score(objective: Objective, target: &[f64], predictions: &[f64]) -> Option<f64> {
+    match objective {
+        Objective::Linear => {
+            let mean = target.iter().sum::<f64>() / target.len() as f64;
+            let total: f64 = target.iter().map(|y| (y - mean).powi(2)).sum();
+            let residual: f64 = target
+                .iter()
+                .zip(predictions)
+                .map(|(y, p)| (y - p).powi(2))
+                .sum();
+            (total > 0.0).then(|| 1.0 - residual / total)
+        }
+        Objective::Logistic => {
+            let positives = target.iter().filter(|&&y| y == 1.0).count() as f64;
+            let negatives = target.len() as f64 - positives;
+            if positives == 0.0 || negatives == 0.0 {
+                return None;
+            }
+            // Mann-Whitney U of the positives over the negatives
+            let rank_sum: f64 = average_ranks(predictions)
+                .iter()
+                .zip(target)
+                .filter(|(_, &y)| y == 1.0)
+                .map(|(rank, _)| rank)
+                .sum();
+            Some((rank_sum - positives * (positives + 1.0) / 2.0) / (positives * negatives))
+        }
+    }
+}
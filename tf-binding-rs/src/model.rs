@@ -0,0 +1,280 @@
+use crate::error::MotifError;
+use crate::matrix::matrix_rows;
+use crate::occupancy::{encode_sequence, occupancies, stranded_hits, StrandedEwm};
+use crate::types::*;
+use polars::prelude::*;
+
+/// A model scoring fixed-width DNA windows, scanned and turned into landscapes by the
+/// functions of this module, `find_model_hits()` and `model_landscape()`
+///
+/// Scores are energies: lower scores bind more strongly, and the occupancy of a window is
+/// `1 / (1 + exp(score - mu))` as for energy matrices. Windows are given as base indices,
+/// 0 to 3 for A, C, G, T. Only `width()` and `score()` are required; the reverse strand
+/// is scored on the reverse complement of the window unless `score_reverse()` is
+/// overridden, e.g. with a precomputed reverse-complement matrix.
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::model::{find_model_hits, ScoringModel};
+///
+/// /// A learned linear model over one-hot encoded windows
+/// struct Linear {
+///     weights: Vec<[f64; 4]>,
+///     bias: f64,
+/// }
+///
+/// impl ScoringModel for Linear {
+///     fn width(&self) -> usize {
+///         self.weights.len()
+///     }
+///
+///     fn score(&self, window: &[usize]) -> f64 {
+///         self.bias + window.iter().zip(&self.weights).map(|(&b, w)| w[b]).sum::<f64>()
+///     }
+/// }
+///
+/// let hits = find_model_hits(seq, &[("linear", &model as &dyn ScoringModel)], 9.0, 0.2)?;
+/// ```
+pub trait ScoringModel: Send + Sync {
+    /// Width of the scored windows
+    fn width(&self) -> usize;
+
+    /// Score of a forward-strand window of `width()` bases
+    fn score(&self, window: &[usize]) -> f64;
+
+    /// Score of the reverse strand of a window of `width()` bases
+    fn score_reverse(&self, window: &[usize]) -> f64 {
+        let complement: Vec<usize> = window.iter().rev().map(|&b| 3 - b).collect();
+        self.score(&complement)
+    }
+
+    /// Scores of every window of `seq` on both strands
+    ///
+    /// # Errors
+    /// * `MotifError::InvalidSequence` - If the sequence is shorter than the model
+    /// * `MotifError::InvalidInput` - If the sequence contains characters other than A, C, G, T
+    fn energy_landscape(&self, seq: &str) -> Result<StrandScores, MotifError> {
+        let width = self.width();
+        let encoded = encode_sequence(seq, width)?;
+        if width == 0 {
+            return Ok((Vec::new(), Vec::new()));
+        }
+        Ok((
+            encoded.windows(width).map(|w| self.score(w)).collect(),
+            encoded
+                .windows(width)
+                .map(|w| self.score_reverse(w))
+                .collect(),
+        ))
+    }
+
+    /// Occupancies of every window of `seq` on both strands at chemical potential `mu`
+    ///
+    /// # Errors
+    /// * See `energy_landscape()`
+    fn occupancy_landscape(&self, seq: &str, mu: f64) -> Result<StrandScores, MotifError> {
+        Ok(occupancies(&self.energy_landscape(seq)?, mu))
+    }
+}
+
+impl ScoringModel for StrandedEwm {
+    fn width(&self) -> usize {
+        self.len()
+    }
+
+    fn score(&self, window: &[usize]) -> f64 {
+        self.forward_energy(window)
+    }
+
+    fn score_reverse(&self, window: &[usize]) -> f64 {
+        self.reverse_energy(window)
+    }
+
+    fn energy_landscape(&self, seq: &str) -> Result<StrandScores, MotifError> {
+        StrandedEwm::energy_landscape(self, seq)
+    }
+
+    fn occupancy_landscape(&self, seq: &str, mu: f64) -> Result<StrandScores, MotifError> {
+        StrandedEwm::occupancy_landscape(self, seq, mu)
+    }
+}
+
+/// Position weight matrix scored as log-odds against a background
+///
+/// The log-odds of a window is `sum log2(p / background)` in bits, higher for better
+/// sites; as a `ScoringModel` it scores `-log_odds`, so occupancies rise with the
+/// log-odds and `mu` is in bits.
+#[derive(Debug, Clone)]
+pub struct LogOddsPwm {
+    /// log2(p / background) per position and base
+    bits: Vec<[f64; 4]>,
+}
+
+impl LogOddsPwm {
+    /// Log-odds matrix of a PWM; `pseudocount` is added to every probability before the
+    /// rows are renormalized, so bases never seen in the sites stay finite
+    ///
+    /// # Errors
+    /// * `MotifError::InvalidParameter` - If a background probability is not positive or
+    ///   `pseudocount` is negative
+    /// * `MotifError::DataError` - If a nucleotide column is missing or not of type f64
+    pub fn new(pwm: &PWM, background: [f64; 4], pseudocount: f64) -> Result<Self, MotifError> {
+        if background.iter().any(|&p| !p.is_finite() || p <= 0.0) {
+            return Err(MotifError::invalid_parameter(
+                "background",
+                format!("{:?}", background),
+                "probabilities must be positive",
+            ));
+        }
+        if !pseudocount.is_finite() || pseudocount < 0.0 {
+            return Err(MotifError::invalid_parameter(
+                "pseudocount",
+                pseudocount,
+                "must not be negative",
+            ));
+        }
+        let bits = matrix_rows(pwm)?
+            .iter()
+            .map(|row| {
+                let total: f64 = row.iter().map(|p| p + pseudocount).sum();
+                std::array::from_fn(|base| {
+                    ((row[base] + pseudocount) / total / background[base]).log2()
+                })
+            })
+            .collect();
+        Ok(LogOddsPwm { bits })
+    }
+
+    /// Log-odds of a forward-strand window, in bits
+    pub fn log_odds(&self, window: &[usize]) -> f64 {
+        window.iter().zip(&self.bits).map(|(&b, row)| row[b]).sum()
+    }
+}
+
+impl ScoringModel for LogOddsPwm {
+    fn width(&self) -> usize {
+        self.bits.len()
+    }
+
+    fn score(&self, window: &[usize]) -> f64 {
+        -self.log_odds(window)
+    }
+}
+
+/// Dinucleotide energy matrix: the energy of a window is the sum of one value per pair of
+/// adjacent bases, so dependencies between neighbouring positions can be modelled
+///
+/// Row `i` holds the energies of the 16 pairs at positions `i` and `i + 1`, indexed by
+/// `4 * first + second` in A, C, G, T order, so a model of width W has W - 1 rows.
+#[derive(Debug, Clone)]
+pub struct DiPwm {
+    rows: Vec<[f64; 16]>,
+}
+
+impl DiPwm {
+    /// A dinucleotide model from its rows
+    ///
+    /// # Errors
+    /// * `MotifError::InvalidParameter` - If there are no rows or a value is not finite
+    pub fn new(rows: Vec<[f64; 16]>) -> Result<Self, MotifError> {
+        if rows.is_empty() {
+            return Err(MotifError::invalid_parameter(
+                "rows",
+                0,
+                "a dinucleotide model needs at least one row",
+            ));
+        }
+        if rows.iter().flatten().any(|value| !value.is_finite()) {
+            return Err(MotifError::invalid_parameter(
+                "rows",
+                rows.len(),
+                "energies must be finite",
+            ));
+        }
+        Ok(DiPwm { rows })
+    }
+
+    /// The dinucleotide form of an energy matrix, giving every window the energy of
+    /// the matrix; a starting point for fitting dependencies between positions
+    ///
+    /// # Errors
+    /// * `MotifError::InvalidParameter` - If the matrix is narrower than 2 positions
+    /// * `MotifError::DataError` - If a nucleotide column is missing or not of type f64
+    pub fn from_ewm(ewm: &EWM) -> Result<Self, MotifError> {
+        let energies = matrix_rows(ewm)?;
+        if energies.len() < 2 {
+            return Err(MotifError::invalid_parameter(
+                "ewm",
+                energies.len(),
+                "a dinucleotide model needs at least 2 positions",
+            ));
+        }
+        let last = energies.len() - 2;
+        let rows = (0..=last)
+            .map(|i| {
+                std::array::from_fn(|pair| {
+                    // the last pair also carries the energy of the final position
+                    let tail = if i == last {
+                        energies[i + 1][pair % 4]
+                    } else {
+                        0.0
+                    };
+                    energies[i][pair / 4] + tail
+                })
+            })
+            .collect();
+        DiPwm::new(rows)
+    }
+}
+
+impl ScoringModel for DiPwm {
+    fn width(&self) -> usize {
+        self.rows.len() + 1
+    }
+
+    fn score(&self, window: &[usize]) -> f64 {
+        window
+            .windows(2)
+            .zip(&self.rows)
+            .map(|(pair, row)| row[4 * pair[0] + pair[1]])
+            .sum()
+    }
+}
+
+/// Hits of several models on one sequence, in the layout of `find_hits()`: one row per
+/// window with occupancy strictly above `cutoff`, in the order of `models`
+///
+/// # Errors
+/// * See `ScoringModel::energy_landscape()`
+/// * `MotifError::DataError` - If the DataFrame cannot be created
+pub fn find_model_hits(
+    seq: &str,
+    models: &[(&str, &dyn ScoringModel)],
+    mu: f64,
+    cutoff: f64,
+) -> Result<DataFrame, MotifError> {
+    stranded_hits(seq, models, mu, cutoff)
+}
+
+/// Combined occupancy landscape of several models, in the layout of `total_landscape()`:
+/// "{ID}_F" and "{ID}_R" columns in the order of `models`, padded with 0 to the sequence
+/// length
+///
+/// # Errors
+/// * See `ScoringModel::energy_landscape()`
+/// * `MotifError::DataError` - If the DataFrame cannot be created
+pub fn model_landscape(
+    seq: &str,
+    models: &[(&str, &dyn ScoringModel)],
+    mu: f64,
+) -> Result<DataFrame, MotifError> {
+    let mut columns: Vec<Column> = Vec::with_capacity(2 * models.len());
+    for (id, model) in models {
+        let (mut focc, mut rocc) = model.occupancy_landscape(seq, mu)?;
+        focc.resize(seq.len(), 0.0);
+        rocc.resize(seq.len(), 0.0);
+        columns.push(Column::new(format!("{}_F", id).into(), focc));
+        columns.push(Column::new(format!("{}_R", id).into(), rocc));
+    }
+    DataFrame::new(columns).map_err(|e| MotifError::DataError(e.to_string()))
+}
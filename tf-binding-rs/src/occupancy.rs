@@ -1,27 +1,209 @@
-use crate::error::MotifError;
-use crate::fasta::reverse_complement;
+use crate::error::{MotifError, ResultExt};
+use crate::matrix::matrix_rows;
+use crate::model::ScoringModel;
 use crate::types::*;
-use polars::lazy::dsl::*;
 use polars::prelude::*;
+use polars_core::POOL;
+use rayon::prelude::*;
 use std::collections::HashMap;
+use std::fmt;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::iter::Peekable;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Instant;
+use tracing::{debug, warn};
 
 const PSEUDOCOUNT: f64 = 0.0001;
-const RT: f64 = 2.5;
 
-/// Advances the iterator until a MOTIF line is found
-fn skip_until_motif<I>(lines: &mut Peekable<I>)
+/// RT (kJ/mol) of the energy conversion unless a temperature is given
+pub const DEFAULT_RT: f64 = 2.5;
+
+/// Molar gas constant in kJ/(mol·K)
+pub const GAS_CONSTANT: f64 = 8.314_462_618e-3;
+
+/// Floating-point precision of computed landscapes
+///
+/// `F32` halves the memory of landscapes. Both precisions share one kernel: window energies
+/// are summed and occupancies computed in f64, and only the stored values are rounded to
+/// f32, so each differs from its f64 value by a relative error of at most about 6e-8
+/// (half an f32 ulp). Sums over many windows, e.g. total occupancies, should still be
+/// accumulated in f64.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Precision {
+    /// Double precision (the default)
+    #[default]
+    F64,
+    /// Single precision
+    F32,
+}
+
+impl fmt::Display for Precision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Precision::F64 => "f64",
+            Precision::F32 => "f32",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Precision {
+    type Err = MotifError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "f64" | "double" => Ok(Precision::F64),
+            "f32" | "single" => Ok(Precision::F32),
+            _ => Err(MotifError::invalid_parameter(
+                "precision",
+                s,
+                "expected f64 or f32",
+            )),
+        }
+    }
+}
+
+/// Parameters of the conversion of PWMs to energy matrices
+///
+/// Energies are `-RT ln(p / p_max)`, so RT sets the scale of every energy and, through
+/// them, of the occupancies `1 / (1 + exp(energy - mu))` computed from the matrix by the
+/// occupancy functions; `mu` is in the same units. Build the matrices of every condition
+/// with its own temperature to compare, say, 30 °C and 37 °C. The default RT of 2.5 kJ/mol
+/// (about 28 °C) reproduces the energies of `pwm_to_ewm()`.
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::formats::{read_motifs, MotifFormat};
+/// use tf_binding_rs::occupancy::{pwm_to_ewm_with, EwmOptions};
+///
+/// let options = EwmOptions::default().with_temperature(37.0);
+/// let ewm = pwm_to_ewm_with(&pwm, &options).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EwmOptions {
+    /// RT in kJ/mol
+    pub rt: f64,
+    /// Value added to every probability, so unobserved bases get a finite energy
+    pub pseudocount: f64,
+}
+
+impl Default for EwmOptions {
+    fn default() -> Self {
+        EwmOptions {
+            rt: DEFAULT_RT,
+            pseudocount: PSEUDOCOUNT,
+        }
+    }
+}
+
+impl EwmOptions {
+    /// Sets RT from a temperature in °C
+    pub fn with_temperature(mut self, celsius: f64) -> Self {
+        self.rt = GAS_CONSTANT * (celsius + 273.15);
+        self
+    }
+
+    /// Sets the pseudocount added to every probability
+    pub fn with_pseudocount(mut self, pseudocount: f64) -> Self {
+        self.pseudocount = pseudocount;
+        self
+    }
+
+    /// Temperature in °C corresponding to RT
+    pub fn temperature(&self) -> f64 {
+        self.rt / GAS_CONSTANT - 273.15
+    }
+}
+
+/// Element-wise maximum of two expressions
+fn max_expr(a: Expr, b: Expr) -> Expr {
+    when(a.clone().gt_eq(b.clone())).then(a).otherwise(b)
+}
+
+/// Reads a MEME file one motif at a time, yielding the lines of each motif from its MOTIF
+/// line up to the next one; the header before the first motif is dropped
+struct MotifChunks<R> {
+    lines: std::io::Lines<R>,
+    next_motif: Option<String>,
+}
+
+impl<R: BufRead> MotifChunks<R> {
+    fn new(reader: R) -> Self {
+        MotifChunks {
+            lines: reader.lines(),
+            next_motif: None,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for MotifChunks<R> {
+    type Item = Result<Vec<String>, MotifError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chunk: Vec<String> = self.next_motif.take().into_iter().collect();
+        for line in self.lines.by_ref() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e.into())),
+            };
+            if line.starts_with("MOTIF") && !chunk.is_empty() {
+                self.next_motif = Some(line);
+                return Some(Ok(chunk));
+            }
+            if line.starts_with("MOTIF") || !chunk.is_empty() {
+                chunk.push(line);
+            }
+        }
+        (!chunk.is_empty()).then_some(Ok(chunk))
+    }
+}
+
+/// Parses the motifs of a MEME file in parallel, one chunk per motif, and converts each
+/// with `convert` on the same thread, so even databases of thousands of motifs are read in
+/// a fraction of the sequential time. The file is streamed: each motif is handed to the
+/// pool as soon as its lines are read, so the file is never held in memory as a whole.
+/// Motifs are returned in file order.
+///
+/// Runs on the polars thread pool: a conversion collects a lazy frame, and doing that from a
+/// worker of another pool blocks it while it steals further chunks, nesting one conversion
+/// per motif on its stack.
+fn parse_meme<T, F>(filename: &str, convert: F) -> Result<Vec<(String, T)>, MotifError>
 where
-    I: Iterator<Item = Result<String, std::io::Error>>,
+    T: Send,
+    F: Fn(PWM) -> Result<T, MotifError> + Sync,
 {
-    while let Some(Ok(line)) = lines.peek() {
-        if line.starts_with("MOTIF") {
-            break;
+    let reader = BufReader::new(File::open(filename)?);
+    let mut motifs = POOL.install(|| {
+        MotifChunks::new(reader)
+            .enumerate()
+            .par_bridge()
+            .map(|(index, chunk)| {
+                let mut lines = chunk?.into_iter().map(Ok);
+                let (id, pwm) = parse_pwm(&mut lines)?
+                    .ok_or_else(|| MotifError::InvalidFileFormat("Missing motif ID".into()))?;
+                let converted = convert(pwm).with_motif(&id)?;
+                Ok((index, id, converted))
+            })
+            .collect::<Result<Vec<_>, MotifError>>()
+    })?;
+    motifs.sort_unstable_by_key(|(index, _, _)| *index);
+    Ok(motifs
+        .into_iter()
+        .map(|(_, id, converted)| (id, converted))
+        .collect())
+}
+
+/// Collects parsed motifs into a collection, keeping the last matrix of a repeated ID
+fn into_collection<T>(filename: &str, motifs: Vec<(String, T)>) -> HashMap<String, T> {
+    let mut collection = HashMap::with_capacity(motifs.len());
+    for (id, matrix) in motifs {
+        if collection.contains_key(&id) {
+            warn!(file = filename, motif = %id, "duplicate motif ID, keeping the last matrix");
         }
-        lines.next();
+        collection.insert(id, matrix);
     }
+    collection
 }
 
 /// Parses a single PWM from the iterator
@@ -54,7 +236,7 @@ where
                 .unwrap_or(false)
         })
         .map(|line| {
-            let line = line.map_err(|e| MotifError::Io(e))?;
+            let line = line.map_err(MotifError::Io)?;
             let values: Vec<f64> = line
                 .split_whitespace()
                 .map(|s| s.parse::<f64>())
@@ -122,26 +304,22 @@ where
 ///
 /// # Format
 /// The input file should be in MEME format, where each PWM is preceded by a "MOTIF" line
-/// containing the motif ID, followed by the matrix values.
+/// containing the motif ID, followed by the matrix values. The motifs are parsed in
+/// parallel on the polars thread pool.
 pub fn read_pwm_files(filename: &str) -> Result<PWMCollection, MotifError> {
-    let file = File::open(filename)?;
-    let reader = BufReader::new(file);
-    let mut lines = reader.lines().peekable();
-    let mut pwms = PWMCollection::new();
-
-    // Skip header until first MOTIF
-    skip_until_motif(&mut lines);
-
-    // Parse all PWMs
-    while let Some((id, pwm)) = parse_pwm(&mut lines)? {
-        pwms.insert(id, pwm);
-        skip_until_motif(&mut lines);
-    }
+    let start = Instant::now();
+    let pwms = into_collection(filename, parse_meme(filename, Ok)?);
 
     if pwms.is_empty() {
         return Err(MotifError::InvalidFileFormat("No PWMs found".into()));
     }
 
+    debug!(
+        file = filename,
+        motifs = pwms.len(),
+        elapsed_ms = start.elapsed().as_millis() as u64,
+        "read PWMs"
+    );
     Ok(pwms)
 }
 
@@ -171,7 +349,8 @@ pub fn read_pwm_files(filename: &str) -> Result<PWMCollection, MotifError> {
 ///
 /// # Constants
 /// * `PSEUDOCOUNT` - Value (default: 0.0001) added to every matrix position to handle zeros
-/// * `RT` - The RT value (default: 2.5) used in the ddG formula in kJ/mol
+/// * `RT` - The RT value (default: 2.5) used in the ddG formula in kJ/mol; see
+///   `read_pwm_to_ewm_with()` to set the temperature or pseudocount
 ///
 /// # Example
 /// ```ignore
@@ -183,57 +362,670 @@ pub fn read_pwm_files(filename: &str) -> Result<PWMCollection, MotifError> {
 /// }
 /// ```
 pub fn read_pwm_to_ewm(filename: &str) -> Result<EWMCollection, MotifError> {
-    let pwms = read_pwm_files(filename)?;
+    read_pwm_to_ewm_with(filename, &EwmOptions::default())
+}
 
-    let ewms: EWMCollection = pwms
-        .into_iter()
-        .map(|(id, pwm)| {
-            let normalized = pwm
-                .clone()
-                .lazy()
-                .select([
-                    (col("A") + lit(PSEUDOCOUNT)).alias("A_pseudo"),
-                    (col("C") + lit(PSEUDOCOUNT)).alias("C_pseudo"),
-                    (col("G") + lit(PSEUDOCOUNT)).alias("G_pseudo"),
-                    (col("T") + lit(PSEUDOCOUNT)).alias("T_pseudo"),
-                ])
-                .with_column(
-                    max_horizontal([
-                        col("A_pseudo"),
-                        col("C_pseudo"),
-                        col("G_pseudo"),
-                        col("T_pseudo"),
-                    ])
-                    .unwrap()
-                    .alias("max_val"),
-                )
-                .select([
-                    (col("A_pseudo") / col("max_val")).alias("A_norm"),
-                    (col("C_pseudo") / col("max_val")).alias("C_norm"),
-                    (col("G_pseudo") / col("max_val")).alias("G_norm"),
-                    (col("T_pseudo") / col("max_val")).alias("T_norm"),
-                ])
-                .select([
-                    (-lit(RT) * col("A_norm").log(std::f64::consts::E)).alias("A"),
-                    (-lit(RT) * col("C_norm").log(std::f64::consts::E)).alias("C"),
-                    (-lit(RT) * col("G_norm").log(std::f64::consts::E)).alias("G"),
-                    (-lit(RT) * col("T_norm").log(std::f64::consts::E)).alias("T"),
-                ])
+/// Reads the PWMs of a MEME format file and converts them to EWMs with the given RT and
+/// pseudocount; see `read_pwm_to_ewm()` and `EwmOptions`
+///
+/// # Errors
+/// * See `read_pwm_to_ewm()`
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::occupancy::{read_pwm_to_ewm_with, EwmOptions};
+///
+/// let at_30 = read_pwm_to_ewm_with("motifs.meme", &EwmOptions::default().with_temperature(30.0))?;
+/// let at_37 = read_pwm_to_ewm_with("motifs.meme", &EwmOptions::default().with_temperature(37.0))?;
+/// ```
+pub fn read_pwm_to_ewm_with(
+    filename: &str,
+    options: &EwmOptions,
+) -> Result<EWMCollection, MotifError> {
+    let start = Instant::now();
+    // each motif is converted right after it is parsed, on the same thread
+    let ewms = into_collection(
+        filename,
+        parse_meme(filename, |pwm| pwm_to_ewm_with(&pwm, options))?,
+    );
+
+    if ewms.is_empty() {
+        return Err(MotifError::InvalidFileFormat("No PWMs found".into()));
+    }
+
+    debug!(
+        file = filename,
+        motifs = ewms.len(),
+        elapsed_ms = start.elapsed().as_millis() as u64,
+        "read EWMs"
+    );
+    Ok(ewms)
+}
+
+/// PWMs of a motif database converted to EWMs only when first needed, so a scan of a few
+/// motifs of a large database does not pay for converting every matrix.
+///
+/// Conversions are memoized, and the collection can be shared between threads; two
+/// threads asking for the same unconverted motif at once may both convert it, but keep one
+/// result.
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::occupancy::{EwmOptions, LazyEwmCollection};
+///
+/// let motifs = LazyEwmCollection::read("JASPAR2024.meme", &EwmOptions::default())?;
+/// let nrl = motifs.get("MA0842.1")?.unwrap();
+/// let subset = motifs.collect(["MA0842.1", "MA1573.1"])?;
+/// assert_eq!(motifs.converted(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct LazyEwmCollection {
+    pwms: PWMCollection,
+    options: EwmOptions,
+    ewms: HashMap<String, OnceLock<EWM>>,
+}
+
+impl LazyEwmCollection {
+    /// Wraps PWMs to be converted with `options` on first use
+    pub fn new(pwms: PWMCollection, options: EwmOptions) -> Self {
+        let ewms = pwms
+            .keys()
+            .map(|id| (id.clone(), OnceLock::new()))
+            .collect();
+        LazyEwmCollection {
+            pwms,
+            options,
+            ewms,
+        }
+    }
+
+    /// Reads the PWMs of a MEME file without converting them; see `read_pwm_files()`
+    pub fn read(filename: &str, options: &EwmOptions) -> Result<Self, MotifError> {
+        Ok(LazyEwmCollection::new(read_pwm_files(filename)?, *options))
+    }
+
+    /// Number of motifs
+    pub fn len(&self) -> usize {
+        self.pwms.len()
+    }
+
+    /// Whether there are no motifs
+    pub fn is_empty(&self) -> bool {
+        self.pwms.is_empty()
+    }
+
+    /// IDs of the motifs, in arbitrary order
+    pub fn ids(&self) -> impl Iterator<Item = &String> {
+        self.pwms.keys()
+    }
+
+    /// The PWMs, e.g. for filtering on information content before any conversion
+    pub fn pwms(&self) -> &PWMCollection {
+        &self.pwms
+    }
+
+    /// Number of motifs converted so far
+    pub fn converted(&self) -> usize {
+        self.ewms.values().filter(|ewm| ewm.get().is_some()).count()
+    }
+
+    /// The EWM of `id`, converting it on the first call; `None` if there is no such motif
+    ///
+    /// # Errors
+    /// * `MotifError::DataError` - If the conversion fails; see `pwm_to_ewm_with()`
+    pub fn get(&self, id: &str) -> Result<Option<&EWM>, MotifError> {
+        let (Some(pwm), Some(cell)) = (self.pwms.get(id), self.ewms.get(id)) else {
+            return Ok(None);
+        };
+        if let Some(ewm) = cell.get() {
+            return Ok(Some(ewm));
+        }
+        let ewm = pwm_to_ewm_with(pwm, &self.options).with_motif(id)?;
+        Ok(Some(cell.get_or_init(|| ewm)))
+    }
+
+    /// Converts the motifs `ids` in parallel and returns them as an EWM collection
+    ///
+    /// # Errors
+    /// * `MotifError::InvalidInput` - If an ID is not in the collection
+    /// * `MotifError::DataError` - If a conversion fails
+    pub fn collect<'a, I>(&self, ids: I) -> Result<EWMCollection, MotifError>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let ids: Vec<&str> = ids.into_iter().collect();
+        // on the polars pool, as in `parse_meme()`
+        POOL.install(|| {
+            ids.into_par_iter()
+                .map(|id| {
+                    let ewm = self.get(id)?.ok_or_else(|| {
+                        MotifError::InvalidInput(format!("motif '{}' not found", id))
+                    })?;
+                    Ok((id.to_string(), ewm.clone()))
+                })
                 .collect()
-                .map_err(|e| MotifError::DataError(e.to_string()))?;
+        })
+    }
 
-            Ok((id, normalized))
+    /// Converts every motif not converted yet and returns the full EWM collection
+    ///
+    /// # Errors
+    /// * `MotifError::DataError` - If a conversion fails
+    pub fn into_collection(self) -> Result<EWMCollection, MotifError> {
+        let LazyEwmCollection {
+            pwms,
+            options,
+            mut ewms,
+        } = self;
+        let motifs: Vec<(String, PWM, Option<EWM>)> = pwms
+            .into_iter()
+            .map(|(id, pwm)| {
+                let ewm = ewms.remove(&id).and_then(OnceLock::into_inner);
+                (id, pwm, ewm)
+            })
+            .collect();
+        POOL.install(|| {
+            motifs
+                .into_par_iter()
+                .map(|(id, pwm, ewm)| {
+                    let ewm = match ewm {
+                        Some(ewm) => ewm,
+                        None => pwm_to_ewm_with(&pwm, &options).with_motif(&id)?,
+                    };
+                    Ok((id, ewm))
+                })
+                .collect()
         })
-        .collect::<Result<HashMap<_, _>, MotifError>>()?;
+    }
+}
 
-    Ok(ewms)
+/// Converts a Position Weight Matrix to an Energy Weight Matrix
+///
+/// Adds `PSEUDOCOUNT` to every probability, normalizes each position by its most likely
+/// base and converts to energies with -RT ln(p / p_max), as `read_pwm_to_ewm` does for
+/// MEME files. Use it for PWMs read from other formats with `formats::read_motifs`.
+///
+/// # Arguments
+/// * `pwm` - Position Weight Matrix with columns A, C, G, T
+///
+/// # Returns
+/// * `Result<EWM, MotifError>` - Energy Weight Matrix with columns A, C, G, T
+///
+/// # Errors
+/// * `MotifError::DataError` - If the matrix lacks a nucleotide column or conversion fails
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::formats::{read_motifs, MotifFormat};
+/// use tf_binding_rs::occupancy::pwm_to_ewm;
+///
+/// let pwms = read_motifs("JASPAR2024.jaspar", MotifFormat::Jaspar).unwrap();
+/// let ewm = pwm_to_ewm(&pwms["MA0842.1"]).unwrap();
+/// ```
+pub fn pwm_to_ewm(pwm: &PWM) -> Result<EWM, MotifError> {
+    pwm_to_ewm_with(pwm, &EwmOptions::default())
+}
+
+/// Converts a Position Weight Matrix to an Energy Weight Matrix with the given RT and
+/// pseudocount; see `pwm_to_ewm()` and `EwmOptions`
+///
+/// # Errors
+/// * `MotifError::DataError` - If the matrix lacks a nucleotide column or conversion fails
+pub fn pwm_to_ewm_with(pwm: &PWM, options: &EwmOptions) -> Result<EWM, MotifError> {
+    let EwmOptions { rt, pseudocount } = *options;
+    pwm.clone()
+        .lazy()
+        .select([
+            (col("A") + lit(pseudocount)).alias("A_pseudo"),
+            (col("C") + lit(pseudocount)).alias("C_pseudo"),
+            (col("G") + lit(pseudocount)).alias("G_pseudo"),
+            (col("T") + lit(pseudocount)).alias("T_pseudo"),
+        ])
+        .with_column(
+            max_expr(
+                max_expr(col("A_pseudo"), col("C_pseudo")),
+                max_expr(col("G_pseudo"), col("T_pseudo")),
+            )
+            .alias("max_val"),
+        )
+        .select([
+            (col("A_pseudo") / col("max_val")).alias("A_norm"),
+            (col("C_pseudo") / col("max_val")).alias("C_norm"),
+            (col("G_pseudo") / col("max_val")).alias("G_norm"),
+            (col("T_pseudo") / col("max_val")).alias("T_norm"),
+        ])
+        .select([
+            (-lit(rt) * col("A_norm").log(std::f64::consts::E)).alias("A"),
+            (-lit(rt) * col("C_norm").log(std::f64::consts::E)).alias("C"),
+            (-lit(rt) * col("G_norm").log(std::f64::consts::E)).alias("G"),
+            (-lit(rt) * col("T_norm").log(std::f64::consts::E)).alias("T"),
+        ])
+        .collect()
+        .map_err(|e| MotifError::DataError(e.to_string()))
+}
+
+/// An energy matrix prepared for scanning both strands of a sequence
+///
+/// Holds the rows of the EWM and of its reverse complement, so the reverse strand is scored by
+/// scanning the forward sequence with the reverse-complement matrix instead of building and
+/// scanning the reverse-complement sequence. Build it once per motif and reuse it for every
+/// sequence.
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::occupancy::StrandedEwm;
+///
+/// let matrix = StrandedEwm::new(&ewm).unwrap();
+/// for seq in sequences {
+///     let (fwd_occ, rev_occ) = matrix.occupancy_landscape(seq, 9.0).unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct StrandedEwm {
+    pub(crate) forward: Vec<[f64; 4]>,
+    pub(crate) reverse: Vec<[f64; 4]>,
+}
+
+/// Index of a nucleotide in the A, C, G, T columns of a matrix
+fn base_index(base: u8) -> Result<usize, MotifError> {
+    match base {
+        b'A' => Ok(0),
+        b'C' => Ok(1),
+        b'G' => Ok(2),
+        b'T' => Ok(3),
+        _ => Err(MotifError::InvalidInput(format!(
+            "Invalid nucleotide: {}",
+            base as char
+        ))),
+    }
+}
+
+/// Bases an IUPAC nucleotide code stands for, as indices into A, C, G, T
+fn iupac_bases(base: u8) -> Option<&'static [usize]> {
+    Some(match base {
+        b'A' => &[0],
+        b'C' => &[1],
+        b'G' => &[2],
+        b'T' => &[3],
+        b'R' => &[0, 2],
+        b'Y' => &[1, 3],
+        b'S' => &[1, 2],
+        b'W' => &[0, 3],
+        b'K' => &[2, 3],
+        b'M' => &[0, 1],
+        b'B' => &[1, 2, 3],
+        b'D' => &[0, 2, 3],
+        b'H' => &[0, 1, 3],
+        b'V' => &[0, 1, 2],
+        b'N' => &[0, 1, 2, 3],
+        _ => return None,
+    })
+}
+
+/// A DNA sequence encoded once as A, C, G, T base indices, for scoring the same sequence
+/// many times, e.g. over a sweep of chemical potentials or for in silico mutagenesis, without
+/// validating and encoding it on every call.
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::occupancy::{EncodedSeq, StrandedEwm};
+///
+/// let matrix = StrandedEwm::new(&ewm)?;
+/// let mut seq = EncodedSeq::new("ATCGATCGTAGCTACGT")?;
+/// let reference = matrix.energy_landscape_encoded(&seq)?;
+/// seq.substitute(4, b'T')?;
+/// let mutant = matrix.energy_landscape_encoded(&seq)?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodedSeq {
+    bases: Vec<usize>,
+}
+
+impl EncodedSeq {
+    /// Encodes a sequence of uppercase A, C, G, T
+    ///
+    /// # Errors
+    /// * `MotifError::InvalidInput` - If the sequence contains any other character
+    pub fn new(seq: &str) -> Result<Self, MotifError> {
+        Ok(EncodedSeq {
+            bases: seq.bytes().map(base_index).collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Length of the sequence
+    pub fn len(&self) -> usize {
+        self.bases.len()
+    }
+
+    /// Whether the sequence is empty
+    pub fn is_empty(&self) -> bool {
+        self.bases.is_empty()
+    }
+
+    /// Base indices (A = 0, C = 1, G = 2, T = 3)
+    pub fn bases(&self) -> &[usize] {
+        &self.bases
+    }
+
+    /// Replaces the base at `position` by `base` (one of A, C, G, T)
+    ///
+    /// # Errors
+    /// * `MotifError::InvalidSequence` - If `position` is past the end of the sequence
+    /// * `MotifError::InvalidInput` - If `base` is not A, C, G or T
+    pub fn substitute(&mut self, position: usize, base: u8) -> Result<(), MotifError> {
+        let index = base_index(base)?;
+        let slot = self.bases.get_mut(position).ok_or_else(|| {
+            MotifError::invalid_sequence(position, "position is past the end of the sequence")
+        })?;
+        *slot = index;
+        Ok(())
+    }
+
+    /// Checks that a motif of `motif_len` fits the sequence
+    fn check_fits(&self, motif_len: usize) -> Result<(), MotifError> {
+        if self.len() < motif_len {
+            return Err(MotifError::invalid_sequence(
+                self.len(),
+                format!("sequence is shorter than the motif ({} bp)", motif_len),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// New energies of the windows overlapping a point mutation; see
+/// `StrandedEwm::rescore_mutation()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowUpdate {
+    /// Position of the first window overlapping the mutation
+    pub start: usize,
+    /// Forward-strand energies of the windows from `start` on
+    pub forward: Vec<f64>,
+    /// Reverse-strand energies of the same windows
+    pub reverse: Vec<f64>,
+}
+
+impl WindowUpdate {
+    /// Position after the last window overlapping the mutation
+    pub fn end(&self) -> usize {
+        self.start + self.forward.len()
+    }
+
+    /// Writes the new energies into the landscape they were computed from, giving the
+    /// landscape of the mutant
+    pub fn apply(&self, landscape: &mut StrandScores) {
+        landscape.0[self.start..self.end()].copy_from_slice(&self.forward);
+        landscape.1[self.start..self.end()].copy_from_slice(&self.reverse);
+    }
+}
+
+/// Occupancies `1 / (1 + exp(energy - mu))` of energies on both strands, e.g. of one
+/// `energy_landscape()` at every chemical potential of a sweep
+pub fn occupancies(energies: &StrandScores, mu: f64) -> StrandScores {
+    let occupancy = |&s: &f64| 1.0 / (1.0 + (s - mu).exp());
+    (
+        energies.0.iter().map(occupancy).collect(),
+        energies.1.iter().map(occupancy).collect(),
+    )
+}
+
+/// Encodes a sequence as base indices, checking that it fits a motif of `motif_len`
+pub(crate) fn encode_sequence(seq: &str, motif_len: usize) -> Result<Vec<usize>, MotifError> {
+    if seq.len() < motif_len {
+        return Err(MotifError::invalid_sequence(
+            seq.len(),
+            format!("sequence is shorter than the motif ({} bp)", motif_len),
+        ));
+    }
+    seq.bytes().map(base_index).collect()
+}
+
+impl StrandedEwm {
+    /// Prepares an Energy Weight Matrix for scanning
+    ///
+    /// # Errors
+    /// * `MotifError::DataError` - If a nucleotide column is missing or not of type f64
+    pub fn new(ewm: &EWM) -> Result<Self, MotifError> {
+        let forward = matrix_rows(ewm)?;
+        let reverse = forward
+            .iter()
+            .rev()
+            .map(|&[a, c, g, t]| [t, g, c, a])
+            .collect();
+        Ok(StrandedEwm { forward, reverse })
+    }
+
+    /// Width of the motif
+    pub fn len(&self) -> usize {
+        self.forward.len()
+    }
+
+    /// Whether the matrix has no positions
+    pub fn is_empty(&self) -> bool {
+        self.forward.is_empty()
+    }
+
+    /// Lowest and highest energy of any window, i.e. of the best and the worst possible site
+    pub fn energy_range(&self) -> (f64, f64) {
+        self.forward
+            .iter()
+            .fold((0.0, 0.0), |(lowest, highest), row| {
+                (
+                    lowest + row.iter().copied().fold(f64::INFINITY, f64::min),
+                    highest + row.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+                )
+            })
+    }
+
+    /// Binding energy of the window of `seq` starting at `position`, on the forward strand
+    /// or, with `reverse`, on the reverse strand; the same value as in `energy_landscape()`
+    ///
+    /// # Errors
+    /// * `MotifError::InvalidSequence` - If the window extends past the end of the sequence
+    /// * `MotifError::InvalidInput` - If the window contains characters other than A, C, G, T
+    pub fn window_energy(
+        &self,
+        seq: &str,
+        position: usize,
+        reverse: bool,
+    ) -> Result<f64, MotifError> {
+        let window = seq.get(position..position + self.len()).ok_or_else(|| {
+            MotifError::invalid_sequence(position, "window extends past the end of the sequence")
+        })?;
+        let kmer = encode_sequence(window, self.len())?;
+        Ok(if reverse {
+            self.reverse_energy(&kmer)
+        } else {
+            self.forward_energy(&kmer)
+        })
+    }
+
+    /// Energy of a forward-strand window, given as base indices
+    pub(crate) fn forward_energy(&self, kmer: &[usize]) -> f64 {
+        (0..kmer.len()).map(|i| self.forward[i][kmer[i]]).sum()
+    }
+
+    /// Energy of the reverse strand of a window, given as base indices
+    pub(crate) fn reverse_energy(&self, kmer: &[usize]) -> f64 {
+        // sum in the order of the reverse strand's own positions, which keeps the result
+        // identical to scoring the reverse-complement sequence with the forward matrix
+        (0..kmer.len())
+            .rev()
+            .map(|i| self.reverse[i][kmer[i]])
+            .sum()
+    }
+
+    /// Binding energies of every window on both strands; see `energy_landscape()`
+    pub fn energy_landscape(&self, seq: &str) -> Result<(Vec<f64>, Vec<f64>), MotifError> {
+        Ok(self.score_encoded(&encode_sequence(seq, self.len())?))
+    }
+
+    /// Binding energies of every window of an encoded sequence on both strands; the same
+    /// values as `energy_landscape()` without encoding the sequence again
+    ///
+    /// # Errors
+    /// * `MotifError::InvalidSequence` - If the sequence is shorter than the motif
+    pub fn energy_landscape_encoded(&self, seq: &EncodedSeq) -> Result<StrandScores, MotifError> {
+        seq.check_fits(self.len())?;
+        Ok(self.score_encoded(seq.bases()))
+    }
+
+    /// Occupancies of every window of an encoded sequence on both strands; see
+    /// `energy_landscape_encoded()` and `occupancies()`
+    ///
+    /// # Errors
+    /// * `MotifError::InvalidSequence` - If the sequence is shorter than the motif
+    pub fn occupancy_landscape_encoded(
+        &self,
+        seq: &EncodedSeq,
+        mu: f64,
+    ) -> Result<StrandScores, MotifError> {
+        Ok(occupancies(&self.energy_landscape_encoded(seq)?, mu))
+    }
+
+    /// Energies of the windows changed by mutating `position` of `seq` to `base`, given the
+    /// energy landscape of `seq` from `energy_landscape_encoded()`.
+    ///
+    /// Only the windows overlapping the mutation change, and each is updated by the energy
+    /// difference of the two bases at its offset, so one mutant costs O(W) for a motif of
+    /// width W and a saturation mutagenesis of a sequence of length L costs O(L·W) instead
+    /// of the O(L²·W) of rescoring every mutant. The energies equal a full rescoring of the
+    /// mutant up to floating-point rounding.
+    ///
+    /// # Errors
+    /// * `MotifError::InvalidSequence` - If `position` is past the end of the sequence
+    /// * `MotifError::InvalidInput` - If `base` is not A, C, G or T
+    /// * `MotifError::InvalidParameter` - If `landscape` does not have one energy per window
+    ///   of `seq`
+    ///
+    /// # Example
+    /// ```ignore
+    /// use tf_binding_rs::occupancy::{EncodedSeq, StrandedEwm};
+    ///
+    /// let seq = EncodedSeq::new("ATCGATCGTAGCTACGT")?;
+    /// let reference = matrix.energy_landscape_encoded(&seq)?;
+    /// let update = matrix.rescore_mutation(&seq, &reference, 4, b'T')?;
+    /// let mut mutant = reference.clone();
+    /// update.apply(&mut mutant);
+    /// ```
+    pub fn rescore_mutation(
+        &self,
+        seq: &EncodedSeq,
+        landscape: &StrandScores,
+        position: usize,
+        base: u8,
+    ) -> Result<WindowUpdate, MotifError> {
+        let new = base_index(base)?;
+        let old = *seq.bases().get(position).ok_or_else(|| {
+            MotifError::invalid_sequence(position, "position is past the end of the sequence")
+        })?;
+        seq.check_fits(self.len())?;
+        let n_windows = seq.len() - self.len() + 1;
+        if landscape.0.len() != n_windows || landscape.1.len() != n_windows {
+            return Err(MotifError::invalid_parameter(
+                "landscape",
+                landscape.0.len(),
+                format!("expected {} windows of the sequence", n_windows),
+            ));
+        }
+
+        let start = (position + 1).saturating_sub(self.len());
+        let end = (position + 1).min(n_windows);
+        let (forward, reverse) = (start..end)
+            .map(|window| {
+                let offset = position - window;
+                (
+                    landscape.0[window] + self.forward[offset][new] - self.forward[offset][old],
+                    landscape.1[window] + self.reverse[offset][new] - self.reverse[offset][old],
+                )
+            })
+            .unzip();
+        Ok(WindowUpdate {
+            start,
+            forward,
+            reverse,
+        })
+    }
+
+    /// Energies of the windows of base indices at least as long as the motif
+    fn score_encoded(&self, encoded: &[usize]) -> StrandScores {
+        let windows = encoded.windows(self.len());
+        let fscores = windows
+            .clone()
+            .map(|kmer| self.forward_energy(kmer))
+            .collect();
+        let rscores = windows.map(|kmer| self.reverse_energy(kmer)).collect();
+        (fscores, rscores)
+    }
+
+    /// Binding energies of every window on both strands, scoring IUPAC ambiguity codes (N,
+    /// R, Y, ...) with the mean energy of the bases they stand for; the same values as
+    /// `energy_landscape()` on sequences of A, C, G and T only
+    ///
+    /// # Errors
+    /// * `MotifError::InvalidSequence` - If the sequence is shorter than the motif
+    /// * `MotifError::InvalidInput` - If the sequence contains a character that is not an
+    ///   IUPAC nucleotide code
+    pub fn lenient_energy_landscape(&self, seq: &str) -> Result<StrandScores, MotifError> {
+        if seq.len() < self.len() {
+            return Err(MotifError::invalid_sequence(
+                seq.len(),
+                format!("sequence is shorter than the motif ({} bp)", self.len()),
+            ));
+        }
+        let codes = seq
+            .bytes()
+            .map(|base| {
+                iupac_bases(base).ok_or_else(|| {
+                    MotifError::InvalidInput(format!("Invalid nucleotide: {}", base as char))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let energy = |row: &[f64; 4], bases: &[usize]| {
+            bases.iter().map(|&b| row[b]).sum::<f64>() / bases.len() as f64
+        };
+        let windows = codes.windows(self.len());
+        let fscores = windows
+            .clone()
+            .map(|window| {
+                (0..window.len())
+                    .map(|i| energy(&self.forward[i], window[i]))
+                    .sum()
+            })
+            .collect();
+        let rscores = windows
+            .map(|window| {
+                (0..window.len())
+                    .rev()
+                    .map(|i| energy(&self.reverse[i], window[i]))
+                    .sum()
+            })
+            .collect();
+        Ok((fscores, rscores))
+    }
+
+    /// Occupancies of every window on both strands; see `occupancy_landscape()`
+    pub fn occupancy_landscape(
+        &self,
+        seq: &str,
+        mu: f64,
+    ) -> Result<(Vec<f64>, Vec<f64>), MotifError> {
+        let (fscores, rscores) = self.energy_landscape(seq)?;
+        let occupancy = |s: f64| 1.0 / (1.0 + (s - mu).exp());
+        Ok((
+            fscores.into_iter().map(occupancy).collect(),
+            rscores.into_iter().map(occupancy).collect(),
+        ))
+    }
 }
 
 /// Scans both strands of a sequence with an energy matrix to compute binding energies
 ///
 /// This function calculates the energy score for each possible k-mer in the sequence on both
-/// forward and reverse strands. For each position, it extracts the k-mer subsequence and
-/// calculates the total energy score by summing individual nucleotide contributions.
+/// forward and reverse strands. Each k-mer is scored with the matrix for the forward strand
+/// and with its reverse complement for the reverse strand, summing individual nucleotide
+/// contributions. When scanning many sequences with the same motif, build a `StrandedEwm`
+/// once instead.
 ///
 /// # Arguments
 /// * `seq` - The DNA sequence to scan
@@ -243,6 +1035,8 @@ pub fn read_pwm_to_ewm(filename: &str) -> Result<EWMCollection, MotifError> {
 /// * `Result<(Vec<f64>, Vec<f64>), MotifError>` - A tuple containing forward and reverse strand scores
 ///
 /// # Errors
+/// * `MotifError::InvalidSequence` - If the sequence is shorter than the motif
+/// * `MotifError::InvalidInput` - If the sequence contains characters other than A, C, G, T
 /// * `MotifError::DataError` - If there are issues extracting values from the EWM DataFrame
 ///
 /// # Example
@@ -255,42 +1049,7 @@ pub fn read_pwm_to_ewm(filename: &str) -> Result<EWMCollection, MotifError> {
 /// println!("Reverse strand scores: {:?}", rev_scores);
 /// ```
 pub fn energy_landscape(seq: &str, ewm: &EWM) -> Result<(Vec<f64>, Vec<f64>), MotifError> {
-    let motif_len = ewm.height();
-    let n_scores = seq.len() - motif_len + 1;
-    let r_seq = reverse_complement(seq)?;
-
-    let mut fscores = vec![0.0; n_scores];
-    let mut rscores = vec![0.0; n_scores];
-
-    for (pos, (fscore, rscore)) in fscores.iter_mut().zip(rscores.iter_mut()).enumerate() {
-        let f_kmer = &seq[pos..pos + motif_len];
-        let r_kmer = &r_seq[pos..pos + motif_len];
-
-        *fscore = (0..motif_len)
-            .map(|i| {
-                ewm.column(&f_kmer[i..i + 1])
-                    .unwrap()
-                    .get(i)
-                    .unwrap()
-                    .try_extract::<f64>()
-                    .map_err(|e| MotifError::DataError(e.to_string()))
-            })
-            .sum::<Result<f64, MotifError>>()?;
-
-        *rscore = (0..motif_len)
-            .map(|i| {
-                ewm.column(&r_kmer[i..i + 1])
-                    .unwrap()
-                    .get(i)
-                    .unwrap()
-                    .try_extract::<f64>()
-                    .map_err(|e| MotifError::DataError(e.to_string()))
-            })
-            .sum::<Result<f64, MotifError>>()?;
-    }
-
-    rscores.reverse();
-    Ok((fscores, rscores))
+    StrandedEwm::new(ewm)?.energy_landscape(seq)
 }
 
 /// Computes the occupancy landscape by scanning sequence with the energy matrix
@@ -299,7 +1058,7 @@ pub fn energy_landscape(seq: &str, ewm: &EWM) -> Result<(Vec<f64>, Vec<f64>), Mo
 /// 1. Computing energy scores using `energy_landscape()`
 /// 2. Converting energy scores to occupancy probabilities using the formula:
 ///    occupancy = 1 / (1 + exp(energy - mu))
-/// where mu is the chemical potential of the transcription factor.
+///    where mu is the chemical potential of the transcription factor.
 ///
 /// # Arguments
 /// * `seq` - The DNA sequence to scan
@@ -326,26 +1085,412 @@ pub fn occupancy_landscape(
     ewm: &EWM,
     mu: f64,
 ) -> Result<(Vec<f64>, Vec<f64>), MotifError> {
-    let (fscores, rscores) = energy_landscape(seq, ewm)?;
+    StrandedEwm::new(ewm)?.occupancy_landscape(seq, mu)
+}
 
-    let foccupancies: Vec<f64> = fscores
-        .into_iter()
-        .map(|s| 1.0 / (1.0 + (s - mu).exp()))
-        .collect();
+/// A collection of energy matrices stacked for scanning a sequence with all motifs in one pass
+///
+/// The rows of every matrix and of its reverse complement are stored contiguously. The kernel
+/// encodes the sequence once and walks its positions a single time, scoring every motif that
+/// fits at each position, instead of making one full pass over the sequence per motif. With
+/// hundreds of motifs this keeps the sequence window and the matrices in cache. Motifs are kept
+/// in sorted ID order.
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::occupancy::MotifBank;
+///
+/// let bank = MotifBank::new(&ewm_collection).unwrap();
+/// let landscape = bank.total_landscape("ATCGATCGATCG", 9.0).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct MotifBank {
+    ids: Vec<String>,
+    offsets: Vec<usize>,
+    lengths: Vec<usize>,
+    forward: Vec<[f64; 4]>,
+    reverse: Vec<[f64; 4]>,
+    /// "{ID}_F" and "{ID}_R" landscape column names, built once rather than per sequence
+    column_names: Vec<(PlSmallStr, PlSmallStr)>,
+}
 
-    let roccupancies: Vec<f64> = rscores
-        .into_iter()
-        .map(|s| 1.0 / (1.0 + (s - mu).exp()))
-        .collect();
+/// Values landscapes are stored in; the kernel always computes in f64
+trait LandscapeValue: Copy + Default {
+    fn from_f64(value: f64) -> Self;
+    fn column(name: PlSmallStr, values: &[Self]) -> Column;
+}
+
+impl LandscapeValue for f64 {
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn column(name: PlSmallStr, values: &[Self]) -> Column {
+        Float64Chunked::from_slice(name, values).into_column()
+    }
+}
+
+impl LandscapeValue for f32 {
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+
+    fn column(name: PlSmallStr, values: &[Self]) -> Column {
+        Float32Chunked::from_slice(name, values).into_column()
+    }
+}
+
+/// Scratch space of `MotifBank::total_landscape_into()`, reused across sequences so
+/// scanning many sequences does not allocate per-motif landscapes for each of them
+#[derive(Debug, Clone, Default)]
+pub struct LandscapeBuffer {
+    encoded: Vec<usize>,
+    scores: Vec<StrandScores>,
+}
+
+impl MotifBank {
+    /// Stacks the matrices of a collection
+    ///
+    /// # Errors
+    /// * `MotifError::DataError` - If a nucleotide column is missing or not of type f64
+    pub fn new(ewms: &EWMCollection) -> Result<Self, MotifError> {
+        let mut ids: Vec<String> = ewms.keys().cloned().collect();
+        ids.sort();
+
+        let mut bank = MotifBank {
+            offsets: Vec::with_capacity(ids.len()),
+            lengths: Vec::with_capacity(ids.len()),
+            forward: Vec::new(),
+            reverse: Vec::new(),
+            column_names: Vec::with_capacity(ids.len()),
+            ids: Vec::new(),
+        };
+        for id in &ids {
+            let matrix = StrandedEwm::new(&ewms[id]).with_motif(id)?;
+            bank.offsets.push(bank.forward.len());
+            bank.lengths.push(matrix.len());
+            bank.column_names
+                .push((format!("{}_F", id).into(), format!("{}_R", id).into()));
+            bank.forward.extend(matrix.forward);
+            bank.reverse.extend(matrix.reverse);
+        }
+        bank.ids = ids;
+        Ok(bank)
+    }
+
+    /// Motif IDs, in the order of the landscapes returned by the kernel
+    pub fn ids(&self) -> &[String] {
+        &self.ids
+    }
+
+    /// Binding energies of every motif on both strands, in `ids()` order; each entry is the
+    /// result of `energy_landscape()` for that motif
+    ///
+    /// # Errors
+    /// * `MotifError::InvalidSequence` - If the sequence is shorter than a motif
+    /// * `MotifError::InvalidInput` - If the sequence contains characters other than A, C, G, T
+    pub fn energy_landscapes(&self, seq: &str) -> Result<Vec<StrandScores>, MotifError> {
+        let longest = self.lengths.iter().copied().max().unwrap_or(0);
+        Ok(self.score_encoded(&encode_sequence(seq, longest)?, |energy| energy))
+    }
+
+    /// Binding energies of every motif on an encoded sequence, in `ids()` order; the same
+    /// values as `energy_landscapes()` without encoding the sequence again
+    ///
+    /// # Errors
+    /// * `MotifError::InvalidSequence` - If the sequence is shorter than a motif
+    pub fn energy_landscapes_encoded(
+        &self,
+        seq: &EncodedSeq,
+    ) -> Result<Vec<StrandScores>, MotifError> {
+        seq.check_fits(self.lengths.iter().copied().max().unwrap_or(0))?;
+        Ok(self.score_encoded(seq.bases(), |energy| energy))
+    }
+
+    /// Binding energies of every motif on both strands in single precision, in `ids()`
+    /// order; see `Precision` and `energy_landscapes()`
+    ///
+    /// # Errors
+    /// * See `energy_landscapes()`
+    pub fn energy_landscapes_f32(&self, seq: &str) -> Result<Vec<StrandScores32>, MotifError> {
+        let longest = self.lengths.iter().copied().max().unwrap_or(0);
+        Ok(self.score_encoded(&encode_sequence(seq, longest)?, |energy| energy as f32))
+    }
+
+    /// Occupancies of every motif on both strands in single precision, in `ids()` order
+    ///
+    /// # Errors
+    /// * See `energy_landscapes()`
+    pub fn occupancy_landscapes_f32(
+        &self,
+        seq: &str,
+        mu: f64,
+    ) -> Result<Vec<StrandScores32>, MotifError> {
+        let longest = self.lengths.iter().copied().max().unwrap_or(0);
+        let encoded = encode_sequence(seq, longest)?;
+        Ok(self.score_encoded(&encoded, |energy| {
+            (1.0 / (1.0 + (energy - mu).exp())) as f32
+        }))
+    }
+
+    /// `value(energy)` of every window of every motif on base indices at least as long as the
+    /// longest motif
+    fn score_encoded<T, F>(&self, encoded: &[usize], value: F) -> Vec<(Vec<T>, Vec<T>)>
+    where
+        T: Copy + Default,
+        F: Fn(f64) -> T,
+    {
+        let mut landscapes = Vec::new();
+        self.score_padded(encoded, &mut landscapes, value);
+        for ((fscores, rscores), len) in landscapes.iter_mut().zip(&self.lengths) {
+            let n_scores = encoded.len() - len + 1;
+            fscores.truncate(n_scores);
+            rscores.truncate(n_scores);
+        }
+        landscapes
+    }
+
+    /// Writes `value(energy)` of every window of every motif into `landscapes`, reusing its
+    /// vectors; every landscape is padded with zeros to the length of the sequence
+    ///
+    /// This is the kernel of both precisions: energies are always summed in f64 and only
+    /// the value stored is converted.
+    fn score_padded<T, F>(
+        &self,
+        encoded: &[usize],
+        landscapes: &mut Vec<(Vec<T>, Vec<T>)>,
+        value: F,
+    ) where
+        T: Copy + Default,
+        F: Fn(f64) -> T,
+    {
+        landscapes.resize_with(self.lengths.len(), Default::default);
+        for (fscores, rscores) in landscapes.iter_mut() {
+            fscores.clear();
+            fscores.resize(encoded.len(), T::default());
+            rscores.clear();
+            rscores.resize(encoded.len(), T::default());
+        }
 
-    Ok((foccupancies, roccupancies))
+        for pos in 0..encoded.len() {
+            for (m, (fscores, rscores)) in landscapes.iter_mut().enumerate() {
+                let len = self.lengths[m];
+                if pos + len > encoded.len() {
+                    continue;
+                }
+                let kmer = &encoded[pos..pos + len];
+                let forward = &self.forward[self.offsets[m]..self.offsets[m] + len];
+                let reverse = &self.reverse[self.offsets[m]..self.offsets[m] + len];
+
+                fscores[pos] = value((0..len).map(|i| forward[i][kmer[i]]).sum());
+                rscores[pos] = value((0..len).rev().map(|i| reverse[i][kmer[i]]).sum());
+            }
+        }
+    }
+
+    /// Calls `f(motif, position, strand, occupancy)` for every window with occupancy strictly
+    /// above `cutoff`, without building landscapes or DataFrames; see `scan_with()`
+    ///
+    /// # Errors
+    /// * See `energy_landscapes()`; errors are returned before `f` is first called
+    pub fn scan_with<F>(&self, seq: &str, mu: f64, cutoff: f64, mut f: F) -> Result<(), MotifError>
+    where
+        F: FnMut(&str, usize, &str, f64),
+    {
+        let longest = self.lengths.iter().copied().max().unwrap_or(0);
+        if seq.len() < longest {
+            return Err(MotifError::invalid_sequence(
+                seq.len(),
+                format!("sequence is shorter than the motif ({} bp)", longest),
+            ));
+        }
+        for base in seq.bytes() {
+            base_index(base)?;
+        }
+
+        let bases = seq.as_bytes();
+        let code = |i: usize| base_index(bases[i]).unwrap_or_default();
+        for pos in 0..bases.len() {
+            for (m, id) in self.ids.iter().enumerate() {
+                let len = self.lengths[m];
+                if pos + len > bases.len() {
+                    continue;
+                }
+                let forward = &self.forward[self.offsets[m]..self.offsets[m] + len];
+                let reverse = &self.reverse[self.offsets[m]..self.offsets[m] + len];
+
+                let fscore: f64 = (0..len).map(|i| forward[i][code(pos + i)]).sum();
+                let rscore: f64 = (0..len).rev().map(|i| reverse[i][code(pos + i)]).sum();
+                for (strand, score) in [("F", fscore), ("R", rscore)] {
+                    let occ = 1.0 / (1.0 + (score - mu).exp());
+                    if occ > cutoff {
+                        f(id, pos, strand, occ);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Occupancies of every motif on both strands, in `ids()` order
+    ///
+    /// # Errors
+    /// * See `energy_landscapes()`
+    pub fn occupancy_landscapes(
+        &self,
+        seq: &str,
+        mu: f64,
+    ) -> Result<Vec<StrandScores>, MotifError> {
+        let longest = self.lengths.iter().copied().max().unwrap_or(0);
+        let encoded = encode_sequence(seq, longest)?;
+        Ok(self.score_encoded(&encoded, |energy| 1.0 / (1.0 + (energy - mu).exp())))
+    }
+
+    /// Combined occupancy landscape of all motifs; see `total_landscape()`
+    ///
+    /// # Errors
+    /// * `MotifError::DataError` - If the DataFrame cannot be created
+    /// * See `energy_landscapes()`
+    pub fn total_landscape(&self, seq: &str, mu: f64) -> Result<DataFrame, MotifError> {
+        self.total_landscape_with(seq, mu, Precision::F64)
+    }
+
+    /// Combined occupancy landscape of all motifs with Float32 columns for
+    /// `Precision::F32`; see `total_landscape()`
+    ///
+    /// # Errors
+    /// * See `total_landscape()`
+    pub fn total_landscape_with(
+        &self,
+        seq: &str,
+        mu: f64,
+        precision: Precision,
+    ) -> Result<DataFrame, MotifError> {
+        match precision {
+            Precision::F64 => self.total_landscape_into(seq, mu, &mut LandscapeBuffer::default()),
+            Precision::F32 => {
+                self.occupancy_frame::<f32>(seq, mu, &mut Vec::new(), &mut Vec::new())
+            }
+        }
+    }
+
+    /// Combined occupancy landscape of all motifs, computed in `buffer`; the same frame as
+    /// `total_landscape()`, but landscapes are scored into the buffer's vectors and copied
+    /// once into the Arrow arrays of the columns, so pass the same buffer for every
+    /// sequence of a batch
+    ///
+    /// # Errors
+    /// * See `total_landscape()`
+    ///
+    /// # Example
+    /// ```ignore
+    /// use tf_binding_rs::occupancy::{LandscapeBuffer, MotifBank};
+    ///
+    /// let bank = MotifBank::new(&ewm_collection).unwrap();
+    /// let mut buffer = LandscapeBuffer::default();
+    /// for seq in &sequences {
+    ///     let landscape = bank.total_landscape_into(seq, 9.0, &mut buffer).unwrap();
+    /// }
+    /// ```
+    pub fn total_landscape_into(
+        &self,
+        seq: &str,
+        mu: f64,
+        buffer: &mut LandscapeBuffer,
+    ) -> Result<DataFrame, MotifError> {
+        self.occupancy_frame::<f64>(seq, mu, &mut buffer.encoded, &mut buffer.scores)
+    }
+
+    /// Occupancy landscape frame of all motifs with columns of type `T`, scored into
+    /// `encoded` and `scores`; windows past the end keep an occupancy of 0
+    fn occupancy_frame<T: LandscapeValue>(
+        &self,
+        seq: &str,
+        mu: f64,
+        encoded: &mut Vec<usize>,
+        scores: &mut Vec<(Vec<T>, Vec<T>)>,
+    ) -> Result<DataFrame, MotifError> {
+        let longest = self.lengths.iter().copied().max().unwrap_or(0);
+        if seq.len() < longest {
+            return Err(MotifError::invalid_sequence(
+                seq.len(),
+                format!("sequence is shorter than the motif ({} bp)", longest),
+            ));
+        }
+        encoded.clear();
+        for base in seq.bytes() {
+            encoded.push(base_index(base)?);
+        }
+        self.score_padded(encoded, scores, |energy| {
+            T::from_f64(1.0 / (1.0 + (energy - mu).exp()))
+        });
+
+        let mut columns: Vec<Column> = Vec::with_capacity(2 * self.ids.len());
+        for ((fname, rname), (fscores, rscores)) in self.column_names.iter().zip(scores.iter()) {
+            columns.push(T::column(fname.clone(), fscores));
+            columns.push(T::column(rname.clone(), rscores));
+        }
+        DataFrame::new(columns).map_err(|e| MotifError::DataError(e.to_string()))
+    }
+
+    /// Combined energy landscape of all motifs; see `total_energy_landscape()`
+    ///
+    /// # Errors
+    /// * `MotifError::DataError` - If the DataFrame cannot be created
+    /// * See `energy_landscapes()`
+    pub fn total_energy_landscape(&self, seq: &str) -> Result<DataFrame, MotifError> {
+        let mut columns: Vec<Column> = Vec::with_capacity(2 * self.ids.len());
+        for ((fname, rname), (fscores, rscores)) in
+            self.column_names.iter().zip(self.energy_landscapes(seq)?)
+        {
+            // windows running past the end of the sequence have no energy
+            let pad = |scores: Vec<f64>| {
+                let mut padded: Vec<Option<f64>> = scores.into_iter().map(Some).collect();
+                padded.resize(seq.len(), None);
+                padded
+            };
+            columns.push(Column::new(fname.clone(), pad(fscores)));
+            columns.push(Column::new(rname.clone(), pad(rscores)));
+        }
+        DataFrame::new(columns).map_err(|e| MotifError::DataError(e.to_string()))
+    }
+
+    /// Energies of every window of every motif in long form; see `energy_landscape_table()`
+    ///
+    /// # Errors
+    /// * `MotifError::DataError` - If the DataFrame cannot be created
+    /// * See `energy_landscapes()`
+    pub fn energy_landscape_table(&self, seq: &str) -> Result<DataFrame, MotifError> {
+        let landscapes = self.energy_landscapes(seq)?;
+        let rows: usize = landscapes.iter().map(|(f, r)| f.len() + r.len()).sum();
+        let mut motifs = Vec::with_capacity(rows);
+        let mut positions = Vec::with_capacity(rows);
+        let mut strands = Vec::with_capacity(rows);
+        let mut energies = Vec::with_capacity(rows);
+        for (id, (fscores, rscores)) in self.ids.iter().zip(landscapes) {
+            for (strand, scores) in [("F", fscores), ("R", rscores)] {
+                motifs.extend(std::iter::repeat_n(id.as_str(), scores.len()));
+                positions.extend(0..scores.len() as i32);
+                strands.extend(std::iter::repeat_n(strand, scores.len()));
+                energies.extend(scores);
+            }
+        }
+        df!(
+            "motif" => motifs,
+            "position" => positions,
+            "strand" => strands,
+            "energy" => energies,
+        )
+        .map_err(|e| MotifError::DataError(e.to_string()))
+    }
 }
 
 /// Computes the occupancy landscape for multiple transcription factors
 ///
 /// This function calculates binding probabilities for each TF in the collection and combines
 /// them into a single DataFrame. The results include both forward and reverse strand occupancies
-/// for each TF, with values padded to match the sequence length.
+/// for each TF, with values padded to match the sequence length. All TFs are scored in a single
+/// pass over the sequence with a `MotifBank`; build the bank once to scan many sequences.
 ///
 /// # Arguments
 /// * `seq` - The DNA sequence to scan
@@ -355,10 +1500,12 @@ pub fn occupancy_landscape(
 /// # Returns
 /// * `Result<DataFrame, MotifError>` - DataFrame containing occupancy predictions where:
 ///   - Rows represent positions in the sequence
-///   - Columns are named "{TF_NAME}_F" and "{TF_NAME}_R" for forward/reverse orientations
+///   - Columns are named "{TF_NAME}_F" and "{TF_NAME}_R" for forward/reverse orientations,
+///     in sorted TF order
 ///   - Values indicate predicted occupancy (0-1) at each position
 ///
 /// # Errors
+/// * `MotifError::InvalidSequence` - If the sequence is shorter than a motif
 /// * `MotifError::DataError` - If there are issues creating the DataFrame or calculating occupancies
 ///
 /// # Example
@@ -371,25 +1518,324 @@ pub fn occupancy_landscape(
 /// println!("Combined occupancy landscape:\n{}", landscape);
 /// ```
 pub fn total_landscape(seq: &str, ewms: &EWMCollection, mu: f64) -> Result<DataFrame, MotifError> {
-    let seq_len = seq.len();
-    let mut columns: Vec<Column> = Vec::new();
-    let mut names: Vec<String> = Vec::new();
+    MotifBank::new(ewms)?.total_landscape(seq, mu)
+}
+
+/// A motif skipped by `total_landscape_isolated()` and why
+#[derive(Debug)]
+pub struct MotifFailure {
+    /// ID of the motif
+    pub motif: String,
+    /// The error that would have aborted `total_landscape()`
+    pub error: MotifError,
+}
+
+/// Motifs that failed during an isolated scan, in sorted ID order
+#[derive(Debug, Default)]
+pub struct LandscapeDiagnostics {
+    /// Skipped motifs and their errors
+    pub failures: Vec<MotifFailure>,
+}
+
+impl LandscapeDiagnostics {
+    /// Whether every motif was scanned
+    pub fn is_clean(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// IDs of the skipped motifs
+    pub fn failed_motifs(&self) -> Vec<&str> {
+        self.failures.iter().map(|f| f.motif.as_str()).collect()
+    }
+}
+
+/// `total_landscape()` that skips motifs which cannot be scanned instead of aborting
+///
+/// A malformed matrix or a motif wider than the sequence only drops the columns of that
+/// motif; its error is recorded in the returned diagnostics and the other motifs are
+/// scanned as usual. Problems of the sequence itself still abort, since they would fail
+/// every motif.
+///
+/// # Arguments
+/// * `seq` - The DNA sequence to scan
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are TF names
+/// * `mu` - Chemical potential of the transcription factors
+///
+/// # Returns
+/// * `Result<(DataFrame, LandscapeDiagnostics), MotifError>` - The landscape of the motifs
+///   that could be scanned, in the layout of `total_landscape()`, and the skipped motifs
+///
+/// # Errors
+/// * `MotifError::InvalidInput` - If the sequence contains characters other than A, C, G, T
+/// * `MotifError::DataError` - If the DataFrame cannot be created
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::occupancy::total_landscape_isolated;
+///
+/// let (landscape, diagnostics) = total_landscape_isolated(seq, &ewm_collection, 9.0).unwrap();
+/// for failure in &diagnostics.failures {
+///     eprintln!("skipped {}: {}", failure.motif, failure.error);
+/// }
+/// ```
+pub fn total_landscape_isolated(
+    seq: &str,
+    ewms: &EWMCollection,
+    mu: f64,
+) -> Result<(DataFrame, LandscapeDiagnostics), MotifError> {
+    for base in seq.bytes() {
+        base_index(base)?;
+    }
+
+    let mut ids: Vec<&String> = ewms.keys().collect();
+    ids.sort();
+    let mut columns: Vec<Column> = Vec::with_capacity(2 * ids.len());
+    let mut diagnostics = LandscapeDiagnostics::default();
+    for id in ids {
+        let landscape =
+            StrandedEwm::new(&ewms[id]).and_then(|matrix| matrix.occupancy_landscape(seq, mu));
+        match landscape {
+            Ok((mut focc, mut rocc)) => {
+                focc.resize(seq.len(), 0.0);
+                rocc.resize(seq.len(), 0.0);
+                columns.push(Column::new(format!("{}_F", id).into(), focc));
+                columns.push(Column::new(format!("{}_R", id).into(), rocc));
+            }
+            Err(error) => {
+                warn!(motif = %id, %error, "skipping motif");
+                diagnostics.failures.push(MotifFailure {
+                    motif: id.clone(),
+                    error,
+                });
+            }
+        }
+    }
+    let landscape = DataFrame::new(columns).map_err(|e| MotifError::DataError(e.to_string()))?;
+    Ok((landscape, diagnostics))
+}
+
+/// Computes the binding energy landscape for multiple transcription factors
+///
+/// The energy counterpart of `total_landscape()`, with the same layout: the binding energy
+/// (ddG, in kJ/mol relative to the best site of each motif) of the window starting at every
+/// position, on both strands. Windows that would run past the end of the sequence are null
+/// rather than padded, since an energy of 0 is the strongest possible site. Occupancies
+/// follow as `1 / (1 + exp(energy - mu))` for any chemical potential.
+///
+/// # Arguments
+/// * `seq` - The DNA sequence to scan
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are TF names
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - DataFrame of energies where:
+///   - Rows represent positions in the sequence
+///   - Columns are named "{TF_NAME}_F" and "{TF_NAME}_R" for forward/reverse orientations,
+///     in sorted TF order
+///
+/// # Errors
+/// * `MotifError::InvalidSequence` - If the sequence is shorter than a motif
+/// * `MotifError::InvalidInput` - If the sequence contains characters other than A, C, G, T
+/// * `MotifError::DataError` - If there are issues creating the DataFrame
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::occupancy::total_energy_landscape;
+///
+/// let energies = total_energy_landscape("ATCGATCGTAGCTACGT", &ewm_collection).unwrap();
+/// println!("Energy landscape:\n{}", energies);
+/// ```
+pub fn total_energy_landscape(seq: &str, ewms: &EWMCollection) -> Result<DataFrame, MotifError> {
+    MotifBank::new(ewms)?.total_energy_landscape(seq)
+}
+
+/// Computes the binding energies of every window of every motif as a long table
+///
+/// The same energies as `total_energy_landscape()`, with one row per motif, strand and
+/// window instead of one column per motif and strand, which suits grouping and joins
+/// (e.g. with a hit table on "motif", "position" and "strand"). Only windows that fit in
+/// the sequence are listed.
+///
+/// # Arguments
+/// * `seq` - The DNA sequence to scan
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are motif IDs
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - DataFrame with columns "motif", "position" (0-based
+///   window start), "strand" ("F" or "R") and "energy", sorted by motif, strand and position
+///
+/// # Errors
+/// * See `total_energy_landscape()`
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::occupancy::energy_landscape_table;
+///
+/// let energies = energy_landscape_table("ATCGATCGTAGCTACGT", &ewm_collection).unwrap();
+/// let best = energies.lazy().group_by([col("motif")]).agg([col("energy").min()]);
+/// ```
+pub fn energy_landscape_table(seq: &str, ewms: &EWMCollection) -> Result<DataFrame, MotifError> {
+    MotifBank::new(ewms)?.energy_landscape_table(seq)
+}
+
+/// Streams the windows whose predicted occupancy exceeds a cutoff to a callback
+///
+/// The streaming counterpart of `find_hits()`: `f(motif, position, strand, occupancy)` is
+/// called for every window with occupancy strictly greater than `cutoff`, so hits can be
+/// counted or aggregated on the fly. No landscape, hit list or DataFrame is allocated per
+/// sequence. Windows are visited by position, and by sorted motif ID within a position; the
+/// occupancies are identical to those of `find_hits()`. To scan many sequences, build a
+/// `MotifBank` once and call `MotifBank::scan_with()`.
+///
+/// # Arguments
+/// * `seq` - The DNA sequence to scan
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are motif IDs
+/// * `mu` - Chemical potential of the transcription factors
+/// * `cutoff` - Minimum occupancy for a window to be reported
+/// * `f` - Callback receiving the motif ID, 0-based position, strand ("F" or "R") and
+///   occupancy of each window
+///
+/// # Errors
+/// * `MotifError::InvalidSequence` - If the sequence is shorter than a motif
+/// * `MotifError::InvalidInput` - If the sequence contains characters other than A, C, G, T
+/// * `MotifError::DataError` - If a nucleotide column is missing or not of type f64
+///
+/// # Example
+/// ```ignore
+/// use std::collections::HashMap;
+/// use tf_binding_rs::occupancy::scan_with;
+///
+/// let mut expected: HashMap<String, f64> = HashMap::new();
+/// scan_with("ATCGATCGATCG", &ewm_collection, 9.0, 0.2, |motif, _, _, occ| {
+///     *expected.entry(motif.to_string()).or_default() += occ;
+/// })
+/// .unwrap();
+/// ```
+pub fn scan_with<F>(
+    seq: &str,
+    ewms: &EWMCollection,
+    mu: f64,
+    cutoff: f64,
+    f: F,
+) -> Result<(), MotifError>
+where
+    F: FnMut(&str, usize, &str, f64),
+{
+    MotifBank::new(ewms)?.scan_with(seq, mu, cutoff, f)
+}
 
-    for (name, ewm) in ewms {
-        let (fscores, rscores) = occupancy_landscape(seq, ewm, mu)?;
+/// Finds all windows whose predicted occupancy exceeds a cutoff
+///
+/// This function scans the sequence with every motif in the collection and reports each
+/// window (on either strand) whose occupancy is strictly greater than `cutoff`. Motifs are
+/// processed in sorted ID order so the output is deterministic.
+///
+/// # Arguments
+/// * `seq` - The DNA sequence to scan
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are motif IDs
+/// * `mu` - Chemical potential of the transcription factors
+/// * `cutoff` - Minimum occupancy for a window to be reported
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - DataFrame with one row per hit and columns:
+///   - "motif": Motif ID
+///   - "position": 0-based start of the window on the forward strand
+///   - "strand": "F" or "R"
+///   - "length": Width of the motif
+///   - "occupancy": Predicted occupancy of the window
+///
+/// # Errors
+/// * `MotifError::DataError` - If there are issues calculating occupancies or creating the DataFrame
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::occupancy::find_hits;
+///
+/// let hits = find_hits("ATCGATCG", &ewm_collection, 9.0, 0.2).unwrap();
+/// println!("{}", hits);
+/// ```
+pub fn find_hits(
+    seq: &str,
+    ewms: &EWMCollection,
+    mu: f64,
+    cutoff: f64,
+) -> Result<DataFrame, MotifError> {
+    let mut motif_ids: Vec<&String> = ewms.keys().collect();
+    motif_ids.sort();
+    let matrices = motif_ids
+        .into_iter()
+        .map(|id| Ok((id.as_str(), StrandedEwm::new(&ewms[id]).with_motif(id)?)))
+        .collect::<Result<Vec<_>, MotifError>>()?;
+    let matrices: Vec<(&str, &StrandedEwm)> = matrices.iter().map(|(id, m)| (*id, m)).collect();
 
-        // pad scores to sequence length
-        let amount_to_add = seq_len - fscores.len();
-        let mut fscores_padded = fscores.clone();
-        let mut rscores_padded = rscores.clone();
-        fscores_padded.extend(vec![0.0; amount_to_add]);
-        rscores_padded.extend(vec![0.0; amount_to_add]);
+    stranded_hits(seq, &matrices, mu, cutoff)
+}
 
-        // create series for forward and reverse scores
-        columns.push(Column::new(format!("{}_F", name).into(), fscores_padded));
-        columns.push(Column::new(format!("{}_R", name).into(), rscores_padded));
-        names.push(name.to_string());
+/// `find_hits()` with matrices (or other scoring models) prepared ahead, in the order the
+/// hits are reported
+pub(crate) fn stranded_hits<M: ScoringModel + ?Sized>(
+    seq: &str,
+    matrices: &[(&str, &M)],
+    mu: f64,
+    cutoff: f64,
+) -> Result<DataFrame, MotifError> {
+    let mut motifs: Vec<&str> = Vec::new();
+    let mut positions: Vec<i32> = Vec::new();
+    let mut strands: Vec<&str> = Vec::new();
+    let mut lengths: Vec<i32> = Vec::new();
+    let mut occupancies: Vec<f64> = Vec::new();
+
+    for (motif_id, matrix) in matrices {
+        let (focc, rocc) = matrix.occupancy_landscape(seq, mu).with_motif(motif_id)?;
+
+        for (strand, occs) in [("F", focc), ("R", rocc)] {
+            for (pos, occ) in occs.into_iter().enumerate() {
+                if occ > cutoff {
+                    motifs.push(motif_id);
+                    positions.push(pos as i32);
+                    strands.push(strand);
+                    lengths.push(matrix.width() as i32);
+                    occupancies.push(occ);
+                }
+            }
+        }
     }
 
-    DataFrame::new(columns).map_err(|e| MotifError::DataError(e.to_string()))
+    DataFrame::new(vec![
+        Column::new("motif".into(), motifs),
+        Column::new("position".into(), positions),
+        Column::new("strand".into(), strands),
+        Column::new("length".into(), lengths),
+        Column::new("occupancy".into(), occupancies),
+    ])
+    .map_err(|e| MotifError::DataError(e.to_string()))
+}
+
+/// Computes the total predicted occupancy of each motif over a sequence
+///
+/// The total is the sum of forward and reverse strand occupancies over all windows, i.e.
+/// the expected number of bound molecules of each factor.
+///
+/// # Arguments
+/// * `seq` - The DNA sequence to scan
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are motif IDs
+/// * `mu` - Chemical potential of the transcription factors
+///
+/// # Returns
+/// * `Result<HashMap<String, f64>, MotifError>` - Total occupancy keyed by motif ID
+///
+/// # Errors
+/// * `MotifError::DataError` - If there are issues calculating occupancies
+pub fn total_occupancy(
+    seq: &str,
+    ewms: &EWMCollection,
+    mu: f64,
+) -> Result<HashMap<String, f64>, MotifError> {
+    let bank = MotifBank::new(ewms)?;
+    Ok(bank
+        .ids()
+        .iter()
+        .cloned()
+        .zip(bank.occupancy_landscapes(seq, mu)?)
+        .map(|(name, (focc, rocc))| (name, focc.iter().chain(&rocc).sum()))
+        .collect())
 }
@@ -0,0 +1,472 @@
+use crate::error::MotifError;
+use polars::prelude::*;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// One DataFrame row, serialized as a map from column name to value in column order
+struct Row<'a> {
+    columns: &'a [Column],
+    index: usize,
+}
+
+impl Serialize for Row<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.columns.len()))?;
+        for column in self.columns {
+            let value = column.get(self.index).map_err(serde::ser::Error::custom)?;
+            map.serialize_entry(column.name().as_str(), &Value(value))?;
+        }
+        map.end()
+    }
+}
+
+/// A single cell, serialized as the matching JSON scalar
+struct Value<'a>(AnyValue<'a>);
+
+impl Serialize for Value<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match &self.0 {
+            AnyValue::Null => serializer.serialize_none(),
+            AnyValue::Boolean(b) => serializer.serialize_bool(*b),
+            AnyValue::String(s) => serializer.serialize_str(s),
+            AnyValue::StringOwned(s) => serializer.serialize_str(s),
+            AnyValue::Float32(v) => serializer.serialize_f32(*v),
+            AnyValue::Float64(v) => serializer.serialize_f64(*v),
+            v if v.is_signed_integer() => {
+                serializer.serialize_i64(v.extract::<i64>().unwrap_or_default())
+            }
+            v if v.is_unsigned_integer() => {
+                serializer.serialize_u64(v.extract::<u64>().unwrap_or_default())
+            }
+            v => serializer.serialize_str(&v.to_string()),
+        }
+    }
+}
+
+/// Writes a DataFrame as newline-delimited JSON, one object per row.
+///
+/// Keys follow the column order, so a hit table from `find_hits()` becomes lines like
+/// `{"motif":"NRL_HUMAN.MA0842.1","position":14,"strand":"R","length":11,"occupancy":0.99}`.
+///
+/// # Arguments
+/// * `df` - DataFrame to write, e.g. a hit table
+/// * `writer` - Destination of the JSON lines
+///
+/// # Returns
+/// * `Result<(), MotifError>` - Unit type if successful
+///
+/// # Errors
+/// * `MotifError::Io` - For write failures
+/// * `MotifError::DataError` - If a value cannot be serialized
+pub fn write_jsonl<W: Write>(df: &DataFrame, writer: W) -> Result<(), MotifError> {
+    let mut writer = BufWriter::new(writer);
+    let columns = df.get_columns();
+
+    for index in 0..df.height() {
+        serde_json::to_writer(&mut writer, &Row { columns, index })
+            .map_err(|e| MotifError::DataError(e.to_string()))?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Writes a DataFrame to a newline-delimited JSON file.
+///
+/// # Arguments
+/// * `df` - DataFrame to write, e.g. a hit table
+/// * `filename` - Path of the output file
+///
+/// # Returns
+/// * `Result<(), MotifError>` - Unit type if successful
+///
+/// # Errors
+/// * `MotifError::Io` - For file writing issues
+/// * `MotifError::DataError` - If a value cannot be serialized
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::occupancy::find_hits;
+/// use tf_binding_rs::output::write_jsonl_file;
+///
+/// let hits = find_hits(seq, &ewms, 9.0, 0.2).unwrap();
+/// write_jsonl_file(&hits, "hits.jsonl").unwrap();
+/// ```
+pub fn write_jsonl_file(df: &DataFrame, filename: &str) -> Result<(), MotifError> {
+    write_jsonl(df, File::create(filename)?)
+}
+
+/// Writes genomic hits as a BED6 file (`chrom`, `start`, `end`, `name`, `score`, `strand`).
+///
+/// Expects the columns added by `coords::project_hits()`; `name` is the motif and `score`
+/// the occupancy scaled to 0-1000. Hits without genomic coordinates are skipped.
+///
+/// # Arguments
+/// * `hits` - Hit table with "chrom", "genomic_start", "genomic_end", "genomic_strand",
+///   "motif" and "occupancy" columns
+/// * `filename` - Path of the BED file
+///
+/// # Returns
+/// * `Result<usize, MotifError>` - Number of intervals written
+///
+/// # Errors
+/// * `MotifError::DataError` - If a column is missing or has the wrong type
+/// * `MotifError::Io` - For write failures
+pub fn write_bed(hits: &DataFrame, filename: &str) -> Result<usize, MotifError> {
+    let mut writer = BufWriter::new(File::create(filename)?);
+    let mut written = 0;
+    for (chrom, start, end, rest) in bed_records(hits)? {
+        writeln!(writer, "{}\t{}\t{}\t{}", chrom, start, end, rest)?;
+        written += 1;
+    }
+    writer.flush()?;
+    Ok(written)
+}
+
+/// (chrom, start, end, "name\tscore\tstrand") of every hit with genomic coordinates
+fn bed_records(hits: &DataFrame) -> Result<Vec<(String, i64, i64, String)>, MotifError> {
+    let data_err = |e: PolarsError| MotifError::DataError(e.to_string());
+    let column = |name: &str, dtype: DataType| {
+        hits.column(name)
+            .and_then(|c| c.cast(&dtype))
+            .map_err(data_err)
+    };
+    let (chroms, strands, motifs) = (
+        column("chrom", DataType::String)?,
+        column("genomic_strand", DataType::String)?,
+        column("motif", DataType::String)?,
+    );
+    let (starts, ends) = (
+        column("genomic_start", DataType::Int64)?,
+        column("genomic_end", DataType::Int64)?,
+    );
+    let occupancies = column("occupancy", DataType::Float64)?;
+    let (chroms, strands, motifs) = (
+        chroms.str().map_err(data_err)?,
+        strands.str().map_err(data_err)?,
+        motifs.str().map_err(data_err)?,
+    );
+    let (starts, ends) = (
+        starts.i64().map_err(data_err)?,
+        ends.i64().map_err(data_err)?,
+    );
+    let occupancies = occupancies.f64().map_err(data_err)?;
+
+    let mut records = Vec::with_capacity(hits.height());
+    for i in 0..hits.height() {
+        let (Some(chrom), Some(start), Some(end)) = (chroms.get(i), starts.get(i), ends.get(i))
+        else {
+            continue;
+        };
+        let score = (occupancies.get(i).unwrap_or(0.0) * 1000.0)
+            .round()
+            .clamp(0.0, 1000.0);
+        records.push((
+            chrom.to_string(),
+            start,
+            end,
+            format!(
+                "{}\t{}\t{}",
+                motifs.get(i).unwrap_or("."),
+                score,
+                strands.get(i).unwrap_or(".")
+            ),
+        ));
+    }
+    Ok(records)
+}
+
+/// Side of a heatmap cell in pixels
+const HEATMAP_CELL: usize = 14;
+
+/// Writes a matrix of values in [-1, 1], such as a motif correlation matrix, as an SVG
+/// heatmap.
+///
+/// Rows and columns are drawn in table order, so cluster the matrix first for a clustered
+/// heatmap (`stats::cluster_correlation()`). Values go from blue (-1) through white (0) to
+/// red (1) and are clamped to that range; nulls are grey. Every cell carries its row,
+/// column and value as a tooltip.
+///
+/// # Arguments
+/// * `matrix` - Table with a string column of row labels and numeric value columns
+/// * `label_column` - Name of the row label column, "motif" for correlation matrices
+/// * `filename` - Path of the SVG file
+///
+/// # Returns
+/// * `Result<(), MotifError>` - Unit type if successful
+///
+/// # Errors
+/// * `MotifError::DataError` - If the label column is missing or a value column cannot be
+///   converted to f64
+/// * `MotifError::Io` - For write failures
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::output::write_heatmap_svg;
+/// use tf_binding_rs::stats::{cluster_correlation, occupancy_correlation};
+///
+/// let correlation = cluster_correlation(&occupancy_correlation(&features)?)?;
+/// write_heatmap_svg(&correlation, "motif", "correlation.svg")?;
+/// ```
+pub fn write_heatmap_svg(
+    matrix: &DataFrame,
+    label_column: &str,
+    filename: &str,
+) -> Result<(), MotifError> {
+    let data_err = |e: PolarsError| MotifError::DataError(e.to_string());
+    let labels = matrix
+        .column(label_column)
+        .and_then(|c| c.cast(&DataType::String))
+        .map_err(data_err)?;
+    let labels: Vec<&str> = labels
+        .str()
+        .map_err(data_err)?
+        .into_iter()
+        .map(|label| label.unwrap_or(""))
+        .collect();
+    let mut names = Vec::new();
+    let mut columns = Vec::new();
+    for column in matrix.get_columns() {
+        if column.name() == label_column || !column.dtype().is_numeric() {
+            continue;
+        }
+        let values = column.cast(&DataType::Float64).map_err(data_err)?;
+        names.push(column.name().as_str());
+        columns.push(values.f64().map_err(data_err)?.clone());
+    }
+
+    // room for the labels left of the rows and above the columns, at ~7 px per character
+    let margin = |labels: &[&str]| 10 + 7 * labels.iter().map(|l| l.len()).max().unwrap_or(0);
+    let (left, top) = (margin(&labels), margin(&names));
+    let width = left + HEATMAP_CELL * names.len() + 10;
+    let height = top + HEATMAP_CELL * labels.len() + 10;
+
+    let mut writer = BufWriter::new(File::create(filename)?);
+    writeln!(
+        writer,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="sans-serif" font-size="11">"#,
+        width, height
+    )?;
+    for (j, name) in names.iter().enumerate() {
+        let x = left + HEATMAP_CELL * j + HEATMAP_CELL / 2 + 4;
+        writeln!(
+            writer,
+            r#"<text transform="translate({},{}) rotate(-90)">{}</text>"#,
+            x,
+            top - 4,
+            xml_escape(name)
+        )?;
+    }
+    for (i, label) in labels.iter().enumerate() {
+        let y = top + HEATMAP_CELL * i;
+        writeln!(
+            writer,
+            r#"<text x="{}" y="{}" text-anchor="end">{}</text>"#,
+            left - 4,
+            y + HEATMAP_CELL - 3,
+            xml_escape(label)
+        )?;
+        for (j, (name, values)) in names.iter().zip(&columns).enumerate() {
+            let value = values.get(i);
+            writeln!(
+                writer,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"><title>{} / {}: {}</title></rect>"#,
+                left + HEATMAP_CELL * j,
+                y,
+                HEATMAP_CELL,
+                HEATMAP_CELL,
+                heatmap_color(value),
+                xml_escape(label),
+                xml_escape(name),
+                value.map_or("NA".to_string(), |v| format!("{:.3}", v))
+            )?;
+        }
+    }
+    writeln!(writer, "</svg>")?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Fill of a heatmap cell: white at 0, towards blue at -1 and red at 1, grey for nulls
+fn heatmap_color(value: Option<f64>) -> String {
+    let Some(value) = value.filter(|v| !v.is_nan()) else {
+        return "#cccccc".to_string();
+    };
+    let value = value.clamp(-1.0, 1.0);
+    let (full, t) = if value < 0.0 {
+        ((33, 102, 172), -value)
+    } else {
+        ((178, 24, 43), value)
+    };
+    let channel = |c: u8| (255.0 + (c as f64 - 255.0) * t).round() as u8;
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        channel(full.0),
+        channel(full.1),
+        channel(full.2)
+    )
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// autoSql of the BED6 records written by `write_bigbed()`
+#[cfg(feature = "bigbed")]
+const BED6_AUTOSQL: &str = r#"table hits
+"Predicted transcription factor binding sites"
+    (
+    string chrom;      "Chromosome"
+    uint   chromStart; "Start of the site (0-based)"
+    uint   chromEnd;   "End of the site"
+    string name;       "Motif"
+    uint   score;      "Occupancy scaled to 0-1000"
+    char[1] strand;    "Strand of the site"
+    )
+"#;
+
+/// Writes genomic hits as a bigBed file, for hit sets too large to load as BED.
+///
+/// Only available with the `bigbed` feature. The records are those of `write_bed()`; they are
+/// sorted by chromosome and start here, so no external `sort`/`bedToBigBed` step is needed.
+///
+/// # Arguments
+/// * `hits` - Hit table with the columns required by `write_bed()`
+/// * `chrom_sizes` - Length of every chromosome, e.g. from `coords::read_chrom_sizes()`
+/// * `filename` - Path of the bigBed file
+///
+/// # Returns
+/// * `Result<usize, MotifError>` - Number of intervals written
+///
+/// # Errors
+/// * `MotifError::InvalidInput` - If a hit lies on a chromosome missing from `chrom_sizes` or
+///   beyond its end
+/// * `MotifError::DataError` - If a column is missing or has the wrong type
+/// * `MotifError::Io` - For write failures
+#[cfg(feature = "bigbed")]
+pub fn write_bigbed(
+    hits: &DataFrame,
+    chrom_sizes: &std::collections::HashMap<String, u32>,
+    filename: &str,
+) -> Result<usize, MotifError> {
+    use bigtools::beddata::BedParserStreamingIterator;
+    use bigtools::{BedEntry, BigBedWrite};
+
+    let mut records = bed_records(hits)?;
+    for (chrom, start, end, _) in &records {
+        let size = chrom_sizes.get(chrom).ok_or_else(|| {
+            MotifError::InvalidInput(format!("chromosome {} is missing from the sizes", chrom))
+        })?;
+        if *start < 0 || *end > i64::from(*size) {
+            return Err(MotifError::InvalidInput(format!(
+                "site {}:{}-{} lies outside the chromosome",
+                chrom, start, end
+            )));
+        }
+    }
+    records.sort_by(|a, b| (&a.0, a.1, a.2).cmp(&(&b.0, b.1, b.2)));
+    let written = records.len();
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .map_err(MotifError::Io)?;
+    let mut writer = BigBedWrite::create_file(filename, chrom_sizes.clone())?;
+    writer.autosql = Some(BED6_AUTOSQL.to_string());
+    let entries = records.into_iter().map(|(chrom, start, end, rest)| {
+        (
+            chrom,
+            BedEntry {
+                start: start as u32,
+                end: end as u32,
+                rest,
+            },
+        )
+    });
+    writer
+        .write(
+            BedParserStreamingIterator::wrap_infallible_iter(entries, false),
+            runtime,
+        )
+        .map_err(|e| MotifError::InvalidInput(format!("bigBed: {}", e)))?;
+    Ok(written)
+}
+
+/// Writes per-base values, such as the occupancy landscape of a motif projected onto the
+/// genome, as a bigWig file.
+///
+/// Only available with the `bigbed` feature. Each value covers one base; the values are
+/// sorted by chromosome and position here. Bases without a value are left out of the file,
+/// so browsers draw them as gaps rather than zeros.
+///
+/// # Arguments
+/// * `values` - (chrom, 0-based position, value) of every base, each base at most once
+/// * `chrom_sizes` - Length of every chromosome, e.g. from `coords::read_chrom_sizes()`
+/// * `filename` - Path of the bigWig file
+///
+/// # Returns
+/// * `Result<usize, MotifError>` - Number of bases written
+///
+/// # Errors
+/// * `MotifError::InvalidInput` - If a base lies on a chromosome missing from `chrom_sizes`
+///   or beyond its end, or is given twice
+/// * `MotifError::Io` - For write failures
+#[cfg(feature = "bigbed")]
+pub fn write_bigwig(
+    values: &[(String, u32, f32)],
+    chrom_sizes: &std::collections::HashMap<String, u32>,
+    filename: &str,
+) -> Result<usize, MotifError> {
+    use bigtools::beddata::BedParserStreamingIterator;
+    use bigtools::BigWigWrite;
+
+    for (chrom, position, _) in values {
+        let size = chrom_sizes.get(chrom).ok_or_else(|| {
+            MotifError::InvalidInput(format!("chromosome {} is missing from the sizes", chrom))
+        })?;
+        if position >= size {
+            return Err(MotifError::InvalidInput(format!(
+                "base {}:{} lies outside the chromosome",
+                chrom, position
+            )));
+        }
+    }
+    let mut sorted: Vec<&(String, u32, f32)> = values.iter().collect();
+    sorted.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+    if let Some(pair) = sorted
+        .windows(2)
+        .find(|pair| (&pair[0].0, pair[0].1) == (&pair[1].0, pair[1].1))
+    {
+        return Err(MotifError::InvalidInput(format!(
+            "base {}:{} has more than one value",
+            pair[0].0, pair[0].1
+        )));
+    }
+    let written = sorted.len();
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .map_err(MotifError::Io)?;
+    let writer = BigWigWrite::create_file(filename, chrom_sizes.clone())?;
+    let entries = sorted.into_iter().map(|(chrom, position, value)| {
+        (
+            chrom.clone(),
+            bigtools::Value {
+                start: *position,
+                end: position + 1,
+                value: *value,
+            },
+        )
+    });
+    writer
+        .write(
+            BedParserStreamingIterator::wrap_infallible_iter(entries, false),
+            runtime,
+        )
+        .map_err(|e| MotifError::InvalidInput(format!("bigWig: {}", e)))?;
+    Ok(written)
+}
@@ -0,0 +1,360 @@
+use crate::error::MotifError;
+use polars::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+/// Genomic intervals, merged and sorted per chromosome, such as ChIP-seq peaks or the
+/// regions hits may be shuffled into
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::overlap::IntervalSet;
+///
+/// let peaks = IntervalSet::read_bed("GATA1_peaks.narrowPeak")?;
+/// assert!(peaks.overlaps("chr1", 4357766, 4357930));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct IntervalSet {
+    chroms: HashMap<String, Vec<(u64, u64)>>,
+}
+
+impl IntervalSet {
+    /// Builds a set from `(chrom, start, end)` intervals, 0-based with exclusive ends;
+    /// overlapping and adjacent intervals are merged and empty ones dropped
+    pub fn new<I, S>(intervals: I) -> Self
+    where
+        I: IntoIterator<Item = (S, u64, u64)>,
+        S: Into<String>,
+    {
+        let mut chroms: HashMap<String, Vec<(u64, u64)>> = HashMap::new();
+        for (chrom, start, end) in intervals {
+            if end > start {
+                chroms.entry(chrom.into()).or_default().push((start, end));
+            }
+        }
+        for intervals in chroms.values_mut() {
+            intervals.sort_unstable();
+            let mut merged: Vec<(u64, u64)> = Vec::with_capacity(intervals.len());
+            for &(start, end) in intervals.iter() {
+                match merged.last_mut() {
+                    Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                    _ => merged.push((start, end)),
+                }
+            }
+            *intervals = merged;
+        }
+        IntervalSet { chroms }
+    }
+
+    /// Reads the first three columns of a BED file (BED3 or wider, e.g. narrowPeak)
+    ///
+    /// Header lines starting with `track`, `browser` or `#` are skipped.
+    ///
+    /// # Errors
+    /// * `MotifError::InvalidFileFormat` - If a line has fewer than 3 fields or invalid
+    ///   coordinates
+    /// * `MotifError::Io` - For file reading issues
+    pub fn read_bed(filename: &str) -> Result<Self, MotifError> {
+        let reader = BufReader::new(File::open(filename)?);
+        let mut intervals = Vec::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty()
+                || line.starts_with('#')
+                || line.starts_with("track")
+                || line.starts_with("browser")
+            {
+                continue;
+            }
+            let invalid = || {
+                MotifError::InvalidFileFormat(format!(
+                    "{} line {}: expected chrom, start and end",
+                    filename,
+                    i + 1
+                ))
+            };
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 3 {
+                return Err(invalid());
+            }
+            let start = fields[1].parse::<u64>().map_err(|_| invalid())?;
+            let end = fields[2].parse::<u64>().map_err(|_| invalid())?;
+            intervals.push((fields[0].to_string(), start, end));
+        }
+        Ok(Self::new(intervals))
+    }
+
+    /// Builds a set from the "chrom", "start" and "end" columns of a region table, such as
+    /// the output of `coords::parse_labels()`; rows with null coordinates are skipped
+    ///
+    /// # Errors
+    /// * `MotifError::DataError` - If a column is missing or has the wrong type
+    pub fn from_regions(regions: &DataFrame) -> Result<Self, MotifError> {
+        let (chroms, starts, ends) = coordinate_columns(regions, "start", "end")?;
+        let intervals = (0..regions.height()).filter_map(|i| {
+            match (chroms.get(i), starts.get(i), ends.get(i)) {
+                (Some(chrom), Some(start), Some(end)) if start >= 0 && end >= 0 => {
+                    Some((chrom.to_string(), start as u64, end as u64))
+                }
+                _ => None,
+            }
+        });
+        Ok(Self::new(intervals))
+    }
+
+    /// Builds a set covering whole chromosomes, e.g. from `coords::read_chrom_sizes()`
+    pub fn from_chrom_sizes(sizes: &HashMap<String, u32>) -> Self {
+        Self::new(
+            sizes
+                .iter()
+                .map(|(chrom, &size)| (chrom.clone(), 0, size as u64)),
+        )
+    }
+
+    /// Whether `start..end` of `chrom` shares at least one base with the set
+    pub fn overlaps(&self, chrom: &str, start: u64, end: u64) -> bool {
+        let Some(intervals) = self.chroms.get(chrom) else {
+            return false;
+        };
+        let i = intervals.partition_point(|&(_, e)| e <= start);
+        intervals.get(i).is_some_and(|&(s, _)| s < end)
+    }
+
+    /// Number of bases covered by the set
+    pub fn covered_bases(&self) -> u64 {
+        self.chroms
+            .values()
+            .flat_map(|intervals| intervals.iter().map(|(start, end)| end - start))
+            .sum()
+    }
+}
+
+/// Marks the hits overlapping an annotation
+///
+/// # Arguments
+/// * `hits` - Hit table with "chrom", "genomic_start" and "genomic_end" columns, e.g. from
+///   `coords::project_hits()`
+/// * `annotation` - Intervals to intersect with, e.g. ChIP-seq peaks
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - `hits` with a boolean "in_annotation" column, null
+///   for hits without coordinates
+///
+/// # Errors
+/// * `MotifError::DataError` - If a column is missing or has the wrong type
+pub fn annotate_overlaps(
+    hits: &DataFrame,
+    annotation: &IntervalSet,
+) -> Result<DataFrame, MotifError> {
+    let (chroms, starts, ends) = coordinate_columns(hits, "genomic_start", "genomic_end")?;
+    let overlapping: Vec<Option<bool>> = (0..hits.height())
+        .map(|i| match (chroms.get(i), starts.get(i), ends.get(i)) {
+            (Some(chrom), Some(start), Some(end)) if start >= 0 && end >= 0 => {
+                Some(annotation.overlaps(chrom, start as u64, end as u64))
+            }
+            _ => None,
+        })
+        .collect();
+    let mut out = hits.clone();
+    out.with_column(Column::new("in_annotation".into(), overlapping))
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    Ok(out)
+}
+
+/// Tests every motif's hits for overlap with an annotation, against hits shuffled within a
+/// domain
+///
+/// Predicted sites are validated against experimental data such as ChIP-seq peaks by
+/// comparing the fraction of hits in the annotation with the fraction expected by chance.
+/// In each shuffle every hit is moved to a uniformly random position of the same width on
+/// its chromosome's part of `domain`, as `bedtools shuffle -chrom -incl`. The domain sets
+/// the null: the scanned regions (`IntervalSet::from_regions()`) control for the design of
+/// the library, whole chromosomes (`IntervalSet::from_chrom_sizes()`) for nothing. Hits
+/// without coordinates, or that do not fit in the domain of their chromosome, are left out.
+///
+/// # Arguments
+/// * `hits` - Hit table with "motif", "chrom", "genomic_start" and "genomic_end" columns
+/// * `annotation` - Intervals to intersect with
+/// * `domain` - Intervals the hits are shuffled within
+/// * `shuffles` - Number of shuffles
+/// * `seed` - Seed of the shuffles, so results are reproducible
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - One row per motif, sorted by motif, with columns:
+///   - "motif": Motif ID
+///   - "hits": Number of tested hits
+///   - "overlapping": Number of them overlapping the annotation
+///   - "fraction": Fraction overlapping the annotation
+///   - "expected_fraction": Mean fraction over the shuffles
+///   - "enrichment": Fraction over expected fraction (null when nothing is expected)
+///   - "pvalue": Empirical one-sided p-value, (1 + shuffles with at least as many
+///     overlapping hits) / (1 + shuffles)
+///
+/// # Errors
+/// * `MotifError::InvalidParameter` - If `shuffles` is 0
+/// * `MotifError::DataError` - If a column is missing or has the wrong type
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::overlap::{overlap_enrichment, IntervalSet};
+///
+/// let peaks = IntervalSet::read_bed("GATA1_peaks.bed")?;
+/// let domain = IntervalSet::from_regions(&regions)?;
+/// let enrichment = overlap_enrichment(&hits, &peaks, &domain, 1000, 42)?;
+/// ```
+pub fn overlap_enrichment(
+    hits: &DataFrame,
+    annotation: &IntervalSet,
+    domain: &IntervalSet,
+    shuffles: usize,
+    seed: u64,
+) -> Result<DataFrame, MotifError> {
+    if shuffles == 0 {
+        return Err(MotifError::invalid_parameter(
+            "shuffles",
+            shuffles,
+            "must be at least 1",
+        ));
+    }
+    let data_error = |e: PolarsError| MotifError::DataError(e.to_string());
+    let motifs = hits
+        .column("motif")
+        .and_then(|c| c.cast(&DataType::String))
+        .map_err(data_error)?;
+    let motifs = motifs.str().map_err(data_error)?;
+    let (chroms, starts, ends) = coordinate_columns(hits, "genomic_start", "genomic_end")?;
+
+    let names: BTreeSet<&str> = motifs.into_iter().flatten().collect();
+    let motif_index: HashMap<&str, usize> = names
+        .iter()
+        .enumerate()
+        .map(|(i, &motif)| (motif, i))
+        .collect();
+
+    // start positions available to each (chromosome, width), as cumulative counts over the
+    // domain intervals of the chromosome
+    let mut placements: HashMap<(&str, u64), Vec<u64>> = HashMap::new();
+    // (motif index, chromosome, width) of the tested hits, with their observed overlaps
+    let mut tested: Vec<(usize, &str, u64)> = Vec::new();
+    let mut observed = vec![0u64; motif_index.len()];
+    let mut counts = vec![0u64; motif_index.len()];
+    for i in 0..hits.height() {
+        let (Some(motif), Some(chrom), Some(start), Some(end)) =
+            (motifs.get(i), chroms.get(i), starts.get(i), ends.get(i))
+        else {
+            continue;
+        };
+        if start < 0 || end <= start {
+            continue;
+        }
+        let (start, width) = (start as u64, (end - start) as u64);
+        let cumulative = placements.entry((chrom, width)).or_insert_with(|| {
+            let mut total = 0;
+            domain
+                .chroms
+                .get(chrom)
+                .map(|intervals| {
+                    intervals
+                        .iter()
+                        .map(|(s, e)| {
+                            total += (e - s + 1).saturating_sub(width);
+                            total
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        });
+        if cumulative.last().copied().unwrap_or(0) == 0 {
+            continue;
+        }
+        let motif = motif_index[motif];
+        tested.push((motif, chrom, width));
+        counts[motif] += 1;
+        if annotation.overlaps(chrom, start, start + width) {
+            observed[motif] += 1;
+        }
+    }
+
+    let shuffled: Vec<Vec<u64>> = (0..shuffles)
+        .into_par_iter()
+        .map(|shuffle| {
+            let mut rng = StdRng::seed_from_u64(seed.wrapping_add(shuffle as u64));
+            let mut overlapping = vec![0u64; motif_index.len()];
+            for &(motif, chrom, width) in &tested {
+                let cumulative = &placements[&(chrom, width)];
+                let draw = rng.gen_range(0..*cumulative.last().expect("placeable hit"));
+                let interval = cumulative.partition_point(|&c| c <= draw);
+                let before = if interval == 0 {
+                    0
+                } else {
+                    cumulative[interval - 1]
+                };
+                let start = domain.chroms[chrom][interval].0 + (draw - before);
+                if annotation.overlaps(chrom, start, start + width) {
+                    overlapping[motif] += 1;
+                }
+            }
+            overlapping
+        })
+        .collect();
+
+    let n = motif_index.len();
+    let (mut fractions, mut expected, mut enrichments, mut pvalues) = (
+        Vec::with_capacity(n),
+        Vec::with_capacity(n),
+        Vec::with_capacity(n),
+        Vec::with_capacity(n),
+    );
+    for motif in 0..n {
+        let tested_hits = counts[motif].max(1) as f64;
+        let fraction = observed[motif] as f64 / tested_hits;
+        let mean =
+            shuffled.iter().map(|s| s[motif]).sum::<u64>() as f64 / shuffles as f64 / tested_hits;
+        let extreme = shuffled
+            .iter()
+            .filter(|s| s[motif] >= observed[motif])
+            .count();
+        fractions.push(fraction);
+        expected.push(mean);
+        enrichments.push((mean > 0.0).then(|| fraction / mean));
+        pvalues.push((1 + extreme) as f64 / (1 + shuffles) as f64);
+    }
+
+    DataFrame::new(vec![
+        Column::new("motif".into(), names.into_iter().collect::<Vec<_>>()),
+        Column::new("hits".into(), counts),
+        Column::new("overlapping".into(), observed),
+        Column::new("fraction".into(), fractions),
+        Column::new("expected_fraction".into(), expected),
+        Column::new("enrichment".into(), enrichments),
+        Column::new("pvalue".into(), pvalues),
+    ])
+    .map_err(data_error)
+}
+
+/// The chromosome, start and end columns of a table, cast to strings and 64-bit integers
+fn coordinate_columns(
+    df: &DataFrame,
+    start: &str,
+    end: &str,
+) -> Result<(StringChunked, Int64Chunked, Int64Chunked), MotifError> {
+    let data_error = |e: PolarsError| MotifError::DataError(e.to_string());
+    let column = |name: &str, dtype: DataType| {
+        df.column(name)
+            .and_then(|c| c.cast(&dtype))
+            .map_err(data_error)
+    };
+    let chroms = column("chrom", DataType::String)?;
+    let starts = column(start, DataType::Int64)?;
+    let ends = column(end, DataType::Int64)?;
+    Ok((
+        chroms.str().map_err(data_error)?.clone(),
+        starts.i64().map_err(data_error)?.clone(),
+        ends.i64().map_err(data_error)?.clone(),
+    ))
+}
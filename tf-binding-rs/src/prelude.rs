@@ -0,0 +1,37 @@
+//! The core of the crate, for `use tf_binding_rs::prelude::*;`
+//!
+//! The prelude covers the matrix and collection types, reading MEME files and FASTA
+//! sequences, converting PWMs to energies, occupancy and energy landscapes, hit calling,
+//! scan options and the error types. While the crate is at 0.x, these items are only
+//! renamed, removed or changed in signature in a release that bumps the minor version;
+//! patch releases only add to the prelude.
+//! Items reached only through their modules carry no such promise and may change in any
+//! release.
+//!
+//! Matrices are DataFrames with A, C, G, T columns (`PWM`, `EWM`), a motif set is a
+//! collection keyed by motif ID (`PWMCollection`, `EWMCollection`) or a prepared
+//! `MotifBank`, and a landscape is the per-window `StrandScores` of one motif or the
+//! DataFrame of `total_landscape()`.
+//!
+//! # Example
+//! ```ignore
+//! use tf_binding_rs::prelude::*;
+//!
+//! fn main() -> Result<()> {
+//!     let ewms = read_pwm_to_ewm("motifs.meme")?;
+//!     let landscape = total_landscape("ATCGATCGATCG", &ewms, 9.0)?;
+//!     println!("{}", landscape);
+//!     Ok(())
+//! }
+//! ```
+
+pub use crate::error::{ErrorKind, MotifError, Result};
+pub use crate::fasta::{read_fasta, read_fasta_with, DuplicateLabels};
+pub use crate::occupancy::{
+    energy_landscape, find_hits, occupancies, occupancy_landscape, pwm_to_ewm, pwm_to_ewm_with,
+    read_pwm_files, read_pwm_to_ewm, read_pwm_to_ewm_with, total_energy_landscape, total_landscape,
+    total_occupancy, EwmOptions, MotifBank,
+};
+pub use crate::scan::{scan_sequences, AmbiguousPolicy, ScanResult, ScanWarning, SkipReason};
+pub use crate::scanner::{Scanner, ScannerBuilder, Strand};
+pub use crate::types::{EWMCollection, PCMCollection, PWMCollection, StrandScores, EWM, PCM, PWM};
@@ -0,0 +1,185 @@
+use crate::error::MotifError;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// An input file recorded in a run manifest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputFile {
+    /// Path as given on the command line
+    pub path: String,
+    /// Hex-encoded SHA-256 of the file contents
+    pub sha256: String,
+    /// File size in bytes
+    pub bytes: u64,
+}
+
+/// Machine-readable record of how a result file was produced
+///
+/// Written as a sidecar JSON file next to each output (see `manifest_path()`), so results
+/// can be traced back to the exact inputs and parameters long after the run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunManifest {
+    /// Name of the program that produced the output
+    pub tool: String,
+    /// Version of the program
+    pub version: String,
+    /// Version of tf-binding-rs used by the program
+    pub library_version: String,
+    /// Run parameters, by name
+    pub parameters: BTreeMap<String, String>,
+    /// Record counts of the run, e.g. sequences scanned and hits written
+    pub counts: BTreeMap<String, u64>,
+    /// Input files with their hashes
+    pub inputs: Vec<InputFile>,
+    /// Paths of the files written by the run
+    pub outputs: Vec<String>,
+    /// Start of the run, in seconds since the Unix epoch
+    pub started_at: u64,
+    /// Wall time of the run in seconds
+    pub wall_time_seconds: f64,
+}
+
+impl RunManifest {
+    /// Starts a manifest for a run of `tool` at the current time.
+    pub fn new(tool: &str, version: &str) -> Self {
+        RunManifest {
+            tool: tool.to_string(),
+            version: version.to_string(),
+            library_version: env!("CARGO_PKG_VERSION").to_string(),
+            parameters: BTreeMap::new(),
+            counts: BTreeMap::new(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            wall_time_seconds: 0.0,
+        }
+    }
+
+    /// Records a run parameter.
+    pub fn parameter(&mut self, name: &str, value: impl ToString) -> &mut Self {
+        self.parameters.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Records a count, e.g. the number of hits written.
+    pub fn count(&mut self, name: &str, value: usize) -> &mut Self {
+        self.counts.insert(name.to_string(), value as u64);
+        self
+    }
+
+    /// Records an input file, hashing its contents.
+    ///
+    /// # Errors
+    /// * `MotifError::Io` - If the file cannot be read
+    pub fn add_input(&mut self, path: &str) -> Result<&mut Self, MotifError> {
+        let bytes = std::fs::metadata(path)?.len();
+        self.inputs.push(InputFile {
+            path: path.to_string(),
+            sha256: file_sha256(path)?,
+            bytes,
+        });
+        Ok(self)
+    }
+
+    /// Records an output file.
+    pub fn add_output(&mut self, path: &str) -> &mut Self {
+        self.outputs.push(path.to_string());
+        self
+    }
+
+    /// Records the wall time of the run.
+    pub fn finish(&mut self, wall_time: Duration) -> &mut Self {
+        self.wall_time_seconds = wall_time.as_secs_f64();
+        self
+    }
+
+    /// Flattens the manifest into key/value pairs, e.g. for database metadata tables.
+    ///
+    /// Parameters are keyed `param.{name}`, counts `count.{name}` and inputs `input.{path}`
+    /// (valued by hash).
+    pub fn to_key_values(&self) -> Vec<(String, String)> {
+        let mut pairs = vec![
+            ("tool".to_string(), self.tool.clone()),
+            ("version".to_string(), self.version.clone()),
+            ("library_version".to_string(), self.library_version.clone()),
+            ("started_at".to_string(), self.started_at.to_string()),
+            (
+                "wall_time_seconds".to_string(),
+                self.wall_time_seconds.to_string(),
+            ),
+        ];
+        pairs.extend(
+            self.parameters
+                .iter()
+                .map(|(name, value)| (format!("param.{}", name), value.clone())),
+        );
+        pairs.extend(
+            self.counts
+                .iter()
+                .map(|(name, value)| (format!("count.{}", name), value.to_string())),
+        );
+        pairs.extend(
+            self.inputs
+                .iter()
+                .map(|input| (format!("input.{}", input.path), input.sha256.clone())),
+        );
+        pairs
+    }
+
+    /// Writes the manifest as pretty-printed JSON.
+    ///
+    /// # Errors
+    /// * `MotifError::Io` - For file writing issues
+    /// * `MotifError::DataError` - If serialization fails
+    pub fn write(&self, filename: &str) -> Result<(), MotifError> {
+        let mut writer = BufWriter::new(File::create(filename)?);
+        serde_json::to_writer_pretty(&mut writer, self)
+            .map_err(|e| MotifError::DataError(e.to_string()))?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Reads a manifest written by `write()`.
+    ///
+    /// # Errors
+    /// * `MotifError::Io` - If the file cannot be opened
+    /// * `MotifError::InvalidFileFormat` - If the file is not a valid manifest
+    pub fn read(filename: &str) -> Result<Self, MotifError> {
+        let reader = BufReader::new(File::open(filename)?);
+        serde_json::from_reader(reader).map_err(|e| MotifError::InvalidFileFormat(e.to_string()))
+    }
+}
+
+/// Path of the sidecar manifest for an output file: `{output}.manifest.json`
+pub fn manifest_path(output_file: &str) -> String {
+    format!("{}.manifest.json", output_file)
+}
+
+/// Computes the hex-encoded SHA-256 hash of a file.
+///
+/// # Errors
+/// * `MotifError::Io` - If the file cannot be read
+pub fn file_sha256(path: &str) -> Result<String, MotifError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
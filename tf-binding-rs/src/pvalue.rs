@@ -0,0 +1,223 @@
+use crate::error::MotifError;
+use crate::matrix::matrix_rows;
+use crate::types::*;
+use polars::prelude::*;
+use std::collections::HashMap;
+
+/// Default bin width (kJ/mol) of the energy distributions
+pub const DEFAULT_STEP: f64 = 0.01;
+
+/// Uniform base composition, used when no background is given
+pub const UNIFORM_BACKGROUND: [f64; 4] = [0.25; 4];
+
+/// Windows scanned per kb of sequence, counting both strands
+pub const WINDOWS_PER_KB: f64 = 2000.0;
+
+/// Null distribution of the binding energy of a motif on random sequence
+///
+/// Computed exactly for an i.i.d. background by dynamic programming over the motif
+/// positions, with energies binned to `step` kJ/mol. Binning moves every window energy by at
+/// most `len * step / 2`, and `pvalue()` adds this tolerance to the queried energy, so
+/// p-values are slightly conservative and never zero for a window that occurs in a sequence.
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::pvalue::{ScoreDistribution, DEFAULT_STEP, UNIFORM_BACKGROUND};
+///
+/// let dist = ScoreDistribution::new(&ewm, UNIFORM_BACKGROUND, DEFAULT_STEP).unwrap();
+/// let p = dist.pvalue(3.5);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ScoreDistribution {
+    min_energy: f64,
+    step: f64,
+    tolerance: f64,
+    /// `cdf[i]` is the probability of a binned energy of at most `min_energy + i * step`
+    cdf: Vec<f64>,
+}
+
+impl ScoreDistribution {
+    /// Computes the energy distribution of a motif
+    ///
+    /// # Arguments
+    /// * `ewm` - Energy Weight Matrix of the motif
+    /// * `background` - Probabilities of A, C, G and T in random sequence
+    /// * `step` - Bin width in kJ/mol; smaller bins are more precise but slower
+    ///
+    /// # Errors
+    /// * `MotifError::InvalidParameter` - If `step` is not positive or the background does
+    ///   not sum to 1
+    /// * `MotifError::DataError` - If a nucleotide column is missing or not of type f64
+    pub fn new(ewm: &EWM, background: [f64; 4], step: f64) -> Result<Self, MotifError> {
+        if step <= 0.0 || !step.is_finite() {
+            return Err(MotifError::invalid_parameter(
+                "step",
+                step,
+                "must be positive",
+            ));
+        }
+        let total: f64 = background.iter().sum();
+        if background.iter().any(|&p| p < 0.0) || (total - 1.0).abs() > 1e-6 {
+            return Err(MotifError::invalid_parameter(
+                "background",
+                format!("{:?}", background),
+                "must be probabilities summing to 1",
+            ));
+        }
+
+        let rows = matrix_rows(ewm)?;
+        let mut min_energy = 0.0;
+        let mut pmf = vec![1.0];
+        for row in &rows {
+            let row_min = row.iter().copied().fold(f64::INFINITY, f64::min);
+            min_energy += row_min;
+            let bins = row.map(|e| ((e - row_min) / step).round() as usize);
+            let width = bins.iter().copied().max().unwrap_or(0);
+
+            let mut next = vec![0.0; pmf.len() + width];
+            for (i, &p) in pmf.iter().enumerate() {
+                if p == 0.0 {
+                    continue;
+                }
+                for (base, &bin) in bins.iter().enumerate() {
+                    next[i + bin] += p * background[base];
+                }
+            }
+            pmf = next;
+        }
+
+        let mut cdf = pmf;
+        let mut sum = 0.0;
+        for p in cdf.iter_mut() {
+            sum += *p;
+            *p = sum;
+        }
+        Ok(ScoreDistribution {
+            min_energy,
+            step,
+            tolerance: rows.len() as f64 * step / 2.0,
+            cdf,
+        })
+    }
+
+    /// Probability that a random window binds at least as strongly, i.e. has an energy of at
+    /// most `energy`
+    pub fn pvalue(&self, energy: f64) -> f64 {
+        // the small margin keeps a window in its own bin despite rounding
+        let bins = (energy + self.tolerance - self.min_energy) / self.step + 1e-9;
+        if bins.is_nan() || bins < 0.0 {
+            return 0.0;
+        }
+        let bin = (bins.floor() as usize).min(self.cdf.len() - 1);
+        self.cdf[bin].min(1.0)
+    }
+
+    /// Probability that a random window has an occupancy of at least `occupancy` at
+    /// chemical potential `mu`
+    pub fn occupancy_pvalue(&self, occupancy: f64, mu: f64) -> f64 {
+        // invert occupancy = 1 / (1 + exp(energy - mu)); saturated hits get the best energy
+        let energy = mu + ((1.0 - occupancy) / occupancy).ln();
+        self.pvalue(energy.max(self.min_energy))
+    }
+
+    /// Expected number of windows per kb of random sequence, on both strands, with an
+    /// occupancy of at least `occupancy` at chemical potential `mu`: how often a hit this
+    /// strong or stronger turns up by chance
+    pub fn occupancy_rate_per_kb(&self, occupancy: f64, mu: f64) -> f64 {
+        self.occupancy_pvalue(occupancy, mu) * WINDOWS_PER_KB
+    }
+}
+
+/// Computes the energy distribution of every motif of a collection
+///
+/// # Arguments
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are motif IDs
+/// * `background` - Probabilities of A, C, G and T in random sequence
+/// * `step` - Bin width in kJ/mol
+///
+/// # Returns
+/// * `Result<HashMap<String, ScoreDistribution>, MotifError>` - Distributions keyed by motif ID
+///
+/// # Errors
+/// * See `ScoreDistribution::new()`
+pub fn score_distributions(
+    ewms: &EWMCollection,
+    background: [f64; 4],
+    step: f64,
+) -> Result<HashMap<String, ScoreDistribution>, MotifError> {
+    ewms.iter()
+        .map(|(id, ewm)| Ok((id.clone(), ScoreDistribution::new(ewm, background, step)?)))
+        .collect()
+}
+
+/// Adds p-values to a hit table such as the one returned by `find_hits()`
+///
+/// Occupancy depends on motif width and affinity, so equal occupancies of two motifs are
+/// not equally surprising; the p-value of a hit is the probability that a random window of
+/// the same motif reaches its occupancy (see `ScoreDistribution::occupancy_pvalue()`). The
+/// expected number of such windows per kb of random sequence is added alongside, as a
+/// specificity that reads more directly than a p-value.
+///
+/// # Arguments
+/// * `hits` - DataFrame with "motif" and "occupancy" columns
+/// * `distributions` - Energy distributions keyed by motif ID, from `score_distributions()`
+/// * `mu` - Chemical potential the occupancies were computed with
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - The hits with added columns "pvalue",
+///   "neg_log10_pvalue" and "background_per_kb"
+///
+/// # Errors
+/// * `MotifError::InvalidInput` - If a hit's motif has no distribution
+/// * `MotifError::DataError` - If a column is missing or has the wrong type
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::occupancy::find_hits;
+/// use tf_binding_rs::pvalue::{add_pvalues, score_distributions, DEFAULT_STEP, UNIFORM_BACKGROUND};
+///
+/// let distributions = score_distributions(&ewms, UNIFORM_BACKGROUND, DEFAULT_STEP).unwrap();
+/// let hits = find_hits(seq, &ewms, 9.0, 0.2).unwrap();
+/// let hits = add_pvalues(&hits, &distributions, 9.0).unwrap();
+/// ```
+pub fn add_pvalues(
+    hits: &DataFrame,
+    distributions: &HashMap<String, ScoreDistribution>,
+    mu: f64,
+) -> Result<DataFrame, MotifError> {
+    let data_error = |e: PolarsError| MotifError::DataError(e.to_string());
+    let motifs = hits
+        .column("motif")
+        .and_then(|c| c.str())
+        .map_err(data_error)?;
+    let occupancies = hits
+        .column("occupancy")
+        .and_then(|c| c.f64())
+        .map_err(data_error)?;
+
+    let pvalues = motifs
+        .into_iter()
+        .zip(occupancies)
+        .map(|(motif, occupancy)| match (motif, occupancy) {
+            (Some(motif), Some(occupancy)) => distributions
+                .get(motif)
+                .map(|dist| Some(dist.occupancy_pvalue(occupancy, mu)))
+                .ok_or_else(|| {
+                    MotifError::InvalidInput(format!("no score distribution for motif {}", motif))
+                }),
+            _ => Ok(None),
+        })
+        .collect::<Result<Vec<Option<f64>>, MotifError>>()?;
+    let neg_log10: Vec<Option<f64>> = pvalues.iter().map(|p| p.map(|p| -p.log10())).collect();
+    let per_kb: Vec<Option<f64>> = pvalues
+        .iter()
+        .map(|p| p.map(|p| p * WINDOWS_PER_KB))
+        .collect();
+
+    let mut hits = hits.clone();
+    hits.with_column(Column::new("pvalue".into(), pvalues))
+        .and_then(|df| df.with_column(Column::new("neg_log10_pvalue".into(), neg_log10)))
+        .and_then(|df| df.with_column(Column::new("background_per_kb".into(), per_kb)))
+        .map_err(data_error)?;
+    Ok(hits)
+}
@@ -0,0 +1,211 @@
+use crate::error::MotifError;
+use crate::occupancy::{encode_sequence, StrandedEwm};
+use crate::types::*;
+use polars::prelude::*;
+
+/// Positions and occupancies of the hits of one motif on the forward and reverse strands
+pub type StrandHits = (Vec<(usize, f64)>, Vec<(usize, f64)>);
+
+/// An energy matrix with an integer copy for fast prefiltering of windows
+///
+/// Energies are scaled so the largest entry maps to `i16::MAX` and rounded to `i16`, so every
+/// entry is off by at most `0.5 / scale`, and a window energy summed from `len` entries by at
+/// most `len / (2 * scale)` (see `error_bound()`). Windows whose quantized energy is more than
+/// the bound above the hit threshold cannot be hits and are skipped; all others are rescored in
+/// f64. The hits are therefore exactly those of `find_hits()`. With the default pseudocount,
+/// energies stay below about 23 kJ/mol, so the bound is below 0.01 kJ/mol for a 30 bp motif.
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::quantized::QuantizedEwm;
+///
+/// let matrix = QuantizedEwm::new(&ewm).unwrap();
+/// let (forward, reverse) = matrix.hits("ATCGATCGATCG", 9.0, 0.5).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct QuantizedEwm {
+    exact: StrandedEwm,
+    forward: Vec<[i16; 4]>,
+    reverse: Vec<[i16; 4]>,
+    scale: f64,
+}
+
+fn quantize(rows: &[[f64; 4]], scale: f64) -> Vec<[i16; 4]> {
+    rows.iter()
+        .map(|row| row.map(|e| (e * scale).round() as i16))
+        .collect()
+}
+
+fn quantized_energy(rows: &[[i16; 4]], kmer: &[usize]) -> i32 {
+    rows.iter()
+        .zip(kmer)
+        .map(|(row, &base)| row[base] as i32)
+        .sum()
+}
+
+impl QuantizedEwm {
+    /// Prepares the exact and quantized matrices
+    ///
+    /// # Errors
+    /// * `MotifError::DataError` - If a nucleotide column is missing or not of type f64
+    pub fn new(ewm: &EWM) -> Result<Self, MotifError> {
+        let exact = StrandedEwm::new(ewm)?;
+        let max_abs = exact
+            .forward
+            .iter()
+            .flatten()
+            .fold(0.0f64, |max, e| max.max(e.abs()));
+        let scale = if max_abs > 0.0 {
+            i16::MAX as f64 / max_abs
+        } else {
+            1.0
+        };
+        Ok(QuantizedEwm {
+            forward: quantize(&exact.forward, scale),
+            reverse: quantize(&exact.reverse, scale),
+            exact,
+            scale,
+        })
+    }
+
+    /// Width of the motif
+    pub fn len(&self) -> usize {
+        self.exact.len()
+    }
+
+    /// Whether the matrix has no positions
+    pub fn is_empty(&self) -> bool {
+        self.exact.is_empty()
+    }
+
+    /// The exact matrix, which scores the windows passing the prefilter
+    pub fn exact(&self) -> &StrandedEwm {
+        &self.exact
+    }
+
+    /// Quantization steps per kJ/mol
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Largest possible difference (kJ/mol) between the quantized and the exact energy of a
+    /// window
+    pub fn error_bound(&self) -> f64 {
+        self.len() as f64 / (2.0 * self.scale)
+    }
+
+    /// Finds the windows with occupancy strictly above `cutoff` on both strands
+    ///
+    /// # Arguments
+    /// * `seq` - The DNA sequence to scan
+    /// * `mu` - Chemical potential of the transcription factor
+    /// * `cutoff` - Minimum occupancy for a window to be reported
+    ///
+    /// # Returns
+    /// * `Result<StrandHits, MotifError>` - (position, occupancy) of the forward and reverse
+    ///   strand hits, with positions as in `occupancy_landscape()`
+    ///
+    /// # Errors
+    /// * `MotifError::InvalidSequence` - If the sequence is shorter than the motif
+    /// * `MotifError::InvalidInput` - If the sequence contains characters other than A, C, G, T
+    pub fn hits(&self, seq: &str, mu: f64, cutoff: f64) -> Result<StrandHits, MotifError> {
+        let encoded = encode_sequence(seq, self.len())?;
+        let mut hits: StrandHits = (Vec::new(), Vec::new());
+        if cutoff >= 1.0 {
+            return Ok(hits);
+        }
+
+        // occupancy > cutoff  <=>  energy < mu + ln(1 / cutoff - 1)
+        let max_energy = if cutoff > 0.0 {
+            mu + (1.0 / cutoff - 1.0).ln()
+        } else {
+            f64::INFINITY
+        };
+        // a small margin absorbs rounding in the threshold itself
+        let reject_above =
+            ((max_energy + self.error_bound()) * self.scale * (1.0 + 1e-9)).ceil() + 1.0;
+        let occupancy = |e: f64| 1.0 / (1.0 + (e - mu).exp());
+
+        for (pos, kmer) in encoded.windows(self.len()).enumerate() {
+            if (quantized_energy(&self.forward, kmer) as f64) < reject_above {
+                let occ = occupancy(self.exact.forward_energy(kmer));
+                if occ > cutoff {
+                    hits.0.push((pos, occ));
+                }
+            }
+            if (quantized_energy(&self.reverse, kmer) as f64) < reject_above {
+                let occ = occupancy(self.exact.reverse_energy(kmer));
+                if occ > cutoff {
+                    hits.1.push((pos, occ));
+                }
+            }
+        }
+        Ok(hits)
+    }
+}
+
+/// Finds all windows whose predicted occupancy exceeds a cutoff, using quantized prefiltering
+///
+/// Returns exactly the hits of `find_hits()`, in the same layout. Most windows of a genome scan
+/// are far from the threshold and are rejected with integer arithmetic; only the rest are
+/// scored in f64. The saving is largest for selective cutoffs; with a cutoff near zero every
+/// window is rescored and `find_hits()` is faster.
+///
+/// # Arguments
+/// * `seq` - The DNA sequence to scan
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are motif IDs
+/// * `mu` - Chemical potential of the transcription factors
+/// * `cutoff` - Minimum occupancy for a window to be reported
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - DataFrame with columns "motif", "position", "strand",
+///   "length" and "occupancy", as `find_hits()`
+///
+/// # Errors
+/// * `MotifError::InvalidSequence` - If the sequence is shorter than a motif
+/// * `MotifError::DataError` - If there are issues creating the DataFrame
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::quantized::find_hits_quantized;
+///
+/// let hits = find_hits_quantized(&chromosome, &ewm_collection, 9.0, 0.5).unwrap();
+/// ```
+pub fn find_hits_quantized(
+    seq: &str,
+    ewms: &EWMCollection,
+    mu: f64,
+    cutoff: f64,
+) -> Result<DataFrame, MotifError> {
+    let mut motif_ids: Vec<&String> = ewms.keys().collect();
+    motif_ids.sort();
+
+    let mut motifs: Vec<&str> = Vec::new();
+    let mut positions: Vec<i32> = Vec::new();
+    let mut strands: Vec<&str> = Vec::new();
+    let mut lengths: Vec<i32> = Vec::new();
+    let mut occupancies: Vec<f64> = Vec::new();
+
+    for motif_id in motif_ids {
+        let matrix = QuantizedEwm::new(&ewms[motif_id])?;
+        let (forward, reverse) = matrix.hits(seq, mu, cutoff)?;
+        for (strand, hits) in [("F", forward), ("R", reverse)] {
+            for (pos, occ) in hits {
+                motifs.push(motif_id);
+                positions.push(pos as i32);
+                strands.push(strand);
+                lengths.push(matrix.len() as i32);
+                occupancies.push(occ);
+            }
+        }
+    }
+
+    DataFrame::new(vec![
+        Column::new("motif".into(), motifs),
+        Column::new("position".into(), positions),
+        Column::new("strand".into(), strands),
+        Column::new("length".into(), lengths),
+        Column::new("occupancy".into(), occupancies),
+    ])
+    .map_err(|e| MotifError::DataError(e.to_string()))
+}
@@ -0,0 +1,371 @@
+use crate::error::{MotifError, ResultExt};
+use crate::occupancy::{find_hits, stranded_hits, StrandedEwm};
+use crate::types::*;
+use polars::prelude::*;
+use std::fmt;
+use std::str::FromStr;
+
+/// Why a sequence (or a motif on a sequence) was skipped during a scan
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkipReason {
+    /// The sequence value is null
+    MissingSequence,
+    /// The sequence contains a character other than A, C, G, T
+    AmbiguousBases {
+        /// Position of the first offending character
+        position: usize,
+        /// The offending character
+        base: char,
+    },
+    /// The sequence is shorter than the motif
+    TooShort {
+        /// Sequence length
+        length: usize,
+        /// Motif length
+        motif_length: usize,
+    },
+    /// The label was already used by an earlier record, which was kept instead
+    DuplicateLabel {
+        /// Row index of the first record with this label
+        first_index: usize,
+    },
+    /// Scoring failed for another reason
+    ScanError(String),
+}
+
+impl SkipReason {
+    /// Short machine-readable name of the reason, e.g. for grouping warnings
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SkipReason::MissingSequence => "missing_sequence",
+            SkipReason::AmbiguousBases { .. } => "ambiguous_bases",
+            SkipReason::TooShort { .. } => "too_short",
+            SkipReason::DuplicateLabel { .. } => "duplicate_label",
+            SkipReason::ScanError(_) => "scan_error",
+        }
+    }
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::MissingSequence => write!(f, "sequence is missing"),
+            SkipReason::AmbiguousBases { position, base } => {
+                write!(f, "ambiguous base '{}' at position {}", base, position)
+            }
+            SkipReason::TooShort {
+                length,
+                motif_length,
+            } => write!(
+                f,
+                "sequence of {} bp is shorter than the {} bp motif",
+                length, motif_length
+            ),
+            SkipReason::DuplicateLabel { first_index } => {
+                write!(f, "label repeats the record at row {}", first_index)
+            }
+            SkipReason::ScanError(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+/// A record skipped during a scan
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanWarning {
+    /// Row index of the sequence in the input
+    pub index: usize,
+    /// Label of the sequence, if the input has one
+    pub label: Option<String>,
+    /// Motif that was skipped, or `None` if the whole sequence was skipped
+    pub motif: Option<String>,
+    /// Why the record was skipped
+    pub reason: SkipReason,
+}
+
+impl fmt::Display for ScanWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sequence {}", self.index)?;
+        if let Some(label) = &self.label {
+            write!(f, " ({})", label)?;
+        }
+        if let Some(motif) = &self.motif {
+            write!(f, ", motif {}", motif)?;
+        }
+        write!(f, ": {}", self.reason)
+    }
+}
+
+/// Result of `scan_sequences()`: hits plus everything that was skipped
+#[derive(Debug, Clone)]
+pub struct ScanResult {
+    /// Hits with columns "label", "motif", "position", "strand", "length", "occupancy"
+    pub hits: DataFrame,
+    /// Skipped sequences and motifs, in input order
+    pub warnings: Vec<ScanWarning>,
+}
+
+/// Checks whether a sequence can be scanned with a motif of the given length.
+///
+/// # Arguments
+/// * `seq` - DNA sequence
+/// * `motif_length` - Length of the motif the sequence is scanned with
+///
+/// # Returns
+/// * `Option<SkipReason>` - Why the sequence cannot be scanned, or `None` if it can
+pub fn check_sequence(seq: &str, motif_length: usize) -> Option<SkipReason> {
+    if let Some((position, base)) = seq
+        .char_indices()
+        .find(|(_, c)| !matches!(c, 'A' | 'C' | 'G' | 'T'))
+    {
+        return Some(SkipReason::AmbiguousBases { position, base });
+    }
+    if seq.len() < motif_length {
+        return Some(SkipReason::TooShort {
+            length: seq.len(),
+            motif_length,
+        });
+    }
+    None
+}
+
+/// What a scan does with sequences containing bases other than A, C, G, T
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AmbiguousPolicy {
+    /// Skip the whole sequence, reporting it as `SkipReason::AmbiguousBases`
+    #[default]
+    Drop,
+    /// Scan the stretches of A, C, G, T between ambiguous bases; windows overlapping an
+    /// ambiguous base are not scored
+    Mask,
+    /// Score IUPAC ambiguity codes with the mean energy of the bases they stand for; see
+    /// `StrandedEwm::lenient_energy_landscape()`
+    ScanLenient,
+}
+
+impl fmt::Display for AmbiguousPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AmbiguousPolicy::Drop => "drop",
+            AmbiguousPolicy::Mask => "mask",
+            AmbiguousPolicy::ScanLenient => "scan-lenient",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for AmbiguousPolicy {
+    type Err = MotifError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('_', "-").as_str() {
+            "drop" => Ok(AmbiguousPolicy::Drop),
+            "mask" => Ok(AmbiguousPolicy::Mask),
+            "scan-lenient" | "lenient" => Ok(AmbiguousPolicy::ScanLenient),
+            _ => Err(MotifError::invalid_parameter(
+                "ambiguous_policy",
+                s,
+                "expected one of drop, mask, scan-lenient",
+            )),
+        }
+    }
+}
+
+/// 0-based (start, exclusive end) of the maximal stretches of A, C, G, T in a sequence that
+/// are at least `min_length` long, i.e. the parts a motif of that length can be scanned on
+/// when ambiguous bases are masked
+pub fn unambiguous_segments(seq: &str, min_length: usize) -> Vec<(usize, usize)> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for (i, base) in seq.bytes().chain([b'N']).enumerate() {
+        if !matches!(base, b'A' | b'C' | b'G' | b'T') {
+            if i - start >= min_length.max(1) {
+                segments.push((start, i));
+            }
+            start = i + 1;
+        }
+    }
+    segments
+}
+
+/// Scans every sequence of a DataFrame, reporting skipped records instead of dropping them
+/// silently
+///
+/// Sequences with non-ACGT characters are skipped entirely; motifs longer than a sequence
+/// are skipped for that sequence only. Every skipped record is returned as a `ScanWarning`
+/// and also passed to `on_warning` as it happens, so callers can log or count them live.
+///
+/// # Arguments
+/// * `df` - DataFrame containing sequences with "label" and "sequence" columns
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are motif IDs
+/// * `mu` - Chemical potential of the transcription factors
+/// * `cutoff` - Minimum occupancy for a window to be reported
+/// * `on_warning` - Callback invoked for every skipped record
+///
+/// # Returns
+/// * `Result<ScanResult, MotifError>` - Hits of all sequences and the skipped records
+///
+/// # Errors
+/// * `MotifError::DataError` - If required columns are missing or DataFrame creation fails
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::scan::scan_sequences;
+///
+/// let result = scan_sequences(&df, &ewms, 9.0, 0.2, |w| eprintln!("skipped {}", w)).unwrap();
+/// println!("{} hits, {} warnings", result.hits.height(), result.warnings.len());
+/// ```
+pub fn scan_sequences<F: FnMut(&ScanWarning)>(
+    df: &DataFrame,
+    ewms: &EWMCollection,
+    mu: f64,
+    cutoff: f64,
+    mut on_warning: F,
+) -> Result<ScanResult, MotifError> {
+    let labels = df
+        .column("label")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .str()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    let sequences = df
+        .column("sequence")
+        .map_err(|e| MotifError::DataError(e.to_string()))?
+        .str()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    let mut motif_ids: Vec<&String> = ewms.keys().collect();
+    motif_ids.sort();
+    let matrices = motif_ids
+        .into_iter()
+        .map(|id| Ok((id.as_str(), StrandedEwm::new(&ewms[id]).with_motif(id)?)))
+        .collect::<Result<Vec<_>, MotifError>>()?;
+
+    let mut warnings: Vec<ScanWarning> = Vec::new();
+    let mut warn = |warning: ScanWarning| {
+        on_warning(&warning);
+        warnings.push(warning);
+    };
+
+    let mut frames: Vec<DataFrame> = Vec::new();
+    for (index, (label, seq)) in labels.into_iter().zip(sequences).enumerate() {
+        let label = label.map(str::to_string);
+        let Some(seq) = seq else {
+            warn(ScanWarning {
+                index,
+                label,
+                motif: None,
+                reason: SkipReason::MissingSequence,
+            });
+            continue;
+        };
+        if let Some(reason) = check_sequence(seq, 0) {
+            warn(ScanWarning {
+                index,
+                label,
+                motif: None,
+                reason,
+            });
+            continue;
+        }
+
+        let mut scannable = Vec::new();
+        for (motif_id, matrix) in &matrices {
+            match check_sequence(seq, matrix.len()) {
+                Some(reason) => warn(ScanWarning {
+                    index,
+                    label: label.clone(),
+                    motif: Some(motif_id.to_string()),
+                    reason,
+                }),
+                None => scannable.push((*motif_id, matrix)),
+            }
+        }
+
+        match stranded_hits(seq, &scannable, mu, cutoff) {
+            Ok(mut hits) => {
+                let n = hits.height();
+                hits.insert_column(
+                    0,
+                    Column::new("label".into(), vec![label.as_deref().unwrap_or(""); n]),
+                )
+                .map_err(|e| MotifError::DataError(e.to_string()))?;
+                frames.push(hits);
+            }
+            Err(e) => warn(ScanWarning {
+                index,
+                label,
+                motif: None,
+                reason: SkipReason::ScanError(e.to_string()),
+            }),
+        }
+    }
+
+    let hits = match frames.split_first() {
+        Some((first, rest)) => {
+            let mut hits = first.clone();
+            for frame in rest {
+                hits.vstack_mut(frame)
+                    .map_err(|e| MotifError::DataError(e.to_string()))?;
+            }
+            hits
+        }
+        None => {
+            let mut empty = find_hits("", &EWMCollection::new(), mu, cutoff)?;
+            empty
+                .insert_column(0, Column::new("label".into(), Vec::<&str>::new()))
+                .map_err(|e| MotifError::DataError(e.to_string()))?;
+            empty
+        }
+    };
+
+    Ok(ScanResult { hits, warnings })
+}
+
+/// Converts scan warnings to a DataFrame for export.
+///
+/// # Arguments
+/// * `warnings` - Warnings, e.g. from `ScanResult::warnings`
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - A DataFrame with:
+///   - "index": Row index of the sequence
+///   - "label": Sequence label (null if unknown)
+///   - "motif": Skipped motif (null if the whole sequence was skipped)
+///   - "reason": Short reason name, e.g. "too_short"
+///   - "message": Human-readable description
+///
+/// # Errors
+/// * `MotifError::DataError` - If DataFrame creation fails
+pub fn warnings_frame(warnings: &[ScanWarning]) -> Result<DataFrame, MotifError> {
+    DataFrame::new(vec![
+        Column::new(
+            "index".into(),
+            warnings.iter().map(|w| w.index as u64).collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "label".into(),
+            warnings
+                .iter()
+                .map(|w| w.label.as_deref())
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "motif".into(),
+            warnings
+                .iter()
+                .map(|w| w.motif.as_deref())
+                .collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "reason".into(),
+            warnings.iter().map(|w| w.reason.kind()).collect::<Vec<_>>(),
+        ),
+        Column::new(
+            "message".into(),
+            warnings
+                .iter()
+                .map(|w| w.reason.to_string())
+                .collect::<Vec<_>>(),
+        ),
+    ])
+    .map_err(|e| MotifError::DataError(e.to_string()))
+}
@@ -0,0 +1,382 @@
+use crate::error::{MotifError, ResultExt};
+use crate::occupancy::{stranded_hits, MotifBank, StrandedEwm};
+use crate::scan::{check_sequence, ScanResult, ScanWarning, SkipReason};
+use crate::types::*;
+use polars::prelude::*;
+use rayon::prelude::*;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Strands reported by a `Scanner`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Strand {
+    /// Forward and reverse strands (the default)
+    #[default]
+    Both,
+    /// Forward strand only
+    Forward,
+    /// Reverse strand only
+    Reverse,
+}
+
+impl Strand {
+    /// Whether windows on the strand named `strand` ("F" or "R") are reported
+    fn includes(&self, strand: &str) -> bool {
+        match self {
+            Strand::Both => true,
+            Strand::Forward => strand == "F",
+            Strand::Reverse => strand == "R",
+        }
+    }
+}
+
+impl fmt::Display for Strand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Strand::Both => "both",
+            Strand::Forward => "forward",
+            Strand::Reverse => "reverse",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Strand {
+    type Err = MotifError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "both" | "+-" => Ok(Strand::Both),
+            "forward" | "f" | "+" => Ok(Strand::Forward),
+            "reverse" | "r" | "-" => Ok(Strand::Reverse),
+            _ => Err(MotifError::invalid_parameter(
+                "strand",
+                s,
+                "expected one of both, forward, reverse",
+            )),
+        }
+    }
+}
+
+/// Builder of a `Scanner`; see `Scanner::builder()`
+#[derive(Debug, Clone)]
+pub struct ScannerBuilder {
+    motifs: Option<EWMCollection>,
+    mu: f64,
+    cutoff: f64,
+    strand: Strand,
+    threads: Option<usize>,
+}
+
+impl Default for ScannerBuilder {
+    fn default() -> Self {
+        ScannerBuilder {
+            motifs: None,
+            mu: 9.0,
+            cutoff: 0.2,
+            strand: Strand::Both,
+            threads: None,
+        }
+    }
+}
+
+impl ScannerBuilder {
+    /// Energy matrices to scan with, keyed by motif ID
+    pub fn motifs(mut self, motifs: EWMCollection) -> Self {
+        self.motifs = Some(motifs);
+        self
+    }
+
+    /// Chemical potential of the transcription factors (default 9)
+    pub fn mu(mut self, mu: f64) -> Self {
+        self.mu = mu;
+        self
+    }
+
+    /// Minimum occupancy of a reported hit (default 0.2)
+    pub fn cutoff(mut self, cutoff: f64) -> Self {
+        self.cutoff = cutoff;
+        self
+    }
+
+    /// Strands to report (default both)
+    pub fn strand(mut self, strand: Strand) -> Self {
+        self.strand = strand;
+        self
+    }
+
+    /// Threads of `Scanner::scan_batch()`; the global rayon pool is used unless set
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Prepares the matrices and the thread pool
+    ///
+    /// # Errors
+    /// * `MotifError::InvalidParameter` - If no motifs were given, `mu` is not finite, the
+    ///   cutoff is outside [0, 1] or `threads` is 0
+    /// * `MotifError::DataError` - If a matrix is malformed
+    pub fn build(self) -> Result<Scanner, MotifError> {
+        let motifs = self
+            .motifs
+            .filter(|motifs| !motifs.is_empty())
+            .ok_or_else(|| MotifError::invalid_parameter("motifs", 0, "no motifs to scan"))?;
+        if !self.mu.is_finite() {
+            return Err(MotifError::invalid_parameter(
+                "mu",
+                self.mu,
+                "must be a finite number",
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.cutoff) {
+            return Err(MotifError::invalid_parameter(
+                "cutoff",
+                self.cutoff,
+                "must be between 0 and 1",
+            ));
+        }
+        let pool = match self.threads {
+            Some(0) => {
+                return Err(MotifError::invalid_parameter(
+                    "threads",
+                    0,
+                    "must be at least 1",
+                ))
+            }
+            Some(threads) => Some(Arc::new(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .map_err(|e| {
+                        MotifError::invalid_parameter("threads", threads, e.to_string())
+                    })?,
+            )),
+            None => None,
+        };
+
+        let bank = MotifBank::new(&motifs)?;
+        let matrices = bank
+            .ids()
+            .iter()
+            .map(|id| Ok((id.clone(), StrandedEwm::new(&motifs[id]).with_motif(id)?)))
+            .collect::<Result<Vec<_>, MotifError>>()?;
+        Ok(Scanner {
+            bank,
+            matrices,
+            mu: self.mu,
+            cutoff: self.cutoff,
+            strand: self.strand,
+            pool,
+        })
+    }
+}
+
+/// Reusable scanner holding prepared matrices and scan parameters, so repeated scans do
+/// not pass (and re-prepare) the motifs, chemical potential and cutoff every time
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::scanner::{Scanner, Strand};
+///
+/// let scanner = Scanner::builder()
+///     .motifs(ewms)
+///     .mu(9.0)
+///     .cutoff(0.2)
+///     .strand(Strand::Forward)
+///     .threads(4)
+///     .build()?;
+/// let hits = scanner.scan("ATCGATCGATCGTAGCTAGC")?;
+/// let result = scanner.scan_batch(&df)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Scanner {
+    bank: MotifBank,
+    /// Matrices in `bank.ids()` order
+    matrices: Vec<(String, StrandedEwm)>,
+    mu: f64,
+    cutoff: f64,
+    strand: Strand,
+    pool: Option<Arc<rayon::ThreadPool>>,
+}
+
+impl Scanner {
+    /// A builder with the defaults mu = 9, cutoff = 0.2, both strands and the global
+    /// thread pool; `motifs()` must be set
+    pub fn builder() -> ScannerBuilder {
+        ScannerBuilder::default()
+    }
+
+    /// Motif IDs, in the order hits and landscape columns are reported
+    pub fn ids(&self) -> &[String] {
+        self.bank.ids()
+    }
+
+    /// Chemical potential of the scans
+    pub fn mu(&self) -> f64 {
+        self.mu
+    }
+
+    /// Minimum occupancy of a reported hit
+    pub fn cutoff(&self) -> f64 {
+        self.cutoff
+    }
+
+    /// Strands that are reported
+    pub fn strand(&self) -> Strand {
+        self.strand
+    }
+
+    /// Hits of every motif on one sequence; the layout of `find_hits()`
+    ///
+    /// # Errors
+    /// * `MotifError::InvalidSequence` - If the sequence is shorter than a motif
+    /// * `MotifError::InvalidInput` - If the sequence contains characters other than A, C, G, T
+    pub fn scan(&self, seq: &str) -> Result<DataFrame, MotifError> {
+        let matrices: Vec<(&str, &StrandedEwm)> = self
+            .matrices
+            .iter()
+            .map(|(id, matrix)| (id.as_str(), matrix))
+            .collect();
+        self.hits(seq, &matrices)
+    }
+
+    /// Scans the "label" and "sequence" columns of a DataFrame, in parallel on the
+    /// scanner's threads; sequences and motifs that cannot be scanned are reported as in
+    /// `scan_sequences()`
+    ///
+    /// # Errors
+    /// * `MotifError::DataError` - If required columns are missing or DataFrame creation fails
+    pub fn scan_batch(&self, df: &DataFrame) -> Result<ScanResult, MotifError> {
+        let labels = df
+            .column("label")
+            .map_err(|e| MotifError::DataError(e.to_string()))?
+            .str()
+            .map_err(|e| MotifError::DataError(e.to_string()))?;
+        let sequences = df
+            .column("sequence")
+            .map_err(|e| MotifError::DataError(e.to_string()))?
+            .str()
+            .map_err(|e| MotifError::DataError(e.to_string()))?;
+        let records: Vec<(Option<&str>, Option<&str>)> =
+            labels.into_iter().zip(sequences).collect();
+
+        let scan_all = || {
+            records
+                .par_iter()
+                .enumerate()
+                .map(|(index, &(label, seq))| self.scan_record(index, label, seq))
+                .collect::<Result<Vec<_>, MotifError>>()
+        };
+        let scanned = match &self.pool {
+            Some(pool) => pool.install(scan_all)?,
+            None => scan_all()?,
+        };
+
+        let mut hits: Option<DataFrame> = None;
+        let mut warnings = Vec::new();
+        for (frame, record_warnings) in scanned {
+            warnings.extend(record_warnings);
+            match (&mut hits, frame) {
+                (Some(hits), Some(frame)) => {
+                    hits.vstack_mut(&frame)
+                        .map_err(|e| MotifError::DataError(e.to_string()))?;
+                }
+                (None, Some(frame)) => hits = Some(frame),
+                (_, None) => {}
+            }
+        }
+        let hits = match hits {
+            Some(hits) => hits,
+            None => labelled(self.hits("", &[])?, None)?,
+        };
+        Ok(ScanResult { hits, warnings })
+    }
+
+    /// Combined occupancy landscape of all motifs at the scanner's chemical potential; the
+    /// layout of `total_landscape()` with only the columns of the scanned strands
+    ///
+    /// # Errors
+    /// * See `MotifBank::total_landscape()`
+    pub fn landscape(&self, seq: &str) -> Result<DataFrame, MotifError> {
+        let landscape = self.bank.total_landscape(seq, self.mu)?;
+        let suffix = match self.strand {
+            Strand::Both => return Ok(landscape),
+            Strand::Forward => "_F",
+            Strand::Reverse => "_R",
+        };
+        let names: Vec<String> = self
+            .ids()
+            .iter()
+            .map(|id| format!("{}{}", id, suffix))
+            .collect();
+        landscape
+            .select(names)
+            .map_err(|e| MotifError::DataError(e.to_string()))
+    }
+
+    /// Hits of `matrices` on `seq` on the scanned strands
+    fn hits(&self, seq: &str, matrices: &[(&str, &StrandedEwm)]) -> Result<DataFrame, MotifError> {
+        let hits = stranded_hits(seq, matrices, self.mu, self.cutoff)?;
+        if self.strand == Strand::Both {
+            return Ok(hits);
+        }
+        let keep: Vec<bool> = hits
+            .column("strand")
+            .map_err(|e| MotifError::DataError(e.to_string()))?
+            .str()
+            .map_err(|e| MotifError::DataError(e.to_string()))?
+            .into_iter()
+            .map(|strand| strand.is_some_and(|s| self.strand.includes(s)))
+            .collect();
+        hits.filter(&BooleanChunked::from_slice("keep".into(), &keep))
+            .map_err(|e| MotifError::DataError(e.to_string()))
+    }
+
+    /// Labelled hits and skipped records of one row of `scan_batch()`
+    fn scan_record(
+        &self,
+        index: usize,
+        label: Option<&str>,
+        seq: Option<&str>,
+    ) -> Result<(Option<DataFrame>, Vec<ScanWarning>), MotifError> {
+        let label = label.map(str::to_string);
+        let skip = |motif: Option<&str>, reason: SkipReason| ScanWarning {
+            index,
+            label: label.clone(),
+            motif: motif.map(str::to_string),
+            reason,
+        };
+        let Some(seq) = seq else {
+            return Ok((None, vec![skip(None, SkipReason::MissingSequence)]));
+        };
+        if let Some(reason) = check_sequence(seq, 0) {
+            return Ok((None, vec![skip(None, reason)]));
+        }
+
+        let mut warnings = Vec::new();
+        let mut scannable = Vec::new();
+        for (motif_id, matrix) in &self.matrices {
+            match check_sequence(seq, matrix.len()) {
+                Some(reason) => warnings.push(skip(Some(motif_id), reason)),
+                None => scannable.push((motif_id.as_str(), matrix)),
+            }
+        }
+        match self.hits(seq, &scannable) {
+            Ok(hits) => Ok((Some(labelled(hits, label.as_deref())?), warnings)),
+            Err(e) => {
+                warnings.push(skip(None, SkipReason::ScanError(e.to_string())));
+                Ok((None, warnings))
+            }
+        }
+    }
+}
+
+/// `hits` with a leading "label" column
+fn labelled(mut hits: DataFrame, label: Option<&str>) -> Result<DataFrame, MotifError> {
+    let n = hits.height();
+    hits.insert_column(0, Column::new("label".into(), vec![label.unwrap_or(""); n]))
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    Ok(hits)
+}
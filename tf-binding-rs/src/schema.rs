@@ -0,0 +1,342 @@
+use crate::coords::{parse_labels, project_hits, LabelParser};
+use crate::error::{MotifError, ResultExt};
+use crate::fasta::reverse_complement;
+use crate::occupancy::StrandedEwm;
+use crate::pvalue::{ScoreDistribution, DEFAULT_STEP, UNIFORM_BACKGROUND, WINDOWS_PER_KB};
+use crate::types::*;
+use polars::prelude::*;
+use std::collections::HashMap;
+
+/// Version of the hit table schema written by this crate
+pub const SCHEMA_VERSION: u32 = 4;
+
+/// Columns a version of the hit table schema added
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchemaVersion {
+    pub version: u32,
+    /// Columns added in this version, in output order
+    pub added: &'static [&'static str],
+    pub description: &'static str,
+}
+
+/// Every version of the hit table schema, oldest first; the columns of later versions are
+/// optional in the outputs, the columns of version 1 are always written
+pub const SCHEMA_HISTORY: [SchemaVersion; 4] = [
+    SchemaVersion {
+        version: 1,
+        added: &[
+            "label",
+            "position",
+            "motif",
+            "strand",
+            "length",
+            "occupancy",
+        ],
+        description: "hit positions, strands and occupancies",
+    },
+    SchemaVersion {
+        version: 2,
+        added: &[
+            "pvalue",
+            "neg_log10_pvalue",
+            "chrom",
+            "genomic_start",
+            "genomic_end",
+            "genomic_strand",
+        ],
+        description: "p-values and genomic coordinates",
+    },
+    SchemaVersion {
+        version: 3,
+        added: &["energy", "relative_score", "matched_seq"],
+        description: "binding energies, relative scores and matched sequences",
+    },
+    SchemaVersion {
+        version: 4,
+        added: &["background_per_kb"],
+        description: "expected background hits per kb",
+    },
+];
+
+/// Columns of the current schema in the order motif-scanner writes them
+const OUTPUT_ORDER: [&str; 16] = [
+    "label",
+    "position",
+    "motif",
+    "strand",
+    "length",
+    "occupancy",
+    "energy",
+    "relative_score",
+    "matched_seq",
+    "pvalue",
+    "neg_log10_pvalue",
+    "background_per_kb",
+    "chrom",
+    "genomic_start",
+    "genomic_end",
+    "genomic_strand",
+];
+
+/// Type of a column of the hit table schema
+fn column_type(name: &str) -> DataType {
+    match name {
+        "position" | "length" => DataType::Int32,
+        "genomic_start" | "genomic_end" => DataType::Int64,
+        "motif" | "strand" | "chrom" | "genomic_strand" | "matched_seq" => DataType::String,
+        _ => DataType::Float64,
+    }
+}
+
+/// Oldest schema version with all the versioned columns of a table
+///
+/// Tables whose columns were selected with `--columns` may be detected as older than the
+/// version that wrote them; use the version recorded in the schema sidecar or run manifest
+/// when there is one.
+pub fn detect_version(df: &DataFrame) -> u32 {
+    SCHEMA_HISTORY
+        .iter()
+        .rev()
+        .find(|v| v.added.iter().any(|name| df.column(name).is_ok()))
+        .map_or(1, |v| v.version)
+}
+
+/// Columns of the current schema that a table lacks, in the order motif-scanner writes them
+pub fn missing_columns(df: &DataFrame) -> Vec<&'static str> {
+    OUTPUT_ORDER
+        .into_iter()
+        .filter(|name| df.column(name).is_err())
+        .collect()
+}
+
+/// What `migrate()` can use to compute added columns instead of leaving them null
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MigrationSources<'a> {
+    /// Chemical potential the occupancies were computed with, for "energy" and the p-values
+    pub mu: Option<f64>,
+    /// Motifs of the scan, for "relative_score" and the p-values
+    pub ewms: Option<&'a EWMCollection>,
+    /// The scanned sequences, in input order, for "matched_seq" and the genomic columns
+    pub sequences: Option<&'a DataFrame>,
+    /// Parser of the sequence labels, for the genomic columns
+    pub label_parser: Option<&'a LabelParser>,
+}
+
+/// Energy matrix of a hit's motif, by full ID or by the name before the first '_'
+fn find_motif<'a>(ewms: &'a HashMap<String, StrandedEwm>, motif: &str) -> Option<&'a StrandedEwm> {
+    ewms.get(motif).or_else(|| {
+        ewms.iter()
+            .find(|(id, _)| id.split('_').next() == Some(motif))
+            .map(|(_, ewm)| ewm)
+    })
+}
+
+/// Upgrades a hit table written by an older version to the current schema
+///
+/// Adds every column of `missing_columns()`, after the existing ones, so tables written by
+/// different versions can be concatenated. Added columns are computed when `sources` has
+/// what they need and null otherwise:
+/// - "energy": from the occupancy and `mu`, inverting `1 / (1 + exp(energy - mu))`
+/// - "relative_score": from the energy and the energy range of the motif in `ewms`
+/// - "pvalue", "neg_log10_pvalue", "background_per_kb": from the occupancy, `mu` and the
+///   motif in `ewms`
+/// - "matched_seq": from the window in `sequences`, row "label" of the input
+/// - "chrom", "genomic_start", "genomic_end", "genomic_strand": from the labels of
+///   `sequences` and `label_parser`
+///
+/// Existing columns are never changed.
+///
+/// # Arguments
+/// * `hits` - Hit table of any schema version
+/// * `sources` - Parameters and inputs of the scan that wrote it
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - The hits with all columns of the current schema
+///
+/// # Errors
+/// * `MotifError::DataError` - If a column of version 1 is missing or has the wrong type
+/// * `MotifError::InvalidInput` - If a hit's label is not a row of `sequences`
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::schema::{migrate, MigrationSources};
+///
+/// let sources = MigrationSources { mu: Some(9.0), ewms: Some(&ewms), ..Default::default() };
+/// let hits = migrate(&old_hits, &sources).unwrap();
+/// ```
+pub fn migrate(hits: &DataFrame, sources: &MigrationSources) -> Result<DataFrame, MotifError> {
+    let data_error = |e: PolarsError| MotifError::DataError(e.to_string());
+    let missing = missing_columns(hits);
+    let mut migrated = hits.clone();
+    if missing.is_empty() {
+        return Ok(migrated);
+    }
+    let height = hits.height();
+    let motifs = hits
+        .column("motif")
+        .and_then(|c| c.str())
+        .map_err(data_error)?;
+    let occupancies = hits
+        .column("occupancy")
+        .and_then(|c| c.f64())
+        .map_err(data_error)?;
+
+    let matrices: Option<HashMap<String, StrandedEwm>> = sources
+        .ewms
+        .map(|ewms| {
+            ewms.iter()
+                .map(|(id, ewm)| Ok((id.clone(), StrandedEwm::new(ewm).with_motif(id)?)))
+                .collect::<Result<_, MotifError>>()
+        })
+        .transpose()?;
+    let energies: Option<Vec<Option<f64>>> = sources.mu.map(|mu| {
+        occupancies
+            .into_iter()
+            .map(|occ| occ.map(|occ| mu - (occ / (1.0 - occ)).ln()))
+            .collect()
+    });
+
+    let mut computed: HashMap<&str, Column> = HashMap::new();
+    if let Some(energies) = &energies {
+        computed.insert("energy", Column::new("energy".into(), energies.clone()));
+    }
+    if let (Some(energies), Some(matrices)) = (&energies, &matrices) {
+        let relative: Vec<Option<f64>> = motifs
+            .into_iter()
+            .zip(energies)
+            .map(|(motif, energy)| {
+                let (lowest, highest) = find_motif(matrices, motif?)?.energy_range();
+                Some((highest - (*energy)?) / (highest - lowest))
+            })
+            .collect();
+        computed.insert(
+            "relative_score",
+            Column::new("relative_score".into(), relative),
+        );
+    }
+    if let (Some(mu), Some(ewms), true) = (
+        sources.mu,
+        sources.ewms,
+        missing.contains(&"pvalue")
+            || missing.contains(&"neg_log10_pvalue")
+            || missing.contains(&"background_per_kb"),
+    ) {
+        // one distribution per motif of the table
+        let mut distributions: HashMap<&str, Option<ScoreDistribution>> = HashMap::new();
+        let mut pvalues = Vec::with_capacity(height);
+        for (motif, occ) in motifs.into_iter().zip(occupancies) {
+            let (Some(motif), Some(occ)) = (motif, occ) else {
+                pvalues.push(None);
+                continue;
+            };
+            if !distributions.contains_key(motif) {
+                let ewm = ewms.get(motif).or_else(|| {
+                    ewms.iter()
+                        .find(|(id, _)| id.split('_').next() == Some(motif))
+                        .map(|(_, ewm)| ewm)
+                });
+                let distribution = ewm
+                    .map(|ewm| ScoreDistribution::new(ewm, UNIFORM_BACKGROUND, DEFAULT_STEP))
+                    .transpose()?;
+                distributions.insert(motif, distribution);
+            }
+            pvalues.push(
+                distributions[motif]
+                    .as_ref()
+                    .map(|d| d.occupancy_pvalue(occ, mu)),
+            );
+        }
+        let neg_log10: Vec<Option<f64>> = pvalues.iter().map(|p| p.map(|p| -p.log10())).collect();
+        let per_kb: Vec<Option<f64>> = pvalues
+            .iter()
+            .map(|p| p.map(|p| p * WINDOWS_PER_KB))
+            .collect();
+        computed.insert(
+            "background_per_kb",
+            Column::new("background_per_kb".into(), per_kb),
+        );
+        computed.insert("pvalue", Column::new("pvalue".into(), pvalues));
+        computed.insert(
+            "neg_log10_pvalue",
+            Column::new("neg_log10_pvalue".into(), neg_log10),
+        );
+    }
+    if let Some(sequences) = sources.sequences {
+        let seqs = sequences
+            .column("sequence")
+            .and_then(|c| c.str())
+            .map_err(data_error)?;
+        // tables read from CSV without a schema sidecar have 64-bit integers
+        let integers = |name: &str| {
+            hits.column(name)
+                .and_then(|c| c.cast(&DataType::Int64))
+                .map_err(data_error)
+        };
+        let labels = integers("label")?;
+        let labels = labels.i64().map_err(data_error)?;
+        let (positions, lengths) = (integers("position")?, integers("length")?);
+        let positions = positions.i64().map_err(data_error)?;
+        let lengths = lengths.i64().map_err(data_error)?;
+        let strands = hits
+            .column("strand")
+            .and_then(|c| c.str())
+            .map_err(data_error)?;
+
+        let mut matched = Vec::with_capacity(height);
+        for (((label, position), length), strand) in
+            labels.into_iter().zip(positions).zip(lengths).zip(strands)
+        {
+            let (Some(label), Some(position), Some(length)) = (label, position, length) else {
+                matched.push(None);
+                continue;
+            };
+            let seq = usize::try_from(label)
+                .ok()
+                .filter(|&row| row < seqs.len())
+                .and_then(|row| seqs.get(row))
+                .ok_or_else(|| {
+                    MotifError::InvalidInput(format!(
+                        "hit label {} is not a row of the sequences",
+                        label
+                    ))
+                })?;
+            let (start, end) = (position as usize, (position + length) as usize);
+            let window = seq.get(start..end).ok_or_else(|| {
+                MotifError::invalid_sequence(start, "hit window extends past its sequence")
+            })?;
+            matched.push(Some(if strand == Some("R") {
+                reverse_complement(window)?
+            } else {
+                window.to_string()
+            }));
+        }
+        computed.insert("matched_seq", Column::new("matched_seq".into(), matched));
+
+        let genomic = ["chrom", "genomic_start", "genomic_end", "genomic_strand"];
+        if let (Some(parser), true) = (
+            sources.label_parser,
+            genomic.iter().all(|name| missing.contains(name)),
+        ) {
+            let mut regions = parse_labels(sequences, "label", parser)?;
+            regions
+                .with_column(Column::new(
+                    "label".into(),
+                    (0..sequences.height() as i64).collect::<Vec<_>>(),
+                ))
+                .map_err(data_error)?;
+            let projected = project_hits(hits, &regions)?;
+            for name in genomic {
+                computed.insert(name, projected.column(name).map_err(data_error)?.clone());
+            }
+        }
+    }
+
+    for name in missing {
+        let column = match computed.remove(name) {
+            Some(column) => column.cast(&column_type(name)).map_err(data_error)?,
+            None => Column::full_null(name.into(), height, &column_type(name)),
+        };
+        migrated.with_column(column).map_err(data_error)?;
+    }
+    Ok(migrated)
+}
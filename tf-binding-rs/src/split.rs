@@ -0,0 +1,250 @@
+use crate::coords::LabelParser;
+use crate::error::MotifError;
+use polars::prelude::*;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::collections::{BTreeMap, HashMap};
+
+/// What sequences are grouped or stratified by when they are dealt into folds
+#[derive(Debug, Clone)]
+pub enum SplitKey {
+    /// Part of the label before the first occurrence of the separator, so the variants of
+    /// one element (`enh12_wt`, `enh12_mut3`) share a key; labels without the separator
+    /// are their own key
+    LabelPrefix(char),
+    /// Chromosome of the label, parsed with the parser, for leave-chromosome-out splits of
+    /// genomic sequences that may overlap or be homologous to their neighbours
+    Chromosome(LabelParser),
+    /// One of this many equal-width bins of GC content, for folds with the same GC
+    /// distribution
+    GcBin(usize),
+    /// Value of a string column, e.g. the "source_label" of `generate_controls()`
+    Column(String),
+}
+
+/// Key of every row of a sequence DataFrame, for `group_folds()` and `stratified_folds()`
+///
+/// # Arguments
+/// * `df` - DataFrame with a "label" column, a "sequence" column for `SplitKey::GcBin` or
+///   the column of `SplitKey::Column`
+/// * `key` - What to key the rows by
+///
+/// # Returns
+/// * `Result<Vec<String>, MotifError>` - One key per row; GC bins are keyed by their index
+///
+/// # Errors
+/// * `MotifError::InvalidInput` - If a row has no value, or a label does not parse for
+///   `SplitKey::Chromosome`
+/// * `MotifError::InvalidParameter` - If `SplitKey::GcBin` has 0 bins
+/// * `MotifError::DataError` - If the column is missing or not a string column
+pub fn split_keys(df: &DataFrame, key: &SplitKey) -> Result<Vec<String>, MotifError> {
+    let column = match key {
+        SplitKey::LabelPrefix(_) | SplitKey::Chromosome(_) => "label",
+        SplitKey::GcBin(_) => "sequence",
+        SplitKey::Column(name) => name.as_str(),
+    };
+    if let SplitKey::GcBin(0) = key {
+        return Err(MotifError::invalid_parameter(
+            "bins",
+            0,
+            "must be at least 1",
+        ));
+    }
+    let values = df
+        .column(column)
+        .and_then(|c| c.str())
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(index, value)| {
+            let value = value.ok_or_else(|| {
+                MotifError::InvalidInput(format!("row {} has no {}", index, column))
+            })?;
+            Ok(match key {
+                SplitKey::LabelPrefix(separator) => {
+                    value.split(*separator).next().unwrap_or(value).to_string()
+                }
+                SplitKey::Chromosome(parser) => {
+                    parser
+                        .parse(value)
+                        .ok_or_else(|| {
+                            MotifError::InvalidInput(format!("label {} has no coordinates", value))
+                        })?
+                        .chrom
+                }
+                SplitKey::GcBin(bins) => {
+                    let gc = value
+                        .bytes()
+                        .filter(|b| matches!(b, b'G' | b'C' | b'g' | b'c'))
+                        .count() as f64
+                        / value.len().max(1) as f64;
+                    ((gc * *bins as f64) as usize).min(bins - 1).to_string()
+                }
+                SplitKey::Column(_) => value.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Deals rows into folds keeping every key in a single fold, so related sequences are
+/// never on both sides of a split
+///
+/// Keys are dealt in decreasing size into the fold with the fewest rows so far, ties
+/// broken by a shuffle with `seed`, which keeps the folds about equally large.
+///
+/// # Arguments
+/// * `keys` - Key of every row, e.g. from `split_keys()`
+/// * `folds` - Number of folds
+/// * `seed` - Seed of the shuffle, so folds are reproducible
+///
+/// # Returns
+/// * `Result<Vec<usize>, MotifError>` - Fold of every row, from 0 to `folds - 1`
+///
+/// # Errors
+/// * `MotifError::InvalidParameter` - If `folds` is below 2 or above the number of keys
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::split::{group_folds, split_keys, SplitKey};
+///
+/// let keys = split_keys(&sequences, &SplitKey::LabelPrefix('_'))?;
+/// let folds = group_folds(&keys, 5, 42)?;
+/// ```
+pub fn group_folds(keys: &[String], folds: usize, seed: u64) -> Result<Vec<usize>, MotifError> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_of: HashMap<&str, usize> = HashMap::new();
+    for (row, key) in keys.iter().enumerate() {
+        let group = *group_of.entry(key.as_str()).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[group].push(row);
+    }
+    check_folds(folds, groups.len(), "keys")?;
+
+    groups.shuffle(&mut StdRng::seed_from_u64(seed));
+    // stable, so groups of equal size stay in shuffled order
+    groups.sort_by_key(|group| std::cmp::Reverse(group.len()));
+    let mut sizes = vec![0; folds];
+    let mut fold_of = vec![0; keys.len()];
+    for group in groups {
+        let (fold, _) = sizes
+            .iter()
+            .enumerate()
+            .min_by_key(|&(_, size)| *size)
+            .expect("at least 2 folds");
+        sizes[fold] += group.len();
+        for row in group {
+            fold_of[row] = fold;
+        }
+    }
+    Ok(fold_of)
+}
+
+/// Deals rows into folds so every key is spread as evenly as possible over the folds,
+/// e.g. to give every fold the same GC distribution or class balance
+///
+/// The rows of each key, in sorted key order, are shuffled with `seed` and dealt in turn,
+/// continuing from the fold the previous key stopped at.
+///
+/// # Arguments
+/// * `keys` - Key of every row, e.g. from `split_keys()`
+/// * `folds` - Number of folds
+/// * `seed` - Seed of the shuffle, so folds are reproducible
+///
+/// # Returns
+/// * `Result<Vec<usize>, MotifError>` - Fold of every row, from 0 to `folds - 1`
+///
+/// # Errors
+/// * `MotifError::InvalidParameter` - If `folds` is below 2 or above the number of rows
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::split::{split_keys, stratified_folds, SplitKey};
+///
+/// let keys = split_keys(&sequences, &SplitKey::GcBin(5))?;
+/// let folds = stratified_folds(&keys, 5, 42)?;
+/// ```
+pub fn stratified_folds(
+    keys: &[String],
+    folds: usize,
+    seed: u64,
+) -> Result<Vec<usize>, MotifError> {
+    check_folds(folds, keys.len(), "rows")?;
+    let mut strata: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (row, key) in keys.iter().enumerate() {
+        strata.entry(key.as_str()).or_default().push(row);
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut fold_of = vec![0; keys.len()];
+    let mut next = 0;
+    for rows in strata.values_mut() {
+        rows.shuffle(&mut rng);
+        for &row in rows.iter() {
+            fold_of[row] = next;
+            next = (next + 1) % folds;
+        }
+    }
+    Ok(fold_of)
+}
+
+/// Splits a DataFrame into the rows outside and inside one fold
+///
+/// # Arguments
+/// * `df` - Any DataFrame, e.g. sequences or a feature matrix
+/// * `fold_of` - Fold of every row, e.g. from `group_folds()`
+/// * `test_fold` - Fold of the test rows
+///
+/// # Returns
+/// * `Result<(DataFrame, DataFrame), MotifError>` - The training and test rows, in their
+///   original order
+///
+/// # Errors
+/// * `MotifError::InvalidInput` - If `fold_of` does not have one fold per row
+/// * `MotifError::DataError` - If filtering fails
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::split::{group_folds, split_keys, train_test_split, SplitKey};
+///
+/// let keys = split_keys(&sequences, &SplitKey::Chromosome(LabelParser::default()))?;
+/// let (train, test) = train_test_split(&sequences, &group_folds(&keys, 5, 42)?, 0)?;
+/// ```
+pub fn train_test_split(
+    df: &DataFrame,
+    fold_of: &[usize],
+    test_fold: usize,
+) -> Result<(DataFrame, DataFrame), MotifError> {
+    if fold_of.len() != df.height() {
+        return Err(MotifError::InvalidInput(format!(
+            "{} folds for {} rows",
+            fold_of.len(),
+            df.height()
+        )));
+    }
+    let test: Vec<bool> = fold_of.iter().map(|&fold| fold == test_fold).collect();
+    let test = BooleanChunked::from_slice("test".into(), &test);
+    let data_error = |e: PolarsError| MotifError::DataError(e.to_string());
+    Ok((
+        df.filter(&!&test).map_err(data_error)?,
+        df.filter(&test).map_err(data_error)?,
+    ))
+}
+
+fn check_folds(folds: usize, available: usize, unit: &str) -> Result<(), MotifError> {
+    if folds < 2 || folds > available {
+        return Err(MotifError::invalid_parameter(
+            "folds",
+            folds,
+            format!(
+                "must be between 2 and the number of {} ({})",
+                unit, available
+            ),
+        ));
+    }
+    Ok(())
+}
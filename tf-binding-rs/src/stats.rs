@@ -0,0 +1,317 @@
+use crate::error::MotifError;
+use polars::prelude::*;
+use statrs::distribution::{Binomial, Discrete};
+use std::collections::BTreeMap;
+
+/// Pearson correlation of two equally long samples
+///
+/// # Returns
+/// * `Option<f64>` - The correlation, or `None` if the lengths differ, there are fewer than two
+///   values or either sample is constant
+pub fn pearson_correlation(x: &[f64], y: &[f64]) -> Option<f64> {
+    if x.len() != y.len() || x.len() < 2 {
+        return None;
+    }
+    let n = x.len() as f64;
+    let (mean_x, mean_y) = (x.iter().sum::<f64>() / n, y.iter().sum::<f64>() / n);
+    let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+    for (a, b) in x.iter().zip(y) {
+        let (dx, dy) = (a - mean_x, b - mean_y);
+        cov += dx * dy;
+        var_x += dx * dx;
+        var_y += dy * dy;
+    }
+    (var_x > 0.0 && var_y > 0.0).then(|| cov / (var_x * var_y).sqrt())
+}
+
+/// Two-sided exact binomial test
+///
+/// The p-value is the total probability of all outcomes that are at most as likely as the
+/// observed one under `Binomial(trials, p)`, as in R's `binom.test`.
+///
+/// # Arguments
+/// * `successes` - Observed number of successes
+/// * `trials` - Number of trials
+/// * `p` - Success probability under the null hypothesis
+///
+/// # Returns
+/// * `Result<f64, MotifError>` - The p-value, 1.0 when there are no trials
+///
+/// # Errors
+/// * `MotifError::InvalidParameter` - If `p` is outside [0, 1] or `successes > trials`
+pub fn binomial_test(successes: u64, trials: u64, p: f64) -> Result<f64, MotifError> {
+    if successes > trials {
+        return Err(MotifError::invalid_parameter(
+            "successes",
+            successes,
+            format!("must not exceed trials ({})", trials),
+        ));
+    }
+    let binomial = Binomial::new(p, trials)
+        .map_err(|e| MotifError::invalid_parameter("p", p, e.to_string()))?;
+    if trials == 0 {
+        return Ok(1.0);
+    }
+
+    // relative tolerance so outcomes exactly as likely as the observed one are counted
+    let observed = binomial.pmf(successes) * (1.0 + 1e-7);
+    let pvalue: f64 = (0..=trials)
+        .map(|k| binomial.pmf(k))
+        .filter(|&pmf| pmf <= observed)
+        .sum();
+    Ok(pvalue.min(1.0))
+}
+
+/// Summarizes the strand balance of the hits of every motif
+///
+/// Most motifs bind both orientations equally, so a strong excess of one strand across a
+/// sequence set points to either a scanning or orientation bug upstream (e.g. sequences
+/// partly reverse-complemented) or a genuinely oriented element, such as sites positioned
+/// relative to a TSS. Each motif's forward count is tested against an even split with a
+/// two-sided exact binomial test.
+///
+/// # Arguments
+/// * `hits` - Hit table with "motif" and "strand" ("F" or "R") columns, such as the output
+///   of `find_hits()`
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - One row per motif, sorted by motif, with columns:
+///   - "motif": Motif ID
+///   - "forward", "reverse": Number of hits on each strand
+///   - "forward_fraction": Fraction of hits on the forward strand
+///   - "ratio": Forward over reverse hits (null without reverse hits)
+///   - "pvalue": Binomial test p-value for strand bias
+///
+/// # Errors
+/// * `MotifError::DataError` - If a column is missing or has the wrong type
+/// * `MotifError::InvalidInput` - If a strand is neither "F" nor "R"
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::stats::strand_bias;
+///
+/// let bias = strand_bias(&hits).unwrap();
+/// println!("{}", bias);
+/// ```
+pub fn strand_bias(hits: &DataFrame) -> Result<DataFrame, MotifError> {
+    let data_error = |e: PolarsError| MotifError::DataError(e.to_string());
+    let motifs = hits
+        .column("motif")
+        .and_then(|c| c.str())
+        .map_err(data_error)?;
+    let strands = hits
+        .column("strand")
+        .and_then(|c| c.str())
+        .map_err(data_error)?;
+
+    let mut counts: BTreeMap<&str, (u64, u64)> = BTreeMap::new();
+    for (motif, strand) in motifs.into_iter().zip(strands) {
+        let (Some(motif), Some(strand)) = (motif, strand) else {
+            continue;
+        };
+        let count = counts.entry(motif).or_default();
+        match strand {
+            "F" => count.0 += 1,
+            "R" => count.1 += 1,
+            _ => {
+                return Err(MotifError::InvalidInput(format!(
+                    "invalid strand '{}' of motif {}",
+                    strand, motif
+                )))
+            }
+        }
+    }
+
+    let mut forward = Vec::with_capacity(counts.len());
+    let mut reverse = Vec::with_capacity(counts.len());
+    let mut fractions = Vec::with_capacity(counts.len());
+    let mut ratios = Vec::with_capacity(counts.len());
+    let mut pvalues = Vec::with_capacity(counts.len());
+    for &(f, r) in counts.values() {
+        forward.push(f);
+        reverse.push(r);
+        fractions.push(f as f64 / (f + r) as f64);
+        ratios.push((r > 0).then(|| f as f64 / r as f64));
+        pvalues.push(binomial_test(f, f + r, 0.5)?);
+    }
+
+    DataFrame::new(vec![
+        Column::new("motif".into(), counts.keys().copied().collect::<Vec<_>>()),
+        Column::new("forward".into(), forward),
+        Column::new("reverse".into(), reverse),
+        Column::new("forward_fraction".into(), fractions),
+        Column::new("ratio".into(), ratios),
+        Column::new("pvalue".into(), pvalues),
+    ])
+    .map_err(data_error)
+}
+
+/// Correlation matrix of the motif columns of a feature matrix across its sequences
+///
+/// Strongly correlated motifs are redundant database entries or factors of one
+/// co-varying program in the library; either way a model cannot tell them apart. Each pair
+/// is correlated over the sequences where both are non-null.
+///
+/// # Arguments
+/// * `features` - Feature matrix, e.g. from `features::occupancy_features()`; the "label"
+///   column and other non-numeric columns are ignored
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - One row per motif, in column order, with a "motif"
+///   column and one column per motif holding the Pearson correlation (null when it is
+///   undefined, e.g. for a motif without variance)
+///
+/// # Errors
+/// * `MotifError::DataError` - If a column cannot be converted to f64
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::stats::{cluster_correlation, occupancy_correlation};
+///
+/// let features = features::occupancy_features(&sequences, &ewms, 9.0)?;
+/// let correlation = cluster_correlation(&occupancy_correlation(&features)?)?;
+/// ```
+pub fn occupancy_correlation(features: &DataFrame) -> Result<DataFrame, MotifError> {
+    let data_error = |e: PolarsError| MotifError::DataError(e.to_string());
+    let mut motifs = Vec::new();
+    let mut columns: Vec<Vec<Option<f64>>> = Vec::new();
+    for column in features.get_columns() {
+        if !column.dtype().is_numeric() || column.name() == "label" {
+            continue;
+        }
+        let values = column.cast(&DataType::Float64).map_err(data_error)?;
+        motifs.push(column.name().to_string());
+        columns.push(values.f64().map_err(data_error)?.into_iter().collect());
+    }
+
+    let mut matrix = vec![vec![None; motifs.len()]; motifs.len()];
+    for (i, x) in columns.iter().enumerate() {
+        for (j, y) in columns.iter().enumerate().skip(i) {
+            let (x, y): (Vec<f64>, Vec<f64>) = x
+                .iter()
+                .zip(y)
+                .filter_map(|(a, b)| Some(((*a)?, (*b)?)))
+                .unzip();
+            let r = pearson_correlation(&x, &y);
+            matrix[i][j] = r;
+            matrix[j][i] = r;
+        }
+    }
+    square_frame(&motifs, &matrix, &(0..motifs.len()).collect::<Vec<_>>())
+}
+
+/// Reorders a correlation matrix so that correlated motifs are adjacent
+///
+/// Motifs are clustered by average linkage on the distance 1 - r (r = 0 where the
+/// correlation is null) and listed in the leaf order of the tree, the order a clustered
+/// heatmap shows; see `output::write_heatmap_svg()`.
+///
+/// # Arguments
+/// * `correlation` - Square matrix with a "motif" column, from `occupancy_correlation()`
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - The matrix with rows and columns in clustered order
+///
+/// # Errors
+/// * `MotifError::InvalidInput` - If the columns besides "motif" are not the motifs of the
+///   rows, in the same order
+/// * `MotifError::DataError` - If a column is missing or cannot be converted to f64
+pub fn cluster_correlation(correlation: &DataFrame) -> Result<DataFrame, MotifError> {
+    let data_error = |e: PolarsError| MotifError::DataError(e.to_string());
+    let motifs: Vec<String> = correlation
+        .column("motif")
+        .and_then(|c| c.str())
+        .map_err(data_error)?
+        .into_iter()
+        .map(|motif| motif.unwrap_or_default().to_string())
+        .collect();
+    let columns: Vec<&str> = correlation
+        .get_column_names()
+        .into_iter()
+        .map(|name| name.as_str())
+        .filter(|&name| name != "motif")
+        .collect();
+    if columns != motifs {
+        return Err(MotifError::InvalidInput(
+            "the correlation matrix is not square with matching motif columns".to_string(),
+        ));
+    }
+
+    let mut matrix = vec![Vec::with_capacity(motifs.len()); motifs.len()];
+    for name in &columns {
+        let values = correlation
+            .column(name)
+            .and_then(|c| c.cast(&DataType::Float64))
+            .map_err(data_error)?;
+        for (row, value) in matrix.iter_mut().zip(values.f64().map_err(data_error)?) {
+            row.push(value);
+        }
+    }
+    let distances = matrix
+        .iter()
+        .map(|row| row.iter().map(|r| 1.0 - r.unwrap_or(0.0)).collect())
+        .collect();
+    square_frame(&motifs, &matrix, &average_linkage_order(distances))
+}
+
+/// Leaf order of the average-linkage clustering of a distance matrix
+#[allow(clippy::needless_range_loop)]
+fn average_linkage_order(mut distances: Vec<Vec<f64>>) -> Vec<usize> {
+    let n = distances.len();
+    let mut clusters: Vec<Option<Vec<usize>>> = (0..n).map(|i| Some(vec![i])).collect();
+    for _ in 1..n {
+        let mut closest: Option<(f64, usize, usize)> = None;
+        for i in 0..n {
+            for j in i + 1..n {
+                if clusters[i].is_none() || clusters[j].is_none() {
+                    continue;
+                }
+                let d = distances[i][j];
+                let closer = match closest {
+                    Some((best, _, _)) => d < best,
+                    None => true,
+                };
+                if closer {
+                    closest = Some((d, i, j));
+                }
+            }
+        }
+        let Some((_, i, j)) = closest else {
+            break;
+        };
+
+        // merge j into i, with distances averaged over the leaves of both
+        let merged = clusters[j].take().expect("active cluster");
+        let kept = clusters[i].as_mut().expect("active cluster");
+        let (a, b) = (kept.len() as f64, merged.len() as f64);
+        for k in 0..n {
+            let d = (a * distances[i][k] + b * distances[j][k]) / (a + b);
+            distances[i][k] = d;
+            distances[k][i] = d;
+        }
+        kept.extend(merged);
+    }
+    clusters.into_iter().flatten().flatten().collect()
+}
+
+/// A square motif × motif matrix as a DataFrame, with rows and columns in `order`
+fn square_frame(
+    motifs: &[String],
+    matrix: &[Vec<Option<f64>>],
+    order: &[usize],
+) -> Result<DataFrame, MotifError> {
+    let mut columns = vec![Column::new(
+        "motif".into(),
+        order
+            .iter()
+            .map(|&i| motifs[i].as_str())
+            .collect::<Vec<_>>(),
+    )];
+    for &j in order {
+        columns.push(Column::new(
+            motifs[j].as_str().into(),
+            order.iter().map(|&i| matrix[i][j]).collect::<Vec<_>>(),
+        ));
+    }
+    DataFrame::new(columns).map_err(|e| MotifError::DataError(e.to_string()))
+}
@@ -0,0 +1,146 @@
+//! Occupancy scanning as a table function over Arrow record batches
+//!
+//! SQL engines call table functions in two phases: a bind phase that needs the output
+//! schema before any data is read, and repeated calls that each turn one chunk of input
+//! rows into output rows. `ScanTable` follows that shape so it can back a DuckDB table
+//! function (`vtab` in duckdb-rs, or an Arrow scan registered with the connection) or a
+//! DataFusion `TableProvider`: `schema()` answers the bind phase and `scan_batch()`
+//! turns a batch of sequences into a batch of hits. Registered under a name such as
+//! `scan_occupancy`, a query over an existing table could then read:
+//!
+//! ```sql
+//! SELECT motif, count(*) FROM scan_occupancy((SELECT id, seq FROM enhancers)) GROUP BY motif;
+//! ```
+
+use crate::arrow::{from_arrow, to_arrow};
+use crate::error::MotifError;
+use crate::scan::scan_sequences;
+use crate::types::EWMCollection;
+use arrow_array::RecordBatch;
+use arrow_schema::SchemaRef;
+use polars::prelude::*;
+
+/// A batch scanner with the interface of a SQL table function
+pub struct ScanTable {
+    ewms: EWMCollection,
+    mu: f64,
+    cutoff: f64,
+    label_column: String,
+    sequence_column: String,
+    schema: SchemaRef,
+}
+
+impl ScanTable {
+    /// Creates a table function scanning with the given motifs.
+    ///
+    /// Input batches are expected to have "label" and "sequence" columns; see
+    /// `with_columns()` for other names.
+    ///
+    /// # Arguments
+    /// * `ewms` - Energy matrices of the motifs to scan
+    /// * `mu` - Chemical potential of the transcription factors
+    /// * `cutoff` - Minimum occupancy of a reported hit
+    ///
+    /// # Errors
+    /// * `MotifError::DataError` - If the hit schema cannot be built
+    ///
+    /// # Example
+    /// ```ignore
+    /// use tf_binding_rs::table::ScanTable;
+    ///
+    /// let table = ScanTable::new(ewms, 9.0, 0.2)?.with_columns("id", "seq");
+    /// for batch in batches {
+    ///     let hits = table.scan_batch(&batch)?;
+    /// }
+    /// ```
+    pub fn new(ewms: EWMCollection, mu: f64, cutoff: f64) -> Result<Self, MotifError> {
+        let empty = df!("label" => Vec::<&str>::new(), "sequence" => Vec::<&str>::new())
+            .map_err(|e| MotifError::DataError(e.to_string()))?;
+        let schema = to_arrow(&scan_sequences(&empty, &ewms, mu, cutoff, |_| {})?.hits)?.schema();
+        Ok(ScanTable {
+            ewms,
+            mu,
+            cutoff,
+            label_column: "label".to_string(),
+            sequence_column: "sequence".to_string(),
+            schema,
+        })
+    }
+
+    /// Reads labels and sequences from other input columns.
+    ///
+    /// The label column may have any type that casts to a string, e.g. an integer ID.
+    pub fn with_columns(mut self, label_column: &str, sequence_column: &str) -> Self {
+        self.label_column = label_column.to_string();
+        self.sequence_column = sequence_column.to_string();
+        self
+    }
+
+    /// Schema of every batch returned by `scan_batch()`: "label", "motif", "position",
+    /// "strand", "length" and "occupancy".
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    /// Scans one batch of sequences.
+    ///
+    /// Every hit row carries the label of its sequence, so results join back to the
+    /// input table on the label column. Sequences that cannot be scanned (missing,
+    /// non-ACGT bases, shorter than a motif) produce no rows and are logged at debug.
+    ///
+    /// # Arguments
+    /// * `batch` - Input rows with the label and sequence columns
+    ///
+    /// # Returns
+    /// * `Result<RecordBatch, MotifError>` - Hits of the batch, following `schema()`
+    ///
+    /// # Errors
+    /// * `MotifError::DataError` - If the label or sequence column is missing or has the
+    ///   wrong type
+    pub fn scan_batch(&self, batch: &RecordBatch) -> Result<RecordBatch, MotifError> {
+        let df = from_arrow(batch)?;
+        let column = |name: &str, what: &str| {
+            df.column(name)
+                .and_then(|c| c.cast(&DataType::String))
+                .map(|c| c.with_name(what.into()))
+                .map_err(|_| {
+                    MotifError::DataError(format!(
+                        "input has no {} column '{}' convertible to strings",
+                        what, name
+                    ))
+                })
+        };
+        let sequences = DataFrame::new(vec![
+            column(&self.label_column, "label")?,
+            column(&self.sequence_column, "sequence")?,
+        ])
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+        let result = scan_sequences(&sequences, &self.ewms, self.mu, self.cutoff, |w| {
+            tracing::debug!(warning = %w, "skipped in scan table");
+        })?;
+        let hits = to_arrow(&result.hits)?;
+        RecordBatch::try_new(self.schema.clone(), hits.columns().to_vec())
+            .map_err(|e| MotifError::DataError(e.to_string()))
+    }
+
+    /// Scans a stream of batches, e.g. the chunks of a SQL query result.
+    ///
+    /// # Arguments
+    /// * `batches` - Input batches
+    ///
+    /// # Returns
+    /// * An iterator over one hit batch per input batch
+    pub fn scan_batches<'a, I>(
+        &'a self,
+        batches: I,
+    ) -> impl Iterator<Item = Result<RecordBatch, MotifError>> + 'a
+    where
+        I: IntoIterator<Item = RecordBatch>,
+        I::IntoIter: 'a,
+    {
+        batches
+            .into_iter()
+            .map(move |batch| self.scan_batch(&batch))
+    }
+}
@@ -0,0 +1,189 @@
+use crate::error::MotifError;
+use crate::occupancy::MotifBank;
+use crate::types::*;
+use polars::prelude::*;
+
+/// Computes `ln(1 + exp(x))` without overflowing for large `x`
+fn softplus(x: f64) -> f64 {
+    if x > 0.0 {
+        x + (-x).exp().ln_1p()
+    } else {
+        x.exp().ln_1p()
+    }
+}
+
+/// Computes `ln(sum(exp(x)))` of a set of log values without overflowing
+fn log_sum_exp(values: impl Iterator<Item = f64> + Clone) -> f64 {
+    let max = values.clone().fold(f64::NEG_INFINITY, f64::max);
+    if max == f64::NEG_INFINITY {
+        return max;
+    }
+    max + values.map(|v| (v - max).exp()).sum::<f64>().ln()
+}
+
+/// Computes the log partition function of a set of windows under the occupancy model
+///
+/// Every window is an independent two-state system, unbound (weight 1) or bound (Boltzmann
+/// weight `exp(mu - energy)`), so its occupancy is `w / (1 + w)` as in
+/// `occupancy_landscape()` and the partition function of all windows is the product of
+/// `1 + w`. The sum is accumulated in log space, so it stays finite for strong sites and
+/// high chemical potentials.
+///
+/// # Arguments
+/// * `energies` - Binding energies of the windows, e.g. from `energy_landscape()`
+/// * `mu` - Chemical potential of the transcription factor
+///
+/// # Returns
+/// * `f64` - `ln Z`, the sum of `ln(1 + exp(mu - energy))` over the windows
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::occupancy::energy_landscape;
+/// use tf_binding_rs::thermo::log_partition;
+///
+/// let (forward, reverse) = energy_landscape(seq, &ewm).unwrap();
+/// let log_z = log_partition(forward.iter().chain(&reverse).copied(), 9.0);
+/// ```
+pub fn log_partition(energies: impl IntoIterator<Item = f64>, mu: f64) -> f64 {
+    energies.into_iter().map(|e| softplus(mu - e)).sum()
+}
+
+/// Computes the Boltzmann weight of every window of every motif on both strands
+///
+/// For each window, the weight `exp(mu - energy)` of its bound state relative to the
+/// unbound one, its occupancy `w / (1 + w)` and its probability conditional on binding:
+/// `w / sum(w)` over all windows of the motif in the sequence, i.e. where a single bound
+/// molecule of the factor sits. Weights of strong sites under a high chemical potential can
+/// exceed the range of `f64`, so derived quantities should be built from `log_weight`.
+///
+/// # Arguments
+/// * `seq` - The DNA sequence to scan
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are motif IDs
+/// * `mu` - Chemical potential of the transcription factors
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - DataFrame with one row per motif, strand and window,
+///   sorted in that order, and the columns "motif", "position" (0-based window start),
+///   "strand" ("F" or "R"), "energy", "log_weight", "weight", "occupancy" and "conditional"
+///
+/// # Errors
+/// * `MotifError::InvalidSequence` - If the sequence is shorter than a motif
+/// * `MotifError::InvalidInput` - If the sequence contains characters other than A, C, G, T
+/// * `MotifError::DataError` - If there are issues creating the DataFrame
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::thermo::boltzmann_weights;
+///
+/// let weights = boltzmann_weights("ATCGATCGTAGCTACGT", &ewm_collection, 9.0).unwrap();
+/// let likely = weights.lazy().filter(col("conditional").gt(lit(0.1))).collect().unwrap();
+/// ```
+pub fn boltzmann_weights(
+    seq: &str,
+    ewms: &EWMCollection,
+    mu: f64,
+) -> Result<DataFrame, MotifError> {
+    let bank = MotifBank::new(ewms)?;
+    let landscapes = bank.energy_landscapes(seq)?;
+
+    let rows: usize = landscapes.iter().map(|(f, r)| f.len() + r.len()).sum();
+    let mut motifs = Vec::with_capacity(rows);
+    let mut positions = Vec::with_capacity(rows);
+    let mut strands = Vec::with_capacity(rows);
+    let mut energies: Vec<f64> = Vec::with_capacity(rows);
+    let mut conditionals = Vec::with_capacity(rows);
+    for (id, (fscores, rscores)) in bank.ids().iter().zip(&landscapes) {
+        let log_norm = log_sum_exp(fscores.iter().chain(rscores).map(|e| mu - e));
+        for (strand, scores) in [("F", fscores), ("R", rscores)] {
+            motifs.extend(std::iter::repeat_n(id.as_str(), scores.len()));
+            positions.extend(0..scores.len() as i32);
+            strands.extend(std::iter::repeat_n(strand, scores.len()));
+            energies.extend(scores);
+            conditionals.extend(scores.iter().map(|e| (mu - e - log_norm).exp()));
+        }
+    }
+    let log_weights: Vec<f64> = energies.iter().map(|e| mu - e).collect();
+    let weights: Vec<f64> = log_weights.iter().map(|w| w.exp()).collect();
+    let occupancies: Vec<f64> = energies
+        .iter()
+        .map(|e| 1.0 / (1.0 + (e - mu).exp()))
+        .collect();
+
+    df!(
+        "motif" => motifs,
+        "position" => positions,
+        "strand" => strands,
+        "energy" => energies,
+        "log_weight" => log_weights,
+        "weight" => weights,
+        "occupancy" => occupancies,
+        "conditional" => conditionals,
+    )
+    .map_err(|e| MotifError::DataError(e.to_string()))
+}
+
+/// Computes the partition function of every motif over a sequence
+///
+/// Summarizes the ensemble of bound states of each factor on the sequence, both strands
+/// included: the log partition function `ln Z` of `log_partition()`, the free energy of the
+/// ensemble `-ln Z` (in the units of the matrix energies, as in the occupancy
+/// `1 / (1 + exp(energy - mu))`), the expected number of bound molecules (the same value as
+/// `total_occupancy()`) and `ln sum(w)`, the log partition function of the single-molecule
+/// ensemble whose probabilities are the "conditional" column of `boltzmann_weights()`.
+///
+/// # Arguments
+/// * `seq` - The DNA sequence to scan
+/// * `ewms` - Collection of Energy Weight Matrices, where keys are motif IDs
+/// * `mu` - Chemical potential of the transcription factors
+///
+/// # Returns
+/// * `Result<DataFrame, MotifError>` - DataFrame with one row per motif, in sorted order,
+///   and the columns "motif", "windows", "log_partition", "free_energy", "expected_bound"
+///   and "log_single_site"
+///
+/// # Errors
+/// * See `boltzmann_weights()`
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::thermo::partition_function;
+///
+/// let ensemble = partition_function("ATCGATCGTAGCTACGT", &ewm_collection, 9.0).unwrap();
+/// println!("{}", ensemble);
+/// ```
+pub fn partition_function(
+    seq: &str,
+    ewms: &EWMCollection,
+    mu: f64,
+) -> Result<DataFrame, MotifError> {
+    let bank = MotifBank::new(ewms)?;
+    let landscapes = bank.energy_landscapes(seq)?;
+
+    let mut windows = Vec::with_capacity(landscapes.len());
+    let mut log_partitions = Vec::with_capacity(landscapes.len());
+    let mut expected = Vec::with_capacity(landscapes.len());
+    let mut single_site = Vec::with_capacity(landscapes.len());
+    for (fscores, rscores) in &landscapes {
+        let energies = fscores.iter().chain(rscores).copied();
+        windows.push((fscores.len() + rscores.len()) as u32);
+        log_partitions.push(log_partition(energies.clone(), mu));
+        expected.push(
+            energies
+                .clone()
+                .map(|e| 1.0 / (1.0 + (e - mu).exp()))
+                .sum::<f64>(),
+        );
+        single_site.push(log_sum_exp(energies.map(|e| mu - e)));
+    }
+    let free_energies: Vec<f64> = log_partitions.iter().map(|z| -z).collect();
+
+    df!(
+        "motif" => bank.ids(),
+        "windows" => windows,
+        "log_partition" => log_partitions,
+        "free_energy" => free_energies,
+        "expected_bound" => expected,
+        "log_single_site" => single_site,
+    )
+    .map_err(|e| MotifError::DataError(e.to_string()))
+}
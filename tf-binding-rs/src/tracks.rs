@@ -0,0 +1,440 @@
+use crate::error::MotifError;
+use polars::prelude::*;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+/// Reads a bedGraph file into a Polars DataFrame.
+///
+/// Header lines starting with `track`, `browser` or `#` are skipped.
+///
+/// # Arguments
+/// * `filename` - Path to the bedGraph file to read
+///
+/// # Returns
+/// * `Result<DataFrame>` - A DataFrame with four columns:
+///   - "chrom": Chromosome name
+///   - "start": 0-based start of the interval
+///   - "end": Exclusive end of the interval
+///   - "value": Signal value over the interval
+///
+/// # Errors
+/// * Returns `MotifError::InvalidFileFormat` if a line cannot be parsed or no intervals are found
+/// * Returns `MotifError::DataError` if DataFrame creation fails
+/// * Returns `MotifError::Io` for file reading issues
+pub fn read_bedgraph(filename: &str) -> Result<DataFrame, MotifError> {
+    let file = File::open(filename)?;
+    let reader = BufReader::new(file);
+
+    let mut chroms: Vec<String> = Vec::new();
+    let mut starts: Vec<u64> = Vec::new();
+    let mut ends: Vec<u64> = Vec::new();
+    let mut values: Vec<f64> = Vec::new();
+
+    for (line_no, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+
+        if line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with("track")
+            || line.starts_with("browser")
+        {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 4 {
+            return Err(MotifError::InvalidFileFormat(format!(
+                "bedGraph line {} has {} fields, expected 4",
+                line_no + 1,
+                fields.len()
+            )));
+        }
+
+        let parse_err = |e: &dyn std::fmt::Display| {
+            MotifError::InvalidFileFormat(format!("bedGraph line {}: {}", line_no + 1, e))
+        };
+        chroms.push(fields[0].to_string());
+        starts.push(fields[1].parse::<u64>().map_err(|e| parse_err(&e))?);
+        ends.push(fields[2].parse::<u64>().map_err(|e| parse_err(&e))?);
+        values.push(fields[3].parse::<f64>().map_err(|e| parse_err(&e))?);
+    }
+
+    if chroms.is_empty() {
+        return Err(MotifError::InvalidFileFormat(
+            "No intervals found".to_string(),
+        ));
+    }
+
+    DataFrame::new(vec![
+        Column::new("chrom".into(), chroms),
+        Column::new("start".into(), starts),
+        Column::new("end".into(), ends),
+        Column::new("value".into(), values),
+    ])
+    .map_err(|e| MotifError::DataError(e.to_string()))
+}
+
+/// Expands bedGraph intervals into a per-base track over a genomic region.
+///
+/// Bases of the region not covered by any interval are filled with `0.0`, so the
+/// returned track always has length `end - start` and can be aligned directly with
+/// a sequence extracted from the same region.
+///
+/// # Arguments
+/// * `bedgraph` - DataFrame as returned by `read_bedgraph()`
+/// * `chrom` - Chromosome of the region
+/// * `start` - 0-based start of the region
+/// * `end` - Exclusive end of the region
+///
+/// # Returns
+/// * `Result<Vec<f64>>` - One value per base of the region
+///
+/// # Errors
+/// * Returns `MotifError::InvalidParameter` if `end` is not greater than `start`
+/// * Returns `MotifError::DataError` if required columns are missing
+pub fn track_for_region(
+    bedgraph: &DataFrame,
+    chrom: &str,
+    start: u64,
+    end: u64,
+) -> Result<Vec<f64>, MotifError> {
+    if end <= start {
+        return Err(MotifError::invalid_parameter(
+            "end",
+            end,
+            format!("must be greater than start ({})", start),
+        ));
+    }
+
+    let get = |name: &str| {
+        bedgraph
+            .column(name)
+            .map_err(|e| MotifError::DataError(e.to_string()))
+    };
+    let chroms = get("chrom")?
+        .str()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    let starts = get("start")?
+        .u64()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    let ends = get("end")?
+        .u64()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+    let values = get("value")?
+        .f64()
+        .map_err(|e| MotifError::DataError(e.to_string()))?;
+
+    let mut track = vec![0.0; (end - start) as usize];
+
+    for idx in 0..bedgraph.height() {
+        if chroms.get(idx) != Some(chrom) {
+            continue;
+        }
+        let (Some(iv_start), Some(iv_end), Some(value)) =
+            (starts.get(idx), ends.get(idx), values.get(idx))
+        else {
+            continue;
+        };
+
+        let lo = iv_start.max(start);
+        let hi = iv_end.min(end);
+        for pos in lo..hi {
+            track[(pos - start) as usize] = value;
+        }
+    }
+
+    Ok(track)
+}
+
+/// Computes the mean value of a per-base track over a window.
+///
+/// # Arguments
+/// * `track` - Per-base values
+/// * `start` - 0-based start of the window
+/// * `len` - Length of the window
+///
+/// # Returns
+/// * `f64` - Mean of the values in the window, or `0.0` for an empty window
+pub fn window_mean(track: &[f64], start: usize, len: usize) -> f64 {
+    let end = (start + len).min(track.len());
+    if start >= end {
+        return 0.0;
+    }
+    track[start..end].iter().sum::<f64>() / (end - start) as f64
+}
+
+/// Counts the hits overlapping every base of a sequence, a cheap regulatory-potential
+/// signal: regions bound by many sites or motifs get high counts.
+///
+/// # Arguments
+/// * `hits` - Hits of one sequence with "motif", "position" and "length" columns, as
+///   returned by `occupancy::find_hits()`
+/// * `seq_len` - Length of the sequence
+/// * `motifs` - Motifs to count, by full ID or name before the first `_`; all when `None`
+///
+/// # Returns
+/// * `Result<Vec<f64>>` - Number of hits covering each base of the sequence, aligned the
+///   same way as `track_for_region()`
+///
+/// # Errors
+/// * Returns `MotifError::DataError` if required columns are missing
+/// * Returns `MotifError::InvalidInput` if a hit extends beyond `seq_len`
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::{occupancy, tracks};
+///
+/// let hits = occupancy::find_hits(&seq, &ewms, 9.0, 0.2)?;
+/// let density = tracks::hit_density(&hits, seq.len(), Some(&["CRX", "NRL"]))?;
+/// let bedgraph = tracks::track_to_bedgraph(&density, "chr1", 4357766)?;
+/// tracks::write_bedgraph(&bedgraph, "density.bedGraph")?;
+/// ```
+pub fn hit_density(
+    hits: &DataFrame,
+    seq_len: usize,
+    motifs: Option<&[&str]>,
+) -> Result<Vec<f64>, MotifError> {
+    let data_err = |e: PolarsError| MotifError::DataError(e.to_string());
+    let column = |name: &str, dtype: DataType| {
+        hits.column(name)
+            .and_then(|c| c.cast(&dtype))
+            .map_err(data_err)
+    };
+    let motif_ids = column("motif", DataType::String)?;
+    let positions = column("position", DataType::Int64)?;
+    let lengths = column("length", DataType::Int64)?;
+    let (motif_ids, positions, lengths) = (
+        motif_ids.str().map_err(data_err)?,
+        positions.i64().map_err(data_err)?,
+        lengths.i64().map_err(data_err)?,
+    );
+
+    // +1 where a hit starts and -1 after it ends, summed into counts below
+    let mut changes = vec![0i64; seq_len + 1];
+    for ((motif, position), length) in motif_ids.into_iter().zip(positions).zip(lengths) {
+        let (Some(motif), Some(position), Some(length)) = (motif, position, length) else {
+            continue;
+        };
+        if let Some(motifs) = motifs {
+            let name = motif.split('_').next().unwrap_or(motif);
+            if !motifs.iter().any(|&m| m == motif || m == name) {
+                continue;
+            }
+        }
+        let end = position + length;
+        if position < 0 || end as usize > seq_len {
+            return Err(MotifError::InvalidInput(format!(
+                "hit of {} at {}..{} extends beyond the sequence of {} bases",
+                motif, position, end, seq_len
+            )));
+        }
+        changes[position as usize] += 1;
+        changes[end as usize] -= 1;
+    }
+
+    let mut count = 0;
+    Ok(changes[..seq_len]
+        .iter()
+        .map(|change| {
+            count += change;
+            count as f64
+        })
+        .collect())
+}
+
+/// Converts a per-base track of a region into bedGraph intervals, merging runs of equal
+/// values and leaving out zeros, so `track_for_region()` gives the track back.
+///
+/// # Arguments
+/// * `track` - Per-base values, e.g. from `hit_density()`
+/// * `chrom` - Chromosome of the region
+/// * `start` - 0-based start of the region
+///
+/// # Returns
+/// * `Result<DataFrame>` - Intervals in the layout of `read_bedgraph()`
+///
+/// # Errors
+/// * Returns `MotifError::DataError` if DataFrame creation fails
+pub fn track_to_bedgraph(track: &[f64], chrom: &str, start: u64) -> Result<DataFrame, MotifError> {
+    let mut starts: Vec<u64> = Vec::new();
+    let mut ends: Vec<u64> = Vec::new();
+    let mut values: Vec<f64> = Vec::new();
+
+    let mut run_start = 0;
+    for pos in 1..=track.len() {
+        if pos < track.len() && track[pos] == track[run_start] {
+            continue;
+        }
+        if track[run_start] != 0.0 {
+            starts.push(start + run_start as u64);
+            ends.push(start + pos as u64);
+            values.push(track[run_start]);
+        }
+        run_start = pos;
+    }
+
+    DataFrame::new(vec![
+        Column::new("chrom".into(), vec![chrom; starts.len()]),
+        Column::new("start".into(), starts),
+        Column::new("end".into(), ends),
+        Column::new("value".into(), values),
+    ])
+    .map_err(|e| MotifError::DataError(e.to_string()))
+}
+
+/// Writes bedGraph intervals, e.g. from `track_to_bedgraph()`, as a four-column bedGraph
+/// file readable by `read_bedgraph()` and genome browsers.
+///
+/// # Arguments
+/// * `bedgraph` - DataFrame with "chrom", "start", "end" and "value" columns
+/// * `filename` - Path of the bedGraph file
+///
+/// # Returns
+/// * `Result<usize>` - Number of intervals written
+///
+/// # Errors
+/// * Returns `MotifError::DataError` if required columns are missing
+/// * Returns `MotifError::Io` for write failures
+pub fn write_bedgraph(bedgraph: &DataFrame, filename: &str) -> Result<usize, MotifError> {
+    let data_err = |e: PolarsError| MotifError::DataError(e.to_string());
+    let column = |name: &str, dtype: DataType| {
+        bedgraph
+            .column(name)
+            .and_then(|c| c.cast(&dtype))
+            .map_err(data_err)
+    };
+    let chroms = column("chrom", DataType::String)?;
+    let starts = column("start", DataType::UInt64)?;
+    let ends = column("end", DataType::UInt64)?;
+    let values = column("value", DataType::Float64)?;
+    let (chroms, starts, ends, values) = (
+        chroms.str().map_err(data_err)?,
+        starts.u64().map_err(data_err)?,
+        ends.u64().map_err(data_err)?,
+        values.f64().map_err(data_err)?,
+    );
+
+    let mut writer = BufWriter::new(File::create(filename)?);
+    let mut written = 0;
+    for idx in 0..bedgraph.height() {
+        let (Some(chrom), Some(start), Some(end), Some(value)) = (
+            chroms.get(idx),
+            starts.get(idx),
+            ends.get(idx),
+            values.get(idx),
+        ) else {
+            continue;
+        };
+        writeln!(writer, "{}\t{}\t{}\t{}", chrom, start, end, value)?;
+        written += 1;
+    }
+    writer.flush()?;
+    Ok(written)
+}
+
+/// Computes per-base read coverage over a genomic region from an indexed BAM file.
+///
+/// Only available with the `noodles` feature. Coverage is counted like `samtools depth`:
+/// only aligned bases (CIGAR `M`, `=` and `X`) contribute, while deletions and reference
+/// skips do not. Unmapped, secondary, supplementary, duplicate and QC-failed records are
+/// ignored, as are records below `min_mapping_quality`.
+///
+/// # Arguments
+/// * `filename` - Path to a coordinate-sorted BAM file with an index (`.bai`) next to it
+/// * `chrom` - Chromosome of the region
+/// * `start` - 0-based start of the region
+/// * `end` - Exclusive end of the region
+/// * `min_mapping_quality` - Minimum mapping quality of counted records
+///
+/// # Returns
+/// * `Result<Vec<f64>>` - One coverage value per base of the region, aligned the same way
+///   as `track_for_region()`
+///
+/// # Errors
+/// * Returns `MotifError::InvalidParameter` if `end` is not greater than `start`
+/// * Returns `MotifError::InvalidFileFormat` if the BAM or its index cannot be read
+/// * Returns `MotifError::Io` for file reading issues
+#[cfg(feature = "noodles")]
+pub fn bam_coverage(
+    filename: &str,
+    chrom: &str,
+    start: u64,
+    end: u64,
+    min_mapping_quality: u8,
+) -> Result<Vec<f64>, MotifError> {
+    use noodles::bam;
+    use noodles::core::{Position, Region};
+    use noodles::sam::alignment::record::cigar::op::Kind;
+
+    if end <= start {
+        return Err(MotifError::invalid_parameter(
+            "end",
+            end,
+            format!("must be greater than start ({})", start),
+        ));
+    }
+
+    let mut reader = File::open(filename).map(bam::io::Reader::new)?;
+    let header = reader.read_header()?;
+    let index = bam::fs::read_associated_index(filename)
+        .map_err(|e| MotifError::InvalidFileFormat(format!("BAM index: {}", e)))?;
+
+    // BAM regions are 1-based and inclusive
+    let to_position = |pos: u64| {
+        Position::try_from(pos as usize)
+            .map_err(|e| MotifError::invalid_parameter("start", pos, e.to_string()))
+    };
+    let region = Region::new(chrom, to_position(start + 1)?..=to_position(end)?);
+
+    let mut coverage = vec![0.0; (end - start) as usize];
+    let query = reader
+        .query(&header, &index, &region)
+        .map_err(|e| MotifError::InvalidFileFormat(format!("BAM query: {}", e)))?;
+
+    for result in query.records() {
+        let record = result?;
+
+        let flags = record.flags();
+        if flags.is_unmapped()
+            || flags.is_secondary()
+            || flags.is_supplementary()
+            || flags.is_duplicate()
+            || flags.is_qc_fail()
+        {
+            continue;
+        }
+        if record
+            .mapping_quality()
+            .is_some_and(|mapq| mapq.get() < min_mapping_quality)
+        {
+            continue;
+        }
+
+        let Some(alignment_start) = record.alignment_start().transpose()? else {
+            continue;
+        };
+
+        // 0-based reference position of the next CIGAR operation
+        let mut ref_pos = usize::from(alignment_start) as u64 - 1;
+        for op in record.cigar().iter() {
+            let op = op?;
+            let len = op.len() as u64;
+            match op.kind() {
+                Kind::Match | Kind::SequenceMatch | Kind::SequenceMismatch => {
+                    let lo = ref_pos.max(start);
+                    let hi = (ref_pos + len).min(end);
+                    for pos in lo..hi {
+                        coverage[(pos - start) as usize] += 1.0;
+                    }
+                }
+                _ => {}
+            }
+            if op.kind().consumes_reference() {
+                ref_pos += len;
+            }
+        }
+    }
+
+    Ok(coverage)
+}
@@ -14,3 +14,17 @@ pub type EWM = DataFrame;
 
 /// Collection of EWMs indexed by motif ID
 pub type EWMCollection = HashMap<String, EWM>;
+
+/// Represents a Position Count Matrix (PCM)
+/// Stored as a DataFrame with columns A, C, G, T holding the number of sites per base
+pub type PCM = DataFrame;
+
+/// Collection of PCMs indexed by motif ID
+pub type PCMCollection = HashMap<String, PCM>;
+
+/// Per-window scores of one motif on the forward and reverse strands
+pub type StrandScores = (Vec<f64>, Vec<f64>);
+
+/// Per-window scores of one motif on both strands in single precision; see
+/// `occupancy::Precision`
+pub type StrandScores32 = (Vec<f32>, Vec<f32>);
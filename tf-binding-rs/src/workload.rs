@@ -0,0 +1,106 @@
+use crate::error::MotifError;
+use crate::matrix::pwm_from_rows;
+use crate::occupancy::pwm_to_ewm;
+use crate::types::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const BASES: [char; 4] = ['A', 'C', 'G', 'T'];
+
+/// A reproducible synthetic scanning workload: random sequences scanned with random motifs
+///
+/// The same parameters and seed always produce the same sequences and matrices, so the
+/// throughput measured by the Criterion benchmarks (`cargo bench`) and by `tfbind bench` can
+/// be compared across machines and builds.
+///
+/// # Example
+/// ```ignore
+/// use tf_binding_rs::workload::Workload;
+///
+/// let workload = Workload { sequences: 100, ..Workload::default() };
+/// let sequences = workload.sequences();
+/// let ewms = workload.ewms().unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Workload {
+    /// Number of sequences
+    pub sequences: usize,
+    /// Length of every sequence in bp
+    pub length: usize,
+    /// Number of motifs
+    pub motifs: usize,
+    /// Width of every motif in bp
+    pub motif_length: usize,
+    /// Seed of the random generator
+    pub seed: u64,
+}
+
+impl Default for Workload {
+    /// 1,000 sequences of 1 kb scanned with 10 motifs of 12 bp
+    fn default() -> Self {
+        Workload {
+            sequences: 1000,
+            length: 1000,
+            motifs: 10,
+            motif_length: 12,
+            seed: 42,
+        }
+    }
+}
+
+impl Workload {
+    /// Uniformly random sequences
+    pub fn sequences(&self) -> Vec<String> {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        (0..self.sequences)
+            .map(|_| {
+                (0..self.length)
+                    .map(|_| BASES[rng.gen_range(0..4)])
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Random motifs named `BENCH01_RANDOM`, `BENCH02_RANDOM`, ..., each position preferring
+    /// one base with probability 0.55 to 0.95
+    ///
+    /// # Errors
+    /// * `MotifError::DataError` - If the matrices cannot be built
+    pub fn ewms(&self) -> Result<EWMCollection, MotifError> {
+        // a different stream than the sequences, so both can change independently
+        let mut rng = StdRng::seed_from_u64(self.seed ^ 0x5eed_f00d);
+        let mut ewms = EWMCollection::new();
+        for i in 0..self.motifs {
+            let rows: Vec<[f64; 4]> = (0..self.motif_length)
+                .map(|_| {
+                    let mut row = [0.0; 4];
+                    let preferred = rng.gen_range(0..4);
+                    let p = rng.gen_range(0.55..0.95);
+                    for (base, value) in row.iter_mut().enumerate() {
+                        *value = if base == preferred {
+                            p
+                        } else {
+                            (1.0 - p) / 3.0
+                        };
+                    }
+                    row
+                })
+                .collect();
+            ewms.insert(
+                format!("BENCH{:02}_RANDOM", i + 1),
+                pwm_to_ewm(&pwm_from_rows(&rows)?)?,
+            );
+        }
+        Ok(ewms)
+    }
+
+    /// Total number of bases scanned
+    pub fn bases(&self) -> usize {
+        self.sequences * self.length
+    }
+
+    /// Amount of work in bp·motifs, the unit in which throughput is reported
+    pub fn work(&self) -> usize {
+        self.bases() * self.motifs
+    }
+}
@@ -0,0 +1,97 @@
+use polars::prelude::*;
+use std::io::Write;
+use tf_binding_rs::annotations::{TfAnnotation, TfAnnotations};
+use tf_binding_rs::features::{Aggregation, MotifRole};
+
+#[test]
+fn test_read_and_annotate() {
+    let path = std::env::temp_dir().join("tf_annotations_test.tsv");
+    let mut file = std::fs::File::create(&path).unwrap();
+    writeln!(file, "# curated factors").unwrap();
+    writeln!(file, "motif_id\ttf_name\trole\tconcentration\tsource").unwrap();
+    writeln!(file, "NRL_HUMAN.MA0842.1\tNRL\tactivator\t12.5\tjaspar").unwrap();
+    writeln!(file, "CRX\tCRX\trepressor\t\tjaspar").unwrap();
+    drop(file);
+
+    let annotations = TfAnnotations::read(path.to_str().unwrap()).unwrap();
+    assert_eq!(annotations.len(), 2);
+    assert_eq!(annotations.columns(), ["tf_name", "role", "concentration"]);
+    // full IDs, names of full IDs and full IDs of names all match
+    assert_eq!(
+        annotations.get("NRL").unwrap().tf_name.as_deref(),
+        Some("NRL")
+    );
+    assert_eq!(
+        annotations.get("CRX_HUMAN.MA0467.1").unwrap().role,
+        Some(MotifRole::Repressor)
+    );
+    assert!(annotations.get("RAX").is_none());
+    assert_eq!(annotations.concentrations().len(), 1);
+
+    let hits = df!("motif" => ["NRL", "RAX", "CRX"], "position" => [3, 7, 11]).unwrap();
+    let annotated = annotations.annotate(&hits, "motif").unwrap();
+    let expected = df!(
+        "motif" => ["NRL", "RAX", "CRX"],
+        "position" => [3, 7, 11],
+        "tf_name" => [Some("NRL"), None, Some("CRX")],
+        "role" => [Some("activator"), None, Some("repressor")],
+        "concentration" => [Some(12.5), None, None],
+    )
+    .unwrap();
+    assert!(annotated.equals_missing(&expected));
+
+    std::fs::write(&path, "motif_id,role\nNRL,maybe\n").unwrap();
+    assert!(TfAnnotations::read(path.to_str().unwrap()).is_err());
+    std::fs::write(&path, "tf_name,role\nNRL,activator\n").unwrap();
+    assert!(TfAnnotations::read(path.to_str().unwrap()).is_err());
+}
+
+#[test]
+fn test_family_aggregation() {
+    let annotations = TfAnnotations::new(vec![
+        TfAnnotation {
+            motif_id: "ELK1".to_string(),
+            family: Some("ETS".to_string()),
+            ..Default::default()
+        },
+        TfAnnotation {
+            motif_id: "ETV6".to_string(),
+            family: Some("ETS".to_string()),
+            ..Default::default()
+        },
+    ]);
+    assert_eq!(annotations.family("ELK1_HUMAN.MA0028.2"), Some("ETS"));
+
+    let features = df!(
+        "label" => ["a", "b"],
+        "ELK1_HUMAN.MA0028.2" => [Some(0.5), Some(2.0)],
+        "ETV6_HUMAN.MA0645.1" => [Some(1.5), None],
+        "CRX" => [0.25, 0.75],
+    )
+    .unwrap();
+    let max = annotations
+        .family_features(&features, Aggregation::Max)
+        .unwrap();
+    let expected = df!(
+        "label" => ["a", "b"],
+        "CRX" => [0.25, 0.75],
+        "ETS" => [1.5, 2.0],
+    )
+    .unwrap();
+    assert!(max.equals_missing(&expected));
+    let sum = annotations
+        .family_features(&features, Aggregation::Sum)
+        .unwrap();
+    let ets = sum.column("ETS").unwrap().f64().unwrap();
+    assert_eq!(ets.get(0), Some(2.0));
+
+    let hits = df!("motif" => ["ETV6", "CRX"], "position" => [3, 7]).unwrap();
+    let families = annotations.family_hits(&hits, "motif").unwrap();
+    let expected = df!(
+        "motif" => ["ETS", "CRX"],
+        "position" => [3, 7],
+        "source_motif" => ["ETV6", "CRX"],
+    )
+    .unwrap();
+    assert!(families.equals_missing(&expected));
+}
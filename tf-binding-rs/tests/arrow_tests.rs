@@ -0,0 +1,90 @@
+#![cfg(feature = "arrow")]
+
+use arrow_array::{Array, Int32Array, RecordBatch, StringArray};
+use arrow_schema::DataType;
+use std::sync::Arc;
+use tf_binding_rs::arrow::{from_arrow, to_arrow};
+use tf_binding_rs::table::ScanTable;
+use tf_binding_rs::{fasta, occupancy, scan};
+
+#[test]
+fn test_arrow_roundtrip() {
+    let sequences = fasta::read_fasta("tests/data/test1.fasta").unwrap();
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let hits = scan::scan_sequences(&sequences, &ewms, 9.0, 0.2, |_| {})
+        .unwrap()
+        .hits;
+
+    let batch = to_arrow(&hits).unwrap();
+    assert_eq!(batch.num_rows(), hits.height());
+    assert_eq!(batch.num_columns(), hits.width());
+    let schema = batch.schema();
+    assert_eq!(schema.field(0).name(), "label");
+    assert_eq!(schema.field(0).data_type(), &DataType::LargeUtf8);
+    let occupancy = batch.column_by_name("occupancy").unwrap();
+    assert_eq!(occupancy.data_type(), &DataType::Float64);
+    assert_eq!(occupancy.null_count(), 0);
+
+    let back = from_arrow(&batch).unwrap();
+    assert!(back.equals(&hits));
+}
+
+#[test]
+fn test_arrow_nulls_and_features() {
+    let df = polars::df!(
+        "label" => [Some("a"), None, Some("c")],
+        "count" => [Some(1i64), Some(2), None],
+        "flag" => [true, false, true],
+    )
+    .unwrap();
+    let batch = to_arrow(&df).unwrap();
+    assert_eq!(batch.column(1).null_count(), 1);
+    assert!(from_arrow(&batch).unwrap().equals_missing(&df));
+}
+
+#[test]
+fn test_scan_table() {
+    let sequences = fasta::read_fasta("tests/data/test1.fasta").unwrap();
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let expected = scan::scan_sequences(&sequences, &ewms, 9.0, 0.2, |_| {})
+        .unwrap()
+        .hits;
+
+    let seqs: Vec<&str> = sequences
+        .column("sequence")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_no_null_iter()
+        .collect();
+    let batch = RecordBatch::try_from_iter([
+        (
+            "id",
+            Arc::new(Int32Array::from(vec![1, 2, 3])) as Arc<dyn Array>,
+        ),
+        ("seq", Arc::new(StringArray::from(seqs)) as Arc<dyn Array>),
+    ])
+    .unwrap();
+
+    let table = ScanTable::new(ewms, 9.0, 0.2)
+        .unwrap()
+        .with_columns("id", "seq");
+    let hits = table.scan_batch(&batch).unwrap();
+    assert_eq!(hits.schema(), table.schema());
+    assert_eq!(hits.num_rows(), expected.height());
+
+    let batches: Vec<_> = table
+        .scan_batches([batch.slice(0, 1), batch.slice(1, 2)])
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let rows: usize = batches.iter().map(|b| b.num_rows()).sum();
+    assert_eq!(rows, expected.height());
+
+    let unnamed = ScanTable::new(
+        occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap(),
+        9.0,
+        0.2,
+    )
+    .unwrap();
+    assert!(unnamed.scan_batch(&batch).is_err());
+}
@@ -0,0 +1,85 @@
+use polars::prelude::*;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use tf_binding_rs::calibration::{calibrate_cutoff, calibrate_cutoffs};
+use tf_binding_rs::{matrix, occupancy};
+
+fn random_sequences(n: usize, length: usize, seed: u64) -> Vec<String> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..n)
+        .map(|_| {
+            (0..length)
+                .map(|_| *[b'A', b'C', b'G', b'T'].choose(&mut rng).unwrap() as char)
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn test_calibrated_cutoff_meets_rate() {
+    let pwm = matrix::pwm_from_rows(&[
+        [0.7, 0.1, 0.1, 0.1],
+        [0.1, 0.1, 0.7, 0.1],
+        [0.1, 0.7, 0.1, 0.1],
+        [0.05, 0.05, 0.05, 0.85],
+        [0.4, 0.4, 0.1, 0.1],
+    ])
+    .unwrap();
+    let ewm = occupancy::pwm_to_ewm(&pwm).unwrap();
+    let sequences = random_sequences(20, 500, 7);
+    let background: Vec<&str> = sequences.iter().map(String::as_str).collect();
+
+    let loose = calibrate_cutoff(&background, &ewm, 9.0, 5.0).unwrap();
+    let strict = calibrate_cutoff(&background, &ewm, 9.0, 0.5).unwrap();
+    assert_eq!(loose.background_bases, 10_000);
+    assert!(loose.hits_per_kb() <= 5.0);
+    assert!(strict.hits_per_kb() <= 0.5);
+    assert!(strict.cutoff >= loose.cutoff);
+
+    // the reported hits are the background windows above the cutoff
+    let above: usize = background
+        .iter()
+        .map(|seq| {
+            let (forward, reverse) = occupancy::occupancy_landscape(seq, &ewm, 9.0).unwrap();
+            forward
+                .iter()
+                .chain(&reverse)
+                .filter(|&&occ| occ > strict.cutoff)
+                .count()
+        })
+        .sum();
+    assert_eq!(above, strict.background_hits);
+
+    let none = calibrate_cutoff(&background, &ewm, 9.0, 0.0).unwrap();
+    assert_eq!(none.background_hits, 0);
+    let all = calibrate_cutoff(&background, &ewm, 9.0, 1e4).unwrap();
+    assert_eq!(all.cutoff, 0.0);
+    assert!(calibrate_cutoff(&background, &ewm, 9.0, -1.0).is_err());
+    assert!(calibrate_cutoff(&["ACG", "ACNNTGCA"], &ewm, 9.0, 1.0).is_err());
+
+    // sequences with N are skipped and counted, not scored
+    let mut with_n = background.clone();
+    with_n.push("ACGTNACGTACGT");
+    let counted = calibrate_cutoff(&with_n, &ewm, 9.0, 0.5).unwrap();
+    assert_eq!(counted.skipped_sequences, 1);
+    assert_eq!(counted.background_bases, 10_000);
+    assert_eq!(counted.cutoff, strict.cutoff);
+    assert_eq!(strict.skipped_sequences, 0);
+}
+
+#[test]
+fn test_calibrate_cutoffs_per_motif() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let sequences = random_sequences(10, 1000, 3);
+    let background =
+        df!("label" => (0..10).map(|i| i.to_string()).collect::<Vec<_>>(), "sequence" => &sequences)
+            .unwrap();
+
+    let cutoffs = calibrate_cutoffs(&background, &ewms, 9.0, 1.0).unwrap();
+    assert_eq!(cutoffs.len(), ewms.len());
+    for calibrated in cutoffs.values() {
+        assert_eq!(calibrated.background_bases, 10_000);
+        assert!(calibrated.background_hits <= 10);
+    }
+}
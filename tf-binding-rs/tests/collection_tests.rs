@@ -0,0 +1,154 @@
+use polars::prelude::*;
+use tf_binding_rs::cache;
+use tf_binding_rs::collection::{
+    deduplicate, sample_motifs, MotifFilter, RejectReason, DEFAULT_DEDUPE_TOLERANCE,
+};
+use tf_binding_rs::matrix::reverse_complement_matrix;
+use tf_binding_rs::occupancy::{
+    pwm_to_ewm, read_pwm_files, read_pwm_to_ewm, EwmOptions, LazyEwmCollection,
+};
+use tf_binding_rs::types::PWMCollection;
+
+const MOTIFS: &str = "tests/data/tdmMotifs.meme";
+
+#[test]
+fn test_filter_by_width() {
+    let pwms = read_pwm_files(MOTIFS).unwrap();
+    let filter = MotifFilter {
+        min_information: None,
+        max_width: Some(10),
+    };
+
+    let flagged = filter.flag(&pwms).unwrap();
+    assert_eq!(flagged.len(), 2);
+    assert_eq!(flagged[0].id, "MAZ_MOUSE.H11MO.1.A");
+    assert_eq!(
+        flagged[0].reason,
+        RejectReason::TooWide {
+            width: 11,
+            max_width: 10
+        }
+    );
+
+    let (kept, rejected) = filter.apply(pwms).unwrap();
+    assert_eq!(rejected, flagged);
+    assert_eq!(kept.keys().collect::<Vec<_>>(), ["GFI1_MOUSE.H11MO.0.C"]);
+}
+
+#[test]
+fn test_filter_by_information() {
+    let pwms = read_pwm_files(MOTIFS).unwrap();
+    assert!(MotifFilter::default().flag(&pwms).unwrap().is_empty());
+
+    // a uniform matrix carries no information
+    let uniform =
+        df!("A" => [0.25, 0.25], "C" => [0.25, 0.25], "G" => [0.25, 0.25], "T" => [0.25, 0.25])
+            .unwrap();
+    let filter = MotifFilter {
+        min_information: Some(1.0),
+        max_width: None,
+    };
+    match filter.check(&uniform).unwrap() {
+        Some(RejectReason::LowInformation { bits, .. }) => assert!(bits.abs() < 1e-9),
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(filter.flag(&pwms).unwrap().is_empty());
+}
+
+#[test]
+fn test_deduplicate() {
+    let mut pwms = read_pwm_files(MOTIFS).unwrap();
+    let nrl = pwms["NRL_HUMAN.MA0842.1"].clone();
+    pwms.insert("NRL_COPY".to_string(), nrl.clone());
+    pwms.insert(
+        "NRL_RC".to_string(),
+        reverse_complement_matrix(&nrl).unwrap(),
+    );
+
+    let deduplicated = deduplicate(pwms, DEFAULT_DEDUPE_TOLERANCE).unwrap();
+    assert_eq!(deduplicated.pwms.len(), 3);
+    assert!(deduplicated.pwms.contains_key("NRL_COPY"));
+    assert_eq!(deduplicated.merged.len(), 2);
+    assert_eq!(deduplicated.merged[0].duplicate, "NRL_HUMAN.MA0842.1");
+    assert!(!deduplicated.merged[0].reverse_complement);
+    assert_eq!(deduplicated.merged[1].duplicate, "NRL_RC");
+    assert!(deduplicated.merged[1].reverse_complement);
+    assert_eq!(deduplicated.aliases["NRL_RC"], "NRL_COPY");
+
+    assert!(deduplicate(PWMCollection::new(), -1.0).is_err());
+}
+
+#[test]
+fn test_lazy_ewm_collection() {
+    let motifs = LazyEwmCollection::read(MOTIFS, &EwmOptions::default()).unwrap();
+    assert_eq!(motifs.len(), 3);
+    assert_eq!(motifs.converted(), 0);
+
+    let nrl = motifs.get("NRL_HUMAN.MA0842.1").unwrap().unwrap();
+    assert!(nrl.equals(&pwm_to_ewm(&motifs.pwms()["NRL_HUMAN.MA0842.1"]).unwrap()));
+    assert_eq!(motifs.converted(), 1);
+    // memoized: the second call returns the same matrix
+    let again = motifs.get("NRL_HUMAN.MA0842.1").unwrap().unwrap();
+    assert!(std::ptr::eq(nrl, again));
+    assert!(motifs.get("MISSING").unwrap().is_none());
+
+    let subset = motifs.collect(["MAZ_MOUSE.H11MO.1.A"]).unwrap();
+    assert_eq!(subset.len(), 1);
+    assert_eq!(motifs.converted(), 2);
+    assert!(motifs.collect(["MISSING"]).is_err());
+
+    let all = motifs.into_collection().unwrap();
+    let eager = read_pwm_to_ewm(MOTIFS).unwrap();
+    assert_eq!(all.len(), eager.len());
+    for (id, ewm) in &eager {
+        assert!(all[id].equals(ewm), "{}", id);
+    }
+}
+
+#[test]
+fn test_motif_cache() {
+    let path = std::env::temp_dir().join("tf_binding_motifs.tfbm");
+    let path = path.to_str().unwrap();
+    let _ = std::fs::remove_file(path);
+    let options = EwmOptions::default();
+
+    // the first load parses the motif file and writes the cache, the second reads it
+    let parsed = cache::load_cached(MOTIFS, &options, path).unwrap();
+    let key = cache::cache_key(MOTIFS, &options).unwrap();
+    let cached = cache::read_motif_cache(path, &key).unwrap().unwrap();
+    assert_eq!(cached.pwms.len(), 3);
+    for (id, ewm) in &parsed.ewms {
+        assert!(cached.ewms[id].equals(ewm), "{}", id);
+        assert!(cached.pwms[id].equals(&parsed.pwms[id]), "{}", id);
+    }
+
+    // other conversion options make the cache stale
+    let warm = EwmOptions::default().with_temperature(37.0);
+    let other_key = cache::cache_key(MOTIFS, &warm).unwrap();
+    assert_ne!(key, other_key);
+    assert!(cache::read_motif_cache(path, &other_key).unwrap().is_none());
+    let rebuilt = cache::load_cached(MOTIFS, &warm, path).unwrap();
+    assert!(!rebuilt.ewms["NRL_HUMAN.MA0842.1"].equals(&parsed.ewms["NRL_HUMAN.MA0842.1"]));
+
+    // files that are not caches are errors, missing ones are not
+    assert!(cache::read_motif_cache(MOTIFS, &key).is_err());
+    std::fs::remove_file(path).unwrap();
+    assert!(cache::read_motif_cache(path, &key).unwrap().is_none());
+}
+
+#[test]
+fn test_sample_motifs() {
+    let pwms = read_pwm_files(MOTIFS).unwrap();
+    let sampled = sample_motifs(pwms.clone(), 2, 7);
+    assert_eq!(sampled.len(), 2);
+    assert!(sampled.keys().all(|id| pwms.contains_key(id)));
+
+    // the same seed gives the same motifs, whatever the order of the collection
+    let mut ids: Vec<_> = sampled.keys().cloned().collect();
+    let mut again: Vec<_> = sample_motifs(pwms.clone(), 2, 7).into_keys().collect();
+    ids.sort();
+    again.sort();
+    assert_eq!(ids, again);
+
+    assert_eq!(sample_motifs(pwms, 10, 7).len(), 3);
+}
@@ -0,0 +1,250 @@
+use polars::prelude::*;
+use tf_binding_rs::{compare, fasta, occupancy};
+
+#[test]
+fn test_delta_landscape() {
+    let seqs = fasta::read_fasta("tests/data/test1.fasta").unwrap();
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let sequences = seqs.column("sequence").unwrap().str().unwrap();
+
+    // pair the WT and MUT versions of the first enhancer under the same label
+    let wt = df!("label" => ["CPPP", "CPPE"], "sequence" => [sequences.get(0), sequences.get(2)])
+        .unwrap();
+    let mutant = df!("label" => ["CPPP"], "sequence" => [sequences.get(1)]).unwrap();
+
+    let comparison = compare::delta_landscape(&wt, &mutant, &ewms, 9.0, 0.2).unwrap();
+    assert_eq!(comparison.per_pair.height(), ewms.len());
+    assert_eq!(comparison.summary.height(), ewms.len());
+
+    let deltas = comparison.per_pair.column("delta").unwrap().f64().unwrap();
+    assert!(deltas.into_iter().any(|d| d.unwrap() != 0.0));
+
+    // identical sequences produce no differences
+    let same = compare::delta_landscape(&wt, &wt, &ewms, 9.0, 0.2).unwrap();
+    let changed = same.summary.column("pairs_changed").unwrap().u32().unwrap();
+    assert!(changed.into_iter().all(|c| c.unwrap() == 0));
+
+    let unrelated = df!("label" => ["other"], "sequence" => [sequences.get(1)]).unwrap();
+    assert!(compare::delta_landscape(&wt, &unrelated, &ewms, 9.0, 0.2).is_err());
+}
+
+#[test]
+fn test_config_diff() {
+    let seqs = fasta::read_fasta("tests/data/test1.fasta").unwrap();
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let reference = compare::ScanConfig {
+        ewms: &ewms,
+        mu: 9.0,
+        cutoff: 0.2,
+    };
+
+    let same = compare::config_diff(&seqs, &reference, &reference, 0.0).unwrap();
+    assert!(same.is_identical());
+    assert_eq!(same.per_sequence.height(), seqs.height() * ewms.len());
+    assert_eq!(same.summary.height(), ewms.len());
+
+    let stronger = compare::ScanConfig {
+        mu: 11.0,
+        ..reference
+    };
+    let diff = compare::config_diff(&seqs, &reference, &stronger, 1e-6).unwrap();
+    assert!(diff.n_changed() > 0);
+    let deltas = diff.per_sequence.column("delta").unwrap().f64().unwrap();
+    assert!(deltas.into_iter().all(|d| d.unwrap() > 0.0));
+    let lost = diff.summary.column("sites_lost").unwrap().u32().unwrap();
+    assert!(lost.into_iter().all(|n| n == Some(0)));
+
+    // a version bump pairs by name; a motif missing from the release is reported
+    let mut release = ewms.clone();
+    let nrl = release.remove("NRL_HUMAN.MA0842.1").unwrap();
+    release.insert("NRL_HUMAN.MA0842.2".to_string(), nrl);
+    release.remove("GFI1_MOUSE.H11MO.0.C");
+    let upgraded = compare::ScanConfig {
+        ewms: &release,
+        ..reference
+    };
+    let diff = compare::config_diff(&seqs, &reference, &upgraded, 0.0).unwrap();
+    assert_eq!(diff.only_a, ["GFI1_MOUSE.H11MO.0.C"]);
+    assert!(diff.only_b.is_empty());
+    assert_eq!(diff.n_changed(), 0);
+    let renamed = diff.summary.column("motif_b").unwrap().str().unwrap();
+    assert!(renamed
+        .into_iter()
+        .any(|id| id == Some("NRL_HUMAN.MA0842.2")));
+}
+
+#[test]
+fn test_pairwise_similarity() {
+    let seqs = fasta::read_fasta("tests/data/test1.fasta").unwrap();
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let n = seqs.height();
+
+    let all = compare::pairwise_similarity(&seqs, &ewms, 9.0, 0.2, None).unwrap();
+    assert_eq!(all.pairs.height(), n * (n - 1) / 2);
+    assert_eq!(all.per_motif.height(), all.pairs.height() * ewms.len());
+
+    let labels = seqs.column("label").unwrap().str().unwrap();
+    let reference = labels.get(0).unwrap();
+    let against = compare::pairwise_similarity(&seqs, &ewms, 9.0, 0.2, Some(reference)).unwrap();
+    assert_eq!(against.pairs.height(), n - 1);
+
+    // a sequence is identical to itself
+    let twice = df!(
+        "label" => ["a", "b"],
+        "sequence" => [seqs.column("sequence").unwrap().str().unwrap().get(0); 2],
+    )
+    .unwrap();
+    let same = compare::pairwise_similarity(&twice, &ewms, 9.0, 0.2, None).unwrap();
+    let value = |df: &DataFrame, name: &str| df.column(name).unwrap().f64().unwrap().get(0);
+    assert_eq!(value(&same.pairs, "jaccard"), Some(1.0));
+    assert!((value(&same.pairs, "occupancy_correlation").unwrap() - 1.0).abs() < 1e-12);
+    let landscapes = same.per_motif.column("landscape_correlation").unwrap();
+    assert!(landscapes
+        .f64()
+        .unwrap()
+        .into_no_null_iter()
+        .all(|r| (r - 1.0).abs() < 1e-12));
+
+    assert!(compare::pairwise_similarity(&seqs, &ewms, 9.0, 0.2, Some("missing")).is_err());
+}
+
+#[test]
+fn test_pair_sequences_and_variant_delta() {
+    let seqs = fasta::read_fasta("tests/data/test1.fasta").unwrap();
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let sequences = seqs.column("sequence").unwrap().str().unwrap();
+    let (wt, mutant) = (sequences.get(0).unwrap(), sequences.get(1).unwrap());
+
+    let reference = df!(
+        "label" => ["CPPP", "short", "same", "ref_only"],
+        "sequence" => [wt, "ACGTACGTACGT", wt, wt],
+    )
+    .unwrap();
+    let edited = df!(
+        "label" => ["CPPP", "short", "same", "edited_only"],
+        "sequence" => [mutant.to_string(), "ACGTACGTAC".to_string(), wt.to_lowercase(), wt.to_string()],
+    )
+    .unwrap();
+
+    let pairing = compare::pair_sequences(&reference, &edited).unwrap();
+    let expected = wt
+        .bytes()
+        .zip(mutant.bytes())
+        .filter(|(a, b)| a != b)
+        .count();
+    assert!(expected > 0);
+    assert_eq!(pairing.variants.height(), expected);
+    assert_eq!(pairing.identical, 1);
+    assert_eq!(
+        pairing.length_mismatches,
+        vec![("short".to_string(), 12, 10)]
+    );
+    assert_eq!(pairing.reference_only, vec!["ref_only".to_string()]);
+    assert_eq!(pairing.edited_only, vec!["edited_only".to_string()]);
+
+    let positions = pairing.variants.column("position").unwrap().u32().unwrap();
+    let refs = pairing.variants.column("ref").unwrap().str().unwrap();
+    let first = positions.get(0).unwrap() as usize;
+    assert_eq!(refs.get(0), Some(&wt[first..first + 1]));
+
+    let pairs = df!("label" => ["CPPP"], "sequence" => [wt]).unwrap();
+    let edits = df!("label" => ["CPPP"], "sequence" => [mutant]).unwrap();
+    // a cutoff low enough for the weak GFI1 site to count
+    let effects = compare::variant_delta(&pairs, &edits, &ewms, 9.0, 0.1).unwrap();
+    assert_eq!(effects.height(), expected * ewms.len());
+    // the A>C edits of the CRX sites knock out a GFI1 site at 50 and create an NRL site at
+    // 81, and leave every other motif alone
+    let positions = effects.column("position").unwrap().u32().unwrap();
+    let motifs = effects.column("motif").unwrap().str().unwrap();
+    let deltas = effects.column("delta").unwrap().f64().unwrap();
+    let created = effects.column("sites_created").unwrap().u32().unwrap();
+    let destroyed = effects.column("sites_destroyed").unwrap().u32().unwrap();
+    for row in 0..effects.height() {
+        let delta = deltas.get(row).unwrap();
+        let sites = (created.get(row).unwrap(), destroyed.get(row).unwrap());
+        match (positions.get(row).unwrap(), motifs.get(row).unwrap()) {
+            (50, "GFI1_MOUSE.H11MO.0.C") => {
+                assert!(delta < -0.18, "{}", delta);
+                assert_eq!(sites, (0, 1));
+            }
+            (81, "NRL_HUMAN.MA0842.1") => {
+                assert!(delta > 0.4, "{}", delta);
+                assert_eq!(sites, (1, 0));
+            }
+            _ => {
+                assert!(delta.abs() < 0.01, "{}", delta);
+                assert_eq!(sites, (0, 0));
+            }
+        }
+    }
+
+    let unrelated = df!("label" => ["other"], "sequence" => [mutant]).unwrap();
+    assert!(compare::pair_sequences(&pairs, &unrelated).is_err());
+}
+
+#[test]
+fn test_variant_delta_scores_each_edit_alone() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let wt = "GGGCTTAGTAACGTTACGGAAGGGCGTGGCCTTTAAATTCCGGGG";
+    // two adjacent edits share every window covering either of them
+    let mutant = format!("{}CG{}", &wt[..9], &wt[11..]);
+    let pairs = df!("label" => ["tile"], "sequence" => [wt]).unwrap();
+    let edits = df!("label" => ["tile"], "sequence" => [mutant.as_str()]).unwrap();
+    let effects = compare::variant_delta(&pairs, &edits, &ewms, 9.0, 0.2).unwrap();
+    assert_eq!(effects.height(), 2 * ewms.len());
+
+    let total = |s: &str, id: &str| {
+        let (f, r) = occupancy::occupancy_landscape(s, &ewms[id], 9.0).unwrap();
+        f.iter().chain(&r).sum::<f64>()
+    };
+    let positions = effects.column("position").unwrap().u32().unwrap();
+    let alts = effects.column("alt").unwrap().str().unwrap();
+    let motifs = effects.column("motif").unwrap().str().unwrap();
+    let deltas = effects.column("delta").unwrap().f64().unwrap();
+    for row in 0..effects.height() {
+        let position = positions.get(row).unwrap() as usize;
+        let id = motifs.get(row).unwrap();
+        let single = format!(
+            "{}{}{}",
+            &wt[..position],
+            alts.get(row).unwrap(),
+            &wt[position + 1..]
+        );
+        let expected = total(&single, id) - total(wt, id);
+        assert!((deltas.get(row).unwrap() - expected).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn test_saturation_mutagenesis() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let seq = "GGGCTTAGTAACGTTACGGAAGGGCGTGGCCTTTAAATTCCGGGG";
+    let effects = compare::saturation_mutagenesis(seq, &ewms, 9.0).unwrap();
+    assert_eq!(effects.height(), 3 * seq.len() * ewms.len());
+
+    // every row matches a full rescoring of its mutant
+    let column = |name: &str| effects.column(name).unwrap().clone();
+    let motifs = column("motif");
+    let positions = column("position");
+    let alts = column("alt");
+    let max_occupancy = column("max_occupancy");
+    let delta_total = column("delta_total_occupancy");
+    let best = |s: &str, id: &str| {
+        let (f, r) = occupancy::occupancy_landscape(s, &ewms[id], 9.0).unwrap();
+        let max = f.iter().chain(&r).fold(f64::NEG_INFINITY, |a, &b| a.max(b));
+        (max, f.iter().chain(&r).sum::<f64>())
+    };
+    for row in [0, 50, effects.height() - 1] {
+        let id = motifs.str().unwrap().get(row).unwrap();
+        let position = positions.u32().unwrap().get(row).unwrap() as usize;
+        let alt = alts.str().unwrap().get(row).unwrap();
+        let mutant = format!("{}{}{}", &seq[..position], alt, &seq[position + 1..]);
+        let (expected_max, mutant_total) = best(&mutant, id);
+        let (_, reference_total) = best(seq, id);
+        let got = max_occupancy.f64().unwrap().get(row).unwrap();
+        assert!((got - expected_max).abs() < 1e-9);
+        let change = delta_total.f64().unwrap().get(row).unwrap();
+        assert!((change - (mutant_total - reference_total)).abs() < 1e-9);
+    }
+    assert!(compare::saturation_mutagenesis("ACGTN", &ewms, 9.0).is_err());
+}
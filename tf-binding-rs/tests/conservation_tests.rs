@@ -0,0 +1,73 @@
+use tf_binding_rs::{conservation, fasta, occupancy, tracks};
+
+#[test]
+fn test_read_bedgraph() {
+    let df = tracks::read_bedgraph("tests/data/test1.bedGraph").unwrap();
+    assert_eq!(df.height(), 4);
+    assert_eq!(df.width(), 4);
+
+    let track = tracks::track_for_region(&df, "chr1", 4357790, 4357810).unwrap();
+    assert_eq!(track.len(), 20);
+    assert_eq!(track[0], 0.1);
+    assert_eq!(track[19], 0.9);
+
+    // region end must come after start
+    assert!(tracks::track_for_region(&df, "chr1", 10, 10).is_err());
+}
+
+#[test]
+fn test_conservation_weighted_hits() {
+    let seqs = fasta::read_fasta("tests/data/test1.fasta").unwrap();
+    let seq = seqs
+        .column("sequence")
+        .unwrap()
+        .str()
+        .unwrap()
+        .get(0)
+        .unwrap();
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+
+    // a fully conserved track leaves occupancies unchanged
+    let track = vec![1.0; seq.len()];
+    let hits = conservation::conservation_weighted_hits(seq, &ewms, 9.0, 0.2, &track).unwrap();
+    let plain = occupancy::find_hits(seq, &ewms, 9.0, 0.2).unwrap();
+    assert!(plain.height() > 0);
+    assert_eq!(hits.height(), plain.height());
+    assert!(hits
+        .column("occupancy")
+        .unwrap()
+        .f64()
+        .unwrap()
+        .into_iter()
+        .zip(hits.column("weighted_occupancy").unwrap().f64().unwrap())
+        .all(|(a, b)| a == b));
+
+    // track must be aligned to the sequence
+    let short = vec![1.0; seq.len() - 1];
+    assert!(conservation::conservation_weighted_hits(seq, &ewms, 9.0, 0.2, &short).is_err());
+}
+
+#[test]
+fn test_conserved_hits() {
+    let seqs = fasta::read_fasta("tests/data/test1.fasta").unwrap();
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+
+    let hits = conservation::conserved_hits(&seqs, &ewms, 9.0, 0.2, 2, 0).unwrap();
+    let reference = seqs
+        .column("sequence")
+        .unwrap()
+        .str()
+        .unwrap()
+        .get(0)
+        .unwrap();
+    let plain = occupancy::find_hits(reference, &ewms, 9.0, 0.2).unwrap();
+    assert_eq!(hits.height(), plain.height());
+
+    // every reference hit is supported by at least the reference itself
+    let n_species = hits.column("n_species").unwrap().u32().unwrap();
+    assert!(n_species.into_iter().all(|n| n.unwrap() >= 1));
+
+    // the first two sequences are a WT/MUT pair, so most sites are shared
+    let conserved = hits.column("conserved").unwrap().bool().unwrap();
+    assert!(conserved.into_iter().any(|c| c.unwrap()));
+}
@@ -0,0 +1,55 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::HashMap;
+use tf_binding_rs::controls::{self, ControlKind};
+use tf_binding_rs::{fasta, occupancy};
+
+fn dinucleotide_counts(seq: &str) -> HashMap<(char, char), usize> {
+    let chars: Vec<char> = seq.chars().collect();
+    let mut counts = HashMap::new();
+    for pair in chars.windows(2) {
+        *counts.entry((pair[0], pair[1])).or_insert(0) += 1;
+    }
+    counts
+}
+
+#[test]
+fn test_dinucleotide_shuffle() {
+    let seq = "AGCTTTTTAATAGAGTCAGCAAAACTGAAGCCTCTTCTCATCCTCTGATAATCACTGACC";
+    let mut rng = StdRng::seed_from_u64(1);
+    let shuffled = controls::dinucleotide_shuffle(seq, &mut rng);
+
+    assert_eq!(shuffled.len(), seq.len());
+    assert_ne!(shuffled, seq);
+    assert_eq!(dinucleotide_counts(&shuffled), dinucleotide_counts(seq));
+    assert_eq!(shuffled.chars().next(), seq.chars().next());
+    assert_eq!(shuffled.chars().last(), seq.chars().last());
+}
+
+#[test]
+fn test_generate_controls() {
+    let df = fasta::read_fasta("tests/data/test1.fasta").unwrap();
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+
+    let controls = controls::generate_controls(&df, ControlKind::Shuffle, 2, 42).unwrap();
+    assert_eq!(controls.height(), 6);
+    let again = controls::generate_controls(&df, ControlKind::Shuffle, 2, 42).unwrap();
+    assert!(controls.equals(&again));
+
+    let kind = ControlKind::MotifScramble {
+        ewms: &ewms,
+        mu: 9.0,
+        cutoff: 0.2,
+    };
+    let scrambled = controls::generate_controls(&df, kind, 1, 42).unwrap();
+    let original = df.column("sequence").unwrap().str().unwrap();
+    let controls = scrambled.column("sequence").unwrap().str().unwrap();
+    for (a, b) in original.into_iter().zip(controls) {
+        let (a, b) = (a.unwrap(), b.unwrap());
+        let mut a_sorted: Vec<char> = a.chars().collect();
+        let mut b_sorted: Vec<char> = b.chars().collect();
+        a_sorted.sort();
+        b_sorted.sort();
+        assert_eq!(a_sorted, b_sorted);
+    }
+}
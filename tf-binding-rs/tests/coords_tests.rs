@@ -0,0 +1,192 @@
+use polars::prelude::*;
+use tf_binding_rs::coords::{
+    cluster_tile_hits, parse_labels, project_hits, CoordinateSystem, LabelParser,
+};
+use tf_binding_rs::fasta;
+use tf_binding_rs::output;
+
+#[test]
+fn test_label_parser() {
+    let parser = LabelParser::default();
+    let region = parser
+        .parse("chr1-4357766-4357930_CPPP_MUT-allCrxSites")
+        .unwrap();
+    assert_eq!(region.chrom, "chr1");
+    assert_eq!((region.start, region.end), (4357766, 4357930));
+    assert_eq!(region.suffix.as_deref(), Some("CPPP_MUT-allCrxSites"));
+
+    let region = parser.parse("chrX:100-200").unwrap();
+    assert_eq!((region.start, region.end, region.suffix), (100, 200, None));
+    assert!(parser.parse("enhancer_7").is_none());
+    assert!(parser.parse("chr1-200-100").is_none());
+
+    let one_based = LabelParser::new(
+        r"^(?P<chrom>\w+):(?P<start>\d+)\.\.(?P<end>\d+)$",
+        CoordinateSystem::OneBased,
+    )
+    .unwrap();
+    let region = one_based.parse("chr2:101..200").unwrap();
+    assert_eq!((region.start, region.end), (100, 200));
+    assert!(LabelParser::new(r"(?P<chrom>\w+)", CoordinateSystem::ZeroBased).is_err());
+}
+
+#[test]
+fn test_parse_labels() {
+    let df = fasta::read_fasta("tests/data/test1.fasta").unwrap();
+    let parsed = parse_labels(&df, "label", &LabelParser::default()).unwrap();
+    assert_eq!(parsed.width(), 7);
+    let starts: Vec<_> = parsed
+        .column("start")
+        .unwrap()
+        .u64()
+        .unwrap()
+        .into_no_null_iter()
+        .collect();
+    assert_eq!(starts, [4357766, 4357766, 73826292]);
+    let suffix = parsed.column("suffix").unwrap().str().unwrap().get(2);
+    assert_eq!(suffix, Some("CPPE_WT"));
+
+    assert!(parse_labels(&df, "name", &LabelParser::default()).is_err());
+}
+
+#[test]
+fn test_project_hits() {
+    let sequences = polars::df!(
+        "label" => ["chr1-1000-1100_A", "chr2:500-600(-)", "unplaced"],
+        "sequence" => ["A", "C", "G"],
+    )
+    .unwrap();
+    let regions = parse_labels(&sequences, "label", &LabelParser::default()).unwrap();
+    let hits = polars::df!(
+        "label" => ["chr1-1000-1100_A", "chr2:500-600(-)", "chr2:500-600(-)", "unplaced"],
+        "motif" => ["NRL", "NRL", "CRX", "CRX"],
+        "position" => [10i32, 10, 0, 3],
+        "strand" => ["F", "F", "R", "F"],
+        "length" => [11i32, 11, 6, 6],
+        "occupancy" => [0.5, 0.9, 1.0, 0.7],
+    )
+    .unwrap();
+
+    let projected = project_hits(&hits, &regions).unwrap();
+    let starts: Vec<_> = projected
+        .column("genomic_start")
+        .unwrap()
+        .i64()
+        .unwrap()
+        .into_iter()
+        .collect();
+    // minus strand: position 10 of an 11 bp site counts back from the region end
+    assert_eq!(starts, [Some(1010), Some(579), Some(594), None]);
+    let strands: Vec<_> = projected
+        .column("genomic_strand")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_iter()
+        .collect();
+    assert_eq!(strands, [Some("+"), Some("-"), Some("+"), None]);
+
+    let path = "tests/data/project_test.bed";
+    assert_eq!(output::write_bed(&projected, path).unwrap(), 3);
+    let bed = std::fs::read_to_string(path).unwrap();
+    assert_eq!(bed.lines().next().unwrap(), "chr1\t1010\t1021\tNRL\t500\t+");
+    std::fs::remove_file(path).unwrap();
+}
+
+#[cfg(feature = "bigbed")]
+#[test]
+fn test_write_bigbed() {
+    let hits = polars::df!(
+        "chrom" => [Some("chr2"), Some("chr1"), None],
+        "genomic_start" => [Some(100i64), Some(50), None],
+        "genomic_end" => [Some(111i64), Some(61), None],
+        "genomic_strand" => [Some("+"), Some("-"), None],
+        "motif" => ["NRL", "CRX", "CRX"],
+        "occupancy" => [0.5, 0.9, 0.7],
+    )
+    .unwrap();
+    let sizes =
+        std::collections::HashMap::from([("chr1".to_string(), 1000), ("chr2".to_string(), 1000)]);
+
+    let path = "tests/data/project_test.bb";
+    assert_eq!(output::write_bigbed(&hits, &sizes, path).unwrap(), 2);
+    let bytes = std::fs::read(path).unwrap();
+    // bigBed magic number, little-endian
+    assert_eq!(&bytes[..4], &0x8789_F2EBu32.to_le_bytes());
+    std::fs::remove_file(path).unwrap();
+
+    let small =
+        std::collections::HashMap::from([("chr1".to_string(), 60), ("chr2".to_string(), 1000)]);
+    assert!(output::write_bigbed(&hits, &small, path).is_err());
+}
+
+#[cfg(feature = "bigbed")]
+#[test]
+fn test_write_bigwig() {
+    let values = vec![
+        ("chr2".to_string(), 10, 0.5f32),
+        ("chr1".to_string(), 5, 0.9),
+        ("chr1".to_string(), 4, 0.1),
+    ];
+    let sizes =
+        std::collections::HashMap::from([("chr1".to_string(), 100), ("chr2".to_string(), 100)]);
+
+    let path = "tests/data/landscape_test.bw";
+    assert_eq!(output::write_bigwig(&values, &sizes, path).unwrap(), 3);
+    let bytes = std::fs::read(path).unwrap();
+    // bigWig magic number, little-endian
+    assert_eq!(&bytes[..4], &0x888F_FC26u32.to_le_bytes());
+    std::fs::remove_file(path).unwrap();
+
+    let mut duplicated = values.clone();
+    duplicated.push(("chr1".to_string(), 5, 0.2));
+    assert!(output::write_bigwig(&duplicated, &sizes, path).is_err());
+    let small =
+        std::collections::HashMap::from([("chr1".to_string(), 5), ("chr2".to_string(), 100)]);
+    assert!(output::write_bigwig(&values, &small, path).is_err());
+}
+
+#[test]
+fn test_cluster_tile_hits() {
+    let records = fasta::read_fasta("tests/data/test1.fasta").unwrap();
+    let seq = records
+        .column("sequence")
+        .unwrap()
+        .str()
+        .unwrap()
+        .get(0)
+        .unwrap();
+    let ewms = tf_binding_rs::occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+
+    // two overlapping tiles of chr1-4357766-4357930 and a third tile far away
+    let tiles = df!(
+        "label" => [
+            "chr1-4357766-4357866",
+            "chr1-4357776-4357930",
+            "chr1-4357800-4357930",
+        ],
+        "sequence" => [&seq[..100], &seq[10..], &seq[34..]],
+    )
+    .unwrap();
+    let hits = tf_binding_rs::scan::scan_sequences(&tiles, &ewms, 9.0, 0.2, |_| {})
+        .unwrap()
+        .hits;
+    let regions = parse_labels(&tiles, "label", &LabelParser::default()).unwrap();
+    let sites = cluster_tile_hits(&hits, &regions, 0).unwrap();
+
+    let nrl = sites
+        .lazy()
+        .filter(col("genomic_start").eq(lit(4357780i64)))
+        .collect()
+        .unwrap();
+    assert_eq!(nrl.height(), 1);
+    let value = |name: &str| nrl.column(name).unwrap().get(0).unwrap().to_string();
+    assert_eq!(value("support"), "2");
+    // the third tile starts after the site
+    assert_eq!(value("covering"), "2");
+    assert_eq!(value("genomic_strand"), "\"-\"");
+    assert_eq!(
+        value("tiles"),
+        "\"chr1-4357766-4357866,chr1-4357776-4357930\""
+    );
+}
@@ -0,0 +1,199 @@
+use polars::prelude::*;
+use tf_binding_rs::design::{self, TileOptions};
+use tf_binding_rs::occupancy;
+
+#[test]
+fn test_design_tiles() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let regions = df!(
+        "label" => ["enh1", "enh2"],
+        "sequence" => [
+            "AGCTTTTTAATAGAGTCAGCAAAACTGAAGCCTCTTCTCATCCTCTGATAATCACTGACCTGACCTTGAATAGCCTGC",
+            "TTAATCTCTTTTCCCTCACGAATTCCAACTACAAGAAGGTAAGAGGCTGTTTCACCAG",
+        ],
+    )
+    .unwrap();
+
+    let options = TileOptions {
+        tile_length: 40,
+        step: 20,
+        left_adapter: "ACTG".to_string(),
+        right_adapter: "CAGT".to_string(),
+        restriction_sites: vec!["GAATTC".to_string()],
+        ..Default::default()
+    };
+    let design = design::design_tiles(&regions, &options, &ewms, 9.0).unwrap();
+
+    // enh1 (80 bp) -> starts 0, 20, 40; enh2 (58 bp) -> starts 0, 18
+    assert_eq!(design.height(), 5);
+
+    let sequences = design.column("sequence").unwrap().str().unwrap();
+    assert!(sequences
+        .into_iter()
+        .all(|s| s.unwrap().len() == 48 && !s.unwrap().contains("GAATTC")));
+
+    let status = design.column("status").unwrap().str().unwrap();
+    assert_eq!(status.get(3), Some("repaired"));
+    assert_eq!(status.get(0), Some("ok"));
+}
+
+#[test]
+fn test_design_tiles_rejects_unrepairable() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let regions = df!(
+        "label" => ["enh1"],
+        "sequence" => ["GAATTCGAATTCGAATTCGAATTCAGCTTTTTAATAGAGTCAG"],
+    )
+    .unwrap();
+
+    let options = TileOptions {
+        tile_length: 50,
+        step: 50,
+        restriction_sites: vec!["GAATTC".to_string()],
+        max_edits: 2,
+        ..Default::default()
+    };
+    let design = design::design_tiles(&regions, &options, &ewms, 9.0).unwrap();
+    assert_eq!(design.height(), 1);
+    let status = design.column("status").unwrap().str().unwrap();
+    assert_eq!(status.get(0), Some("rejected"));
+}
+
+#[test]
+fn test_design_tiles_short_and_empty_regions() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let regions = df!(
+        "label" => ["empty", "short", "enh1"],
+        "sequence" => ["", "GAATTCA", "TTAATCTCTTTTCCCTCACGAATTCCAACTACAAGAAGG"],
+    )
+    .unwrap();
+
+    let options = TileOptions {
+        tile_length: 40,
+        step: 40,
+        restriction_sites: vec!["GAATTC".to_string()],
+        ..Default::default()
+    };
+    let design = design::design_tiles(&regions, &options, &ewms, 9.0).unwrap();
+
+    // the empty region has no tiles, and the short tile is too short to score its edits
+    let labels = design.column("label").unwrap().str().unwrap();
+    assert_eq!(labels.get(0), Some("short"));
+    assert_eq!(labels.get(1), Some("enh1"));
+    let status = design.column("status").unwrap().str().unwrap();
+    assert_eq!(status.get(0), Some("rejected"));
+    assert_eq!(status.get(1), Some("repaired"));
+    assert_eq!(design.height(), 2);
+}
+
+#[test]
+fn test_ablate_site() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let seq = "AGCTTTTTAATAGAGTCAGCAAAACTGAAGCCTCTTCTCATCCTCTGATAATCACTGACCTGACCTTGAATAGCCTGC";
+
+    let hits = occupancy::find_hits(seq, &ewms, 9.0, 0.5).unwrap();
+    assert!(hits.height() > 0);
+    let site = design::Site {
+        motif: hits
+            .column("motif")
+            .unwrap()
+            .str()
+            .unwrap()
+            .get(0)
+            .unwrap()
+            .to_string(),
+        position: hits
+            .column("position")
+            .unwrap()
+            .i32()
+            .unwrap()
+            .get(0)
+            .unwrap() as usize,
+        strand: hits
+            .column("strand")
+            .unwrap()
+            .str()
+            .unwrap()
+            .get(0)
+            .unwrap()
+            .to_string(),
+    };
+
+    let options = design::AblationOptions {
+        threshold: 0.1,
+        tolerance: 1.0,
+        max_edits: 2,
+    };
+    let knockout = design::ablate_site(seq, &ewms, 9.0, &site, &options)
+        .unwrap()
+        .unwrap();
+    assert!(knockout.site_occupancy < 0.1);
+    assert!(!knockout.edits.is_empty() && knockout.edits.len() <= 2);
+    assert_eq!(knockout.sequence.len(), seq.len());
+
+    // unknown motifs are rejected
+    let unknown = design::Site {
+        motif: "NOPE".to_string(),
+        ..site
+    };
+    assert!(design::ablate_site(seq, &ewms, 9.0, &unknown, &options).is_err());
+}
+
+#[test]
+fn test_knock_in_site() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let seq = "AGCTTTTTAATAGAGTCAGCAAAACTGAAGCCTCTTCTCATCCTCTGATAATCACTGACCTGACCTTGAATAGCCTGC";
+    let motif = ewms.keys().min().unwrap().clone();
+
+    let site = design::Site {
+        motif,
+        position: 30,
+        strand: "R".to_string(),
+    };
+    let result = design::knock_in_site(seq, &ewms, 9.0, &site, 0.9)
+        .unwrap()
+        .unwrap();
+    assert!(result.site_occupancy >= 0.9);
+    assert!(!result.edits.is_empty());
+    assert!(result.audit.height() > 0);
+
+    // editing only changes bases listed in the edits
+    let changed = seq
+        .chars()
+        .zip(result.sequence.chars())
+        .filter(|(a, b)| a != b)
+        .count();
+    assert_eq!(changed, result.edits.len());
+}
+
+#[test]
+fn test_optimize_sequence() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let seq = "AGCTTTTTAATAGAGTCAGCAAAACTGAAGCCTCTTCTCATCCTCTGATAATCACTGACC";
+    let mut ids: Vec<&String> = ewms.keys().collect();
+    ids.sort();
+
+    let options = design::OptimizeOptions {
+        targets: [(ids[0].clone(), 2.0), (ids[1].clone(), 0.0)].into(),
+        gc_min: 0.3,
+        gc_max: 0.7,
+        forbidden_sites: vec!["GAATTC".to_string()],
+        iterations: 300,
+        seed: 7,
+        ..Default::default()
+    };
+
+    let initial = occupancy::total_occupancy(seq, &ewms, 9.0).unwrap();
+    let initial_loss = (initial[ids[0]] - 2.0).powi(2) + initial[ids[1]].powi(2);
+
+    let result = design::optimize_sequence(seq, &ewms, 9.0, &options).unwrap();
+    assert_eq!(result.sequence.len(), seq.len());
+    assert!(result.feasible);
+    assert!(result.loss <= initial_loss);
+    assert!(!result.sequence.contains("GAATTC"));
+
+    // runs are reproducible for a fixed seed
+    let again = design::optimize_sequence(seq, &ewms, 9.0, &options).unwrap();
+    assert_eq!(result.sequence, again.sequence);
+    assert_eq!(result.loss.to_bits(), again.loss.to_bits());
+}
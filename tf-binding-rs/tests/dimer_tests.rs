@@ -0,0 +1,141 @@
+use polars::prelude::*;
+use std::collections::HashMap;
+use tf_binding_rs::dimer::{find_dimer_hits, DimerOrientation, DimerScanner, DimerSpec};
+use tf_binding_rs::fasta::reverse_complement;
+use tf_binding_rs::matrix;
+use tf_binding_rs::occupancy::{pwm_to_ewm, StrandedEwm};
+use tf_binding_rs::types::EWMCollection;
+
+/// Nearly deterministic motif of `consensus`
+fn sharp_ewm(consensus: &str) -> DataFrame {
+    let rows: Vec<[f64; 4]> = consensus
+        .chars()
+        .map(|base| {
+            let mut row = [0.01; 4];
+            row["ACGT".find(base).unwrap()] = 0.97;
+            row
+        })
+        .collect();
+    pwm_to_ewm(&matrix::pwm_from_rows(&rows).unwrap()).unwrap()
+}
+
+fn motifs() -> EWMCollection {
+    HashMap::from([
+        ("CRX_TEST".to_string(), sharp_ewm("ACCA")),
+        ("NRL_TEST".to_string(), sharp_ewm("GTGT")),
+    ])
+}
+
+fn spec(first: &str, second: &str, orientation: DimerOrientation) -> DimerSpec {
+    DimerSpec {
+        first: first.to_string(),
+        second: second.to_string(),
+        min_spacing: 1,
+        max_spacing: 3,
+        orientation,
+        interaction: -1.0,
+    }
+}
+
+#[test]
+fn test_tandem_dimer_on_both_strands() {
+    let ewms = motifs();
+    let seq = "TTTTACCATTGTGTTTTT";
+    let scanner = DimerScanner::new(&spec("CRX", "NRL", DimerOrientation::Tandem), &ewms).unwrap();
+
+    let sites = scanner.scan(seq, 2.0, 0.5).unwrap();
+    assert_eq!(sites.len(), 1);
+    let site = &sites[0];
+    assert_eq!((site.position, site.length, site.spacing), (4, 10, 2));
+    assert_eq!((site.first_position, site.second_position), (4, 10));
+    assert_eq!(
+        (site.strand, site.first_strand, site.second_strand),
+        ("F", "F", "F")
+    );
+    let energy = StrandedEwm::new(&ewms["CRX_TEST"])
+        .unwrap()
+        .window_energy(seq, 4, false)
+        .unwrap()
+        + StrandedEwm::new(&ewms["NRL_TEST"])
+            .unwrap()
+            .window_energy(seq, 10, false)
+            .unwrap()
+        - 1.0;
+    assert!((site.energy - energy).abs() < 1e-9);
+    assert!((site.occupancy - 1.0 / (1.0 + (energy - 4.0).exp())).abs() < 1e-12);
+
+    // the same element read on the reverse strand
+    let sites = scanner
+        .scan(&reverse_complement(seq).unwrap(), 2.0, 0.5)
+        .unwrap();
+    assert_eq!(sites.len(), 1);
+    let site = &sites[0];
+    assert_eq!(
+        (site.position, site.first_position, site.second_position),
+        (4, 10, 4)
+    );
+    assert_eq!(
+        (site.strand, site.first_strand, site.second_strand),
+        ("R", "R", "R")
+    );
+
+    // spacing and orientation constraints
+    let inverted =
+        DimerScanner::new(&spec("CRX", "NRL", DimerOrientation::Inverted), &ewms).unwrap();
+    assert!(inverted.scan(seq, 2.0, 0.5).unwrap().is_empty());
+    let mut far = spec("CRX", "NRL", DimerOrientation::Any);
+    far.min_spacing = 3;
+    assert!(DimerScanner::new(&far, &ewms)
+        .unwrap()
+        .scan(seq, 2.0, 0.5)
+        .unwrap()
+        .is_empty());
+    far.max_spacing = 2;
+    assert!(DimerScanner::new(&far, &ewms).is_err());
+    assert!(DimerScanner::new(&spec("CRX", "SOX", DimerOrientation::Any), &ewms).is_err());
+}
+
+#[test]
+fn test_homodimer_orientations() {
+    let ewms = motifs();
+    // CRX facing its reverse complement: an inverted repeat
+    let seq = "TTTTACCATTTTGGTTTTT";
+    let scan = |orientation| {
+        DimerScanner::new(&spec("CRX", "CRX", orientation), &ewms)
+            .unwrap()
+            .scan(seq, 2.0, 0.5)
+            .unwrap()
+    };
+    let sites = scan(DimerOrientation::Inverted);
+    assert_eq!(sites.len(), 1);
+    assert_eq!((sites[0].first_position, sites[0].second_position), (4, 11));
+    assert_eq!(sites[0].spacing, 3);
+    assert!(scan(DimerOrientation::Tandem).is_empty());
+    assert!(scan(DimerOrientation::Everted).is_empty());
+    assert_eq!(scan(DimerOrientation::Any).len(), 1);
+}
+
+#[test]
+fn test_find_dimer_hits() {
+    let ewms = motifs();
+    let df = df!(
+        "label" => ["enh1", "enh2", "short"],
+        "sequence" => ["TTTTACCATTGTGTTTTT", "TTTTTTTTTTTTTTTTTT", "ACC"],
+    )
+    .unwrap();
+    let specs = [spec("CRX", "NRL", DimerOrientation::Tandem)];
+    let hits = find_dimer_hits(&df, &ewms, &specs, 2.0, 0.5).unwrap();
+    assert_eq!(hits.height(), 1);
+    assert_eq!(
+        hits.column("label").unwrap().str().unwrap().get(0),
+        Some("enh1")
+    );
+    assert_eq!(
+        hits.column("dimer").unwrap().str().unwrap().get(0),
+        Some("CRX::NRL")
+    );
+    assert_eq!(
+        hits.column("spacing").unwrap().i32().unwrap().get(0),
+        Some(2)
+    );
+}
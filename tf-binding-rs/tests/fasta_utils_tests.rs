@@ -50,3 +50,314 @@ fn test_reverse_complement() {
     let sequence = "ATCGX";
     assert!(fasta::reverse_complement(sequence).is_err());
 }
+
+#[test]
+fn test_sequence_stats() {
+    let df: DataFrame = df!(
+        "label" => ["a", "b", "a", "c"],
+        "sequence" => ["ACGT", "GGCCNN", "AAAAAAAAAA", "ACGX"],
+    )
+    .unwrap();
+
+    let stats = fasta::sequence_stats(&df).unwrap();
+    assert_eq!(stats.n_sequences, 4);
+    assert_eq!(stats.min_length, 4);
+    assert_eq!(stats.median_length, 5.0);
+    assert_eq!(stats.max_length, 10);
+    assert_eq!(stats.n50, 6);
+    assert_eq!(stats.n_content, 2.0 / 24.0);
+    assert_eq!(stats.duplicate_labels, vec!["a"]);
+    assert_eq!(stats.invalid_labels, vec!["c"]);
+    assert_eq!(stats.to_dataframe().unwrap().height(), 9);
+}
+
+#[test]
+fn test_resolve_duplicate_labels() {
+    use fasta::DuplicateLabels;
+
+    let df = df!(
+        "label" => ["a", "b", "a", "a_2", "a"],
+        "sequence" => ["AC", "GT", "AA", "CC", "GG"],
+    )
+    .unwrap();
+
+    let (kept, duplicates) = fasta::resolve_duplicate_labels(&df, DuplicateLabels::Keep).unwrap();
+    assert_eq!(kept.height(), 5);
+    assert_eq!(duplicates.len(), 2);
+    assert_eq!((duplicates[0].index, duplicates[0].first_index), (2, 0));
+
+    assert!(fasta::resolve_duplicate_labels(&df, DuplicateLabels::Error).is_err());
+
+    let (first, _) = fasta::resolve_duplicate_labels(&df, DuplicateLabels::KeepFirst).unwrap();
+    let sequences: Vec<_> = first
+        .column("sequence")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_no_null_iter()
+        .collect();
+    assert_eq!(sequences, ["AC", "GT", "CC"]);
+
+    // "a_2" is taken, so the repeats become a_3 and a_4
+    let (renamed, duplicates) =
+        fasta::resolve_duplicate_labels(&df, DuplicateLabels::Suffix).unwrap();
+    let labels: Vec<_> = renamed
+        .column("label")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_no_null_iter()
+        .collect();
+    assert_eq!(labels, ["a", "b", "a_3", "a_2", "a_4"]);
+    assert_eq!(duplicates[1].renamed.as_deref(), Some("a_4"));
+
+    assert_eq!(
+        "keep-first".parse::<DuplicateLabels>().unwrap(),
+        DuplicateLabels::KeepFirst
+    );
+    assert!("drop".parse::<DuplicateLabels>().is_err());
+}
+
+#[test]
+fn test_subset() {
+    let df = fasta::read_fasta("tests/data/test1.fasta").unwrap();
+    let wild_type = fasta::subset(&df, &fasta::LabelSelector::pattern("_WT$").unwrap()).unwrap();
+    assert_eq!(wild_type.height(), 2);
+
+    let selector = fasta::LabelSelector::labels(["chr1-73826292-73826456_CPPE_WT", "missing"]);
+    assert_eq!(fasta::subset(&df, &selector).unwrap().height(), 1);
+    assert!(fasta::LabelSelector::pattern("(").is_err());
+}
+
+#[test]
+fn test_subset_file() {
+    let path = "tests/data/subset_test.fasta";
+    std::fs::write(path, ">a enhancer\nACGT\nac\n>b\nGGGG\nTT\n>c\nTTTT\n").unwrap();
+    let selector = fasta::LabelSelector::pattern("^[ac]").unwrap();
+
+    // streamed: labels are full headers
+    let streamed = fasta::subset_file(path, &selector).unwrap();
+    let labels: Vec<_> = streamed
+        .column("label")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_no_null_iter()
+        .collect();
+    assert_eq!(labels, ["a enhancer", "c"]);
+
+    // indexed: labels are the index names
+    let index = format!("{}.fai", path);
+    std::fs::write(&index, "a\t6\t12\t4\t5\nb\t6\t23\t4\t5\nc\t4\t34\t4\t5\n").unwrap();
+    let indexed = fasta::subset_file(path, &selector).unwrap();
+    let labels: Vec<_> = indexed
+        .column("label")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_no_null_iter()
+        .collect();
+    assert_eq!(labels, ["a", "c"]);
+    assert!(indexed
+        .column("sequence")
+        .unwrap()
+        .equals(streamed.column("sequence").unwrap()));
+    assert_eq!(fasta::read_fasta_index(&index).unwrap()[1].offset, 23);
+
+    std::fs::remove_file(path).unwrap();
+    std::fs::remove_file(index).unwrap();
+}
+
+#[test]
+fn test_trim() {
+    let df = df!(
+        "label" => ["a", "b", "c"],
+        "sequence" => [Some("AACCGGTT"), Some("ACG"), None],
+    )
+    .unwrap();
+    let trimmed = fasta::trim(&df, 2, 1).unwrap();
+    let sequences = trimmed.column("sequence").unwrap().str().unwrap();
+    assert_eq!(sequences.get(0), Some("CCGGT"));
+    assert_eq!(sequences.get(1), Some(""));
+    assert_eq!(sequences.get(2), None);
+    assert_eq!(
+        trimmed.column("label").unwrap(),
+        df.column("label").unwrap()
+    );
+}
+
+#[test]
+fn test_trim_adapters() {
+    let five_prime = "GGTACC";
+    let three_prime = "TTAACC";
+    let df = df!(
+        "label" => ["full", "partial", "mismatch", "none"],
+        "sequence" => [
+            "GGTACCACGTACGTTTAACC",
+            "TACCACGTACGTTTA",
+            "GGTTCCACGTACGTTTAACC",
+            "ACGTACGT",
+        ],
+    )
+    .unwrap();
+    let options = fasta::AdapterOptions::default();
+    let trimmed = fasta::trim_adapters(&df, Some(five_prime), Some(three_prime), &options).unwrap();
+    let sequences = trimmed.column("sequence").unwrap().str().unwrap();
+    assert_eq!(sequences.get(0), Some("ACGTACGT"));
+    // partial adapters of at least 3 bases at both ends
+    assert_eq!(sequences.get(1), Some("ACGTACGT"));
+    // one mismatch in six bases exceeds the default error rate
+    assert_eq!(sequences.get(2), Some("GGTTCCACGTACGT"));
+    assert_eq!(sequences.get(3), Some("ACGTACGT"));
+    let left = trimmed.column("trimmed_5p").unwrap().u32().unwrap();
+    assert_eq!(left.get(1), Some(4));
+
+    let lenient = fasta::AdapterOptions {
+        min_overlap: 3,
+        error_rate: 0.2,
+    };
+    let trimmed = fasta::trim_adapters(&df, Some(five_prime), None, &lenient).unwrap();
+    let sequences = trimmed.column("sequence").unwrap().str().unwrap();
+    assert_eq!(sequences.get(2), Some("ACGTACGTTTAACC"));
+
+    let invalid = fasta::AdapterOptions {
+        min_overlap: 3,
+        error_rate: 2.0,
+    };
+    assert!(fasta::trim_adapters(&df, Some(five_prime), None, &invalid).is_err());
+}
+
+#[test]
+fn test_n_report() {
+    let df = df!(
+        "label" => ["clean", "gaps", "all"],
+        "sequence" => ["ACGT", "NNACGTnACGNNN", "NNNN"],
+    )
+    .unwrap();
+    let report = fasta::n_report(&df).unwrap();
+    assert_eq!(report.height(), 3);
+    let column = |name: &str| report.column(name).unwrap().u32().unwrap().clone();
+    assert_eq!(column("n_count").get(0), Some(0));
+    assert_eq!(column("n_count").get(1), Some(6));
+    assert_eq!(column("n_runs").get(1), Some(3));
+    assert_eq!(column("longest_n_run").get(1), Some(3));
+    assert_eq!(column("longest_n_run").get(2), Some(4));
+
+    let positions = report.column("n_positions").unwrap().str().unwrap();
+    assert_eq!(positions.get(0), Some(""));
+    assert_eq!(positions.get(1), Some("0-2,6-7,10-13"));
+    assert_eq!(positions.get(2), Some("0-4"));
+    let fractions = report.column("n_fraction").unwrap().f64().unwrap();
+    assert_eq!(fractions.get(2), Some(1.0));
+}
+
+#[test]
+fn test_normalize_sequences() {
+    let df = df!(
+        "label" => ["spaced", "masked"],
+        "sequence" => ["AC GT\r", "acgtNNac"],
+    )
+    .unwrap();
+    let normalized = fasta::normalize_sequences(&df, &fasta::Normalization::default()).unwrap();
+    let sequences = normalized.column("sequence").unwrap().str().unwrap();
+    assert_eq!(sequences.get(0), Some("ACGT"));
+    assert_eq!(sequences.get(1), Some("ACGTNNAC"));
+
+    let keep_case = fasta::Normalization {
+        uppercase: false,
+        validate: true,
+        ..fasta::Normalization::default()
+    };
+    let masked = fasta::normalize_sequences(&df, &keep_case).unwrap();
+    let sequences = masked.column("sequence").unwrap().str().unwrap();
+    assert_eq!(sequences.get(1), Some("acgtNNac"));
+
+    // the error points at the record and the offset of the character in the input
+    let invalid = df!("label" => ["ok", "bad"], "sequence" => ["ACGT", "AC-GT"]).unwrap();
+    match fasta::normalize_sequences(&invalid, &keep_case) {
+        Err(tf_binding_rs::error::MotifError::InvalidSequence { position, message }) => {
+            assert_eq!(position, 2);
+            assert!(message.contains("record 1 (bad)"));
+        }
+        other => panic!("expected an invalid sequence error, got {:?}", other),
+    }
+    // without validation the character is left to the scan
+    assert!(fasta::normalize_sequences(&invalid, &fasta::Normalization::default()).is_ok());
+
+    let path = std::env::temp_dir().join("tf_binding_normalize.fasta");
+    std::fs::write(&path, ">seq1\r\nacgt\r\nAC GT\r\n>seq2\nNNac\n").unwrap();
+    let read = fasta::read_fasta_normalized(path.to_str().unwrap(), &keep_case).unwrap();
+    let sequences = read.column("sequence").unwrap().str().unwrap();
+    assert_eq!(sequences.get(0), Some("acgtACGT"));
+    assert_eq!(sequences.get(1), Some("NNac"));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_sample_sequences() {
+    let labels: Vec<String> = (0..100).map(|i| format!("seq{}", i)).collect();
+    let df = df!(
+        "label" => &labels,
+        "sequence" => vec!["ACGT"; 100],
+    )
+    .unwrap();
+    let sampled = fasta::sample_sequences(&df, 10, 42).unwrap();
+    assert_eq!(sampled.height(), 10);
+    assert!(sampled.equals(&fasta::sample_sequences(&df, 10, 42).unwrap()));
+    assert!(!sampled.equals(&fasta::sample_sequences(&df, 10, 43).unwrap()));
+
+    // rows stay in input order
+    let rows: Vec<usize> = sampled
+        .column("label")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_iter()
+        .map(|label| label.unwrap()[3..].parse().unwrap())
+        .collect();
+    assert!(rows.windows(2).all(|w| w[0] < w[1]));
+
+    assert_eq!(fasta::sample_sequences(&df, 500, 42).unwrap().height(), 100);
+}
+
+#[test]
+fn test_rename_input_columns() {
+    let table = df!(
+        "oligo_id" => [101, 102],
+        "oligo_seq" => ["ACGT", "GGCC"],
+        "sequence" => ["full", "full"],
+    )
+    .unwrap();
+    let columns = fasta::InputColumns {
+        sequence: "oligo_seq".to_string(),
+        id: Some("oligo_id".to_string()),
+    };
+    assert!(!columns.is_default());
+
+    let renamed = fasta::rename_input_columns(table.clone(), &columns).unwrap();
+    assert_eq!(renamed.width(), 2);
+    let sequences: Vec<&str> = renamed
+        .column("sequence")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_no_null_iter()
+        .collect();
+    assert_eq!(sequences, ["ACGT", "GGCC"]);
+    let labels: Vec<&str> = renamed
+        .column("label")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_no_null_iter()
+        .collect();
+    assert_eq!(labels, ["101", "102"]);
+
+    let unchanged = fasta::rename_input_columns(table.clone(), &fasta::InputColumns::default());
+    assert!(unchanged.unwrap().equals(&table));
+    let missing = fasta::InputColumns {
+        sequence: "seq".to_string(),
+        id: None,
+    };
+    assert!(fasta::rename_input_columns(table, &missing).is_err());
+}
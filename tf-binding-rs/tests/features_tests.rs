@@ -0,0 +1,218 @@
+use polars::prelude::*;
+use std::collections::HashMap;
+use tf_binding_rs::error::MotifError;
+use tf_binding_rs::features::{
+    hit_matrix, normalize_features, occupancy_features, positional_features, predicted_activity,
+    read_roles, signed_features, Aggregation, MotifRole, Normalization,
+};
+use tf_binding_rs::occupancy;
+
+fn values(df: &DataFrame, name: &str) -> Vec<f64> {
+    df.column(name)
+        .unwrap()
+        .f64()
+        .unwrap()
+        .into_no_null_iter()
+        .collect()
+}
+
+#[test]
+fn test_occupancy_features() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let seq = "GGGCTTAGTAACGTTACGGAAGGGCGTGGCCTTTAAATTCCGGGG";
+    let df = df!("label" => ["a", "short"], "sequence" => [seq, "ACGT"]).unwrap();
+
+    let features = occupancy_features(&df, &ewms, 9.0).unwrap();
+    assert_eq!(features.shape(), (2, 1 + ewms.len()));
+
+    let totals = occupancy::total_occupancy(seq, &ewms, 9.0).unwrap();
+    for (id, total) in &totals {
+        let column = features.column(id).unwrap().f64().unwrap();
+        assert!((column.get(0).unwrap() - total).abs() < 1e-12);
+        assert_eq!(column.get(1), None);
+    }
+}
+
+#[test]
+fn test_positional_features() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let seq = "GGGCTTAGTAACGTTACGGAAGGGCGTGGCCTTTAAATTCCGGGG";
+    let df = df!("label" => ["a", "short"], "sequence" => [seq, "ACGT"]).unwrap();
+
+    let features = occupancy_features(&df, &ewms, 9.0).unwrap();
+    let binned = positional_features(&df, &ewms, 9.0, 4).unwrap();
+    assert_eq!(binned.shape(), (2, 1 + 4 * ewms.len()));
+    for id in ewms.keys() {
+        let bins: Vec<Option<f64>> = (0..4)
+            .map(|bin| {
+                let column = binned.column(&format!("{}_bin{}", id, bin)).unwrap();
+                assert_eq!(column.f64().unwrap().get(1), None);
+                column.f64().unwrap().get(0)
+            })
+            .collect();
+        let total = features.column(id).unwrap().f64().unwrap().get(0).unwrap();
+        let sum: f64 = bins.iter().map(|bin| bin.unwrap()).sum();
+        assert!((sum - total).abs() < 1e-9);
+    }
+
+    // a strong NRL site centered at 30.5 of 40 bp lies in the last of 4 bins
+    let site = "GTCAGCAAAAC";
+    let nrl_bins = |seq: &str, bins: usize| -> Vec<f64> {
+        let df = df!("label" => ["site"], "sequence" => [seq]).unwrap();
+        let binned = positional_features(&df, &ewms, 9.0, bins).unwrap();
+        (0..bins)
+            .map(|bin| {
+                let column = format!("NRL_HUMAN.MA0842.1_bin{}", bin);
+                binned
+                    .column(&column)
+                    .unwrap()
+                    .f64()
+                    .unwrap()
+                    .get(0)
+                    .unwrap()
+            })
+            .collect()
+    };
+    let strong_bin = |bins: &[f64]| {
+        let strong = bins.iter().position(|&occ| occ > 0.99).unwrap();
+        for (bin, occ) in bins.iter().enumerate() {
+            assert!(bin == strong || *occ < 1e-6, "{:?}", bins);
+        }
+        strong
+    };
+    let end = format!("{}{}{}", "G".repeat(25), site, "G".repeat(4));
+    assert_eq!(strong_bin(&nrl_bins(&end, 4)), 3);
+
+    // centered at 16.5 of 33 bp, exactly on a bin boundary, the site counts towards the bin
+    // starting there
+    let middle = format!("{}{}{}", "G".repeat(11), site, "G".repeat(11));
+    assert_eq!(strong_bin(&nrl_bins(&middle, 2)), 1);
+    assert_eq!(strong_bin(&nrl_bins(&middle, 4)), 2);
+
+    assert!(positional_features(&df, &ewms, 9.0, 0).is_err());
+}
+
+#[test]
+fn test_hit_matrix() {
+    let hits = df!(
+        "label" => ["b", "b", "a", "b"],
+        "motif" => ["NRL", "NRL", "CRX", "CRX"],
+        "occupancy" => [0.5, 0.25, 0.75, 0.125],
+    )
+    .unwrap();
+
+    let sums = hit_matrix(&hits, None, Aggregation::Sum).unwrap();
+    assert_eq!(sums.get_column_names(), ["label", "CRX", "NRL"]);
+    let labels = sums.column("label").unwrap().str().unwrap();
+    assert_eq!(labels.get(0), Some("b"));
+    assert_eq!(values(&sums, "CRX"), [0.125, 0.75]);
+    assert_eq!(values(&sums, "NRL"), [0.75, 0.0]);
+
+    let sequences = df!("label" => ["a", "b", "c"], "sequence" => ["A", "C", "G"]).unwrap();
+    let maxima = hit_matrix(&hits, Some(&sequences), Aggregation::Max).unwrap();
+    assert_eq!(maxima.height(), 3);
+    assert_eq!(values(&maxima, "CRX"), [0.75, 0.125, 0.0]);
+    assert_eq!(values(&maxima, "NRL"), [0.0, 0.5, 0.0]);
+
+    let missing = df!("label" => ["a"], "sequence" => ["A"]).unwrap();
+    assert!(matches!(
+        hit_matrix(&hits, Some(&missing), Aggregation::Sum),
+        Err(MotifError::InvalidInput(_))
+    ));
+}
+
+#[test]
+fn test_normalize_features() {
+    let features = df!(
+        "label" => ["a", "b", "c", "d"],
+        "x" => [5.0, 2.0, 3.0, 4.0],
+        "y" => [4.0, 1.0, 4.0, 2.0],
+        "z" => [3.0, 4.0, 6.0, 8.0],
+    )
+    .unwrap();
+
+    // the classic example of quantile normalization, with a tie in y
+    let quantile = normalize_features(&features, Normalization::Quantile).unwrap();
+    assert_eq!(
+        quantile.column("label").unwrap(),
+        features.column("label").unwrap()
+    );
+    let expected = [
+        [5.666666666666667, 2.0, 3.0, 4.666666666666667],
+        [5.166666666666667, 2.0, 5.166666666666667, 3.0],
+        [2.0, 3.0, 4.666666666666667, 5.666666666666667],
+    ];
+    for (name, expected) in ["x", "y", "z"].iter().zip(expected) {
+        for (a, b) in values(&quantile, name).iter().zip(expected) {
+            assert!((a - b).abs() < 1e-12, "{}: {} vs {}", name, a, b);
+        }
+    }
+
+    let rank = normalize_features(&features, Normalization::Rank).unwrap();
+    assert_eq!(values(&rank, "y"), vec![0.875, 0.25, 0.875, 0.5]);
+
+    let zscore = normalize_features(&features, Normalization::ZScore).unwrap();
+    for name in ["x", "y", "z"] {
+        let z = values(&zscore, name);
+        let mean = z.iter().sum::<f64>() / 4.0;
+        let var = z.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / 4.0;
+        assert!(mean.abs() < 1e-12 && (var - 1.0).abs() < 1e-12);
+    }
+
+    let with_null = df!("x" => [Some(1.0), None]).unwrap();
+    assert!(normalize_features(&with_null, Normalization::Quantile).is_err());
+    let ranked = normalize_features(&with_null, Normalization::Rank).unwrap();
+    assert_eq!(ranked.column("x").unwrap().f64().unwrap().get(1), None);
+}
+
+#[test]
+fn test_signed_features_and_activity() {
+    let features = df!(
+        "label" => ["a", "b", "c"],
+        "NRL_HUMAN" => [Some(1.0), Some(0.5), None],
+        "MAZ" => [Some(2.0), Some(0.0), None],
+        "CRX" => [Some(0.5), Some(1.5), None],
+    )
+    .unwrap();
+    let path = std::env::temp_dir().join("tf_binding_roles.tsv");
+    std::fs::write(&path, "# motif\trole\nNRL\trepressor\nMAZ,activator\n").unwrap();
+    let roles = read_roles(path.to_str().unwrap()).unwrap();
+    assert_eq!(roles["NRL"], MotifRole::Repressor);
+    assert!("enhancer".parse::<MotifRole>().is_err());
+
+    // repressors are matched by name and negated, untagged motifs are kept
+    let signed = signed_features(&features, &roles).unwrap();
+    assert_eq!(
+        signed.column("NRL_HUMAN").unwrap().f64().unwrap().get(0),
+        Some(-1.0)
+    );
+    assert!(signed
+        .column("CRX")
+        .unwrap()
+        .equals_missing(features.column("CRX").unwrap()));
+
+    let activity = predicted_activity(&features, &roles, None).unwrap();
+    assert_eq!(
+        activity.get_column_names(),
+        ["label", "activation", "repression", "activity"]
+    );
+    let column = |name: &str| -> Vec<Option<f64>> {
+        activity
+            .column(name)
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_iter()
+            .collect()
+    };
+    assert_eq!(column("activation"), [Some(2.5), Some(1.5), None]);
+    assert_eq!(column("repression"), [Some(1.0), Some(0.5), None]);
+    assert_eq!(column("activity"), [Some(1.5), Some(1.0), None]);
+
+    let weights = HashMap::from([("NRL".to_string(), 2.0)]);
+    let weighted = predicted_activity(&features, &roles, Some(&weights)).unwrap();
+    assert_eq!(
+        weighted.column("activity").unwrap().f64().unwrap().get(0),
+        Some(0.5)
+    );
+}
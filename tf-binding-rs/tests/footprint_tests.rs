@@ -0,0 +1,43 @@
+use tf_binding_rs::{fasta, footprint, occupancy};
+
+#[test]
+fn test_protection_score() {
+    // a clean footprint: cleavage in the flanks, none over the site
+    let mut cuts = vec![10.0; 30];
+    for c in cuts.iter_mut().skip(10).take(10) {
+        *c = 0.0;
+    }
+    assert_eq!(footprint::protection_score(&cuts, 10, 10, 10), 1.0);
+
+    // uniform cleavage gives no protection
+    let flat = vec![5.0; 30];
+    assert_eq!(footprint::protection_score(&flat, 10, 10, 10), 0.0);
+
+    // inaccessible DNA gives no evidence of binding
+    let closed = vec![0.0; 30];
+    assert_eq!(footprint::protection_score(&closed, 10, 10, 10), 0.0);
+}
+
+#[test]
+fn test_footprint_landscape() {
+    let seqs = fasta::read_fasta("tests/data/test1.fasta").unwrap();
+    let seq = seqs
+        .column("sequence")
+        .unwrap()
+        .str()
+        .unwrap()
+        .get(0)
+        .unwrap();
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+
+    let flat = vec![1.0; seq.len()];
+    let landscape = footprint::footprint_landscape(seq, &ewms, 9.0, &flat, 10).unwrap();
+    let plain = occupancy::total_landscape(seq, &ewms, 9.0).unwrap();
+    assert_eq!(landscape.shape(), plain.shape());
+
+    let hits = footprint::footprint_hits(seq, &ewms, 9.0, 0.2, &flat, 10).unwrap();
+    let adjusted = hits.column("footprint_occupancy").unwrap().f64().unwrap();
+    assert!(adjusted.into_iter().all(|v| v.unwrap() == 0.0));
+
+    assert!(footprint::footprint_hits(seq, &ewms, 9.0, 0.2, &flat[1..], 10).is_err());
+}
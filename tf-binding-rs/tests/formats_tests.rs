@@ -0,0 +1,189 @@
+use tf_binding_rs::formats::{read_counts, read_motifs, write_counts, write_motifs, MotifFormat};
+use tf_binding_rs::{matrix, occupancy};
+
+#[test]
+fn test_round_trip_all_formats() {
+    let pwms = read_motifs("tests/data/tdmMotifs.meme", MotifFormat::Meme).unwrap();
+
+    for format in MotifFormat::ALL {
+        let path = std::env::temp_dir().join(format!("tf_binding_round_trip.{}", format));
+        let path = path.to_str().unwrap();
+        write_motifs(&pwms, path, format).unwrap();
+        let back = read_motifs(path, format).unwrap();
+
+        assert_eq!(back.len(), pwms.len(), "{}", format);
+        for (id, pwm) in &pwms {
+            let original = matrix::matrix_rows(pwm).unwrap();
+            let converted = matrix::matrix_rows(&back[id]).unwrap();
+            assert_eq!(original.len(), converted.len(), "{} {}", format, id);
+            for (a, b) in original.iter().zip(&converted) {
+                for (x, y) in a.iter().zip(b) {
+                    // count-based formats round to DEFAULT_NSITES sites
+                    assert!((x - y).abs() < 0.01, "{} {}: {} vs {}", format, id, x, y);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_format_detection() {
+    assert_eq!(
+        MotifFormat::from_path("motifs.jaspar"),
+        Some(MotifFormat::Jaspar)
+    );
+    assert_eq!(
+        MotifFormat::from_path("known.motifs"),
+        Some(MotifFormat::Homer)
+    );
+    assert_eq!(MotifFormat::from_path("motifs.csv"), None);
+    assert_eq!(
+        "TRANSFAC".parse::<MotifFormat>().unwrap(),
+        MotifFormat::Transfac
+    );
+    assert!("fasta".parse::<MotifFormat>().is_err());
+}
+
+#[test]
+fn test_matrix_transforms() {
+    let pwms = read_motifs("tests/data/tdmMotifs.meme", MotifFormat::Meme).unwrap();
+    let pwm = &pwms["GFI1_MOUSE.H11MO.0.C"];
+
+    let rc = matrix::reverse_complement_matrix(pwm).unwrap();
+    let rows = matrix::matrix_rows(pwm).unwrap();
+    let rc_rows = matrix::matrix_rows(&rc).unwrap();
+    assert_eq!(rc_rows[0][0], rows[rows.len() - 1][3]);
+    assert!(matrix::reverse_complement_matrix(&rc).unwrap().equals(pwm));
+
+    let ic = matrix::information_content(pwm).unwrap();
+    assert!(ic.iter().all(|&v| (0.0..=2.0 + 1e-9).contains(&v)));
+    let trimmed = matrix::trim_pwm(pwm, 0.5).unwrap();
+    assert!(trimmed.height() <= pwm.height());
+    let trimmed_ic = matrix::information_content(&trimmed).unwrap();
+    assert!(trimmed_ic[0] >= 0.5 && trimmed_ic[trimmed_ic.len() - 1] >= 0.5);
+
+    let smoothed = matrix::add_pseudocount(pwm, 0.1).unwrap();
+    assert!(matrix::matrix_rows(&smoothed)
+        .unwrap()
+        .iter()
+        .all(|row| row.iter().all(|&v| v > 0.0) && (row.iter().sum::<f64>() - 1.0).abs() < 1e-9));
+}
+
+#[test]
+fn test_pwm_to_ewm_matches_meme_reader() {
+    let pwms = read_motifs("tests/data/tdmMotifs.meme", MotifFormat::Meme).unwrap();
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+
+    for (id, pwm) in &pwms {
+        assert!(
+            occupancy::pwm_to_ewm(pwm).unwrap().equals(&ewms[id]),
+            "{}",
+            id
+        );
+    }
+}
+
+#[test]
+fn test_counts_survive_conversion() {
+    let path = std::env::temp_dir().join("tf_binding_counts.jaspar");
+    std::fs::write(
+        &path,
+        ">MA0001.1\tTEST\nA  [ 10  0  3 ]\nC  [  0  2  3 ]\nG  [  0  8  3 ]\nT  [  0  0  1 ]\n",
+    )
+    .unwrap();
+    let pcms = read_counts(path.to_str().unwrap(), MotifFormat::Jaspar).unwrap();
+    let rows = matrix::matrix_rows(&pcms["MA0001.1"]).unwrap();
+    assert_eq!(rows[0], [10.0, 0.0, 0.0, 0.0]);
+    assert_eq!(rows[2], [3.0, 3.0, 3.0, 1.0]);
+
+    for format in [
+        MotifFormat::Transfac,
+        MotifFormat::Hocomoco,
+        MotifFormat::Meme,
+    ] {
+        let out = std::env::temp_dir().join(format!("tf_binding_counts.{}", format));
+        let out = out.to_str().unwrap();
+        write_counts(&pcms, out, format).unwrap();
+        let back = matrix::matrix_rows(&read_counts(out, format).unwrap()["MA0001.1"]).unwrap();
+        for (a, b) in rows.iter().zip(&back) {
+            for (x, y) in a.iter().zip(b) {
+                assert!((x - y).abs() < 1e-4, "{}: {} vs {}", format, x, y);
+            }
+        }
+    }
+
+    // 10 sites: a pseudocount of 1 gives (10 + 1) / 14, sqrt(10) sites weigh in less
+    let pwm = matrix::pcm_to_pwm(&pcms["MA0001.1"], matrix::Prior::Pseudocount(1.0)).unwrap();
+    assert!((matrix::matrix_rows(&pwm).unwrap()[0][0] - 11.0 / 14.0).abs() < 1e-12);
+    let pwm = matrix::pcm_to_pwm(&pcms["MA0001.1"], matrix::Prior::SqrtSites).unwrap();
+    let expected = (10.0 + 10f64.sqrt() / 4.0) / (10.0 + 10f64.sqrt());
+    assert!((matrix::matrix_rows(&pwm).unwrap()[0][0] - expected).abs() < 1e-12);
+    let prior = matrix::Prior::Background {
+        total: 2.0,
+        background: [0.3, 0.2, 0.2, 0.3],
+    };
+    let pwm = matrix::pcm_to_pwm(&pcms["MA0001.1"], prior).unwrap();
+    assert!((matrix::matrix_rows(&pwm).unwrap()[0][3] - 0.6 / 12.0).abs() < 1e-12);
+
+    let pcm = matrix::pwm_to_pcm(&pwm, 50.0).unwrap();
+    assert!((matrix::matrix_rows(&pcm).unwrap()[1].iter().sum::<f64>() - 50.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_ewm_temperature_scales_energies() {
+    let pwms = read_motifs("tests/data/tdmMotifs.meme", MotifFormat::Meme).unwrap();
+    let default = occupancy::EwmOptions::default();
+    let warm = default.with_temperature(37.0);
+    assert!((warm.temperature() - 37.0).abs() < 1e-9);
+
+    for pwm in pwms.values() {
+        let base = occupancy::pwm_to_ewm(pwm).unwrap();
+        assert!(occupancy::pwm_to_ewm_with(pwm, &default)
+            .unwrap()
+            .equals(&base));
+
+        // energies are proportional to RT
+        let scaled = occupancy::pwm_to_ewm_with(pwm, &warm).unwrap();
+        let ratio = warm.rt / occupancy::DEFAULT_RT;
+        for base_column in ["A", "C", "G", "T"] {
+            let expected = base.column(base_column).unwrap().f64().unwrap();
+            let actual = scaled.column(base_column).unwrap().f64().unwrap();
+            for (e, a) in expected.into_no_null_iter().zip(actual.into_no_null_iter()) {
+                assert!((e * ratio - a).abs() < 1e-9);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_read_large_meme() {
+    // a database of many motifs, parsed in parallel, with a repeated ID that keeps the last
+    let mut text = String::from("MEME version 5\n\nALPHABET= ACGT\n\n");
+    for i in 0..500 {
+        let a = (i % 10) as f64 / 10.0;
+        text.push_str(&format!(
+            "MOTIF M{}\n\nletter-probability matrix: alength= 4 w= 2\n {:.1} {:.1} 0.0 0.0\n 0.0 0.0 0.5 0.5\n\n",
+            i, a, 1.0 - a
+        ));
+    }
+    text.push_str("MOTIF M0\n\nletter-probability matrix: alength= 4 w= 1\n 0.0 0.0 0.0 1.0\n");
+    let path = std::env::temp_dir().join("tf_binding_large.meme");
+    std::fs::write(&path, text).unwrap();
+    let path = path.to_str().unwrap();
+
+    let pwms = occupancy::read_pwm_files(path).unwrap();
+    assert_eq!(pwms.len(), 500);
+    assert_eq!(pwms["M0"].height(), 1);
+    assert_eq!(
+        matrix::matrix_rows(&pwms["M13"]).unwrap()[0],
+        [0.3, 0.7, 0.0, 0.0]
+    );
+
+    let ewms = occupancy::read_pwm_to_ewm(path).unwrap();
+    assert_eq!(ewms.len(), 500);
+    for id in ["M0", "M13", "M499"] {
+        let expected = occupancy::pwm_to_ewm(&pwms[id]).unwrap();
+        assert!(ewms[id].equals(&expected), "{}", id);
+    }
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,131 @@
+#![cfg(feature = "liftover")]
+
+use polars::prelude::*;
+use tf_binding_rs::error::MotifError;
+use tf_binding_rs::liftover::{lift_hits, ChainMap, LiftedInterval, DEFAULT_MIN_MATCH};
+
+/// chr1:100-400 maps to chrA:500-800 with a 50 base gap on both sides after 100 bases;
+/// chr2:0-100 maps to the minus strand of chrB at 200-300
+const CHAINS: &str = "\
+chain 1000 chr1 1000 + 100 400 chrA 2000 + 500 800 1
+100 50 50
+150
+
+chain 500 chr2 500 + 0 100 chrB 1000 - 200 300 2
+100
+";
+
+fn write_chains(name: &str, content: &str) -> String {
+    let path = std::env::temp_dir().join(name);
+    std::fs::write(&path, content).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn test_lift() {
+    let chains = ChainMap::read(&write_chains("tf_binding_lift.chain", CHAINS)).unwrap();
+    let lifted = |chrom: &str, start, end, reversed| LiftedInterval {
+        chrom: chrom.to_string(),
+        start,
+        end,
+        reversed,
+    };
+
+    assert_eq!(
+        chains.lift("chr1", 110, 150, DEFAULT_MIN_MATCH),
+        Some(lifted("chrA", 510, 550, false))
+    );
+    // half of the interval falls in the gap
+    assert_eq!(chains.lift("chr1", 190, 210, DEFAULT_MIN_MATCH), None);
+    assert_eq!(
+        chains.lift("chr1", 190, 210, 0.5),
+        Some(lifted("chrA", 590, 600, false))
+    );
+    // the gap is bridged from the first to the last mapped base
+    assert_eq!(
+        chains.lift("chr1", 180, 270, 0.4),
+        Some(lifted("chrA", 580, 670, false))
+    );
+    assert_eq!(
+        chains.lift("chr2", 10, 20, DEFAULT_MIN_MATCH),
+        Some(lifted("chrB", 780, 790, true))
+    );
+    assert_eq!(chains.lift("chr1", 0, 50, DEFAULT_MIN_MATCH), None);
+    assert_eq!(chains.lift("chr3", 10, 20, DEFAULT_MIN_MATCH), None);
+
+    let malformed = write_chains("tf_binding_lift_malformed.chain", "100 50 50\n");
+    assert!(matches!(
+        ChainMap::read(&malformed),
+        Err(MotifError::InvalidFileFormat(_))
+    ));
+}
+
+#[test]
+fn test_lift_hits() {
+    let chains = ChainMap::read(&write_chains("tf_binding_lift_hits.chain", CHAINS)).unwrap();
+    let hits = df!(
+        "chrom" => ["chr1", "chr2", "chr3"],
+        "genomic_start" => [110i64, 10, 1],
+        "genomic_end" => [150i64, 20, 5],
+        "genomic_strand" => ["+", "+", "-"],
+        "motif" => ["GATA1", "GATA1", "KLF1"],
+    )
+    .unwrap();
+
+    let lifted = lift_hits(&hits, &chains, DEFAULT_MIN_MATCH).unwrap();
+    assert_eq!(lifted.height(), 3);
+    let text = |name: &str| -> Vec<Option<String>> {
+        lifted
+            .column(name)
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_iter()
+            .map(|v| v.map(str::to_string))
+            .collect()
+    };
+    assert_eq!(
+        text("chrom"),
+        [Some("chrA".to_string()), Some("chrB".to_string()), None]
+    );
+    assert_eq!(
+        text("genomic_strand"),
+        [Some("+".to_string()), Some("-".to_string()), None]
+    );
+    let starts: Vec<Option<i64>> = lifted
+        .column("genomic_start")
+        .unwrap()
+        .i64()
+        .unwrap()
+        .into_iter()
+        .collect();
+    assert_eq!(starts, [Some(510), Some(780), None]);
+    // unmapped hits keep their other columns
+    assert_eq!(text("motif")[2], Some("KLF1".to_string()));
+
+    // lifted coordinates keep the dtype of the source columns
+    let unsigned = hits
+        .clone()
+        .lazy()
+        .with_columns([
+            col("genomic_start").cast(DataType::UInt32),
+            col("genomic_end").cast(DataType::UInt32),
+        ])
+        .collect()
+        .unwrap();
+    let lifted = lift_hits(&unsigned, &chains, DEFAULT_MIN_MATCH).unwrap();
+    assert_eq!(lifted.schema(), unsigned.schema());
+    let ends: Vec<Option<u32>> = lifted
+        .column("genomic_end")
+        .unwrap()
+        .u32()
+        .unwrap()
+        .into_iter()
+        .collect();
+    assert_eq!(ends, [Some(550), Some(790), None]);
+
+    assert!(matches!(
+        lift_hits(&hits, &chains, 0.0),
+        Err(MotifError::InvalidParameter { .. })
+    ));
+}
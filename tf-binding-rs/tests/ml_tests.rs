@@ -0,0 +1,117 @@
+use polars::prelude::*;
+use tf_binding_rs::error::MotifError;
+use tf_binding_rs::ml::{cross_validate, fit_regression, Objective, RegressionOptions};
+
+/// 40 sequences with two features; "x" drives the target and "z" is a repeating nuisance
+fn features() -> DataFrame {
+    let labels: Vec<String> = (0..40).map(|i| format!("seq{}", i)).collect();
+    let x: Vec<f64> = (0..40).map(|i| i as f64).collect();
+    let z: Vec<f64> = (0..40).map(|i| ((i * 7) % 5) as f64).collect();
+    df!("label" => labels, "x" => x, "z" => z).unwrap()
+}
+
+fn column(df: &DataFrame, name: &str) -> Vec<f64> {
+    df.column(name)
+        .unwrap()
+        .f64()
+        .unwrap()
+        .into_no_null_iter()
+        .collect()
+}
+
+#[test]
+fn test_fit_regression_linear() {
+    let features = features();
+    let target: Vec<f64> = column(&features, "x")
+        .iter()
+        .zip(column(&features, "z"))
+        .map(|(x, z)| 1.0 + 2.0 * x - 0.5 * z)
+        .collect();
+    let options = RegressionOptions {
+        l2: 0.0,
+        ..Default::default()
+    };
+
+    let model = fit_regression(&features, &target, &options).unwrap();
+    assert_eq!(model.features, ["x", "z"]);
+    for (prediction, y) in model.predict(&features).unwrap().iter().zip(&target) {
+        assert!((prediction - y).abs() < 1e-9);
+    }
+    let table = model.coefficient_table().unwrap();
+    let unit = column(&table, "unit_coefficient");
+    assert!((unit[0] - 2.0).abs() < 1e-9);
+    assert!((unit[1] + 0.5).abs() < 1e-9);
+
+    // the penalty shrinks the coefficients towards 0
+    let ridge = fit_regression(&features, &target, &RegressionOptions::default()).unwrap();
+    assert!(ridge.coefficients[0] > 0.0 && ridge.coefficients[0] < model.coefficients[0]);
+}
+
+#[test]
+fn test_fit_regression_logistic() {
+    let features = features();
+    // active above x = 20, with two overlapping sequences on each side
+    let target: Vec<f64> = (0..40)
+        .map(|i| match i {
+            18 | 19 => 1.0,
+            20 | 21 => 0.0,
+            i if i >= 20 => 1.0,
+            _ => 0.0,
+        })
+        .collect();
+    let options = RegressionOptions {
+        objective: Objective::Logistic,
+        ..Default::default()
+    };
+
+    let model = fit_regression(&features, &target, &options).unwrap();
+    assert!(model.coefficients[0] > 0.0);
+    assert!(model.coefficients[0].abs() > model.coefficients[1].abs());
+    let probabilities = model.predict(&features).unwrap();
+    assert!(probabilities.iter().all(|p| (0.0..=1.0).contains(p)));
+    assert!(probabilities[0] < 0.5 && probabilities[39] > 0.5);
+
+    let cv = cross_validate(&features, &target, &options, 5, 42).unwrap();
+    assert_eq!(cv.folds.len(), 5);
+    assert_eq!(cv.predictions.len(), 40);
+    assert!(cv.mean_score().unwrap() > 0.8);
+
+    let labels = [0.0, 1.0, 2.0].repeat(14)[..40].to_vec();
+    assert!(matches!(
+        fit_regression(&features, &labels, &options),
+        Err(MotifError::InvalidInput(_))
+    ));
+}
+
+#[test]
+fn test_cross_validate() {
+    let features = features();
+    let target: Vec<f64> = column(&features, "x").iter().map(|x| 3.0 * x).collect();
+    let options = RegressionOptions {
+        l2: 1e-6,
+        ..Default::default()
+    };
+
+    let cv = cross_validate(&features, &target, &options, 4, 7).unwrap();
+    assert_eq!(cv.folds.iter().map(|f| f.test).sum::<usize>(), 40);
+    for fold in &cv.folds {
+        assert_eq!(fold.train + fold.test, 40);
+        assert!(fold.score.unwrap() > 0.99);
+    }
+    for (prediction, y) in cv.predictions.iter().zip(&target) {
+        assert!((prediction - y).abs() < 1e-3);
+    }
+
+    // the same seed deals the same folds
+    let again = cross_validate(&features, &target, &options, 4, 7).unwrap();
+    assert_eq!(cv.predictions, again.predictions);
+
+    assert!(matches!(
+        cross_validate(&features, &target, &options, 1, 7),
+        Err(MotifError::InvalidParameter { .. })
+    ));
+    assert!(matches!(
+        cross_validate(&features, &target[..10], &options, 4, 7),
+        Err(MotifError::InvalidInput(_))
+    ));
+}
@@ -0,0 +1,80 @@
+use tf_binding_rs::fasta::reverse_complement;
+use tf_binding_rs::model::{find_model_hits, model_landscape, DiPwm, LogOddsPwm, ScoringModel};
+use tf_binding_rs::occupancy::{self, StrandedEwm};
+
+const SEQ: &str = "GGGCTTAGTAACGTTACGGAAGGGCGTGGCCTTTAAATTCCGGGG";
+
+/// Counts the G and C bases of a window, scoring GC-rich windows as stronger sites
+struct GcCount(usize);
+
+impl ScoringModel for GcCount {
+    fn width(&self) -> usize {
+        self.0
+    }
+
+    fn score(&self, window: &[usize]) -> f64 {
+        -(window.iter().filter(|&&b| b == 1 || b == 2).count() as f64)
+    }
+}
+
+#[test]
+fn test_models_match_energy_matrices() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let mut ids: Vec<&String> = ewms.keys().collect();
+    ids.sort();
+    let matrices: Vec<StrandedEwm> = ids
+        .iter()
+        .map(|id| StrandedEwm::new(&ewms[*id]).unwrap())
+        .collect();
+    let models: Vec<(&str, &dyn ScoringModel)> = ids
+        .iter()
+        .zip(&matrices)
+        .map(|(id, matrix)| (id.as_str(), matrix as &dyn ScoringModel))
+        .collect();
+
+    let hits = find_model_hits(SEQ, &models, 9.0, 0.0).unwrap();
+    assert!(hits.equals(&occupancy::find_hits(SEQ, &ewms, 9.0, 0.0).unwrap()));
+    let landscape = model_landscape(SEQ, &models, 9.0).unwrap();
+    assert!(landscape.equals(&occupancy::total_landscape(SEQ, &ewms, 9.0).unwrap()));
+
+    for id in ids {
+        let dipwm = DiPwm::from_ewm(&ewms[id]).unwrap();
+        let (forward, reverse) = dipwm.energy_landscape(SEQ).unwrap();
+        let (expected_f, expected_r) = occupancy::energy_landscape(SEQ, &ewms[id]).unwrap();
+        for (a, b) in forward
+            .iter()
+            .chain(&reverse)
+            .zip(expected_f.iter().chain(&expected_r))
+        {
+            assert!((a - b).abs() < 1e-9, "{} vs {}", a, b);
+        }
+    }
+}
+
+#[test]
+fn test_log_odds_and_custom_models() {
+    let pwms = occupancy::read_pwm_files("tests/data/tdmMotifs.meme").unwrap();
+    let pwm = pwms.values().next().unwrap();
+    let log_odds = LogOddsPwm::new(pwm, [0.25; 4], 0.01).unwrap();
+    assert_eq!(log_odds.width(), pwm.height());
+
+    // the reverse strand of a sequence is the forward strand of its reverse complement
+    let (forward, reverse) = log_odds.energy_landscape(SEQ).unwrap();
+    let (rc_forward, _) = log_odds
+        .energy_landscape(&reverse_complement(SEQ).unwrap())
+        .unwrap();
+    for (a, b) in reverse.iter().zip(rc_forward.iter().rev()) {
+        assert!((a - b).abs() < 1e-9);
+    }
+    assert_eq!(forward.len(), SEQ.len() - pwm.height() + 1);
+    assert!(LogOddsPwm::new(pwm, [0.5, 0.5, 0.0, 0.0], 0.01).is_err());
+
+    let gc = GcCount(4);
+    let hits = find_model_hits(SEQ, &[("gc", &gc as &dyn ScoringModel)], 0.0, 0.97).unwrap();
+    // only windows of four G or C bases pass: 1 / (1 + exp(-3)) < 0.97 < 1 / (1 + exp(-4))
+    assert!(hits.height() > 0);
+    let landscape = model_landscape(SEQ, &[("gc", &gc as &dyn ScoringModel)], 0.0).unwrap();
+    assert_eq!(landscape.shape(), (SEQ.len(), 2));
+    assert!(gc.energy_landscape("GCG").is_err());
+    assert!(DiPwm::new(Vec::new()).is_err());
+}
@@ -0,0 +1,49 @@
+use polars::prelude::*;
+use tf_binding_rs::output;
+
+#[test]
+fn test_write_jsonl() {
+    let df = df!(
+        "motif" => ["NRL", "MAZ"],
+        "position" => [14i32, 3],
+        "strand" => [Some("R"), None],
+        "occupancy" => [0.5f64, 0.25],
+    )
+    .unwrap();
+
+    let mut buffer: Vec<u8> = Vec::new();
+    output::write_jsonl(&df, &mut buffer).unwrap();
+    let text = String::from_utf8(buffer).unwrap();
+
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(
+        lines[0],
+        r#"{"motif":"NRL","position":14,"strand":"R","occupancy":0.5}"#
+    );
+    assert_eq!(
+        lines[1],
+        r#"{"motif":"MAZ","position":3,"strand":null,"occupancy":0.25}"#
+    );
+}
+
+#[test]
+fn test_write_heatmap_svg() {
+    let matrix = df!(
+        "motif" => ["CRX", "NRL<1>"],
+        "CRX" => [Some(1.0), Some(-1.0)],
+        "NRL<1>" => [None, Some(0.0)],
+    )
+    .unwrap();
+    let path = std::env::temp_dir().join("tf_binding_heatmap.svg");
+    output::write_heatmap_svg(&matrix, "motif", path.to_str().unwrap()).unwrap();
+
+    let svg = std::fs::read_to_string(&path).unwrap();
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert_eq!(svg.matches("<rect").count(), 4);
+    for color in ["#b2182b", "#2166ac", "#cccccc", "#ffffff"] {
+        assert!(svg.contains(color), "{}", color);
+    }
+    assert!(svg.contains("NRL&lt;1&gt;"));
+}
@@ -0,0 +1,106 @@
+use polars::prelude::*;
+use std::collections::HashMap;
+use tf_binding_rs::error::MotifError;
+use tf_binding_rs::overlap::{annotate_overlaps, overlap_enrichment, IntervalSet};
+
+fn hits() -> DataFrame {
+    let chr1 = Some("chr1");
+    df!(
+        "motif" => ["GATA1", "GATA1", "GATA1", "GATA1", "GATA1", "KLF1", "KLF1", "KLF1", "KLF1"],
+        "chrom" => [chr1, chr1, chr1, chr1, chr1, chr1, chr1, Some("chr2"), None],
+        "genomic_start" => [
+            Some(100i64), Some(130), Some(150), Some(170), Some(190), Some(500), Some(700),
+            Some(100), None,
+        ],
+        "genomic_end" => [
+            Some(110i64), Some(140), Some(160), Some(180), Some(200), Some(510), Some(710),
+            Some(110), None,
+        ],
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_interval_set() {
+    let path = std::env::temp_dir().join("tf_binding_peaks.bed");
+    std::fs::write(
+        &path,
+        "track name=peaks\nchr1\t100\t150\tpeak1\nchr1\t140\t200\tpeak2\nchr1\t300\t400\n",
+    )
+    .unwrap();
+    let peaks = IntervalSet::read_bed(path.to_str().unwrap()).unwrap();
+    assert_eq!(peaks.covered_bases(), 200);
+    assert!(peaks.overlaps("chr1", 190, 210));
+    assert!(!peaks.overlaps("chr1", 200, 300));
+    assert!(peaks.overlaps("chr1", 399, 400));
+    assert!(!peaks.overlaps("chr2", 100, 200));
+
+    let sizes = HashMap::from([("chr1".to_string(), 1000)]);
+    assert_eq!(IntervalSet::from_chrom_sizes(&sizes).covered_bases(), 1000);
+
+    std::fs::write(&path, "chr1\t100\n").unwrap();
+    assert!(matches!(
+        IntervalSet::read_bed(path.to_str().unwrap()),
+        Err(MotifError::InvalidFileFormat(_))
+    ));
+}
+
+#[test]
+fn test_annotate_overlaps() {
+    let peaks = IntervalSet::new([("chr1", 100, 200)]);
+    let annotated = annotate_overlaps(&hits(), &peaks).unwrap();
+    let overlapping: Vec<Option<bool>> = annotated
+        .column("in_annotation")
+        .unwrap()
+        .bool()
+        .unwrap()
+        .into_iter()
+        .collect();
+    assert_eq!(
+        overlapping,
+        [
+            Some(true),
+            Some(true),
+            Some(true),
+            Some(true),
+            Some(true),
+            Some(false),
+            Some(false),
+            Some(false),
+            None
+        ]
+    );
+}
+
+#[test]
+fn test_overlap_enrichment() {
+    let peaks = IntervalSet::new([("chr1", 100, 200)]);
+    let domain = IntervalSet::new([("chr1", 0, 1000)]);
+    let enrichment = overlap_enrichment(&hits(), &peaks, &domain, 200, 42).unwrap();
+    assert_eq!(enrichment.height(), 2);
+
+    // the KLF1 hits on chr2 and without coordinates are not in the domain
+    let tested = enrichment.column("hits").unwrap().u64().unwrap();
+    assert_eq!(tested.get(0), Some(5));
+    assert_eq!(tested.get(1), Some(2));
+    let fraction = enrichment.column("fraction").unwrap().f64().unwrap();
+    assert_eq!(fraction.get(0), Some(1.0));
+    assert_eq!(fraction.get(1), Some(0.0));
+    // 109 of the 991 start positions of a 10 base hit overlap the peak
+    let expected = enrichment
+        .column("expected_fraction")
+        .unwrap()
+        .f64()
+        .unwrap();
+    assert!((expected.get(0).unwrap() - 109.0 / 991.0).abs() < 0.05);
+    let pvalue = enrichment.column("pvalue").unwrap().f64().unwrap();
+    assert_eq!(pvalue.get(0), Some(1.0 / 201.0));
+    assert_eq!(pvalue.get(1), Some(1.0));
+
+    let again = overlap_enrichment(&hits(), &peaks, &domain, 200, 42).unwrap();
+    assert!(enrichment.equals_missing(&again));
+    assert!(matches!(
+        overlap_enrichment(&hits(), &peaks, &domain, 0, 42),
+        Err(MotifError::InvalidParameter { .. })
+    ));
+}
@@ -0,0 +1,14 @@
+use tf_binding_rs::prelude::*;
+
+#[test]
+fn test_prelude_covers_a_scan() {
+    let ewms: EWMCollection = read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let seq = "GGGCTTAGTAACGTTACGGAAGGGCGTGGCCTTTAAATTCCGGGG";
+
+    let bank = MotifBank::new(&ewms).unwrap();
+    let landscape = total_landscape(seq, &ewms, 9.0).unwrap();
+    assert!(landscape.equals(&bank.total_landscape(seq, 9.0).unwrap()));
+
+    let result: Result<_> = find_hits("ACG", &ewms, 9.0, 0.2);
+    assert!(result.is_err());
+}
@@ -0,0 +1,31 @@
+use tf_binding_rs::provenance::{file_sha256, manifest_path, RunManifest};
+
+#[test]
+fn test_run_manifest_round_trip() {
+    let mut manifest = RunManifest::new("motif-scanner", "0.1.0");
+    manifest
+        .parameter("mu", 9)
+        .parameter("cutoff", 0.2)
+        .add_input("tests/data/tdmMotifs.meme")
+        .unwrap();
+    manifest
+        .add_output("hits.parquet")
+        .count("hits", 42)
+        .finish(std::time::Duration::from_millis(1500));
+
+    assert_eq!(manifest.inputs[0].sha256.len(), 64);
+    assert_eq!(
+        manifest.inputs[0].sha256,
+        file_sha256("tests/data/tdmMotifs.meme").unwrap()
+    );
+    assert_eq!(manifest.parameters["mu"], "9");
+    assert!(manifest
+        .to_key_values()
+        .contains(&("param.cutoff".to_string(), "0.2".to_string())));
+
+    let path = std::env::temp_dir().join("tf_binding_hits.parquet");
+    let path = manifest_path(path.to_str().unwrap());
+    manifest.write(&path).unwrap();
+    assert_eq!(RunManifest::read(&path).unwrap(), manifest);
+    std::fs::remove_file(&path).unwrap();
+}
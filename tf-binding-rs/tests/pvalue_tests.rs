@@ -0,0 +1,86 @@
+use tf_binding_rs::{fasta, matrix, occupancy, pvalue};
+
+#[test]
+fn test_pvalue_matches_enumeration() {
+    let pwm = matrix::pwm_from_rows(&[
+        [0.7, 0.1, 0.1, 0.1],
+        [0.1, 0.1, 0.7, 0.1],
+        [0.25, 0.25, 0.25, 0.25],
+        [0.05, 0.05, 0.05, 0.85],
+        [0.4, 0.4, 0.1, 0.1],
+    ])
+    .unwrap();
+    let ewm = occupancy::pwm_to_ewm(&pwm).unwrap();
+    let rows = matrix::matrix_rows(&ewm).unwrap();
+    let background = [0.3, 0.2, 0.2, 0.3];
+    let dist = pvalue::ScoreDistribution::new(&ewm, background, pvalue::DEFAULT_STEP).unwrap();
+
+    // energy and probability of every 5-mer
+    let kmers: Vec<(f64, f64)> = (0..4usize.pow(5))
+        .map(|code| {
+            (0..5).fold((0.0, 1.0), |(e, p), i| {
+                let base = (code >> (2 * i)) & 3;
+                (e + rows[i][base], p * background[base])
+            })
+        })
+        .collect();
+    let tolerance = 5.0 * pvalue::DEFAULT_STEP / 2.0;
+    for &(energy, _) in &kmers {
+        let at_most = |e: f64| kmers.iter().filter(|k| k.0 <= e).map(|k| k.1).sum::<f64>();
+        let p = dist.pvalue(energy);
+        assert!(p >= at_most(energy) - 1e-12, "energy {}", energy);
+        assert!(
+            p <= at_most(energy + 2.0 * tolerance) + 1e-12,
+            "energy {}",
+            energy
+        );
+    }
+    assert!((dist.pvalue(f64::INFINITY) - 1.0).abs() < 1e-12);
+    assert_eq!(dist.pvalue(-1.0), 0.0);
+    assert!(pvalue::ScoreDistribution::new(&ewm, [0.5; 4], 0.01).is_err());
+}
+
+#[test]
+fn test_add_pvalues_to_hits() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let sequences = fasta::read_fasta("tests/data/test1.fasta").unwrap();
+    let seq = sequences
+        .column("sequence")
+        .unwrap()
+        .str()
+        .unwrap()
+        .get(0)
+        .unwrap();
+    let distributions =
+        pvalue::score_distributions(&ewms, pvalue::UNIFORM_BACKGROUND, pvalue::DEFAULT_STEP)
+            .unwrap();
+
+    let hits = occupancy::find_hits(seq, &ewms, 9.0, 1e-3).unwrap();
+    let hits = pvalue::add_pvalues(&hits, &distributions, 9.0).unwrap();
+    let occupancies = hits.column("occupancy").unwrap().f64().unwrap();
+    let pvalues = hits.column("pvalue").unwrap().f64().unwrap();
+    let scores = hits.column("neg_log10_pvalue").unwrap().f64().unwrap();
+    let rates = hits.column("background_per_kb").unwrap().f64().unwrap();
+    let motifs = hits.column("motif").unwrap().str().unwrap();
+    assert!(hits.height() > 1);
+
+    for i in 0..hits.height() {
+        let p = pvalues.get(i).unwrap();
+        assert!(p > 0.0 && p <= 1.0);
+        assert!((scores.get(i).unwrap() + p.log10()).abs() < 1e-12);
+        // two strands of 1000 windows per kb
+        assert!((rates.get(i).unwrap() - 2000.0 * p).abs() < 1e-9);
+        let dist = &distributions[motifs.get(i).unwrap()];
+        let occ = occupancies.get(i).unwrap();
+        assert_eq!(dist.occupancy_rate_per_kb(occ, 9.0), rates.get(i).unwrap());
+    }
+
+    // within a motif, stronger hits are rarer
+    for i in 0..hits.height() {
+        for j in 0..hits.height() {
+            if motifs.get(i) == motifs.get(j) && occupancies.get(i) > occupancies.get(j) {
+                assert!(pvalues.get(i) <= pvalues.get(j));
+            }
+        }
+    }
+}
@@ -0,0 +1,522 @@
+use polars::prelude::*;
+use tf_binding_rs::{fasta, occupancy, quantized, scan, workload};
+
+#[test]
+fn test_scan_sequences_warnings() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let df = df!(
+        "label" => ["ok", "ambiguous", "short"],
+        "sequence" => [
+            "GGGCTTAGTAACGTTACGGAAGGGCGTGGCCTTTAAATTCCGGGG",
+            "ACGTNACGTACGTACGTACG",
+            "ACGT",
+        ],
+    )
+    .unwrap();
+
+    let mut seen = 0;
+    let result = scan::scan_sequences(&df, &ewms, 9.0, 0.0, |_| seen += 1).unwrap();
+    assert_eq!(seen, result.warnings.len());
+
+    // the ambiguous sequence is skipped once, the short one once per motif
+    assert_eq!(result.warnings.len(), 1 + ewms.len());
+    assert_eq!(
+        result.warnings[0].reason,
+        scan::SkipReason::AmbiguousBases {
+            position: 4,
+            base: 'N'
+        }
+    );
+    assert_eq!(result.warnings[0].motif, None);
+    assert!(result.warnings[1..].iter().all(|w| w.index == 2
+        && w.label.as_deref() == Some("short")
+        && w.motif.is_some()
+        && w.reason.kind() == "too_short"));
+
+    // only the valid sequence has hits
+    let labels = result.hits.column("label").unwrap().str().unwrap();
+    assert!(result.hits.height() > 0);
+    assert!(labels.into_iter().all(|l| l == Some("ok")));
+
+    let warnings = scan::warnings_frame(&result.warnings).unwrap();
+    assert_eq!(warnings.height(), result.warnings.len());
+    assert_eq!(
+        warnings.get_column_names_str(),
+        ["index", "label", "motif", "reason", "message"]
+    );
+}
+
+#[test]
+fn test_empty_scan_keeps_schema() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let sequences = fasta::read_fasta("tests/data/test1.fasta").unwrap();
+    let hits = scan::scan_sequences(&sequences, &ewms, 9.0, 0.2, |_| {})
+        .unwrap()
+        .hits;
+    assert!(hits.height() > 0);
+
+    // nothing passes the cutoff, and nothing to scan at all
+    let none = scan::scan_sequences(&sequences, &ewms, 9.0, 1.0, |_| {})
+        .unwrap()
+        .hits;
+    let empty = scan::scan_sequences(&sequences.clear(), &ewms, 9.0, 0.2, |_| {})
+        .unwrap()
+        .hits;
+    for frame in [none, empty] {
+        assert_eq!(frame.height(), 0);
+        assert_eq!(frame.schema(), hits.schema());
+    }
+}
+
+#[test]
+fn test_energy_landscape_short_sequence() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let ewm = ewms.values().next().unwrap();
+    assert!(occupancy::energy_landscape("ACG", ewm).is_err());
+    assert!(scan::check_sequence("ACG", ewm.height()).is_some());
+}
+
+#[test]
+fn test_reverse_strand_matches_reverse_complement_scan() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let seq = "GGGCTTAGTAACGTTACGGAAGGGCGTGGCCTTTAAATTCCGGGG";
+    let rc = fasta::reverse_complement(seq).unwrap();
+
+    for ewm in ewms.values() {
+        let matrix = occupancy::StrandedEwm::new(ewm).unwrap();
+        let (_, mut reverse) = matrix.energy_landscape(seq).unwrap();
+        let (forward_of_rc, _) = occupancy::energy_landscape(&rc, ewm).unwrap();
+        reverse.reverse();
+        assert_eq!(reverse, forward_of_rc);
+    }
+}
+
+#[test]
+fn test_window_energy_matches_landscape() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let seq = "GGGCTTAGTAACGTTACGGAAGGGCGTGGCCTTTAAATTCCGGGG";
+    for ewm in ewms.values() {
+        let matrix = occupancy::StrandedEwm::new(ewm).unwrap();
+        let (forward, reverse) = matrix.energy_landscape(seq).unwrap();
+        let (lowest, highest) = matrix.energy_range();
+        for (position, (&f, &r)) in forward.iter().zip(&reverse).enumerate() {
+            assert_eq!(matrix.window_energy(seq, position, false).unwrap(), f);
+            assert_eq!(matrix.window_energy(seq, position, true).unwrap(), r);
+            assert!(lowest <= f && f <= highest);
+        }
+        assert!(matrix.window_energy(seq, forward.len(), false).is_err());
+    }
+}
+
+#[test]
+fn test_encoded_landscapes_match_string_scans() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let seq = "GGGCTTAGTAACGTTACGGAAGGGCGTGGCCTTTAAATTCCGGGG";
+    let mut encoded = occupancy::EncodedSeq::new(seq).unwrap();
+    assert_eq!(encoded.len(), seq.len());
+
+    for ewm in ewms.values() {
+        let matrix = occupancy::StrandedEwm::new(ewm).unwrap();
+        let energies = matrix.energy_landscape_encoded(&encoded).unwrap();
+        assert_eq!(energies, matrix.energy_landscape(seq).unwrap());
+        for mu in [5.0, 9.0, 13.0] {
+            assert_eq!(
+                occupancy::occupancies(&energies, mu),
+                matrix.occupancy_landscape(seq, mu).unwrap()
+            );
+        }
+    }
+    let bank = occupancy::MotifBank::new(&ewms).unwrap();
+    assert_eq!(
+        bank.energy_landscapes_encoded(&encoded).unwrap(),
+        bank.energy_landscapes(seq).unwrap()
+    );
+
+    // a substitution rescoring without re-encoding
+    encoded.substitute(0, b'T').unwrap();
+    let mutant = format!("T{}", &seq[1..]);
+    assert_eq!(
+        bank.energy_landscapes_encoded(&encoded).unwrap(),
+        bank.energy_landscapes(&mutant).unwrap()
+    );
+    assert!(encoded.substitute(seq.len(), b'A').is_err());
+    assert!(encoded.substitute(0, b'N').is_err());
+    assert!(occupancy::EncodedSeq::new("ACGN").is_err());
+    assert!(bank
+        .energy_landscapes_encoded(&occupancy::EncodedSeq::new("ACG").unwrap())
+        .is_err());
+}
+
+#[test]
+fn test_motif_bank_matches_single_motif_scans() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let seq = "GGGCTTAGTAACGTTACGGAAGGGCGTGGCCTTTAAATTCCGGGG";
+    let bank = occupancy::MotifBank::new(&ewms).unwrap();
+
+    let mut ids: Vec<&String> = ewms.keys().collect();
+    ids.sort();
+    assert_eq!(bank.ids().iter().collect::<Vec<_>>(), ids);
+
+    let landscapes = bank.occupancy_landscapes(seq, 9.0).unwrap();
+    for (id, landscape) in ids.iter().zip(&landscapes) {
+        let single = occupancy::occupancy_landscape(seq, &ewms[*id], 9.0).unwrap();
+        assert_eq!(*landscape, single, "{}", id);
+    }
+
+    let total = occupancy::total_landscape(seq, &ewms, 9.0).unwrap();
+    assert_eq!(total.shape(), (seq.len(), 2 * ewms.len()));
+    assert!(bank.energy_landscapes("ACG").is_err());
+}
+
+#[test]
+fn test_total_energy_landscape_matches_single_motif_scans() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let seq = "GGGCTTAGTAACGTTACGGAAGGGCGTGGCCTTTAAATTCCGGGG";
+
+    let wide = occupancy::total_energy_landscape(seq, &ewms).unwrap();
+    let long = occupancy::energy_landscape_table(seq, &ewms).unwrap();
+    assert_eq!(wide.shape(), (seq.len(), 2 * ewms.len()));
+
+    let mut rows = 0;
+    for (id, ewm) in &ewms {
+        let (forward, _) = occupancy::energy_landscape(seq, ewm).unwrap();
+        let column: Vec<Option<f64>> = wide
+            .column(&format!("{}_F", id))
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(
+            column[..forward.len()],
+            forward.iter().map(|&e| Some(e)).collect::<Vec<_>>()[..]
+        );
+        assert!(column[forward.len()..].iter().all(Option::is_none));
+        rows += 2 * forward.len();
+    }
+    assert_eq!(long.height(), rows);
+    assert_eq!(
+        long.get_column_names(),
+        ["motif", "position", "strand", "energy"]
+    );
+}
+
+#[test]
+fn test_quantized_hits_match_exact_hits() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let seq = "GGGCTTAGTAACGTTACGGAAGGGCGTGGCCTTTAAATTCCGGGGTTACGTAAGGCGCGTTAAACCGT";
+
+    for cutoff in [0.0, 0.01, 0.2, 0.5, 0.9, 1.0] {
+        let exact = occupancy::find_hits(seq, &ewms, 9.0, cutoff).unwrap();
+        let fast = quantized::find_hits_quantized(seq, &ewms, 9.0, cutoff).unwrap();
+        assert!(exact.equals(&fast), "cutoff {}", cutoff);
+    }
+
+    for (id, ewm) in &ewms {
+        let matrix = quantized::QuantizedEwm::new(ewm).unwrap();
+        assert!(matrix.error_bound() < 0.01, "{}", id);
+    }
+}
+
+#[test]
+fn test_workload_is_reproducible() {
+    let workload = workload::Workload {
+        sequences: 3,
+        length: 50,
+        motifs: 2,
+        ..workload::Workload::default()
+    };
+    let sequences = workload.sequences();
+    assert_eq!(sequences, workload.sequences());
+    assert!(sequences.iter().all(|s| s.len() == 50));
+
+    let ewms = workload.ewms().unwrap();
+    let again = workload.ewms().unwrap();
+    assert_eq!(ewms.len(), 2);
+    for (id, ewm) in &ewms {
+        assert_eq!(ewm.height(), 12);
+        assert!(ewm.equals(&again[id]));
+    }
+    assert_eq!(workload.work(), 3 * 50 * 2);
+}
+
+#[test]
+fn test_scan_with_matches_find_hits() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let seq = "GGGCTTAGTAACGTTACGGAAGGGCGTGGCCTTTAAATTCCGGGG";
+
+    let mut streamed = Vec::new();
+    occupancy::scan_with(seq, &ewms, 9.0, 1e-6, |motif, pos, strand, occ| {
+        streamed.push((motif.to_string(), pos as i32, strand.to_string(), occ));
+    })
+    .unwrap();
+    streamed.sort_by(|a, b| (&a.0, &a.2, a.1).cmp(&(&b.0, &b.2, b.1)));
+
+    let hits = occupancy::find_hits(seq, &ewms, 9.0, 1e-6).unwrap();
+    let motifs = hits.column("motif").unwrap().str().unwrap().clone();
+    let positions = hits.column("position").unwrap().i32().unwrap().clone();
+    let strands = hits.column("strand").unwrap().str().unwrap().clone();
+    let occupancies = hits.column("occupancy").unwrap().f64().unwrap().clone();
+    let expected: Vec<_> = (0..hits.height())
+        .map(|i| {
+            (
+                motifs.get(i).unwrap().to_string(),
+                positions.get(i).unwrap(),
+                strands.get(i).unwrap().to_string(),
+                occupancies.get(i).unwrap(),
+            )
+        })
+        .collect();
+    assert!(!expected.is_empty());
+    assert_eq!(streamed, expected);
+
+    let mut called = false;
+    assert!(
+        occupancy::scan_with("ACGTNACGTACGTACGTACG", &ewms, 9.0, 0.0, |_, _, _, _| {
+            called = true
+        })
+        .is_err()
+    );
+    assert!(!called);
+}
+
+#[test]
+fn test_lenient_energy_landscape() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let seq = "GGGCTTAGTAACGTTACGGAAGGGCGTGGCCTTTAAATTCCGGGG";
+    for ewm in ewms.values() {
+        let matrix = occupancy::StrandedEwm::new(ewm).unwrap();
+        assert_eq!(
+            matrix.lenient_energy_landscape(seq).unwrap(),
+            matrix.energy_landscape(seq).unwrap()
+        );
+
+        // N scores as the mean of the four bases at its position
+        let masked = format!("N{}", &seq[1..]);
+        let (forward, _) = matrix.lenient_energy_landscape(&masked).unwrap();
+        let mean: f64 = ["A", "C", "G", "T"]
+            .iter()
+            .map(|base| {
+                let variant = format!("{}{}", base, &seq[1..]);
+                matrix.energy_landscape(&variant).unwrap().0[0]
+            })
+            .sum::<f64>()
+            / 4.0;
+        assert!((forward[0] - mean).abs() < 1e-9);
+        assert!(matrix
+            .lenient_energy_landscape("X".repeat(50).as_str())
+            .is_err());
+    }
+}
+
+#[test]
+fn test_ambiguous_policy_and_segments() {
+    use scan::AmbiguousPolicy;
+
+    assert_eq!(
+        "drop".parse::<AmbiguousPolicy>().unwrap(),
+        AmbiguousPolicy::Drop
+    );
+    assert_eq!(
+        "scan_lenient".parse::<AmbiguousPolicy>().unwrap(),
+        AmbiguousPolicy::ScanLenient
+    );
+    assert_eq!(AmbiguousPolicy::Mask.to_string(), "mask");
+    assert!("skip".parse::<AmbiguousPolicy>().is_err());
+
+    assert_eq!(
+        scan::unambiguous_segments("ACGTNNACGTACGYAC", 3),
+        vec![(0, 4), (6, 13)]
+    );
+    assert_eq!(scan::unambiguous_segments("NNNN", 1), vec![]);
+    assert_eq!(scan::unambiguous_segments("ACGT", 5), vec![]);
+}
+
+#[test]
+fn test_rescore_mutation_matches_full_rescoring() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let seq = "GGGCTTAGTAACGTTACGGAAGGGCGTGGCCTTTAAATTCCGGGG";
+    let encoded = occupancy::EncodedSeq::new(seq).unwrap();
+    for ewm in ewms.values() {
+        let matrix = occupancy::StrandedEwm::new(ewm).unwrap();
+        let reference = matrix.energy_landscape_encoded(&encoded).unwrap();
+        for position in [0, 7, seq.len() - 1] {
+            let update = matrix
+                .rescore_mutation(&encoded, &reference, position, b'A')
+                .unwrap();
+            assert!(update.start <= position && update.forward.len() <= matrix.len());
+            let mut mutant = reference.clone();
+            update.apply(&mut mutant);
+
+            let mut expected = encoded.clone();
+            expected.substitute(position, b'A').unwrap();
+            let expected = matrix.energy_landscape_encoded(&expected).unwrap();
+            for (a, b) in mutant
+                .0
+                .iter()
+                .chain(&mutant.1)
+                .zip(expected.0.iter().chain(&expected.1))
+            {
+                assert!((a - b).abs() < 1e-9);
+            }
+        }
+        assert!(matrix
+            .rescore_mutation(&encoded, &reference, seq.len(), b'A')
+            .is_err());
+        let truncated = (reference.0[1..].to_vec(), reference.1[1..].to_vec());
+        assert!(matrix
+            .rescore_mutation(&encoded, &truncated, 0, b'A')
+            .is_err());
+    }
+}
+
+#[test]
+fn test_f32_landscapes_match_f64() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let seq = "GGGCTTAGTAACGTTACGGAAGGGCGTGGCCTTTAAATTCCGGGG";
+    let bank = occupancy::MotifBank::new(&ewms).unwrap();
+
+    // values are computed in f64 and rounded once, so they agree to half an f32 ulp, or
+    // to the smallest normal f32 for occupancies that underflow
+    let close = |a: f64, b: f32| {
+        (a - b as f64).abs() <= a.abs() * f32::EPSILON as f64 / 2.0 + f32::MIN_POSITIVE as f64
+    };
+    let single = bank.energy_landscapes_f32(seq).unwrap();
+    for ((f64s, r64s), (f32s, r32s)) in bank.energy_landscapes(seq).unwrap().iter().zip(&single) {
+        assert_eq!(f64s.len(), f32s.len());
+        for (a, b) in f64s.iter().chain(r64s).zip(f32s.iter().chain(r32s)) {
+            assert!(close(*a, *b), "{} vs {}", a, b);
+        }
+    }
+    let occupancies = bank.occupancy_landscapes_f32(seq, 9.0).unwrap();
+    for ((f64s, r64s), (f32s, r32s)) in bank
+        .occupancy_landscapes(seq, 9.0)
+        .unwrap()
+        .iter()
+        .zip(&occupancies)
+    {
+        for (a, b) in f64s.iter().chain(r64s).zip(f32s.iter().chain(r32s)) {
+            assert!(close(*a, *b), "{} vs {}", a, b);
+        }
+    }
+
+    let table = bank
+        .total_landscape_with(seq, 9.0, occupancy::Precision::F32)
+        .unwrap();
+    assert_eq!(table.shape(), (seq.len(), 2 * ewms.len()));
+    assert!(table
+        .dtypes()
+        .iter()
+        .all(|dtype| dtype == &DataType::Float32));
+    let reference = bank.total_landscape(seq, 9.0).unwrap();
+    for (double, single) in reference.get_columns().iter().zip(table.get_columns()) {
+        assert_eq!(double.name(), single.name());
+        for (a, b) in double
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .zip(single.f32().unwrap().into_no_null_iter())
+        {
+            assert!(close(a, b), "{}: {} vs {}", double.name(), a, b);
+        }
+    }
+    assert_eq!(
+        "f32".parse::<occupancy::Precision>().unwrap(),
+        occupancy::Precision::F32
+    );
+    assert!("f16".parse::<occupancy::Precision>().is_err());
+}
+
+#[test]
+fn test_total_landscape_into_reuses_buffer() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let bank = occupancy::MotifBank::new(&ewms).unwrap();
+    let mut buffer = occupancy::LandscapeBuffer::default();
+
+    // a longer sequence first, so stale scores would show up in the shorter one
+    for seq in [
+        "GGGCTTAGTAACGTTACGGAAGGGCGTGGCCTTTAAATTCCGGGGTTAACGTA",
+        "GGGCTTAGTAACGTTACGGAAGGGCG",
+    ] {
+        let table = bank.total_landscape_into(seq, 9.0, &mut buffer).unwrap();
+        assert_eq!(table.shape(), (seq.len(), 2 * ewms.len()));
+        for (id, ewm) in &ewms {
+            let (focc, rocc) = occupancy::occupancy_landscape(seq, ewm, 9.0).unwrap();
+            for (strand, expected) in [("F", focc), ("R", rocc)] {
+                let column = table.column(&format!("{}_{}", id, strand)).unwrap();
+                let values: Vec<f64> = column.f64().unwrap().into_no_null_iter().collect();
+                for (a, b) in values.iter().zip(&expected) {
+                    assert!((a - b).abs() < 1e-12);
+                }
+                assert!(values[expected.len()..].iter().all(|&v| v == 0.0));
+            }
+        }
+    }
+    assert!(bank.total_landscape_into("ACGT", 9.0, &mut buffer).is_err());
+}
+
+#[test]
+fn test_total_landscape_isolated_skips_failing_motifs() {
+    let mut ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    ewms.insert(
+        "broken".to_string(),
+        df!("A" => [0.0], "C" => [1.0], "G" => [1.0]).unwrap(),
+    );
+
+    // 10 bp fits only the 10-bp motif
+    let seq = "GGGCTTAGTA";
+    assert!(occupancy::total_landscape(seq, &ewms, 9.0).is_err());
+    let (landscape, diagnostics) = occupancy::total_landscape_isolated(seq, &ewms, 9.0).unwrap();
+    assert_eq!(diagnostics.failures.len(), ewms.len() - 1);
+    assert!(diagnostics.failed_motifs().contains(&"broken"));
+    assert_eq!(landscape.shape(), (seq.len(), 2));
+
+    let long = "GGGCTTAGTAACGTTACGGAAGGGCGTGGCCTTTAAATTCCGGGG";
+    let (landscape, diagnostics) = occupancy::total_landscape_isolated(long, &ewms, 9.0).unwrap();
+    assert_eq!(diagnostics.failed_motifs(), vec!["broken"]);
+    ewms.remove("broken");
+    assert!(landscape.equals(&occupancy::total_landscape(long, &ewms, 9.0).unwrap()));
+    assert!(occupancy::total_landscape_isolated("GGGCNTAGTAAC", &ewms, 9.0).is_err());
+}
+
+#[test]
+fn test_errors_carry_motif_and_sequence_context() {
+    use tf_binding_rs::error::{MotifError, ResultExt};
+
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let error = occupancy::find_hits("GGGCTTAGTA", &ewms, 9.0, 0.2).unwrap_err();
+    let motif = error.motif().expect("motif context").to_string();
+    assert!(ewms.contains_key(&motif));
+    assert!(error.to_string().contains(&format!("motif {}", motif)));
+    assert!(matches!(error.root(), MotifError::InvalidSequence { .. }));
+
+    let error = Err::<(), _>(MotifError::DataError("bad value".into()))
+        .with_motif("CRX")
+        .with_label("tile_7")
+        .with_position(12)
+        .with_motif("NRL")
+        .unwrap_err();
+    assert_eq!(error.motif(), Some("CRX"));
+    assert_eq!(error.label(), Some("tile_7"));
+    assert_eq!(error.position(), Some(12));
+    assert_eq!(
+        error.to_string(),
+        "motif CRX, sequence tile_7, position 12: Data error: bad value"
+    );
+}
+
+#[test]
+fn test_error_kinds() {
+    use tf_binding_rs::error::{ErrorKind, Result};
+
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let short: Result<DataFrame> = occupancy::find_hits("GGGCTTAGTA", &ewms, 9.0, 0.2);
+    let error = short.unwrap_err();
+    // the motif context does not hide the class of the error
+    assert_eq!(error.kind(), ErrorKind::InvalidSequence);
+    assert!(error.is(ErrorKind::InvalidSequence));
+
+    let missing = occupancy::read_pwm_to_ewm("tests/data/missing.meme").unwrap_err();
+    assert_eq!(missing.kind(), ErrorKind::Io);
+    assert_eq!(ErrorKind::InvalidParameter.to_string(), "invalid_parameter");
+}
@@ -0,0 +1,83 @@
+use polars::prelude::*;
+use tf_binding_rs::occupancy;
+use tf_binding_rs::scan::SkipReason;
+use tf_binding_rs::scanner::{Scanner, Strand};
+
+const SEQ: &str = "GGGCTTAGTAACGTTACGGAAGGGCGTGGCCTTTAAATTCCGGGG";
+
+#[test]
+fn test_scanner_matches_free_functions() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let scanner = Scanner::builder()
+        .motifs(ewms.clone())
+        .mu(9.0)
+        .cutoff(0.0)
+        .threads(2)
+        .build()
+        .unwrap();
+
+    let hits = scanner.scan(SEQ).unwrap();
+    assert!(hits.equals(&occupancy::find_hits(SEQ, &ewms, 9.0, 0.0).unwrap()));
+    let landscape = scanner.landscape(SEQ).unwrap();
+    assert!(landscape.equals(&occupancy::total_landscape(SEQ, &ewms, 9.0).unwrap()));
+
+    let df = df!(
+        "label" => ["ok", "ambiguous", "short"],
+        "sequence" => [SEQ, "ACGTNACGTACGTACGTACG", "ACGT"],
+    )
+    .unwrap();
+    let result = scanner.scan_batch(&df).unwrap();
+    // the short sequence fits no motif, so every hit is on the first sequence
+    assert_eq!(result.hits.height(), hits.height());
+    assert!(result
+        .hits
+        .column("label")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_no_null_iter()
+        .all(|label| label == "ok"));
+    assert!(matches!(
+        result.warnings[0].reason,
+        SkipReason::AmbiguousBases { position: 4, .. }
+    ));
+    assert!(result.warnings[1..]
+        .iter()
+        .all(|w| w.index == 2 && matches!(w.reason, SkipReason::TooShort { .. })));
+}
+
+#[test]
+fn test_scanner_strand_and_validation() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let scanner = Scanner::builder()
+        .motifs(ewms.clone())
+        .cutoff(0.0)
+        .strand("forward".parse().unwrap())
+        .build()
+        .unwrap();
+    assert_eq!(scanner.strand(), Strand::Forward);
+
+    let hits = scanner.scan(SEQ).unwrap();
+    assert!(hits
+        .column("strand")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_no_null_iter()
+        .all(|s| s == "F"));
+    let landscape = scanner.landscape(SEQ).unwrap();
+    assert_eq!(landscape.width(), ewms.len());
+    assert!(landscape
+        .get_column_names()
+        .iter()
+        .all(|name| name.ends_with("_F")));
+
+    assert!(Scanner::builder().build().is_err());
+    assert!(Scanner::builder()
+        .motifs(ewms.clone())
+        .cutoff(1.5)
+        .build()
+        .is_err());
+    assert!(Scanner::builder().motifs(ewms).threads(0).build().is_err());
+    assert!("sideways".parse::<Strand>().is_err());
+}
@@ -0,0 +1,65 @@
+use polars::prelude::*;
+use tf_binding_rs::occupancy::{self, StrandedEwm};
+use tf_binding_rs::schema::{self, MigrationSources, SCHEMA_VERSION};
+use tf_binding_rs::{coords, fasta};
+
+#[test]
+fn test_migrate_version_1_hits() {
+    let sequences = fasta::read_fasta("tests/data/test1.fasta").unwrap();
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let seq = sequences
+        .column("sequence")
+        .unwrap()
+        .str()
+        .unwrap()
+        .get(0)
+        .unwrap();
+    let matrix = StrandedEwm::new(&ewms["NRL_HUMAN.MA0842.1"]).unwrap();
+    let energy = matrix.window_energy(seq, 14, true).unwrap();
+    let occ = 1.0 / (1.0 + (energy - 9.0f64).exp());
+
+    let hits = df!(
+        "label" => [0i32],
+        "position" => [14i32],
+        "motif" => ["NRL"],
+        "strand" => ["R"],
+        "length" => [11i32],
+        "occupancy" => [occ],
+    )
+    .unwrap();
+    assert_eq!(schema::detect_version(&hits), 1);
+
+    // without sources, the new columns are typed nulls
+    let bare = schema::migrate(&hits, &MigrationSources::default()).unwrap();
+    assert!(schema::missing_columns(&bare).is_empty());
+    assert_eq!(schema::detect_version(&bare), SCHEMA_VERSION);
+    assert_eq!(bare.column("energy").unwrap().null_count(), 1);
+    assert_eq!(
+        bare.column("genomic_start").unwrap().dtype(),
+        &DataType::Int64
+    );
+
+    let parser = coords::LabelParser::default();
+    let sources = MigrationSources {
+        mu: Some(9.0),
+        ewms: Some(&ewms),
+        sequences: Some(&sequences),
+        label_parser: Some(&parser),
+    };
+    let migrated = schema::migrate(&hits, &sources).unwrap();
+    let value = |name: &str| migrated.column(name).unwrap().get(0).unwrap();
+    let AnyValue::Float64(recovered) = value("energy") else {
+        panic!("energy is not a float");
+    };
+    assert!((recovered - energy).abs() < 1e-9);
+    assert!(matches!(value("pvalue"), AnyValue::Float64(p) if p > 0.0 && p < 1.0));
+    assert_eq!(value("chrom"), AnyValue::String("chr1"));
+
+    let window = &seq[14..25];
+    let expected = fasta::reverse_complement(window).unwrap();
+    assert_eq!(value("matched_seq"), AnyValue::String(&expected));
+
+    // current tables are left as they are
+    let again = schema::migrate(&migrated, &MigrationSources::default()).unwrap();
+    assert!(again.equals_missing(&migrated));
+}
@@ -0,0 +1,129 @@
+use polars::prelude::*;
+use std::collections::HashMap;
+use tf_binding_rs::coords::LabelParser;
+use tf_binding_rs::error::MotifError;
+use tf_binding_rs::ml::{cross_validate_folds, RegressionOptions};
+use tf_binding_rs::split::{group_folds, split_keys, stratified_folds, train_test_split, SplitKey};
+
+fn sequences() -> DataFrame {
+    df!(
+        "label" => [
+            "chr1-100-120_wt", "chr1-100-120_mut1", "chr1-400-420_wt", "chr2-100-120_wt",
+            "chr2-300-320_mut2", "chr3-50-70_wt",
+        ],
+        "sequence" => ["GGGGCCCCGG", "GGGCCCAAGG", "AAAATTTTAA", "ATATATATGC", "GCGCATATAT", "CCCCCCCCCC"],
+        "source_label" => ["a", "a", "b", "b", "c", "c"],
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_split_keys() {
+    let df = sequences();
+    assert_eq!(
+        split_keys(&df, &SplitKey::LabelPrefix('_')).unwrap(),
+        [
+            "chr1-100-120",
+            "chr1-100-120",
+            "chr1-400-420",
+            "chr2-100-120",
+            "chr2-300-320",
+            "chr3-50-70"
+        ]
+    );
+    assert_eq!(
+        split_keys(&df, &SplitKey::Chromosome(LabelParser::default())).unwrap(),
+        ["chr1", "chr1", "chr1", "chr2", "chr2", "chr3"]
+    );
+    assert_eq!(
+        split_keys(&df, &SplitKey::GcBin(2)).unwrap(),
+        ["1", "1", "0", "0", "0", "1"]
+    );
+    assert_eq!(
+        split_keys(&df, &SplitKey::Column("source_label".to_string())).unwrap(),
+        ["a", "a", "b", "b", "c", "c"]
+    );
+
+    let unparsed = df!("label" => ["enh1_wt"], "sequence" => ["ACGT"]).unwrap();
+    assert!(matches!(
+        split_keys(&unparsed, &SplitKey::Chromosome(LabelParser::default())),
+        Err(MotifError::InvalidInput(_))
+    ));
+    assert!(split_keys(&df, &SplitKey::GcBin(0)).is_err());
+}
+
+#[test]
+fn test_group_folds() {
+    let keys: Vec<String> = ["a", "a", "a", "b", "b", "c", "d", "d", "e"]
+        .iter()
+        .map(|k| k.to_string())
+        .collect();
+    let folds = group_folds(&keys, 3, 42).unwrap();
+    assert_eq!(folds, group_folds(&keys, 3, 42).unwrap());
+
+    let mut fold_of_key: HashMap<&str, usize> = HashMap::new();
+    for (key, &fold) in keys.iter().zip(&folds) {
+        assert_eq!(*fold_of_key.entry(key).or_insert(fold), fold);
+    }
+    let mut sizes = [0; 3];
+    for &fold in &folds {
+        sizes[fold] += 1;
+    }
+    assert_eq!(sizes.iter().max(), Some(&3));
+    assert_eq!(sizes.iter().min(), Some(&3));
+
+    assert!(matches!(
+        group_folds(&keys, 6, 42),
+        Err(MotifError::InvalidParameter { .. })
+    ));
+}
+
+#[test]
+fn test_stratified_folds() {
+    let keys: Vec<String> = (0..30)
+        .map(|i| if i % 3 == 0 { "low" } else { "high" }.to_string())
+        .collect();
+    let folds = stratified_folds(&keys, 5, 7).unwrap();
+    for stratum in ["low", "high"] {
+        let mut counts = [0; 5];
+        for (key, &fold) in keys.iter().zip(&folds) {
+            if key == stratum {
+                counts[fold] += 1;
+            }
+        }
+        let expected = if stratum == "low" { 2 } else { 4 };
+        assert!(counts.iter().all(|&c| c == expected), "{:?}", counts);
+    }
+}
+
+#[test]
+fn test_train_test_split_and_grouped_cross_validation() {
+    let df = sequences();
+    let keys = split_keys(&df, &SplitKey::Chromosome(LabelParser::default())).unwrap();
+    let folds = group_folds(&keys, 3, 1).unwrap();
+    let test_fold = folds[0];
+
+    let (train, test) = train_test_split(&df, &folds, test_fold).unwrap();
+    assert_eq!(train.height() + test.height(), df.height());
+    let test_labels = test.column("label").unwrap().str().unwrap();
+    assert!(test_labels
+        .into_no_null_iter()
+        .all(|label| label.starts_with("chr1")));
+
+    let features = df
+        .select(["label"])
+        .unwrap()
+        .hstack(&[Column::new("x".into(), [1.0, 2.0, 3.0, 4.0, 5.0, 6.0])])
+        .unwrap();
+    let target = [2.0, 4.0, 6.0, 8.0, 10.0, 12.0];
+    let cv =
+        cross_validate_folds(&features, &target, &RegressionOptions::default(), &folds).unwrap();
+    assert_eq!(cv.folds.len(), 3);
+    assert_eq!(cv.folds.iter().map(|f| f.test).sum::<usize>(), 6);
+
+    assert!(matches!(
+        cross_validate_folds(&features, &target, &RegressionOptions::default(), &[0; 6]),
+        Err(MotifError::InvalidParameter { .. })
+    ));
+    assert!(train_test_split(&df, &folds[..3], 0).is_err());
+}
@@ -0,0 +1,86 @@
+use polars::prelude::*;
+use tf_binding_rs::stats;
+
+#[test]
+fn test_binomial_test() {
+    // R: binom.test(9, 10)$p.value and binom.test(3, 10, 0.2)$p.value
+    assert!((stats::binomial_test(9, 10, 0.5).unwrap() - 0.021484375).abs() < 1e-12);
+    assert!((stats::binomial_test(3, 10, 0.2).unwrap() - 0.4295747).abs() < 1e-6);
+    assert_eq!(stats::binomial_test(5, 10, 0.5).unwrap(), 1.0);
+    assert_eq!(stats::binomial_test(0, 0, 0.5).unwrap(), 1.0);
+    assert!(stats::binomial_test(11, 10, 0.5).is_err());
+}
+
+#[test]
+fn test_strand_bias() {
+    let hits = df!(
+        "motif" => ["CRX", "CRX", "CRX", "CRX", "NRL", "NRL"],
+        "strand" => ["F", "F", "F", "F", "F", "R"],
+    )
+    .unwrap();
+    let bias = stats::strand_bias(&hits).unwrap();
+
+    assert_eq!(bias.height(), 2);
+    let forward = bias.column("forward").unwrap().u64().unwrap();
+    let ratio = bias.column("ratio").unwrap().f64().unwrap();
+    let pvalue = bias.column("pvalue").unwrap().f64().unwrap();
+    assert_eq!(forward.get(0), Some(4));
+    assert_eq!(ratio.get(0), None);
+    assert_eq!(ratio.get(1), Some(1.0));
+    assert!((pvalue.get(0).unwrap() - 0.125).abs() < 1e-12);
+    assert_eq!(pvalue.get(1), Some(1.0));
+
+    let bad = df!("motif" => ["CRX"], "strand" => ["+"]).unwrap();
+    assert!(stats::strand_bias(&bad).is_err());
+}
+
+fn correlated_features() -> DataFrame {
+    df!(
+        "label" => ["s1", "s2", "s3", "s4", "s5"],
+        "CRX" => [1.0, 2.0, 3.0, 4.0, 5.0],
+        "NRL" => [5.0, 3.0, 4.0, 1.0, 2.0],
+        "OTX2" => [2.0, 4.0, 6.0, 8.0, 10.5],
+        "RAX" => [Some(4.0), Some(2.0), Some(3.0), None, Some(1.0)],
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_occupancy_correlation() {
+    let correlation = stats::occupancy_correlation(&correlated_features()).unwrap();
+    assert_eq!(
+        correlation.get_column_names(),
+        ["motif", "CRX", "NRL", "OTX2", "RAX"]
+    );
+    let crx = correlation.column("CRX").unwrap().f64().unwrap();
+    assert!((crx.get(0).unwrap() - 1.0).abs() < 1e-12);
+    assert!(crx.get(2).unwrap() > 0.99);
+    assert!((crx.get(1).unwrap() + 0.8).abs() < 1e-12);
+    // RAX is correlated over the four sequences where it has a value
+    let rax = correlation.column("RAX").unwrap().f64().unwrap();
+    assert!((rax.get(1).unwrap() - 1.0).abs() < 1e-12);
+    assert_eq!(rax.get(0), crx.get(3));
+}
+
+#[test]
+fn test_cluster_correlation() {
+    let correlation = stats::occupancy_correlation(&correlated_features()).unwrap();
+    let clustered = stats::cluster_correlation(&correlation).unwrap();
+    let order: Vec<&str> = clustered
+        .column("motif")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_no_null_iter()
+        .collect();
+    assert_eq!(order, ["CRX", "OTX2", "NRL", "RAX"]);
+    assert_eq!(
+        clustered.get_column_names()[1..],
+        ["CRX", "OTX2", "NRL", "RAX"]
+    );
+    let otx2 = clustered.column("OTX2").unwrap().f64().unwrap();
+    assert!((otx2.get(1).unwrap() - 1.0).abs() < 1e-12);
+
+    let not_square = correlation.drop("RAX").unwrap();
+    assert!(stats::cluster_correlation(&not_square).is_err());
+}
@@ -0,0 +1,85 @@
+use polars::prelude::*;
+use tf_binding_rs::{occupancy, thermo};
+
+const SEQ: &str = "GGGCTTAGTAACGTTACGGAAGGGCGTGGCCTTTAAATTCCGGGG";
+
+fn values(df: &DataFrame, name: &str) -> Vec<f64> {
+    df.column(name)
+        .unwrap()
+        .f64()
+        .unwrap()
+        .into_no_null_iter()
+        .collect()
+}
+
+#[test]
+fn test_boltzmann_weights_match_occupancies() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let weights = thermo::boltzmann_weights(SEQ, &ewms, 9.0).unwrap();
+
+    let mut ids: Vec<&String> = ewms.keys().collect();
+    ids.sort();
+    let mut expected = Vec::new();
+    for id in &ids {
+        let (forward, reverse) = occupancy::occupancy_landscape(SEQ, &ewms[*id], 9.0).unwrap();
+        expected.extend(forward);
+        expected.extend(reverse);
+    }
+    for (occupancy, expected) in values(&weights, "occupancy").iter().zip(&expected) {
+        assert!((occupancy - expected).abs() < 1e-12);
+    }
+    for (weight, occupancy) in values(&weights, "weight")
+        .iter()
+        .zip(values(&weights, "occupancy"))
+    {
+        assert!((weight / (1.0 + weight) - occupancy).abs() < 1e-12);
+    }
+
+    // the conditional probabilities of each motif sum to one
+    let sums = weights
+        .lazy()
+        .group_by([col("motif")])
+        .agg([col("conditional").sum()])
+        .collect()
+        .unwrap();
+    for sum in values(&sums, "conditional") {
+        assert!((sum - 1.0).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn test_partition_function() {
+    let ewms = occupancy::read_pwm_to_ewm("tests/data/tdmMotifs.meme").unwrap();
+    let ensemble = thermo::partition_function(SEQ, &ewms, 9.0).unwrap();
+    assert_eq!(ensemble.height(), ewms.len());
+
+    let totals = occupancy::total_occupancy(SEQ, &ewms, 9.0).unwrap();
+    let motifs: Vec<&str> = ensemble
+        .column("motif")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_no_null_iter()
+        .collect();
+    for (motif, bound) in motifs.iter().zip(values(&ensemble, "expected_bound")) {
+        assert!((bound - totals[*motif]).abs() < 1e-9);
+
+        let (forward, reverse) = occupancy::energy_landscape(SEQ, &ewms[*motif]).unwrap();
+        let direct: f64 = forward
+            .iter()
+            .chain(&reverse)
+            .map(|e| (1.0 + (9.0 - e).exp()).ln())
+            .sum();
+        let log_z = thermo::log_partition(forward.iter().chain(&reverse).copied(), 9.0);
+        assert!((log_z - direct).abs() < 1e-9);
+    }
+    for (log_z, free_energy) in values(&ensemble, "log_partition")
+        .iter()
+        .zip(values(&ensemble, "free_energy"))
+    {
+        assert_eq!(free_energy, -log_z);
+    }
+
+    // strong sites under a high chemical potential stay finite in log space
+    assert!(thermo::log_partition([0.0], 1000.0).is_finite());
+}
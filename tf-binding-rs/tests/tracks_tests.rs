@@ -0,0 +1,93 @@
+use polars::prelude::*;
+use tf_binding_rs::tracks;
+
+#[test]
+fn test_hit_density_bedgraph() {
+    let hits = df!(
+        "motif" => ["CRX_1", "NRL", "CRX_1"],
+        "position" => [0, 2, 6],
+        "length" => [4, 4, 2],
+    )
+    .unwrap();
+    let density = tracks::hit_density(&hits, 10, None).unwrap();
+    assert_eq!(
+        density,
+        vec![1.0, 1.0, 2.0, 2.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.0]
+    );
+
+    // subsets match full IDs or names before '_'
+    let crx = tracks::hit_density(&hits, 10, Some(&["CRX"])).unwrap();
+    assert_eq!(crx, vec![1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0]);
+    assert!(tracks::hit_density(&hits, 6, None).is_err());
+
+    let bedgraph = tracks::track_to_bedgraph(&density, "chr1", 100).unwrap();
+    assert_eq!(bedgraph.height(), 3);
+    let path = std::env::temp_dir().join("tf_binding_density.bedGraph");
+    let path = path.to_str().unwrap();
+    assert_eq!(tracks::write_bedgraph(&bedgraph, path).unwrap(), 3);
+    let read = tracks::read_bedgraph(path).unwrap();
+    assert_eq!(
+        tracks::track_for_region(&read, "chr1", 100, 110).unwrap(),
+        density
+    );
+}
+
+/// Writes `sam` as a coordinate-sorted BAM file with its index
+#[cfg(feature = "noodles")]
+fn write_indexed_bam(sam: &str, path: &str) {
+    use noodles::bam;
+    use noodles::sam;
+    use noodles::sam::alignment::io::Write;
+
+    let mut reader = sam::io::Reader::new(sam.as_bytes());
+    let header = reader.read_header().unwrap();
+    let mut writer = bam::io::Writer::new(std::fs::File::create(path).unwrap());
+    writer.write_header(&header).unwrap();
+    for record in reader.record_bufs(&header) {
+        writer
+            .write_alignment_record(&header, &record.unwrap())
+            .unwrap();
+    }
+    writer.try_finish().unwrap();
+
+    let bam::Index::Bai(index) = bam::fs::index(path).unwrap() else {
+        panic!("expected a BAI index");
+    };
+    bam::bai::fs::write(format!("{}.bai", path), &index).unwrap();
+}
+
+#[cfg(feature = "noodles")]
+#[test]
+fn test_bam_coverage() {
+    // r1 spans 100-124 (0-based) with a 2 bp deletion at 105 and a 10 bp skip at 110,
+    // r2 is a duplicate, r3 has a low mapping quality and r4 starts with a soft clip and
+    // has an insertion, neither of which covers the reference
+    let sam = "@HD\tVN:1.6\tSO:coordinate\n\
+               @SQ\tSN:chr1\tLN:1000\n\
+               r1\t0\tchr1\t101\t60\t5M2D3M10N5M\t*\t0\t0\t*\t*\n\
+               r2\t1024\tchr1\t101\t60\t10M\t*\t0\t0\t*\t*\n\
+               r3\t0\tchr1\t101\t5\t10M\t*\t0\t0\t*\t*\n\
+               r4\t0\tchr1\t103\t30\t2S3M1I4M\t*\t0\t0\t*\t*\n";
+    let path = std::env::temp_dir().join("tf_binding_coverage.bam");
+    let path = path.to_str().unwrap();
+    write_indexed_bam(sam, path);
+
+    let coverage = tracks::bam_coverage(path, "chr1", 100, 130, 10).unwrap();
+    let mut expected = vec![1.0, 1.0, 2.0, 2.0, 2.0, 1.0, 1.0, 2.0, 2.0, 1.0];
+    expected.extend([0.0; 10]);
+    expected.extend([1.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+    assert_eq!(coverage, expected);
+
+    // without a mapping quality threshold r3 counts too
+    let all = tracks::bam_coverage(path, "chr1", 100, 130, 0).unwrap();
+    for (pos, (all, depth)) in all.iter().zip(&expected).enumerate() {
+        assert_eq!(*all, depth + if pos < 10 { 1.0 } else { 0.0 });
+    }
+
+    // regions clip the reads that overlap them
+    assert_eq!(
+        tracks::bam_coverage(path, "chr1", 122, 127, 10).unwrap(),
+        vec![1.0, 1.0, 1.0, 0.0, 0.0]
+    );
+    assert!(tracks::bam_coverage(path, "chr1", 130, 130, 10).is_err());
+}
@@ -0,0 +1,201 @@
+//! R bindings of tf-binding-rs
+//!
+//! The functions here are registered with R through extendr and called by the thin
+//! wrappers in `R/tfbindr.R`, which validate arguments and turn the returned lists
+//! into data.frames or tibbles. Every polars DataFrame crosses into R as a named
+//! list of column vectors with the `data.frame` class. Motifs are read and converted
+//! to energies once by `load_motifs_impl` and handed to R as an external pointer, so
+//! repeated landscape and scan calls reuse them instead of re-reading the file.
+
+use extendr_api::prelude::*;
+use extendr_api::Result;
+use polars::prelude::{Column, DataFrame, DataType, PolarsError};
+use tf_binding_rs::error::MotifError;
+use tf_binding_rs::formats::{self, MotifFormat};
+use tf_binding_rs::occupancy;
+use tf_binding_rs::scan;
+use tf_binding_rs::types::{EWMCollection, PWMCollection};
+
+/// Turns a library error into an R error
+fn r_error(e: MotifError) -> Error {
+    Error::Other(e.to_string())
+}
+
+/// Turns a polars error into an R error
+fn data_error(e: PolarsError) -> Error {
+    Error::Other(e.to_string())
+}
+
+/// Converts a polars DataFrame into an R data.frame.
+///
+/// Small integer columns become integer vectors, other numeric columns double
+/// vectors, strings character vectors and booleans logical vectors; nulls become `NA`.
+fn to_data_frame(df: &DataFrame) -> Result<Robj> {
+    let mut names = Vec::with_capacity(df.width());
+    let mut values = Vec::with_capacity(df.width());
+    for column in df.get_columns() {
+        let series = column.as_materialized_series();
+        let value: Robj = match series.dtype() {
+            DataType::String => series
+                .str()
+                .map_err(data_error)?
+                .into_iter()
+                .map(|v| v.map(str::to_string))
+                .collect::<Vec<Option<String>>>()
+                .into(),
+            DataType::Boolean => series
+                .bool()
+                .map_err(data_error)?
+                .into_iter()
+                .collect::<Vec<Option<bool>>>()
+                .into(),
+            DataType::Int8
+            | DataType::Int16
+            | DataType::Int32
+            | DataType::UInt8
+            | DataType::UInt16 => {
+                let cast = series.cast(&DataType::Int32).map_err(data_error)?;
+                cast.i32()
+                    .map_err(data_error)?
+                    .into_iter()
+                    .collect::<Vec<Option<i32>>>()
+                    .into()
+            }
+            dtype if dtype.is_integer() || dtype.is_float() => {
+                // i64 and u32/u64 columns (positions in a genome) may not fit an R integer
+                let cast = series.cast(&DataType::Float64).map_err(data_error)?;
+                cast.f64()
+                    .map_err(data_error)?
+                    .into_iter()
+                    .collect::<Vec<Option<f64>>>()
+                    .into()
+            }
+            dtype => {
+                return Err(Error::Other(format!(
+                    "column '{}' has unsupported type {}",
+                    series.name(),
+                    dtype
+                )))
+            }
+        };
+        names.push(series.name().to_string());
+        values.push(value);
+    }
+
+    let mut frame: Robj = List::from_names_and_values(names, values)?.into();
+    let row_names: Vec<i32> = (1..=df.height() as i32).collect();
+    frame.set_attrib(row_names_symbol(), row_names)?;
+    frame.set_attrib(class_symbol(), "data.frame")?;
+    Ok(frame)
+}
+
+/// Reads the PWMs of a motif file, guessing the format from the extension when
+/// `format` is `NULL`
+fn read_pwms(path: &str, format: Nullable<String>) -> Result<PWMCollection> {
+    let format = match format {
+        Nullable::NotNull(name) => name.parse::<MotifFormat>().map_err(r_error)?,
+        Nullable::Null => MotifFormat::from_path(path).ok_or_else(|| {
+            Error::Other(format!(
+                "cannot guess the motif format of '{}', pass `format`",
+                path
+            ))
+        })?,
+    };
+    formats::read_motifs(path, format).map_err(r_error)
+}
+
+/// Energy matrices of a motif file, owned by R through an external pointer
+#[derive(Debug)]
+struct Motifs {
+    ewms: EWMCollection,
+}
+
+/// Reads a motif file and converts every PWM into an EWM
+fn read_ewms(path: &str, format: Nullable<String>) -> Result<EWMCollection> {
+    read_pwms(path, format)?
+        .into_iter()
+        .map(|(id, pwm)| Ok((id, occupancy::pwm_to_ewm(&pwm).map_err(r_error)?)))
+        .collect()
+}
+
+/// Reads a motif file once and returns its energy matrices as an external pointer
+/// @noRd
+#[extendr]
+fn load_motifs_impl(path: &str, format: Nullable<String>) -> Result<ExternalPtr<Motifs>> {
+    Ok(ExternalPtr::new(Motifs {
+        ewms: read_ewms(path, format)?,
+    }))
+}
+
+/// Sorted motif IDs of loaded motifs
+/// @noRd
+#[extendr]
+fn motif_ids_impl(motifs: ExternalPtr<Motifs>) -> Vec<String> {
+    let mut ids: Vec<String> = motifs.ewms.keys().cloned().collect();
+    ids.sort();
+    ids
+}
+
+/// Reads a motif file into a named list of PWM data.frames (columns A, C, G, T)
+/// @noRd
+#[extendr]
+fn read_motifs_impl(path: &str, format: Nullable<String>) -> Result<List> {
+    let pwms = read_pwms(path, format)?;
+    let mut ids: Vec<&String> = pwms.keys().collect();
+    ids.sort();
+    let frames = ids
+        .iter()
+        .map(|id| to_data_frame(&pwms[*id]))
+        .collect::<Result<Vec<Robj>>>()?;
+    List::from_names_and_values(ids, frames)
+}
+
+/// Occupancy landscape of every motif along one sequence
+/// @noRd
+#[extendr]
+fn total_landscape_impl(sequence: &str, motifs: ExternalPtr<Motifs>, mu: f64) -> Result<Robj> {
+    let landscape = occupancy::total_landscape(sequence, &motifs.ewms, mu).map_err(r_error)?;
+    to_data_frame(&landscape)
+}
+
+/// Scans many sequences, returning the hits and the skipped records
+/// @noRd
+#[extendr]
+fn scan_sequences_impl(
+    labels: Vec<String>,
+    sequences: Vec<String>,
+    motifs: ExternalPtr<Motifs>,
+    mu: f64,
+    cutoff: f64,
+) -> Result<List> {
+    let df = DataFrame::new(vec![
+        Column::new("label".into(), labels),
+        Column::new("sequence".into(), sequences),
+    ])
+    .map_err(data_error)?;
+
+    let result = scan::scan_sequences(&df, &motifs.ewms, mu, cutoff, |_| {}).map_err(r_error)?;
+    let warnings = scan::warnings_frame(&result.warnings).map_err(r_error)?;
+    List::from_names_and_values(
+        ["hits", "skipped"],
+        [to_data_frame(&result.hits)?, to_data_frame(&warnings)?],
+    )
+}
+
+/// Reads a FASTA file into a data.frame with `label` and `sequence` columns
+/// @noRd
+#[extendr]
+fn read_fasta_impl(path: &str) -> Result<Robj> {
+    let df = tf_binding_rs::fasta::read_fasta(path).map_err(r_error)?;
+    to_data_frame(&df)
+}
+
+extendr_module! {
+    mod tfbindr;
+    fn read_motifs_impl;
+    fn load_motifs_impl;
+    fn motif_ids_impl;
+    fn total_landscape_impl;
+    fn scan_sequences_impl;
+    fn read_fasta_impl;
+}